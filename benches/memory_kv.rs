@@ -0,0 +1,97 @@
+//! Put latency of `MemoryKv` while other threads put and long-poll, with a
+//! single lock and with the default sharding.
+//!
+//! ```text
+//! cargo bench --bench memory_kv
+//! ```
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use hubert::{KvStore, MemoryKv};
+
+/// Threads putting envelopes at fresh ARIDs.
+const WRITERS: usize = 8;
+/// Puts made by each writer.
+const PUTS: usize = 20_000;
+/// Threads polling ARIDs that are never written, as long-polling gets do.
+const POLLERS: usize = 8;
+
+fn main() {
+    bc_components::register_tags();
+    for shards in [1, MemoryKv::DEFAULT_SHARDS] {
+        let report = run(MemoryKv::new().with_shards(shards));
+        println!("{:>2} shards: {}", shards, report);
+    }
+}
+
+fn run(store: MemoryKv) -> String {
+    let stop = Arc::new(AtomicBool::new(false));
+    let pollers: Vec<_> = (0..POLLERS)
+        .map(|_| {
+            let (store, stop) = (store.clone(), stop.clone());
+            thread::spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .build()
+                    .unwrap();
+                let arid = ARID::new();
+                while !stop.load(Ordering::Relaxed) {
+                    // A zero timeout checks once without sleeping
+                    runtime.block_on(store.get(&arid, Some(0), false)).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    let writers: Vec<_> = (0..WRITERS)
+        .map(|i| {
+            let store = store.clone();
+            thread::spawn(move || {
+                let envelope = Envelope::new(format!("Writer {}", i));
+                (0..PUTS)
+                    .map(|_| {
+                        let arid = ARID::new();
+                        let start = Instant::now();
+                        store
+                            .put_tagged(&arid, &envelope, None, None, false)
+                            .unwrap();
+                        start.elapsed()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let mut latencies: Vec<Duration> = writers
+        .into_iter()
+        .flat_map(|writer| writer.join().unwrap())
+        .collect();
+    let elapsed = start.elapsed();
+
+    stop.store(true, Ordering::Relaxed);
+    for poller in pollers {
+        poller.join().unwrap();
+    }
+
+    latencies.sort();
+    let at = |p: f64| {
+        let rank = (p / 100.0 * latencies.len() as f64).ceil() as usize;
+        latencies[rank.clamp(1, latencies.len()) - 1]
+    };
+    let us = |d: Duration| format!("{:.1} µs", d.as_secs_f64() * 1e6);
+    format!(
+        "{:.0} puts/s, p50 {}, p99 {}, max {}",
+        latencies.len() as f64 / elapsed.as_secs_f64(),
+        us(at(50.0)),
+        us(at(99.0)),
+        us(*latencies.last().unwrap())
+    )
+}
@@ -0,0 +1,140 @@
+//! Two-party request/response exchange over Hubert.
+//!
+//! An initiator publishes a request at a fresh ARID, naming a second ARID
+//! where it expects the reply. A responder polls for the request, reads the
+//! response ARID out of it, and publishes its reply there. The parties share
+//! nothing but the request ARID, which in practice would be handed over out
+//! of band (a QR code, a Signal message).
+//!
+//! Real deployments would encrypt both messages with GSTP; this example
+//! uses plain envelopes to keep the storage flow in focus.
+//!
+//! Pass a backend name (default: `server`, which starts an in-process Hubert
+//! server):
+//!
+//! ```text
+//! cargo run --example two_party -- server
+//! cargo run --example two_party -- mainline
+//! cargo run --example two_party -- ipfs     # needs a local IPFS daemon
+//! cargo run --example two_party -- hybrid   # needs a local IPFS daemon
+//! ```
+
+use anyhow::{Result, anyhow, bail};
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use hubert::{
+    KvStore,
+    hybrid::HybridKv,
+    ipfs::IpfsKv,
+    mainline::MainlineDhtKv,
+    server::{Server, ServerConfig, ServerKvClient},
+};
+
+const SERVER_PORT: u16 = 45689;
+const IPFS_URL: &str = "http://127.0.0.1:5001";
+const TIMEOUT_SECONDS: u64 = 60;
+
+/// Open one party's connection to the chosen backend.
+async fn open_store(backend: &str) -> Result<Box<dyn KvStore>> {
+    let store: Box<dyn KvStore> = match backend {
+        "server" => Box::new(ServerKvClient::new(&format!(
+            "http://127.0.0.1:{}",
+            SERVER_PORT
+        ))),
+        "mainline" => {
+            Box::new(MainlineDhtKv::new().await.map_err(|e| anyhow!("{}", e))?)
+        }
+        "ipfs" => Box::new(IpfsKv::new(IPFS_URL)),
+        "hybrid" => Box::new(
+            HybridKv::new(IPFS_URL)
+                .await
+                .map_err(|e| anyhow!("{}", e))?,
+        ),
+        other => bail!(
+            "Unknown backend '{}'. Use server, mainline, ipfs, or hybrid",
+            other
+        ),
+    };
+    Ok(store)
+}
+
+/// Publish a request and wait for the reply.
+async fn initiator(store: &dyn KvStore, request_arid: ARID) -> Result<()> {
+    let response_arid = ARID::new();
+    let request = Envelope::new("Please sign: document.pdf")
+        .add_assertion("responseArid", response_arid);
+
+    store
+        .put(&request_arid, &request, None, false)
+        .await
+        .map_err(|e| anyhow!("{}", e))?;
+    println!(
+        "initiator: published request at {}",
+        request_arid.ur_string()
+    );
+
+    let response = store
+        .get(&response_arid, Some(TIMEOUT_SECONDS), false)
+        .await
+        .map_err(|e| anyhow!("{}", e))?
+        .ok_or_else(|| anyhow!("no response within {}s", TIMEOUT_SECONDS))?;
+    let reply: String = response.extract_subject()?;
+    println!("initiator: received response: {}", reply);
+
+    Ok(())
+}
+
+/// Wait for a request and publish a reply at the ARID it names.
+async fn responder(store: &dyn KvStore, request_arid: ARID) -> Result<()> {
+    let request = store
+        .get(&request_arid, Some(TIMEOUT_SECONDS), false)
+        .await
+        .map_err(|e| anyhow!("{}", e))?
+        .ok_or_else(|| anyhow!("no request within {}s", TIMEOUT_SECONDS))?;
+    let body: String = request.extract_subject()?;
+    let response_arid: ARID =
+        request.extract_object_for_predicate("responseArid")?;
+    println!("responder: received request: {}", body);
+
+    let response = Envelope::new("Signed: document.pdf");
+    store
+        .put(&response_arid, &response, None, false)
+        .await
+        .map_err(|e| anyhow!("{}", e))?;
+    println!(
+        "responder: published response at {}",
+        response_arid.ur_string()
+    );
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    bc_components::register_tags();
+
+    let backend = std::env::args().nth(1).unwrap_or_else(|| "server".into());
+
+    if backend == "server" {
+        let config = ServerConfig { port: SERVER_PORT, ..Default::default() };
+        let server = Server::new_memory(config);
+        tokio::spawn(async move { server.run().await });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    let initiator_store = open_store(&backend).await?;
+    let responder_store = open_store(&backend).await?;
+
+    // The only thing the parties share
+    let request_arid = ARID::new();
+
+    let (initiated, responded) = tokio::join!(
+        initiator(initiator_store.as_ref(), request_arid),
+        responder(responder_store.as_ref(), request_arid),
+    );
+    responded?;
+    initiated?;
+
+    println!("✓ Exchange complete over {}", backend);
+    Ok(())
+}
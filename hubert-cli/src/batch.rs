@@ -0,0 +1,359 @@
+//! Manifest-driven puts for `hubert put-batch`, for scripted setup of
+//! coordinations that span many ARIDs and backends.
+//!
+//! A manifest is a JSON Lines file with one entry per line:
+//!
+//! ```text
+//! {"arid": "ur:arid/...", "envelope": "ur:envelope/...", "ttl": 3600}
+//! {"file": "share-2.envelope", "backend": "mainline"}
+//! ```
+//!
+//! Every field but one of `envelope` and `file` is optional. An entry
+//! without an ARID is stored at a new one, which the report shows.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use clap::ValueEnum;
+use futures_util::{StreamExt, stream};
+use hubert::KvStore;
+use serde::Deserialize;
+
+use super::{
+    StorageBackend,
+    exit::{self, Failure, bail_with, fail},
+    parse_arid, parse_envelope,
+};
+
+/// One line of a manifest, as written.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Line {
+    arid: Option<String>,
+    envelope: Option<String>,
+    /// File holding a `ur:envelope` or the envelope's binary dCBOR,
+    /// relative to the manifest.
+    file: Option<PathBuf>,
+    /// Seconds, or a duration such as `"90m"`.
+    #[serde(default, deserialize_with = "hubert::duration::deserialize_option")]
+    ttl: Option<u64>,
+    #[serde(alias = "backend")]
+    storage: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+}
+
+/// The command's `--storage`, `--host`, `--port`, and `--ttl`, which apply
+/// to entries that do not give their own.
+pub struct Defaults {
+    pub storage: StorageBackend,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub ttl: Option<u64>,
+}
+
+/// A put to make.
+pub struct Entry {
+    /// Line number in the manifest, from 1.
+    pub line: usize,
+    pub arid: ARID,
+    pub envelope: Envelope,
+    pub ttl: Option<u64>,
+    pub storage: StorageBackend,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// A successful put.
+pub struct Stored {
+    pub receipt: String,
+    pub latency: Duration,
+}
+
+/// Read the manifest at `path`.
+pub fn read_manifest(path: &Path, defaults: &Defaults) -> Result<Vec<Entry>> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        Failure::Usage.error(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    parse_manifest(path, &text, defaults)
+}
+
+/// Parse the manifest `text` read from `path`. Errors name the offending
+/// line as `path:line`.
+pub fn parse_manifest(
+    path: &Path,
+    text: &str,
+    defaults: &Defaults,
+) -> Result<Vec<Entry>> {
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut entries = Vec::new();
+    let mut seen = HashMap::new();
+    for (index, text) in text.lines().enumerate() {
+        let line = index + 1;
+        if text.trim().is_empty() {
+            continue;
+        }
+        let at = |message: String| {
+            Failure::Usage.error(format!(
+                "{}:{}: {}",
+                path.display(),
+                line,
+                message
+            ))
+        };
+        let entry = serde_json::from_str::<Line>(text)
+            .map_err(anyhow::Error::from)
+            .and_then(|parsed| entry(line, parsed, base, defaults))
+            .map_err(|e| at(e.to_string()))?;
+
+        let key = (
+            entry.arid.clone(),
+            entry.storage,
+            entry.host.clone(),
+            entry.port,
+        );
+        if let Some(first) = seen.insert(key, line) {
+            return Err(at(format!(
+                "Repeats the ARID and backend of line {}",
+                first
+            )));
+        }
+        entries.push(entry);
+    }
+    if entries.is_empty() {
+        bail_with!(Failure::Usage, "{} has no entries", path.display());
+    }
+    Ok(entries)
+}
+
+fn entry(
+    line: usize,
+    parsed: Line,
+    base: &Path,
+    defaults: &Defaults,
+) -> Result<Entry> {
+    let arid = match parsed.arid {
+        Some(arid) => parse_arid(&arid)?,
+        None => ARID::new(),
+    };
+    let envelope = match (parsed.envelope, parsed.file) {
+        (Some(envelope), None) => parse_envelope(&envelope)?,
+        (None, Some(file)) => read_envelope(&base.join(file))?,
+        _ => bail_with!(
+            Failure::Usage,
+            "Give exactly one of \"envelope\" and \"file\""
+        ),
+    };
+
+    // An entry naming its own backend does not inherit the command's
+    // host and port, which belong to the command's backend
+    let (storage, host, port) = match parsed.storage {
+        Some(name) => (
+            StorageBackend::from_str(&name, false)
+                .map_err(|e| Failure::Usage.error(e))?,
+            parsed.host,
+            parsed.port,
+        ),
+        None => (
+            defaults.storage,
+            parsed.host.or_else(|| defaults.host.clone()),
+            parsed.port.or(defaults.port),
+        ),
+    };
+    if host.is_some() && storage != StorageBackend::Server {
+        bail_with!(
+            Failure::Usage,
+            "\"host\" is only supported for storage server"
+        );
+    }
+    if port.is_some() && storage == StorageBackend::Mainline {
+        bail_with!(
+            Failure::Usage,
+            "\"port\" is not supported for storage mainline"
+        );
+    }
+
+    Ok(Entry {
+        line,
+        arid,
+        envelope,
+        ttl: parsed.ttl.or(defaults.ttl),
+        storage,
+        host,
+        port,
+    })
+}
+
+/// Read an envelope from a file holding a `ur:envelope` or binary dCBOR.
+fn read_envelope(path: &Path) -> Result<Envelope> {
+    let data = std::fs::read(path).map_err(|e| {
+        Failure::Usage.error(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    match std::str::from_utf8(&data) {
+        Ok(text) if text.trim_start().starts_with("ur:") => {
+            parse_envelope(text.trim())
+        }
+        _ => Envelope::try_from_cbor_data(data).map_err(|e| {
+            Failure::Usage.error(format!(
+                "{} is neither a ur:envelope nor an envelope's dCBOR: {}",
+                path.display(),
+                e
+            ))
+        }),
+    }
+}
+
+/// Put every entry in `stores`, the store for each entry, with at most
+/// `concurrency` puts in flight. Outcomes are in the entries' order, and a
+/// failure does not stop the others.
+pub async fn run(
+    entries: &[Entry],
+    stores: &[&dyn KvStore],
+    concurrency: usize,
+    verbose: bool,
+) -> Vec<Result<Stored>> {
+    stream::iter(entries.iter().zip(stores))
+        .map(|(entry, store)| put(*store, entry, verbose))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+async fn put(
+    store: &dyn KvStore,
+    entry: &Entry,
+    verbose: bool,
+) -> Result<Stored> {
+    if entry.ttl.is_some() && !store.capabilities().ttl {
+        bail_with!(
+            Failure::Usage,
+            "TTL is not supported for --storage {}",
+            entry.storage.name()
+        );
+    }
+    store.validate(&entry.envelope).map_err(|e| {
+        exit::invalid(e, entry.storage == StorageBackend::Mainline)
+    })?;
+    let start = Instant::now();
+    let receipt = store
+        .put(&entry.arid, &entry.envelope, entry.ttl, verbose)
+        .await
+        .map_err(fail)?;
+    Ok(Stored { receipt: receipt.to_string(), latency: start.elapsed() })
+}
+
+/// One line per entry, then a summary.
+pub fn report(
+    entries: &[Entry],
+    outcomes: &[Result<Stored>],
+    elapsed: Duration,
+) -> String {
+    let mut lines = Vec::new();
+    for (entry, outcome) in entries.iter().zip(outcomes) {
+        let status = match outcome {
+            Ok(stored) => format!(
+                "ok in {:.1} ms",
+                stored.latency.as_secs_f64() * 1e3
+            ),
+            Err(e) => format!("failed: {}", e),
+        };
+        lines.push(format!(
+            "{}  {}  {}  {}",
+            entry.line,
+            entry.storage.name(),
+            entry.arid.ur_string(),
+            status
+        ));
+    }
+    let failed = outcomes.iter().filter(|o| o.is_err()).count();
+    lines.push(format!(
+        "{} entries in {:.1}s: {} stored, {} failed",
+        entries.len(),
+        elapsed.as_secs_f64(),
+        entries.len() - failed,
+        failed
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Defaults {
+        Defaults {
+            storage: StorageBackend::Server,
+            host: Some("hubert.example.com".to_string()),
+            port: None,
+            ttl: Some(600),
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        bc_components::register_tags();
+        let arid = ARID::new().ur_string();
+        let envelope = Envelope::new("Hello").ur_string();
+        let text = format!(
+            "{{\"arid\": \"{arid}\", \"envelope\": \"{envelope}\"}}\n\
+             \n\
+             {{\"envelope\": \"{envelope}\", \"backend\": \"mainline\"}}\n\
+             {{\"envelope\": \"{envelope}\", \"ttl\": \"1m\", \
+             \"port\": 8080}}\n"
+        );
+        let path = Path::new("setup.jsonl");
+        let entries = parse_manifest(path, &text, &defaults()).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].line, 1);
+        assert_eq!(entries[0].arid.ur_string(), arid);
+        assert_eq!(entries[0].host.as_deref(), Some("hubert.example.com"));
+        assert_eq!(entries[0].ttl, Some(600));
+
+        assert_eq!(entries[1].line, 3);
+        assert_eq!(entries[1].storage, StorageBackend::Mainline);
+        assert_eq!(entries[1].host, None);
+
+        assert_eq!(entries[2].ttl, Some(60));
+        assert_eq!(entries[2].port, Some(8080));
+    }
+
+    #[test]
+    fn test_manifest_errors_name_the_line() {
+        bc_components::register_tags();
+        let envelope = Envelope::new("Hello").ur_string();
+        let arid = ARID::new().ur_string();
+        let path = Path::new("setup.jsonl");
+        let error = |text: String| {
+            parse_manifest(path, &text, &defaults())
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        let unknown = format!("\n{{\"envelope\": \"{envelope}\", \"x\": 1}}");
+        assert!(error(unknown).starts_with("setup.jsonl:2: unknown field"));
+        let neither = "{\"ttl\": 60}".to_string();
+        assert!(error(neither).contains("exactly one"));
+        let host = format!(
+            "{{\"envelope\": \"{envelope}\", \"storage\": \"ipfs\", \
+             \"host\": \"example.com\"}}"
+        );
+        assert!(error(host).contains("\"host\" is only supported"));
+        let line =
+            format!("{{\"arid\": \"{arid}\", \"envelope\": \"{envelope}\"}}");
+        let repeated = format!("{line}\n{line}");
+        assert_eq!(
+            error(repeated),
+            "setup.jsonl:2: Repeats the ARID and backend of line 1"
+        );
+        assert!(error(String::new()).ends_with("has no entries"));
+    }
+}
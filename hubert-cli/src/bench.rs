@@ -0,0 +1,201 @@
+//! Synthetic put/get load for `hubert bench`, for sizing a backend before
+//! relying on it.
+
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use bc_components::ARID;
+use futures_util::{StreamExt, stream};
+use hubert::{KvStore, logging::verbose_println};
+
+use super::generate_random_envelope;
+
+/// What `hubert bench` runs.
+pub struct Workload {
+    /// Number of put/get pairs.
+    pub ops: usize,
+    /// Pairs in flight at once.
+    pub concurrency: usize,
+    /// Random bytes in each envelope's subject.
+    pub size: usize,
+    pub ttl: Option<u64>,
+    /// Seconds each get waits for its value.
+    pub timeout: u64,
+}
+
+/// Run `workload` against `store` and return the report.
+///
+/// Each pair puts a fresh envelope at a new ARID and then gets it back,
+/// so gets measure read-after-write latency. Failures are counted rather
+/// than stopping the run.
+pub async fn run(
+    store: &dyn KvStore,
+    workload: &Workload,
+    verbose: bool,
+) -> String {
+    let start = Instant::now();
+    let done = &Cell::new(0);
+    let samples: Vec<Sample> = stream::iter(0..workload.ops)
+        .map(|_| async move {
+            let sample = pair(store, workload, verbose).await;
+            done.set(done.get() + 1);
+            if verbose && done.get() % 100 == 0 {
+                verbose_println(&format!(
+                    "{} of {} pairs done",
+                    done.get(),
+                    workload.ops
+                ));
+            }
+            sample
+        })
+        .buffer_unordered(workload.concurrency.max(1))
+        .collect()
+        .await;
+    let elapsed = start.elapsed();
+
+    let mut puts = Outcomes::default();
+    let mut gets = Outcomes::default();
+    for sample in samples {
+        puts.record(sample.put);
+        if let Some(get) = sample.get {
+            gets.record(get);
+        }
+    }
+
+    let rate = workload.ops as f64 / elapsed.as_secs_f64();
+    let mut lines = vec![format!(
+        "{} pairs of {} byte envelopes, concurrency {}, in {:.1}s ({:.1} pairs/s)",
+        workload.ops,
+        workload.size,
+        workload.concurrency,
+        elapsed.as_secs_f64(),
+        rate
+    )];
+    lines.extend(puts.describe("put"));
+    lines.extend(gets.describe("get"));
+    lines.join("\n")
+}
+
+/// The timing of one put/get pair. The get is skipped if the put failed.
+struct Sample {
+    put: Result<Duration, String>,
+    get: Option<Result<Duration, String>>,
+}
+
+async fn pair(
+    store: &dyn KvStore,
+    workload: &Workload,
+    verbose: bool,
+) -> Sample {
+    let arid = ARID::new();
+    let envelope = generate_random_envelope(workload.size);
+
+    let start = Instant::now();
+    if let Err(e) = store.put(&arid, &envelope, workload.ttl, verbose).await {
+        return Sample { put: Err(e.to_string()), get: None };
+    }
+    let put = Ok(start.elapsed());
+
+    let start = Instant::now();
+    let get = match store.get(&arid, Some(workload.timeout), verbose).await {
+        Ok(Some(found)) if found == envelope => Ok(start.elapsed()),
+        Ok(Some(_)) => Err("Retrieved envelope does not match".to_string()),
+        Ok(None) => {
+            Err(format!("Not found within {} seconds", workload.timeout))
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    Sample { put, get: Some(get) }
+}
+
+/// Latencies of the operations that succeeded and counts of the errors of
+/// those that did not.
+#[derive(Default)]
+struct Outcomes {
+    latencies: Vec<Duration>,
+    errors: BTreeMap<String, usize>,
+}
+
+impl Outcomes {
+    fn record(&mut self, outcome: Result<Duration, String>) {
+        match outcome {
+            Ok(latency) => self.latencies.push(latency),
+            Err(error) => *self.errors.entry(error).or_default() += 1,
+        }
+    }
+
+    /// A summary line for `operation`, then one line per distinct error.
+    fn describe(mut self, operation: &str) -> Vec<String> {
+        self.latencies.sort();
+        let failed: usize = self.errors.values().sum();
+        let total = self.latencies.len() + failed;
+        if total == 0 {
+            return vec![format!("{}: not attempted", operation)];
+        }
+
+        let mut line = format!(
+            "{}: {} ok, {} failed ({:.1}%)",
+            operation,
+            self.latencies.len(),
+            failed,
+            100.0 * failed as f64 / total as f64
+        );
+        if let Some(max) = self.latencies.last() {
+            let ms = |d: Duration| format!("{:.1} ms", d.as_secs_f64() * 1e3);
+            line.push_str(&format!(
+                ", p50 {}, p90 {}, p99 {}, max {}",
+                ms(percentile(&self.latencies, 50.0)),
+                ms(percentile(&self.latencies, 90.0)),
+                ms(percentile(&self.latencies, 99.0)),
+                ms(*max)
+            ));
+        }
+
+        let mut lines = vec![line];
+        lines.extend(
+            self.errors
+                .iter()
+                .map(|(error, count)| format!("  {} × {}", count, error)),
+        );
+        lines
+    }
+}
+
+/// The nearest-rank percentile of `sorted`, which must not be empty.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_errors() {
+        let ms = Duration::from_millis;
+        let sorted: Vec<Duration> = (1..=100).map(ms).collect();
+        assert_eq!(percentile(&sorted, 50.0), ms(50));
+        assert_eq!(percentile(&sorted, 99.0), ms(99));
+        assert_eq!(percentile(&sorted, 100.0), ms(100));
+        assert_eq!(percentile(&[ms(7)], 50.0), ms(7));
+
+        let mut outcomes = Outcomes::default();
+        outcomes.record(Ok(ms(10)));
+        outcomes.record(Err("Timed out".into()));
+        outcomes.record(Err("Timed out".into()));
+        outcomes.record(Ok(ms(30)));
+        let lines = outcomes.describe("get");
+        assert_eq!(
+            lines,
+            [
+                "get: 2 ok, 2 failed (50.0%), p50 10.0 ms, p90 30.0 ms, \
+                 p99 30.0 ms, max 30.0 ms",
+                "  2 × Timed out",
+            ]
+        );
+    }
+}
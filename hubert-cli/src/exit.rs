@@ -0,0 +1,205 @@
+//! Exit codes, so scripts can tell why a command failed.
+//!
+//! | Code | Meaning                                                      |
+//! | ---- | ------------------------------------------------------------ |
+//! | 0    | Success                                                      |
+//! | 1    | Any other failure                                            |
+//! | 2    | Invalid arguments or input                                   |
+//! | 3    | Nothing found at the ARID or name, or the entry has expired  |
+//! | 4    | The ARID is already taken                                    |
+//! | 5    | The backend could not be reached                             |
+//! | 6    | A network operation timed out                                |
+//!
+//! Errors that are not tagged with a [`Failure`] exit with 1.
+
+use std::{fmt, process::ExitCode};
+
+/// Why a command failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    General = 1,
+    Usage = 2,
+    NotFound = 3,
+    Conflict = 4,
+    Unavailable = 5,
+    Timeout = 6,
+}
+
+impl Failure {
+    /// The failure for a library error.
+    pub fn of(error: &hubert::Error) -> Self {
+        use hubert::{Error, ErrorCategory, server};
+
+        match error.category() {
+            ErrorCategory::AlreadyExists => Self::Conflict,
+            ErrorCategory::NotFound => Self::NotFound,
+            ErrorCategory::TooLarge => Self::Usage,
+            ErrorCategory::Timeout => Self::Timeout,
+            ErrorCategory::Unavailable => Self::Unavailable,
+            ErrorCategory::Other => match error {
+                Error::InvalidArid
+                | Error::InvalidEnvelope
+                | Error::EnvelopeExpired { .. }
+                | Error::InvalidAttachment(_)
+                | Error::UnknownKnownValue(_)
+                | Error::InvalidUrInput { .. }
+                | Error::InvalidName(_)
+                | Error::Unsupported { .. }
+                | Error::Server(server::Error::Config(_)) => Self::Usage,
+                _ => Self::General,
+            },
+        }
+    }
+
+    /// The failure `error` was tagged with, or [`Failure::General`].
+    pub fn of_error(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(error) = cause.downcast_ref::<CliError>() {
+                    Some(error.failure)
+                } else {
+                    cause.downcast_ref::<clap::Error>().map(|_| Self::Usage)
+                }
+            })
+            .unwrap_or(Self::General)
+    }
+
+    /// The exit code reporting this failure.
+    pub fn exit_code(self) -> ExitCode { ExitCode::from(self as u8) }
+
+    /// An error with `message` that exits with this failure's code.
+    pub fn error(self, message: impl Into<String>) -> anyhow::Error {
+        anyhow::Error::new(CliError { failure: self, message: message.into() })
+    }
+}
+
+/// Convert a library error, keeping its exit code. Errors for envelopes
+/// too large for the backend suggest one that would take them.
+pub fn fail(error: impl Into<hubert::Error>) -> anyhow::Error {
+    use hubert::{Error, ipfs, mainline};
+
+    let error = error.into();
+    let hint = match &error {
+        Error::Mainline(mainline::Error::ValueTooLarge { size, .. }) => {
+            Some(larger_backend(*size, true))
+        }
+        Error::Ipfs(ipfs::Error::EnvelopeTooLarge { size, .. }) => {
+            Some(larger_backend(*size, false))
+        }
+        _ => None,
+    };
+    let message = match hint {
+        Some(hint) => format!("{}; {}", error, hint),
+        None => error.to_string(),
+    };
+    Failure::of(&error).error(message)
+}
+
+/// Convert a failed [`KvStore::validate`](hubert::KvStore::validate) of a
+/// put to the DHT, or if not `dht`, to a store that keeps large envelopes
+/// in IPFS.
+pub fn invalid(error: hubert::ValidationError, dht: bool) -> anyhow::Error {
+    let message = match &error {
+        hubert::ValidationError::TooLarge { size, .. } => {
+            format!("{}; {}", error, larger_backend(*size, dht))
+        }
+        _ => error.to_string(),
+    };
+    Failure::Usage.error(message)
+}
+
+/// Which backend takes an envelope of `size` bytes that the DHT, or if not
+/// `dht`, IPFS rejected as too large.
+fn larger_backend(size: usize, dht: bool) -> &'static str {
+    use hubert::{ipfs, server};
+
+    if size > server::MAX_ENVELOPE_SIZE {
+        "no backend stores envelopes this large"
+    } else if dht && size <= ipfs::DEFAULT_MAX_ENVELOPE_SIZE {
+        "use --storage hybrid, which moves large envelopes to IPFS, or \
+         --storage server"
+    } else {
+        "use --storage server, which takes envelopes of up to 10.5 MB"
+    }
+}
+
+/// An error tagged with the exit code it produces.
+#[derive(Debug)]
+struct CliError {
+    failure: Failure,
+    message: String,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Return early with an error that exits with the given [`Failure`]'s code.
+macro_rules! bail_with {
+    ($failure:expr, $($arg:tt)+) => {
+        return Err($failure.error(format!($($arg)+)))
+    };
+}
+
+pub(crate) use bail_with;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let conflict = fail(hubert::Error::AlreadyExists { arid: "x".into() });
+        assert_eq!(conflict.to_string(), "x already exists");
+        assert_eq!(Failure::of_error(&conflict), Failure::Conflict);
+
+        // Context added by scripts keeps the code
+        let timeout = fail(hubert::Error::DeadlineExceeded).context("run:3");
+        assert_eq!(Failure::of_error(&timeout), Failure::Timeout);
+
+        let untagged = anyhow::anyhow!("untagged");
+        assert_eq!(Failure::of_error(&untagged), Failure::General);
+        let usage = Failure::Usage.error("bad input").context("run:1");
+        assert_eq!(Failure::of_error(&usage), Failure::Usage);
+        assert_eq!(
+            Failure::of(&hubert::ipfs::Error::Timeout.into()),
+            Failure::Timeout
+        );
+    }
+
+    #[test]
+    fn test_too_large_suggests_backend() {
+        let dht = fail(hubert::mainline::Error::ValueTooLarge {
+            size: 2000,
+            limit: 500,
+        });
+        assert_eq!(Failure::of_error(&dht), Failure::Usage);
+        assert!(dht.to_string().starts_with(
+            "Mainline DHT error: Value size 2000 exceeds DHT limit of 500 bytes"
+        ));
+        assert!(dht.to_string().contains("--storage hybrid"));
+
+        let ipfs = fail(hubert::ipfs::Error::EnvelopeTooLarge {
+            size: 2000,
+            limit: 1500,
+        });
+        assert!(ipfs.to_string().contains("limit of 1500 bytes"));
+        assert!(ipfs.to_string().ends_with(
+            "--storage server, which takes envelopes of up to 10.5 MB"
+        ));
+
+        // Nothing takes what the server would refuse too
+        let size = hubert::server::MAX_ENVELOPE_SIZE + 1;
+        let huge = fail(hubert::ipfs::Error::EnvelopeTooLarge {
+            size,
+            limit: hubert::ipfs::DEFAULT_MAX_ENVELOPE_SIZE,
+        });
+        let message = huge.to_string();
+        assert!(message.ends_with("no backend stores envelopes this large"));
+    }
+}
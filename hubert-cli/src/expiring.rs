@@ -0,0 +1,188 @@
+//! Reminders for `hubert history expiring`: puts in the ledger whose TTL is
+//! about to run out, so they can be reposted before they disappear.
+//!
+//! Besides printing them, each reminder can raise a desktop notification
+//! or be POSTed as JSON to a webhook, either once (from cron, say) or from
+//! a long-running `--watch` loop.
+
+use std::{collections::HashSet, time::Duration};
+
+use anyhow::{Result, bail};
+use hubert::logging::verbose_println;
+use serde::Serialize;
+
+use crate::ledger::{Entry, Ledger, format_time, now};
+
+/// How to deliver reminders, besides printing them.
+pub struct Notifications {
+    /// Raise a desktop notification for each reminder.
+    pub desktop: bool,
+    /// URL to POST each reminder to, as JSON.
+    pub webhook: Option<String>,
+}
+
+/// The JSON body POSTed to a webhook.
+#[derive(Serialize)]
+struct Reminder<'a> {
+    id: i64,
+    arid: &'a str,
+    location: String,
+    expires_at: u64,
+    repost: String,
+}
+
+/// One line describing `entry`, which expires after `now`.
+pub fn describe(entry: &Entry, now: u64) -> String {
+    let expires_at = entry.expires_at().unwrap_or(now);
+    format!(
+        "{}  {}  in {}  {}  {}",
+        entry.id,
+        format_time(expires_at),
+        format_remaining(expires_at.saturating_sub(now)),
+        entry.location.storage,
+        entry.arid
+    )
+}
+
+/// Report the entries expiring within `within` seconds and deliver their
+/// notifications, returning one line per entry.
+pub async fn check(
+    ledger: &Ledger,
+    within: u64,
+    notifications: &Notifications,
+    verbose: bool,
+) -> Result<Vec<String>> {
+    let now = now();
+    let mut lines = Vec::new();
+    for entry in ledger.expiring(now, now + within)? {
+        notify(&entry, now, notifications, verbose).await;
+        lines.push(describe(&entry, now));
+    }
+    Ok(lines)
+}
+
+/// Check the ledger every `interval` seconds until interrupted, reminding
+/// once about each entry as it comes within `within` seconds of expiring.
+pub async fn watch(
+    ledger: &Ledger,
+    within: u64,
+    interval: u64,
+    notifications: &Notifications,
+    verbose: bool,
+) -> Result<()> {
+    let mut reminded = HashSet::new();
+    loop {
+        let now = now();
+        for entry in ledger.expiring(now, now + within)? {
+            if reminded.insert(entry.id) {
+                println!("{}", describe(&entry, now));
+                notify(&entry, now, notifications, verbose).await;
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(interval.max(1))) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Deliver the notifications for `entry`. Failures are reported but do not
+/// stop the remaining reminders.
+async fn notify(
+    entry: &Entry,
+    now: u64,
+    notifications: &Notifications,
+    verbose: bool,
+) {
+    let expires_at = entry.expires_at().unwrap_or(now);
+    let repost = format!("hubert history repost {}", entry.id);
+
+    if notifications.desktop {
+        let body = format!(
+            "{} expires in {}. To publish it again: {}",
+            entry.arid,
+            format_remaining(expires_at.saturating_sub(now)),
+            repost
+        );
+        if let Err(e) = desktop_notification("Hubert entry expiring", &body) {
+            eprintln!("Desktop notification failed: {}", e);
+        }
+    }
+
+    if let Some(url) = &notifications.webhook {
+        let reminder = Reminder {
+            id: entry.id,
+            arid: &entry.arid,
+            location: entry.location.to_string(),
+            expires_at,
+            repost,
+        };
+        match post_webhook(url, &reminder).await {
+            Ok(()) if verbose => verbose_println(&format!(
+                "Sent reminder for ledger entry {} to {}",
+                entry.id, url
+            )),
+            Ok(()) => {}
+            Err(e) => eprintln!("Webhook {} failed: {}", url, e),
+        }
+    }
+}
+
+async fn post_webhook(url: &str, reminder: &Reminder<'_>) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(reminder)?)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        bail!("responded {}", response.status());
+    }
+    Ok(())
+}
+
+/// Show a notification with the platform's own tool: `osascript` on macOS,
+/// `notify-send` elsewhere.
+fn desktop_notification(title: &str, body: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {:?} with title {:?}",
+            body, title
+        ));
+        command
+    } else {
+        let mut command = std::process::Command::new("notify-send");
+        command.args([title, body]);
+        command
+    };
+    let status = command.status()?;
+    if !status.success() {
+        bail!("{:?} exited with {}", command.get_program(), status);
+    }
+    Ok(())
+}
+
+/// A number of seconds as a short duration, such as `42m` or `1h 30m`.
+fn format_remaining(seconds: u64) -> String {
+    match seconds {
+        s if s >= 86400 => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+        s if s >= 3600 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(42), "42s");
+        assert_eq!(format_remaining(2700), "45m");
+        assert_eq!(format_remaining(5400), "1h 30m");
+        assert_eq!(format_remaining(90000), "1d 1h");
+    }
+}
@@ -0,0 +1,145 @@
+//! The registry behind `hubert keepalive`: the ARIDs whose Mainline DHT
+//! entries `hubert keepalive run` republishes.
+//!
+//! The registry is a text file, by default `keepalive.txt` in the data
+//! directory shown by `hubert paths`, listing one ARID per line.
+//! `hubert keepalive start` and `stop` edit it, and a running daemon picks
+//! up the change at its next reload, so entries come and go without
+//! restarting it.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use bc_components::ARID;
+use bc_ur::prelude::*;
+use hubert::{logging::verbose_println, mainline::MainlineDhtKv};
+
+use super::{
+    exit::{Failure, bail_with, fail},
+    parse_arid,
+};
+
+/// How often a running daemon rereads the registry.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The registry file to use: `path`, or the default location.
+pub fn resolve(path: Option<PathBuf>) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => hubert::paths::default_keepalive_file().map_err(fail),
+    }
+}
+
+/// The ARIDs in the registry at `path`, in the order they were started.
+/// A registry that does not exist yet is empty.
+pub fn read(path: &Path) -> Result<Vec<ARID>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new());
+        }
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Cannot read {}", path.display()));
+        }
+    };
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_arid)
+        .collect()
+}
+
+fn write(path: &Path, arids: &[ARID]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let text: String = arids
+        .iter()
+        .map(|arid| format!("{}\n", arid.ur_string()))
+        .collect();
+    std::fs::write(path, text)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Add `arids` to the registry at `path`. Returns how many were not in it
+/// already.
+pub fn start(path: &Path, arids: &[ARID]) -> Result<usize> {
+    let mut registered = read(path)?;
+    let before = registered.len();
+    for arid in arids {
+        if !registered.contains(arid) {
+            registered.push(*arid);
+        }
+    }
+    write(path, &registered)?;
+    Ok(registered.len() - before)
+}
+
+/// Remove `arids` from the registry at `path`. Fails, changing nothing, if
+/// any of them is not in it.
+pub fn stop(path: &Path, arids: &[ARID]) -> Result<()> {
+    let mut registered = read(path)?;
+    if let Some(arid) = arids.iter().find(|arid| !registered.contains(arid)) {
+        bail_with!(
+            Failure::NotFound,
+            "✗ {} is not being kept alive",
+            arid.ur_string()
+        );
+    }
+    registered.retain(|arid| !arids.contains(arid));
+    write(path, &registered)
+}
+
+/// Republish the entries in the registry at `path` through `store` until
+/// interrupted, rereading the registry every minute.
+pub async fn run(store: &MainlineDhtKv, path: &Path, verbose: bool) {
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+    let mut reload = tokio::time::interval(RELOAD_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = reload.tick() => match read(path) {
+                Ok(registered) => sync(store, &registered, verbose).await,
+                // A registry being edited is read again at the next reload
+                Err(e) => eprintln!("✗ {:#}", e),
+            },
+            _ = &mut interrupted => return,
+        }
+    }
+}
+
+/// Keep alive exactly the entries in `registered`.
+async fn sync(store: &MainlineDhtKv, registered: &[ARID], verbose: bool) {
+    let kept = store.kept_alive();
+    for arid in kept.iter().filter(|arid| !registered.contains(arid)) {
+        store.stop_keepalive(arid);
+        if verbose {
+            verbose_println(&format!(
+                "Stopped keeping {} alive",
+                arid.ur_string()
+            ));
+        }
+    }
+    // Entries not found, or not reached, are looked for at the next reload
+    for arid in registered.iter().filter(|arid| !kept.contains(arid)) {
+        match store.keepalive(arid).await {
+            Ok(true) => {
+                if verbose {
+                    verbose_println(&format!(
+                        "Keeping {} alive",
+                        arid.ur_string()
+                    ));
+                }
+            }
+            Ok(false) => {
+                eprintln!("✗ No entry found at {} yet", arid.ur_string())
+            }
+            Err(e) => eprintln!("✗ {}: {}", arid.ur_string(), e),
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! A local record of the puts made with `--ledger`, browsed with
+//! `hubert history`.
+//!
+//! The ledger is a SQLite file, by default `ledger.sqlite` in the data
+//! directory shown by `hubert paths`. It holds each envelope and its ARID,
+//! so anyone who can read the file can read and republish what was stored.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+use crate::exit::{Failure, bail_with, fail};
+
+/// A put as recorded in the ledger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Position in the ledger, starting at 1.
+    pub id: i64,
+    /// UNIX time of the put.
+    pub timestamp: u64,
+    /// The ARID, in ur:arid form.
+    pub arid: String,
+    pub location: Location,
+    pub ttl: Option<u64>,
+    /// The receipt the backend returned.
+    pub receipt: String,
+    /// The stored envelope, in ur:envelope form.
+    pub envelope: String,
+}
+
+/// Where a put went, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// The `--storage` backend name.
+    pub storage: String,
+    /// The `--host` given, if any.
+    pub host: Option<String>,
+    /// The `--port` given, if any.
+    pub port: Option<u16>,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "--storage {}", self.storage)?;
+        if let Some(host) = &self.host {
+            write!(f, " --host {}", host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, " --port {}", port)?;
+        }
+        Ok(())
+    }
+}
+
+/// The ledger file to use: `path`, or the default location if `--ledger`
+/// was given without one.
+pub fn resolve(path: Option<PathBuf>) -> Result<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => {
+            let dir = hubert::paths::data_dir().map_err(fail)?;
+            Ok(dir.join(hubert::paths::LEDGER_FILE_NAME))
+        }
+    }
+}
+
+/// An open ledger.
+pub struct Ledger {
+    connection: Connection,
+}
+
+impl Ledger {
+    /// Open the ledger at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) =
+            path.parent().filter(|p| !p.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create {}", parent.display())
+            })?;
+        }
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        Self::init(connection)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS puts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp INTEGER NOT NULL,
+                arid TEXT NOT NULL,
+                storage TEXT NOT NULL,
+                host TEXT,
+                port INTEGER,
+                ttl INTEGER,
+                receipt TEXT NOT NULL,
+                envelope TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_puts_arid ON puts(arid);",
+        )?;
+        Ok(Self { connection })
+    }
+
+    /// Record a put made now, returning its entry.
+    pub fn record(
+        &self,
+        arid: &str,
+        location: &Location,
+        ttl: Option<u64>,
+        receipt: &str,
+        envelope: &str,
+    ) -> Result<Entry> {
+        let timestamp = now();
+        self.connection.execute(
+            "INSERT INTO puts
+                (timestamp, arid, storage, host, port, ttl, receipt, envelope)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                timestamp as i64,
+                arid,
+                location.storage,
+                location.host,
+                location.port,
+                ttl.map(|ttl| ttl as i64),
+                receipt,
+                envelope
+            ],
+        )?;
+        Ok(Entry {
+            id: self.connection.last_insert_rowid(),
+            timestamp,
+            arid: arid.to_string(),
+            location: location.clone(),
+            ttl,
+            receipt: receipt.to_string(),
+            envelope: envelope.to_string(),
+        })
+    }
+
+    /// The `limit` most recent entries, newest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<Entry>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, timestamp, arid, storage, host, port, ttl, receipt,
+                    envelope
+             FROM puts ORDER BY id DESC LIMIT ?1",
+        )?;
+        let entries = statement
+            .query_map([limit as i64], entry_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(entries)
+    }
+
+    /// The entries stored with a TTL that expire after `from` and no later
+    /// than `until`, soonest first.
+    pub fn expiring(&self, from: u64, until: u64) -> Result<Vec<Entry>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, timestamp, arid, storage, host, port, ttl, receipt,
+                    envelope
+             FROM puts
+             WHERE ttl IS NOT NULL AND timestamp + ttl > ?1
+                   AND timestamp + ttl <= ?2
+             ORDER BY timestamp + ttl, id",
+        )?;
+        let entries = statement
+            .query_map([from as i64, until as i64], entry_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(entries)
+    }
+
+    /// The entry selected by `selector`: its ID, or an ARID, which selects
+    /// the latest put to that ARID.
+    pub fn find(&self, selector: &str) -> Result<Entry> {
+        let selector = selector.trim();
+        let select = "SELECT id, timestamp, arid, storage, host, port, ttl,
+                             receipt, envelope
+                      FROM puts";
+        let entry = match selector.parse::<i64>() {
+            Ok(id) => self
+                .connection
+                .query_row(
+                    &format!("{} WHERE id = ?1", select),
+                    [id],
+                    entry_from_row,
+                )
+                .optional()?,
+            Err(_) => self
+                .connection
+                .query_row(
+                    &format!("{} WHERE arid = ?1 ORDER BY id DESC", select),
+                    [selector],
+                    entry_from_row,
+                )
+                .optional()?,
+        };
+        match entry {
+            Some(entry) => Ok(entry),
+            None => {
+                bail_with!(
+                    Failure::NotFound,
+                    "No ledger entry matches {}",
+                    selector
+                )
+            }
+        }
+    }
+}
+
+fn entry_from_row(row: &Row<'_>) -> rusqlite::Result<Entry> {
+    Ok(Entry {
+        id: row.get(0)?,
+        timestamp: row.get::<_, i64>(1)? as u64,
+        arid: row.get(2)?,
+        location: Location {
+            storage: row.get(3)?,
+            host: row.get(4)?,
+            port: row.get(5)?,
+        },
+        ttl: row.get::<_, Option<i64>>(6)?.map(|ttl| ttl as u64),
+        receipt: row.get(7)?,
+        envelope: row.get(8)?,
+    })
+}
+
+impl Entry {
+    /// UNIX time the entry expires, if it was stored with a TTL.
+    pub fn expires_at(&self) -> Option<u64> {
+        self.ttl.map(|ttl| self.timestamp + ttl)
+    }
+
+    /// One line for `history list`.
+    pub fn summary(&self) -> String {
+        format!(
+            "{}  {}  {}  {}",
+            self.id,
+            format_time(self.timestamp),
+            self.location.storage,
+            self.arid
+        )
+    }
+
+    /// Every field, for `history show`.
+    pub fn details(&self) -> String {
+        let ttl = self
+            .ttl
+            .map_or_else(|| "-".to_string(), |ttl| format!("{}s", ttl));
+        [
+            format!("ID:       {}", self.id),
+            format!("Time:     {}", format_time(self.timestamp)),
+            format!("ARID:     {}", self.arid),
+            format!("Location: {}", self.location),
+            format!("TTL:      {}", ttl),
+            format!("Receipt:  {}", self.receipt),
+            format!("Envelope: {}", self.envelope),
+        ]
+        .join("\n")
+    }
+}
+
+/// The current UNIX time.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// A UNIX time as a UTC date and time, such as `2025-01-31 14:05:09 UTC`.
+pub fn format_time(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_find() {
+        let ledger =
+            Ledger::init(Connection::open_in_memory().unwrap()).unwrap();
+        let server = Location {
+            storage: "server".into(),
+            host: None,
+            port: Some(45678),
+        };
+        let mainline =
+            Location { storage: "mainline".into(), host: None, port: None };
+        let first = ledger
+            .record("ur:arid/a", &server, Some(60), "ok", "e1")
+            .unwrap();
+        let second = ledger
+            .record("ur:arid/a", &mainline, None, "ok", "e2")
+            .unwrap();
+        assert_eq!((first.id, second.id), (1, 2));
+
+        assert_eq!(ledger.recent(10).unwrap(), [second.clone(), first.clone()]);
+        assert_eq!(ledger.recent(1).unwrap(), [second.clone()]);
+        assert_eq!(ledger.find("1").unwrap(), first);
+        assert_eq!(ledger.find("ur:arid/a").unwrap(), second);
+        assert!(ledger.find("3").is_err());
+        assert_eq!(server.to_string(), "--storage server --port 45678");
+    }
+
+    #[test]
+    fn test_expiring() {
+        let ledger =
+            Ledger::init(Connection::open_in_memory().unwrap()).unwrap();
+        let location =
+            Location { storage: "server".into(), host: None, port: None };
+        let soon = ledger
+            .record("ur:arid/a", &location, Some(60), "ok", "e1")
+            .unwrap();
+        let later = ledger
+            .record("ur:arid/b", &location, Some(7200), "ok", "e2")
+            .unwrap();
+        ledger
+            .record("ur:arid/c", &location, None, "ok", "e3")
+            .unwrap();
+
+        let start = soon.timestamp;
+        assert_eq!(soon.expires_at(), Some(start + 60));
+        assert_eq!(
+            ledger.expiring(start, start + 3600).unwrap(),
+            [soon.clone()]
+        );
+        assert_eq!(
+            ledger.expiring(start, start + 86400).unwrap(),
+            [soon, later.clone()]
+        );
+        // Entries that have already expired are left out
+        assert_eq!(
+            ledger.expiring(start + 60, start + 86400).unwrap(),
+            [later]
+        );
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_time(1_738_332_309), "2025-01-31 14:05:09 UTC");
+    }
+}
@@ -0,0 +1,2388 @@
+//! Hubert: Secure Distributed Substrate for Multiparty Transactions
+//!
+//! A command-line tool for storing and retrieving Gordian Envelopes using
+//! distributed storage backends (BitTorrent Mainline DHT or IPFS).
+
+mod batch;
+mod bench;
+mod exit;
+mod expiring;
+mod keepalive;
+mod ledger;
+mod monitor;
+mod recommend;
+mod script;
+mod verify;
+
+use std::{
+    collections::HashMap, net::Ipv4Addr, path::PathBuf, process::ExitCode,
+};
+
+use anyhow::Result;
+use bc_components::{ARID, Digest, DigestProvider};
+use bc_envelope::Envelope;
+use bc_rand::random_data;
+use bc_ur::prelude::*;
+use clap::{Parser, Subcommand, ValueEnum};
+use exit::{Failure, bail_with, fail};
+use hubert::{
+    HealthReport, KvStore, Mutability,
+    attachments::{add_attachment, fetch_attachments, store_attachment},
+    duration::parse_duration,
+    expiry,
+    filter::{check_types, parse_known_value},
+    hybrid::HybridKv,
+    ipfs::IpfsKv,
+    logging::verbose_println,
+    mainline::{MainlineDhtKv, MainlineDhtKvBuilder},
+    names::{DnsTxtResolver, HubertName, NameResolver, WellKnownResolver},
+    payload::{decrypt_payload, encrypt_payload},
+    server::{GetOutcome, ReceiptStatus, ServerKvClient},
+    ur_input::{parse_arid_ur, parse_envelope_ur},
+};
+
+/// Hubert: Distributed substrate for multiparty transactions
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+#[command(infer_subcommands = true)]
+struct Cli {
+    /// Enable verbose logging
+    #[arg(long, short, global = true)]
+    verbose: bool,
+
+    /// Print only results and errors, suppressing status messages
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// UDP port for the embedded Mainline DHT node (default: any free port)
+    #[arg(long, global = true, value_name = "PORT")]
+    dht_port: Option<u16>,
+
+    /// Local IPv4 address of the interface the DHT node binds to (default:
+    /// all interfaces)
+    #[arg(long, global = true, value_name = "ADDRESS")]
+    dht_bind: Option<Ipv4Addr>,
+
+    /// Milliseconds to wait for each DHT node to answer a request
+    #[arg(long, global = true, value_name = "MS")]
+    dht_request_timeout: Option<u64>,
+
+    /// Deployment domain that DHT and IPFS keys are derived in (default:
+    /// $HUBERT_DOMAIN, or none). Readers must use the writer's domain
+    #[arg(long, global = true, value_name = "DOMAIN")]
+    domain: Option<String>,
+
+    /// Record each successful put in a local ledger (see `hubert history`),
+    /// kept in the data directory or at PATH if given as --ledger=PATH
+    #[arg(long, global = true, value_name = "PATH", require_equals = true)]
+    ledger: Option<Option<PathBuf>>,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+enum StorageBackend {
+    /// BitTorrent Mainline DHT (fast, ≤1 KB messages)
+    Mainline,
+    /// IPFS (large capacity, up to 10 MB messages)
+    Ipfs,
+    /// Hybrid (automatic: DHT for small, IPFS for large)
+    Hybrid,
+    /// Hubert HTTP server (centralized coordination)
+    Server,
+}
+
+impl StorageBackend {
+    /// The name used on the command line.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mainline => "mainline",
+            Self::Ipfs => "ipfs",
+            Self::Hybrid => "hybrid",
+            Self::Server => "server",
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Generate a new ARID or example Envelope
+    Generate {
+        #[command(subcommand)]
+        generate_type: GenerateType,
+    },
+
+    /// Store an envelope at an ARID
+    Put {
+        /// Storage backend to use. If not given, one is chosen from the
+        /// envelope's size and which backends answer: a server given with
+        /// --host or --capability, then the DHT for envelopes that fit,
+        /// then hybrid storage or IPFS
+        #[arg(long, short)]
+        storage: Option<StorageBackend>,
+
+        /// Print why the backend was chosen (only without --storage)
+        #[arg(long, conflicts_with = "storage")]
+        explain: bool,
+
+        /// Server/IPFS host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server, --storage ipfs, or --storage hybrid)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// ARID key (ur:arid format)
+        #[arg(value_name = "ARID")]
+        arid: String,
+
+        /// Envelope value (ur:envelope format)
+        #[arg(value_name = "ENVELOPE")]
+        envelope: String,
+
+        /// Time-to-live, such as 3600, 90m, or 2h30m (for --storage server
+        /// or --storage ipfs/hybrid). Server: controls data retention
+        /// (default: 24 hours). IPFS: controls IPNS record lifetime
+        /// (default: 24 hours). Hybrid: how long an envelope stored in IPFS
+        /// stays reachable (default: 24 hours); see `hubert keepalive`.
+        #[arg(long, value_parser = parse_ttl)]
+        ttl: Option<u64>,
+
+        /// Pin content in IPFS (only for --storage ipfs or --storage hybrid)
+        #[arg(long)]
+        pin: bool,
+
+        /// Attachment to store alongside the envelope (ur:envelope format).
+        /// Each is stored at its own ARID and referenced from the envelope
+        /// by ARID and digest. May be repeated.
+        #[arg(long, value_name = "ENVELOPE")]
+        attach: Vec<String>,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one (only for --storage server)
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+
+        /// Encrypt the envelope with a key derived from the ARID, so only
+        /// those who know the ARID can read it, not the storage operators
+        #[arg(long, conflicts_with = "attach")]
+        arid_encrypt: bool,
+
+        /// Context for the --arid-encrypt key, giving a different key for
+        /// each use of an ARID (default: none)
+        #[arg(long, value_name = "CONTEXT", requires = "arid_encrypt")]
+        key_context: Option<String>,
+
+        /// Replace the envelope already at the ARID, or store it if there
+        /// is none, reporting its new version. The backend must accept
+        /// updates; a server must be configured with `mutability =
+        /// "updatable"`
+        #[arg(long, requires = "storage", conflicts_with = "attach")]
+        update: bool,
+    },
+
+    /// Store the envelopes listed in a manifest, several at a time, and
+    /// report how each put went
+    PutBatch {
+        /// JSON Lines file with one entry per line: "envelope" (ur:envelope
+        /// format) or "file" (relative to the manifest), and optionally
+        /// "arid", "ttl", "storage", "host", and "port"
+        #[arg(long, value_name = "PATH")]
+        manifest: PathBuf,
+
+        /// Storage backend for entries that do not name one
+        #[arg(long, short, default_value = "mainline")]
+        storage: StorageBackend,
+
+        /// Server host for entries that do not name a backend (for
+        /// --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port for entries that do not name a backend (for --storage
+        /// server, --storage ipfs, or --storage hybrid)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Time-to-live, such as 3600 or 90m, for entries that do not give
+        /// one
+        #[arg(long, value_parser = parse_ttl)]
+        ttl: Option<u64>,
+
+        /// Puts to run at once
+        #[arg(long, default_value = "8")]
+        concurrency: usize,
+
+        /// Capability token (ur:envelope format) to present to servers that
+        /// require one
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+    },
+
+    /// Retrieve an envelope by ARID
+    Get {
+        /// Storage backend to use
+        #[arg(long, short, default_value = "mainline")]
+        storage: StorageBackend,
+
+        /// Server/IPFS host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server, --storage ipfs, or --storage hybrid)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// ARID key (ur:arid format), or a name
+        /// (hubert:<domain>/<label>) to resolve to one
+        #[arg(value_name = "ARID")]
+        arid: String,
+
+        /// Where to look up a name given instead of an ARID
+        #[arg(long, default_value = "well-known")]
+        resolver: ResolverKind,
+
+        /// Maximum time to wait, such as 30, 90s, or 2m (default: 30s)
+        #[arg(long, short, default_value = "30", value_parser = parse_duration)]
+        timeout: u64,
+
+        /// Also fetch and verify the envelope's attachments, printing each
+        /// on its own line after the envelope
+        #[arg(long)]
+        with_attachments: bool,
+
+        /// Fail unless the envelope declares this type (a known value name
+        /// such as 'Seed', or its number). May be repeated.
+        #[arg(long, value_name = "KNOWN_VALUE")]
+        expect_type: Vec<String>,
+
+        /// Pin the retrieved content in the local IPFS node and print its
+        /// CID to stderr (only for --storage ipfs or --storage hybrid)
+        #[arg(long)]
+        pin: bool,
+
+        /// Fail unless the envelope has this digest (ur:digest format), as
+        /// recorded by the writer
+        #[arg(long, value_name = "DIGEST")]
+        digest: Option<String>,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one (only for --storage server)
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+
+        /// Decrypt an envelope stored with `put --arid-encrypt`
+        #[arg(long, conflicts_with = "with_attachments")]
+        arid_encrypt: bool,
+
+        /// Context the envelope's --arid-encrypt key was derived with
+        #[arg(long, value_name = "CONTEXT", requires = "arid_encrypt")]
+        key_context: Option<String>,
+    },
+
+    /// Check if storage backend is available
+    Check {
+        /// Storage backend to use
+        #[arg(long, short, default_value = "mainline")]
+        storage: StorageBackend,
+
+        /// Server/IPFS host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server, --storage ipfs, or --storage hybrid)
+        #[arg(long)]
+        port: Option<u16>,
+    },
+
+    /// Self-test a backend end to end: put a throwaway envelope at a new
+    /// ARID, get it back, and report each step's latency. Exits nonzero if
+    /// any step fails, for monitoring from cron
+    Verify {
+        /// Storage backend to use
+        #[arg(long, short, default_value = "mainline")]
+        storage: StorageBackend,
+
+        /// Server/IPFS host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server, --storage ipfs, or --storage hybrid)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Maximum time the get waits, such as 30 or 2m (default: 30s)
+        #[arg(long, short, default_value = "30", value_parser = parse_duration)]
+        timeout: u64,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one (only for --storage server)
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+    },
+
+    /// Start the Hubert HTTP server
+    Server {
+        /// TOML configuration file. Values can be overridden by HUBERT_*
+        /// environment variables and by the flags below. Defaults to
+        /// hubert.toml in the config directory shown by `hubert paths`, if
+        /// it exists.
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Address to bind to (default: 127.0.0.1)
+        #[arg(long)]
+        bind: Option<String>,
+
+        /// Port for the server to listen on (default: 45678)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// SQLite database file path for persistent storage.
+        /// If a directory is provided, uses 'hubert.sqlite' in that directory.
+        /// If given without a value, uses the platform's default data
+        /// directory. If not provided, uses in-memory storage.
+        #[arg(long, value_name = "PATH")]
+        sqlite: Option<Option<PathBuf>>,
+
+        /// Persist entries in SQLite in the default data directory
+        /// (shorthand for --sqlite with no value)
+        #[arg(long, conflicts_with = "sqlite")]
+        persist: bool,
+
+        /// Reject all puts with 403 while still serving gets
+        #[arg(long)]
+        read_only: bool,
+
+        /// TTL of puts that do not request one, such as 3600 or 6h
+        /// (default: the maximum TTL)
+        #[arg(long, value_name = "DURATION", value_parser = parse_ttl)]
+        default_ttl: Option<u64>,
+
+        /// Longest TTL a put may request, such as 86400 or 1d; longer ones
+        /// are shortened (default: 1d)
+        #[arg(long, value_name = "DURATION", value_parser = parse_ttl)]
+        max_ttl: Option<u64>,
+    },
+
+    /// Show how an ARID resolves through IPNS: key name, IPNS name, CID,
+    /// record validity, and content size
+    Resolve {
+        /// Storage backend to use (only ipfs)
+        #[arg(long, short, default_value = "ipfs")]
+        storage: StorageBackend,
+
+        /// IPFS port (default: 5001)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// ARID key (ur:arid format)
+        #[arg(value_name = "ARID")]
+        arid: String,
+    },
+
+    /// Unpin content pinned by `put --pin` or `get --pin`, letting the local
+    /// IPFS node garbage-collect it
+    Unpin {
+        /// Storage backend to use (ipfs or hybrid)
+        #[arg(long, short, default_value = "ipfs")]
+        storage: StorageBackend,
+
+        /// IPFS port (default: 5001)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// ARID key (ur:arid format)
+        #[arg(value_name = "ARID")]
+        arid: String,
+
+        /// Maximum time to wait, such as 30, 90s, or 2m (default: 30s)
+        #[arg(long, short, default_value = "30", value_parser = parse_duration)]
+        timeout: u64,
+    },
+
+    /// Delete an envelope before it expires, so it can no longer be read.
+    /// Exits nonzero if nothing was stored at the ARID
+    Delete {
+        /// Storage backend to use (server or ipfs)
+        #[arg(long, short, default_value = "server")]
+        storage: StorageBackend,
+
+        /// Server host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server or --storage ipfs)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// ARID key (ur:arid format)
+        #[arg(value_name = "ARID")]
+        arid: String,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one (only for --storage server)
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+    },
+
+    /// Show an envelope's size, when it was stored, and how long it will
+    /// remain available, without fetching it. Exits nonzero if nothing is
+    /// stored at the ARID
+    Stat {
+        /// Storage backend to use (server)
+        #[arg(long, short, default_value = "server")]
+        storage: StorageBackend,
+
+        /// Server host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// ARID key (ur:arid format)
+        #[arg(value_name = "ARID")]
+        arid: String,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one (only for --storage server)
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+    },
+
+    /// Keep Mainline DHT entries alive by republishing them. The DHT
+    /// otherwise drops entries after about two hours.
+    Keepalive {
+        #[command(subcommand)]
+        command: KeepaliveCommand,
+
+        /// Keepalive registry file (default: keepalive.txt in the data
+        /// directory)
+        #[arg(long, global = true, value_name = "PATH")]
+        registry: Option<PathBuf>,
+    },
+
+    /// Measure a backend under synthetic load: put random envelopes at new
+    /// ARIDs, get each back, and report latency percentiles and error rates
+    Bench {
+        /// Storage backend to use
+        #[arg(long, short, default_value = "mainline")]
+        storage: StorageBackend,
+
+        /// Server/IPFS host (for --storage server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for --storage server, --storage ipfs, or --storage hybrid)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Number of put/get pairs to run
+        #[arg(long, default_value = "1000")]
+        ops: usize,
+
+        /// Pairs to run at once
+        #[arg(long, default_value = "16")]
+        concurrency: usize,
+
+        /// Random bytes in each envelope
+        #[arg(long, default_value = "500")]
+        size: usize,
+
+        /// Time-to-live, such as 600 or 10m, for the stored envelopes (for
+        /// backends that support it)
+        #[arg(long, value_parser = parse_ttl)]
+        ttl: Option<u64>,
+
+        /// Maximum time each get waits, such as 30 or 2m (default: 30s)
+        #[arg(long, short, default_value = "30", value_parser = parse_duration)]
+        timeout: u64,
+    },
+
+    /// Watch a set of ARIDs, looking each up every few seconds and showing
+    /// which are found, pending, or expired in a table that refreshes in
+    /// place
+    Monitor {
+        /// File listing one ARID (ur:arid format) per line, optionally
+        /// followed by the backend to look in
+        #[arg(long, value_name = "PATH")]
+        arids: PathBuf,
+
+        /// Storage backend for ARIDs that do not name one
+        #[arg(long, short, default_value = "mainline")]
+        storage: StorageBackend,
+
+        /// Server host (for ARIDs looked up on a server)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port (for ARIDs looked up on a server, IPFS, or hybrid storage)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Time between lookups, such as 5 or 1m
+        #[arg(long, default_value = "5", value_parser = parse_duration)]
+        interval: u64,
+
+        /// Print the table once and exit
+        #[arg(long)]
+        once: bool,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+    },
+
+    /// Browse and republish the puts recorded with --ledger
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+
+    /// Print where Hubert keeps its data, configuration, and cache
+    Paths,
+
+    /// Run a script of hubert commands, one per line
+    Run {
+        /// Script file (conventionally *.hub)
+        #[arg(value_name = "SCRIPT")]
+        script: PathBuf,
+    },
+
+    /// Test vectors for checking other implementations of Hubert
+    Vectors {
+        #[command(subcommand)]
+        command: VectorsCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum VectorsCommand {
+    /// Write the deterministic test vectors: derived keys, obfuscated
+    /// payloads, and reference envelopes for fixed ARIDs
+    Generate {
+        /// Output format
+        #[arg(long, short, value_enum, default_value = "json")]
+        format: VectorFormat,
+
+        /// File to write to (default: standard output, with CBOR as hex)
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ResolverKind {
+    /// https://<domain>/.well-known/hubert/<label>
+    WellKnown,
+    /// DNS TXT record of <label>._hubert.<domain>
+    Dns,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum VectorFormat {
+    Json,
+    Cbor,
+}
+
+#[derive(Debug, Subcommand)]
+enum KeepaliveCommand {
+    /// Add entries to the registry, for `hubert keepalive run` to keep
+    /// alive
+    Start {
+        /// ARIDs of the entries to keep alive (ur:arid format)
+        #[arg(value_name = "ARID", required = true)]
+        arids: Vec<String>,
+    },
+    /// Remove entries from the registry, leaving them to lapse
+    Stop {
+        /// ARIDs of the entries to stop keeping alive (ur:arid format)
+        #[arg(value_name = "ARID", required = true)]
+        arids: Vec<String>,
+    },
+    /// List the entries in the registry
+    List,
+    /// Republish the entries in the registry until interrupted, picking up
+    /// starts and stops within a minute
+    Run {
+        /// Time between republishing, such as 3600 or 30m (default: 1h)
+        #[arg(long, default_value = "1h", value_parser = parse_duration)]
+        interval: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum HistoryCommand {
+    /// List recorded puts, newest first
+    List {
+        /// Number of puts to list
+        #[arg(long, short = 'n', default_value = "20")]
+        limit: usize,
+    },
+    /// Show everything recorded about a put
+    Show {
+        /// Ledger ID of the put, or its ARID (ur:arid format) for the
+        /// latest put to it
+        #[arg(value_name = "ENTRY")]
+        entry: String,
+    },
+    /// Put a recorded envelope again at its ARID, with the same backend
+    /// and TTL, for example after it expired or the DHT dropped it
+    Repost {
+        /// Ledger ID of the put, or its ARID (ur:arid format) for the
+        /// latest put to it
+        #[arg(value_name = "ENTRY")]
+        entry: String,
+
+        /// Capability token (ur:envelope format) to present to a server
+        /// that requires one (only for --storage server)
+        #[arg(long, value_name = "ENVELOPE")]
+        capability: Option<String>,
+    },
+    /// List recorded puts whose TTL runs out soon, soonest first, so they
+    /// can be reposted in time
+    Expiring {
+        /// How far ahead to look, such as 90s, 15m, 1h, or 2d
+        #[arg(long, default_value = "1h", value_parser = parse_duration)]
+        within: u64,
+
+        /// Also raise a desktop notification for each entry (notify-send,
+        /// or osascript on macOS)
+        #[arg(long)]
+        desktop: bool,
+
+        /// Also POST each entry as JSON to this URL
+        #[arg(long, value_name = "URL")]
+        webhook: Option<String>,
+
+        /// Keep running, reminding once about each entry as it comes
+        /// within --within of expiring
+        #[arg(long)]
+        watch: bool,
+
+        /// Time between checks with --watch, such as 60 or 5m
+        #[arg(
+            long,
+            default_value = "60",
+            requires = "watch",
+            value_parser = parse_duration
+        )]
+        interval: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum GenerateType {
+    /// Generate a new ARID
+    Arid,
+    /// Generate a test envelope with random data
+    Envelope {
+        /// Number of random bytes to include in the envelope's subject
+        #[arg(value_name = "SIZE")]
+        size: usize,
+    },
+}
+
+/// Parse a TTL flag, which must be at least a second.
+fn parse_ttl(s: &str) -> std::result::Result<u64, String> {
+    match parse_duration(s)? {
+        0 => Err("a TTL must be at least one second".to_string()),
+        ttl => Ok(ttl),
+    }
+}
+
+fn parse_arid(s: &str) -> Result<ARID> {
+    parse_arid_ur(s).map_err(|e| match e {
+        hubert::Error::InvalidArid => {
+            Failure::Usage.error("Invalid ARID format. Expected ur:arid")
+        }
+        e => Failure::Usage.error(format!("{}. Expected ur:arid", e)),
+    })
+}
+
+/// Parse an ARID argument, resolving it with `resolver` if it is a name.
+async fn resolve_arid(
+    s: &str,
+    resolver: ResolverKind,
+    verbose: bool,
+) -> Result<ARID> {
+    if !HubertName::is_name(s) {
+        return parse_arid(s);
+    }
+    let name: HubertName = s.parse().map_err(fail)?;
+    let arid = match resolver {
+        ResolverKind::WellKnown => {
+            WellKnownResolver::new().resolve(&name).await
+        }
+        ResolverKind::Dns => {
+            DnsTxtResolver::new().map_err(fail)?.resolve(&name).await
+        }
+    }
+    .map_err(fail)?;
+    if verbose {
+        verbose_println(&format!("Resolved {} to {}", name, arid.ur_string()));
+    }
+    Ok(arid)
+}
+
+fn parse_envelope(s: &str) -> Result<Envelope> {
+    parse_envelope_ur(s).map_err(|e| match e {
+        hubert::Error::InvalidEnvelope => Failure::Usage
+            .error("Invalid envelope format. Expected ur:envelope"),
+        e => Failure::Usage.error(format!("{}. Expected ur:envelope", e)),
+    })
+}
+
+/// Parse a `--capability` token, which only servers accept.
+fn parse_capability(
+    storage: StorageBackend,
+    capability: Option<String>,
+) -> Result<Option<Envelope>> {
+    match capability {
+        Some(_) if !matches!(storage, StorageBackend::Server) => {
+            bail_with!(
+                Failure::Usage,
+                "--capability option is only supported for --storage server"
+            )
+        }
+        Some(token) => Ok(Some(parse_envelope(&token)?)),
+        None => Ok(None),
+    }
+}
+
+fn generate_random_envelope(size: usize) -> Envelope {
+    let random_bytes = random_data(size);
+    let byte_string = ByteString::new(random_bytes);
+    Envelope::new(byte_string)
+}
+
+/// Backends kept alive across commands, so a script bootstraps the DHT once
+/// rather than on every line.
+#[derive(Default)]
+struct Backends {
+    /// Network settings for the Mainline DHT node.
+    dht: MainlineDhtKvBuilder,
+    mainline: Option<MainlineDhtKv>,
+    ipfs: HashMap<(u16, bool), IpfsKv>,
+    hybrid: HashMap<(u16, bool), HybridKv>,
+    servers: HashMap<(String, Option<String>), ServerKvClient>,
+    /// Whether successful puts are recorded in the ledger.
+    record_puts: bool,
+    /// Ledger file, if not the default.
+    ledger_path: Option<PathBuf>,
+    /// Deployment domain for DHT and IPFS key derivation.
+    domain: String,
+}
+
+impl Backends {
+    async fn mainline(&mut self) -> Result<&MainlineDhtKv> {
+        if self.mainline.is_none() {
+            let store = self.dht.clone().build().await.map_err(fail)?;
+            self.mainline = Some(
+                store
+                    .with_domain(self.domain.clone())
+                    .with_mutability(Mutability::Updatable),
+            );
+        }
+        Ok(self.mainline.as_ref().unwrap())
+    }
+
+    fn ledger(&self) -> Result<ledger::Ledger> {
+        ledger::Ledger::open(&ledger::resolve(self.ledger_path.clone())?)
+    }
+
+    async fn hybrid(&mut self, port: u16, pin: bool) -> Result<&HybridKv> {
+        if !self.hybrid.contains_key(&(port, pin)) {
+            let url = format!("http://127.0.0.1:{}", port);
+            let store = HybridKv::new(&url)
+                .await
+                .map_err(fail)?
+                .with_pin_content(pin)
+                .with_domain(self.domain.clone());
+            self.hybrid.insert((port, pin), store);
+        }
+        Ok(&self.hybrid[&(port, pin)])
+    }
+
+    /// The store for a backend with the same host and port defaults as the
+    /// individual commands.
+    async fn store(
+        &mut self,
+        storage: StorageBackend,
+        host: Option<&str>,
+        port: Option<u16>,
+        pin: bool,
+        capability: Option<&Envelope>,
+    ) -> Result<&dyn KvStore> {
+        let store: &dyn KvStore = match storage {
+            StorageBackend::Mainline => self.mainline().await?,
+            StorageBackend::Ipfs => {
+                let port = port.unwrap_or(5001);
+                let domain = &self.domain;
+                self.ipfs.entry((port, pin)).or_insert_with(|| {
+                    ipfs_store(port, domain).with_pin_content(pin)
+                })
+            }
+            StorageBackend::Hybrid => {
+                self.hybrid(port.unwrap_or(5001), pin).await?
+            }
+            StorageBackend::Server => {
+                let url = server_url(host, port);
+                let token = capability.map(|c| c.ur_string());
+                self.servers
+                    .entry((url.clone(), token))
+                    .or_insert_with(|| server_client(&url, capability))
+            }
+        };
+        Ok(store)
+    }
+
+    /// The store [`Backends::store`] returned for the same arguments, if it
+    /// has been opened, so several can be used at once.
+    fn opened(
+        &self,
+        storage: StorageBackend,
+        host: Option<&str>,
+        port: Option<u16>,
+        pin: bool,
+        capability: Option<&Envelope>,
+    ) -> Option<&dyn KvStore> {
+        match storage {
+            StorageBackend::Mainline => {
+                self.mainline.as_ref().map(|s| s as &dyn KvStore)
+            }
+            StorageBackend::Ipfs => self
+                .ipfs
+                .get(&(port.unwrap_or(5001), pin))
+                .map(|s| s as &dyn KvStore),
+            StorageBackend::Hybrid => self
+                .hybrid
+                .get(&(port.unwrap_or(5001), pin))
+                .map(|s| s as &dyn KvStore),
+            StorageBackend::Server => {
+                let token = capability.map(|c| c.ur_string());
+                self.servers
+                    .get(&(server_url(host, port), token))
+                    .map(|s| s as &dyn KvStore)
+            }
+        }
+    }
+}
+
+/// The URL of a server, with the same defaults as the individual commands.
+fn server_url(host: Option<&str>, port: Option<u16>) -> String {
+    format!(
+        "http://{}:{}",
+        host.unwrap_or("127.0.0.1"),
+        port.unwrap_or(45678)
+    )
+}
+
+/// A backend's name and, where it has one, its address, as in
+/// `("Hubert server", " at 127.0.0.1:45678")`.
+fn describe_backend(
+    storage: StorageBackend,
+    host: Option<&str>,
+    port: Option<u16>,
+) -> (&'static str, String) {
+    match storage {
+        StorageBackend::Mainline => ("Mainline DHT", String::new()),
+        StorageBackend::Ipfs => {
+            ("IPFS", format!(" at 127.0.0.1:{}", port.unwrap_or(5001)))
+        }
+        StorageBackend::Hybrid => ("Hybrid storage", String::new()),
+        StorageBackend::Server => (
+            "Hubert server",
+            format!(
+                " at {}:{}",
+                host.unwrap_or("127.0.0.1"),
+                port.unwrap_or(45678)
+            ),
+        ),
+    }
+}
+
+/// One line summarizing a successful health probe.
+fn describe_health(report: &HealthReport) -> String {
+    let mut line =
+        format!("  {}: {} ms", report.backend, report.latency.as_millis());
+    if let Some(version) = &report.version {
+        line.push_str(&format!(", version {}", version));
+    }
+    if let Some(size) = report.max_value_size {
+        line.push_str(&format!(", max {} bytes", size));
+    }
+    line
+}
+
+async fn put_mainline(
+    store: &MainlineDhtKv,
+    arid: &ARID,
+    envelope: &Envelope,
+    verbose: bool,
+) -> Result<String> {
+    store
+        .validate(envelope)
+        .map_err(|e| exit::invalid(e, true))?;
+    let receipt = store
+        .put(arid, envelope, None, verbose) // No TTL for mainline (not supported)
+        .await
+        .map_err(fail)?;
+    if verbose {
+        verbose_println("✓ Stored envelope at ARID");
+    }
+    Ok(receipt.to_string())
+}
+
+/// An IPFS store for the local daemon's RPC port, deriving keys in `domain`.
+///
+/// Stores the CLI opens accept updates, since only `put --update` makes
+/// one.
+fn ipfs_store(port: u16, domain: &str) -> IpfsKv {
+    IpfsKv::new(&format!("http://127.0.0.1:{}", port))
+        .with_domain(domain)
+        .with_mutability(Mutability::Updatable)
+}
+
+async fn put_ipfs(
+    store: IpfsKv,
+    arid: &ARID,
+    envelope: &Envelope,
+    ttl: Option<u64>,
+    pin: bool,
+    verbose: bool,
+) -> Result<String> {
+    let store = store.with_pin_content(pin);
+    let receipt = store
+        .put(arid, envelope, ttl, verbose) // IPNS lifetime, 24h if None
+        .await
+        .map_err(fail)?;
+
+    if verbose {
+        verbose_println("✓ Stored envelope at ARID");
+    }
+
+    // Print the CID if pinning was requested
+    if pin && let Some(cid) = &receipt.cid {
+        println!("CID: {}", cid);
+    }
+
+    Ok(receipt.to_string())
+}
+
+async fn get_mainline(
+    store: &MainlineDhtKv,
+    arid: &ARID,
+    timeout: u64,
+    verbose: bool,
+) -> Result<Option<Envelope>> {
+    store.get(arid, Some(timeout), verbose).await.map_err(fail)
+}
+
+async fn get_ipfs(
+    store: IpfsKv,
+    arid: &ARID,
+    timeout: u64,
+    pin: bool,
+    digest: Option<&Digest>,
+    verbose: bool,
+) -> Result<Option<Envelope>> {
+    if let Some(digest) = digest
+        && !pin
+    {
+        return store
+            .get_verified(arid, digest, Some(timeout), verbose)
+            .await
+            .map_err(fail);
+    }
+    if !pin {
+        return store.get(arid, Some(timeout), verbose).await.map_err(fail);
+    }
+
+    let found = store
+        .get_and_pin(arid, Some(timeout), verbose)
+        .await
+        .map_err(fail)?;
+    // Report on stderr so stdout stays the envelope alone
+    Ok(found.map(|(envelope, cid)| {
+        eprintln!("CID: {}", cid);
+        envelope
+    }))
+}
+
+async fn put_hybrid(
+    store: &HybridKv,
+    arid: &ARID,
+    envelope: &Envelope,
+    ttl: Option<u64>,
+    pin: bool,
+    verbose: bool,
+) -> Result<String> {
+    store
+        .validate(envelope)
+        .map_err(|e| exit::invalid(e, false))?;
+    let receipt = store
+        .put(arid, envelope, ttl, verbose)
+        .await
+        .map_err(fail)?;
+
+    if verbose {
+        verbose_println("✓ Stored envelope at ARID");
+    }
+
+    // Print the CID if pinning was requested and IPFS was used
+    if pin && let Some(cid) = &receipt.cid {
+        println!("CID: {}", cid);
+    }
+
+    Ok(receipt.to_string())
+}
+
+async fn get_hybrid(
+    store: &HybridKv,
+    arid: &ARID,
+    timeout: u64,
+    pin: bool,
+    verbose: bool,
+) -> Result<Option<Envelope>> {
+    if !pin {
+        return store.get(arid, Some(timeout), verbose).await.map_err(fail);
+    }
+
+    let found = store
+        .get_and_pin(arid, Some(timeout), verbose)
+        .await
+        .map_err(fail)?;
+    // Only content stored in IPFS has a CID to pin
+    Ok(found.map(|(envelope, cid)| {
+        if let Some(cid) = cid {
+            eprintln!("CID: {}", cid);
+        }
+        envelope
+    }))
+}
+
+fn server_client(url: &str, capability: Option<&Envelope>) -> ServerKvClient {
+    let store =
+        ServerKvClient::new(url).with_mutability(Mutability::Updatable);
+    match capability {
+        Some(token) => store.with_capability(token),
+        None => store,
+    }
+}
+
+async fn put_server(
+    host: &str,
+    port: u16,
+    arid: &ARID,
+    envelope: &Envelope,
+    ttl: Option<u64>,
+    capability: Option<&Envelope>,
+    verbose: bool,
+) -> Result<ReceiptStatus> {
+    let url = format!("http://{}:{}", host, port);
+    let store = server_client(&url, capability);
+    let receipt = store
+        .put_detailed(arid, envelope, ttl, verbose)
+        .await
+        .map_err(fail)?;
+    if verbose {
+        verbose_println("✓ Stored envelope at ARID");
+    }
+    Ok(receipt)
+}
+
+/// Report when a server put expires, and whether the server shortened the
+/// requested `ttl`, returning the text of the receipt for the ledger.
+fn report_server_receipt(
+    receipt: ReceiptStatus,
+    ttl: Option<u64>,
+    quiet: bool,
+) -> String {
+    // Servers that predate receipts in put responses report no expiry
+    let ReceiptStatus::Stored { stored_at, expires_at } = receipt else {
+        return "Stored successfully".to_string();
+    };
+    if !quiet {
+        let applied = expires_at.saturating_sub(stored_at);
+        let shortened = ttl
+            .filter(|&ttl| applied < ttl)
+            .map(|ttl| {
+                format!(" (TTL shortened from {}s to {}s)", ttl, applied)
+            })
+            .unwrap_or_default();
+        eprintln!(
+            "✓ Stored until {}{}",
+            ledger::format_time(expires_at),
+            shortened
+        );
+    }
+    format!("Stored until {} (UNIX time)", expires_at)
+}
+
+async fn get_server(
+    host: &str,
+    port: u16,
+    arid: &ARID,
+    timeout: u64,
+    capability: Option<&Envelope>,
+    verbose: bool,
+) -> Result<Option<Envelope>> {
+    let url = format!("http://{}:{}", host, port);
+    let store = server_client(&url, capability);
+    let outcome = store
+        .get_detailed(arid, Some(timeout), verbose)
+        .await
+        .map_err(fail)?;
+    match outcome {
+        GetOutcome::Found(envelope) => Ok(Some(envelope)),
+        GetOutcome::Expired { expired_at, digest } => {
+            let digest = digest
+                .map(|d| format!(" (envelope digest {})", d.ur_string()))
+                .unwrap_or_default();
+            bail_with!(
+                Failure::NotFound,
+                "Value expired at {} (UNIX time){}",
+                expired_at,
+                digest
+            )
+        }
+        GetOutcome::NeverExisted => {
+            bail_with!(
+                Failure::NotFound,
+                "Nothing was ever stored at this ARID on the server"
+            )
+        }
+        // Only conditional gets are answered with NotModified
+        GetOutcome::NotFound | GetOutcome::NotModified => Ok(None),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Register CBOR tags for URs
+    bc_components::register_tags();
+
+    let cli = Cli::parse();
+    let mut dht = MainlineDhtKv::builder();
+    if let Some(port) = cli.dht_port {
+        dht = dht.port(port);
+    }
+    if let Some(address) = cli.dht_bind {
+        dht = dht.bind_address(address);
+    }
+    if let Some(ms) = cli.dht_request_timeout {
+        dht = dht.request_timeout(std::time::Duration::from_millis(ms));
+    }
+    if let Ok(path) = hubert::paths::default_dht_routing_cache() {
+        dht = dht.routing_cache(path);
+    }
+    let mut backends = Backends {
+        dht,
+        record_puts: cli.ledger.is_some(),
+        ledger_path: cli.ledger.flatten(),
+        domain: cli
+            .domain
+            .or_else(|| std::env::var("HUBERT_DOMAIN").ok())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let result = match cli.command {
+        Commands::Run { script } => {
+            script::run(&script, cli.verbose, cli.quiet, &mut backends).await
+        }
+        command => execute(command, cli.verbose, cli.quiet, &mut backends)
+            .await
+            .map(|output| {
+                if let Some(output) = output {
+                    println!("{}", output);
+                }
+            }),
+    };
+
+    // Remember good DHT nodes so the next run bootstraps faster
+    if let Some(store) = &backends.mainline {
+        let _ = store.save_routing_cache().await;
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+            Failure::of_error(&error).exit_code()
+        }
+    }
+}
+
+/// Execute a single command, returning its primary output, if any.
+///
+/// Results go to stdout; status messages go to stderr unless `quiet`.
+async fn execute(
+    command: Commands,
+    verbose: bool,
+    quiet: bool,
+    backends: &mut Backends,
+) -> Result<Option<String>> {
+    let output = match command {
+        Commands::Generate { generate_type } => match generate_type {
+            GenerateType::Arid => Some(ARID::new().ur_string()),
+            GenerateType::Envelope { size } => {
+                Some(generate_random_envelope(size).ur_string())
+            }
+        },
+
+        Commands::Put {
+            storage,
+            explain,
+            host,
+            port,
+            arid,
+            envelope,
+            ttl,
+            pin,
+            attach,
+            capability,
+            arid_encrypt,
+            key_context,
+            update,
+        } => {
+            // Validate port/host usage based on storage backend
+            match storage {
+                Some(StorageBackend::Mainline) => {
+                    if port.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--port option is not supported for --storage mainline"
+                        );
+                    }
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage mainline"
+                        );
+                    }
+                }
+                Some(StorageBackend::Ipfs) => {
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage ipfs (always uses 127.0.0.1)"
+                        );
+                    }
+                }
+                Some(StorageBackend::Hybrid) => {
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage hybrid (always uses 127.0.0.1)"
+                        );
+                    }
+                }
+                Some(StorageBackend::Server) | None => {
+                    // host and port are allowed
+                }
+            }
+
+            let arid = parse_arid(&arid)?;
+            let mut envelope = parse_envelope(&envelope)?;
+            let (storage, host, port, capability) = match storage {
+                Some(storage) => (
+                    storage,
+                    host,
+                    port,
+                    parse_capability(storage, capability)?,
+                ),
+                None => {
+                    let capability = capability
+                        .as_deref()
+                        .map(parse_envelope)
+                        .transpose()?;
+                    let request =
+                        recommend::Request::new(&envelope, ttl.is_some(), pin);
+                    let chosen = recommend::recommend(
+                        backends,
+                        &request,
+                        host.as_deref(),
+                        port,
+                        capability.as_ref(),
+                    )
+                    .await?;
+                    if explain {
+                        for line in &chosen.considered {
+                            eprintln!("  {}", line);
+                        }
+                    }
+                    if explain || !quiet {
+                        eprintln!(
+                            "Using --storage {}: {}",
+                            chosen.backend.name(),
+                            chosen.reason
+                        );
+                    }
+                    // --host, --port, and --capability name a server, so
+                    // they do not apply to another backend chosen instead
+                    let server_given = host.is_some() || capability.is_some();
+                    match chosen.backend {
+                        StorageBackend::Server => {
+                            (chosen.backend, host, port, capability)
+                        }
+                        _ if server_given => (chosen.backend, None, None, None),
+                        _ => (chosen.backend, None, port, None),
+                    }
+                }
+            };
+            let payloads = attach
+                .iter()
+                .map(|s| parse_envelope(s))
+                .collect::<Result<Vec<_>>>()?;
+
+            let store = backends
+                .store(storage, host.as_deref(), port, pin, capability.as_ref())
+                .await?;
+
+            // Reject what the backend cannot honor before storing anything
+            let capabilities = store.capabilities();
+            if ttl.is_some() && !capabilities.ttl {
+                bail_with!(
+                    Failure::Usage,
+                    "--ttl option is not supported for --storage {}",
+                    storage.name()
+                );
+            }
+            if update && !capabilities.update {
+                bail_with!(
+                    Failure::Usage,
+                    "--update option is not supported for --storage {}",
+                    storage.name()
+                );
+            }
+
+            // Store attachments first so the envelope never refers to
+            // missing entries
+            for payload in &payloads {
+                let attachment = store_attachment(store, payload, ttl, verbose)
+                    .await
+                    .map_err(fail)?;
+                envelope = add_attachment(envelope, &attachment);
+            }
+            if arid_encrypt {
+                let context = key_context.unwrap_or_default();
+                envelope = encrypt_payload(&envelope, &arid, context);
+            }
+
+            let size = envelope.to_cbor_data().len();
+            if let Some(limit) = capabilities.max_value_size
+                && size > limit
+            {
+                bail_with!(
+                    Failure::Usage,
+                    "Envelope of {} bytes exceeds the {} byte limit for --storage {}",
+                    size,
+                    limit,
+                    storage.name()
+                );
+            }
+
+            let receipt = match storage {
+                _ if update => {
+                    let receipt = store
+                        .update(&arid, &envelope, ttl, verbose)
+                        .await
+                        .map_err(fail)?;
+                    if verbose {
+                        verbose_println("✓ Updated envelope at ARID");
+                    }
+                    if !quiet {
+                        match receipt.sequence {
+                            Some(sequence) => {
+                                eprintln!("✓ Updated to version {}", sequence)
+                            }
+                            None => eprintln!("✓ Updated"),
+                        }
+                    }
+                    receipt.to_string()
+                }
+                StorageBackend::Mainline => {
+                    if pin {
+                        bail_with!(
+                            Failure::Usage,
+                            "--pin option is only supported for --storage ipfs or --storage hybrid"
+                        );
+                    }
+                    let store = backends.mainline().await?;
+                    put_mainline(store, &arid, &envelope, verbose).await?
+                }
+                StorageBackend::Ipfs => {
+                    let store =
+                        ipfs_store(port.unwrap_or(5001), &backends.domain);
+                    put_ipfs(store, &arid, &envelope, ttl, pin, verbose)
+                        .await?
+                }
+                StorageBackend::Hybrid => {
+                    let port = port.unwrap_or(5001);
+                    let store = backends.hybrid(port, pin).await?;
+                    put_hybrid(store, &arid, &envelope, ttl, pin, verbose)
+                        .await?
+                }
+                StorageBackend::Server => {
+                    if pin {
+                        bail_with!(
+                            Failure::Usage,
+                            "--pin option is only supported for --storage ipfs or --storage hybrid"
+                        );
+                    }
+                    let receipt = put_server(
+                        host.as_deref().unwrap_or("127.0.0.1"),
+                        port.unwrap_or(45678),
+                        &arid,
+                        &envelope,
+                        ttl,
+                        capability.as_ref(),
+                        verbose,
+                    )
+                    .await?;
+                    report_server_receipt(receipt, ttl, quiet)
+                }
+            };
+
+            if backends.record_puts {
+                let location = ledger::Location {
+                    storage: storage.name().to_string(),
+                    host,
+                    port,
+                };
+                let entry = backends.ledger()?.record(
+                    &arid.ur_string(),
+                    &location,
+                    ttl,
+                    &receipt,
+                    &envelope.ur_string(),
+                )?;
+                if verbose {
+                    verbose_println(&format!(
+                        "Recorded as ledger entry {}",
+                        entry.id
+                    ));
+                }
+            }
+            None
+        }
+
+        Commands::PutBatch {
+            manifest,
+            storage,
+            host,
+            port,
+            ttl,
+            concurrency,
+            capability,
+        } => {
+            let defaults = batch::Defaults { storage, host, port, ttl };
+            let entries = batch::read_manifest(&manifest, &defaults)?;
+            let capability =
+                capability.as_deref().map(parse_envelope).transpose()?;
+
+            // Open every backend first, so the puts can share them
+            for entry in &entries {
+                let capability = match entry.storage {
+                    StorageBackend::Server => capability.as_ref(),
+                    _ => None,
+                };
+                backends
+                    .store(
+                        entry.storage,
+                        entry.host.as_deref(),
+                        entry.port,
+                        false,
+                        capability,
+                    )
+                    .await?;
+            }
+            let stores: Vec<&dyn KvStore> = entries
+                .iter()
+                .map(|entry| {
+                    let capability = match entry.storage {
+                        StorageBackend::Server => capability.as_ref(),
+                        _ => None,
+                    };
+                    backends
+                        .opened(
+                            entry.storage,
+                            entry.host.as_deref(),
+                            entry.port,
+                            false,
+                            capability,
+                        )
+                        .expect("opened above")
+                })
+                .collect();
+
+            if !quiet {
+                eprintln!(
+                    "Storing {} entries from {}...",
+                    entries.len(),
+                    manifest.display()
+                );
+            }
+            let start = std::time::Instant::now();
+            let outcomes =
+                batch::run(&entries, &stores, concurrency, verbose).await;
+            let report = batch::report(&entries, &outcomes, start.elapsed());
+
+            if backends.record_puts {
+                let ledger = backends.ledger()?;
+                for (entry, outcome) in entries.iter().zip(&outcomes) {
+                    let Ok(stored) = outcome else { continue };
+                    let location = ledger::Location {
+                        storage: entry.storage.name().to_string(),
+                        host: entry.host.clone(),
+                        port: entry.port,
+                    };
+                    ledger.record(
+                        &entry.arid.ur_string(),
+                        &location,
+                        entry.ttl,
+                        &stored.receipt,
+                        &entry.envelope.ur_string(),
+                    )?;
+                }
+            }
+
+            let failed = outcomes.iter().filter(|o| o.is_err()).count();
+            let first = entries.iter().zip(&outcomes).find_map(
+                |(entry, outcome)| Some((entry, outcome.as_ref().err()?)),
+            );
+            if let Some((entry, error)) = first {
+                println!("{}", report);
+                bail_with!(
+                    Failure::of_error(error),
+                    "{} of {} entries failed, first at {}:{}: {}",
+                    failed,
+                    entries.len(),
+                    manifest.display(),
+                    entry.line,
+                    error
+                );
+            }
+            Some(report)
+        }
+
+        Commands::Get {
+            storage,
+            host,
+            port,
+            arid,
+            resolver,
+            timeout,
+            with_attachments,
+            expect_type,
+            pin,
+            digest,
+            capability,
+            arid_encrypt,
+            key_context,
+        } => {
+            // Validate port/host usage based on storage backend
+            match storage {
+                StorageBackend::Mainline => {
+                    if port.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--port option is not supported for --storage mainline"
+                        );
+                    }
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage mainline"
+                        );
+                    }
+                }
+                StorageBackend::Ipfs => {
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage ipfs (always uses 127.0.0.1)"
+                        );
+                    }
+                }
+                StorageBackend::Hybrid => {
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage hybrid (always uses 127.0.0.1)"
+                        );
+                    }
+                }
+                StorageBackend::Server => {
+                    // host and port are allowed
+                }
+            }
+
+            let arid = resolve_arid(&arid, resolver, verbose).await?;
+            let capability = parse_capability(storage, capability)?;
+            let expected_types = expect_type
+                .iter()
+                .map(|s| parse_known_value(s))
+                .collect::<hubert::Result<Vec<_>>>()
+                .map_err(fail)?;
+            let digest = digest
+                .map(|d| Digest::from_ur_string(d.trim()))
+                .transpose()
+                .map_err(|e| {
+                    Failure::Usage.error(format!("Invalid digest: {}", e))
+                })?;
+
+            if pin
+                && matches!(
+                    storage,
+                    StorageBackend::Mainline | StorageBackend::Server
+                )
+            {
+                bail_with!(
+                    Failure::Usage,
+                    "--pin option is only supported for --storage ipfs or --storage hybrid"
+                );
+            }
+
+            let envelope = match storage {
+                StorageBackend::Mainline => {
+                    let store = backends.mainline().await?;
+                    get_mainline(store, &arid, timeout, verbose).await?
+                }
+                StorageBackend::Ipfs => {
+                    let store =
+                        ipfs_store(port.unwrap_or(5001), &backends.domain);
+                    // The digest is of the decrypted envelope, checked below
+                    let stored_digest =
+                        digest.as_ref().filter(|_| !arid_encrypt);
+                    get_ipfs(
+                        store,
+                        &arid,
+                        timeout,
+                        pin,
+                        stored_digest,
+                        verbose,
+                    )
+                    .await?
+                }
+                StorageBackend::Hybrid => {
+                    let port = port.unwrap_or(5001);
+                    let store = backends.hybrid(port, false).await?;
+                    get_hybrid(store, &arid, timeout, pin, verbose).await?
+                }
+                StorageBackend::Server => {
+                    let host = host.as_deref().unwrap_or("127.0.0.1");
+                    let port = port.unwrap_or(45678);
+                    get_server(
+                        host,
+                        port,
+                        &arid,
+                        timeout,
+                        capability.as_ref(),
+                        verbose,
+                    )
+                    .await?
+                }
+            };
+
+            let Some(envelope) = envelope else {
+                bail_with!(
+                    Failure::NotFound,
+                    "Value not found within {} seconds",
+                    timeout
+                );
+            };
+            let envelope = if arid_encrypt {
+                let context = key_context.unwrap_or_default();
+                decrypt_payload(&envelope, &arid, context).map_err(|e| {
+                    Failure::General
+                        .error(format!("Could not decrypt envelope: {}", e))
+                })?
+            } else {
+                envelope
+            };
+            if let Some(digest) = &digest
+                && envelope.digest() != *digest
+            {
+                bail_with!(
+                    Failure::General,
+                    "Envelope digest {} does not match expected {}",
+                    envelope.digest().ur_string(),
+                    digest.ur_string()
+                );
+            }
+            check_types(&envelope, &expected_types).map_err(fail)?;
+            // The DHT and IPFS keep entries past the expiry they declare
+            if !quiet
+                && let Some(expires_at) = expiry::expiry(&envelope)
+                && expiry::is_expired(&envelope)
+            {
+                eprintln!(
+                    "Warning: envelope expired at {} ({})",
+                    ledger::format_time(expires_at),
+                    expires_at
+                );
+            }
+
+            let mut lines = vec![envelope.ur_string()];
+            if with_attachments {
+                let store = backends
+                    .store(
+                        storage,
+                        host.as_deref(),
+                        port,
+                        false,
+                        capability.as_ref(),
+                    )
+                    .await?;
+                let payloads =
+                    fetch_attachments(store, &envelope, Some(timeout), verbose)
+                        .await
+                        .map_err(fail)?;
+                lines.extend(payloads.iter().map(|p| p.ur_string()));
+            }
+            Some(lines.join("\n"))
+        }
+
+        Commands::Check { storage, host, port } => {
+            // Validate port/host usage based on storage backend
+            match storage {
+                StorageBackend::Mainline => {
+                    if port.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--port option is not supported for --storage mainline"
+                        );
+                    }
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage mainline"
+                        );
+                    }
+                }
+                StorageBackend::Ipfs => {
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage ipfs (always uses 127.0.0.1)"
+                        );
+                    }
+                }
+                StorageBackend::Hybrid => {
+                    if host.is_some() {
+                        bail_with!(
+                            Failure::Usage,
+                            "--host option is not supported for --storage hybrid (always uses 127.0.0.1)"
+                        );
+                    }
+                }
+                StorageBackend::Server => {
+                    // host and port are allowed
+                }
+            }
+
+            let (name, location) =
+                describe_backend(storage, host.as_deref(), port);
+            let report = backends
+                .store(storage, host.as_deref(), port, false, None)
+                .await?
+                .health()
+                .await;
+            if let Some(error) = &report.error {
+                bail_with!(
+                    Failure::Unavailable,
+                    "✗ {} is not available{}: {}",
+                    name,
+                    location,
+                    error
+                );
+            }
+            let mut lines = vec![
+                format!("✓ {} is available{}", name, location),
+                describe_health(&report),
+            ];
+            lines.extend(report.components.iter().map(describe_health));
+            Some(lines.join("\n"))
+        }
+
+        Commands::Verify { storage, host, port, timeout, capability } => {
+            if host.is_some() && !matches!(storage, StorageBackend::Server) {
+                bail_with!(
+                    Failure::Usage,
+                    "--host option is only supported for --storage server"
+                );
+            }
+            if port.is_some() && matches!(storage, StorageBackend::Mainline) {
+                bail_with!(
+                    Failure::Usage,
+                    "--port option is not supported for --storage mainline"
+                );
+            }
+
+            let capability = parse_capability(storage, capability)?;
+            let (name, location) =
+                describe_backend(storage, host.as_deref(), port);
+            if !quiet {
+                eprintln!("Verifying {}{}...", name, location);
+            }
+            let store = backends
+                .store(
+                    storage,
+                    host.as_deref(),
+                    port,
+                    false,
+                    capability.as_ref(),
+                )
+                .await?;
+            Some(verify::run(store, timeout, verbose).await?)
+        }
+
+        Commands::Server {
+            config,
+            bind,
+            port,
+            sqlite,
+            persist,
+            read_only,
+            default_ttl,
+            max_ttl,
+        } => {
+            use hubert::server::{Server, ServerConfig};
+
+            // Fall back to the default config file if one exists
+            let config = config.or_else(|| {
+                hubert::paths::default_config_file()
+                    .ok()
+                    .filter(|path| path.is_file())
+            });
+
+            // Precedence: defaults < config file < environment < flags
+            let mut config = match config {
+                Some(path) => ServerConfig::from_file(&path).map_err(fail)?,
+                None => ServerConfig::default(),
+            };
+            config.apply_env().map_err(fail)?;
+            if let Some(bind) = bind {
+                config.bind = bind;
+            }
+            if let Some(port) = port {
+                config.port = port;
+            }
+            match sqlite {
+                Some(Some(path)) => config.sqlite = Some(path),
+                Some(None) => {
+                    config.sqlite = Some(
+                        hubert::paths::default_sqlite_path().map_err(fail)?,
+                    )
+                }
+                None if persist => {
+                    config.sqlite = Some(
+                        hubert::paths::default_sqlite_path().map_err(fail)?,
+                    )
+                }
+                None => {}
+            }
+            config.verbose |= verbose;
+            config.quiet |= quiet;
+            config.read_only |= read_only;
+            if let Some(default_ttl) = default_ttl {
+                config.default_ttl = Some(default_ttl);
+            }
+            if let Some(max_ttl) = max_ttl {
+                config.max_ttl = max_ttl;
+            }
+
+            // Resolve directories and create any that are missing
+            if let Some(path) = &config.sqlite {
+                config.sqlite = Some(
+                    hubert::paths::resolve_sqlite_path(path).map_err(fail)?,
+                );
+            }
+
+            match &config.sqlite {
+                _ if config.quiet => {}
+                Some(path) => eprintln!(
+                    "Starting Hubert server on port {} with SQLite storage: {}",
+                    config.port,
+                    path.display()
+                ),
+                None => eprintln!(
+                    "Starting Hubert server on port {} with in-memory storage",
+                    config.port
+                ),
+            }
+
+            let server = Server::from_config(config).map_err(fail)?;
+            server.run().await.map_err(fail)?;
+            None
+        }
+
+        Commands::Resolve { storage, port, arid } => {
+            if !matches!(storage, StorageBackend::Ipfs) {
+                bail_with!(
+                    Failure::Usage,
+                    "resolve is only supported for --storage ipfs"
+                );
+            }
+            let arid = parse_arid(&arid)?;
+            let info = ipfs_store(port.unwrap_or(5001), &backends.domain)
+                .resolve_info(&arid)
+                .await
+                .map_err(fail)?;
+
+            let missing = || "-".to_string();
+            let lines = [
+                format!("Key name:    {}", info.key_name),
+                format!(
+                    "IPNS name:   {}",
+                    info.peer_id.unwrap_or_else(|| "- (no local key)".into())
+                ),
+                format!("CID:         {}", info.cid.unwrap_or_else(missing)),
+                format!(
+                    "Valid until: {}",
+                    info.validity.unwrap_or_else(missing)
+                ),
+                format!(
+                    "Sequence:    {}",
+                    info.sequence.map_or_else(missing, |s| s.to_string())
+                ),
+                format!(
+                    "Cache TTL:   {}",
+                    info.ttl
+                        .map_or_else(missing, |t| format!("{}s", t.as_secs()))
+                ),
+                format!(
+                    "DAG size:    {}",
+                    info.dag_size
+                        .map_or_else(missing, |s| format!("{} bytes", s))
+                ),
+            ];
+            Some(lines.join("\n"))
+        }
+
+        Commands::Unpin { storage, port, arid, timeout } => {
+            let arid = parse_arid(&arid)?;
+            let port = port.unwrap_or(5001);
+            let cid = match storage {
+                StorageBackend::Ipfs => {
+                    ipfs_store(port, &backends.domain)
+                        .unpin(&arid, Some(timeout), verbose)
+                        .await
+                        .map_err(fail)?
+                }
+                StorageBackend::Hybrid => backends
+                    .hybrid(port, false)
+                    .await?
+                    .unpin(&arid, Some(timeout), verbose)
+                    .await
+                    .map_err(fail)?,
+                StorageBackend::Mainline | StorageBackend::Server => {
+                    bail_with!(
+                        Failure::Usage,
+                        "unpin is only supported for --storage ipfs or --storage hybrid"
+                    )
+                }
+            };
+            let Some(cid) = cid else {
+                bail_with!(
+                    Failure::NotFound,
+                    "No IPFS content found within {} seconds",
+                    timeout
+                );
+            };
+            Some(format!("CID: {}", cid))
+        }
+
+        Commands::Delete { storage, host, port, arid, capability } => {
+            if host.is_some() && !matches!(storage, StorageBackend::Server) {
+                bail_with!(
+                    Failure::Usage,
+                    "--host option is only supported for --storage server"
+                );
+            }
+            let arid = parse_arid(&arid)?;
+            let capability = parse_capability(storage, capability)?;
+            let store = backends
+                .store(
+                    storage,
+                    host.as_deref(),
+                    port,
+                    false,
+                    capability.as_ref(),
+                )
+                .await?;
+            if !store.delete(&arid, verbose).await.map_err(fail)? {
+                bail_with!(Failure::NotFound, "Nothing stored at the ARID");
+            }
+            Some("✓ Deleted".to_string())
+        }
+
+        Commands::Stat { storage, host, port, arid, capability } => {
+            if host.is_some() && !matches!(storage, StorageBackend::Server) {
+                bail_with!(
+                    Failure::Usage,
+                    "--host option is only supported for --storage server"
+                );
+            }
+            let arid = parse_arid(&arid)?;
+            let capability = parse_capability(storage, capability)?;
+            let store = backends
+                .store(
+                    storage,
+                    host.as_deref(),
+                    port,
+                    false,
+                    capability.as_ref(),
+                )
+                .await?;
+            let Some(meta) = store.stat(&arid, verbose).await.map_err(fail)?
+            else {
+                bail_with!(Failure::NotFound, "Nothing stored at the ARID");
+            };
+
+            let never = || "never".to_string();
+            let lines = [
+                format!("Location:  {}", meta.location),
+                format!("Size:      {} bytes", meta.size),
+                format!(
+                    "Stored:    {}",
+                    meta.created_at
+                        .map_or_else(|| "-".to_string(), ledger::format_time)
+                ),
+                format!(
+                    "Expires:   {}",
+                    meta.expires_at.map_or_else(never, ledger::format_time)
+                ),
+                format!(
+                    "Remaining: {}",
+                    meta.ttl_remaining
+                        .map_or_else(never, |t| format!("{}s", t))
+                ),
+            ];
+            Some(lines.join("\n"))
+        }
+
+        Commands::Keepalive { command, registry } => {
+            let registry = keepalive::resolve(registry)?;
+            let parse_arids = |arids: &[String]| {
+                arids
+                    .iter()
+                    .map(|arid| parse_arid(arid))
+                    .collect::<Result<Vec<_>>>()
+            };
+            match command {
+                KeepaliveCommand::Start { arids } => {
+                    let arids = parse_arids(&arids)?;
+                    let added = keepalive::start(&registry, &arids)?;
+                    if !quiet {
+                        eprintln!(
+                            "✓ Added {} entries to {}",
+                            added,
+                            registry.display()
+                        );
+                    }
+                    None
+                }
+                KeepaliveCommand::Stop { arids } => {
+                    keepalive::stop(&registry, &parse_arids(&arids)?)?;
+                    if !quiet {
+                        eprintln!(
+                            "✓ Removed {} entries from {}",
+                            arids.len(),
+                            registry.display()
+                        );
+                    }
+                    None
+                }
+                KeepaliveCommand::List => {
+                    let arids = keepalive::read(&registry)?;
+                    if arids.is_empty() {
+                        None
+                    } else {
+                        let lines: Vec<String> =
+                            arids.iter().map(|arid| arid.ur_string()).collect();
+                        Some(lines.join("\n"))
+                    }
+                }
+                KeepaliveCommand::Run { interval } => {
+                    let store = backends
+                        .dht
+                        .clone()
+                        .build()
+                        .await
+                        .map_err(fail)?
+                        .with_keepalive_interval(
+                            std::time::Duration::from_secs(interval),
+                        );
+                    if !quiet {
+                        eprintln!(
+                            "✓ Keeping the entries in {} alive every {}s; \
+                             press Ctrl-C to stop",
+                            registry.display(),
+                            interval
+                        );
+                    }
+                    keepalive::run(&store, &registry, verbose).await;
+                    let _ = store.save_routing_cache().await;
+                    None
+                }
+            }
+        }
+
+        Commands::Bench {
+            storage,
+            host,
+            port,
+            ops,
+            concurrency,
+            size,
+            ttl,
+            timeout,
+        } => {
+            if host.is_some() && !matches!(storage, StorageBackend::Server) {
+                bail_with!(
+                    Failure::Usage,
+                    "--host option is only supported for --storage server"
+                );
+            }
+            if port.is_some() && matches!(storage, StorageBackend::Mainline) {
+                bail_with!(
+                    Failure::Usage,
+                    "--port option is not supported for --storage mainline"
+                );
+            }
+
+            let store = backends
+                .store(storage, host.as_deref(), port, false, None)
+                .await?;
+            let capabilities = store.capabilities();
+            if ttl.is_some() && !capabilities.ttl {
+                bail_with!(
+                    Failure::Usage,
+                    "--ttl option is not supported for --storage {}",
+                    storage.name()
+                );
+            }
+            let sample = generate_random_envelope(size).to_cbor_data().len();
+            if !capabilities.fits(sample) {
+                bail_with!(
+                    Failure::Usage,
+                    "Envelopes of {} bytes exceed the limit for --storage {}",
+                    sample,
+                    storage.name()
+                );
+            }
+
+            if !quiet {
+                eprintln!(
+                    "Running {} put/get pairs against --storage {}...",
+                    ops,
+                    storage.name()
+                );
+            }
+            let workload =
+                bench::Workload { ops, concurrency, size, ttl, timeout };
+            Some(bench::run(store, &workload, verbose).await)
+        }
+
+        Commands::Monitor {
+            arids,
+            storage,
+            host,
+            port,
+            interval,
+            once,
+            capability,
+        } => {
+            let targets = monitor::read_targets(&arids, storage)?;
+            let capability =
+                capability.as_deref().map(parse_envelope).transpose()?;
+            // --host and --port apply to the backends that take them
+            let location = |storage: StorageBackend| match storage {
+                StorageBackend::Server => {
+                    (host.as_deref(), port, capability.as_ref())
+                }
+                StorageBackend::Mainline => (None, None, None),
+                _ => (None, port, None),
+            };
+
+            for target in &targets {
+                let (host, port, capability) = location(target.storage);
+                backends
+                    .store(target.storage, host, port, false, capability)
+                    .await?;
+            }
+            let probes: Vec<monitor::Probe> = targets
+                .iter()
+                .map(|target| {
+                    let (host, port, capability) = location(target.storage);
+                    if target.storage == StorageBackend::Server {
+                        let token = capability.map(|c| c.ur_string());
+                        let url = server_url(host, port);
+                        monitor::Probe::Server(&backends.servers[&(url, token)])
+                    } else {
+                        monitor::Probe::Store(
+                            backends
+                                .opened(
+                                    target.storage,
+                                    host,
+                                    port,
+                                    false,
+                                    capability,
+                                )
+                                .expect("opened above"),
+                        )
+                    }
+                })
+                .collect();
+
+            if !once && !quiet {
+                eprintln!(
+                    "Watching {} ARIDs every {}s; press Ctrl-C to stop",
+                    targets.len(),
+                    interval
+                );
+            }
+            monitor::run(&targets, &probes, interval, once, verbose).await
+        }
+
+        Commands::History { command } => {
+            let ledger = backends.ledger()?;
+            match command {
+                HistoryCommand::List { limit } => {
+                    let entries = ledger.recent(limit)?;
+                    if entries.is_empty() {
+                        if !quiet {
+                            eprintln!(
+                                "No puts recorded; use --ledger to record them"
+                            );
+                        }
+                        None
+                    } else {
+                        Some(
+                            entries
+                                .iter()
+                                .map(|entry| entry.summary())
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        )
+                    }
+                }
+                HistoryCommand::Show { entry } => {
+                    Some(ledger.find(&entry)?.details())
+                }
+                HistoryCommand::Repost { entry, capability } => {
+                    let entry = ledger.find(&entry)?;
+                    let storage = StorageBackend::from_str(
+                        &entry.location.storage,
+                        false,
+                    )
+                    .map_err(|e| Failure::General.error(e))?;
+                    let put = Commands::Put {
+                        storage: Some(storage),
+                        explain: false,
+                        host: entry.location.host,
+                        port: entry.location.port,
+                        arid: entry.arid,
+                        envelope: entry.envelope,
+                        ttl: entry.ttl,
+                        pin: false,
+                        attach: Vec::new(),
+                        capability,
+                        // The ledger holds the envelope as stored
+                        arid_encrypt: false,
+                        key_context: None,
+                        update: false,
+                    };
+                    Box::pin(execute(put, verbose, quiet, backends)).await?;
+                    if !quiet {
+                        eprintln!("✓ Reposted ledger entry {}", entry.id);
+                    }
+                    None
+                }
+                HistoryCommand::Expiring {
+                    within,
+                    desktop,
+                    webhook,
+                    watch,
+                    interval,
+                } => {
+                    let notifications =
+                        expiring::Notifications { desktop, webhook };
+                    if watch {
+                        expiring::watch(
+                            &ledger,
+                            within,
+                            interval,
+                            &notifications,
+                            verbose,
+                        )
+                        .await?;
+                        None
+                    } else {
+                        let lines = expiring::check(
+                            &ledger,
+                            within,
+                            &notifications,
+                            verbose,
+                        )
+                        .await?;
+                        (!lines.is_empty()).then(|| lines.join("\n"))
+                    }
+                }
+            }
+        }
+
+        Commands::Paths => {
+            use hubert::paths;
+
+            let data = paths::data_dir().map_err(fail)?;
+            let config = paths::default_config_file().map_err(fail)?;
+            let cache = paths::cache_dir().map_err(fail)?;
+            let sqlite = paths::default_sqlite_path().map_err(fail)?;
+
+            Some(format!(
+                "data:   {}\nconfig: {}\ncache:  {}\nsqlite: {}",
+                data.display(),
+                config.display(),
+                cache.display(),
+                sqlite.display()
+            ))
+        }
+
+        Commands::Vectors {
+            command: VectorsCommand::Generate { format, output },
+        } => {
+            let vectors = hubert::vectors::generate();
+            let bytes = match format {
+                VectorFormat::Json => {
+                    serde_json::to_string_pretty(&vectors)?.into_bytes()
+                }
+                VectorFormat::Cbor => vectors.to_cbor_data(),
+            };
+            match (output, format) {
+                (Some(path), _) => {
+                    std::fs::write(&path, bytes)?;
+                    None
+                }
+                (None, VectorFormat::Json) => {
+                    Some(String::from_utf8(bytes).unwrap())
+                }
+                (None, VectorFormat::Cbor) => Some(hex::encode(bytes)),
+            }
+        }
+
+        Commands::Run { .. } => {
+            bail_with!(Failure::Usage, "run cannot be used inside a script")
+        }
+    };
+
+    Ok(output)
+}
@@ -0,0 +1,317 @@
+//! Live status of a set of ARIDs for `hubert monitor`, for supervising a
+//! multiparty ceremony while its participants publish.
+//!
+//! The ARIDs file lists one ARID per line, optionally followed by the
+//! backend to look in:
+//!
+//! ```text
+//! # Round 1 commitments
+//! ur:arid/...
+//! ur:arid/... server
+//! ```
+
+use std::{
+    io::IsTerminal,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bc_components::ARID;
+use bc_ur::prelude::*;
+use clap::ValueEnum;
+use futures_util::future::join_all;
+use hubert::{
+    KvStore,
+    server::{GetOutcome, ServerKvClient},
+};
+
+use super::{
+    StorageBackend,
+    exit::{Failure, bail_with},
+    ledger, parse_arid,
+};
+
+/// An ARID to watch.
+pub struct Target {
+    /// Line number in the ARIDs file, from 1.
+    pub line: usize,
+    pub arid: ARID,
+    pub storage: StorageBackend,
+}
+
+/// Where a target is looked up.
+pub enum Probe<'a> {
+    /// A server, which can tell an expired entry from one never stored.
+    Server(&'a ServerKvClient),
+    Store(&'a dyn KvStore),
+}
+
+/// Read the ARIDs file at `path`. Lines that do not name a backend use
+/// `storage`.
+pub fn read_targets(
+    path: &Path,
+    storage: StorageBackend,
+) -> Result<Vec<Target>> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        Failure::Usage.error(format!("Cannot read {}: {}", path.display(), e))
+    })?;
+    parse_targets(path, &text, storage)
+}
+
+/// Parse the ARIDs file `text` read from `path`. Blank lines and lines
+/// starting with `#` are ignored.
+pub fn parse_targets(
+    path: &Path,
+    text: &str,
+    storage: StorageBackend,
+) -> Result<Vec<Target>> {
+    let mut targets = Vec::new();
+    for (index, text) in text.lines().enumerate() {
+        let line = index + 1;
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let at = |message: String| {
+            Failure::Usage.error(format!(
+                "{}:{}: {}",
+                path.display(),
+                line,
+                message
+            ))
+        };
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let (arid, backend) = match words.as_slice() {
+            [arid] => (arid, None),
+            [arid, backend] => (arid, Some(backend)),
+            _ => return Err(at("Expected an ARID and a backend".to_string())),
+        };
+        let arid = parse_arid(arid).map_err(|e| at(e.to_string()))?;
+        let storage = match backend {
+            Some(name) => StorageBackend::from_str(name, false).map_err(at)?,
+            None => storage,
+        };
+        targets.push(Target { line, arid, storage });
+    }
+    if targets.is_empty() {
+        bail_with!(Failure::Usage, "{} lists no ARIDs", path.display());
+    }
+    Ok(targets)
+}
+
+/// What the last look at a target found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Status {
+    /// Not stored yet.
+    Pending,
+    /// Stored, with its envelope taking `size` bytes.
+    Found { size: usize },
+    /// Stored once and gone now.
+    Expired,
+    /// The backend could not be asked.
+    Failed(String),
+}
+
+/// A target's row in the table.
+struct Row {
+    status: Status,
+    latency: Option<Duration>,
+    /// Whether the envelope has been found, so that a backend that cannot
+    /// tell why an entry is missing shows it as expired rather than
+    /// pending.
+    seen: bool,
+}
+
+impl Row {
+    fn new() -> Self {
+        Self { status: Status::Pending, latency: None, seen: false }
+    }
+
+    fn update(&mut self, outcome: Result<Outcome, String>, latency: Duration) {
+        self.latency = Some(latency);
+        self.status = match outcome {
+            Ok(Outcome::Found { size }) => {
+                self.seen = true;
+                Status::Found { size }
+            }
+            Ok(Outcome::Missing) if self.seen => Status::Expired,
+            Ok(Outcome::Missing) => Status::Pending,
+            Ok(Outcome::Expired) => Status::Expired,
+            Err(error) => Status::Failed(error),
+        };
+    }
+}
+
+/// What one look at a target's backend answered.
+enum Outcome {
+    Found { size: usize },
+    Missing,
+    Expired,
+}
+
+/// Look up `arid` once, without waiting for it to appear.
+async fn probe(
+    probe: &Probe<'_>,
+    arid: &ARID,
+    verbose: bool,
+) -> (Result<Outcome, String>, Duration) {
+    let start = Instant::now();
+    let outcome = match probe {
+        Probe::Server(client) => client
+            .get_detailed(arid, Some(0), verbose)
+            .await
+            .map(|outcome| match outcome {
+                GetOutcome::Found(envelope) => {
+                    Outcome::Found { size: envelope.to_cbor_data().len() }
+                }
+                GetOutcome::Expired { .. } => Outcome::Expired,
+                GetOutcome::NeverExisted
+                | GetOutcome::NotFound
+                | GetOutcome::NotModified => Outcome::Missing,
+            }),
+        Probe::Store(store) => {
+            store.get(arid, Some(0), verbose).await.map(|found| match found {
+                Some(envelope) => {
+                    Outcome::Found { size: envelope.to_cbor_data().len() }
+                }
+                None => Outcome::Missing,
+            })
+        }
+    };
+    (outcome.map_err(|e| e.to_string()), start.elapsed())
+}
+
+/// Look up every target every `interval` seconds and redraw the table,
+/// until Ctrl-C, or after one round if `once`.
+///
+/// On a terminal each table replaces the last; otherwise the tables are
+/// printed one after another. Returns the table of a single round.
+pub async fn run(
+    targets: &[Target],
+    probes: &[Probe<'_>],
+    interval: u64,
+    once: bool,
+    verbose: bool,
+) -> Option<String> {
+    let mut rows: Vec<Row> = targets.iter().map(|_| Row::new()).collect();
+    let redraw = std::io::stdout().is_terminal();
+    loop {
+        let round = join_all(
+            targets
+                .iter()
+                .zip(probes)
+                .map(|(target, p)| probe(p, &target.arid, verbose)),
+        );
+        let outcomes = tokio::select! {
+            outcomes = round => outcomes,
+            _ = tokio::signal::ctrl_c() => return None,
+        };
+        for (row, (outcome, latency)) in rows.iter_mut().zip(outcomes) {
+            row.update(outcome, latency);
+        }
+
+        let table = render(targets, &rows, ledger::now());
+        if once {
+            return Some(table);
+        }
+        if redraw {
+            // Move to the top left and clear the screen
+            print!("\x1b[H\x1b[2J");
+        }
+        println!("{}\n", table);
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(interval.max(1))) => {}
+            _ = tokio::signal::ctrl_c() => return None,
+        }
+    }
+}
+
+/// A summary line, then one line per target.
+fn render(targets: &[Target], rows: &[Row], now: u64) -> String {
+    let count = |matches: fn(&Status) -> bool| {
+        rows.iter().filter(|row| matches(&row.status)).count()
+    };
+    let mut lines = vec![format!(
+        "{} ARIDs at {}: {} found, {} pending, {} expired, {} failed",
+        targets.len(),
+        ledger::format_time(now),
+        count(|s| matches!(s, Status::Found { .. })),
+        count(|s| matches!(s, Status::Pending)),
+        count(|s| matches!(s, Status::Expired)),
+        count(|s| matches!(s, Status::Failed(_))),
+    )];
+    for (target, row) in targets.iter().zip(rows) {
+        let (status, size) = match &row.status {
+            Status::Found { size } => ("found", format!("{} B", size)),
+            Status::Pending => ("pending", "-".to_string()),
+            Status::Expired => ("expired", "-".to_string()),
+            Status::Failed(_) => ("failed", "-".to_string()),
+        };
+        let latency = row
+            .latency
+            .map(|d| format!("{:.0} ms", d.as_secs_f64() * 1e3))
+            .unwrap_or_else(|| "-".to_string());
+        let mut line = format!(
+            "{:<4} {:<8} {:>8} {:>9}  {:<8} {}",
+            target.line,
+            status,
+            latency,
+            size,
+            target.storage.name(),
+            target.arid.ur_string()
+        );
+        if let Status::Failed(error) = &row.status {
+            line.push_str(&format!("  ({})", error));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_targets() {
+        bc_components::register_tags();
+        let (first, second) = (ARID::new(), ARID::new());
+        let text = format!(
+            "# Commitments\n\n{}\n  {} server\n",
+            first.ur_string(),
+            second.ur_string()
+        );
+        let path = Path::new("arids.txt");
+        let targets =
+            parse_targets(path, &text, StorageBackend::Mainline).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].line, 3);
+        assert_eq!(targets[0].arid, first);
+        assert_eq!(targets[0].storage, StorageBackend::Mainline);
+        assert_eq!(targets[1].line, 4);
+        assert_eq!(targets[1].arid, second);
+        assert_eq!(targets[1].storage, StorageBackend::Server);
+
+        let bad = format!("{} dropbox", first.ur_string());
+        let error = parse_targets(path, &bad, StorageBackend::Mainline)
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("arids.txt:1: "), "{}", error);
+    }
+
+    #[test]
+    fn test_missing_after_found_is_expired() {
+        let mut row = Row::new();
+        let ms = Duration::from_millis(5);
+        row.update(Ok(Outcome::Missing), ms);
+        assert_eq!(row.status, Status::Pending);
+        row.update(Ok(Outcome::Found { size: 10 }), ms);
+        assert_eq!(row.status, Status::Found { size: 10 });
+        row.update(Err("Network error".to_string()), ms);
+        assert_eq!(row.status, Status::Failed("Network error".to_string()));
+        row.update(Ok(Outcome::Missing), ms);
+        assert_eq!(row.status, Status::Expired);
+    }
+}
@@ -0,0 +1,302 @@
+//! The backend a `hubert put` without `--storage` uses, chosen from the
+//! envelope's size, what the put asks for, and which backends answer.
+
+use anyhow::Result;
+use bc_envelope::Envelope;
+use hubert::{
+    HealthReport, KvStore,
+    hubert_core::reference::{DEFAULT_DHT_SIZE_LIMIT, needs_reference},
+    ipfs::DEFAULT_MAX_ENVELOPE_SIZE,
+    wire::{Codec, stored_size},
+};
+
+use super::{Backends, StorageBackend, describe_backend, exit::Failure};
+
+/// What a put needs from the backend it goes to.
+#[derive(Debug, Clone, Copy)]
+pub struct Request {
+    /// The envelope's size as the DHT counts it, bencoded.
+    pub dht_size: usize,
+    /// The envelope's size as dCBOR, as IPFS and servers count it.
+    pub size: usize,
+    /// Whether the put asks for a TTL, which the DHT ignores.
+    pub ttl: bool,
+    /// Whether the put asks to pin its content in IPFS.
+    pub pin: bool,
+}
+
+impl Request {
+    pub fn new(envelope: &Envelope, ttl: bool, pin: bool) -> Self {
+        Self {
+            dht_size: stored_size(envelope, Codec::Bep44),
+            size: stored_size(envelope, Codec::Dcbor),
+            ttl,
+            pin,
+        }
+    }
+
+    fn fits_dht(&self) -> bool {
+        !needs_reference(self.dht_size, DEFAULT_DHT_SIZE_LIMIT)
+    }
+
+    fn fits_ipfs(&self) -> bool { self.size <= DEFAULT_MAX_ENVELOPE_SIZE }
+}
+
+/// The outcome of a backend's health probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+    Up,
+    Down(String),
+    /// Not probed, because an earlier choice made it moot.
+    Skipped,
+}
+
+impl Probe {
+    fn of(report: &HealthReport) -> Self {
+        match &report.error {
+            Some(error) => Self::Down(error.clone()),
+            None => Self::Up,
+        }
+    }
+
+    fn is_up(&self) -> bool { *self == Self::Up }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Up => "available".to_string(),
+            Self::Down(error) => format!("unavailable: {}", error),
+            Self::Skipped => "not probed".to_string(),
+        }
+    }
+}
+
+/// Which candidate backends answered, with where the server and IPFS
+/// daemon were looked for.
+#[derive(Debug, Clone)]
+pub struct Availability {
+    /// The server's probe and address, or `None` if no server was given.
+    pub server: Option<(Probe, String)>,
+    pub ipfs: (Probe, String),
+    pub dht: Probe,
+}
+
+/// The backend chosen for a put, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recommendation {
+    pub backend: StorageBackend,
+    /// One sentence on why this backend was chosen.
+    pub reason: String,
+    /// One line per fact the choice rested on, for `--explain`.
+    pub considered: Vec<String>,
+}
+
+/// Choose a backend for `request` from those `available`.
+///
+/// A server, when one is given, is preferred, since it was named for this
+/// put. Otherwise envelopes that fit go to the DHT, and the rest to hybrid
+/// storage, or to IPFS alone if the DHT is unreachable. Puts asking for a
+/// TTL or a pin skip the DHT, which honors neither.
+pub fn choose(
+    request: &Request,
+    available: &Availability,
+) -> Result<Recommendation> {
+    let mut considered = vec![format!(
+        "envelope: {} bytes, {} bytes bencoded (DHT limit {})",
+        request.size, request.dht_size, DEFAULT_DHT_SIZE_LIMIT
+    )];
+    match &available.server {
+        Some((probe, url)) => {
+            considered.push(format!("server at {}: {}", url, probe.describe()))
+        }
+        None => considered.push(
+            "server: none given (use --host or --capability)".to_string(),
+        ),
+    }
+    considered.push(format!(
+        "ipfs at {}: {}",
+        available.ipfs.1,
+        available.ipfs.0.describe()
+    ));
+    considered.push(format!("mainline: {}", available.dht.describe()));
+
+    let choice = |backend, reason: String| Recommendation {
+        backend,
+        reason,
+        considered: considered.clone(),
+    };
+
+    if let Some((probe, url)) = &available.server
+        && probe.is_up()
+        && !request.pin
+    {
+        return Ok(choice(
+            StorageBackend::Server,
+            format!("a server was given and answers at {}", url),
+        ));
+    }
+
+    let dht_ok = !request.ttl && !request.pin;
+    if request.fits_dht() && dht_ok && available.dht.is_up() {
+        return Ok(choice(
+            StorageBackend::Mainline,
+            format!(
+                "the envelope fits the DHT limit of {} bytes",
+                DEFAULT_DHT_SIZE_LIMIT
+            ),
+        ));
+    }
+
+    if available.ipfs.0.is_up() && request.fits_ipfs() {
+        let needs = if request.pin {
+            "--pin needs IPFS"
+        } else if request.ttl {
+            "--ttl needs a backend that honors it"
+        } else {
+            "the envelope is too large for the DHT"
+        };
+        if !request.fits_dht() && available.dht.is_up() {
+            return Ok(choice(
+                StorageBackend::Hybrid,
+                format!("{} and IPFS and the DHT both answer", needs),
+            ));
+        }
+        return Ok(choice(
+            StorageBackend::Ipfs,
+            format!("{} and IPFS answers", needs),
+        ));
+    }
+
+    Err(Failure::Unavailable.error(format!(
+        "No backend can take this put; pass --storage to choose one\n  {}",
+        considered.join("\n  ")
+    )))
+}
+
+/// Probe the candidate backends and choose one for `request`. A server is
+/// a candidate only if `host` or `capability` names one, in which case
+/// `port` is its port; otherwise `port` is the IPFS daemon's.
+pub async fn recommend(
+    backends: &mut Backends,
+    request: &Request,
+    host: Option<&str>,
+    port: Option<u16>,
+    capability: Option<&Envelope>,
+) -> Result<Recommendation> {
+    let server_given = host.is_some() || capability.is_some();
+    let ipfs_port = if server_given { None } else { port };
+
+    let mut server = None;
+    if server_given {
+        let (_, location) =
+            describe_backend(StorageBackend::Server, host, port);
+        let report = backends
+            .store(StorageBackend::Server, host, port, false, capability)
+            .await?
+            .health()
+            .await;
+        server = Some((Probe::of(&report), location_url(&location)));
+    }
+
+    let (_, location) = describe_backend(StorageBackend::Ipfs, None, ipfs_port);
+    let ipfs_url = location_url(&location);
+    if server.as_ref().is_some_and(|(probe, _)| probe.is_up()) && !request.pin {
+        // The server is taken as given, without waiting on the others
+        let available = Availability {
+            server,
+            ipfs: (Probe::Skipped, ipfs_url),
+            dht: Probe::Skipped,
+        };
+        return choose(request, &available);
+    }
+
+    let ipfs = Probe::of(
+        &backends
+            .store(StorageBackend::Ipfs, None, ipfs_port, false, None)
+            .await?
+            .health()
+            .await,
+    );
+    // Building the DHT node can fail, as when its port is taken
+    let dht = match backends.mainline().await {
+        Ok(store) => Probe::of(&store.health().await),
+        Err(e) => Probe::Down(e.to_string()),
+    };
+    choose(
+        request,
+        &Availability { server, ipfs: (ipfs, ipfs_url), dht },
+    )
+}
+
+/// The address in a [`describe_backend`] location such as `" at host:port"`.
+fn location_url(location: &str) -> String {
+    location.trim_start_matches(" at ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(size: usize) -> Request {
+        Request { dht_size: size + 4, size, ttl: false, pin: false }
+    }
+
+    fn available(
+        server: Option<Probe>,
+        ipfs: Probe,
+        dht: Probe,
+    ) -> Availability {
+        Availability {
+            server: server.map(|probe| (probe, "127.0.0.1:45678".into())),
+            ipfs: (ipfs, "127.0.0.1:5001".into()),
+            dht,
+        }
+    }
+
+    fn backend(request: &Request, available: &Availability) -> StorageBackend {
+        choose(request, available).unwrap().backend
+    }
+
+    #[test]
+    fn test_choose_by_size_and_availability() {
+        let all = available(None, Probe::Up, Probe::Up);
+        assert_eq!(backend(&request(100), &all), StorageBackend::Mainline);
+        assert_eq!(backend(&request(5000), &all), StorageBackend::Hybrid);
+
+        let no_dht = available(None, Probe::Up, Probe::Down("offline".into()));
+        assert_eq!(backend(&request(100), &no_dht), StorageBackend::Ipfs);
+        assert_eq!(backend(&request(5000), &no_dht), StorageBackend::Ipfs);
+
+        let no_ipfs = available(None, Probe::Down("refused".into()), Probe::Up);
+        assert_eq!(backend(&request(100), &no_ipfs), StorageBackend::Mainline);
+        let error = choose(&request(5000), &no_ipfs).unwrap_err();
+        assert_eq!(Failure::of_error(&error), Failure::Unavailable);
+        assert!(error.to_string().contains("refused"));
+    }
+
+    #[test]
+    fn test_choose_prefers_given_server() {
+        let skipped =
+            available(Some(Probe::Up), Probe::Skipped, Probe::Skipped);
+        let chosen = choose(&request(5000), &skipped).unwrap();
+        assert_eq!(chosen.backend, StorageBackend::Server);
+        assert!(chosen.reason.contains("127.0.0.1:45678"));
+
+        let down = available(
+            Some(Probe::Down("refused".into())),
+            Probe::Up,
+            Probe::Up,
+        );
+        assert_eq!(backend(&request(100), &down), StorageBackend::Mainline);
+    }
+
+    #[test]
+    fn test_choose_honors_ttl_and_pin() {
+        let all = available(None, Probe::Up, Probe::Up);
+        let ttl = Request { ttl: true, ..request(100) };
+        assert_eq!(backend(&ttl, &all), StorageBackend::Ipfs);
+
+        let pin = Request { pin: true, ..request(100) };
+        let with_server = available(Some(Probe::Up), Probe::Up, Probe::Up);
+        assert_eq!(backend(&pin, &with_server), StorageBackend::Ipfs);
+    }
+}
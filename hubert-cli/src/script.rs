@@ -0,0 +1,270 @@
+//! Batch execution of hubert commands from a script file.
+//!
+//! A script has one command per line, written as it would be on the command
+//! line without the leading `hubert`. A command's output can be bound to a
+//! variable and used in later lines:
+//!
+//! ```text
+//! # Round-trip an envelope through a local server
+//! $arid = generate arid
+//! $envelope = generate envelope 32
+//! put --storage server $arid $envelope
+//! $result = get --storage server $arid
+//! expect $result $envelope
+//! echo "Round trip OK"
+//! ```
+//!
+//! Blank lines and lines starting with `#` are ignored. Besides the regular
+//! commands, scripts support `echo ARGS...`, which prints its arguments, and
+//! `expect A B`, which fails the script unless `A` and `B` are equal. Lines
+//! run in order against the same backends, and the first failing line stops
+//! the script.
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+
+use crate::{
+    Backends, Cli, execute,
+    exit::{Failure, bail_with},
+};
+
+/// A parsed script line.
+#[derive(Debug, PartialEq)]
+struct Line {
+    /// Variable to bind the command's output to, if any.
+    target: Option<String>,
+    /// Command words, with `$name` references still unresolved.
+    words: Vec<String>,
+}
+
+/// Run the script at `path`, stopping at the first failing line.
+pub async fn run(
+    path: &Path,
+    verbose: bool,
+    quiet: bool,
+    backends: &mut Backends,
+) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut vars = HashMap::new();
+    for (index, text) in text.lines().enumerate() {
+        run_line(text, verbose, quiet, backends, &mut vars)
+            .await
+            .with_context(|| format!("{}:{}", path.display(), index + 1))?;
+    }
+    Ok(())
+}
+
+async fn run_line(
+    text: &str,
+    verbose: bool,
+    quiet: bool,
+    backends: &mut Backends,
+    vars: &mut HashMap<String, String>,
+) -> Result<()> {
+    let Some(line) = parse_line(text)? else {
+        return Ok(());
+    };
+    let words = line
+        .words
+        .iter()
+        .map(|word| substitute(word, vars))
+        .collect::<Result<Vec<_>>>()?;
+
+    let output = match words[0].as_str() {
+        "echo" => Some(words[1..].join(" ")),
+        "expect" => {
+            let [_, expected, actual] = words.as_slice() else {
+                bail_with!(
+                    Failure::Usage,
+                    "expect takes exactly two arguments"
+                );
+            };
+            if expected != actual {
+                bail_with!(
+                    Failure::General,
+                    "expected {}, got {}",
+                    expected,
+                    actual
+                );
+            }
+            None
+        }
+        _ => {
+            let args = std::iter::once("hubert")
+                .chain(words.iter().map(|w| w.as_str()));
+            let cli = Cli::try_parse_from(args)?;
+            let verbose = (verbose && !cli.quiet) || cli.verbose;
+            let quiet = (quiet && !cli.verbose) || cli.quiet;
+            match cli.ledger {
+                // A line's own --ledger applies to that line only
+                Some(path) => {
+                    let saved =
+                        (backends.record_puts, backends.ledger_path.take());
+                    backends.record_puts = true;
+                    backends.ledger_path = path;
+                    let output =
+                        execute(cli.command, verbose, quiet, backends).await;
+                    (backends.record_puts, backends.ledger_path) = saved;
+                    output?
+                }
+                None => execute(cli.command, verbose, quiet, backends).await?,
+            }
+        }
+    };
+
+    match (line.target, output) {
+        (Some(name), Some(output)) => {
+            vars.insert(name, output);
+        }
+        (Some(name), None) => {
+            bail_with!(
+                Failure::Usage,
+                "command produced no output to assign to ${}",
+                name
+            )
+        }
+        (None, Some(output)) => println!("{}", output),
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+/// Parse a line into an optional assignment target and its words.
+///
+/// Returns `None` for blank lines and comments.
+fn parse_line(text: &str) -> Result<Option<Line>> {
+    let text = text.trim();
+    if text.is_empty() || text.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (target, command) = match text.split_once('=') {
+        Some((lhs, rhs)) if lhs.trim_start().starts_with('$') => {
+            let name = lhs.trim().trim_start_matches('$');
+            if !is_variable_name(name) {
+                bail_with!(Failure::Usage, "invalid variable name ${}", name);
+            }
+            (Some(name.to_string()), rhs)
+        }
+        _ => (None, text),
+    };
+
+    let words = split_words(command)?;
+    if words.is_empty() {
+        bail_with!(Failure::Usage, "missing command");
+    }
+    Ok(Some(Line { target, words }))
+}
+
+/// Split a command into words on whitespace, honoring double quotes.
+fn split_words(text: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut in_quotes = false;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_word = true;
+            }
+            '\\' if in_quotes => match chars.next() {
+                Some(escaped) => word.push(escaped),
+                None => bail_with!(Failure::Usage, "unterminated escape"),
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_quotes {
+        bail_with!(Failure::Usage, "unterminated quote");
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Replace a `$name` word with the variable's value.
+fn substitute(word: &str, vars: &HashMap<String, String>) -> Result<String> {
+    match word.strip_prefix('$') {
+        Some(name) if is_variable_name(name) => {
+            vars.get(name).cloned().ok_or_else(|| {
+                Failure::Usage.error(format!("undefined variable ${}", name))
+            })
+        }
+        _ => Ok(word.to_string()),
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        let line = parse_line("$arid1 = generate arid").unwrap().unwrap();
+        assert_eq!(line.target.as_deref(), Some("arid1"));
+        assert_eq!(line.words, words(&["generate", "arid"]));
+    }
+
+    #[test]
+    fn test_parse_command_and_comments() {
+        let line = parse_line("  get --storage server $a ").unwrap().unwrap();
+        assert_eq!(line.target, None);
+        assert_eq!(line.words, words(&["get", "--storage", "server", "$a"]));
+        assert_eq!(parse_line("# comment").unwrap(), None);
+        assert_eq!(parse_line("   ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_line("$1x = generate arid").is_err());
+        assert!(parse_line("$x =").is_err());
+        assert!(parse_line("echo \"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_quoted_words() {
+        assert_eq!(
+            split_words(r#"echo "hello world" "say \"hi\"""#).unwrap(),
+            words(&["echo", "hello world", "say \"hi\""])
+        );
+        assert_eq!(split_words(r#"echo """#).unwrap(), words(&["echo", ""]));
+    }
+
+    #[test]
+    fn test_substitute() {
+        let vars: HashMap<String, String> =
+            [("a".to_string(), "ur:arid/x".to_string())].into();
+        assert_eq!(substitute("$a", &vars).unwrap(), "ur:arid/x");
+        assert_eq!(substitute("plain", &vars).unwrap(), "plain");
+        assert!(substitute("$missing", &vars).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! An end-to-end self-test for `hubert verify`: a put and a get of a
+//! throwaway envelope, timed, for monitoring a backend from cron.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use bc_components::ARID;
+use bc_ur::prelude::*;
+use hubert::KvStore;
+
+use super::{exit::Failure, generate_random_envelope};
+
+/// Seconds a throwaway envelope is kept by backends that support TTLs.
+pub const TTL: u64 = 60;
+
+/// Put a fresh envelope at a new ARID, get it back, and return a report of
+/// each step's latency. Fails with the exit code of the first step that
+/// went wrong.
+pub async fn run(
+    store: &dyn KvStore,
+    timeout: u64,
+    verbose: bool,
+) -> Result<String> {
+    let arid = ARID::new();
+    let envelope = generate_random_envelope(32);
+    let ttl = store.capabilities().ttl.then_some(TTL);
+
+    let start = Instant::now();
+    if let Err(e) = store.put(&arid, &envelope, ttl, verbose).await {
+        return Err(Failure::of(&e).error(format!("✗ Put failed: {}", e)));
+    }
+    let put = start.elapsed();
+
+    let start = Instant::now();
+    let found = match store.get(&arid, Some(timeout), verbose).await {
+        Ok(found) => found,
+        Err(e) => {
+            return Err(Failure::of(&e).error(format!("✗ Get failed: {}", e)));
+        }
+    };
+    let get = start.elapsed();
+    match found {
+        Some(found) if found == envelope => {}
+        Some(_) => {
+            return Err(Failure::General
+                .error("✗ Get returned a different envelope than was put"));
+        }
+        None => {
+            return Err(Failure::Timeout.error(format!(
+                "✗ Get did not find the envelope within {} seconds",
+                timeout
+            )));
+        }
+    }
+
+    let cleanup = match ttl {
+        Some(ttl) => format!("✓ Left to expire in {}s", ttl),
+        None => {
+            "✓ Left in place (the backend has no TTL or delete)".to_string()
+        }
+    };
+    Ok([
+        format!(
+            "✓ Put {} bytes in {}",
+            envelope.to_cbor_data().len(),
+            format_latency(put)
+        ),
+        format!("✓ Got them back in {}", format_latency(get)),
+        cleanup,
+    ]
+    .join("\n"))
+}
+
+fn format_latency(latency: Duration) -> String {
+    format!("{:.1} ms", latency.as_secs_f64() * 1e3)
+}
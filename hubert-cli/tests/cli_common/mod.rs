@@ -50,6 +50,15 @@ pub fn run_cli_expect_error(args: &[&str]) -> Result<()> {
     Ok(())
 }
 
+/// Run the hubert CLI and return its exit code.
+pub fn run_cli_exit_code(args: &[&str]) -> i32 {
+    let output = assert_cmd::cargo::cargo_bin_cmd!("hubert")
+        .args(args)
+        .output()
+        .unwrap();
+    output.status.code().unwrap()
+}
+
 /// Check if output contains a specific string.
 pub fn run_cli_contains(args: &[&str], expected: &str) -> Result<()> {
     let output = run_cli(args)?;
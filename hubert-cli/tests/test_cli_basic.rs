@@ -0,0 +1,215 @@
+use anyhow::Result;
+use bc_components::ARID;
+use bc_ur::prelude::*;
+
+mod cli_common;
+use cli_common::*;
+
+#[test]
+fn test_invalid_command() -> Result<()> {
+    run_cli_expect_error(&["invalid"])?;
+    Ok(())
+}
+
+#[test]
+fn test_missing_arguments() -> Result<()> {
+    run_cli_expect_error(&["put"])?;
+    run_cli_expect_error(&["get"])?;
+    Ok(())
+}
+
+#[test]
+fn test_invalid_arid_format() -> Result<()> {
+    run_cli_expect_error(&[
+        "put",
+        "not-a-valid-arid",
+        "ur:envelope/tpsoiyfdihjzjzjldmksbaoede",
+    ])?;
+    run_cli_expect_error(&["get", "not-a-valid-arid"])?;
+    Ok(())
+}
+
+#[test]
+fn test_invalid_envelope_format() -> Result<()> {
+    run_cli_expect_error(&[
+        "put",
+        "ur:arid/hdcxuestvsdemusrdlkngwtosweortdwbasrdrfxhssgfmvlrflthdplatjydmmwahgdwlflguqz",
+        "not-a-valid-envelope",
+    ])?;
+    Ok(())
+}
+
+#[test]
+fn test_overlong_arid_rejected() -> Result<()> {
+    let long_arid = format!("ur:arid/{}", "a".repeat(1000));
+    let output = run_cli_allow_failure(&["get", &long_arid]);
+    assert!(output.contains("exceeds limit"), "Got: {}", output);
+    Ok(())
+}
+
+#[test]
+fn test_exit_codes() -> Result<()> {
+    bc_components::register_tags();
+    let arid = ARID::new().ur_string();
+
+    assert_eq!(run_cli_exit_code(&["generate", "arid"]), 0);
+    assert_eq!(run_cli_exit_code(&["invalid"]), 2);
+    assert_eq!(run_cli_exit_code(&["get", "not-a-valid-arid"]), 2);
+    assert_eq!(
+        run_cli_exit_code(&[
+            "get",
+            "--capability",
+            "x",
+            "--timeout",
+            "0",
+            &arid
+        ]),
+        2
+    );
+    // Nothing listens on port 1
+    assert_eq!(
+        run_cli_exit_code(&[
+            "get",
+            "--storage",
+            "server",
+            "--port",
+            "1",
+            "--timeout",
+            "0",
+            &arid,
+        ]),
+        5
+    );
+    Ok(())
+}
+
+#[test]
+fn test_quiet_flag() -> Result<()> {
+    run_cli_contains(&["--quiet", "generate", "arid"], "ur:arid/")?;
+    assert_eq!(run_cli_exit_code(&["-q", "-v", "generate", "arid"]), 2);
+    Ok(())
+}
+
+#[test]
+fn test_paths_command() -> Result<()> {
+    run_cli_contains(&["paths"], "hubert.sqlite")?;
+    run_cli_contains(&["paths"], "hubert.toml")?;
+    Ok(())
+}
+
+#[test]
+fn test_storage_backend_option() -> Result<()> {
+    // Storage option is now command-specific, not global
+    run_cli_contains(
+        &["check", "--storage", "mainline", "--help"],
+        "mainline",
+    )?;
+    run_cli_contains(&["put", "--storage", "ipfs", "--help"], "ipfs")?;
+    Ok(())
+}
+
+#[test]
+fn test_invalid_storage_backend() -> Result<()> {
+    // Storage option is now command-specific
+    run_cli_expect_error(&["check", "--storage", "invalid"])?;
+    Ok(())
+}
+
+#[test]
+fn test_generate_help() -> Result<()> {
+    run_cli_contains(
+        &["generate", "--help"],
+        "Generate a new ARID or example Envelope",
+    )?;
+    run_cli_contains(&["generate", "--help"], "arid")?;
+    Ok(())
+}
+
+#[test]
+fn test_generate_arid() -> Result<()> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    // Generate two ARIDs and verify they're different and valid
+    let output1 = run_cli(&["generate", "arid"])?;
+    let output2 = run_cli(&["generate", "arid"])?;
+
+    // Should be different
+    assert_ne!(output1, output2, "Generated ARIDs should be unique");
+
+    // Should be valid ur:arid format
+    assert!(
+        output1.starts_with("ur:arid/"),
+        "Should start with ur:arid/"
+    );
+    assert!(
+        output2.starts_with("ur:arid/"),
+        "Should start with ur:arid/"
+    );
+
+    // Should be parseable as ARID
+    ARID::from_ur_string(&output1)?;
+    ARID::from_ur_string(&output2)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_hex_arid_not_accepted() -> Result<()> {
+    // Hex ARIDs should NOT be accepted (only ur:arid)
+    run_cli_expect_error(&[
+        "put",
+        "dec7e82893c32f7a4fcec633c02c0ec32a4361ca3ee3bc8758ae07742e940550",
+        "ur:envelope/tpsoiyfdihjzjzjldmksbaoede",
+    ])?;
+    Ok(())
+}
+
+#[test]
+fn test_vectors_generate() -> Result<()> {
+    let json = run_cli(&["vectors", "generate"])?;
+    assert_eq!(json, run_cli(&["vectors", "generate"])?);
+
+    let vectors: serde_json::Value = serde_json::from_str(&json)?;
+    let sequential = vectors["vectors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|v| v["name"] == "sequential")
+        .unwrap();
+    // The golden values of tests/test_wire.rs in the hubert crate
+    assert_eq!(
+        sequential["ipfs_key_name"],
+        "5f3ed31fd61de63d5745bfde2d2fef216ae202c32a3ab6688c8e6ec024f6f692"
+    );
+    assert_eq!(sequential["obfuscated_payload"], "8a5ae66670ae6f248f7f");
+
+    let cbor = run_cli(&["vectors", "generate", "--format", "cbor"])?;
+    assert!(cbor.chars().all(|c| c.is_ascii_hexdigit()));
+    Ok(())
+}
+
+#[test]
+fn test_keepalive_registry() -> Result<()> {
+    bc_components::register_tags();
+    let path = std::env::temp_dir()
+        .join(format!("hubert-keepalive-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let registry = format!("--registry={}", path.display());
+    let (first, second) = (ARID::new().ur_string(), ARID::new().ur_string());
+
+    assert_eq!(run_cli(&["keepalive", "list", &registry])?, "");
+    run_cli(&["keepalive", "start", &registry, &first, &second])?;
+    // Starting an entry twice keeps it once
+    run_cli(&["keepalive", "start", &registry, &first])?;
+    let list = run_cli(&["keepalive", "list", &registry])?;
+    assert_eq!(list, format!("{}\n{}", first, second));
+
+    run_cli(&["keepalive", "stop", &registry, &first])?;
+    assert_eq!(run_cli(&["keepalive", "list", &registry])?, second);
+    let stop = ["keepalive", "stop", &registry, &first];
+    assert_eq!(run_cli_exit_code(&stop), 3);
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
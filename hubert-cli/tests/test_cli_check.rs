@@ -1,4 +1,6 @@
 use anyhow::Result;
+use hubert::server::{Server, ServerConfig};
+use tokio::time::{Duration, sleep};
 
 mod cli_common;
 use cli_common::*;
@@ -65,3 +67,26 @@ fn test_check_with_short_flag() -> Result<()> {
     );
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_verify_server() -> Result<()> {
+    let config = ServerConfig { port: 45707, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let output =
+            run_cli(&["verify", "--storage", "server", "--port", "45707"])?;
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("✓ Put "), "{}", output);
+        assert!(lines[1].starts_with("✓ Got them back in "), "{}", output);
+        assert_eq!(lines[2], "✓ Left to expire in 60s");
+
+        // Nothing is listening here, so the put fails as unavailable
+        let args = ["verify", "--storage", "server", "--port", "45705"];
+        assert_eq!(run_cli_exit_code(&args), 5);
+        Ok(())
+    })
+    .await?
+}
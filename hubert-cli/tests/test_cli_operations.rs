@@ -82,6 +82,32 @@ fn test_mainline_get_nonexistent() -> Result<()> {
     Ok(())
 }
 
+/// Test that --ttl is rejected by a backend that cannot honor it
+#[test]
+#[ignore] // Ignored by default due to network requirements and slowness
+fn test_mainline_rejects_ttl() -> Result<()> {
+    ensure_tags_registered();
+
+    let arid_ur = ARID::new().ur_string();
+    let envelope_ur = Envelope::new("Short-lived").ur_string();
+    let output = run_cli_allow_failure(&[
+        "put",
+        "--storage",
+        "mainline",
+        "--ttl",
+        "60",
+        &arid_ur,
+        &envelope_ur,
+    ]);
+    assert!(
+        output.contains("--ttl option is not supported for --storage mainline"),
+        "Put should reject --ttl: {}",
+        output
+    );
+
+    Ok(())
+}
+
 /// Test with IPFS storage backend
 ///
 /// Note: This test requires a running IPFS daemon at 127.0.0.1:5001
@@ -0,0 +1,397 @@
+use anyhow::Result;
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use hubert::server::{Server, ServerConfig};
+use tokio::time::{Duration, sleep};
+
+mod cli_common;
+use cli_common::*;
+
+fn script_path(name: &str) -> String {
+    format!("{}/tests/scripts/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn test_script_variables_and_builtins() -> Result<()> {
+    run_cli_expect(&["run", &script_path("offline.hub")], "offline script ok")
+}
+
+#[test]
+fn test_script_stops_at_failing_line() -> Result<()> {
+    let output = run_cli_allow_failure(&["run", &script_path("mismatch.hub")]);
+    assert!(output.contains("mismatch.hub:3"), "Got: {}", output);
+    assert!(!output.contains("unreachable"), "Got: {}", output);
+    assert_eq!(run_cli_exit_code(&["run", &script_path("mismatch.hub")]), 1);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_script_server_roundtrip() -> Result<()> {
+    let config = ServerConfig { port: 45688, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    let script = script_path("server_roundtrip.hub");
+    tokio::task::spawn_blocking(move || {
+        run_cli_expect(&["run", &script], "server round trip ok")
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_verbose_output_stays_off_stdout() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45703, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let arid = ARID::new().ur_string();
+        let envelope = Envelope::new("Pipe me").ur_string();
+        let server = ["--storage", "server", "--port", "45703", "--verbose"];
+
+        let mut put = vec!["put"];
+        put.extend_from_slice(&server);
+        put.extend_from_slice(&[arid.as_str(), envelope.as_str()]);
+        assert_eq!(run_cli(&put)?, "");
+
+        let mut get = vec!["get"];
+        get.extend_from_slice(&server);
+        get.push(arid.as_str());
+        assert_eq!(run_cli(&get)?, envelope);
+        Ok(())
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_exit_codes() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45702, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let arid = ARID::new().ur_string();
+        let envelope = Envelope::new("Exit codes").ur_string();
+        let missing = ARID::new().ur_string();
+        let code = |command: &str, rest: &[&str]| {
+            let mut args = vec![command, "--storage", "server"];
+            args.extend_from_slice(&["--port", "45702"]);
+            args.extend_from_slice(rest);
+            run_cli_exit_code(&args)
+        };
+
+        assert_eq!(code("put", &[arid.as_str(), envelope.as_str()]), 0);
+        assert_eq!(code("put", &[arid.as_str(), envelope.as_str()]), 4);
+        assert_eq!(code("get", &["--timeout", "0", arid.as_str()]), 0);
+        assert_eq!(code("get", &["--timeout", "0", missing.as_str()]), 3);
+
+        // TTLs and timeouts take duration literals
+        let later = ARID::new().ur_string();
+        let put = |ttl: &str| {
+            code("put", &["--ttl", ttl, later.as_str(), envelope.as_str()])
+        };
+        assert_eq!(put("2x"), 2);
+        assert_eq!(put("0"), 2);
+        assert_eq!(put("2h30m"), 0);
+        assert_eq!(code("get", &["--timeout", "1m", later.as_str()]), 0);
+    })
+    .await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_put_without_storage_uses_given_server() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45722, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let arid = ARID::new().ur_string();
+        let envelope = Envelope::new("Chosen for me").ur_string();
+        let server = ["--host", "127.0.0.1", "--port", "45722"];
+
+        let mut put = vec!["put", "--explain"];
+        put.extend_from_slice(&server);
+        put.extend_from_slice(&[arid.as_str(), envelope.as_str()]);
+        assert_eq!(run_cli(&put)?, "");
+
+        let mut get = vec!["get", "--storage", "server"];
+        get.extend_from_slice(&server);
+        get.push(arid.as_str());
+        assert_eq!(run_cli(&get)?, envelope);
+        Ok(())
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_bench_against_server() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45704, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let report = run_cli(&[
+            "bench",
+            "--storage",
+            "server",
+            "--port",
+            "45704",
+            "--ops",
+            "40",
+            "--concurrency",
+            "8",
+            "--size",
+            "100",
+        ])?;
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("40 pairs of 100 byte envelopes"));
+        assert!(lines[1].starts_with("put: 40 ok, 0 failed (0.0%), p50 "));
+        assert!(lines[2].starts_with("get: 40 ok, 0 failed (0.0%), p50 "));
+        assert_eq!(lines.len(), 3, "{}", report);
+
+        // Gets are skipped for failed puts, and errors are tallied
+        let report = run_cli(&[
+            "bench",
+            "--storage",
+            "server",
+            "--port",
+            "45705",
+            "--ops",
+            "3",
+        ])?;
+        assert!(report.contains("put: 0 ok, 3 failed (100.0%)"));
+        assert!(report.contains("get: not attempted"));
+        Ok(())
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_put_history() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45706, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let path = std::env::temp_dir()
+            .join(format!("hubert-ledger-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let ledger = format!("--ledger={}", path.display());
+        let (first, second) =
+            (ARID::new().ur_string(), ARID::new().ur_string());
+        let envelope = Envelope::new("Remember me").ur_string();
+        let put = |arid: &str, ledger: Option<&str>| {
+            let mut args = vec!["put", "--storage", "server"];
+            args.extend_from_slice(&["--port", "45706", "--ttl", "600"]);
+            args.extend(ledger);
+            args.extend_from_slice(&[arid, envelope.as_str()]);
+            run_cli(&args)
+        };
+
+        put(&first, Some(&ledger))?;
+        put(&second, None)?;
+        let list = run_cli(&["history", "list", &ledger])?;
+        assert_eq!(list.lines().count(), 1, "{}", list);
+        assert!(
+            list.starts_with("1  ") && list.ends_with(&first),
+            "{}",
+            list
+        );
+
+        let show = run_cli(&["history", "show", &ledger, &first])?;
+        assert!(show.contains("--storage server --port 45706"), "{}", show);
+        assert!(show.contains("TTL:      600s"), "{}", show);
+        assert!(show.contains(&envelope), "{}", show);
+
+        let expiring = ["history", "expiring", &ledger, "--within", "1h"];
+        let soon = run_cli(&expiring)?;
+        assert!(
+            soon.starts_with("1  ") && soon.ends_with(&first),
+            "{}",
+            soon
+        );
+        let later = ["history", "expiring", &ledger, "--within", "5m"];
+        assert_eq!(run_cli(&later)?, "");
+
+        // Reposting goes back to the same server, where the ARID is taken
+        let repost = ["history", "repost", &ledger, "1"];
+        assert_eq!(run_cli_exit_code(&repost), 4);
+        assert_eq!(run_cli_exit_code(&["history", "show", &ledger, "9"]), 3);
+
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_put_batch() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45724, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let dir = std::env::temp_dir()
+            .join(format!("hubert-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let (first, second) = (ARID::new(), ARID::new());
+        let inline = Envelope::new("Inline");
+        let from_file = Envelope::new("From a file");
+        let file = dir.join("second.envelope");
+        std::fs::write(file, from_file.to_cbor_data())?;
+        let manifest = dir.join("entries.jsonl");
+        std::fs::write(
+            &manifest,
+            format!(
+                "{{\"arid\": \"{}\", \"envelope\": \"{}\", \"ttl\": 600}}\n\
+                 {{\"arid\": \"{}\", \"file\": \"second.envelope\"}}\n",
+                first.ur_string(),
+                inline.ur_string(),
+                second.ur_string()
+            ),
+        )?;
+        let manifest = manifest.display().to_string();
+        let batch = [
+            "put-batch",
+            "--manifest",
+            manifest.as_str(),
+            "--storage",
+            "server",
+            "--port",
+            "45724",
+        ];
+
+        let report = run_cli(&batch)?;
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", report);
+        let stored = |line: usize, arid: &ARID| {
+            format!("{}  server  {}  ok in ", line, arid.ur_string())
+        };
+        assert!(lines[0].starts_with(&stored(1, &first)), "{}", report);
+        assert!(lines[1].starts_with(&stored(2, &second)), "{}", report);
+        assert!(lines[2].ends_with("2 stored, 0 failed"), "{}", report);
+
+        let get = |arid: &ARID| {
+            let arid = arid.ur_string();
+            run_cli(&["get", "--storage", "server", "--port", "45724", &arid])
+        };
+        assert_eq!(get(&first)?, inline.ur_string());
+        assert_eq!(get(&second)?, from_file.ur_string());
+
+        // Both ARIDs are now taken, so every entry fails
+        assert_eq!(run_cli_exit_code(&batch), 4);
+        let error = run_cli_allow_failure(&batch);
+        assert!(error.contains("2 of 2 entries failed"), "{}", error);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_monitor_once() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45725, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let (stored, pending) = (ARID::new(), ARID::new());
+        let envelope = Envelope::new("Commitment");
+        run_cli(&[
+            "put",
+            "--storage",
+            "server",
+            "--port",
+            "45725",
+            &stored.ur_string(),
+            &envelope.ur_string(),
+        ])?;
+
+        let path = std::env::temp_dir()
+            .join(format!("hubert-monitor-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            format!(
+                "# Round 1\n{} server\n{} server\n",
+                stored.ur_string(),
+                pending.ur_string()
+            ),
+        )?;
+        let arids = path.display().to_string();
+        let monitor = ["monitor", "--arids", &arids, "--port", "45725"];
+        let mut once = monitor.to_vec();
+        once.push("--once");
+        let table = run_cli(&once)?;
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", table);
+        assert!(lines[0].starts_with("2 ARIDs at "), "{}", table);
+        let counts = "1 found, 1 pending, 0 expired, 0 failed";
+        assert!(lines[0].ends_with(counts), "{}", table);
+        let size = envelope.to_cbor_data().len();
+        assert!(lines[1].starts_with("2    found"), "{}", table);
+        assert!(lines[1].contains(&format!(" {} B  server ", size)));
+        assert!(lines[1].ends_with(&stored.ur_string()), "{}", table);
+        assert!(lines[2].starts_with("3    pending"), "{}", table);
+        assert!(lines[2].ends_with(&pending.ur_string()), "{}", table);
+
+        let _ = std::fs::remove_file(&path);
+        Ok(())
+    })
+    .await?
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_arid_encrypt() -> Result<()> {
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45726, ..Default::default() };
+    let server = Server::new_memory(config);
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    tokio::task::spawn_blocking(|| {
+        let arid = ARID::new().ur_string();
+        let envelope = Envelope::new("For the ARID holder").ur_string();
+        let server = ["--storage", "server", "--port", "45726"];
+        let encrypt = ["--arid-encrypt", "--key-context", "chat"];
+
+        let mut put = vec!["put"];
+        put.extend_from_slice(&server);
+        put.extend_from_slice(&encrypt);
+        put.extend_from_slice(&[arid.as_str(), envelope.as_str()]);
+        run_cli(&put)?;
+
+        let mut get = vec!["get"];
+        get.extend_from_slice(&server);
+        get.push(arid.as_str());
+        let stored = run_cli(&get)?;
+        assert_ne!(stored, envelope);
+        assert!(stored.starts_with("ur:envelope/"), "{}", stored);
+
+        let mut decrypt = get.clone();
+        decrypt.extend_from_slice(&encrypt);
+        assert_eq!(run_cli(&decrypt)?, envelope);
+
+        get.extend_from_slice(&["--arid-encrypt", "--key-context", "mail"]);
+        assert_eq!(run_cli_exit_code(&get), 1);
+        Ok(())
+    })
+    .await?
+}
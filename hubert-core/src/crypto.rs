@@ -0,0 +1,159 @@
+#[cfg(target_has_atomic = "ptr")]
+use core::{
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+use chacha20::{
+    ChaCha20,
+    cipher::{KeyIvInit, StreamCipher},
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// The primitives every derivation and obfuscation in this crate is built
+/// from.
+///
+/// Integrators whose compliance rules require approved or audited
+/// implementations provide their own and install it with
+/// `install_crypto_provider`, or, on targets without pointer-sized atomics
+/// where nothing can be installed, pass it to the `_with` variant of each
+/// function; everyone else gets [`DefaultCryptoProvider`]. A provider must
+/// compute exactly these functions, or the keys and obfuscated bytes it
+/// produces will not match other Hubert clients'. To check one, compare
+/// what `hubert::vectors` generates with it installed against the output
+/// of `hubert vectors generate`.
+pub trait CryptoProvider: Sync {
+    /// Fill `output` with HKDF-HMAC-SHA-256 (RFC 5869) of `key_material`
+    /// with `salt` and `info`. `output` is at most 8160 bytes.
+    fn hkdf_sha256(
+        &self,
+        key_material: &[u8],
+        salt: &[u8],
+        info: &[u8],
+        output: &mut [u8],
+    );
+
+    /// XOR `data` with the ChaCha20 (RFC 8439) keystream for `key` and
+    /// `nonce`, starting from block counter 0.
+    fn chacha20_apply(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8; 12],
+        data: &mut [u8],
+    );
+}
+
+/// The provider used unless another is installed: the RustCrypto `hkdf`,
+/// `sha2`, and `chacha20` crates, which compute the same bytes as the
+/// `bc-crypto` functions earlier versions of Hubert used.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultCryptoProvider;
+
+impl CryptoProvider for DefaultCryptoProvider {
+    fn hkdf_sha256(
+        &self,
+        key_material: &[u8],
+        salt: &[u8],
+        info: &[u8],
+        output: &mut [u8],
+    ) {
+        Hkdf::<Sha256>::new(Some(salt), key_material)
+            .expand(info, output)
+            .expect("output is at most 255 hash lengths");
+    }
+
+    fn chacha20_apply(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8; 12],
+        data: &mut [u8],
+    ) {
+        ChaCha20::new(&(*key).into(), &(*nonce).into()).apply_keystream(data);
+    }
+}
+
+static DEFAULT: &dyn CryptoProvider = &DefaultCryptoProvider;
+
+#[cfg(target_has_atomic = "ptr")]
+static INSTALLED: AtomicPtr<&'static dyn CryptoProvider> =
+    AtomicPtr::new(ptr::null_mut());
+
+/// Use `provider` for every derivation and obfuscation in this process.
+///
+/// Install it before the first one; a provider can be installed only
+/// once, so data is never derived with two. The first call to
+/// [`crypto_provider`], which every derivation makes, fixes the provider
+/// in use, so installing fails after it too. Fails with the provider
+/// already in use.
+///
+/// ```
+/// use hubert_core::{CryptoProvider, DefaultCryptoProvider};
+///
+/// static PROVIDER: &dyn CryptoProvider = &DefaultCryptoProvider;
+/// assert!(hubert_core::install_crypto_provider(&PROVIDER).is_ok());
+/// assert!(hubert_core::install_crypto_provider(&PROVIDER).is_err());
+/// ```
+#[cfg(target_has_atomic = "ptr")]
+pub fn install_crypto_provider(
+    provider: &'static &'static dyn CryptoProvider,
+) -> Result<(), &'static dyn CryptoProvider> {
+    let provider = ptr::from_ref(provider).cast_mut();
+    INSTALLED
+        .compare_exchange(
+            ptr::null_mut(),
+            provider,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .map(|_| ())
+        // SAFETY: only `&'static` references are ever stored
+        .map_err(|installed| unsafe { *installed })
+}
+
+/// The installed provider, or [`DefaultCryptoProvider`] if none is.
+///
+/// Once this is called, [`install_crypto_provider`] fails: if no provider
+/// was installed, the default is installed in its place.
+#[cfg(target_has_atomic = "ptr")]
+pub fn crypto_provider() -> &'static dyn CryptoProvider {
+    let mut installed = INSTALLED.load(Ordering::Acquire);
+    if installed.is_null() {
+        let default = ptr::from_ref(&DEFAULT).cast_mut();
+        installed = match INSTALLED.compare_exchange(
+            ptr::null_mut(),
+            default,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => default,
+            Err(installed) => installed,
+        };
+    }
+    // SAFETY: only `&'static` references are ever stored
+    unsafe { *installed }
+}
+
+/// [`DefaultCryptoProvider`], on targets without pointer-sized atomics to
+/// hold an installed provider.
+#[cfg(not(target_has_atomic = "ptr"))]
+pub fn crypto_provider() -> &'static dyn CryptoProvider { DEFAULT }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derive_mainline_key;
+
+    static PROVIDER: &dyn CryptoProvider = &DefaultCryptoProvider;
+
+    #[test]
+    fn test_install_after_use_fails() {
+        derive_mainline_key(&[7u8; 32]);
+        let Err(in_use) = install_crypto_provider(&PROVIDER) else {
+            panic!("installed a provider after a derivation");
+        };
+        // The default, which made the derivation, stays in use
+        assert!(ptr::addr_eq(in_use, DEFAULT));
+        assert!(ptr::addr_eq(crypto_provider(), DEFAULT));
+    }
+}
@@ -0,0 +1,293 @@
+use alloc::{borrow::Cow, string::String, vec, vec::Vec};
+
+use crate::crypto::{CryptoProvider, crypto_provider};
+
+/// Salt for the IPNS key names [`derive_ipfs_key_name`] produces.
+pub const IPFS_IPNS_SALT: &[u8] = b"hubert-ipfs-ipns-v1";
+
+/// Salt for the DHT key material [`derive_mainline_key`] produces.
+pub const MAINLINE_DHT_SALT: &[u8] = b"hubert-mainline-dht-v1";
+
+/// Salt for the payload encryption keys [`derive_payload_key`] produces.
+pub const PAYLOAD_KEY_SALT: &[u8] = b"hubert-payload-key-v1";
+
+/// HKDF-HMAC-SHA-256 with `key_material` as the input key material, `salt`
+/// as the salt, and an empty info string, from `provider`.
+pub(crate) fn hkdf_hmac_sha256(
+    provider: &dyn CryptoProvider,
+    key_material: &[u8],
+    salt: &[u8],
+    output_len: usize,
+) -> Vec<u8> {
+    let mut output = vec![0u8; output_len];
+    provider.hkdf_sha256(key_material, salt, &[], &mut output);
+    output
+}
+
+/// The salt `salt` becomes in deployment `domain`.
+///
+/// A domain isolates a deployment's storage namespace: the same ARID maps
+/// to different DHT items and IPNS names, obfuscated with different keys,
+/// in each. The empty domain leaves `salt` as it is, so deployments without
+/// one derive what every Hubert implementation did before domains; any
+/// other is appended to `salt` after a `/`.
+pub fn domain_salt<'a>(salt: &'a [u8], domain: &str) -> Cow<'a, [u8]> {
+    if domain.is_empty() {
+        return Cow::Borrowed(salt);
+    }
+    let mut salted = Vec::with_capacity(salt.len() + 1 + domain.len());
+    salted.extend_from_slice(salt);
+    salted.push(b'/');
+    salted.extend_from_slice(domain.as_bytes());
+    Cow::Owned(salted)
+}
+
+/// Derive a deterministic key from an ARID using a specific salt.
+///
+/// Uses HKDF to derive key material from the ARID, ensuring that:
+/// - Same ARID always produces same key for a given salt
+/// - Keys are cryptographically derived (not guessable)
+/// - Collision resistance inherited from ARID
+/// - No identifying information in the key (fully anonymized)
+///
+/// The domain salt is HKDF's input key material and the ARID its salt, as
+/// in every Hubert implementation since the first.
+///
+/// # Parameters
+///
+/// - `salt`: Domain-specific salt to ensure different backends derive different
+///   keys
+/// - `arid`: The ARID's bytes
+/// - `output_len`: Length of output in bytes (typically 20 or 32)
+///
+/// # Returns
+///
+/// Derived key bytes
+pub fn derive_key(salt: &[u8], arid: &[u8], output_len: usize) -> Vec<u8> {
+    derive_key_with(crypto_provider(), salt, arid, output_len)
+}
+
+/// [`derive_key`] with `provider`.
+pub fn derive_key_with(
+    provider: &dyn CryptoProvider,
+    salt: &[u8],
+    arid: &[u8],
+    output_len: usize,
+) -> Vec<u8> {
+    hkdf_hmac_sha256(provider, salt, arid, output_len)
+}
+
+/// Derive an IPNS key name from an ARID.
+///
+/// Returns a 64-character hex string suitable for use as an IPFS key name.
+pub fn derive_ipfs_key_name(arid: &[u8]) -> String {
+    derive_ipfs_key_name_in("", arid)
+}
+
+/// [`derive_ipfs_key_name`] in deployment `domain` (see [`domain_salt`]).
+pub fn derive_ipfs_key_name_in(domain: &str, arid: &[u8]) -> String {
+    derive_ipfs_key_name_with(crypto_provider(), domain, arid)
+}
+
+/// [`derive_ipfs_key_name_in`] with `provider`.
+pub fn derive_ipfs_key_name_with(
+    provider: &dyn CryptoProvider,
+    domain: &str,
+    arid: &[u8],
+) -> String {
+    let salt = domain_salt(IPFS_IPNS_SALT, domain);
+    hex::encode(derive_key_with(provider, &salt, arid, 32))
+}
+
+/// Derive Mainline DHT key material from an ARID.
+///
+/// Returns 20 bytes of key material (SHA-1 compatible length).
+pub fn derive_mainline_key(arid: &[u8]) -> Vec<u8> {
+    derive_mainline_key_in("", arid)
+}
+
+/// [`derive_mainline_key`] in deployment `domain` (see [`domain_salt`]).
+pub fn derive_mainline_key_in(domain: &str, arid: &[u8]) -> Vec<u8> {
+    derive_mainline_key_with(crypto_provider(), domain, arid)
+}
+
+/// [`derive_mainline_key_in`] with `provider`.
+pub fn derive_mainline_key_with(
+    provider: &dyn CryptoProvider,
+    domain: &str,
+    arid: &[u8],
+) -> Vec<u8> {
+    let salt = domain_salt(MAINLINE_DHT_SALT, domain);
+    derive_key_with(provider, &salt, arid, 20)
+}
+
+/// Derive the ed25519 seed of the key that signs an ARID's Mainline DHT
+/// item. The item is stored under the hash of the key's public half.
+///
+/// The 20 bytes of [`derive_mainline_key`] are extended to 32: each
+/// further byte `i` is byte `i % 20` multiplied by `i`, wrapping.
+pub fn derive_mainline_seed(arid: &[u8]) -> [u8; 32] {
+    derive_mainline_seed_in("", arid)
+}
+
+/// [`derive_mainline_seed`] in deployment `domain` (see [`domain_salt`]).
+pub fn derive_mainline_seed_in(domain: &str, arid: &[u8]) -> [u8; 32] {
+    derive_mainline_seed_with(crypto_provider(), domain, arid)
+}
+
+/// [`derive_mainline_seed_in`] with `provider`.
+pub fn derive_mainline_seed_with(
+    provider: &dyn CryptoProvider,
+    domain: &str,
+    arid: &[u8],
+) -> [u8; 32] {
+    let key = derive_mainline_key_with(provider, domain, arid);
+    let mut seed = [0u8; 32];
+    seed[..20].copy_from_slice(&key);
+    for i in 20..32 {
+        seed[i] = key[i % 20].wrapping_mul(i as u8);
+    }
+    seed
+}
+
+/// Derive a 32-byte key for encrypting an application's payload, which
+/// only holders of the ARID can derive.
+///
+/// `context` is HKDF's info string, so one ARID yields independent keys for
+/// different purposes; an empty context is the default. The key material
+/// and salt are as in [`derive_key`], with [`PAYLOAD_KEY_SALT`], so the key
+/// is unrelated to those the backends derive for storage and obfuscation.
+pub fn derive_payload_key(arid: &[u8], context: &[u8]) -> [u8; 32] {
+    derive_payload_key_with(crypto_provider(), arid, context)
+}
+
+/// [`derive_payload_key`] with `provider`.
+pub fn derive_payload_key_with(
+    provider: &dyn CryptoProvider,
+    arid: &[u8],
+    context: &[u8],
+) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    provider.hkdf_sha256(PAYLOAD_KEY_SALT, arid, context, &mut key);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultCryptoProvider;
+
+    const ARID_1: [u8; 32] = [1; 32];
+    const ARID_2: [u8; 32] = [2; 32];
+
+    #[test]
+    fn test_determinism() {
+        assert_eq!(
+            derive_ipfs_key_name(&ARID_1),
+            derive_ipfs_key_name(&ARID_1),
+            "Same ARID must produce same key"
+        );
+        assert_eq!(
+            derive_mainline_key(&ARID_1),
+            derive_mainline_key(&ARID_1),
+            "Same ARID must produce same key"
+        );
+    }
+
+    #[test]
+    fn test_uniqueness() {
+        assert_ne!(
+            derive_ipfs_key_name(&ARID_1),
+            derive_ipfs_key_name(&ARID_2),
+            "Different ARIDs must produce different keys"
+        );
+        assert_ne!(
+            derive_mainline_key(&ARID_1),
+            derive_mainline_key(&ARID_2),
+            "Different ARIDs must produce different keys"
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let key = derive_ipfs_key_name(&ARID_1);
+        assert_eq!(key.len(), 64, "IPFS key must be 64 hex characters");
+        assert!(
+            key.chars().all(|c| c.is_ascii_hexdigit()),
+            "Key must be valid hex"
+        );
+        assert_eq!(
+            derive_mainline_key(&ARID_1).len(),
+            20,
+            "Mainline key must be 20 bytes"
+        );
+    }
+
+    #[test]
+    fn test_mainline_seed_extends_key() {
+        let seed = derive_mainline_seed(&ARID_1);
+        let key = derive_mainline_key(&ARID_1);
+        assert_eq!(&seed[..20], key.as_slice());
+        assert_eq!(seed[21], key[1].wrapping_mul(21));
+    }
+
+    #[test]
+    fn test_domains() {
+        assert_eq!(domain_salt(MAINLINE_DHT_SALT, ""), MAINLINE_DHT_SALT);
+        assert_eq!(
+            domain_salt(MAINLINE_DHT_SALT, "acme").as_ref(),
+            b"hubert-mainline-dht-v1/acme"
+        );
+        assert_eq!(
+            derive_mainline_seed_in("", &ARID_1),
+            derive_mainline_seed(&ARID_1)
+        );
+        assert_ne!(
+            derive_mainline_seed_in("acme", &ARID_1),
+            derive_mainline_seed(&ARID_1)
+        );
+        assert_ne!(
+            derive_ipfs_key_name_in("acme", &ARID_1),
+            derive_ipfs_key_name_in("globex", &ARID_1)
+        );
+    }
+
+    #[test]
+    fn test_payload_key() {
+        let key = derive_payload_key(&ARID_1, b"");
+        assert_eq!(key.as_slice(), derive_key(PAYLOAD_KEY_SALT, &ARID_1, 32));
+        assert_ne!(key, derive_payload_key(&ARID_1, b"chat"));
+        assert_ne!(key, derive_payload_key(&ARID_2, b""));
+    }
+
+    #[test]
+    fn test_explicit_provider() {
+        let provider = DefaultCryptoProvider;
+        assert_eq!(
+            derive_key_with(&provider, IPFS_IPNS_SALT, &ARID_1, 32),
+            derive_key(IPFS_IPNS_SALT, &ARID_1, 32)
+        );
+        assert_eq!(
+            derive_ipfs_key_name_with(&provider, "acme", &ARID_1),
+            derive_ipfs_key_name_in("acme", &ARID_1)
+        );
+        assert_eq!(
+            derive_mainline_seed_with(&provider, "acme", &ARID_1),
+            derive_mainline_seed_in("acme", &ARID_1)
+        );
+        assert_eq!(
+            derive_payload_key_with(&provider, &ARID_1, b"chat"),
+            derive_payload_key(&ARID_1, b"chat")
+        );
+    }
+
+    #[test]
+    fn test_different_salts() {
+        let ipfs = derive_ipfs_key_name(&ARID_1);
+        let mainline = hex::encode(derive_mainline_key(&ARID_1));
+        assert_ne!(
+            ipfs, mainline,
+            "Different salts must produce different keys"
+        );
+    }
+}
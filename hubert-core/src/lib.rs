@@ -0,0 +1,48 @@
+//! The pure parts of Hubert, for implementations that cannot use the full
+//! `hubert` crate, such as firmware on an embedded signer.
+//!
+//! Everything here is deterministic and needs only `alloc`: the keys each
+//! backend derives from an ARID, the key applications encrypt payloads with,
+//! the obfuscation applied to stored payloads, and the layout of the
+//! reference envelopes hybrid storage leaves in the DHT. A device computing
+//! these gets the same bytes as `hubert` does, so it can find, decrypt, and
+//! recognize what a Hubert client stored without a network stack or an
+//! envelope library.
+//!
+//! ARIDs are passed as their 32 raw bytes. Functions ending in `_in` take
+//! the deployment's derivation domain, which must match the `hubert`
+//! client's; see [`domain_salt`].
+//!
+//! The HKDF and ChaCha20 computations behind all of these come from a
+//! [`CryptoProvider`], which integrators with compliance requirements can
+//! replace with their approved implementations: install one for the whole
+//! program with `install_crypto_provider`, or, on targets without
+//! pointer-sized atomics where that is unavailable, pass one to the
+//! functions ending in `_with`, which also take the domain.
+#![no_std]
+
+extern crate alloc;
+
+mod crypto;
+mod derivation;
+mod obfuscation;
+pub mod reference;
+
+#[cfg(target_has_atomic = "ptr")]
+pub use crypto::install_crypto_provider;
+pub use crypto::{CryptoProvider, DefaultCryptoProvider, crypto_provider};
+pub use derivation::{
+    IPFS_IPNS_SALT, MAINLINE_DHT_SALT, PAYLOAD_KEY_SALT, derive_ipfs_key_name,
+    derive_ipfs_key_name_in, derive_ipfs_key_name_with, derive_key,
+    derive_key_with, derive_mainline_key, derive_mainline_key_in,
+    derive_mainline_key_with, derive_mainline_seed, derive_mainline_seed_in,
+    derive_mainline_seed_with, derive_payload_key, derive_payload_key_with,
+    domain_salt,
+};
+pub use obfuscation::{
+    OBFUSCATION_SALT, obfuscate_with_arid, obfuscate_with_arid_in,
+    obfuscate_with_arid_with,
+};
+
+/// Bytes in an ARID.
+pub const ARID_SIZE: usize = 32;
@@ -0,0 +1,121 @@
+use alloc::vec::Vec;
+
+use crate::{
+    crypto::{CryptoProvider, crypto_provider},
+    derivation::{domain_salt, hkdf_hmac_sha256},
+};
+
+/// Salt for the key [`obfuscate_with_arid`] derives.
+pub const OBFUSCATION_SALT: &[u8] = b"hubert-obfuscation-v1";
+
+/// Obfuscate or deobfuscate data using ChaCha20 with an ARID-derived key.
+///
+/// This function uses ChaCha20 as a stream cipher to XOR the data with a
+/// keystream derived from the ARID. Since XOR is symmetric, the same function
+/// is used for both obfuscation and deobfuscation.
+///
+/// The result appears as uniform random data to anyone who doesn't have the
+/// ARID, hiding both the structure and content of the reference envelope.
+///
+/// # Parameters
+///
+/// - `arid`: The bytes of the ARID used to derive the obfuscation key
+/// - `data`: The data to obfuscate or deobfuscate
+///
+/// # Returns
+///
+/// The obfuscated (or deobfuscated) data
+pub fn obfuscate_with_arid(arid: &[u8], data: impl AsRef<[u8]>) -> Vec<u8> {
+    obfuscate_with_arid_in("", arid, data)
+}
+
+/// [`obfuscate_with_arid`] in deployment `domain` (see
+/// [`domain_salt`](crate::domain_salt)).
+pub fn obfuscate_with_arid_in(
+    domain: &str,
+    arid: &[u8],
+    data: impl AsRef<[u8]>,
+) -> Vec<u8> {
+    obfuscate_with_arid_with(crypto_provider(), domain, arid, data)
+}
+
+/// [`obfuscate_with_arid_in`] with `provider`.
+pub fn obfuscate_with_arid_with(
+    provider: &dyn CryptoProvider,
+    domain: &str,
+    arid: &[u8],
+    data: impl AsRef<[u8]>,
+) -> Vec<u8> {
+    let data = data.as_ref();
+    if data.is_empty() {
+        return data.to_vec();
+    }
+
+    // Derive a 32-byte key from the ARID using HKDF with domain-specific salt
+    let salt = domain_salt(OBFUSCATION_SALT, domain);
+    let key: [u8; 32] = hkdf_hmac_sha256(provider, &salt, arid, 32)
+        .try_into()
+        .expect("HKDF produces exactly 32 bytes");
+
+    // Derive IV from the key (last 12 bytes, reversed)
+    let mut iv = [0u8; 12];
+    for (iv_byte, key_byte) in iv.iter_mut().zip(key.iter().rev()) {
+        *iv_byte = *key_byte;
+    }
+
+    let mut buffer = data.to_vec();
+    provider.chacha20_apply(&key, &iv, &mut buffer);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultCryptoProvider;
+
+    const ARID_1: [u8; 32] = [1; 32];
+    const ARID_2: [u8; 32] = [2; 32];
+
+    #[test]
+    fn test_obfuscation_roundtrip() {
+        let original = b"Hello, this is test data for obfuscation!";
+
+        let obfuscated = obfuscate_with_arid(&ARID_1, original);
+        let deobfuscated = obfuscate_with_arid(&ARID_1, &obfuscated);
+
+        assert_eq!(original.as_slice(), deobfuscated.as_slice());
+        assert_ne!(original.as_slice(), obfuscated.as_slice());
+    }
+
+    #[test]
+    fn test_obfuscation_different_arids() {
+        let data = b"Same data, different keys";
+
+        assert_ne!(
+            obfuscate_with_arid(&ARID_1, data),
+            obfuscate_with_arid(&ARID_2, data)
+        );
+    }
+
+    #[test]
+    fn test_obfuscation_domains() {
+        let data = b"Same data, different deployments";
+        let acme = obfuscate_with_arid_in("acme", &ARID_1, data);
+        assert_ne!(acme, obfuscate_with_arid(&ARID_1, data));
+        assert_eq!(obfuscate_with_arid_in("acme", &ARID_1, &acme), data);
+    }
+
+    #[test]
+    fn test_obfuscation_explicit_provider() {
+        let data = b"Same data, same provider";
+        assert_eq!(
+            obfuscate_with_arid_with(&DefaultCryptoProvider, "", &ARID_1, data),
+            obfuscate_with_arid(&ARID_1, data)
+        );
+    }
+
+    #[test]
+    fn test_obfuscation_empty_data() {
+        assert!(obfuscate_with_arid(&ARID_1, b"").is_empty());
+    }
+}
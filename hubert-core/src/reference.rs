@@ -0,0 +1,109 @@
+//! The reference envelopes hybrid storage puts in the DHT when an envelope
+//! is too large to store there directly.
+//!
+//! ```text
+//! '' [
+//!     'dereferenceVia': "ipfs",
+//!     'id': <ARID>,
+//!     "size": <usize>
+//! ]
+//! ```
+//!
+//! The actual envelope is stored in IPFS at the ARID in `'id'`. When IPFS
+//! was down and a hybrid store had a fallback server, `'dereferenceVia'` is
+//! "server" instead and a `"url"` assertion gives the server's base URL.
+//!
+//! Version 2 references name every place the envelope is stored, and may
+//! record its digest and encoding:
+//!
+//! ```text
+//! '' [
+//!     "version": 2,
+//!     'id': <ARID>,
+//!     "size": <usize>,
+//!     "codec": "dcbor",
+//!     'dereferenceVia': "ipfs",
+//!     'dereferenceVia': "server" [ "url": <String> ],
+//!     'dereferenceVia': "s3" [ "url": "s3://<bucket>/<key>" ],
+//!     "digest": <Digest>
+//! ]
+//! ```
+//!
+//! A reference without `"version"` is version 1. Building and parsing the
+//! envelope needs an envelope library, so this module only fixes its
+//! vocabulary; a device that has one can match a reference by these values
+//! alone.
+
+/// Known value of the `'dereferenceVia'` predicate.
+pub const DEREFERENCE_VIA: u64 = 9;
+
+/// Known value of the `'id'` predicate, whose object is the ARID of the
+/// actual envelope in IPFS.
+pub const ID: u64 = 2;
+
+/// Object of `'dereferenceVia'` for envelopes stored in IPFS.
+pub const IPFS: &str = "ipfs";
+
+/// Object of `'dereferenceVia'` for envelopes stored on a Hubert server.
+pub const SERVER: &str = "server";
+
+/// Object of `'dereferenceVia'` for envelopes stored in an S3 bucket.
+pub const S3: &str = "s3";
+
+/// Predicate whose object is the base URL of the server holding the actual
+/// envelope.
+pub const URL: &str = "url";
+
+/// Predicate whose object is the size of the actual envelope in bytes.
+pub const SIZE: &str = "size";
+
+/// Predicate whose object is the reference format version; absent in
+/// version 1.
+pub const VERSION: &str = "version";
+
+/// The newest reference format version.
+pub const REFERENCE_VERSION: u64 = 2;
+
+/// Predicate whose object is the digest of the actual envelope.
+pub const DIGEST: &str = "digest";
+
+/// Predicate whose object names how the actual envelope is encoded.
+pub const CODEC: &str = "codec";
+
+/// The encoding of every envelope Hubert stores: tagged dCBOR. Version 1
+/// references imply it.
+pub const DEFAULT_CODEC: &str = "dcbor";
+
+/// Largest serialized envelope stored in the DHT directly; anything larger
+/// is replaced by a reference.
+pub const DEFAULT_DHT_SIZE_LIMIT: usize = 1000;
+
+/// The contents of a reference envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    /// Bytes of the ARID the actual envelope is stored at in IPFS.
+    pub arid: [u8; crate::ARID_SIZE],
+    /// Size of the actual envelope in bytes.
+    pub size: usize,
+}
+
+/// Whether an envelope of `size` serialized bytes is stored behind a
+/// reference rather than in the DHT, given the DHT's `limit`.
+pub fn needs_reference(size: usize, limit: usize) -> bool { size > limit }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_reference() {
+        assert!(!needs_reference(
+            DEFAULT_DHT_SIZE_LIMIT,
+            DEFAULT_DHT_SIZE_LIMIT
+        ));
+        assert!(needs_reference(
+            DEFAULT_DHT_SIZE_LIMIT + 1,
+            DEFAULT_DHT_SIZE_LIMIT
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+//! Installs a provider, so it runs in a process of its own: a derivation
+//! made before the install would fix the default in place.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hubert_core::{
+    CryptoProvider, DefaultCryptoProvider, crypto_provider,
+    derive_mainline_key, install_crypto_provider, obfuscate_with_arid,
+};
+
+/// Counts its calls and delegates to the default.
+struct CountingProvider(AtomicUsize);
+
+impl CryptoProvider for CountingProvider {
+    fn hkdf_sha256(
+        &self,
+        key_material: &[u8],
+        salt: &[u8],
+        info: &[u8],
+        output: &mut [u8],
+    ) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        DefaultCryptoProvider.hkdf_sha256(key_material, salt, info, output)
+    }
+
+    fn chacha20_apply(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8; 12],
+        data: &mut [u8],
+    ) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        DefaultCryptoProvider.chacha20_apply(key, nonce, data)
+    }
+}
+
+static COUNTING: CountingProvider = CountingProvider(AtomicUsize::new(0));
+static PROVIDER: &dyn CryptoProvider = &COUNTING;
+static DEFAULT: &dyn CryptoProvider = &DefaultCryptoProvider;
+
+#[test]
+fn test_installed_provider_is_used() {
+    assert!(install_crypto_provider(&PROVIDER).is_ok());
+    assert!(install_crypto_provider(&DEFAULT).is_err());
+    assert!(std::ptr::addr_eq(crypto_provider(), PROVIDER));
+
+    let arid = [7u8; 32];
+    assert_eq!(derive_mainline_key(&arid), derive_mainline_key(&arid));
+    let obfuscated = obfuscate_with_arid(&arid, b"data");
+    assert_eq!(obfuscate_with_arid(&arid, obfuscated), b"data");
+    assert!(COUNTING.0.load(Ordering::Relaxed) >= 4);
+}
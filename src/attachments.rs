@@ -0,0 +1,204 @@
+//! Supplementary payloads stored alongside an envelope.
+//!
+//! Coordination messages often refer to artifacts too large to carry inline,
+//! such as PSBTs or documents. An attachment is stored as its own entry at an
+//! ARID derived from its digest (see [`attachment_arid`]), and the message
+//! carries a locator assertion naming that ARID together with the
+//! attachment's digest and size:
+//!
+//! ```text
+//! "message" [
+//!     "hubertAttachment": '' [
+//!         'id': <ARID>,
+//!         "digest": <Digest>,
+//!         "size": <usize>
+//!     ]
+//! ]
+//! ```
+//!
+//! Because the location is content-addressed, storing the same payload twice
+//! stores it once. Fetching verifies each locator's ARID and each attachment
+//! against its digest, so a reader holding the message can trust
+//! attachments as much as the message itself. Store attachments in a
+//! backend with room for them, such as IPFS or hybrid.
+
+use bc_components::{ARID, Digest, DigestProvider};
+use bc_envelope::prelude::*;
+use bc_ur::prelude::*;
+
+use crate::{
+    Error, KvStore, Result,
+    wire::{Codec, stored_size},
+};
+
+/// Salt for deriving an attachment's ARID from its digest.
+const ARID_SALT: &[u8] = b"hubert-attachment-v1";
+
+const ATTACHMENT: &str = "hubertAttachment";
+const DIGEST: &str = "digest";
+const SIZE: &str = "size";
+
+/// Locator for an attachment stored at its own ARID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// Where the attachment is stored.
+    pub arid: ARID,
+    /// Digest of the attachment envelope, checked on fetch.
+    pub digest: Digest,
+    /// Size of the attachment's CBOR encoding in bytes.
+    pub size: usize,
+}
+
+impl Attachment {
+    fn to_envelope(&self) -> Envelope {
+        Envelope::unit()
+            .add_assertion(known_values::ID, self.arid)
+            .add_assertion(DIGEST, self.digest.clone())
+            .add_assertion(SIZE, self.size as u64)
+    }
+
+    fn from_envelope(envelope: &Envelope) -> Result<Self> {
+        let invalid =
+            |e: bc_envelope::Error| Error::InvalidAttachment(e.to_string());
+        Ok(Self {
+            arid: envelope
+                .extract_object_for_predicate(known_values::ID)
+                .map_err(invalid)?,
+            digest: envelope
+                .extract_object_for_predicate(DIGEST)
+                .map_err(invalid)?,
+            size: envelope
+                .extract_object_for_predicate::<u64>(SIZE)
+                .map_err(invalid)? as usize,
+        })
+    }
+}
+
+/// The ARID an attachment whose envelope has `digest` is stored at.
+pub fn attachment_arid(digest: &Digest) -> ARID {
+    let key: [u8; 32] = hubert_core::derive_key(ARID_SALT, digest.data(), 32)
+        .try_into()
+        .unwrap();
+    ARID::from_data(key)
+}
+
+/// Store `payload` at the ARID derived from its digest and return its
+/// locator.
+///
+/// If the payload is already stored there, it is checked and not stored
+/// again.
+pub async fn store_attachment(
+    store: &(impl KvStore + ?Sized),
+    payload: &Envelope,
+    ttl_seconds: Option<u64>,
+    verbose: bool,
+) -> Result<Attachment> {
+    let digest = payload.digest();
+    let attachment = Attachment {
+        arid: attachment_arid(&digest),
+        digest,
+        size: stored_size(payload, Codec::Dcbor),
+    };
+    match store
+        .put(&attachment.arid, payload, ttl_seconds, verbose)
+        .await
+    {
+        Ok(_) => {}
+        Err(Error::AlreadyExists { .. }) => {
+            fetch_attachment(store, &attachment, None, verbose).await?;
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(attachment)
+}
+
+/// Add a locator assertion for `attachment` to `envelope`.
+pub fn add_attachment(envelope: Envelope, attachment: &Attachment) -> Envelope {
+    envelope.add_assertion(ATTACHMENT, attachment.to_envelope())
+}
+
+/// The attachment locators carried by `envelope`.
+pub fn attachments(envelope: &Envelope) -> Result<Vec<Attachment>> {
+    envelope
+        .objects_for_predicate(ATTACHMENT)
+        .iter()
+        .map(Attachment::from_envelope)
+        .collect()
+}
+
+/// Fetch one attachment and verify it, and the ARID it was found at,
+/// against its digest.
+pub async fn fetch_attachment(
+    store: &(impl KvStore + ?Sized),
+    attachment: &Attachment,
+    timeout_seconds: Option<u64>,
+    verbose: bool,
+) -> Result<Envelope> {
+    if attachment.arid != attachment_arid(&attachment.digest) {
+        return Err(Error::InvalidAttachment(format!(
+            "{} is not the location of its digest",
+            attachment.arid.ur_string()
+        )));
+    }
+    let payload = store
+        .get(&attachment.arid, timeout_seconds, verbose)
+        .await?
+        .ok_or_else(|| Error::AttachmentNotFound {
+            arid: attachment.arid.ur_string(),
+        })?;
+    if payload.digest() != attachment.digest {
+        return Err(Error::AttachmentDigestMismatch {
+            arid: attachment.arid.ur_string(),
+        });
+    }
+    Ok(payload)
+}
+
+/// Fetch and verify every attachment carried by `envelope`, in order.
+pub async fn fetch_attachments(
+    store: &(impl KvStore + ?Sized),
+    envelope: &Envelope,
+    timeout_seconds: Option<u64>,
+    verbose: bool,
+) -> Result<Vec<Envelope>> {
+    let mut payloads = Vec::new();
+    for attachment in attachments(envelope)? {
+        payloads.push(
+            fetch_attachment(store, &attachment, timeout_seconds, verbose)
+                .await?,
+        );
+    }
+    Ok(payloads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locator_roundtrip() {
+        let payload = Envelope::new("A large document");
+        let attachment = Attachment {
+            arid: ARID::new(),
+            digest: payload.digest(),
+            size: payload.to_cbor_data().len(),
+        };
+
+        let message = add_attachment(Envelope::new("message"), &attachment);
+        assert_eq!(attachments(&message).unwrap(), vec![attachment]);
+    }
+
+    #[test]
+    fn test_arid_derived_from_digest() {
+        let digest = Envelope::new("A large document").digest();
+        assert_eq!(attachment_arid(&digest), attachment_arid(&digest));
+        let other = Envelope::new("Another document").digest();
+        assert_ne!(attachment_arid(&digest), attachment_arid(&other));
+        assert_ne!(attachment_arid(&digest).data(), digest.data());
+    }
+
+    #[test]
+    fn test_no_attachments() {
+        assert!(attachments(&Envelope::new("plain")).unwrap().is_empty());
+    }
+}
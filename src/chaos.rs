@@ -0,0 +1,214 @@
+//! Fault injection for testing how callers cope with unreliable backends.
+//!
+//! A [`ChaosKv`] wraps a real store and, at configured rates, delays
+//! operations, fails them before they reach the store, or lets them through
+//! and then drops the response. The last case is the one write-once callers
+//! must handle with care: the put took effect, so retrying it fails with
+//! [`Error::AlreadyExists`].
+
+use std::{sync::Mutex, time::Duration};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Receipt, Result,
+    StoreCapabilities, ValidationError,
+};
+
+/// A [`KvStore`] that injects delays and failures around an inner store.
+///
+/// Faults apply to `put`, `get`, and `exists`, and so also to the default
+/// `get_all`, `get_until`, and `put_until`. A new wrapper injects nothing
+/// until configured. Rolls come from a small generator seeded randomly, or
+/// with [`with_seed`](Self::with_seed) to replay a run.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use hubert::{MemoryKv, chaos::ChaosKv};
+///
+/// let store = ChaosKv::new(MemoryKv::new())
+///     .with_delay(Duration::from_millis(10), Duration::from_millis(200))
+///     .with_error_rate(0.1)
+///     .with_drop_rate(0.05)
+///     .with_seed(42);
+/// ```
+#[derive(Debug)]
+pub struct ChaosKv<S> {
+    inner: S,
+    delay: (Duration, Duration),
+    error_rate: f64,
+    drop_rate: f64,
+    state: Mutex<u64>,
+}
+
+impl<S: KvStore> ChaosKv<S> {
+    /// Wrap `inner` without injecting any faults yet.
+    pub fn new(inner: S) -> Self {
+        let mut seed = [0u8; 8];
+        bc_rand::fill_random_data(&mut seed);
+        Self {
+            inner,
+            delay: (Duration::ZERO, Duration::ZERO),
+            error_rate: 0.0,
+            drop_rate: 0.0,
+            state: Mutex::new(u64::from_le_bytes(seed)),
+        }
+    }
+
+    /// Delay every operation by a time drawn uniformly from `min..=max`.
+    pub fn with_delay(mut self, min: Duration, max: Duration) -> Self {
+        self.delay = (min, max.max(min));
+        self
+    }
+
+    /// Fail this fraction of operations with [`Error::Injected`] before they
+    /// reach the inner store.
+    pub fn with_error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Perform this fraction of operations on the inner store but report
+    /// [`Error::ResponseDropped`] instead of the result.
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Seed the rolls, so the same sequence of operations meets the same
+    /// faults.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.state = Mutex::new(seed);
+        self
+    }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S { &self.inner }
+
+    /// Unwrap the store.
+    pub fn into_inner(self) -> S { self.inner }
+
+    /// A roll in `0.0..1.0`, from SplitMix64.
+    fn roll(&self) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Run `operation` on the inner store with the configured faults.
+    async fn inject<T>(
+        &self,
+        name: &'static str,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let (min, max) = self.delay;
+        if !max.is_zero() {
+            tokio::time::sleep(min + (max - min).mul_f64(self.roll())).await;
+        }
+        if self.roll() < self.error_rate {
+            return Err(Error::Injected { operation: name });
+        }
+        let result = operation.await;
+        if self.roll() < self.drop_rate {
+            return Err(Error::ResponseDropped { operation: name });
+        }
+        result
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<S: KvStore> KvStore for ChaosKv<S> {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.inject("put", self.inner.put(arid, envelope, ttl_seconds, verbose))
+            .await
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.inject("get", self.inner.get(arid, timeout_seconds, verbose))
+            .await
+    }
+
+    async fn exists(&self, arid: &ARID) -> Result<bool> {
+        self.inject("exists", self.inner.exists(arid)).await
+    }
+
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        self.inject("delete", self.inner.delete(arid, verbose))
+            .await
+    }
+
+    async fn stat(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        self.inject("stat", self.inner.stat(arid, verbose)).await
+    }
+
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.inject(
+            "update",
+            self.inner.update(arid, envelope, ttl_seconds, verbose),
+        )
+        .await
+    }
+
+    fn capabilities(&self) -> StoreCapabilities { self.inner.capabilities() }
+
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        self.inner.validate(envelope)
+    }
+
+    async fn health(&self) -> HealthReport { self.inner.health().await }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryKv;
+
+    #[test]
+    fn test_rolls_are_seeded_and_in_range() {
+        let a = ChaosKv::new(MemoryKv::new()).with_seed(7);
+        let b = ChaosKv::new(MemoryKv::new()).with_seed(7);
+        let rolls: Vec<f64> = (0..1000).map(|_| a.roll()).collect();
+        assert!(rolls.iter().all(|r| (0.0..1.0).contains(r)));
+        assert!(
+            rolls
+                .iter()
+                .zip((0..1000).map(|_| b.roll()))
+                .all(|(x, y)| *x == y)
+        );
+
+        let mean = rolls.iter().sum::<f64>() / rolls.len() as f64;
+        assert!((0.45..0.55).contains(&mean), "mean {}", mean);
+    }
+}
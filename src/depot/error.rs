@@ -0,0 +1,18 @@
+/// Gordian Depot-specific errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Share of {size} bytes exceeds the depot limit of {limit} bytes")]
+    ShareTooLarge { size: usize, limit: usize },
+
+    #[error("Depot refused the request: {0}")]
+    Refused(String),
+
+    #[error("Sealed request or response error: {0}")]
+    Gstp(String),
+
+    #[error("Unexpected depot response: {0}")]
+    UnexpectedResponse(String),
+
+    #[error("Reqwest error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+}
@@ -0,0 +1,334 @@
+use std::time::{Duration, Instant};
+
+use bc_components::{
+    ARID, PrivateKeyBase, PrivateKeysProvider, PublicKeysProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::{GenesisMarkOptions, InceptionKeyOptions, XIDDocument};
+use gstp::{
+    SealedRequest, SealedRequestBehavior, SealedResponse,
+    SealedResponseBehavior,
+};
+use tokio::time::sleep;
+
+use super::error::Error as DepotError;
+use crate::{
+    Error, HealthReport, KvStore, Receipt, Result, StoreCapabilities, expiry,
+    logging::verbose_println,
+    receipt::Backend,
+    ur_input::parse_envelope_ur,
+    wire::{Codec, ObfuscatedPayload, stored_size},
+};
+
+/// The largest share a Gordian Depot stores, in bytes.
+pub const DEFAULT_MAX_SHARE_SIZE: usize = 1000;
+
+/// Salt for deriving the depot account that holds an ARID's entry.
+const ACCOUNT_SALT: &[u8] = b"hubert-depot-account-v1";
+
+/// How long a sealed request stays valid after it is made.
+const REQUEST_LIFETIME: Duration = Duration::from_secs(60);
+
+/// How often a get asks the depot again while waiting.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The keys of the depot account holding the entry at `arid` in deployment
+/// `domain`.
+///
+/// A depot files shares under the account that stored them, behind
+/// receipts it issues, rather than under identifiers a client chooses. So
+/// each ARID gets an account of its own, with keys derived from the ARID:
+/// everyone who holds the ARID reaches the same account, and the depot
+/// sees only its public keys.
+pub fn account_keys(domain: &str, arid: &ARID) -> PrivateKeyBase {
+    let salt = hubert_core::domain_salt(ACCOUNT_SALT, domain);
+    PrivateKeyBase::from_data(crate::arid_derivation::derive_key(
+        &salt, arid, 32,
+    ))
+}
+
+/// A [`KvStore`] backed by a Gordian Depot server, for deployments that
+/// already run one.
+///
+/// Each entry lives alone in its own depot account (see
+/// [`account_keys`]), as one share holding the obfuscated envelope. Puts
+/// call the depot's `storeShare`, gets poll `getShares`, and deletes call
+/// `deleteShares`, each as a GSTP request sealed to the depot and signed
+/// with the account's keys.
+///
+/// - Write-once semantics: a put fails if the account already holds a
+///   share
+/// - Maximum value size: 1000 bytes by default, the depot's share limit
+/// - No TTLs: the depot keeps shares until they are deleted
+///
+/// # Example
+///
+/// ```no_run
+/// use bc_components::ARID;
+/// use bc_envelope::Envelope;
+/// use bc_xid::XIDDocument;
+/// use hubert::{KvStore, depot::DepotKv};
+///
+/// # async fn example(depot: XIDDocument) {
+/// // The depot's XID document, as it publishes it
+/// let store = DepotKv::new("https://depot.example.com", depot);
+/// let arid = ARID::new();
+/// store
+///     .put(&arid, &Envelope::new("Hello, Depot!"), None, false)
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct DepotKv {
+    url: String,
+    depot: XIDDocument,
+    client: reqwest::Client,
+    domain: String,
+    max_share_size: usize,
+}
+
+impl DepotKv {
+    /// A store for the depot at `url` whose identity is `depot`.
+    pub fn new(url: &str, depot: XIDDocument) -> Self {
+        Self {
+            url: url.to_string(),
+            depot,
+            client: reqwest::Client::new(),
+            domain: String::new(),
+            max_share_size: DEFAULT_MAX_SHARE_SIZE,
+        }
+    }
+
+    /// Derive accounts and obfuscate entries in deployment `domain`,
+    /// isolating them from other deployments sharing the depot.
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = domain.into();
+        self
+    }
+
+    /// Accept shares of up to `size` bytes, for depots configured with a
+    /// limit other than the default.
+    pub fn with_max_share_size(mut self, size: usize) -> Self {
+        self.max_share_size = size;
+        self
+    }
+
+    /// The base URL of the depot this client talks to.
+    pub fn url(&self) -> &str { &self.url }
+
+    /// Send `request` from the account `keys`, returning the result of the
+    /// depot's response.
+    async fn call(
+        &self,
+        keys: &PrivateKeyBase,
+        request: SealedRequest,
+    ) -> Result<Envelope> {
+        let id = request.id();
+        let valid_until = Date::with_duration_from_now(REQUEST_LIFETIME);
+        let sealed = request
+            .to_envelope_for_recipients(
+                Some(&valid_until),
+                Some(&keys.private_keys()),
+                &[&self.depot],
+            )
+            .map_err(|e| DepotError::Gstp(e.to_string()))?;
+
+        let response = self
+            .client
+            .post(&self.url)
+            .body(sealed.ur_string())
+            .send()
+            .await
+            .map_err(DepotError::from)?;
+        let status = response.status();
+        let text = response.text().await.map_err(DepotError::from)?;
+        if !status.is_success() {
+            return Err(DepotError::Refused(text).into());
+        }
+
+        let envelope = parse_envelope_ur(text.trim())?;
+        let response = SealedResponse::try_from_encrypted_envelope(
+            &envelope,
+            Some(id),
+            None,
+            &keys.private_keys(),
+        )
+        .map_err(|e| DepotError::Gstp(e.to_string()))?;
+        match response.result() {
+            Ok(result) => Ok(result.clone()),
+            Err(_) => {
+                let reason = response
+                    .error()
+                    .map(|error| error.format_flat())
+                    .unwrap_or_default();
+                Err(DepotError::Refused(reason).into())
+            }
+        }
+    }
+
+    /// A request for `function` from the account `keys`.
+    fn request(&self, keys: &PrivateKeyBase, function: &str) -> SealedRequest {
+        let sender = XIDDocument::new(
+            InceptionKeyOptions::PublicKeys(keys.public_keys()),
+            GenesisMarkOptions::None,
+        );
+        SealedRequest::new(function, ARID::new(), sender)
+    }
+
+    /// The shares the account `keys` holds, which is at most the one
+    /// envelope a put stored.
+    async fn shares(&self, keys: &PrivateKeyBase) -> Result<Vec<Vec<u8>>> {
+        let result = self.call(keys, self.request(keys, "getShares")).await?;
+        // Each assertion maps a receipt to the share's data
+        result
+            .assertions()
+            .iter()
+            .map(|assertion| {
+                let data: ByteString = assertion
+                    .as_object()
+                    .ok_or_else(|| {
+                        DepotError::UnexpectedResponse(result.format_flat())
+                    })?
+                    .extract_subject()?;
+                Ok(data.into())
+            })
+            .collect()
+    }
+
+    async fn get_impl(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        let keys = account_keys(&self.domain, arid);
+        loop {
+            if let Some(data) = self.shares(&keys).await?.into_iter().next() {
+                let envelope = ObfuscatedPayload::from_bytes(data)
+                    .decode_in(&self.domain, arid)?;
+                expiry::log_if_expired(&envelope, verbose);
+                return Ok(Some(envelope));
+            }
+            if Instant::now() >= deadline {
+                if verbose {
+                    verbose_println("Timeout reached, share not found");
+                }
+                return Ok(None);
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            sleep(POLL_INTERVAL.min(left)).await;
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl KvStore for DepotKv {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        _ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let payload =
+            ObfuscatedPayload::encode_in(&self.domain, arid, envelope);
+        if payload.len() > self.max_share_size {
+            return Err(DepotError::ShareTooLarge {
+                size: payload.len(),
+                limit: self.max_share_size,
+            }
+            .into());
+        }
+
+        let keys = account_keys(&self.domain, arid);
+        if !self.shares(&keys).await?.is_empty() {
+            return Err(Error::AlreadyExists { arid: arid.ur_string() });
+        }
+        let request = self
+            .request(&keys, "storeShare")
+            .with_parameter("data", ByteString::from(payload.into_bytes()));
+        self.call(&keys, request).await?;
+
+        if verbose {
+            verbose_println(&format!("PUT {} OK (Depot)", arid.ur_string()));
+        }
+        Ok(Receipt::new(
+            Backend::Depot,
+            *arid,
+            self.url.clone(),
+            stored_size(envelope, Codec::Dcbor),
+        ))
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        let timeout = Duration::from_secs(timeout_seconds.unwrap_or(30));
+        self.get_impl(arid, Instant::now() + timeout, verbose).await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.get_impl(arid, deadline, verbose).await
+    }
+
+    async fn exists(&self, arid: &ARID) -> Result<bool> {
+        let keys = account_keys(&self.domain, arid);
+        Ok(!self.shares(&keys).await?.is_empty())
+    }
+
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        let keys = account_keys(&self.domain, arid);
+        if self.shares(&keys).await?.is_empty() {
+            return Ok(false);
+        }
+        // Without receipts, the depot deletes every share of the account
+        self.call(&keys, self.request(&keys, "deleteShares")).await?;
+        if verbose {
+            verbose_println(&format!("DELETE {} OK (Depot)", arid.ur_string()));
+        }
+        Ok(true)
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            max_value_size: Some(self.max_share_size),
+            delete: true,
+            ..Default::default()
+        }
+    }
+
+    async fn health(&self) -> HealthReport {
+        HealthReport::probe("depot", async {
+            // Any account will do; a fresh one holds no shares
+            let keys = account_keys(&self.domain, &ARID::new());
+            self.shares(&keys).await?;
+            Ok(None)
+        })
+        .await
+        .with_max_value_size(self.max_share_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_keys_are_per_arid_and_domain() {
+        let arid = ARID::new();
+        let keys = |domain: &str, arid: &ARID| {
+            account_keys(domain, arid).public_keys()
+        };
+        assert_eq!(keys("", &arid), keys("", &arid));
+        assert_ne!(keys("", &arid), keys("", &ARID::new()));
+        assert_ne!(keys("", &arid), keys("staging", &arid));
+    }
+}
@@ -0,0 +1,5 @@
+mod error;
+mod kv;
+
+pub use error::Error;
+pub use kv::{DEFAULT_MAX_SHARE_SIZE, DepotKv, account_keys};
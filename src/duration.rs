@@ -0,0 +1,120 @@
+//! Durations written for people, such as `90m` or `2h30m`, for the TTLs and
+//! timeouts given on command lines, in configuration files, and in
+//! environment variables.
+//!
+//! A duration is a bare number of seconds, or one or more numbers each
+//! followed by a unit: `s`, `m`, `h`, `d`, or `w`.
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+/// Parse a duration such as `90`, `90s`, `15m`, `2h30m`, or `1d12h` into
+/// seconds.
+///
+/// ```
+/// # use hubert::duration::parse_duration;
+/// assert_eq!(parse_duration("90"), Ok(90));
+/// assert_eq!(parse_duration("2h30m"), Ok(9000));
+/// assert!(parse_duration("2x").is_err());
+/// ```
+pub fn parse_duration(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let invalid = || {
+        format!(
+            "invalid duration {:?}: use seconds, or numbers followed by s, m, \
+             h, d, or w, such as 90m or 2h30m",
+            text
+        )
+    };
+    if text.is_empty() {
+        return Err(invalid());
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text.parse().map_err(|_| invalid());
+    }
+
+    let mut seconds: u64 = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let (number, tail) = rest.split_at(digits);
+        let mut chars = tail.chars();
+        let scale: u64 = match chars.next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 3600,
+            Some('d') => 86400,
+            Some('w') => 604800,
+            _ => return Err(invalid()),
+        };
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        seconds = number
+            .checked_mul(scale)
+            .and_then(|n| seconds.checked_add(n))
+            .ok_or_else(invalid)?;
+        rest = chars.as_str();
+    }
+    Ok(seconds)
+}
+
+/// A duration in a configuration file: seconds as a number, or a string
+/// for [`parse_duration`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Written {
+    Seconds(u64),
+    Text(String),
+}
+
+impl Written {
+    fn seconds(self) -> Result<u64, String> {
+        match self {
+            Self::Seconds(seconds) => Ok(seconds),
+            Self::Text(text) => parse_duration(&text),
+        }
+    }
+}
+
+/// Deserialize a duration in seconds written as a number or a string such
+/// as `"90m"`, for `#[serde(deserialize_with = "...")]`.
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    Written::deserialize(deserializer)?
+        .seconds()
+        .map_err(D::Error::custom)
+}
+
+/// [`deserialize`] for an optional duration.
+pub fn deserialize_option<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<Written>::deserialize(deserializer)?
+        .map(Written::seconds)
+        .transpose()
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration(" 90s "), Ok(90));
+        assert_eq!(parse_duration("15m"), Ok(900));
+        assert_eq!(parse_duration("1h"), Ok(3600));
+        assert_eq!(parse_duration("2d"), Ok(172800));
+        assert_eq!(parse_duration("1w"), Ok(604800));
+        assert_eq!(parse_duration("2h30m"), Ok(9000));
+        assert_eq!(parse_duration("1d12h30m15s"), Ok(131415));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("2h30").is_err());
+        assert!(parse_duration("1y").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("99999999999999999999w").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! What a store knows about an entry without fetching it, returned by
+//! [`KvStore::stat`].
+//!
+//! [`KvStore::stat`]: crate::KvStore::stat
+
+use bc_components::ARID;
+
+use crate::{receipt::Backend, server::ListedEntry};
+
+/// Metadata about a stored entry: where it is, how large it is, and how
+/// long it will remain available.
+///
+/// Times are UNIX seconds. A coordinator can compare
+/// [`ttl_remaining`](Self::ttl_remaining) with how long a protocol round
+/// still needs, and republish the envelope under a new ARID if it will
+/// expire first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryMeta {
+    pub backend: Backend,
+    /// The ARID the entry is stored at.
+    pub arid: ARID,
+    /// Where the entry lives, as in the [`Receipt`](crate::Receipt) of the
+    /// put that stored it.
+    pub location: String,
+    /// Bytes in the stored envelope's dCBOR.
+    pub size: usize,
+    /// When the entry was stored, if the store recorded it.
+    pub created_at: Option<u64>,
+    /// When the entry expires, or `None` if it is kept until removed.
+    pub expires_at: Option<u64>,
+    /// Seconds until the entry expires, by this machine's clock, or `None`
+    /// if it is kept until removed.
+    pub ttl_remaining: Option<u64>,
+}
+
+impl EntryMeta {
+    /// The metadata of `entry`, held by `backend` in `location`, with the
+    /// TTL remaining as of `now`.
+    pub(crate) fn from_listed(
+        backend: Backend,
+        location: impl Into<String>,
+        entry: ListedEntry,
+        now: u64,
+    ) -> Self {
+        Self {
+            backend,
+            arid: entry.arid,
+            location: location.into(),
+            size: entry.size,
+            created_at: entry.stored_at,
+            expires_at: entry.expires_at,
+            ttl_remaining: entry
+                .expires_at
+                .map(|expires_at| expires_at.saturating_sub(now)),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 /// Top-level error type for the hubert library.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -11,6 +13,101 @@ pub enum Error {
     #[error("Invalid ARID format")]
     InvalidArid,
 
+    #[error("Invalid envelope format")]
+    InvalidEnvelope,
+
+    #[error("Envelope expired at {expires_at} (UNIX time)")]
+    EnvelopeExpired { expires_at: u64 },
+
+    // Attachment errors
+    #[error("Invalid attachment locator: {0}")]
+    InvalidAttachment(String),
+
+    #[error("Attachment {arid} not found")]
+    AttachmentNotFound { arid: String },
+
+    #[error("Attachment {arid} does not match its digest")]
+    AttachmentDigestMismatch { arid: String },
+
+    // Envelope filter errors
+    #[error("Envelope does not declare expected type '{expected}'")]
+    UnexpectedType { expected: String },
+
+    #[error("Unknown known value: {0}")]
+    UnknownKnownValue(String),
+
+    #[error("Store is read-only")]
+    ReadOnly,
+
+    #[error("Store does not support {operation}")]
+    Unsupported { operation: &'static str },
+
+    #[error("No shards configured")]
+    NoShards,
+
+    // Threshold custody errors
+    #[error(
+        "A threshold of {threshold} shares cannot be kept by {custodians} \
+         custodians (1 to 16, and at least the threshold)"
+    )]
+    InvalidThreshold { threshold: usize, custodians: usize },
+
+    #[error(
+        "{succeeded} of {needed} shares {action}: {}",
+        crate::threshold::describe_failures(.failures)
+    )]
+    ThresholdNotMet {
+        action: &'static str,
+        succeeded: usize,
+        needed: usize,
+        failures: Vec<crate::threshold::ShareFailure>,
+    },
+
+    #[error("Session has expired")]
+    SessionExpired,
+
+    #[error("Deadline passed before the operation finished")]
+    DeadlineExceeded,
+
+    #[error("Store worker thread has stopped")]
+    WorkerStopped,
+
+    // Fault injection errors
+    #[error("Injected failure in {operation}")]
+    Injected { operation: &'static str },
+
+    #[error("Response to {operation} was dropped")]
+    ResponseDropped { operation: &'static str },
+
+    // Protocol driver errors
+    #[error("Protocol is complete")]
+    ProtocolComplete,
+
+    #[error("Round '{round}' is written by {writer}")]
+    NotYourTurn { round: String, writer: String },
+
+    #[error("Invalid protocol state: {0}")]
+    InvalidProtocolState(String),
+
+    // Input validation errors
+    #[error(
+        "ur:{ur_type} input of {length} bytes exceeds limit of {limit} bytes"
+    )]
+    InputTooLong { ur_type: &'static str, length: usize, limit: usize },
+
+    #[error("Invalid ur:{ur_type} input: {reason}")]
+    InvalidUrInput { ur_type: &'static str, reason: String },
+
+    // Name resolution errors
+    #[error("Invalid name {0}")]
+    InvalidName(String),
+
+    #[error("{name} is not published")]
+    NameNotFound { name: String },
+
+    #[error("Could not resolve {0}")]
+    NameResolution(String),
+
     // Dependency errors
     #[error("Envelope error: {0}")]
     Envelope(#[from] bc_envelope::Error),
@@ -19,21 +116,208 @@ pub enum Error {
     Cbor(#[from] dcbor::Error),
 
     // Storage layer-specific errors
+    #[cfg(feature = "mainline")]
     #[error("Mainline DHT error: {0}")]
     Mainline(#[from] crate::mainline::Error),
 
+    #[cfg(feature = "ipfs")]
     #[error("IPFS error: {0}")]
     Ipfs(#[from] crate::ipfs::Error),
 
     #[error("Server error: {0}")]
     Server(#[from] crate::server::Error),
 
+    #[cfg(feature = "hybrid")]
     #[error("Hybrid error: {0}")]
     Hybrid(#[from] crate::hybrid::Error),
 
+    #[cfg(feature = "depot")]
+    #[error("Depot error: {0}")]
+    Depot(#[from] crate::depot::Error),
+
+    #[error("Could not determine the home directory for default paths")]
+    NoHomeDirectory,
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
 
+/// What went wrong, whichever backend reported it, so callers can react to
+/// the common failures of every [`KvStore`](crate::KvStore) without
+/// matching each storage layer's errors.
+///
+/// ```
+/// # use hubert::{Error, ErrorCategory, mainline};
+/// let error: Error =
+///     mainline::Error::ValueTooLarge { size: 2000, limit: 1000 }.into();
+/// assert_eq!(error.category(), ErrorCategory::TooLarge);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The ARID is already taken; write-once stores refuse a second put.
+    AlreadyExists,
+    /// Nothing is stored at the ARID or name, or the entry has expired.
+    NotFound,
+    /// The envelope or request is larger than the store accepts.
+    TooLarge,
+    /// The operation did not finish in time.
+    Timeout,
+    /// The backend could not be reached or did not answer.
+    Unavailable,
+    /// Any other failure.
+    Other,
+}
+
+impl Error {
+    /// The [`ErrorCategory`] of this error, looking into the errors of the
+    /// storage layers.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::AlreadyExists { .. } => ErrorCategory::AlreadyExists,
+            Self::NotFound
+            | Self::AttachmentNotFound { .. }
+            | Self::NameNotFound { .. } => ErrorCategory::NotFound,
+            Self::InputTooLong { .. } => ErrorCategory::TooLarge,
+            Self::DeadlineExceeded => ErrorCategory::Timeout,
+            Self::NameResolution(_)
+            | Self::ThresholdNotMet { .. }
+            | Self::Injected { .. }
+            | Self::ResponseDropped { .. }
+            | Self::WorkerStopped => ErrorCategory::Unavailable,
+            #[cfg(feature = "mainline")]
+            Self::Mainline(e) => {
+                use crate::mainline::Error as MainlineError;
+                match e {
+                    MainlineError::ValueTooLarge { .. } => {
+                        ErrorCategory::TooLarge
+                    }
+                    MainlineError::PutNotVerified { .. } => {
+                        ErrorCategory::Timeout
+                    }
+                    MainlineError::DhtError(_) | MainlineError::Io(_) => {
+                        ErrorCategory::Unavailable
+                    }
+                    _ => ErrorCategory::Other,
+                }
+            }
+            #[cfg(feature = "ipfs")]
+            Self::Ipfs(e) => {
+                use crate::ipfs::Error as IpfsError;
+                match e {
+                    IpfsError::EnvelopeTooLarge { .. } => {
+                        ErrorCategory::TooLarge
+                    }
+                    IpfsError::Timeout => ErrorCategory::Timeout,
+                    IpfsError::DaemonError(_) => ErrorCategory::Unavailable,
+                    _ => ErrorCategory::Other,
+                }
+            }
+            Self::Server(e) => {
+                use crate::server::Error as ServerError;
+                match e {
+                    ServerError::Gone(_) => ErrorCategory::NotFound,
+                    ServerError::PayloadTooLarge { .. } => {
+                        ErrorCategory::TooLarge
+                    }
+                    ServerError::NetworkError(_) => ErrorCategory::Unavailable,
+                    #[cfg(feature = "server-client")]
+                    ServerError::ReqwestError(e) if e.is_timeout() => {
+                        ErrorCategory::Timeout
+                    }
+                    #[cfg(feature = "server-client")]
+                    ServerError::ReqwestError(e) if e.is_connect() => {
+                        ErrorCategory::Unavailable
+                    }
+                    _ => ErrorCategory::Other,
+                }
+            }
+            #[cfg(feature = "hybrid")]
+            Self::Hybrid(crate::hybrid::Error::ContentNotFound) => {
+                ErrorCategory::NotFound
+            }
+            #[cfg(feature = "depot")]
+            Self::Depot(e) => {
+                use crate::depot::Error as DepotError;
+                match e {
+                    DepotError::ShareTooLarge { .. } => ErrorCategory::TooLarge,
+                    DepotError::Reqwest(e) if e.is_timeout() => {
+                        ErrorCategory::Timeout
+                    }
+                    DepotError::Reqwest(_) => ErrorCategory::Unavailable,
+                    _ => ErrorCategory::Other,
+                }
+            }
+            _ => ErrorCategory::Other,
+        }
+    }
+
+    /// A stable, machine-readable name for the kind of error, such as
+    /// `already_exists`. Errors from a storage layer are named after the
+    /// layer.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::AlreadyExists { .. } => "already_exists",
+            Self::NotFound => "not_found",
+            Self::InvalidArid => "invalid_arid",
+            Self::InvalidEnvelope => "invalid_envelope",
+            Self::EnvelopeExpired { .. } => "envelope_expired",
+            Self::InvalidAttachment(_) => "invalid_attachment",
+            Self::AttachmentNotFound { .. } => "attachment_not_found",
+            Self::AttachmentDigestMismatch { .. } => {
+                "attachment_digest_mismatch"
+            }
+            Self::UnexpectedType { .. } => "unexpected_type",
+            Self::UnknownKnownValue(_) => "unknown_known_value",
+            Self::ReadOnly => "read_only",
+            Self::Unsupported { .. } => "unsupported",
+            Self::NoShards => "no_shards",
+            Self::InvalidThreshold { .. } => "invalid_threshold",
+            Self::ThresholdNotMet { .. } => "threshold_not_met",
+            Self::SessionExpired => "session_expired",
+            Self::DeadlineExceeded => "deadline_exceeded",
+            Self::WorkerStopped => "worker_stopped",
+            Self::Injected { .. } => "injected",
+            Self::ResponseDropped { .. } => "response_dropped",
+            Self::ProtocolComplete => "protocol_complete",
+            Self::NotYourTurn { .. } => "not_your_turn",
+            Self::InvalidProtocolState(_) => "invalid_protocol_state",
+            Self::InputTooLong { .. } => "input_too_long",
+            Self::InvalidUrInput { .. } => "invalid_ur_input",
+            Self::InvalidName(_) => "invalid_name",
+            Self::NameNotFound { .. } => "name_not_found",
+            Self::NameResolution(_) => "name_resolution",
+            Self::Envelope(_) => "envelope",
+            Self::Cbor(_) => "cbor",
+            #[cfg(feature = "mainline")]
+            Self::Mainline(_) => "mainline",
+            #[cfg(feature = "ipfs")]
+            Self::Ipfs(_) => "ipfs",
+            Self::Server(_) => "server",
+            #[cfg(feature = "hybrid")]
+            Self::Hybrid(_) => "hybrid",
+            #[cfg(feature = "depot")]
+            Self::Depot(_) => "depot",
+            Self::NoHomeDirectory => "no_home_directory",
+            Self::Io(_) => "io",
+        }
+    }
+}
+
+/// An [`Error`] in a form that can be serialized, for reporting errors as
+/// JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// The error's [`kind`](Error::kind).
+    pub kind: String,
+    /// The error's message.
+    pub message: String,
+}
+
+impl From<&Error> for ErrorReport {
+    fn from(error: &Error) -> Self {
+        Self { kind: error.kind().to_string(), message: error.to_string() }
+    }
+}
+
 /// Result type using the top-level Error.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,148 @@
+//! The `expiry` assertion: an envelope's own statement of when it stops
+//! being meaningful.
+//!
+//! ```text
+//! "message" [
+//!     "expiry": Date
+//! ]
+//! ```
+//!
+//! A put of an envelope carrying one with no `ttl_seconds` stores it until
+//! then, on the backends that support TTLs. The Mainline DHT and IPFS keep
+//! entries for as long as the network does, so a get from them may return
+//! an envelope whose expiry has passed; check [`is_expired`] before acting
+//! on one.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use dcbor::Date;
+
+use crate::{Error, Result, logging::verbose_println};
+
+/// Predicate of the expiry assertion.
+pub const EXPIRY: &str = "expiry";
+
+/// Add an assertion that `envelope` expires at `expires_at`, in UNIX
+/// seconds.
+///
+/// ```
+/// # use bc_envelope::prelude::*;
+/// # use hubert::expiry::{add_expiry, expiry};
+/// let envelope = add_expiry(Envelope::new("Offer"), 1_760_000_000);
+/// assert_eq!(expiry(&envelope), Some(1_760_000_000));
+/// ```
+pub fn add_expiry(envelope: Envelope, expires_at: u64) -> Envelope {
+    envelope.add_assertion(EXPIRY, Date::from_timestamp(expires_at as f64))
+}
+
+/// When `envelope` says it expires, in UNIX seconds, or `None` if it
+/// carries no well-formed expiry assertion.
+pub fn expiry(envelope: &Envelope) -> Option<u64> {
+    let object = envelope.objects_for_predicate(EXPIRY).into_iter().next()?;
+    let date: Date = object.extract_subject().ok()?;
+    Some(date.timestamp().max(0.0) as u64)
+}
+
+/// Whether `envelope` carries an expiry that has passed.
+pub fn is_expired(envelope: &Envelope) -> bool {
+    expiry(envelope).is_some_and(|expires_at| expires_at <= unix_now())
+}
+
+/// The TTL for a put of `envelope`: `ttl_seconds` if given, otherwise the
+/// time left until the envelope's expiry, if it has one.
+///
+/// Fails with [`Error::EnvelopeExpired`] if the TTL would come from an
+/// expiry that has already passed.
+pub fn ttl_for(
+    envelope: &Envelope,
+    ttl_seconds: Option<u64>,
+) -> Result<Option<u64>> {
+    if ttl_seconds.is_some() {
+        return Ok(ttl_seconds);
+    }
+    let now = unix_now();
+    match expiry(envelope) {
+        Some(expires_at) if expires_at <= now => {
+            Err(Error::EnvelopeExpired { expires_at })
+        }
+        Some(expires_at) => Ok(Some(expires_at - now)),
+        None => Ok(None),
+    }
+}
+
+/// The TTL for each entry of a batch put, as [`ttl_for`] gives it, failing
+/// if any entry has expired.
+pub(crate) fn ttls_for(
+    entries: &[(ARID, Envelope)],
+    ttl_seconds: Option<u64>,
+) -> Result<Vec<Option<u64>>> {
+    entries
+        .iter()
+        .map(|(_, envelope)| ttl_for(envelope, ttl_seconds))
+        .collect()
+}
+
+/// Log, when `verbose`, that `envelope` was retrieved after its expiry,
+/// for backends that keep entries regardless.
+pub(crate) fn log_if_expired(envelope: &Envelope, verbose: bool) {
+    if verbose
+        && let Some(expires_at) = expiry(envelope)
+        && expires_at <= unix_now()
+    {
+        verbose_println(&format!(
+            "Warning: envelope expired at {} (UNIX time)",
+            expires_at
+        ));
+    }
+}
+
+/// Current time in UNIX seconds.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KvStore, MemoryKv};
+
+    #[test]
+    fn test_ttl_for() {
+        let now = unix_now();
+        let plain = Envelope::new("Plain");
+        assert_eq!(ttl_for(&plain, None).unwrap(), None);
+        assert_eq!(ttl_for(&plain, Some(60)).unwrap(), Some(60));
+
+        let later = add_expiry(Envelope::new("Later"), now + 600);
+        assert!(!is_expired(&later));
+        let ttl = ttl_for(&later, None).unwrap().unwrap();
+        assert!((599..=600).contains(&ttl), "{}", ttl);
+        assert_eq!(ttl_for(&later, Some(60)).unwrap(), Some(60));
+
+        let past = add_expiry(Envelope::new("Past"), now - 1);
+        assert!(is_expired(&past));
+        assert!(matches!(
+            ttl_for(&past, None),
+            Err(Error::EnvelopeExpired { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_derives_ttl_from_expiry() {
+        let store = MemoryKv::new();
+        let now = unix_now();
+        let envelope = add_expiry(Envelope::new("Offer"), now + 600);
+        let receipt = store.put(&ARID::new(), &envelope, None, false).await;
+        let ttl = receipt.unwrap().ttl.unwrap();
+        assert!((599..=600).contains(&ttl), "{}", ttl);
+
+        let past = add_expiry(Envelope::new("Stale offer"), now - 60);
+        let result = store.put(&ARID::new(), &past, None, false).await;
+        assert!(matches!(result, Err(Error::EnvelopeExpired { .. })));
+    }
+}
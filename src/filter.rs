@@ -0,0 +1,79 @@
+//! Checks on retrieved envelopes before they reach downstream parsers.
+//!
+//! Anyone holding an ARID can publish at it first, so a reader expecting a
+//! particular kind of message should confirm that the envelope it retrieved
+//! declares that kind. These helpers check the envelope's `'isA'` type
+//! assertions against expected known values.
+
+use bc_envelope::prelude::*;
+use known_values::{KNOWN_VALUES, KnownValue};
+
+use crate::{Error, Result};
+
+/// Parse a known value given by name (`Seed` or `'Seed'`) or by number
+/// (`200`).
+pub fn parse_known_value(s: &str) -> Result<KnownValue> {
+    let s = s.trim();
+    if let Ok(value) = s.parse::<u64>() {
+        return Ok(KnownValue::new(value));
+    }
+    let name = s.trim_matches('\'');
+    let binding = KNOWN_VALUES.get();
+    binding
+        .as_ref()
+        .and_then(|store| store.known_value_named(name))
+        .cloned()
+        .ok_or_else(|| Error::UnknownKnownValue(s.to_string()))
+}
+
+/// Whether `envelope` declares every type in `expected`.
+pub fn has_types(envelope: &Envelope, expected: &[KnownValue]) -> bool {
+    expected.iter().all(|t| envelope.has_type_value(t))
+}
+
+/// Fail with [`Error::UnexpectedType`] unless `envelope` declares every type
+/// in `expected`.
+///
+/// Only the envelope's own assertions are checked; types inside a wrapped
+/// or encrypted subject are not visible here.
+pub fn check_types(envelope: &Envelope, expected: &[KnownValue]) -> Result<()> {
+    match expected.iter().find(|t| !envelope.has_type_value(t)) {
+        Some(missing) => {
+            Err(Error::UnexpectedType { expected: missing.name() })
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_known_value() {
+        let seed = known_values::SEED_TYPE;
+        assert_eq!(parse_known_value("200").unwrap(), KnownValue::new(200));
+        assert_eq!(parse_known_value(&seed.name()).unwrap(), seed);
+        assert_eq!(
+            parse_known_value(&format!("'{}'", seed.name())).unwrap(),
+            seed
+        );
+        assert!(parse_known_value("no-such-value").is_err());
+    }
+
+    #[test]
+    fn test_check_types() {
+        let seed = Envelope::new("data").add_type(known_values::SEED_TYPE);
+        let plain = Envelope::new("data");
+        let expected = [known_values::SEED_TYPE];
+
+        assert!(has_types(&seed, &expected));
+        assert!(check_types(&seed, &expected).is_ok());
+        assert!(!has_types(&plain, &expected));
+        assert!(matches!(
+            check_types(&plain, &expected),
+            Err(Error::UnexpectedType { .. })
+        ));
+        assert!(check_types(&plain, &[]).is_ok());
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use super::HybridKv;
+use crate::{
+    Result,
+    ipfs::{IpfsConfig, IpfsKv},
+    mainline::{MainlineConfig, MainlineDhtKv},
+    server::ServerKvClient,
+};
+
+/// Every setting of a [`HybridKv`], for building one from a configuration
+/// file with [`HybridKv::with_config`].
+///
+/// The two layers are configured in their own tables. Every field is
+/// optional in a file; missing fields take their default values.
+///
+/// ```toml
+/// dht_size_limit = 800
+/// fallback_server = "https://hubert.example"
+///
+/// [dht]
+/// port = 6881
+///
+/// [ipfs]
+/// rpc_url = "http://127.0.0.1:5001"
+/// pin_content = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HybridConfig {
+    /// Settings for the DHT that holds small envelopes and references.
+    pub dht: MainlineConfig,
+    /// Settings for the IPFS node that holds large envelopes.
+    pub ipfs: IpfsConfig,
+    /// Largest serialized envelope stored directly in the DHT (default: the
+    /// DHT's `max_value_size`). Larger ones go to IPFS.
+    pub dht_size_limit: Option<usize>,
+    /// Base URL of a Hubert server to store large envelopes on while IPFS
+    /// is down (default: none).
+    pub fallback_server: Option<String>,
+}
+
+impl HybridKv {
+    /// Start a store with every setting taken from `config`.
+    pub async fn with_config(config: HybridConfig) -> Result<Self> {
+        let dht = MainlineDhtKv::with_config(config.dht).await?;
+        let mut store =
+            Self::from_stores(dht, IpfsKv::with_config(config.ipfs));
+        if let Some(limit) = config.dht_size_limit {
+            store = store.with_dht_size_limit(limit);
+        }
+        if let Some(url) = config.fallback_server {
+            store = store.with_fallback_server(ServerKvClient::new(&url));
+        }
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_in_tables() {
+        let config: HybridConfig = toml::from_str(
+            "dht_size_limit = 800\nfallback_server = \"https://hubert.example\"\n\
+             [dht]\nport = 6881\n[ipfs]\npin_content = true",
+        )
+        .unwrap();
+        assert_eq!(config.dht_size_limit, Some(800));
+        assert_eq!(
+            config.fallback_server.as_deref(),
+            Some("https://hubert.example")
+        );
+        assert_eq!(config.dht.port, Some(6881));
+        assert!(config.ipfs.pin_content);
+        assert_eq!(config.ipfs.rpc_url, IpfsConfig::default().rpc_url);
+    }
+}
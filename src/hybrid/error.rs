@@ -1,7 +1,7 @@
 /// Hybrid-specific errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Referenced IPFS content not found")]
+    #[error("Referenced content not found")]
     ContentNotFound,
 
     #[error("Not a reference envelope")]
@@ -12,4 +12,27 @@ pub enum Error {
 
     #[error("No id assertion found in reference envelope")]
     NoIdAssertion,
+
+    #[error("Missing or invalid size in reference envelope")]
+    InvalidReferenceSize,
+
+    #[error("Missing or invalid server URL in reference envelope")]
+    InvalidReferenceUrl,
+
+    #[error("Invalid digest in reference envelope")]
+    InvalidReferenceDigest,
+
+    #[error("Invalid codec in reference envelope")]
+    InvalidReferenceCodec,
+
+    #[error("Reference envelope version {0} is newer than supported")]
+    UnsupportedReferenceVersion(u64),
+
+    #[error("Cannot fetch referenced content from {0}")]
+    UnsupportedLocation(String),
+
+    #[error(
+        "Referenced content at {arid} has digest {actual}, expected {expected}"
+    )]
+    DigestMismatch { arid: String, expected: String, actual: String },
 }
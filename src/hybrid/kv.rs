@@ -1,19 +1,32 @@
-use bc_components::ARID;
+use std::time::{Duration, Instant};
+
+use bc_components::{ARID, DigestProvider};
 use bc_envelope::Envelope;
 use bc_ur::prelude::*;
+use hubert_core::reference::{DEFAULT_DHT_SIZE_LIMIT, needs_reference};
 
 use super::{
-    Error as HybridError,
+    Error as HybridError, Location, Reference,
     reference::{
-        create_reference_envelope, extract_reference_arid,
+        extract_reference_arid, extract_reference_locations,
         is_reference_envelope,
     },
 };
 use crate::{
-    KvStore, Result, ipfs::IpfsKv, logging::verbose_println,
-    mainline::MainlineDhtKv,
+    HealthReport, KvStore, Receipt, Result, StoreCapabilities,
+    ValidationError, expiry,
+    ipfs::IpfsKv,
+    logging::verbose_println,
+    mainline::{DHT_ITEM_LIFETIME, MainlineDhtKv},
+    receipt::Backend,
+    server::ServerKvClient,
+    wire::{Codec, stored_size},
 };
 
+/// TTL in seconds of an envelope stored in IPFS by a put that gives none: a
+/// day.
+const DEFAULT_IPFS_TTL: u64 = 24 * 60 * 60;
+
 /// Hybrid storage layer combining Mainline DHT and IPFS.
 ///
 /// Automatically optimizes storage based on envelope size:
@@ -26,6 +39,28 @@ use crate::{
 /// - Large capacity for big messages via IPFS
 /// - Transparent indirection handled automatically
 ///
+/// # Lifetimes
+///
+/// An envelope stored in IPFS is reachable only while both its IPNS record
+/// and the DHT reference to it live. A put keeps the reference alive in the
+/// DHT for the TTL (a day by default), for as long as this store exists, and
+/// gives the IPNS record the TTL plus the time the DHT takes to drop a
+/// reference no longer refreshed, so the content outlasts every reference
+/// to it. The put's receipt reports when the TTL ends.
+///
+/// # Fallback
+///
+/// With [`Self::with_fallback_server`], a put of a large envelope while the
+/// IPFS daemon is unreachable stores it on a Hubert server instead, and the
+/// reference records the server's URL so any reader can find it there.
+///
+/// # Integrity
+///
+/// References record the digest of the envelope they point to, and a get
+/// fails with [`Error::DigestMismatch`](super::Error::DigestMismatch) if
+/// what it fetches does not match. References written before digests were
+/// recorded are followed unchecked.
+///
 /// # Requirements
 ///
 /// - No external daemon for DHT (embedded client)
@@ -60,6 +95,7 @@ pub struct HybridKv {
     dht: MainlineDhtKv,
     ipfs: IpfsKv,
     dht_size_limit: usize,
+    fallback: Option<ServerKvClient>,
 }
 
 impl HybridKv {
@@ -74,18 +110,23 @@ impl HybridKv {
     /// Returns error if DHT client initialization fails.
     pub async fn new(ipfs_rpc_url: &str) -> Result<Self> {
         let dht = MainlineDhtKv::new().await?;
-        let ipfs = IpfsKv::new(ipfs_rpc_url);
+        Ok(Self::from_stores(dht, IpfsKv::new(ipfs_rpc_url)))
+    }
 
-        Ok(Self {
-            dht,
-            ipfs,
-            dht_size_limit: 1000, // Conservative DHT limit
-        })
+    /// Combine configured stores, routing by the DHT store's size limit.
+    pub(super) fn from_stores(dht: MainlineDhtKv, ipfs: IpfsKv) -> Self {
+        let dht_size_limit = dht
+            .capabilities()
+            .max_value_size
+            .unwrap_or(DEFAULT_DHT_SIZE_LIMIT);
+        Self { dht, ipfs, dht_size_limit, fallback: None }
     }
 
     /// Set custom DHT size limit (default: 1000 bytes).
     ///
-    /// Envelopes larger than this will use IPFS indirection.
+    /// Envelopes larger than this, measured with
+    /// [`stored_size`](crate::wire::stored_size) as BEP-44 values, will use
+    /// IPFS indirection.
     pub fn with_dht_size_limit(mut self, limit: usize) -> Self {
         self.dht_size_limit = limit;
         self
@@ -99,10 +140,35 @@ impl HybridKv {
         self
     }
 
-    /// Check if an envelope fits in the DHT.
+    /// Derive the keys of both layers within `domain` (default: empty).
+    ///
+    /// See [`MainlineDhtKv::with_domain`] and [`IpfsKv::with_domain`].
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        let domain = domain.into();
+        self.dht = self.dht.with_domain(domain.clone());
+        self.ipfs = self.ipfs.with_domain(domain);
+        self
+    }
+
+    /// Store large envelopes on the server `fallback` talks to whenever the
+    /// IPFS daemon fails its health check.
+    ///
+    /// References to such envelopes carry the client's base URL, which must
+    /// be one readers can reach. Gets through this store fetch with
+    /// `fallback` itself when the URL matches, so its capability and
+    /// certificates apply, and with a plain [`ServerKvClient`] otherwise.
+    pub fn with_fallback_server(mut self, fallback: ServerKvClient) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Check if an envelope fits in the DHT, measured as the DHT measures
+    /// it.
     fn fits_in_dht(&self, envelope: &Envelope) -> bool {
-        let serialized = envelope.tagged_cbor().to_cbor_data();
-        serialized.len() <= self.dht_size_limit
+        !needs_reference(
+            stored_size(envelope, Codec::Bep44),
+            self.dht_size_limit,
+        )
     }
 
     /// Put an envelope using hybrid storage logic.
@@ -112,7 +178,7 @@ impl HybridKv {
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<String> {
+    ) -> Result<Receipt> {
         // Check if it fits in DHT
         if self.fits_in_dht(envelope) {
             // Store directly in DHT (DHT handles obfuscation)
@@ -122,8 +188,16 @@ impl HybridKv {
                     self.dht_size_limit
                 ));
             }
-            self.dht.put(arid, envelope, ttl_seconds, verbose).await?;
-            Ok(format!("Stored in DHT at ARID: {}", arid.ur_string()))
+            let stored =
+                self.dht.put(arid, envelope, ttl_seconds, verbose).await?;
+            let mut receipt = Receipt::new(
+                Backend::Hybrid,
+                *arid,
+                stored.location,
+                stored.size,
+            );
+            receipt.dht_pubkey = stored.dht_pubkey;
+            Ok(receipt)
         } else {
             // Use IPFS with DHT reference
             if verbose {
@@ -132,90 +206,214 @@ impl HybridKv {
                 );
             }
 
+            // The reference is refreshed for the TTL and may linger in the
+            // DHT for a while after, so the IPNS record must last that much
+            // longer
+            let ttl = ttl_seconds.unwrap_or(DEFAULT_IPFS_TTL);
+            let ipns_lifetime = ttl.saturating_add(DHT_ITEM_LIFETIME.as_secs());
+            let expires_at = expiry::unix_now().saturating_add(ttl);
+
             // 1. Store actual envelope in IPFS with a new ARID (IPFS handles
-            //    obfuscation with reference_arid)
+            //    obfuscation with reference_arid), or on the fallback server if
+            //    IPFS is down
             let reference_arid = ARID::new();
-            if verbose {
-                verbose_println(&format!(
-                    "Storing actual envelope in IPFS with reference ARID: {}",
-                    reference_arid.ur_string()
-                ));
-            }
-            self.ipfs
-                .put(&reference_arid, envelope, ttl_seconds, verbose)
-                .await?;
+            let (location, content) = match &self.fallback {
+                Some(fallback) if !self.ipfs.health().await.is_available() => {
+                    if verbose {
+                        verbose_println(&format!(
+                            "IPFS unavailable, storing actual envelope on {} \
+                             with reference ARID: {}",
+                            fallback.base_url(),
+                            reference_arid.ur_string()
+                        ));
+                    }
+                    let content = fallback
+                        .put(
+                            &reference_arid,
+                            envelope,
+                            Some(ipns_lifetime),
+                            verbose,
+                        )
+                        .await?;
+                    let url = fallback.base_url().to_string();
+                    (Location::Server(url), content)
+                }
+                _ => {
+                    if verbose {
+                        verbose_println(&format!(
+                            "Storing actual envelope in IPFS with reference \
+                             ARID: {}",
+                            reference_arid.ur_string()
+                        ));
+                    }
+                    let content = self
+                        .ipfs
+                        .put(
+                            &reference_arid,
+                            envelope,
+                            Some(ipns_lifetime),
+                            verbose,
+                        )
+                        .await?;
+                    (Location::Ipfs, content)
+                }
+            };
+            let stored_in = match (&location, &content.cid) {
+                (Location::Ipfs, Some(cid)) => format!("ipfs://{}", cid),
+                (Location::Ipfs, None) => content.location.clone(),
+                (Location::Server(url) | Location::S3(url), _) => url.clone(),
+            };
 
             // 2. Create reference envelope
-            let envelope_size = envelope.tagged_cbor().to_cbor_data().len();
+            let envelope_size = stored_size(envelope, Codec::Dcbor);
             let reference =
-                create_reference_envelope(&reference_arid, envelope_size);
+                Reference::new(reference_arid, envelope_size, location)
+                    .with_digest(envelope.digest())
+                    .to_envelope();
 
             // 3. Store reference envelope in DHT at original ARID (DHT handles
-            //    obfuscation with original arid)
+            //    obfuscation with original arid), refreshed for the TTL
             if verbose {
                 verbose_println(
                     "Storing reference envelope in DHT at original ARID",
                 );
             }
-            self.dht.put(arid, &reference, ttl_seconds, verbose).await?;
+            let stored = self
+                .dht
+                .put_with_keepalive_for(
+                    arid,
+                    &reference,
+                    Duration::from_secs(ttl),
+                    verbose,
+                )
+                .await?;
 
-            Ok(format!(
-                "Stored in IPFS (ref: {}) via DHT at ARID: {}",
-                reference_arid.ur_string(),
-                arid.ur_string()
-            ))
+            let mut receipt =
+                Receipt::new(Backend::Hybrid, *arid, stored_in, envelope_size)
+                    .with_reference(reference_arid)
+                    .with_ttl(ttl, expires_at);
+            receipt.cid = content.cid;
+            receipt.dht_pubkey = stored.dht_pubkey;
+            Ok(receipt)
         }
     }
 
-    /// Get an envelope using hybrid storage logic.
-    async fn get_impl(
+    /// Get the envelope at `arid`, pinning its content in IPFS if it is
+    /// stored there.
+    ///
+    /// Returns the envelope and the CID pinned, which is `None` for
+    /// envelopes small enough to live in the DHT or stored on a fallback
+    /// server.
+    pub async fn get_and_pin(
         &self,
         arid: &ARID,
         timeout_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<Option<Envelope>> {
+    ) -> Result<Option<(Envelope, Option<String>)>> {
+        self.get_impl(arid, deadline(timeout_seconds), true, verbose)
+            .await
+    }
+
+    /// Unpin the IPFS content referenced from `arid`.
+    ///
+    /// Returns the CID unpinned, or `None` if nothing is stored at `arid` or
+    /// the envelope lives in the DHT or on a fallback server.
+    pub async fn unpin(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<String>> {
+        match self.dht.get(arid, timeout_seconds, verbose).await? {
+            Some(envelope)
+                if is_reference_envelope(&envelope)
+                    && extract_reference_locations(&envelope).is_ok_and(
+                        |locations| locations.contains(&Location::Ipfs),
+                    ) =>
+            {
+                let reference_arid = extract_reference_arid(&envelope)?;
+                self.ipfs
+                    .unpin(&reference_arid, timeout_seconds, verbose)
+                    .await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Get an envelope using hybrid storage logic, with the CID pinned if
+    /// `pin` is set.
+    ///
+    /// The DHT lookup and the IPFS fetch share `deadline`, so a slow DHT
+    /// leaves less time for IPFS rather than doubling the wait.
+    async fn get_impl(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        pin: bool,
+        verbose: bool,
+    ) -> Result<Option<(Envelope, Option<String>)>> {
         // 1. Try to get from DHT (DHT handles deobfuscation)
-        let dht_envelope = self.dht.get(arid, timeout_seconds, verbose).await?;
+        let dht_envelope = self.dht.get_until(arid, deadline, verbose).await?;
 
         match dht_envelope {
             None => Ok(None),
             Some(envelope) => {
                 // 2. Check if the envelope is a reference envelope
                 if is_reference_envelope(&envelope) {
-                    if verbose {
-                        verbose_println(
-                            "Found reference envelope, fetching actual envelope from IPFS",
-                        );
-                    }
-
-                    // 3. Extract reference ARID
-                    let reference_arid = extract_reference_arid(&envelope)?;
+                    // 3. Extract reference ARID and where it is stored
+                    let reference = Reference::from_envelope(&envelope)?;
 
                     if verbose {
                         verbose_println(&format!(
-                            "Reference ARID: {}",
-                            reference_arid.ur_string()
+                            "Found reference envelope, reference ARID: {}",
+                            reference.arid.ur_string()
                         ));
                     }
 
-                    // 4. Retrieve actual envelope from IPFS (IPFS handles
-                    //    deobfuscation with reference_arid)
-                    let ipfs_envelope = self
-                        .ipfs
-                        .get(&reference_arid, timeout_seconds, verbose)
-                        .await?;
-
-                    match ipfs_envelope {
-                        Some(actual) => {
-                            if verbose {
-                                verbose_println(
-                                    "Successfully retrieved actual envelope from IPFS",
-                                );
+                    // 4. Retrieve actual envelope from each location in turn
+                    //    until one has it
+                    let mut failure = None;
+                    for location in &reference.locations {
+                        if verbose {
+                            verbose_println(&format!(
+                                "Fetching actual envelope from {}",
+                                location.url().unwrap_or("IPFS")
+                            ));
+                        }
+                        match self
+                            .fetch(
+                                &reference.arid,
+                                location,
+                                deadline,
+                                pin,
+                                verbose,
+                            )
+                            .await
+                            .and_then(|found| verify(&reference, found))
+                        {
+                            Ok(Some(actual)) => {
+                                if verbose {
+                                    verbose_println(
+                                        "Successfully retrieved actual envelope",
+                                    );
+                                }
+                                return Ok(Some(actual));
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                if verbose {
+                                    verbose_println(&format!(
+                                        "Fetch from {} failed: {}",
+                                        location.url().unwrap_or("IPFS"),
+                                        e
+                                    ));
+                                }
+                                failure = Some(e);
                             }
-                            Ok(Some(actual))
                         }
-                        None => Err(HybridError::ContentNotFound.into()),
                     }
+                    Err(failure
+                        .unwrap_or_else(|| HybridError::ContentNotFound.into()))
                 } else {
                     // Not a reference envelope, return it directly
                     if verbose {
@@ -223,11 +421,83 @@ impl HybridKv {
                             "Envelope is not a reference, treating as direct payload",
                         );
                     }
-                    Ok(Some(envelope))
+                    Ok(Some((envelope, None)))
                 }
             }
         }
     }
+
+    /// Fetch the actual envelope stored at `reference_arid` in `location`,
+    /// with its CID pinned if it is in IPFS and `pin` is set.
+    async fn fetch(
+        &self,
+        reference_arid: &ARID,
+        location: &Location,
+        deadline: Instant,
+        pin: bool,
+        verbose: bool,
+    ) -> Result<Option<(Envelope, Option<String>)>> {
+        // IPFS and servers handle deobfuscation with reference_arid
+        match location {
+            Location::Ipfs if pin => Ok(self
+                .ipfs
+                .get_and_pin_until(reference_arid, deadline, verbose)
+                .await?
+                .map(|(actual, cid)| (actual, Some(cid)))),
+            Location::Ipfs => Ok(self
+                .ipfs
+                .get_until(reference_arid, deadline, verbose)
+                .await?
+                .map(|actual| (actual, None))),
+            Location::Server(url) => {
+                let found = match &self.fallback {
+                    Some(fallback) if fallback.base_url() == url.as_str() => {
+                        fallback
+                            .get_until(reference_arid, deadline, verbose)
+                            .await?
+                    }
+                    _ => {
+                        ServerKvClient::new(url)
+                            .get_until(reference_arid, deadline, verbose)
+                            .await?
+                    }
+                };
+                Ok(found.map(|actual| (actual, None)))
+            }
+            Location::S3(url) => {
+                Err(HybridError::UnsupportedLocation(url.clone()).into())
+            }
+        }
+    }
+}
+
+/// Check an envelope fetched through `reference` against the digest the
+/// reference records, if any.
+///
+/// Content addressing ties a CID to its bytes, but nothing ties what an
+/// IPNS name or a server returns to what the writer stored; the digest
+/// does.
+fn verify(
+    reference: &Reference,
+    found: Option<(Envelope, Option<String>)>,
+) -> Result<Option<(Envelope, Option<String>)>> {
+    if let (Some(expected), Some((actual, _))) = (&reference.digest, &found) {
+        let actual = actual.digest();
+        if actual != *expected {
+            return Err(HybridError::DigestMismatch {
+                arid: reference.arid.ur_string(),
+                expected: expected.ur_string(),
+                actual: actual.ur_string(),
+            }
+            .into());
+        }
+    }
+    Ok(found)
+}
+
+/// When a get given `timeout_seconds` (default: 30) gives up.
+fn deadline(timeout_seconds: Option<u64>) -> Instant {
+    Instant::now() + Duration::from_secs(timeout_seconds.unwrap_or(30))
 }
 
 #[async_trait::async_trait(?Send)]
@@ -238,7 +508,8 @@ impl KvStore for HybridKv {
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<String> {
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
         self.put_impl(arid, envelope, ttl_seconds, verbose).await
     }
 
@@ -248,13 +519,59 @@ impl KvStore for HybridKv {
         timeout_seconds: Option<u64>,
         verbose: bool,
     ) -> Result<Option<Envelope>> {
-        self.get_impl(arid, timeout_seconds, verbose).await
+        self.get_until(arid, deadline(timeout_seconds), verbose)
+            .await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        let found = self.get_impl(arid, deadline, false, verbose).await?;
+        Ok(found.map(|(envelope, _)| envelope))
     }
 
     async fn exists(&self, arid: &ARID) -> Result<bool> {
         // Check DHT only (references count as existing)
         self.dht.exists(arid).await
     }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        // Envelopes too large for the DHT go to IPFS, so only IPFS limits
+        // size. The TTL bounds IPFS-held content; envelopes small enough for
+        // the DHT lapse on the DHT's own schedule.
+        let ipfs = self.ipfs.capabilities();
+        StoreCapabilities {
+            max_value_size: ipfs.max_value_size,
+            ttl: ipfs.ttl,
+            ..self.dht.capabilities().intersect(ipfs)
+        }
+    }
+
+    /// Rejects envelopes shaped like references as well as those too large
+    /// for IPFS: stored directly in the DHT, a get would follow one rather
+    /// than return it.
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        if is_reference_envelope(envelope) {
+            return Err(ValidationError::Incompatible(
+                "envelope has the form of a hybrid reference".to_string(),
+            ));
+        }
+        self.ipfs.validate(envelope)
+    }
+
+    async fn health(&self) -> HealthReport {
+        let (dht, ipfs) =
+            futures_util::join!(self.dht.health(), self.ipfs.health());
+        let mut report = HealthReport::combine("hybrid", vec![dht, ipfs]);
+        report.max_value_size = self.capabilities().max_value_size;
+        report
+    }
 }
 
 #[cfg(test)]
@@ -264,4 +581,29 @@ mod tests {
         // Unit tests require async runtime
         // See integration tests in tests/test_hybrid_kv.rs
     }
+
+    #[test]
+    fn test_verify_digest() {
+        use bc_components::{ARID, DigestProvider};
+        use bc_envelope::Envelope;
+
+        use super::{HybridError, Location, Reference, verify};
+        use crate::Error;
+
+        let stored = Envelope::new("stored");
+        let reference = Reference::new(ARID::new(), 100, Location::Ipfs)
+            .with_digest(stored.digest());
+        assert!(verify(&reference, None).unwrap().is_none());
+        assert!(verify(&reference, Some((stored.clone(), None))).is_ok());
+        assert!(matches!(
+            verify(&reference, Some((Envelope::new("other"), None))),
+            Err(Error::Hybrid(HybridError::DigestMismatch { .. }))
+        ));
+
+        // Version 1 references record no digest
+        let unchecked = Reference::new(ARID::new(), 100, Location::Ipfs);
+        assert!(
+            verify(&unchecked, Some((Envelope::new("other"), None))).is_ok()
+        );
+    }
 }
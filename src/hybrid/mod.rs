@@ -1,6 +1,9 @@
+mod config;
 mod error;
 mod kv;
 mod reference;
 
+pub use config::HybridConfig;
 pub use error::Error;
 pub use kv::HybridKv;
+pub use reference::{Location, Reference};
@@ -1,8 +1,178 @@
-use bc_components::ARID;
+use bc_components::{ARID, Digest};
 use bc_envelope::prelude::*;
+use hubert_core::reference::{
+    CODEC, DEFAULT_CODEC, DIGEST, IPFS, REFERENCE_VERSION, S3, SERVER, SIZE,
+    URL, VERSION,
+};
 
 use super::error::Error;
 
+/// Where the envelope a [`Reference`] points to is stored.
+///
+/// Locations are ordered by preference: readers try a reference's
+/// locations in this order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+    /// In IPFS, obfuscated and published under the ARID's IPNS name.
+    Ipfs,
+    /// On the Hubert server at this base URL, used when IPFS was down.
+    Server(String),
+    /// In an S3 bucket, at this `s3://<bucket>/<key>` URL. Hybrid storage
+    /// records but cannot fetch from it; it is for readers with S3 access.
+    S3(String),
+}
+
+impl Location {
+    /// The object of `'dereferenceVia'` for this location.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Ipfs => IPFS,
+            Self::Server(_) => SERVER,
+            Self::S3(_) => S3,
+        }
+    }
+
+    /// The URL of a server or S3 location.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Self::Ipfs => None,
+            Self::Server(url) | Self::S3(url) => Some(url),
+        }
+    }
+
+    /// The object of `'dereferenceVia'` in a version 2 reference: the kind,
+    /// with a `"url"` assertion if the location has one.
+    fn to_locator(&self) -> Envelope {
+        let locator = Envelope::new(self.kind());
+        match self.url() {
+            Some(url) => locator.add_assertion(URL, url),
+            None => locator,
+        }
+    }
+
+    /// Parse a `'dereferenceVia'` object, or `None` if it names a kind of
+    /// location this version does not know.
+    fn from_locator(locator: &Envelope) -> Result<Option<Self>, Error> {
+        let url = || {
+            locator
+                .extract_object_for_predicate::<String>(URL)
+                .map_err(|_| Error::InvalidReferenceUrl)
+        };
+        match locator.extract_subject::<String>().ok().as_deref() {
+            Some(IPFS) => Ok(Some(Self::Ipfs)),
+            Some(SERVER) => Ok(Some(Self::Server(url()?))),
+            Some(S3) => Ok(Some(Self::S3(url()?))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The contents of a reference envelope, which hybrid storage puts in the
+/// DHT in place of an envelope too large for it.
+///
+/// A reference to IPFS alone, without a digest, is written in the version 1
+/// format of [`create_reference_envelope`]; any other in version 2, which
+/// [`hubert_core::reference`] describes. Both versions are read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// ARID the actual envelope is stored at.
+    pub arid: ARID,
+    /// Size of the actual envelope in bytes.
+    pub size: usize,
+    /// Every place the actual envelope is stored, sorted and without
+    /// duplicates. Never empty.
+    pub locations: Vec<Location>,
+    /// Digest of the actual envelope, if the reference records it.
+    pub digest: Option<Digest>,
+    /// How the actual envelope is encoded; [`DEFAULT_CODEC`] unless a newer
+    /// writer chose otherwise.
+    pub codec: String,
+}
+
+impl Reference {
+    /// A reference to an envelope stored in `location` alone.
+    pub fn new(arid: ARID, size: usize, location: Location) -> Self {
+        Self {
+            arid,
+            size,
+            locations: vec![location],
+            digest: None,
+            codec: DEFAULT_CODEC.to_string(),
+        }
+    }
+
+    /// Also record that the actual envelope is stored in `location`.
+    pub fn with_location(mut self, location: Location) -> Self {
+        self.locations.push(location);
+        self.locations.sort();
+        self.locations.dedup();
+        self
+    }
+
+    pub fn with_digest(mut self, digest: Digest) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// The format version [`Self::to_envelope`] writes.
+    pub fn version(&self) -> u64 {
+        if self.locations == [Location::Ipfs]
+            && self.digest.is_none()
+            && self.codec == DEFAULT_CODEC
+        {
+            1
+        } else {
+            REFERENCE_VERSION
+        }
+    }
+
+    pub fn to_envelope(&self) -> Envelope {
+        if self.version() == 1 {
+            return create_reference_envelope(&self.arid, self.size);
+        }
+        let mut envelope = Envelope::unit()
+            .add_assertion(VERSION, REFERENCE_VERSION)
+            .add_assertion(known_values::ID, self.arid)
+            .add_assertion(SIZE, self.size as i64)
+            .add_assertion(CODEC, self.codec.as_str());
+        for location in &self.locations {
+            envelope = envelope.add_assertion(
+                known_values::DEREFERENCE_VIA,
+                location.to_locator(),
+            );
+        }
+        if let Some(digest) = self.digest {
+            envelope = envelope.add_assertion(DIGEST, digest);
+        }
+        envelope
+    }
+
+    pub fn from_envelope(envelope: &Envelope) -> Result<Self, Error> {
+        let arid = extract_reference_arid(envelope)?;
+        let size = envelope
+            .extract_object_for_predicate::<u64>(SIZE)
+            .ok()
+            .and_then(|size| usize::try_from(size).ok())
+            .ok_or(Error::InvalidReferenceSize)?;
+        let locations = extract_reference_locations(envelope)?;
+        let digest = match envelope.objects_for_predicate(DIGEST).first() {
+            Some(object) => Some(
+                object
+                    .extract_subject::<Digest>()
+                    .map_err(|_| Error::InvalidReferenceDigest)?,
+            ),
+            None => None,
+        };
+        let codec = match envelope.objects_for_predicate(CODEC).first() {
+            Some(object) => object
+                .extract_subject::<String>()
+                .map_err(|_| Error::InvalidReferenceCodec)?,
+            None => DEFAULT_CODEC.to_string(),
+        };
+        Ok(Self { arid, size, locations, digest, codec })
+    }
+}
+
 /// Creates a reference envelope that points to content stored in IPFS.
 ///
 /// Reference envelopes are small envelopes stored in the DHT that contain
@@ -33,15 +203,26 @@ pub fn create_reference_envelope(
     actual_size: usize,
 ) -> Envelope {
     Envelope::unit()
-        .add_assertion(known_values::DEREFERENCE_VIA, "ipfs")
+        .add_assertion(known_values::DEREFERENCE_VIA, IPFS)
         .add_assertion(known_values::ID, *reference_arid)
-        .add_assertion("size", actual_size as i64)
+        .add_assertion(SIZE, actual_size as i64)
 }
 
 /// Checks if an envelope is a reference envelope.
 ///
-/// A reference envelope contains `dereferenceVia: "ipfs"` and an `id`
-/// assertion.
+/// A reference envelope contains a `dereferenceVia` assertion naming a
+/// known location and an `id` assertion, and is no newer than
+/// [`REFERENCE_VERSION`]. Version 1 references to a server carry the
+/// server's base URL alongside:
+///
+/// ```text
+/// '' [
+///     'dereferenceVia': "server",
+///     'id': <ARID>,
+///     "size": <usize>,
+///     "url": <String>
+/// ]
+/// ```
 ///
 /// # Parameters
 ///
@@ -56,22 +237,13 @@ pub fn is_reference_envelope(envelope: &Envelope) -> bool {
         return false;
     }
 
-    // Check for dereferenceVia: "ipfs" assertion
-    let has_dereference_via = envelope.assertions().iter().any(|assertion| {
-        if let Ok(predicate) = assertion.try_predicate()
-            && let Some(kv) = predicate.as_known_value()
-            && kv.value() == known_values::DEREFERENCE_VIA_RAW
-            && let Ok(object) = assertion.try_object()
-            && let Ok(cbor) = object.subject().try_leaf()
-            && let Ok(text) = cbor.try_into_text()
-        {
-            return text == "ipfs";
-        }
-
-        false
-    });
-
-    if !has_dereference_via {
+    // Check the version, and for a dereferenceVia assertion naming a known
+    // location
+    if reference_version(envelope).is_err()
+        || !dereference_via(envelope)
+            .iter()
+            .any(|kind| matches!(kind.as_str(), IPFS | SERVER | S3))
+    {
         return false;
     }
 
@@ -90,6 +262,68 @@ pub fn is_reference_envelope(envelope: &Envelope) -> bool {
     })
 }
 
+/// The reference format version of the envelope: its `"version"`, or 1 if
+/// it has none.
+fn reference_version(envelope: &Envelope) -> Result<u64, Error> {
+    match envelope.objects_for_predicate(VERSION).first() {
+        None => Ok(1),
+        Some(object) => match object.extract_subject::<u64>() {
+            Ok(version @ 1..=REFERENCE_VERSION) => Ok(version),
+            Ok(version) => Err(Error::UnsupportedReferenceVersion(version)),
+            Err(_) => Err(Error::NotReferenceEnvelope),
+        },
+    }
+}
+
+/// The subjects of the envelope's `dereferenceVia` objects that are text.
+fn dereference_via(envelope: &Envelope) -> Vec<String> {
+    envelope
+        .objects_for_predicate(known_values::DEREFERENCE_VIA)
+        .iter()
+        .filter_map(|object| object.extract_subject::<String>().ok())
+        .collect()
+}
+
+/// Extracts every place the envelope a reference envelope points to is
+/// stored, in the order to try them.
+///
+/// Locations of kinds this version does not know are skipped, so a newer
+/// writer can add kinds without breaking older readers.
+///
+/// # Returns
+///
+/// - `Ok(Vec<Location>)`, sorted and never empty
+/// - `Err(HybridError)` if the envelope is not a reference, is a newer version,
+///   or a server or S3 location has no valid URL
+pub fn extract_reference_locations(
+    envelope: &Envelope,
+) -> Result<Vec<Location>, Error> {
+    if reference_version(envelope)? == 1 {
+        // One bare locator; a server's URL is a top-level assertion
+        return match dereference_via(envelope).first().map(String::as_str) {
+            Some(IPFS) => Ok(vec![Location::Ipfs]),
+            Some(SERVER) => envelope
+                .extract_object_for_predicate::<String>(URL)
+                .map(|url| vec![Location::Server(url)])
+                .map_err(|_| Error::InvalidReferenceUrl),
+            _ => Err(Error::NotReferenceEnvelope),
+        };
+    }
+    let mut locations = Vec::new();
+    for locator in envelope.objects_for_predicate(known_values::DEREFERENCE_VIA)
+    {
+        if let Some(location) = Location::from_locator(&locator)? {
+            locations.push(location);
+        }
+    }
+    if locations.is_empty() {
+        return Err(Error::NotReferenceEnvelope);
+    }
+    locations.sort();
+    locations.dedup();
+    Ok(locations)
+}
+
 /// Extracts the reference ARID from a reference envelope.
 ///
 /// # Parameters
@@ -176,6 +410,114 @@ mod tests {
         assert_eq!(extracted, reference_arid);
     }
 
+    #[test]
+    fn test_reference_roundtrip() {
+        let reference = Reference::new(ARID::new(), 5000, Location::Ipfs);
+        let envelope = reference.to_envelope();
+        assert_eq!(reference.version(), 1);
+        assert_eq!(envelope, create_reference_envelope(&reference.arid, 5000));
+        assert_eq!(Reference::from_envelope(&envelope).unwrap(), reference);
+
+        let on_server = Reference::new(
+            reference.arid,
+            5000,
+            Location::Server("https://hubert.example".into()),
+        );
+        let envelope = on_server.to_envelope();
+        assert!(is_reference_envelope(&envelope));
+        assert_eq!(on_server.version(), 2);
+        assert_eq!(Reference::from_envelope(&envelope).unwrap(), on_server);
+
+        let urlless = Envelope::unit()
+            .add_assertion(known_values::DEREFERENCE_VIA, SERVER)
+            .add_assertion(known_values::ID, reference.arid)
+            .add_assertion(SIZE, 5000);
+        assert!(Reference::from_envelope(&urlless).is_err());
+
+        let sizeless = Envelope::unit()
+            .add_assertion(known_values::DEREFERENCE_VIA, IPFS)
+            .add_assertion(known_values::ID, reference.arid);
+        assert!(Reference::from_envelope(&sizeless).is_err());
+    }
+
+    #[test]
+    fn test_reference_v1_server() {
+        // As written before version 2, with the URL at the top level
+        let arid = ARID::new();
+        let envelope = Envelope::unit()
+            .add_assertion(known_values::DEREFERENCE_VIA, SERVER)
+            .add_assertion(known_values::ID, arid)
+            .add_assertion(SIZE, 5000)
+            .add_assertion(URL, "https://hubert.example");
+        assert!(is_reference_envelope(&envelope));
+        assert_eq!(
+            Reference::from_envelope(&envelope).unwrap(),
+            Reference::new(
+                arid,
+                5000,
+                Location::Server("https://hubert.example".into())
+            )
+        );
+    }
+
+    #[test]
+    fn test_reference_v2_locations() {
+        let envelope = Envelope::new("payload");
+        let reference = Reference::new(
+            ARID::new(),
+            5000,
+            Location::S3("s3://bucket/key".into()),
+        )
+        .with_location(Location::Server("https://hubert.example".into()))
+        .with_location(Location::Ipfs)
+        .with_location(Location::Ipfs)
+        .with_digest(envelope.digest());
+        assert_eq!(
+            reference.locations,
+            [
+                Location::Ipfs,
+                Location::Server("https://hubert.example".into()),
+                Location::S3("s3://bucket/key".into()),
+            ]
+        );
+
+        let encoded = reference.to_envelope();
+        assert!(is_reference_envelope(&encoded));
+        assert_eq!(extract_reference_arid(&encoded).unwrap(), reference.arid);
+        assert_eq!(
+            extract_reference_locations(&encoded).unwrap(),
+            reference.locations
+        );
+        assert_eq!(Reference::from_envelope(&encoded).unwrap(), reference);
+
+        // Unknown kinds of location are skipped
+        let with_unknown = encoded.clone().add_assertion(
+            known_values::DEREFERENCE_VIA,
+            Envelope::new("carrier-pigeon"),
+        );
+        assert_eq!(Reference::from_envelope(&with_unknown).unwrap(), reference);
+
+        // Newer versions are not references this version can follow
+        let newer = Envelope::unit()
+            .add_assertion(VERSION, REFERENCE_VERSION + 1)
+            .add_assertion(known_values::DEREFERENCE_VIA, IPFS)
+            .add_assertion(known_values::ID, reference.arid)
+            .add_assertion(SIZE, 5000);
+        assert!(!is_reference_envelope(&newer));
+        assert!(matches!(
+            extract_reference_locations(&newer),
+            Err(Error::UnsupportedReferenceVersion(3))
+        ));
+    }
+
+    #[test]
+    fn test_core_vocabulary() {
+        use hubert_core::reference::{DEREFERENCE_VIA, ID};
+
+        assert_eq!(DEREFERENCE_VIA, known_values::DEREFERENCE_VIA_RAW);
+        assert_eq!(ID, known_values::ID_RAW);
+    }
+
     #[test]
     fn test_extract_reference_arid_from_non_reference() {
         let regular = Envelope::new("test data");
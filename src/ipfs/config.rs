@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{DEFAULT_MAX_ENVELOPE_SIZE, IpfsKv};
+
+/// Every setting of an [`IpfsKv`], for building one from a configuration
+/// file with [`IpfsKv::with_config`].
+///
+/// Every field is optional in a file; missing fields take their default
+/// values.
+///
+/// ```toml
+/// rpc_url = "http://127.0.0.1:5001"
+/// resolve_timeout = 60
+/// pin_content = true
+/// domain = "acme-prod"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpfsConfig {
+    /// RPC endpoint of the Kubo daemon (default: http://127.0.0.1:5001).
+    pub rpc_url: String,
+    /// Largest serialized envelope to accept (default: 10 MB).
+    pub max_size: usize,
+    /// Seconds to wait for an IPNS name to resolve (default: 30).
+    pub resolve_timeout: u64,
+    /// Pin stored and retrieved content in the local node.
+    pub pin_content: bool,
+    /// Derivation domain isolating this deployment's entries (default:
+    /// none); see [`IpfsKv::with_domain`].
+    pub domain: String,
+}
+
+impl Default for IpfsConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: "http://127.0.0.1:5001".to_string(),
+            max_size: DEFAULT_MAX_ENVELOPE_SIZE,
+            resolve_timeout: 30,
+            pin_content: false,
+            domain: String::new(),
+        }
+    }
+}
+
+impl IpfsKv {
+    /// Create a store with every setting taken from `config`.
+    pub fn with_config(config: IpfsConfig) -> Self {
+        Self::new(&config.rpc_url)
+            .with_max_size(config.max_size)
+            .with_resolve_timeout(Duration::from_secs(config.resolve_timeout))
+            .with_pin_content(config.pin_content)
+            .with_domain(config.domain)
+    }
+}
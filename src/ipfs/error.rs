@@ -1,8 +1,8 @@
 /// IPFS-specific errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Envelope size {size} exceeds practical limit")]
-    EnvelopeTooLarge { size: usize },
+    #[error("Envelope size {size} exceeds IPFS limit of {limit} bytes")]
+    EnvelopeTooLarge { size: usize, limit: usize },
 
     #[error("IPFS daemon error: {0}")]
     DaemonError(#[from] ipfs_api_backend_hyper::Error),
@@ -12,4 +12,7 @@ pub enum Error {
 
     #[error("Unexpected IPNS path format: {0}")]
     UnexpectedIpnsPathFormat(String),
+
+    #[error("Content at {cid} has digest {actual}, expected {expected}")]
+    DigestMismatch { cid: String, expected: String, actual: String },
 }
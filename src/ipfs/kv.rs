@@ -1,29 +1,35 @@
 use std::sync::{Arc, RwLock};
 
-use bc_components::ARID;
+use bc_components::{ARID, Digest, DigestProvider};
 use bc_envelope::Envelope;
 use bc_ur::UREncodable;
-use dcbor::CBOREncodable;
 use ipfs_api_backend_hyper::{IpfsApi, IpfsClient};
 use ipfs_api_prelude::request::KeyType;
 use tokio::time::{Duration, Instant, sleep};
 
 use super::{
     error::Error as IpfsError,
-    value::{add_bytes, cat_bytes, pin_cid},
+    record::IpnsRecord,
+    value::{add_bytes, cat_bytes, pin_cid, unpin_cid},
 };
 use crate::{
-    Error, KvStore, Result,
-    arid_derivation::{derive_ipfs_key_name, obfuscate_with_arid},
+    Error, HealthReport, KvStore, Mutability, Receipt, Result,
+    StoreCapabilities, arid_derivation::derive_ipfs_key_name, expiry,
+    receipt::Backend,
+    wire::ObfuscatedPayload,
 };
 
+/// Largest envelope an [`IpfsKv`] stores unless configured otherwise: 10 MB.
+pub const DEFAULT_MAX_ENVELOPE_SIZE: usize = 10 * 1024 * 1024;
+
 /// IPFS-backed key-value store using IPNS for ARID-based addressing.
 ///
 /// This implementation uses:
 /// - ARID → IPNS key name derivation (deterministic)
 /// - IPFS content addressing (CID) for immutable storage
 /// - IPNS for publish-once mutable names
-/// - Write-once semantics (publish fails if name already exists)
+/// - Write-once semantics (publish fails if name already exists), unless
+///   made updatable with [`IpfsKv::with_mutability`]
 ///
 /// # Requirements
 ///
@@ -53,10 +59,13 @@ use crate::{
 /// ```
 pub struct IpfsKv {
     client: IpfsClient,
+    rpc_url: String,
     key_cache: Arc<RwLock<std::collections::HashMap<String, KeyInfo>>>,
     max_envelope_size: usize,
     resolve_timeout: Duration,
     pin_content: bool,
+    domain: String,
+    mutability: Mutability,
 }
 
 #[derive(Clone, Debug)]
@@ -64,19 +73,45 @@ struct KeyInfo {
     peer_id: String,
 }
 
+/// How an ARID resolves through IPNS, as reported by
+/// [`IpfsKv::resolve_info`].
+///
+/// Fields are `None` from the first step that fails onward, which points at
+/// where a put that succeeded is failing to reach readers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolveInfo {
+    /// Name of the IPNS key derived from the ARID.
+    pub key_name: String,
+    /// IPNS name (peer ID) of the key, if the local node holds it.
+    pub peer_id: Option<String>,
+    /// CID the name resolves to.
+    pub cid: Option<String>,
+    /// When the IPNS record expires, as an RFC 3339 timestamp.
+    pub validity: Option<String>,
+    /// Sequence number of the IPNS record.
+    pub sequence: Option<u64>,
+    /// How long resolvers may cache the IPNS record.
+    pub ttl: Option<Duration>,
+    /// Total size of the content's DAG in bytes.
+    pub dag_size: Option<u64>,
+}
+
 impl IpfsKv {
     /// Create a new IPFS KV store with default settings.
     ///
     /// # Parameters
     ///
     /// - `rpc_url`: IPFS RPC endpoint (e.g., "http://127.0.0.1:5001")
-    pub fn new(_rpc_url: &str) -> Self {
+    pub fn new(rpc_url: &str) -> Self {
         Self {
             client: IpfsClient::default(),
+            rpc_url: rpc_url.trim_end_matches('/').to_string(),
             key_cache: Arc::new(RwLock::new(std::collections::HashMap::new())),
-            max_envelope_size: 10 * 1024 * 1024, // 10 MB
+            max_envelope_size: DEFAULT_MAX_ENVELOPE_SIZE,
             resolve_timeout: Duration::from_secs(30),
             pin_content: false,
+            domain: String::new(),
+            mutability: Mutability::WriteOnce,
         }
     }
 
@@ -98,9 +133,26 @@ impl IpfsKv {
         self
     }
 
+    /// Derive IPNS names and obfuscation keys in deployment `domain`
+    /// (default: none), so the same ARID names a different entry than in
+    /// other deployments. Readers must use the same domain as writers.
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = domain.into();
+        self
+    }
+
+    /// Let [`KvStore::update`] republish IPNS names if `mutability` is
+    /// [`Mutability::Updatable`]. The node numbers each record it publishes
+    /// for a name one past the last. With pinning on, an update unpins the
+    /// content it replaces.
+    pub fn with_mutability(mut self, mutability: Mutability) -> Self {
+        self.mutability = mutability;
+        self
+    }
+
     /// Get or create an IPNS key for the given ARID.
     async fn get_or_create_key(&self, arid: &ARID) -> Result<KeyInfo> {
-        let key_name = derive_ipfs_key_name(arid);
+        let key_name = derive_ipfs_key_name(&self.domain, arid);
 
         // Check cache first
         {
@@ -173,21 +225,19 @@ impl IpfsKv {
         if self.is_published(peer_id).await? {
             return Err(Error::AlreadyExists { arid: arid.ur_string() });
         }
+        self.publish(key_name, cid, ttl_seconds).await
+    }
 
-        // Convert TTL seconds to lifetime string for IPNS
-        // Format: "Ns" for seconds, "Nm" for minutes, "Nh" for hours, "Nd" for
-        // days
-        let lifetime = ttl_seconds.map(|secs| {
-            if secs < 60 {
-                format!("{}s", secs)
-            } else if secs < 3600 {
-                format!("{}m", secs / 60)
-            } else if secs < 86400 {
-                format!("{}h", secs / 3600)
-            } else {
-                format!("{}d", secs / 86400)
-            }
-        });
+    /// Publish a CID to an IPNS name, replacing any earlier record.
+    async fn publish(
+        &self,
+        key_name: &str,
+        cid: &str,
+        ttl_seconds: Option<u64>,
+    ) -> crate::Result<()> {
+        // The IPNS record lifetime, in whole seconds so that it is never
+        // rounded down below the TTL
+        let lifetime = ttl_seconds.map(|secs| format!("{}s", secs));
 
         // Publish to IPNS
         self.client
@@ -203,18 +253,19 @@ impl IpfsKv {
         Ok(())
     }
 
-    /// Resolve an IPNS name to a CID with polling and custom timeout.
-    async fn resolve_with_retry_timeout(
+    /// Resolve an IPNS name to a CID, polling until `deadline`.
+    async fn resolve_with_retry(
         &self,
         peer_id: &str,
-        timeout: Duration,
+        deadline: Instant,
         verbose: bool,
     ) -> crate::Result<Option<String>> {
-        use crate::logging::verbose_print_dot;
+        use crate::logging::PollProgress;
 
-        let deadline = Instant::now() + timeout;
         // Changed to 1000ms for verbose mode polling
         let poll_interval = Duration::from_millis(1000);
+        let mut progress =
+            PollProgress::new("ipfs resolve", deadline.into_std(), verbose);
 
         loop {
             match self.client.name_resolve(Some(peer_id), false, false).await {
@@ -245,10 +296,7 @@ impl IpfsKv {
                         return Err(IpfsError::Timeout.into());
                     }
 
-                    // Print polling dot if verbose
-                    if verbose {
-                        verbose_print_dot();
-                    }
+                    progress.missed();
 
                     // Retry after interval (now 1000ms)
                     sleep(poll_interval).await;
@@ -266,8 +314,21 @@ impl KvStore for IpfsKv {
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<String> {
-        self.put_impl(arid, envelope, ttl_seconds, verbose).await
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        self.put_impl(arid, envelope, ttl_seconds, false, verbose).await
+    }
+
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.mutability.check_update()?;
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        self.put_impl(arid, envelope, ttl_seconds, true, verbose).await
     }
 
     async fn get(
@@ -276,44 +337,93 @@ impl KvStore for IpfsKv {
         timeout_seconds: Option<u64>,
         verbose: bool,
     ) -> Result<Option<Envelope>> {
-        self.get_impl(arid, timeout_seconds, verbose).await
+        self.get_impl(arid, self.deadline(timeout_seconds), verbose)
+            .await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: std::time::Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.get_impl(arid, deadline.into(), verbose).await
     }
 
     async fn exists(&self, arid: &ARID) -> Result<bool> {
         self.exists_impl(arid).await
     }
+
+    /// Best effort: unpins the content and removes the IPNS key from the
+    /// local node. The published record and any copies other nodes cached
+    /// persist until their lifetime ends, and a later put to `arid` makes a
+    /// new key.
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        self.delete_impl(arid, verbose).await
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        // TTL becomes the IPNS record lifetime
+        StoreCapabilities {
+            max_value_size: Some(self.max_envelope_size),
+            ttl: true,
+            delete: true,
+            update: self.mutability.is_updatable(),
+            ..Default::default()
+        }
+    }
+
+    async fn health(&self) -> HealthReport {
+        HealthReport::probe("ipfs", async {
+            let response =
+                self.client.version().await.map_err(IpfsError::from)?;
+            Ok(Some(response.version))
+        })
+        .await
+        .with_max_value_size(self.max_envelope_size)
+    }
 }
 
 impl IpfsKv {
-    /// Internal put implementation with typed errors.
+    /// Internal put implementation with typed errors, republishing the
+    /// name if `update` is set.
     async fn put_impl(
         &self,
         arid: &ARID,
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
+        update: bool,
         verbose: bool,
-    ) -> crate::Result<String> {
+    ) -> crate::Result<Receipt> {
         use crate::logging::verbose_println;
 
         if verbose {
             verbose_println("Starting IPFS put operation");
         }
 
-        // Serialize envelope
-        let bytes = envelope.to_cbor_data();
+        // Serialize and obfuscate with ARID-derived key so it appears as
+        // random data
+        let obfuscated =
+            ObfuscatedPayload::encode_in(&self.domain, arid, envelope);
 
         if verbose {
-            verbose_println(&format!("Envelope size: {} bytes", bytes.len()));
+            verbose_println(&format!(
+                "Envelope size: {} bytes",
+                obfuscated.len()
+            ));
+            verbose_println(&format!(
+                "Envelope digest: {}",
+                envelope.digest().ur_string()
+            ));
         }
 
-        // Obfuscate with ARID-derived key so it appears as random data
-        let obfuscated = obfuscate_with_arid(arid, &bytes);
-
         // Check size after obfuscation (same size, but check anyway)
         if obfuscated.len() > self.max_envelope_size {
-            return Err(
-                IpfsError::EnvelopeTooLarge { size: obfuscated.len() }.into()
-            );
+            return Err(IpfsError::EnvelopeTooLarge {
+                size: obfuscated.len(),
+                limit: self.max_envelope_size,
+            }
+            .into());
         }
 
         if verbose {
@@ -326,13 +436,14 @@ impl IpfsKv {
         }
         let key_info = self.get_or_create_key(arid).await?;
 
-        let key_name = derive_ipfs_key_name(arid);
+        let key_name = derive_ipfs_key_name(&self.domain, arid);
 
         // Add obfuscated data to IPFS
         if verbose {
             verbose_println("Adding content to IPFS");
         }
-        let cid = add_bytes(&self.client, obfuscated).await?;
+        let size = obfuscated.len();
+        let cid = add_bytes(&self.client, obfuscated.into_bytes()).await?;
 
         if verbose {
             verbose_println(&format!("Content CID: {}", cid));
@@ -346,40 +457,282 @@ impl IpfsKv {
             pin_cid(&self.client, &cid, true).await?;
         }
 
-        // Publish to IPNS (write-once)
-        if verbose {
-            verbose_println("Publishing to IPNS (write-once check)");
+        let mut sequence = None;
+        if update {
+            if verbose {
+                verbose_println("Republishing to IPNS");
+            }
+            // What the name resolves to now, if this store pinned it
+            let replaced = if self.pin_content {
+                self.resolve_cid(arid, Instant::now(), false).await
+            } else {
+                Ok(None)
+            };
+            self.publish(&key_name, &cid, ttl_seconds).await?;
+            if let Ok(Some(replaced)) = replaced
+                && replaced != cid
+            {
+                unpin_cid(&self.client, &replaced, true).await.ok();
+            }
+            sequence = self
+                .fetch_record(&key_info.peer_id)
+                .await
+                .and_then(|record| record.sequence)
+                // Kubo numbers records from 0
+                .map(|sequence| sequence + 1);
+        } else {
+            // Publish to IPNS (write-once)
+            if verbose {
+                verbose_println("Publishing to IPNS (write-once check)");
+            }
+            self.publish_once(
+                &key_name,
+                &key_info.peer_id,
+                &cid,
+                ttl_seconds,
+                arid,
+            )
+            .await?;
         }
-        self.publish_once(
-            &key_name,
-            &key_info.peer_id,
-            &cid,
-            ttl_seconds,
-            arid,
-        )
-        .await?;
 
         if verbose {
             verbose_println("IPFS put operation completed");
         }
 
-        Ok(format!("ipns://{} -> ipfs://{}", key_info.peer_id, cid))
+        let location = format!("ipns://{}", key_info.peer_id);
+        let mut receipt =
+            Receipt::new(Backend::Ipfs, *arid, location, size).with_cid(cid);
+        if let Some(sequence) = sequence {
+            receipt = receipt.with_sequence(sequence);
+        }
+        Ok(match ttl_seconds {
+            Some(ttl) => {
+                receipt.with_ttl(ttl, expiry::unix_now().saturating_add(ttl))
+            }
+            None => receipt,
+        })
     }
 
-    /// Internal get implementation with typed errors.
-    async fn get_impl(
+    /// Inspect how `arid` resolves: its derived key name, IPNS name, IPNS
+    /// record, and content.
+    ///
+    /// Useful when a put succeeded but gets time out. Only the local node's
+    /// keystore is consulted for the key, as for [`KvStore::get`].
+    pub async fn resolve_info(&self, arid: &ARID) -> Result<ResolveInfo> {
+        let mut info = ResolveInfo {
+            key_name: derive_ipfs_key_name(&self.domain, arid),
+            ..Default::default()
+        };
+
+        let keys = self.client.key_list().await.map_err(IpfsError::from)?;
+        let Some(key) = keys.keys.into_iter().find(|k| k.name == info.key_name)
+        else {
+            return Ok(info);
+        };
+
+        if let Some(record) = self.fetch_record(&key.id).await {
+            info.cid = record
+                .value
+                .as_deref()
+                .and_then(|path| path.strip_prefix("/ipfs/"))
+                .map(str::to_string);
+            info.validity = record.validity;
+            info.sequence = record.sequence;
+            info.ttl = record.ttl;
+        }
+        if info.cid.is_none() {
+            info.cid = self
+                .client
+                .name_resolve(Some(&key.id), false, false)
+                .await
+                .ok()
+                .and_then(|res| {
+                    res.path.strip_prefix("/ipfs/").map(str::to_string)
+                });
+        }
+        info.peer_id = Some(key.id);
+
+        if let Some(cid) = &info.cid {
+            // Stat fetches the DAG root, which may never arrive
+            let stat = tokio::time::timeout(
+                self.resolve_timeout,
+                self.client.files_stat(&format!("/ipfs/{}", cid)),
+            )
+            .await;
+            if let Ok(Ok(stat)) = stat {
+                info.dag_size = Some(stat.cumulative_size);
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// Fetch and parse the IPNS record for `peer_id` from the routing
+    /// system, or `None` if it cannot be found.
+    async fn fetch_record(&self, peer_id: &str) -> Option<IpnsRecord> {
+        // The text encoding returns the raw record rather than base64 JSON
+        let url = format!(
+            "{}/api/v0/routing/get?arg=/ipns/{}&encoding=text",
+            self.rpc_url, peer_id
+        );
+        let response = reqwest::Client::new()
+            .post(url)
+            .timeout(self.resolve_timeout)
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        IpnsRecord::parse(&response.bytes().await.ok()?)
+    }
+
+    /// Get the envelope at `arid`, failing unless its digest is `digest`.
+    ///
+    /// Content addressing ties a CID to its bytes, but nothing ties the CID
+    /// an IPNS name resolves to with the envelope the writer stored. A
+    /// reader that learned the envelope's digest from the writer, such as
+    /// through a reference or attachment, can detect resolution to
+    /// unexpected content. Returns
+    /// [`Error::DigestMismatch`](super::Error::DigestMismatch) on a
+    /// mismatch.
+    pub async fn get_verified(
         &self,
         arid: &ARID,
+        digest: &Digest,
         timeout_seconds: Option<u64>,
         verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        use crate::logging::verbose_println;
+
+        let deadline = self.deadline(timeout_seconds);
+        let Some(cid) = self.resolve_cid(arid, deadline, verbose).await? else {
+            return Ok(None);
+        };
+        let envelope = self.fetch(arid, &cid, verbose).await?;
+        let actual = envelope.digest();
+        if actual != *digest {
+            return Err(IpfsError::DigestMismatch {
+                cid,
+                expected: digest.ur_string(),
+                actual: actual.ur_string(),
+            }
+            .into());
+        }
+        if verbose {
+            verbose_println("Envelope digest verified");
+        }
+        Ok(Some(envelope))
+    }
+
+    /// Get the envelope at `arid` and pin its content in the local IPFS
+    /// node, so it stays available after the publisher stops providing it.
+    ///
+    /// Returns the envelope and the CID pinned, or `None` if nothing is
+    /// published at `arid` within the timeout.
+    pub async fn get_and_pin(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<(Envelope, String)>> {
+        let deadline = self.deadline(timeout_seconds);
+        self.get_and_pin_until(arid, deadline.into_std(), verbose)
+            .await
+    }
+
+    /// Like [`get_and_pin`](Self::get_and_pin), polling until `deadline`.
+    pub async fn get_and_pin_until(
+        &self,
+        arid: &ARID,
+        deadline: std::time::Instant,
+        verbose: bool,
+    ) -> Result<Option<(Envelope, String)>> {
+        use crate::logging::verbose_println;
+
+        let Some(cid) =
+            self.resolve_cid(arid, deadline.into(), verbose).await?
+        else {
+            return Ok(None);
+        };
+        let envelope = self.fetch(arid, &cid, verbose).await?;
+        pin_cid(&self.client, &cid, true).await?;
+        if verbose {
+            verbose_println(&format!("Pinned CID: {}", cid));
+        }
+        Ok(Some((envelope, cid)))
+    }
+
+    /// Unpin the content published at `arid`, letting the local IPFS node
+    /// garbage-collect it.
+    ///
+    /// Returns the CID unpinned, or `None` if nothing is published at `arid`
+    /// within the timeout.
+    pub async fn unpin(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<String>> {
+        use crate::logging::verbose_println;
+
+        let deadline = self.deadline(timeout_seconds);
+        let Some(cid) = self.resolve_cid(arid, deadline, verbose).await? else {
+            return Ok(None);
+        };
+        unpin_cid(&self.client, &cid, true).await?;
+        if verbose {
+            verbose_println(&format!("Unpinned CID: {}", cid));
+        }
+        Ok(Some(cid))
+    }
+
+    /// When a lookup given `timeout_seconds` gives up, defaulting to the
+    /// resolve timeout.
+    fn deadline(&self, timeout_seconds: Option<u64>) -> Instant {
+        let timeout = timeout_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(self.resolve_timeout);
+        Instant::now() + timeout
+    }
+
+    /// Internal get implementation with typed errors, polling until
+    /// `deadline`.
+    async fn get_impl(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
     ) -> crate::Result<Option<Envelope>> {
-        use crate::logging::{verbose_newline, verbose_println};
+        use crate::logging::verbose_println;
 
         if verbose {
             verbose_println("Starting IPFS get operation");
         }
 
-        let key_name = derive_ipfs_key_name(arid);
+        let Some(cid) = self.resolve_cid(arid, deadline, verbose).await? else {
+            return Ok(None);
+        };
+        let envelope = self.fetch(arid, &cid, verbose).await?;
+        expiry::log_if_expired(&envelope, verbose);
+
+        if verbose {
+            verbose_println("IPFS get operation completed");
+        }
+
+        Ok(Some(envelope))
+    }
+
+    /// Resolve the CID published at `arid`, polling until `deadline`.
+    async fn resolve_cid(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> crate::Result<Option<String>> {
+        use crate::logging::verbose_println;
+
+        let key_name = derive_ipfs_key_name(&self.domain, arid);
 
         // Get key info from cache or daemon
         if verbose {
@@ -398,20 +751,11 @@ impl IpfsKv {
 
         let peer_id = &key.unwrap().id;
 
-        // Resolve IPNS to CID with specified timeout
+        // Resolve IPNS to CID by the deadline
         if verbose {
             verbose_println("Resolving IPNS name (polling)");
         }
-        let timeout = timeout_seconds
-            .map(Duration::from_secs)
-            .unwrap_or(self.resolve_timeout);
-        let cid = self
-            .resolve_with_retry_timeout(peer_id, timeout, verbose)
-            .await?;
-
-        if verbose {
-            verbose_newline();
-        }
+        let cid = self.resolve_with_retry(peer_id, deadline, verbose).await?;
 
         if cid.is_none() {
             if verbose {
@@ -426,32 +770,72 @@ impl IpfsKv {
             verbose_println(&format!("Resolved to CID: {}", cid));
         }
 
+        Ok(Some(cid))
+    }
+
+    /// Fetch and deobfuscate the envelope stored for `arid` at `cid`.
+    async fn fetch(
+        &self,
+        arid: &ARID,
+        cid: &str,
+        verbose: bool,
+    ) -> crate::Result<Envelope> {
+        use crate::logging::verbose_println;
+
         // Cat CID to get obfuscated bytes
         if verbose {
             verbose_println("Fetching content from IPFS");
         }
-        let obfuscated_bytes = cat_bytes(&self.client, &cid).await?;
+        let obfuscated_bytes = cat_bytes(&self.client, cid).await?;
 
-        // Deobfuscate using ARID-derived key
-        let deobfuscated = obfuscate_with_arid(arid, &obfuscated_bytes);
+        // Deobfuscate using ARID-derived key and deserialize the envelope
+        let envelope = ObfuscatedPayload::from_bytes(obfuscated_bytes)
+            .decode_in(&self.domain, arid)?;
 
         if verbose {
             verbose_println("Deobfuscated envelope data");
         }
 
-        // Deserialize envelope from deobfuscated data
-        let envelope = Envelope::try_from_cbor_data(deobfuscated)?;
+        Ok(envelope)
+    }
 
-        if verbose {
-            verbose_println("IPFS get operation completed");
+    /// Internal delete implementation with typed errors.
+    async fn delete_impl(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> crate::Result<bool> {
+        use crate::logging::verbose_println;
+
+        let key_name = derive_ipfs_key_name(&self.domain, arid);
+        let keys = self.client.key_list().await.map_err(IpfsError::from)?;
+        if !keys.keys.iter().any(|k| k.name == key_name) {
+            return Ok(false);
         }
 
-        Ok(Some(envelope))
+        // Content that no longer resolves has nothing left to unpin
+        if let Ok(Some(cid)) =
+            self.resolve_cid(arid, Instant::now(), verbose).await
+            && unpin_cid(&self.client, &cid, true).await.is_ok()
+            && verbose
+        {
+            verbose_println(&format!("Unpinned CID: {}", cid));
+        }
+
+        self.client
+            .key_rm(&key_name)
+            .await
+            .map_err(IpfsError::from)?;
+        self.key_cache.write().unwrap().remove(&key_name);
+        if verbose {
+            verbose_println("Removed IPNS key");
+        }
+        Ok(true)
     }
 
     /// Internal exists implementation with typed errors.
     async fn exists_impl(&self, arid: &ARID) -> crate::Result<bool> {
-        let key_name = derive_ipfs_key_name(arid);
+        let key_name = derive_ipfs_key_name(&self.domain, arid);
 
         // List keys to check if key exists
         let keys = self.client.key_list().await.map_err(IpfsError::from)?;
@@ -1,6 +1,9 @@
+mod config;
 mod error;
 mod kv;
+mod record;
 mod value;
 
+pub use config::IpfsConfig;
 pub use error::Error;
-pub use kv::IpfsKv;
+pub use kv::{DEFAULT_MAX_ENVELOPE_SIZE, IpfsKv, ResolveInfo};
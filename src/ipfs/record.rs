@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+/// Fields of a signed IPNS record (the `IpnsEntry` protobuf) useful when
+/// diagnosing resolution.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct IpnsRecord {
+    /// The path the name points to, such as `/ipfs/<cid>`.
+    pub value: Option<String>,
+    /// When the record stops being valid, as an RFC 3339 timestamp.
+    pub validity: Option<String>,
+    pub sequence: Option<u64>,
+    /// How long resolvers may cache the record.
+    pub ttl: Option<Duration>,
+}
+
+const VALUE: u64 = 1;
+const VALIDITY: u64 = 4;
+const SEQUENCE: u64 = 5;
+const TTL: u64 = 6;
+
+impl IpnsRecord {
+    /// Parse the record's V1 fields, ignoring everything else. Returns
+    /// `None` if `bytes` is not a well-formed protobuf message.
+    pub(super) fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut record = Self::default();
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let key = read_varint(&mut rest)?;
+            let (field, wire_type) = (key >> 3, key & 7);
+            match wire_type {
+                0 => {
+                    let value = read_varint(&mut rest)?;
+                    match field {
+                        SEQUENCE => record.sequence = Some(value),
+                        TTL => record.ttl = Some(Duration::from_nanos(value)),
+                        _ => {}
+                    }
+                }
+                2 => {
+                    let len = usize::try_from(read_varint(&mut rest)?).ok()?;
+                    if len > rest.len() {
+                        return None;
+                    }
+                    let (data, tail) = rest.split_at(len);
+                    rest = tail;
+                    let text = || String::from_utf8(data.to_vec()).ok();
+                    match field {
+                        VALUE => record.value = text(),
+                        VALIDITY => record.validity = text(),
+                        _ => {}
+                    }
+                }
+                1 => rest = rest.get(8..)?,
+                5 => rest = rest.get(4..)?,
+                _ => return None,
+            }
+        }
+        Some(record)
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn bytes_field(field: u64, data: &[u8], out: &mut Vec<u8>) {
+        varint(field << 3 | 2, out);
+        varint(data.len() as u64, out);
+        out.extend_from_slice(data);
+    }
+
+    #[test]
+    fn test_parse_v1_fields() {
+        let mut record = Vec::new();
+        bytes_field(VALUE, b"/ipfs/bafyexample", &mut record);
+        bytes_field(2, &[0xaa; 64], &mut record); // signature, skipped
+        varint(3 << 3, &mut record); // validity type, skipped
+        varint(0, &mut record);
+        bytes_field(VALIDITY, b"2030-01-01T00:00:00Z", &mut record);
+        varint(SEQUENCE << 3, &mut record);
+        varint(7, &mut record);
+        varint(TTL << 3, &mut record);
+        varint(3_600_000_000_000, &mut record);
+
+        let parsed = IpnsRecord::parse(&record).unwrap();
+        assert_eq!(parsed.value.as_deref(), Some("/ipfs/bafyexample"));
+        assert_eq!(parsed.validity.as_deref(), Some("2030-01-01T00:00:00Z"));
+        assert_eq!(parsed.sequence, Some(7));
+        assert_eq!(parsed.ttl, Some(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated() {
+        let mut record = Vec::new();
+        bytes_field(VALIDITY, b"2030-01-01T00:00:00Z", &mut record);
+        assert!(IpnsRecord::parse(&record[..record.len() - 1]).is_none());
+        assert_eq!(IpnsRecord::parse(&[]), Some(IpnsRecord::default()));
+    }
+}
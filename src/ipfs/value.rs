@@ -37,6 +37,16 @@ pub async fn pin_cid(
     Ok(())
 }
 
+/// Unpin a CID so local IPFS storage may garbage-collect it.
+pub async fn unpin_cid(
+    client: &IpfsClient,
+    cid: &str,
+    recursive: bool,
+) -> Result<(), Error> {
+    client.pin_rm(cid, recursive).await?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use bc_envelope::Envelope;
@@ -1,7 +1,254 @@
-use bc_components::ARID;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bc_components::{ARID, Digest, DigestProvider};
 use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use futures_util::{
+    StreamExt, TryStreamExt,
+    future::join_all,
+    stream::{self, LocalBoxStream},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    EntryMeta, Error, Receipt, Result,
+    wire::{Codec, stored_size},
+};
+
+/// First pause between rounds of a batched [`KvStore::get_all`].
+const POLL_ALL_INITIAL_DELAY: Duration = Duration::from_millis(250);
+
+/// Longest pause between rounds of a batched [`KvStore::get_all`].
+const POLL_ALL_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// First pause between lookups of a polling [`KvStore::watch`].
+const WATCH_INITIAL_DELAY: Duration = Duration::from_millis(250);
+
+/// Longest pause between lookups of a polling [`KvStore::watch`], so an
+/// envelope is noticed within this long of becoming visible.
+const WATCH_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// What a store supports, as reported by [`KvStore::capabilities`].
+///
+/// The default describes a store that promises nothing beyond write-once
+/// puts and gets.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct StoreCapabilities {
+    /// The largest serialized envelope the store accepts, if limited.
+    pub max_value_size: Option<usize>,
+    /// Whether puts honor `ttl_seconds`. Stores without TTL support ignore
+    /// it.
+    pub ttl: bool,
+    /// Whether entries can be deleted before they expire.
+    pub delete: bool,
+    /// Whether an entry can be replaced after it is written.
+    pub update: bool,
+    /// Whether [`KvStore::stat`] reports entries' metadata.
+    #[serde(default)]
+    pub stat: bool,
+}
+
+impl StoreCapabilities {
+    /// What a store that writes to both `self` and `other` can promise:
+    /// the smaller size limit, and only the features both support.
+    pub fn intersect(self, other: Self) -> Self {
+        let max_value_size = match (self.max_value_size, other.max_value_size) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Self {
+            max_value_size,
+            ttl: self.ttl && other.ttl,
+            delete: self.delete && other.delete,
+            update: self.update && other.update,
+            stat: self.stat && other.stat,
+        }
+    }
+
+    /// Whether a serialized envelope of `size` bytes fits.
+    pub fn fits(&self, size: usize) -> bool {
+        self.max_value_size.is_none_or(|limit| size <= limit)
+    }
+}
+
+/// Whether a store lets [`KvStore::update`] replace entries, chosen when
+/// the store is constructed.
+///
+/// Write-once is the default and what most protocols want: a reader that
+/// fetched an envelope knows no one can swap it. A store made
+/// [`Updatable`](Self::Updatable) serves channels whose writer republishes
+/// at the same ARID instead. Puts stay write-once either way, so only code
+/// that calls `update` can replace anything.
+///
+/// Parses from and serializes as `write-once` or `updatable`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mutability {
+    /// Updates are refused with [`Error::Unsupported`].
+    #[default]
+    WriteOnce,
+    /// Updates replace the entry and bump its sequence number.
+    Updatable,
+}
+
+impl Mutability {
+    /// Fail with [`Error::Unsupported`] unless updates are allowed.
+    pub(crate) fn check_update(self) -> Result<()> {
+        match self {
+            Self::WriteOnce => Err(Error::Unsupported { operation: "update" }),
+            Self::Updatable => Ok(()),
+        }
+    }
+
+    pub fn is_updatable(self) -> bool { self == Self::Updatable }
+}
+
+impl std::str::FromStr for Mutability {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "write-once" => Ok(Self::WriteOnce),
+            "updatable" => Ok(Self::Updatable),
+            _ => Err(format!(
+                "unknown mutability {}; expected write-once or updatable",
+                s
+            )),
+        }
+    }
+}
+
+/// Why [`KvStore::validate`] expects a put of an envelope to be rejected.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("Envelope is {size} bytes, over the store's limit of {limit}")]
+    TooLarge { size: usize, limit: usize },
+
+    #[error("Store cannot hold this envelope: {0}")]
+    Incompatible(String),
+}
+
+impl ValidationError {
+    /// Check an envelope that takes `size` bytes against a store's `limit`,
+    /// if it has one.
+    pub fn check_size(
+        size: usize,
+        limit: Option<usize>,
+    ) -> std::result::Result<(), Self> {
+        match limit {
+            Some(limit) if size > limit => Err(Self::TooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The outcome of probing a store with [`KvStore::health`].
+///
+/// Serializes with the latency as whole milliseconds in `latency_ms`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// The kind of store probed, such as `mainline` or `server`.
+    pub backend: String,
+    /// Why the store is unusable, or `None` if the probe succeeded.
+    pub error: Option<String>,
+    /// How long the probe took.
+    #[serde(rename = "latency_ms", with = "millis")]
+    pub latency: Duration,
+    /// The version reported by the backing service, if it has one.
+    pub version: Option<String>,
+    /// The largest serialized envelope the store accepts, if limited.
+    pub max_value_size: Option<usize>,
+    /// Reports for the stores this one is built from, such as the DHT and
+    /// IPFS layers of a hybrid store.
+    pub components: Vec<HealthReport>,
+}
 
-use crate::Result;
+impl HealthReport {
+    /// Time `probe` against `backend`. The probe resolves to the version
+    /// reported by the backing service, if any; an error marks the store
+    /// unavailable.
+    pub async fn probe(
+        backend: impl Into<String>,
+        probe: impl Future<Output = Result<Option<String>>>,
+    ) -> Self {
+        let start = Instant::now();
+        let result = probe.await;
+        let latency = start.elapsed();
+        let (version, error) = match result {
+            Ok(version) => (version, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Self {
+            backend: backend.into(),
+            error,
+            latency,
+            version,
+            max_value_size: None,
+            components: Vec::new(),
+        }
+    }
+
+    /// A report for a store built from `components`, which is available
+    /// only while all of them are. Its latency is that of the slowest.
+    pub fn combine(
+        backend: impl Into<String>,
+        components: Vec<HealthReport>,
+    ) -> Self {
+        let error = components.iter().find_map(|c| {
+            c.error.as_ref().map(|e| format!("{}: {}", c.backend, e))
+        });
+        let latency = components
+            .iter()
+            .map(|c| c.latency)
+            .max()
+            .unwrap_or_default();
+        Self {
+            backend: backend.into(),
+            error,
+            latency,
+            version: None,
+            max_value_size: None,
+            components,
+        }
+    }
+
+    /// Set the largest serialized envelope the store accepts.
+    pub fn with_max_value_size(mut self, size: usize) -> Self {
+        self.max_value_size = Some(size);
+        self
+    }
+
+    /// Whether the probe succeeded.
+    pub fn is_available(&self) -> bool { self.error.is_none() }
+}
+
+/// Serde for a [`Duration`] as whole milliseconds.
+mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
 
 /// Unified trait for key-value storage backends using ARID-based addressing.
 ///
@@ -9,6 +256,11 @@ use crate::Result;
 /// at an ARID, subsequent attempts to write to the same ARID will fail with an
 /// `AlreadyExists` error.
 ///
+/// `MemoryKv`, `SqliteKv`, and servers configured with `idempotent_puts` can
+/// let a put of the envelope already stored succeed instead, so a client may
+/// retry a put whose response it lost. Other backends cannot verify that the
+/// stored envelope is the same, and always refuse.
+///
 /// # Security Model
 ///
 /// - ARID holder can read (by deriving storage key)
@@ -78,6 +330,25 @@ use crate::Result;
 /// This limitation comes from underlying network client libraries and is
 /// typical for async I/O code. It does not prevent concurrent operations - each
 /// thread simply needs to `.await` its own futures locally.
+///
+/// To use a store from `tokio::spawn`, wrap it in a
+/// [`SendKv`](crate::SendKv), which runs it on a thread of its own and
+/// implements [`KvStoreSend`](crate::KvStoreSend) with `Send` futures.
+///
+/// # Trait Objects
+///
+/// `Arc<S>` and `Box<S>` are stores whenever `S` is, including
+/// `dyn KvStore`, so a store chosen at runtime can be passed wherever
+/// `impl KvStore` is expected:
+///
+/// ```no_run
+/// # use hubert::{KvStore, MemoryKv, ReadOnlyKv, sharded::ShardedKv};
+/// use std::sync::Arc;
+///
+/// let shared: Arc<dyn KvStore> = Arc::new(MemoryKv::new());
+/// let tier = ShardedKv::new(1).with_shard("a", Arc::clone(&shared));
+/// let readers = ReadOnlyKv::new(shared);
+/// ```
 #[async_trait::async_trait(?Send)]
 pub trait KvStore: Send + Sync {
     /// Store an envelope at the given ARID.
@@ -96,14 +367,17 @@ pub trait KvStore: Send + Sync {
     ///   envelope may be removed from storage.
     ///   - **Mainline DHT**: Ignored (no TTL support)
     ///   - **IPFS**: Used as IPNS record lifetime (default: 24h if None)
-    ///   - **Server**: Clamped to max_ttl if exceeded; uses max_ttl if None.
-    ///     All entries expire (hubert is for coordination, not long-term
-    ///     storage).
+    ///   - **Hybrid**: Used as the IPNS record lifetime for envelopes stored in
+    ///     IPFS; ignored for envelopes small enough for the DHT
+    ///   - **Server**: Clamped to max_ttl if exceeded; uses default_ttl (or
+    ///     max_ttl) if None. All entries expire (hubert is for coordination,
+    ///     not long-term storage).
     /// - `verbose`: If true, log operations with timestamps
     ///
     /// # Returns
     ///
-    /// A receipt containing storage metadata on success, or an error if:
+    /// A [`Receipt`] saying where the envelope was stored, under what TTL,
+    /// and how large it was on success, or an error if:
     /// - The ARID already exists (AlreadyExists)
     /// - The envelope is too large for this backend
     /// - Network operation fails
@@ -137,7 +411,7 @@ pub trait KvStore: Send + Sync {
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<String>;
+    ) -> Result<Receipt>;
 
     /// Retrieve an envelope for the given ARID.
     ///
@@ -149,8 +423,11 @@ pub trait KvStore: Send + Sync {
     ///
     /// - `arid`: The ARID to look up
     /// - `timeout_seconds`: Maximum time to wait for the envelope to appear. If
-    ///   `None`, uses a backend-specific default (typically 30 seconds). After
-    ///   timeout, returns `Ok(None)` rather than continuing to poll.
+    ///   `None`, uses a backend-specific default (30 seconds for the built-in
+    ///   stores). `Some(0)` makes exactly one lookup and returns without
+    ///   polling, though the lookup itself may take as long as the backend
+    ///   needs. After timeout, returns `Ok(None)` rather than continuing to
+    ///   poll.
     /// - `verbose`: If true, log operations with timestamps and print polling
     ///   dots
     ///
@@ -211,4 +488,866 @@ pub trait KvStore: Send + Sync {
     /// # }
     /// ```
     async fn exists(&self, arid: &ARID) -> Result<bool>;
+
+    /// Remove the envelope at `arid` before it expires, so a counterparty
+    /// that has not read it yet never will.
+    ///
+    /// Knowing the ARID is the proof of ownership, as it is for reading;
+    /// servers that require capabilities also require the `put` right.
+    /// Stores that keep tombstones leave one, so the ARID still cannot be
+    /// written again while it is kept; otherwise it can be, as after
+    /// expiry. Copies a reader already fetched are beyond reach.
+    ///
+    /// Returns whether an envelope was removed. Stores that support this
+    /// report [`StoreCapabilities::delete`]; the default implementation
+    /// fails with [`Error::Unsupported`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// # async fn example(store: &impl hubert::KvStore, arid: &ARID) {
+    /// if store.delete(arid, false).await.unwrap() {
+    ///     println!("Retracted");
+    /// }
+    /// # }
+    /// ```
+    async fn delete(&self, _arid: &ARID, _verbose: bool) -> Result<bool> {
+        Err(Error::Unsupported { operation: "delete" })
+    }
+
+    /// Describe the entry at `arid` without fetching it: its size, when it
+    /// was stored, and how long it will remain available.
+    ///
+    /// Returns `Ok(None)` if there is no unexpired entry. Stores that
+    /// support this report [`StoreCapabilities::stat`]; the default
+    /// implementation fails with [`Error::Unsupported`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// # async fn example(store: &impl hubert::KvStore, arid: &ARID) {
+    /// let meta = store.stat(arid, false).await.unwrap();
+    /// if meta.and_then(|meta| meta.ttl_remaining).is_some_and(|t| t < 600) {
+    ///     println!("Expires within 10 minutes; republish");
+    /// }
+    /// # }
+    /// ```
+    async fn stat(
+        &self,
+        _arid: &ARID,
+        _verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        Err(Error::Unsupported { operation: "stat" })
+    }
+
+    /// Replace the envelope at `arid`, or store it if there is none, on a
+    /// store constructed with [`Mutability::Updatable`].
+    ///
+    /// Each update gives the entry the next sequence number, reported in the
+    /// receipt's [`sequence`](Receipt::sequence): Mainline republishes the
+    /// BEP-44 item with its `seq` bumped, IPFS republishes the IPNS name,
+    /// whose record carries the sequence, and servers version the row.
+    /// `ttl_seconds` applies afresh, as for a put. Readers see one version
+    /// or another, never a mix; caches and DHT nodes may serve the older one
+    /// for a while.
+    ///
+    /// An ARID that is tombstoned or reserved cannot be updated any more
+    /// than put to, and fails with [`Error::AlreadyExists`]. Stores that
+    /// support this report [`StoreCapabilities::update`] once made
+    /// updatable; otherwise, and by default, it fails with
+    /// [`Error::Unsupported`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// # use bc_envelope::Envelope;
+    /// use hubert::{KvStore, MemoryKv, Mutability};
+    ///
+    /// # async fn example() {
+    /// let store = MemoryKv::new().with_mutability(Mutability::Updatable);
+    /// let status = ARID::new();
+    /// for round in 1..=3 {
+    ///     let envelope = Envelope::new(format!("Round {}", round));
+    ///     let receipt =
+    ///         store.update(&status, &envelope, None, false).await.unwrap();
+    ///     assert_eq!(receipt.sequence, Some(round));
+    /// }
+    /// # }
+    /// ```
+    async fn update(
+        &self,
+        _arid: &ARID,
+        _envelope: &Envelope,
+        _ttl_seconds: Option<u64>,
+        _verbose: bool,
+    ) -> Result<Receipt> {
+        Err(Error::Unsupported { operation: "update" })
+    }
+
+    /// Retrieve the envelopes at several ARIDs, waiting for them
+    /// concurrently.
+    ///
+    /// Every ARID is polled until its envelope appears or `timeout_seconds`
+    /// (shared by all of them, with the same meaning as for
+    /// [`get`](Self::get)) runs out. Results arrive in the order of `arids`,
+    /// each as soon as it and every ARID before it are settled. An error
+    /// ends the stream.
+    ///
+    /// The default implementation runs one [`get`](Self::get) per ARID
+    /// concurrently. Stores with a cheaper bulk lookup poll all pending ARIDs
+    /// in one query per round instead, backing off between rounds.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn example(store: &impl hubert::KvStore, slots: &[ARID]) {
+    /// let mut results = store.get_all(slots, Some(60), false);
+    /// while let Some(result) = results.next().await {
+    ///     let (arid, envelope) = result.unwrap();
+    ///     match envelope {
+    ///         Some(_) => println!("{} filled", arid),
+    ///         None => println!("{} still empty", arid),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    fn get_all<'a>(
+        &'a self,
+        arids: &'a [ARID],
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>> {
+        stream::iter(arids)
+            .map(move |arid| async move {
+                let envelope = self.get(arid, timeout_seconds, verbose).await?;
+                Ok((*arid, envelope))
+            })
+            .buffered(arids.len().max(1))
+            .boxed_local()
+    }
+
+    /// Wait for an envelope to appear at `arid`, with no timeout.
+    ///
+    /// The stream yields the envelope once it is stored. On a write-once
+    /// store it then ends, since there is never a second. On a store whose
+    /// [`capabilities`](Self::capabilities) report `update`, it goes on and
+    /// yields each new version an [`update`](Self::update) stores, told
+    /// apart by digest. An error, such as the entry expiring on a server
+    /// keeping tombstones, is yielded in place of an envelope and ends the
+    /// stream. Drop the stream to stop watching, or bound it with
+    /// `tokio::time::timeout`.
+    ///
+    /// The default implementation looks the ARID up with
+    /// [`get`](Self::get), backing off from a quarter second to ten seconds
+    /// between lookups. Stores that can be told about puts override it:
+    /// `MemoryKv` and `SqliteKv` look again when a put or update in the
+    /// process wakes the ARID, and `ServerKvClient` holds a `/watch` stream
+    /// open, on which the server pushes each version as it is stored.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// use std::time::Duration;
+    ///
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn example(store: &impl hubert::KvStore, reply: ARID) {
+    /// let mut replies = store.watch(&reply, false);
+    /// let wait = Duration::from_secs(600);
+    /// if let Ok(Some(envelope)) =
+    ///     tokio::time::timeout(wait, replies.next()).await
+    /// {
+    ///     println!("Reply: {}", envelope.unwrap().format());
+    /// }
+    /// # }
+    /// ```
+    fn watch<'a>(
+        &'a self,
+        arid: &'a ARID,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<Envelope>> {
+        let updates = self.capabilities().update;
+        poll_watch(WATCH_INITIAL_DELAY, updates, move |_| {
+            self.get(arid, Some(0), verbose)
+        })
+    }
+
+    /// Store several envelopes, all with the same `ttl_seconds` or, without
+    /// one, each until its own expiry, returning one receipt per entry in
+    /// order.
+    ///
+    /// Stores that can apply a batch atomically do: `MemoryKv` and `SqliteKv`
+    /// store every entry or none, and so does `ServerKvClient` for each
+    /// `/put_many` request of up to a hundred entries. They fail with
+    /// [`Error::AlreadyExists`] if any ARID is taken, including by an
+    /// earlier entry of the same batch, and need only one lock, one
+    /// transaction, or one request per hundred entries.
+    ///
+    /// The default implementation runs one [`put`](Self::put) per entry
+    /// concurrently, so a failure may leave some entries stored; check with
+    /// [`exists`](Self::exists) before retrying.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// # use bc_envelope::Envelope;
+    /// # async fn example(store: &impl hubert::KvStore) {
+    /// let entries: Vec<(ARID, Envelope)> = (0..50)
+    ///     .map(|i| (ARID::new(), Envelope::new(format!("Share {}", i))))
+    ///     .collect();
+    /// store.put_many(&entries, Some(3600), false).await.unwrap();
+    /// # }
+    /// ```
+    async fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        join_all(entries.iter().map(|(arid, envelope)| {
+            self.put(arid, envelope, ttl_seconds, verbose)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Look up several ARIDs once each, without polling, returning their
+    /// envelopes in the order of `arids`.
+    ///
+    /// This is [`get_all`](Self::get_all) with a timeout of zero, so stores
+    /// with a bulk lookup answer in one query or request per round, and the
+    /// server in one `/get_many` request per hundred ARIDs.
+    async fn get_many(
+        &self,
+        arids: &[ARID],
+        verbose: bool,
+    ) -> Result<Vec<Option<Envelope>>> {
+        self.get_all(arids, Some(0), verbose)
+            .map_ok(|(_, envelope)| envelope)
+            .try_collect()
+            .await
+    }
+
+    /// Retrieve an envelope like [`get`](Self::get), polling until
+    /// `deadline` at the latest.
+    ///
+    /// Lets several operations share one overall deadline instead of each
+    /// taking its own timeout. A deadline already passed makes a single
+    /// lookup.
+    ///
+    /// The default implementation calls `get` with the whole seconds left,
+    /// so it may give up to a second early. Stores that poll against a
+    /// deadline internally override it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_components::ARID;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # async fn example(store: &impl hubert::KvStore, slots: [ARID; 2]) {
+    /// // Both responses must arrive within one minute in total
+    /// let deadline = Instant::now() + Duration::from_secs(60);
+    /// let first = store.get_until(&slots[0], deadline, false).await.unwrap();
+    /// let second = store.get_until(&slots[1], deadline, false).await.unwrap();
+    /// # }
+    /// ```
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.get(arid, Some(seconds_until(deadline)), verbose).await
+    }
+
+    /// Store an envelope like [`put`](Self::put), giving up at `deadline`.
+    ///
+    /// Returns [`Error::DeadlineExceeded`] if the put has not finished by
+    /// then. The put is abandoned wherever it was, so it may or may not have
+    /// taken effect; check with [`exists`](Self::exists) before retrying.
+    async fn put_until(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let put = self.put(arid, envelope, ttl_seconds, verbose);
+        tokio::time::timeout_at(deadline.into(), put)
+            .await
+            .map_err(|_| Error::DeadlineExceeded)?
+    }
+
+    /// Describe what this store supports, so callers can validate a put
+    /// before attempting it instead of hardcoding per-backend rules.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_envelope::prelude::*;
+    /// # fn example(store: &impl hubert::KvStore, envelope: &Envelope) {
+    /// let capabilities = store.capabilities();
+    /// if !capabilities.fits(envelope.to_cbor_data().len()) {
+    ///     println!("Envelope is too large for this store");
+    /// }
+    /// if !capabilities.ttl {
+    ///     println!("Entries stay until the store drops them");
+    /// }
+    /// # }
+    /// ```
+    fn capabilities(&self) -> StoreCapabilities { StoreCapabilities::default() }
+
+    /// Check, without network calls, whether a put of `envelope` would be
+    /// rejected for its size or form, so applications can warn before a
+    /// long DHT or IPFS round trip fails.
+    ///
+    /// An envelope that passes may still fail to store for other reasons,
+    /// such as its ARID being taken or the backend being down.
+    ///
+    /// The default implementation checks the envelope's
+    /// [`stored_size`](crate::wire::stored_size) against
+    /// [`capabilities`](Self::capabilities).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bc_envelope::prelude::*;
+    /// # fn example(store: &impl hubert::KvStore, envelope: &Envelope) {
+    /// if let Err(e) = store.validate(envelope) {
+    ///     println!("Will not store: {}", e);
+    /// }
+    /// # }
+    /// ```
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        ValidationError::check_size(
+            stored_size(envelope, Codec::Dcbor),
+            self.capabilities().max_value_size,
+        )
+    }
+
+    /// Probe the store and report whether it is usable.
+    ///
+    /// Network-backed stores make one round trip to their backing service,
+    /// so the report's latency reflects current conditions. Failures are
+    /// reported in [`HealthReport::error`] rather than returned, so a report
+    /// is always available to display.
+    ///
+    /// The default implementation times an [`exists`](Self::exists) check
+    /// on a fresh ARID.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(store: &impl hubert::KvStore) {
+    /// let report = store.health().await;
+    /// match &report.error {
+    ///     None => println!("{} is up ({:?})", report.backend, report.latency),
+    ///     Some(e) => println!("{} is down: {}", report.backend, e),
+    /// }
+    /// # }
+    /// ```
+    async fn health(&self) -> HealthReport {
+        let report =
+            HealthReport::probe(std::any::type_name::<Self>(), async {
+                self.exists(&ARID::new()).await.map(|_| None)
+            })
+            .await;
+        HealthReport {
+            max_value_size: self.capabilities().max_value_size,
+            ..report
+        }
+    }
+}
+
+/// Forward every method, including those with default implementations, so
+/// a wrapped store keeps its own behavior.
+macro_rules! forward_kv_store {
+    ($wrapper:ident) => {
+        #[async_trait::async_trait(?Send)]
+        impl<S: KvStore + ?Sized> KvStore for $wrapper<S> {
+            async fn put(
+                &self,
+                arid: &ARID,
+                envelope: &Envelope,
+                ttl_seconds: Option<u64>,
+                verbose: bool,
+            ) -> Result<Receipt> {
+                (**self).put(arid, envelope, ttl_seconds, verbose).await
+            }
+
+            async fn get(
+                &self,
+                arid: &ARID,
+                timeout_seconds: Option<u64>,
+                verbose: bool,
+            ) -> Result<Option<Envelope>> {
+                (**self).get(arid, timeout_seconds, verbose).await
+            }
+
+            async fn exists(&self, arid: &ARID) -> Result<bool> {
+                (**self).exists(arid).await
+            }
+
+            async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+                (**self).delete(arid, verbose).await
+            }
+
+            async fn stat(
+                &self,
+                arid: &ARID,
+                verbose: bool,
+            ) -> Result<Option<EntryMeta>> {
+                (**self).stat(arid, verbose).await
+            }
+
+            async fn update(
+                &self,
+                arid: &ARID,
+                envelope: &Envelope,
+                ttl_seconds: Option<u64>,
+                verbose: bool,
+            ) -> Result<Receipt> {
+                (**self).update(arid, envelope, ttl_seconds, verbose).await
+            }
+
+            fn get_all<'a>(
+                &'a self,
+                arids: &'a [ARID],
+                timeout_seconds: Option<u64>,
+                verbose: bool,
+            ) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>> {
+                (**self).get_all(arids, timeout_seconds, verbose)
+            }
+
+            fn watch<'a>(
+                &'a self,
+                arid: &'a ARID,
+                verbose: bool,
+            ) -> LocalBoxStream<'a, Result<Envelope>> {
+                (**self).watch(arid, verbose)
+            }
+
+            async fn put_many(
+                &self,
+                entries: &[(ARID, Envelope)],
+                ttl_seconds: Option<u64>,
+                verbose: bool,
+            ) -> Result<Vec<Receipt>> {
+                (**self).put_many(entries, ttl_seconds, verbose).await
+            }
+
+            async fn get_many(
+                &self,
+                arids: &[ARID],
+                verbose: bool,
+            ) -> Result<Vec<Option<Envelope>>> {
+                (**self).get_many(arids, verbose).await
+            }
+
+            async fn get_until(
+                &self,
+                arid: &ARID,
+                deadline: Instant,
+                verbose: bool,
+            ) -> Result<Option<Envelope>> {
+                (**self).get_until(arid, deadline, verbose).await
+            }
+
+            async fn put_until(
+                &self,
+                arid: &ARID,
+                envelope: &Envelope,
+                ttl_seconds: Option<u64>,
+                deadline: Instant,
+                verbose: bool,
+            ) -> Result<Receipt> {
+                (**self)
+                    .put_until(arid, envelope, ttl_seconds, deadline, verbose)
+                    .await
+            }
+
+            fn capabilities(&self) -> StoreCapabilities {
+                (**self).capabilities()
+            }
+
+            fn validate(
+                &self,
+                envelope: &Envelope,
+            ) -> std::result::Result<(), ValidationError> {
+                (**self).validate(envelope)
+            }
+
+            async fn health(&self) -> HealthReport { (**self).health().await }
+        }
+    };
+}
+
+forward_kv_store!(Arc);
+forward_kv_store!(Box);
+
+/// Whole seconds left until `deadline`, or 0 if it has passed.
+pub(crate) fn seconds_until(deadline: Instant) -> u64 {
+    deadline.saturating_duration_since(Instant::now()).as_secs()
+}
+
+/// Fail with [`Error::AlreadyExists`] if an ARID appears twice in a batch
+/// put, since the second entry would find it taken by the first.
+pub(crate) fn check_distinct(entries: &[(ARID, Envelope)]) -> Result<()> {
+    let mut seen = HashSet::with_capacity(entries.len());
+    match entries.iter().find(|(arid, _)| !seen.insert(*arid)) {
+        Some((arid, _)) => Err(Error::AlreadyExists { arid: arid.ur_string() }),
+        None => Ok(()),
+    }
+}
+
+/// Implement [`KvStore::watch`] with `lookup`, repeated until it finds the
+/// envelope.
+///
+/// Pauses between lookups start at `initial_delay` and double up to
+/// [`WATCH_MAX_DELAY`]. A `lookup` that itself waits for a put, such as a
+/// get with a timeout on a store that is woken by puts, passes
+/// `Duration::ZERO` to repeat it at once.
+///
+/// With `updates` set, the stream goes on after the first envelope and
+/// yields each one whose digest differs from the last yielded, which
+/// `lookup` is given so it can wait for a change. Without it, the stream
+/// ends after the first.
+pub(crate) fn poll_watch<'a, F, Fut>(
+    initial_delay: Duration,
+    updates: bool,
+    lookup: F,
+) -> LocalBoxStream<'a, Result<Envelope>>
+where
+    F: FnMut(Option<Digest>) -> Fut + 'a,
+    Fut: Future<Output = Result<Option<Envelope>>> + 'a,
+{
+    stream::unfold(Some((lookup, None)), move |watching| async move {
+        let (mut lookup, last) = watching?;
+        let mut delay = initial_delay;
+        loop {
+            match lookup(last.clone()).await {
+                Err(e) => return Some((Err(e), None)),
+                Ok(Some(envelope)) if last != Some(envelope.digest()) => {
+                    let digest = envelope.digest();
+                    let next = updates.then(|| (lookup, Some(digest)));
+                    return Some((Ok(envelope), next));
+                }
+                Ok(_) => {}
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(WATCH_MAX_DELAY);
+        }
+    })
+    .boxed_local()
+}
+
+/// Implement [`KvStore::get_all`] with `lookup`, which returns whichever of
+/// the given ARIDs hold an envelope in one round trip.
+///
+/// Rounds repeat until every ARID is found or the timeout passes, with one
+/// exponential backoff shared by all pending ARIDs.
+pub(crate) fn poll_all<'a, F, Fut>(
+    arids: &'a [ARID],
+    timeout_seconds: Option<u64>,
+    verbose: bool,
+    lookup: F,
+) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>>
+where
+    F: FnMut(Vec<ARID>) -> Fut + 'a,
+    Fut: Future<Output = Result<HashMap<ARID, Envelope>>> + 'a,
+{
+    use crate::logging::verbose_println;
+
+    struct State<F> {
+        lookup: F,
+        next: usize,
+        found: HashMap<ARID, Envelope>,
+        polled: bool,
+        failed: bool,
+        delay: Duration,
+    }
+
+    let timeout = timeout_seconds.unwrap_or(30);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout);
+    let state = State {
+        lookup,
+        next: 0,
+        found: HashMap::new(),
+        polled: false,
+        failed: false,
+        delay: POLL_ALL_INITIAL_DELAY,
+    };
+    stream::unfold(state, move |mut state| async move {
+        if state.failed {
+            return None;
+        }
+        loop {
+            let arid = *arids.get(state.next)?;
+            if let Some(envelope) = state.found.get(&arid).cloned() {
+                state.next += 1;
+                return Some((Ok((arid, Some(envelope))), state));
+            }
+
+            let now = tokio::time::Instant::now();
+            if state.polled {
+                if now >= deadline {
+                    if verbose {
+                        verbose_println(&format!(
+                            "GET {} NOT_FOUND (timeout after {}s)",
+                            arid.ur_string(),
+                            timeout
+                        ));
+                    }
+                    state.next += 1;
+                    return Some((Ok((arid, None)), state));
+                }
+                tokio::time::sleep(state.delay.min(deadline - now)).await;
+                state.delay = (state.delay * 2).min(POLL_ALL_MAX_DELAY);
+            }
+
+            let pending: HashSet<ARID> = arids[state.next..]
+                .iter()
+                .filter(|arid| !state.found.contains_key(arid))
+                .copied()
+                .collect();
+            if verbose && !state.polled {
+                verbose_println(&format!(
+                    "Polling for {} ARIDs (timeout: {}s)",
+                    pending.len(),
+                    timeout
+                ));
+            }
+            match (state.lookup)(pending.into_iter().collect()).await {
+                Ok(found) => state.found.extend(found),
+                Err(e) => {
+                    state.failed = true;
+                    return Some((Err(e), state));
+                }
+            }
+            state.polled = true;
+        }
+    })
+    .boxed_local()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt::Backend;
+
+    #[test]
+    fn test_intersect_keeps_common_support() {
+        let dht = StoreCapabilities {
+            max_value_size: Some(1000),
+            ..Default::default()
+        };
+        let server = StoreCapabilities { ttl: true, ..Default::default() };
+        let both = dht.intersect(server);
+        assert_eq!(both.max_value_size, Some(1000));
+        assert!(!both.ttl);
+        assert!(both.fits(1000));
+        assert!(!both.fits(1001));
+        assert!(server.fits(usize::MAX));
+    }
+
+    #[test]
+    fn test_validate_checks_capabilities() {
+        struct LimitedKv;
+
+        #[async_trait::async_trait(?Send)]
+        impl KvStore for LimitedKv {
+            async fn put(
+                &self,
+                _arid: &ARID,
+                _envelope: &Envelope,
+                _ttl_seconds: Option<u64>,
+                _verbose: bool,
+            ) -> Result<Receipt> {
+                unreachable!()
+            }
+
+            async fn get(
+                &self,
+                _arid: &ARID,
+                _timeout_seconds: Option<u64>,
+                _verbose: bool,
+            ) -> Result<Option<Envelope>> {
+                unreachable!()
+            }
+
+            async fn exists(&self, _arid: &ARID) -> Result<bool> {
+                unreachable!()
+            }
+
+            fn capabilities(&self) -> StoreCapabilities {
+                StoreCapabilities {
+                    max_value_size: Some(10),
+                    ..Default::default()
+                }
+            }
+        }
+
+        // d8c8d8c96548656c6c6f
+        assert_eq!(LimitedKv.validate(&Envelope::new("Hello")), Ok(()));
+        assert_eq!(
+            LimitedKv.validate(&Envelope::new("Hello!")),
+            Err(ValidationError::TooLarge { size: 11, limit: 10 })
+        );
+        assert_eq!(
+            Arc::new(LimitedKv).validate(&Envelope::new("Hello")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_health_report_json() {
+        let report = HealthReport::combine(
+            "hybrid",
+            vec![HealthReport {
+                backend: "mainline".into(),
+                error: None,
+                latency: Duration::from_millis(120),
+                version: None,
+                max_value_size: Some(1000),
+                components: Vec::new(),
+            }],
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["latency_ms"], 120);
+        assert_eq!(json["components"][0]["max_value_size"], 1000);
+        let parsed: HealthReport = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[tokio::test]
+    async fn test_poll_all_keeps_order() {
+        let arids = [ARID::new(), ARID::new(), ARID::new()];
+        let [late, early, missing] = arids;
+        let mut rounds = 0;
+        let results: Vec<_> = poll_all(&arids, Some(1), false, |pending| {
+            rounds += 1;
+            // Found ARIDs are not asked for again
+            assert_eq!(pending.contains(&early), rounds == 1);
+            let mut found = HashMap::new();
+            if rounds == 1 {
+                found.insert(early, Envelope::new("Early"));
+            } else if rounds == 3 {
+                found.insert(late, Envelope::new("Late"));
+            }
+            std::future::ready(Ok(found))
+        })
+        .collect()
+        .await;
+
+        let results: Vec<_> = results.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            results,
+            [
+                (late, Some(Envelope::new("Late"))),
+                (early, Some(Envelope::new("Early"))),
+                (missing, None),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_watch_backs_off() {
+        let start = tokio::time::Instant::now();
+        let mut lookups = 0;
+        let found: Vec<_> = poll_watch(WATCH_INITIAL_DELAY, false, |_| {
+            lookups += 1;
+            let envelope = (lookups == 4).then(|| Envelope::new("Ready"));
+            std::future::ready(Ok(envelope))
+        })
+        .collect()
+        .await;
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].as_ref().unwrap(), &Envelope::new("Ready"));
+        // Pauses of 250, 500, and 1000 ms
+        assert_eq!(start.elapsed(), Duration::from_millis(1750));
+    }
+
+    /// A store whose puts take a second and whose gets report the timeout
+    /// they were given.
+    struct SlowKv;
+
+    #[async_trait::async_trait(?Send)]
+    impl KvStore for SlowKv {
+        async fn put(
+            &self,
+            arid: &ARID,
+            _envelope: &Envelope,
+            _ttl_seconds: Option<u64>,
+            _verbose: bool,
+        ) -> Result<Receipt> {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(Receipt::new(Backend::Memory, *arid, "memory:", 0))
+        }
+
+        async fn get(
+            &self,
+            _arid: &ARID,
+            timeout_seconds: Option<u64>,
+            _verbose: bool,
+        ) -> Result<Option<Envelope>> {
+            Ok(Some(Envelope::new(timeout_seconds.unwrap())))
+        }
+
+        async fn exists(&self, _arid: &ARID) -> Result<bool> { Ok(false) }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_shims() {
+        let (arid, envelope) = (ARID::new(), Envelope::new("Slow"));
+        let soon = Instant::now() + Duration::from_millis(2500);
+        let get = SlowKv.get_until(&arid, soon, false).await.unwrap();
+        assert_eq!(get, Some(Envelope::new(2u64)));
+        let passed = Instant::now() - Duration::from_secs(1);
+        let get = SlowKv.get_until(&arid, passed, false).await.unwrap();
+        assert_eq!(get, Some(Envelope::new(0u64)));
+
+        let result = SlowKv
+            .put_until(&arid, &envelope, None, passed, false)
+            .await;
+        assert!(matches!(result, Err(Error::DeadlineExceeded)));
+        let later = Instant::now() + Duration::from_secs(5);
+        assert!(
+            SlowKv
+                .put_until(&arid, &envelope, None, later, false)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pointers_forward_to_store() {
+        let arid = ARID::new();
+        let soon = Instant::now() + Duration::from_millis(2500);
+        let shared: Arc<dyn KvStore> = Arc::new(SlowKv);
+        let boxed: Box<dyn KvStore> = Box::new(Arc::clone(&shared));
+        for store in [&shared as &dyn KvStore, &boxed] {
+            let get = store.get_until(&arid, soon, false).await.unwrap();
+            assert_eq!(get, Some(Envelope::new(2u64)));
+            assert_eq!(store.capabilities(), SlowKv.capabilities());
+        }
+
+        // Health names the store behind the pointers
+        let report = boxed.health().await;
+        assert!(report.backend.ends_with("SlowKv"), "{}", report.backend);
+    }
 }
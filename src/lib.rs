@@ -1,12 +1,51 @@
+// Each backend uses only its own derivations
+#[cfg_attr(not(all(feature = "mainline", feature = "ipfs")), allow(dead_code))]
 mod arid_derivation;
+pub mod attachments;
+pub mod chaos;
+#[cfg(feature = "depot")]
+pub mod depot;
+pub mod duration;
+pub mod entry_meta;
 mod error;
+pub mod expiry;
+pub mod filter;
+#[cfg(feature = "hybrid")]
 pub mod hybrid;
+#[cfg(feature = "ipfs")]
 pub mod ipfs;
 mod kv_store;
 pub mod logging;
+#[cfg(feature = "mainline")]
 pub mod mainline;
+pub mod names;
+pub mod paths;
+pub mod payload;
+pub mod protocol;
+pub mod read_only;
+pub mod receipt;
+pub mod send_kv;
 pub mod server;
+pub mod session;
+pub mod sharded;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod threshold;
+pub mod ur_input;
+#[cfg(feature = "hybrid")]
+pub mod vectors;
+pub mod wire;
 
-pub use error::{Error, Result};
-pub use kv_store::KvStore;
-pub use server::{MemoryKv, SqliteKv};
+pub use entry_meta::EntryMeta;
+pub use error::{Error, ErrorCategory, ErrorReport, Result};
+pub use hubert_core;
+pub use kv_store::{
+    HealthReport, KvStore, Mutability, StoreCapabilities, ValidationError,
+};
+pub use read_only::ReadOnlyKv;
+pub use receipt::Receipt;
+pub use send_kv::{KvStoreSend, SendKv};
+pub use server::MemoryKv;
+#[cfg(feature = "server")]
+pub use server::SqliteKv;
+pub use session::{Session, SessionSlot};
@@ -1,6 +1,5 @@
 /// Logging utilities for verbose output with timestamps.
-use std::io::{self, Write};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 /// Format a timestamp in ISO-8601 Zulu format with fractional seconds.
 pub fn timestamp() -> String {
@@ -70,20 +69,102 @@ pub fn timestamp() -> String {
     )
 }
 
-/// Print a verbose message with timestamp prefix.
+/// Print a verbose message with timestamp prefix to stderr, keeping stdout
+/// for data.
 pub fn verbose_println(message: &str) {
     if !message.is_empty() {
-        println!("[{}] {}", timestamp(), message);
+        eprintln!("[{}] {}", timestamp(), message);
     }
 }
 
-/// Print a polling dot on the same line (no newline).
-pub fn verbose_print_dot() {
-    print!(".");
-    let _ = io::stdout().flush();
+/// Time between status lines while a get polls.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress of a polling get.
+///
+/// In verbose mode, each backend reports its polling as status lines giving
+/// the phase, attempt count, elapsed time, and time left before the
+/// deadline: one on the first miss, then at most one every five seconds.
+///
+/// ```text
+/// [2025-10-18T10:11:31.162Z] server: attempt 6, 5.0s elapsed, 25.0s left
+/// ```
+pub struct PollProgress {
+    phase: String,
+    verbose: bool,
+    started: Instant,
+    deadline: Instant,
+    attempts: u32,
+    reported: Option<Instant>,
 }
 
-/// Print a newline after dots.
-pub fn verbose_newline() {
-    println!();
+impl PollProgress {
+    /// Start tracking a get in `phase` that polls until `deadline`.
+    pub fn new(
+        phase: impl Into<String>,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Self {
+        Self {
+            phase: phase.into(),
+            verbose,
+            started: Instant::now(),
+            deadline,
+            attempts: 0,
+            reported: None,
+        }
+    }
+
+    /// Record a lookup that found nothing, printing a status line if one is
+    /// due.
+    pub fn missed(&mut self) {
+        self.attempts += 1;
+        let now = Instant::now();
+        if !self.verbose
+            || self
+                .reported
+                .is_some_and(|at| now.duration_since(at) < PROGRESS_INTERVAL)
+        {
+            return;
+        }
+        self.reported = Some(now);
+        verbose_println(&self.status(now));
+    }
+
+    /// The number of lookups that found nothing so far.
+    pub fn attempts(&self) -> u32 { self.attempts }
+
+    fn status(&self, now: Instant) -> String {
+        format!(
+            "{}: attempt {}, {:.1}s elapsed, {:.1}s left",
+            self.phase,
+            self.attempts,
+            now.duration_since(self.started).as_secs_f64(),
+            self.deadline.saturating_duration_since(now).as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_progress() {
+        let now = Instant::now();
+        let mut progress =
+            PollProgress::new("server", now + Duration::from_secs(30), false);
+        progress.missed();
+        progress.missed();
+        assert_eq!(progress.attempts(), 2);
+
+        let status = progress.status(progress.started + Duration::from_secs(5));
+        assert!(status.starts_with("server: attempt 2, 5.0s elapsed, "));
+        assert!(status.ends_with("s left"));
+        assert!(
+            progress
+                .status(now + Duration::from_secs(60))
+                .ends_with(" 0.0s left")
+        );
+    }
 }
@@ -0,0 +1,122 @@
+use std::{net::Ipv4Addr, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    MainlineDhtKv, keepalive::DEFAULT_INTERVAL,
+    throttle::DEFAULT_MAX_CONCURRENT,
+};
+use crate::Result;
+
+/// Every setting of a [`MainlineDhtKv`], for building one from a
+/// configuration file with [`MainlineDhtKv::with_config`].
+///
+/// Every field is optional in a file; missing fields take their default
+/// values.
+///
+/// ```toml
+/// port = 6881
+/// bind_address = "192.168.1.10"
+/// request_timeout_ms = 2000
+/// verify_put = true
+/// max_queries_per_second = 20
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MainlineConfig {
+    /// UDP port for the DHT node (default: chosen by the OS).
+    pub port: Option<u16>,
+    /// Address of the interface to bind to (default: all interfaces).
+    pub bind_address: Option<Ipv4Addr>,
+    /// Milliseconds to wait for each DHT node to respond to a request.
+    pub request_timeout_ms: Option<u64>,
+    /// File of known-good nodes to bootstrap from and save to.
+    pub routing_cache: Option<PathBuf>,
+    /// Largest serialized envelope to accept (default: 1000 bytes).
+    pub max_value_size: usize,
+    /// Salt separating this store's namespace from others.
+    pub salt: Option<Vec<u8>>,
+    /// Derivation domain isolating this deployment's entries (default:
+    /// none); see [`MainlineDhtKv::with_domain`].
+    pub domain: String,
+    /// Confirm each put by reading it back from a second node.
+    pub verify_put: bool,
+    /// DHT queries allowed to run at once (default: 16).
+    pub max_concurrent_queries: usize,
+    /// DHT queries allowed to start per second (default: unlimited).
+    pub max_queries_per_second: Option<u32>,
+    /// Seconds between republishing kept-alive entries (default: 3600).
+    pub keepalive_interval: u64,
+}
+
+impl Default for MainlineConfig {
+    fn default() -> Self {
+        Self {
+            port: None,
+            bind_address: None,
+            request_timeout_ms: None,
+            routing_cache: None,
+            max_value_size: 1000, // DHT protocol limit
+            salt: None,
+            domain: String::new(),
+            verify_put: false,
+            max_concurrent_queries: DEFAULT_MAX_CONCURRENT,
+            max_queries_per_second: None,
+            keepalive_interval: DEFAULT_INTERVAL.as_secs(),
+        }
+    }
+}
+
+impl MainlineDhtKv {
+    /// Start a store with every setting taken from `config`.
+    pub async fn with_config(config: MainlineConfig) -> Result<Self> {
+        let mut builder = Self::builder();
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let Some(address) = config.bind_address {
+            builder = builder.bind_address(address);
+        }
+        if let Some(ms) = config.request_timeout_ms {
+            builder = builder.request_timeout(Duration::from_millis(ms));
+        }
+        if let Some(path) = config.routing_cache {
+            builder = builder.routing_cache(path);
+        }
+
+        let mut store = builder
+            .build()
+            .await?
+            .with_max_size(config.max_value_size)
+            .with_verify_put(config.verify_put)
+            .with_max_concurrent_queries(config.max_concurrent_queries)
+            .with_keepalive_interval(Duration::from_secs(
+                config.keepalive_interval,
+            ));
+        if let Some(max) = config.max_queries_per_second {
+            store = store.with_max_queries_per_second(max);
+        }
+        if let Some(salt) = config.salt {
+            store = store.with_salt(salt);
+        }
+        Ok(store.with_domain(config.domain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: MainlineConfig =
+            toml::from_str("port = 6881\nverify_put = true").unwrap();
+        assert_eq!(config.port, Some(6881));
+        assert!(config.verify_put);
+        assert_eq!(config.max_value_size, 1000);
+        assert_eq!(config.max_concurrent_queries, DEFAULT_MAX_CONCURRENT);
+        assert_eq!(config.keepalive_interval, 3600);
+
+        assert!(toml::from_str::<MainlineConfig>("prot = 6881").is_err());
+    }
+}
@@ -1,8 +1,11 @@
 /// Mainline DHT-specific errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Value size {size} exceeds DHT limit of 1000 bytes")]
-    ValueTooLarge { size: usize },
+    #[error("Value size {size} exceeds DHT limit of {limit} bytes")]
+    ValueTooLarge { size: usize, limit: usize },
+
+    #[error("Put not observable from an independent node within {seconds}s")]
+    PutNotVerified { seconds: u64 },
 
     #[error("DHT operation error: {0}")]
     DhtError(String),
@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bc_components::ARID;
+use mainline::{MutableItem, async_dht::AsyncDht};
+use tokio::task::JoinHandle;
+
+use super::throttle::Throttle;
+
+/// How long DHT nodes keep an item they have not seen republished.
+pub const DHT_ITEM_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Default time between refreshes, well within [`DHT_ITEM_LIFETIME`].
+pub(super) const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a put keeps its item alive.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Refresh {
+    /// Not at all; the item lapses on the DHT's own schedule.
+    Never,
+    /// For as long as the store exists.
+    Forever,
+    /// Until the deadline, or the store is dropped first.
+    Until(Instant),
+}
+
+/// An item being republished, until `until` if set.
+struct Kept {
+    item: MutableItem,
+    until: Option<Instant>,
+}
+
+/// Items republished periodically so they outlive the DHT's expiry.
+///
+/// The refresh task starts with the first registered item and stops when
+/// the registry is dropped. Items registered until a deadline are forgotten
+/// once it passes, and then lapse from the DHT on its own schedule.
+pub(super) struct KeepAlive {
+    dht: AsyncDht,
+    throttle: Throttle,
+    interval: Duration,
+    items: Arc<Mutex<HashMap<ARID, Kept>>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl KeepAlive {
+    pub(super) fn new(dht: AsyncDht, throttle: Throttle) -> Self {
+        Self {
+            dht,
+            throttle,
+            interval: DEFAULT_INTERVAL,
+            items: Arc::new(Mutex::new(HashMap::new())),
+            task: Mutex::new(None),
+        }
+    }
+
+    pub(super) fn set_throttle(&mut self, throttle: Throttle) {
+        self.throttle = throttle;
+    }
+
+    pub(super) fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Start republishing `item`, the entry stored at `arid`, until `until`
+    /// if given.
+    pub(super) fn insert(
+        &self,
+        arid: ARID,
+        item: MutableItem,
+        until: Option<Instant>,
+    ) {
+        self.items
+            .lock()
+            .unwrap()
+            .insert(arid, Kept { item, until });
+
+        let mut task = self.task.lock().unwrap();
+        if task.is_none() {
+            let dht = self.dht.clone();
+            let throttle = self.throttle.clone();
+            let items = Arc::clone(&self.items);
+            let interval = self.interval;
+            *task = Some(tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    let due: Vec<MutableItem> = {
+                        let mut items = items.lock().unwrap();
+                        let now = Instant::now();
+                        items.retain(|_, kept| {
+                            kept.until.is_none_or(|until| now < until)
+                        });
+                        items.values().map(|kept| kept.item.clone()).collect()
+                    };
+                    // A failed refresh is retried on the next pass
+                    for item in due {
+                        let _permit = throttle.acquire().await;
+                        let _ = dht.put_mutable(item, None).await;
+                    }
+                }
+            }));
+        }
+    }
+
+    /// Republish `item` in place of the entry at `arid`, after an update,
+    /// if that entry is kept alive.
+    pub(super) fn replace(&self, arid: &ARID, item: MutableItem) {
+        if let Some(kept) = self.items.lock().unwrap().get_mut(arid) {
+            kept.item = item;
+        }
+    }
+
+    /// Stop republishing the entry at `arid`. Returns whether it was kept
+    /// alive.
+    pub(super) fn remove(&self, arid: &ARID) -> bool {
+        self.items.lock().unwrap().remove(arid).is_some()
+    }
+
+    pub(super) fn arids(&self) -> Vec<ARID> {
+        self.items.lock().unwrap().keys().copied().collect()
+    }
+}
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+}
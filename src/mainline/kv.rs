@@ -1,29 +1,48 @@
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
 use bc_components::ARID;
 use bc_envelope::Envelope;
 use bc_ur::UREncodable;
 use dcbor::CBOREncodable;
-use mainline::{Dht, MutableItem, SigningKey};
+use mainline::{Dht, Id, MutableItem, SigningKey, async_dht::AsyncDht};
 
-use super::error::Error as MainlineError;
+use super::{
+    QueueStats,
+    error::Error as MainlineError,
+    keepalive::{KeepAlive, Refresh},
+    throttle::{DEFAULT_MAX_CONCURRENT, Throttle},
+};
 use crate::{
-    Error, KvStore, Result,
-    arid_derivation::{derive_mainline_key, obfuscate_with_arid},
+    Error, HealthReport, KvStore, Mutability, Receipt, Result,
+    StoreCapabilities, ValidationError, expiry,
+    receipt::Backend,
+    wire::{Codec, ObfuscatedPayload, stored_size},
 };
 
+/// How long a verified put waits for its value to be observable.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Mainline DHT-backed key-value store using ARID-based addressing.
 ///
 /// This implementation uses:
 /// - ARID → ed25519 signing key derivation (deterministic)
 /// - BEP-44 mutable storage (fixed location based on pubkey)
 /// - Mainline DHT (BitTorrent DHT) for decentralized storage
-/// - Write-once semantics (seq=1, put fails if already exists)
+/// - Write-once semantics (seq=1, put fails if already exists), unless
+///   made updatable with [`MainlineDhtKv::with_mutability`]
 /// - Maximum value size: 1000 bytes (DHT protocol limit)
+/// - Entries fade after about two hours unless republished; see
+///   [`MainlineDhtKv::put_with_keepalive`]
 ///
 /// # Storage Model
 ///
 /// Uses BEP-44 mutable items where:
 /// - Public key derived from ARID (deterministic ed25519)
-/// - Sequence number starts at 1 (write-once)
+/// - Sequence number starts at 1, and only updates bump it
 /// - Optional salt for namespace separation
 /// - Location fixed by pubkey (not content hash)
 ///
@@ -58,26 +77,133 @@ use crate::{
 /// # }
 /// ```
 pub struct MainlineDhtKv {
-    dht: mainline::async_dht::AsyncDht,
+    dht: AsyncDht,
     max_value_size: usize,
     salt: Option<Vec<u8>>,
+    domain: String,
+    keepalive: KeepAlive,
+    verify_put: bool,
+    /// Separate DHT node used to confirm puts, created on first use.
+    verifier: tokio::sync::OnceCell<AsyncDht>,
+    throttle: Throttle,
+    network: MainlineDhtKvBuilder,
+    mutability: Mutability,
 }
 
-impl MainlineDhtKv {
-    /// Create a new Mainline DHT KV store with default settings.
-    pub async fn new() -> Result<Self> {
-        let dht = Dht::client().map_err(MainlineError::from)?.as_async();
+/// Network settings for the DHT node embedded in a [`MainlineDhtKv`].
+///
+/// Firewalled hosts can pin the node's UDP port and interface:
+///
+/// ```no_run
+/// use std::net::Ipv4Addr;
+///
+/// use hubert::mainline::MainlineDhtKv;
+///
+/// # async fn example() -> hubert::Result<()> {
+/// let store = MainlineDhtKv::builder()
+///     .port(6881)
+///     .bind_address(Ipv4Addr::new(192, 168, 1, 10))
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MainlineDhtKvBuilder {
+    port: Option<u16>,
+    bind_address: Option<Ipv4Addr>,
+    request_timeout: Option<Duration>,
+    routing_cache: Option<PathBuf>,
+}
 
-        // Wait for bootstrap
-        dht.bootstrapped().await;
+impl MainlineDhtKvBuilder {
+    /// Listen on this UDP port (default: chosen by the OS).
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Bind to the interface with this address (default: all interfaces).
+    pub fn bind_address(mut self, address: Ipv4Addr) -> Self {
+        self.bind_address = Some(address);
+        self
+    }
+
+    /// How long to wait for each DHT node to respond to a request.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
 
-        Ok(Self {
+    /// Bootstrap from the nodes saved in `path` by
+    /// [`MainlineDhtKv::save_routing_cache`], in addition to the default
+    /// bootstrap nodes. Reusing known-good nodes cuts cold-start time; a
+    /// missing or unreadable file is ignored.
+    pub fn routing_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.routing_cache = Some(path.into());
+        self
+    }
+
+    /// Start the DHT node and wait for it to bootstrap.
+    pub async fn build(self) -> Result<MainlineDhtKv> {
+        let dht = self.start(self.port).await?;
+        let throttle = Throttle::new(DEFAULT_MAX_CONCURRENT, None);
+        Ok(MainlineDhtKv {
+            keepalive: KeepAlive::new(dht.clone(), throttle.clone()),
             dht,
             max_value_size: 1000, // DHT protocol limit
             salt: None,           // No salt by default
+            domain: String::new(),
+            verify_put: false,
+            verifier: tokio::sync::OnceCell::new(),
+            throttle,
+            network: self,
+            mutability: Mutability::WriteOnce,
         })
     }
 
+    /// Start a bootstrapped node with these settings on `port`.
+    async fn start(&self, port: Option<u16>) -> Result<AsyncDht> {
+        let mut builder = Dht::builder();
+        if let Some(port) = port {
+            builder.port(port);
+        }
+        if let Some(address) = self.bind_address {
+            builder.bind_address(address);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder.request_timeout(timeout);
+        }
+        if let Some(path) = &self.routing_cache {
+            builder.extra_bootstrap(&load_routing_cache(path));
+        }
+        let dht = builder.build().map_err(MainlineError::from)?.as_async();
+
+        // Wait for bootstrap
+        dht.bootstrapped().await;
+
+        Ok(dht)
+    }
+}
+
+/// Read saved node addresses, skipping anything malformed.
+fn load_routing_cache(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.parse::<SocketAddrV4>().is_ok())
+        .map(str::to_string)
+        .collect()
+}
+
+impl MainlineDhtKv {
+    /// Create a new Mainline DHT KV store with default settings.
+    pub async fn new() -> Result<Self> { Self::builder().build().await }
+
+    /// Configure the DHT node's network settings before starting it.
+    pub fn builder() -> MainlineDhtKvBuilder { MainlineDhtKvBuilder::default() }
+
     /// Set the maximum value size (default: 1000 bytes).
     ///
     /// Note: Values larger than ~1KB may not be reliably stored in the DHT.
@@ -94,21 +220,242 @@ impl MainlineDhtKv {
         self
     }
 
-    /// Derive an ed25519 signing key from an ARID.
+    /// Derive item keys and obfuscation keys in deployment `domain`
+    /// (default: none), so the same ARID names a different DHT item than in
+    /// other deployments. Unlike [`with_salt`](Self::with_salt), which only
+    /// this backend has, the domain applies to every backend that derives
+    /// from ARIDs. Readers must use the same domain as writers.
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = domain.into();
+        self
+    }
+
+    /// Let [`KvStore::update`] republish entries if `mutability` is
+    /// [`Mutability::Updatable`]. Each update signs the item again with the
+    /// next BEP-44 sequence number, which DHT nodes require to replace it.
+    pub fn with_mutability(mut self, mutability: Mutability) -> Self {
+        self.mutability = mutability;
+        self
+    }
+
+    /// Confirm each put by reading it back before returning (default: off).
+    ///
+    /// The read goes through a second DHT node with its own routing table,
+    /// so success means other parties can find the value, not just that the
+    /// nodes this store contacted accepted it. A put that is not observable
+    /// within 30 seconds fails with
+    /// [`Error::PutNotVerified`](super::Error::PutNotVerified); the value may
+    /// still appear later, so a retry should `get` before putting again.
+    pub fn with_verify_put(mut self, verify: bool) -> Self {
+        self.verify_put = verify;
+        self
+    }
+
+    /// Limit how many DHT queries run at once (default: 16).
+    ///
+    /// Every put, get poll, existence check, and keepalive refresh is one
+    /// query; further queries wait their turn. Lower this for bulk
+    /// operations so they do not flood the network.
+    pub fn with_max_concurrent_queries(mut self, max: usize) -> Self {
+        self.set_throttle(Throttle::new(max, self.throttle.max_per_second()));
+        self
+    }
+
+    /// Limit how many DHT queries start per second (default: unlimited).
+    pub fn with_max_queries_per_second(mut self, max: u32) -> Self {
+        self.set_throttle(Throttle::new(
+            self.throttle.max_concurrent(),
+            Some(max),
+        ));
+        self
+    }
+
+    fn set_throttle(&mut self, throttle: Throttle) {
+        self.keepalive.set_throttle(throttle.clone());
+        self.throttle = throttle;
+    }
+
+    /// Save the nodes in the current routing table to the builder's
+    /// [`routing_cache`](MainlineDhtKvBuilder::routing_cache) file, if any,
+    /// for the next start to bootstrap from.
+    pub async fn save_routing_cache(&self) -> Result<()> {
+        let Some(path) = &self.network.routing_cache else {
+            return Ok(());
+        };
+        let nodes = self.dht.to_bootstrap().await;
+        if nodes.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Replace the file atomically so concurrent runs never read half of it
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, nodes.join("\n") + "\n")?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    /// How many DHT queries are waiting and running right now.
+    pub fn queue_stats(&self) -> QueueStats { self.throttle.stats() }
+
+    /// Set how often kept-alive entries are republished (default: 1 hour).
+    ///
+    /// DHT nodes drop entries after about two hours, so the interval should
+    /// stay well below that.
+    pub fn with_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive.set_interval(interval);
+        self
+    }
+
+    /// Store an envelope like [`KvStore::put`] and keep republishing it for
+    /// as long as this store exists, or until [`Self::stop_keepalive`].
+    ///
+    /// Use this for coordinations that may take longer than the DHT keeps
+    /// entries on its own.
+    pub async fn put_with_keepalive(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.put_impl(arid, envelope, None, Refresh::Forever, verbose)
+            .await
+    }
+
+    /// Store an envelope like [`KvStore::put`] and keep republishing it
+    /// until `lifetime` has passed, so it stays in the DHT for at least that
+    /// long if this store lives as long.
+    ///
+    /// Once the lifetime is up the entry is left to lapse, which takes up to
+    /// [`DHT_ITEM_LIFETIME`](super::DHT_ITEM_LIFETIME) more.
+    pub async fn put_with_keepalive_for(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        lifetime: Duration,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let until = Refresh::Until(Instant::now() + lifetime);
+        self.put_impl(arid, envelope, None, until, verbose).await
+    }
+
+    /// Keep republishing the entry already stored at `arid`, whoever stored
+    /// it. Returns `false` if no entry was found.
+    pub async fn keepalive(&self, arid: &ARID) -> Result<bool> {
+        let pubkey = self.derive_signing_key(arid).verifying_key().to_bytes();
+        match self.fetch(&self.dht, &pubkey).await {
+            Some(item) => {
+                self.keepalive.insert(*arid, item, None);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Stop republishing the entry at `arid`. Returns whether it was being
+    /// kept alive.
+    pub fn stop_keepalive(&self, arid: &ARID) -> bool {
+        self.keepalive.remove(arid)
+    }
+
+    /// The ARIDs whose entries are being kept alive.
+    pub fn kept_alive(&self) -> Vec<ARID> { self.keepalive.arids() }
+
+    /// Store an envelope as a BEP-44 immutable item.
+    ///
+    /// Immutable items are located by the SHA-1 hash of their value rather
+    /// than by an ARID-derived key, so no signing is needed and anyone
+    /// holding the returned target can fetch the envelope with
+    /// [`Self::get_immutable`]. Storing the same envelope twice yields the
+    /// same target. The envelope is stored as is, not obfuscated, so
+    /// encrypt it first unless it is meant to be public. The same size limit
+    /// as [`KvStore::put`] applies.
+    pub async fn put_immutable(
+        &self,
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Id> {
+        use crate::logging::verbose_println;
+
+        let size = stored_size(envelope, Codec::Bep44);
+        if size > self.max_value_size {
+            return Err(MainlineError::ValueTooLarge {
+                size,
+                limit: self.max_value_size,
+            }
+            .into());
+        }
+        let bytes = envelope.to_cbor_data();
+
+        if verbose {
+            verbose_println("Putting immutable item to DHT");
+        }
+        let _permit = self.throttle.acquire().await;
+        let target = self
+            .dht
+            .put_immutable(&bytes)
+            .await
+            .map_err(MainlineError::from)?;
+
+        if verbose {
+            verbose_println(&format!("Stored immutable item {}", target));
+        }
+        Ok(target)
+    }
+
+    /// Fetch an envelope stored with [`Self::put_immutable`], polling until
+    /// `timeout_seconds` (default: 30) have passed.
     ///
-    /// Uses the ARID-derived key material extended to 32 bytes for ed25519.
-    fn derive_signing_key(arid: &ARID) -> SigningKey {
-        let key_bytes = derive_mainline_key(arid);
+    /// DHT nodes check that an immutable item hashes to its target, so the
+    /// envelope returned is the one that was stored.
+    pub async fn get_immutable(
+        &self,
+        target: Id,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        use tokio::time::sleep;
+
+        use crate::logging::PollProgress;
 
-        // Extend to 32 bytes if needed (ARID gives us 20, we need 32)
-        let mut seed = [0u8; 32];
-        seed[..20].copy_from_slice(&key_bytes[..20]);
-        // Use simple derivation for remaining 12 bytes
-        for i in 20..32 {
-            seed[i] = key_bytes[i % 20].wrapping_mul(i as u8);
+        let timeout = timeout_seconds.unwrap_or(30);
+        let deadline = Instant::now() + Duration::from_secs(timeout);
+        let mut progress =
+            PollProgress::new("mainline immutable", deadline, verbose);
+        loop {
+            let value = {
+                let _permit = self.throttle.acquire().await;
+                self.dht.get_immutable(target).await
+            };
+            if let Some(value) = value {
+                return Ok(Some(Envelope::try_from_cbor_data(value.to_vec())?));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            progress.missed();
+            sleep(Duration::from_secs(1)).await;
         }
+    }
 
-        SigningKey::from_bytes(&seed)
+    /// Derive an ed25519 signing key from an ARID in this store's domain.
+    ///
+    /// Uses the ARID-derived key material extended to 32 bytes for ed25519
+    /// (see [`hubert_core::derive_mainline_seed_in`]).
+    fn derive_signing_key(&self, arid: &ARID) -> SigningKey {
+        SigningKey::from_bytes(&hubert_core::derive_mainline_seed_in(
+            &self.domain,
+            arid.data(),
+        ))
+    }
+
+    /// The ed25519 public key whose mutable item holds the envelope stored
+    /// at `arid`, in a store without a domain.
+    pub fn public_key(arid: &ARID) -> [u8; 32] {
+        let seed = hubert_core::derive_mainline_seed(arid.data());
+        SigningKey::from_bytes(&seed).verifying_key().to_bytes()
     }
 }
 
@@ -120,8 +467,24 @@ impl KvStore for MainlineDhtKv {
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<String> {
-        self.put_impl(arid, envelope, ttl_seconds, verbose).await
+    ) -> Result<Receipt> {
+        self.put_impl(arid, envelope, ttl_seconds, Refresh::Never, verbose)
+            .await
+    }
+
+    /// Republishes the item with the sequence number after the newest one
+    /// found, passing that as the compare-and-swap value, so an update
+    /// racing another from elsewhere fails instead of silently losing to
+    /// it. `ttl_seconds` is ignored, as for puts.
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        _ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.mutability.check_update()?;
+        self.update_impl(arid, envelope, verbose).await
     }
 
     async fn get(
@@ -131,12 +494,63 @@ impl KvStore for MainlineDhtKv {
         verbose: bool,
     ) -> Result<Option<Envelope>> {
         // Polls DHT with specified timeout
-        self.get_impl(arid, timeout_seconds, verbose).await
+        let timeout = timeout_seconds.unwrap_or(30); // Default 30 seconds
+        self.get_impl(
+            arid,
+            Instant::now() + Duration::from_secs(timeout),
+            verbose,
+        )
+        .await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.get_impl(arid, deadline, verbose).await
     }
 
     async fn exists(&self, arid: &ARID) -> Result<bool> {
         self.exists_impl(arid).await
     }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            max_value_size: Some(self.max_value_size),
+            update: self.mutability.is_updatable(),
+            ..Default::default()
+        }
+    }
+
+    /// Measures the envelope bencoded, as DHT nodes do.
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        ValidationError::check_size(
+            stored_size(envelope, Codec::Bep44),
+            Some(self.max_value_size),
+        )
+    }
+
+    async fn health(&self) -> HealthReport {
+        // A lookup only fails to find; an empty routing table means it
+        // reached no one
+        HealthReport::probe("mainline", async {
+            self.exists_impl(&ARID::new()).await?;
+            if self.dht.to_bootstrap().await.is_empty() {
+                return Err(MainlineError::DhtError(
+                    "no DHT nodes reachable".into(),
+                )
+                .into());
+            }
+            Ok(None)
+        })
+        .await
+        .with_max_value_size(self.max_value_size)
+    }
 }
 
 impl MainlineDhtKv {
@@ -146,28 +560,33 @@ impl MainlineDhtKv {
         arid: &ARID,
         envelope: &Envelope,
         _ttl_seconds: Option<u64>, // Ignored - DHT has no TTL support
+        refresh: Refresh,
         verbose: bool,
-    ) -> Result<String> {
+    ) -> Result<Receipt> {
         use crate::logging::verbose_println;
 
         if verbose {
             verbose_println("Starting Mainline DHT put operation");
         }
 
-        // Serialize envelope
-        let bytes = envelope.to_cbor_data();
+        // Serialize and obfuscate with ARID-derived key so it appears as
+        // random data
+        let obfuscated =
+            ObfuscatedPayload::encode_in(&self.domain, arid, envelope);
 
         if verbose {
-            verbose_println(&format!("Envelope size: {} bytes", bytes.len()));
+            verbose_println(&format!(
+                "Envelope size: {} bytes",
+                obfuscated.len()
+            ));
         }
 
-        // Obfuscate with ARID-derived key so it appears as random data
-        let obfuscated = obfuscate_with_arid(arid, &bytes);
-
-        // Check size after obfuscation (same size, but check anyway)
-        if obfuscated.len() > self.max_value_size {
+        // Check size as DHT nodes count it, bencoded
+        let size = stored_size(envelope, Codec::Bep44);
+        if size > self.max_value_size {
             return Err(MainlineError::ValueTooLarge {
-                size: obfuscated.len(),
+                size,
+                limit: self.max_value_size,
             }
             .into());
         }
@@ -180,7 +599,7 @@ impl MainlineDhtKv {
         if verbose {
             verbose_println("Deriving DHT signing key from ARID");
         }
-        let signing_key = Self::derive_signing_key(arid);
+        let signing_key = self.derive_signing_key(arid);
         let pubkey = signing_key.verifying_key().to_bytes();
         let salt_opt = self.salt.as_deref();
 
@@ -188,12 +607,7 @@ impl MainlineDhtKv {
         if verbose {
             verbose_println("Checking for existing value (write-once check)");
         }
-        if self
-            .dht
-            .get_mutable_most_recent(&pubkey, salt_opt)
-            .await
-            .is_some()
-        {
+        if self.fetch(&self.dht, &pubkey).await.is_some() {
             return Err(Error::AlreadyExists { arid: arid.ur_string() });
         }
 
@@ -201,36 +615,146 @@ impl MainlineDhtKv {
         if verbose {
             verbose_println("Creating mutable DHT item");
         }
-        let item = MutableItem::new(signing_key, &obfuscated, 1, salt_opt);
+        let item =
+            MutableItem::new(signing_key, obfuscated.as_bytes(), 1, salt_opt);
 
         // Put to DHT (no CAS since we verified it doesn't exist)
         if verbose {
             verbose_println("Putting value to DHT");
         }
-        self.dht
-            .put_mutable(item, None)
-            .await
-            .map_err(MainlineError::from)?;
+        {
+            let _permit = self.throttle.acquire().await;
+            self.dht
+                .put_mutable(item.clone(), None)
+                .await
+                .map_err(MainlineError::from)?;
+        }
+        if self.verify_put {
+            if verbose {
+                verbose_println("Verifying value from an independent node");
+            }
+            self.verify(&pubkey, obfuscated.as_bytes()).await?;
+        }
+        match refresh {
+            Refresh::Never => {}
+            Refresh::Forever => self.keepalive.insert(*arid, item, None),
+            Refresh::Until(until) => {
+                self.keepalive.insert(*arid, item, Some(until))
+            }
+        }
 
         if verbose {
             verbose_println("Mainline DHT put operation completed");
         }
 
-        Ok(format!("dht://{}", hex::encode(pubkey)))
+        let location = format!("dht://{}", hex::encode(pubkey));
+        let size = stored_size(envelope, Codec::Dcbor);
+        Ok(Receipt::new(Backend::Mainline, *arid, location, size)
+            .with_dht_pubkey(pubkey))
+    }
+
+    /// Internal update implementation with typed errors.
+    async fn update_impl(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        use crate::logging::verbose_println;
+
+        if verbose {
+            verbose_println("Starting Mainline DHT update operation");
+        }
+
+        let size = stored_size(envelope, Codec::Bep44);
+        if size > self.max_value_size {
+            return Err(MainlineError::ValueTooLarge {
+                size,
+                limit: self.max_value_size,
+            }
+            .into());
+        }
+        let obfuscated =
+            ObfuscatedPayload::encode_in(&self.domain, arid, envelope);
+        let signing_key = self.derive_signing_key(arid);
+        let pubkey = signing_key.verifying_key().to_bytes();
+
+        // Follow the newest sequence number the DHT holds
+        let current = self.fetch(&self.dht, &pubkey).await.map(|i| i.seq());
+        let seq = current.map_or(1, |seq| seq + 1);
+        if verbose {
+            verbose_println(&format!("Putting value with sequence {}", seq));
+        }
+        let item = MutableItem::new(
+            signing_key,
+            obfuscated.as_bytes(),
+            seq,
+            self.salt.as_deref(),
+        );
+        {
+            let _permit = self.throttle.acquire().await;
+            self.dht
+                .put_mutable(item.clone(), current)
+                .await
+                .map_err(MainlineError::from)?;
+        }
+        if self.verify_put {
+            if verbose {
+                verbose_println("Verifying value from an independent node");
+            }
+            self.verify(&pubkey, obfuscated.as_bytes()).await?;
+        }
+        // Republishing the replaced item would only be refused
+        self.keepalive.replace(arid, item);
+
+        if verbose {
+            verbose_println("Mainline DHT update operation completed");
+        }
+
+        let location = format!("dht://{}", hex::encode(pubkey));
+        let size = stored_size(envelope, Codec::Dcbor);
+        Ok(Receipt::new(Backend::Mainline, *arid, location, size)
+            .with_dht_pubkey(pubkey)
+            .with_sequence(seq as u64))
+    }
+
+    /// Wait until the verifier node observes `value` at `pubkey`.
+    async fn verify(&self, pubkey: &[u8; 32], value: &[u8]) -> Result<()> {
+        use tokio::time::{Instant, sleep};
+
+        let verifier = self
+            .verifier
+            // Same interface, but its own port so it never shares routing
+            .get_or_try_init(|| self.network.start(None))
+            .await?;
+
+        let deadline = Instant::now() + VERIFY_TIMEOUT;
+        loop {
+            let item = self.fetch(verifier, pubkey).await;
+            if item.is_some_and(|item| item.value().to_vec() == value) {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(MainlineError::PutNotVerified {
+                    seconds: VERIFY_TIMEOUT.as_secs(),
+                }
+                .into());
+            }
+            sleep(Duration::from_secs(1)).await;
+        }
     }
 
-    /// Internal get implementation with typed errors.
+    /// Internal get implementation with typed errors, polling until
+    /// `deadline`.
     async fn get_impl(
         &self,
         arid: &ARID,
-        timeout_seconds: Option<u64>,
+        deadline: Instant,
         verbose: bool,
     ) -> Result<Option<Envelope>> {
-        use tokio::time::{Duration, Instant, sleep};
+        use tokio::time::sleep;
 
-        use crate::logging::{
-            verbose_newline, verbose_print_dot, verbose_println,
-        };
+        use crate::logging::{PollProgress, verbose_println};
 
         if verbose {
             verbose_println("Starting Mainline DHT get operation");
@@ -240,40 +764,37 @@ impl MainlineDhtKv {
         if verbose {
             verbose_println("Deriving DHT public key from ARID");
         }
-        let signing_key = Self::derive_signing_key(arid);
+        let signing_key = self.derive_signing_key(arid);
         let pubkey = signing_key.verifying_key().to_bytes();
-        let salt_opt = self.salt.as_deref();
 
-        let timeout = timeout_seconds.unwrap_or(30); // Default 30 seconds
-        let deadline = Instant::now() + Duration::from_secs(timeout);
         // Changed to 1000ms for verbose mode polling
         let poll_interval = Duration::from_millis(1000);
 
         if verbose {
             verbose_println("Polling DHT for value");
         }
+        let mut progress = PollProgress::new("mainline", deadline, verbose);
 
         loop {
             // Get most recent mutable item
-            let item =
-                self.dht.get_mutable_most_recent(&pubkey, salt_opt).await;
+            let item = self.fetch(&self.dht, &pubkey).await;
 
             if let Some(mutable_item) = item {
                 if verbose {
-                    verbose_newline();
                     verbose_println("Value found in DHT");
                 }
 
-                // Deobfuscate the data using ARID-derived key
-                let obfuscated_bytes = mutable_item.value().to_vec();
-                let deobfuscated = obfuscate_with_arid(arid, &obfuscated_bytes);
+                // Deobfuscate the data using ARID-derived key and
+                // deserialize the envelope
+                let envelope = ObfuscatedPayload::from_bytes(
+                    mutable_item.value().to_vec(),
+                )
+                .decode_in(&self.domain, arid)?;
 
                 if verbose {
                     verbose_println("Deobfuscated envelope data");
                 }
-
-                // Deserialize envelope from deobfuscated data
-                let envelope = Envelope::try_from_cbor_data(deobfuscated)?;
+                expiry::log_if_expired(&envelope, verbose);
 
                 if verbose {
                     verbose_println("Mainline DHT get operation completed");
@@ -286,16 +807,12 @@ impl MainlineDhtKv {
             if Instant::now() >= deadline {
                 // Timeout reached
                 if verbose {
-                    verbose_newline();
                     verbose_println("Timeout reached, value not found");
                 }
                 return Ok(None);
             }
 
-            // Print polling dot if verbose
-            if verbose {
-                verbose_print_dot();
-            }
+            progress.missed();
 
             // Wait before retrying (now 1000ms)
             sleep(poll_interval).await;
@@ -304,12 +821,42 @@ impl MainlineDhtKv {
 
     /// Internal exists implementation with typed errors.
     async fn exists_impl(&self, arid: &ARID) -> Result<bool> {
-        let signing_key = Self::derive_signing_key(arid);
+        let signing_key = self.derive_signing_key(arid);
         let pubkey = signing_key.verifying_key().to_bytes();
-        let salt_opt = self.salt.as_deref();
 
         // Check if mutable item exists
-        let item = self.dht.get_mutable_most_recent(&pubkey, salt_opt).await;
+        let item = self.fetch(&self.dht, &pubkey).await;
         Ok(item.is_some())
     }
+
+    /// Look up the most recent item at `pubkey` through `dht`, waiting for
+    /// the throttle first.
+    async fn fetch(
+        &self,
+        dht: &AsyncDht,
+        pubkey: &[u8; 32],
+    ) -> Option<MutableItem> {
+        let _permit = self.throttle.acquire().await;
+        dht.get_mutable_most_recent(pubkey, self.salt.as_deref())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_routing_cache_skips_malformed_lines() {
+        let path = std::env::temp_dir()
+            .join(format!("hubert-dht-nodes-{}.txt", std::process::id()));
+        std::fs::write(&path, "1.2.3.4:6881\nnot a node\n\n 5.6.7.8:51413 \n")
+            .unwrap();
+        assert_eq!(
+            load_routing_cache(&path),
+            vec!["1.2.3.4:6881".to_string(), "5.6.7.8:51413".to_string()]
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_routing_cache(&path).is_empty());
+    }
 }
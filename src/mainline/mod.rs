@@ -1,5 +1,12 @@
+mod config;
 mod error;
+mod keepalive;
 mod kv;
+mod throttle;
 
+pub use ::mainline::Id;
+pub use config::MainlineConfig;
 pub use error::Error;
-pub use kv::MainlineDhtKv;
+pub use keepalive::DHT_ITEM_LIFETIME;
+pub use kv::{MainlineDhtKv, MainlineDhtKvBuilder};
+pub use throttle::QueueStats;
@@ -0,0 +1,166 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{Instant, sleep_until},
+};
+
+/// Default number of DHT queries allowed in flight at once.
+pub(super) const DEFAULT_MAX_CONCURRENT: usize = 16;
+
+/// Snapshot of a store's DHT query queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    /// Queries waiting for a concurrency slot or rate limit.
+    pub queued: usize,
+    /// Queries currently running against the DHT.
+    pub in_flight: usize,
+}
+
+/// Caps the number of concurrent DHT queries and, optionally, their rate.
+#[derive(Clone)]
+pub(super) struct Throttle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    max_concurrent: usize,
+    max_per_second: Option<u32>,
+    slots: Arc<Semaphore>,
+    next_start: Mutex<Instant>,
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+/// Held for the duration of one DHT query.
+pub(super) struct Permit {
+    inner: Arc<Inner>,
+    _slot: OwnedSemaphorePermit,
+}
+
+/// Counts one query as queued until it is dropped, so an acquire cancelled
+/// while waiting leaves the queue.
+struct Queued<'a>(&'a Inner);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) { self.0.queued.fetch_sub(1, Ordering::Relaxed); }
+}
+
+impl Throttle {
+    pub(super) fn new(
+        max_concurrent: usize,
+        max_per_second: Option<u32>,
+    ) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            inner: Arc::new(Inner {
+                max_concurrent,
+                max_per_second: max_per_second.filter(|&n| n > 0),
+                slots: Arc::new(Semaphore::new(max_concurrent)),
+                next_start: Mutex::new(Instant::now()),
+                queued: AtomicUsize::new(0),
+                in_flight: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub(super) fn max_concurrent(&self) -> usize { self.inner.max_concurrent }
+
+    pub(super) fn max_per_second(&self) -> Option<u32> {
+        self.inner.max_per_second
+    }
+
+    /// Wait for a concurrency slot and, if rate limited, for the next start
+    /// time.
+    pub(super) async fn acquire(&self) -> Permit {
+        let inner = &self.inner;
+        inner.queued.fetch_add(1, Ordering::Relaxed);
+        let queued = Queued(inner);
+        let slot = Arc::clone(&inner.slots)
+            .acquire_owned()
+            .await
+            .expect("throttle semaphore is never closed");
+        if let Some(rate) = inner.max_per_second {
+            let spacing = Duration::from_secs(1) / rate;
+            let start = {
+                let mut next_start = inner.next_start.lock().unwrap();
+                let start = (*next_start).max(Instant::now());
+                *next_start = start + spacing;
+                start
+            };
+            sleep_until(start).await;
+        }
+        drop(queued);
+        inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        Permit { inner: Arc::clone(inner), _slot: slot }
+    }
+
+    pub(super) fn stats(&self) -> QueueStats {
+        QueueStats {
+            queued: self.inner.queued.load(Ordering::Relaxed),
+            in_flight: self.inner.in_flight.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) { self.inner.in_flight.fetch_sub(1, Ordering::Relaxed); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrency_cap() {
+        let throttle = Throttle::new(2, None);
+        let first = throttle.acquire().await;
+        let _second = throttle.acquire().await;
+        assert_eq!(throttle.stats(), QueueStats { queued: 0, in_flight: 2 });
+
+        let waiting = tokio::spawn({
+            let throttle = throttle.clone();
+            async move {
+                let _third = throttle.acquire().await;
+            }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(throttle.stats(), QueueStats { queued: 1, in_flight: 2 });
+
+        drop(first);
+        waiting.await.unwrap();
+        assert_eq!(throttle.stats(), QueueStats { queued: 0, in_flight: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_acquire_leaves_queue() {
+        let throttle = Throttle::new(1, None);
+        let _held = throttle.acquire().await;
+        let result = tokio::time::timeout(
+            Duration::from_millis(10),
+            throttle.acquire(),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(throttle.stats(), QueueStats { queued: 0, in_flight: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let throttle = Throttle::new(10, Some(20));
+        let start = Instant::now();
+        for _ in 0..5 {
+            drop(throttle.acquire().await);
+        }
+        // The fifth query starts four spacings after the first
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+    }
+}
@@ -0,0 +1,346 @@
+//! Human-readable names for ARIDs.
+//!
+//! A name such as `hubert:alice.example/invoice` pairs a domain with a label.
+//! A [`NameResolver`] looks the pair up where the domain's owner published
+//! it, and everything after that works with the ARID alone; names are a
+//! convenience for people copying pointers between tools, never stored in
+//! place of ARIDs.
+//!
+//! Two resolvers are provided:
+//!
+//! - [`WellKnownResolver`] fetches `https://alice.example/.well-known/hubert/invoice`,
+//!   whose body is the `ur:arid`. Needs the `server-client` feature.
+//! - [`DnsTxtResolver`] looks up the TXT record of
+//!   `invoice._hubert.alice.example`, whose text is the `ur:arid`. Needs the
+//!   `dns` feature.
+
+use std::{fmt, str::FromStr};
+
+use bc_components::ARID;
+
+use crate::{Error, Result, ur_input::parse_arid_ur};
+
+/// Prefix of a [`HubertName`].
+pub const NAME_SCHEME: &str = "hubert:";
+
+/// Longest label accepted, the longest a DNS label may be.
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// A human-readable name for an ARID: `hubert:<domain>/<label>`.
+///
+/// The domain is a DNS name. For testing against a local server, it may
+/// carry a port, which only [`WellKnownResolver`] accepts. The label is 1 to
+/// 63 ASCII letters, digits, `-`, or `_`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HubertName {
+    domain: String,
+    label: String,
+}
+
+impl HubertName {
+    pub fn new(domain: &str, label: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            Error::InvalidName(format!(
+                "{}{}/{}: {}",
+                NAME_SCHEME, domain, label, reason
+            ))
+        };
+        let (host, port) = match domain.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (domain, None),
+        };
+        let host_valid = !host.is_empty()
+            && host.len() <= 253
+            && host.split('.').all(|part| {
+                (1..=MAX_LABEL_LENGTH).contains(&part.len())
+                    && part
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                    && !part.starts_with('-')
+                    && !part.ends_with('-')
+            });
+        if !host_valid {
+            return Err(invalid("invalid domain"));
+        }
+        if port.is_some_and(|port| port.parse::<u16>().is_err()) {
+            return Err(invalid("invalid port"));
+        }
+        let label_valid = (1..=MAX_LABEL_LENGTH).contains(&label.len())
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !label_valid {
+            return Err(invalid(
+                "label must be 1 to 63 ASCII letters, digits, '-', or '_'",
+            ));
+        }
+        Ok(Self {
+            domain: domain.to_ascii_lowercase(),
+            label: label.to_string(),
+        })
+    }
+
+    /// Whether `input` is written as a name rather than a `ur:arid`.
+    pub fn is_name(input: &str) -> bool {
+        input.trim().starts_with(NAME_SCHEME)
+    }
+
+    /// The domain, lowercased, with its port if it has one.
+    pub fn domain(&self) -> &str { &self.domain }
+
+    pub fn label(&self) -> &str { &self.label }
+
+    /// The domain without its port.
+    pub fn host(&self) -> &str {
+        self.domain
+            .split_once(':')
+            .map_or(self.domain.as_str(), |(host, _)| host)
+    }
+}
+
+impl FromStr for HubertName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let rest = s.strip_prefix(NAME_SCHEME).ok_or_else(|| {
+            Error::InvalidName(format!(
+                "{} does not start with {}",
+                s, NAME_SCHEME
+            ))
+        })?;
+        let (domain, label) = rest.split_once('/').ok_or_else(|| {
+            Error::InvalidName(format!("{} has no /label", s))
+        })?;
+        Self::new(domain, label)
+    }
+}
+
+impl fmt::Display for HubertName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}/{}", NAME_SCHEME, self.domain, self.label)
+    }
+}
+
+/// Looks up the ARID a [`HubertName`] stands for.
+#[async_trait::async_trait(?Send)]
+pub trait NameResolver {
+    /// The ARID `name` stands for.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::NameNotFound` if the domain publishes nothing for the label
+    /// - `Error::NameResolution` if the lookup itself failed, or what was
+    ///   published is not a `ur:arid`
+    async fn resolve(&self, name: &HubertName) -> Result<ARID>;
+}
+
+/// The ARID `input` stands for: `input` itself if it is a `ur:arid`, or
+/// what `resolver` finds if it is a `hubert:` name.
+pub async fn resolve_arid(
+    input: &str,
+    resolver: &dyn NameResolver,
+) -> Result<ARID> {
+    if HubertName::is_name(input) {
+        resolver.resolve(&input.parse()?).await
+    } else {
+        parse_arid_ur(input)
+    }
+}
+
+/// Parse the published `ur:arid` that `name` resolved to.
+fn published_arid(name: &HubertName, text: &str) -> Result<ARID> {
+    parse_arid_ur(text)
+        .map_err(|e| Error::NameResolution(format!("{} published {}", name, e)))
+}
+
+/// Resolves names from documents served at
+/// `https://<domain>/.well-known/hubert/<label>`.
+///
+/// The document's body is the `ur:arid`, optionally followed by a newline.
+/// A 404 means the domain publishes no such label.
+#[cfg(feature = "server-client")]
+#[derive(Debug, Clone)]
+pub struct WellKnownResolver {
+    client: reqwest::Client,
+    scheme: &'static str,
+}
+
+#[cfg(feature = "server-client")]
+impl WellKnownResolver {
+    pub fn new() -> Self {
+        Self { client: reqwest::Client::new(), scheme: "https" }
+    }
+
+    /// Fetch documents over plain HTTP, for testing against a local server.
+    pub fn with_insecure_http(mut self) -> Self {
+        self.scheme = "http";
+        self
+    }
+
+    /// Where the document for `name` is served.
+    pub fn url(&self, name: &HubertName) -> String {
+        format!(
+            "{}://{}/.well-known/hubert/{}",
+            self.scheme, name.domain, name.label
+        )
+    }
+}
+
+#[cfg(feature = "server-client")]
+impl Default for WellKnownResolver {
+    fn default() -> Self { Self::new() }
+}
+
+#[cfg(feature = "server-client")]
+#[async_trait::async_trait(?Send)]
+impl NameResolver for WellKnownResolver {
+    async fn resolve(&self, name: &HubertName) -> Result<ARID> {
+        let failed = |e: reqwest::Error| {
+            Error::NameResolution(format!("{}: {}", name, e))
+        };
+        let response = self
+            .client
+            .get(self.url(name))
+            .send()
+            .await
+            .map_err(failed)?;
+        match response.status() {
+            reqwest::StatusCode::OK => {}
+            reqwest::StatusCode::NOT_FOUND => {
+                return Err(Error::NameNotFound { name: name.to_string() });
+            }
+            status => {
+                return Err(Error::NameResolution(format!(
+                    "{}: server answered {}",
+                    name, status
+                )));
+            }
+        }
+        let text = response.text().await.map_err(failed)?;
+        published_arid(name, &text)
+    }
+}
+
+/// Resolves names from DNS TXT records at `<label>._hubert.<domain>`.
+///
+/// The record's text is the `ur:arid`. A domain may publish one record per
+/// label; if it publishes several, the first that parses is used.
+#[cfg(feature = "dns")]
+pub struct DnsTxtResolver {
+    resolver: hickory_resolver::TokioResolver,
+}
+
+#[cfg(feature = "dns")]
+impl DnsTxtResolver {
+    /// A resolver using the system's DNS configuration.
+    pub fn new() -> Result<Self> {
+        let resolver = hickory_resolver::TokioResolver::builder_tokio()
+            .map_err(|e| Error::NameResolution(e.to_string()))?
+            .build();
+        Ok(Self { resolver })
+    }
+
+    /// The DNS name whose TXT record holds the ARID for `name`.
+    pub fn record_name(name: &HubertName) -> String {
+        format!("{}._hubert.{}.", name.label, name.host())
+    }
+}
+
+#[cfg(feature = "dns")]
+#[async_trait::async_trait(?Send)]
+impl NameResolver for DnsTxtResolver {
+    async fn resolve(&self, name: &HubertName) -> Result<ARID> {
+        if name.domain != name.host() {
+            return Err(Error::InvalidName(format!(
+                "{}: DNS names cannot carry a port",
+                name
+            )));
+        }
+        let lookup = match self
+            .resolver
+            .txt_lookup(Self::record_name(name))
+            .await
+        {
+            Ok(lookup) => lookup,
+            Err(e) if e.is_no_records_found() => {
+                return Err(Error::NameNotFound { name: name.to_string() });
+            }
+            Err(e) => {
+                return Err(Error::NameResolution(format!("{}: {}", name, e)));
+            }
+        };
+        let mut last_error = None;
+        for record in lookup.iter() {
+            let text: String = record
+                .txt_data()
+                .iter()
+                .map(|chunk| String::from_utf8_lossy(chunk))
+                .collect();
+            match published_arid(name, &text) {
+                Ok(arid) => return Ok(arid),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| Error::NameNotFound { name: name.to_string() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_name() {
+        let name: HubertName = "hubert:Alice.Example/invoice".parse().unwrap();
+        assert_eq!(name.domain(), "alice.example");
+        assert_eq!(name.label(), "invoice");
+        assert_eq!(name.to_string(), "hubert:alice.example/invoice");
+
+        let local: HubertName = "hubert:127.0.0.1:8080/test".parse().unwrap();
+        assert_eq!(local.host(), "127.0.0.1");
+
+        for bad in [
+            "alice.example/invoice",
+            "hubert:alice.example",
+            "hubert:/invoice",
+            "hubert:alice..example/invoice",
+            "hubert:-alice.example/invoice",
+            "hubert:alice.example:http/invoice",
+            "hubert:alice.example/in/voice",
+            "hubert:alice.example/",
+        ] {
+            assert!(bad.parse::<HubertName>().is_err(), "{}", bad);
+        }
+    }
+
+    struct Fixed(ARID);
+
+    #[async_trait::async_trait(?Send)]
+    impl NameResolver for Fixed {
+        async fn resolve(&self, _name: &HubertName) -> Result<ARID> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_arid() {
+        use bc_ur::prelude::*;
+
+        bc_components::register_tags();
+        let (named, direct) = (ARID::new(), ARID::new());
+        let resolver = Fixed(named);
+        assert_eq!(
+            resolve_arid("hubert:alice.example/invoice", &resolver)
+                .await
+                .unwrap(),
+            named
+        );
+        assert_eq!(
+            resolve_arid(&direct.ur_string(), &resolver).await.unwrap(),
+            direct
+        );
+        assert!(resolve_arid("alice", &resolver).await.is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Platform-appropriate locations for Hubert's files.
+//!
+//! Defaults follow each platform's conventions via the `directories` crate:
+//! XDG directories on Linux, `~/Library/Application Support` on macOS, and
+//! `%APPDATA%` on Windows.
+
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+
+use crate::{Error, Result};
+
+/// File name used for the SQLite database when only a directory is given.
+pub const SQLITE_FILE_NAME: &str = "hubert.sqlite";
+
+/// File name of the server configuration file inside [`config_dir`].
+pub const CONFIG_FILE_NAME: &str = "hubert.toml";
+
+/// File name of the CLI's put ledger inside [`data_dir`].
+pub const LEDGER_FILE_NAME: &str = "ledger.sqlite";
+
+/// File name of the CLI's keepalive registry inside [`data_dir`].
+pub const KEEPALIVE_FILE_NAME: &str = "keepalive.txt";
+
+/// File name of the DHT routing cache inside [`cache_dir`].
+pub const DHT_ROUTING_CACHE_FILE_NAME: &str = "dht-nodes.txt";
+
+fn project_dirs() -> Result<ProjectDirs> {
+    ProjectDirs::from("com", "Blockchain Commons", "Hubert")
+        .ok_or(Error::NoHomeDirectory)
+}
+
+/// Default directory for persistent data.
+pub fn data_dir() -> Result<PathBuf> {
+    Ok(project_dirs()?.data_dir().to_path_buf())
+}
+
+/// Default directory for configuration files.
+pub fn config_dir() -> Result<PathBuf> {
+    Ok(project_dirs()?.config_dir().to_path_buf())
+}
+
+/// Default directory for cached data that may be deleted at any time.
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(project_dirs()?.cache_dir().to_path_buf())
+}
+
+/// Default SQLite database path, inside [`data_dir`].
+pub fn default_sqlite_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join(SQLITE_FILE_NAME))
+}
+
+/// Default server configuration file path, inside [`config_dir`].
+///
+/// `hubert server` reads this file when it exists and no `--config` is given.
+pub fn default_config_file() -> Result<PathBuf> {
+    Ok(config_dir()?.join(CONFIG_FILE_NAME))
+}
+
+/// Default keepalive registry path, inside [`data_dir`].
+///
+/// `hubert keepalive start` and `stop` edit this file, and
+/// `hubert keepalive run` republishes the entries it lists.
+pub fn default_keepalive_file() -> Result<PathBuf> {
+    Ok(data_dir()?.join(KEEPALIVE_FILE_NAME))
+}
+
+/// Default DHT routing cache path, inside [`cache_dir`].
+///
+/// The CLI saves known-good DHT nodes here on exit and bootstraps from them
+/// on the next start.
+pub fn default_dht_routing_cache() -> Result<PathBuf> {
+    Ok(cache_dir()?.join(DHT_ROUTING_CACHE_FILE_NAME))
+}
+
+/// Resolve a user-supplied SQLite location to a database file path.
+///
+/// A path is treated as a directory if it already is one or if it ends with
+/// a path separator (`/`, or `\` on Windows), in which case
+/// [`SQLITE_FILE_NAME`] is used inside it. Any missing parent directories are
+/// created, so the result can be opened directly.
+pub fn resolve_sqlite_path(path: &Path) -> Result<PathBuf> {
+    let file = if path.is_dir() || ends_with_separator(path) {
+        path.join(SQLITE_FILE_NAME)
+    } else {
+        path.to_path_buf()
+    };
+    if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(file)
+}
+
+fn ends_with_separator(path: &Path) -> bool {
+    path.as_os_str()
+        .to_string_lossy()
+        .chars()
+        .last()
+        .is_some_and(std::path::is_separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hubert-paths-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_trailing_separator_is_directory() {
+        assert!(ends_with_separator(Path::new("data/")));
+        assert!(!ends_with_separator(Path::new("data")));
+        assert!(!ends_with_separator(Path::new("")));
+        #[cfg(windows)]
+        assert!(ends_with_separator(Path::new(r"data\")));
+    }
+
+    #[test]
+    fn test_resolve_existing_directory() {
+        let dir = scratch_dir("existing");
+        std::fs::create_dir_all(&dir).unwrap();
+        let resolved = resolve_sqlite_path(&dir).unwrap();
+        assert_eq!(resolved, dir.join(SQLITE_FILE_NAME));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_creates_missing_directories() {
+        let dir = scratch_dir("missing");
+        let file = dir.join("nested").join("store.db");
+        let resolved = resolve_sqlite_path(&file).unwrap();
+        assert_eq!(resolved, file);
+        assert!(dir.join("nested").is_dir());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_missing_directory_with_separator() {
+        let dir = scratch_dir("separator");
+        let mut input = dir.clone().into_os_string();
+        input.push(std::path::MAIN_SEPARATOR_STR);
+        let resolved = resolve_sqlite_path(Path::new(&input)).unwrap();
+        assert_eq!(resolved, dir.join(SQLITE_FILE_NAME));
+        assert!(dir.is_dir());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
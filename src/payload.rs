@@ -0,0 +1,64 @@
+//! Encrypting envelopes so only holders of their ARID can read them.
+//!
+//! Hubert obfuscates what it stores, but a server operator, or anyone who
+//! learns an ARID from one, can still read entries. An application that
+//! shares the ARID only with the intended reader can instead encrypt the
+//! envelope under a key derived from the ARID:
+//!
+//! ```
+//! # use bc_components::ARID;
+//! # use bc_envelope::prelude::*;
+//! use hubert::payload::{decrypt_payload, encrypt_payload};
+//!
+//! let arid = ARID::new();
+//! let envelope = Envelope::new("Secret").add_assertion("note", "private");
+//! let sealed = encrypt_payload(&envelope, &arid, "chat");
+//! assert!(sealed.assertions().is_empty());
+//! assert_eq!(decrypt_payload(&sealed, &arid, "chat").unwrap(), envelope);
+//! assert!(decrypt_payload(&sealed, &arid, "mail").is_err());
+//! ```
+//!
+//! The envelope is wrapped before its subject is encrypted, so its
+//! assertions are hidden too, including any `expiry`, which a put can then
+//! no longer derive a TTL from.
+
+use bc_components::{ARID, SymmetricKey};
+use bc_envelope::prelude::*;
+
+use crate::Result;
+
+/// Derive the key for encrypting payloads stored at `arid`.
+///
+/// `context` separates the keys of different uses of one ARID; `""` is the
+/// default the CLI uses. See [`hubert_core::derive_payload_key`].
+pub fn derive_payload_key(
+    arid: &ARID,
+    context: impl AsRef<[u8]>,
+) -> SymmetricKey {
+    SymmetricKey::from_data(hubert_core::derive_payload_key(
+        arid.data(),
+        context.as_ref(),
+    ))
+}
+
+/// Wrap `envelope` and encrypt it with the payload key for `arid` and
+/// `context`.
+pub fn encrypt_payload(
+    envelope: &Envelope,
+    arid: &ARID,
+    context: impl AsRef<[u8]>,
+) -> Envelope {
+    envelope.encrypt(&derive_payload_key(arid, context))
+}
+
+/// Decrypt and unwrap an envelope made by [`encrypt_payload`].
+///
+/// Fails with [`Error::Envelope`](crate::Error::Envelope) if `envelope` is
+/// not encrypted, or not with the payload key for `arid` and `context`.
+pub fn decrypt_payload(
+    envelope: &Envelope,
+    arid: &ARID,
+    context: impl AsRef<[u8]>,
+) -> Result<Envelope> {
+    Ok(envelope.decrypt(&derive_payload_key(arid, context))?)
+}
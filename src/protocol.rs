@@ -0,0 +1,268 @@
+//! A driver for multi-round protocols built on [`Session`]s.
+//!
+//! A [`Protocol`] lists its rounds in order, each naming the party that
+//! writes it and the session slot it is written to. Every party runs the same
+//! round list with its own name: on its turn it writes the pending round,
+//! otherwise it waits to read it. Progress can be persisted to a state file
+//! after every round, so a party that restarts resumes at the round it had
+//! reached.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, KvStore, Receipt, Result, Session, SessionSlot,
+    ur_input::parse_arid_ur,
+};
+
+/// One step of a protocol: who writes, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Round {
+    name: String,
+    writer: String,
+    slot: SessionSlot,
+}
+
+impl Round {
+    /// Create a round named `name`, written by `writer` to `slot`.
+    pub fn new(
+        name: impl Into<String>,
+        writer: impl Into<String>,
+        slot: SessionSlot,
+    ) -> Self {
+        Self { name: name.into(), writer: writer.into(), slot }
+    }
+
+    /// The round's name, for progress reporting.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The party that writes this round.
+    pub fn writer(&self) -> &str { &self.writer }
+
+    /// The session slot this round is written to.
+    pub fn slot(&self) -> SessionSlot { self.slot }
+}
+
+/// Progress as persisted to the state file.
+#[derive(Serialize, Deserialize)]
+struct State {
+    root: String,
+    expires_at: u64,
+    party: String,
+    completed: usize,
+}
+
+/// One party's view of a multi-round protocol.
+///
+/// # Example
+///
+/// ```no_run
+/// use bc_envelope::Envelope;
+/// use hubert::{
+///     MemoryKv, Session, SessionSlot,
+///     protocol::{Protocol, Round},
+/// };
+///
+/// # async fn example() -> hubert::Result<()> {
+/// let rounds = vec![
+///     Round::new("request", "alice", SessionSlot::Request),
+///     Round::new("response", "bob", SessionSlot::Response),
+/// ];
+/// let store = MemoryKv::new();
+///
+/// let mut alice = Protocol::new(Session::new(3600), "alice", rounds)?
+///     .with_state_file("alice.json");
+/// alice
+///     .write(&store, &Envelope::new("Please sign"), false)
+///     .await?;
+/// let response = alice.read(&store, Some(30), false).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Protocol {
+    session: Session,
+    party: String,
+    rounds: Vec<Round>,
+    completed: usize,
+    state_file: Option<PathBuf>,
+}
+
+impl Protocol {
+    /// Start a protocol in `session` as `party`, with no rounds completed.
+    ///
+    /// Fails if two rounds share a slot, since slots are write-once.
+    pub fn new(
+        session: Session,
+        party: impl Into<String>,
+        rounds: Vec<Round>,
+    ) -> Result<Self> {
+        let mut slots = HashSet::new();
+        if let Some(round) = rounds.iter().find(|r| !slots.insert(r.slot)) {
+            return Err(Error::InvalidProtocolState(format!(
+                "round '{}' reuses slot {:?}",
+                round.name, round.slot
+            )));
+        }
+        Ok(Self {
+            session,
+            party: party.into(),
+            rounds,
+            completed: 0,
+            state_file: None,
+        })
+    }
+
+    /// Persist progress to `path` after every completed round.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(path.into());
+        self
+    }
+
+    /// Resume a protocol from a state file written by an earlier run.
+    ///
+    /// `rounds` must be the same round list the state was saved with.
+    /// Progress continues to be saved to `path`.
+    pub fn resume(path: impl AsRef<Path>, rounds: Vec<Round>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let state: State = serde_json::from_str(&text)
+            .map_err(|e| Error::InvalidProtocolState(e.to_string()))?;
+
+        if state.completed > rounds.len() {
+            return Err(Error::InvalidProtocolState(format!(
+                "{} rounds completed but only {} defined",
+                state.completed,
+                rounds.len()
+            )));
+        }
+        let root = parse_arid_ur(&state.root)?;
+        let expires_at = UNIX_EPOCH + Duration::from_secs(state.expires_at);
+
+        let mut protocol =
+            Self::new(Session::join(root, expires_at), state.party, rounds)?
+                .with_state_file(path);
+        protocol.completed = state.completed;
+        Ok(protocol)
+    }
+
+    /// The session the protocol runs in.
+    pub fn session(&self) -> &Session { &self.session }
+
+    /// This party's name.
+    pub fn party(&self) -> &str { &self.party }
+
+    /// The number of rounds completed so far.
+    pub fn completed_rounds(&self) -> usize { self.completed }
+
+    /// The next round to be written or read, or `None` when complete.
+    pub fn pending(&self) -> Option<&Round> { self.rounds.get(self.completed) }
+
+    /// Whether every round has completed.
+    pub fn is_complete(&self) -> bool { self.pending().is_none() }
+
+    /// Whether the pending round is ours to write.
+    pub fn is_my_turn(&self) -> bool {
+        self.pending().is_some_and(|r| r.writer == self.party)
+    }
+
+    /// Write the pending round and advance.
+    ///
+    /// If the slot already holds a value, it is assumed to be our own write
+    /// from before a restart, and the round is treated as complete. Returns
+    /// the put's receipt, or `None` in that case.
+    pub async fn write(
+        &mut self,
+        store: &(impl KvStore + ?Sized),
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Option<Receipt>> {
+        let round = self.pending().ok_or(Error::ProtocolComplete)?;
+        if round.writer != self.party {
+            return Err(Error::NotYourTurn {
+                round: round.name.clone(),
+                writer: round.writer.clone(),
+            });
+        }
+
+        let receipt = match self
+            .session
+            .publish(store, round.slot, envelope, verbose)
+            .await
+        {
+            Ok(receipt) => Some(receipt),
+            Err(Error::AlreadyExists { .. }) => None,
+            Err(e) => return Err(e),
+        };
+        self.advance()?;
+        Ok(receipt)
+    }
+
+    /// Wait for the pending round, written by another party, and advance
+    /// once it arrives.
+    ///
+    /// Returns `Ok(None)` if it did not arrive within the timeout; the round
+    /// stays pending.
+    pub async fn read(
+        &mut self,
+        store: &(impl KvStore + ?Sized),
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        let round = self.pending().ok_or(Error::ProtocolComplete)?;
+        if round.writer == self.party {
+            return Err(Error::NotYourTurn {
+                round: round.name.clone(),
+                writer: round.writer.clone(),
+            });
+        }
+
+        let envelope = self
+            .session
+            .await_slot(store, round.slot, timeout_seconds, verbose)
+            .await?;
+        if envelope.is_some() {
+            self.advance()?;
+        }
+        Ok(envelope)
+    }
+
+    /// Save progress to the state file, if one is set.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+        let expires_at = self
+            .session
+            .expires_at()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let state = State {
+            root: self.session.root().ur_string(),
+            expires_at,
+            party: self.party.clone(),
+            completed: self.completed,
+        };
+        let text = serde_json::to_string_pretty(&state)
+            .map_err(|e| Error::InvalidProtocolState(e.to_string()))?;
+
+        // Write then rename, so a crash never leaves a truncated file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        self.completed += 1;
+        self.save()
+    }
+}
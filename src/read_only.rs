@@ -0,0 +1,158 @@
+//! A store wrapper that refuses writes.
+
+use std::time::Instant;
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use futures_util::stream::LocalBoxStream;
+
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Receipt, Result,
+    StoreCapabilities, ValidationError,
+};
+
+/// A [`KvStore`] that serves gets from an inner store and rejects every put
+/// with [`Error::ReadOnly`].
+///
+/// Useful for mirrors and archives, and for freezing a store during incident
+/// response without taking readers offline.
+#[derive(Debug, Clone)]
+pub struct ReadOnlyKv<S> {
+    inner: S,
+}
+
+impl<S: KvStore> ReadOnlyKv<S> {
+    /// Wrap `inner`, which keeps serving gets.
+    pub fn new(inner: S) -> Self { Self { inner } }
+
+    /// The wrapped store.
+    pub fn inner(&self) -> &S { &self.inner }
+
+    /// Unwrap the store, making it writable again.
+    pub fn into_inner(self) -> S { self.inner }
+}
+
+#[async_trait::async_trait(?Send)]
+impl<S: KvStore> KvStore for ReadOnlyKv<S> {
+    async fn put(
+        &self,
+        _arid: &ARID,
+        _envelope: &Envelope,
+        _ttl_seconds: Option<u64>,
+        _verbose: bool,
+    ) -> Result<Receipt> {
+        Err(Error::ReadOnly)
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.inner.get(arid, timeout_seconds, verbose).await
+    }
+
+    async fn exists(&self, arid: &ARID) -> Result<bool> {
+        self.inner.exists(arid).await
+    }
+
+    async fn delete(&self, _arid: &ARID, _verbose: bool) -> Result<bool> {
+        Err(Error::ReadOnly)
+    }
+
+    async fn update(
+        &self,
+        _arid: &ARID,
+        _envelope: &Envelope,
+        _ttl_seconds: Option<u64>,
+        _verbose: bool,
+    ) -> Result<Receipt> {
+        Err(Error::ReadOnly)
+    }
+
+    async fn stat(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        self.inner.stat(arid, verbose).await
+    }
+
+    fn get_all<'a>(
+        &'a self,
+        arids: &'a [ARID],
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>> {
+        self.inner.get_all(arids, timeout_seconds, verbose)
+    }
+
+    fn watch<'a>(
+        &'a self,
+        arid: &'a ARID,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<Envelope>> {
+        self.inner.watch(arid, verbose)
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.inner.get_until(arid, deadline, verbose).await
+    }
+
+    fn capabilities(&self) -> StoreCapabilities { self.inner.capabilities() }
+
+    fn validate(
+        &self,
+        _envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        Err(ValidationError::Incompatible(Error::ReadOnly.to_string()))
+    }
+
+    async fn health(&self) -> HealthReport { self.inner.health().await }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryKv;
+
+    #[tokio::test]
+    async fn test_rejects_puts_serves_gets() {
+        let inner = MemoryKv::new();
+        let stored = ARID::new();
+        inner
+            .put(&stored, &Envelope::new("Archived"), None, false)
+            .await
+            .unwrap();
+        let store = ReadOnlyKv::new(inner.clone());
+
+        let arid = ARID::new();
+        let result = store.put(&arid, &Envelope::new("New"), None, false).await;
+        assert!(matches!(result, Err(Error::ReadOnly)));
+        assert!(!inner.exists(&arid).await.unwrap());
+
+        let retrieved = store.get(&stored, Some(0), false).await.unwrap();
+        assert_eq!(retrieved, Some(Envelope::new("Archived")));
+        assert!(store.exists(&stored).await.unwrap());
+
+        let result = store.delete(&stored, false).await;
+        assert!(matches!(result, Err(Error::ReadOnly)));
+        assert!(inner.exists(&stored).await.unwrap());
+    }
+
+    #[test]
+    fn test_validate_rejects_everything() {
+        let envelope = Envelope::new("New");
+        assert!(MemoryKv::new().validate(&envelope).is_ok());
+        assert!(matches!(
+            ReadOnlyKv::new(MemoryKv::new()).validate(&envelope),
+            Err(ValidationError::Incompatible(_))
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+//! What a put stored and where, returned by [`KvStore::put`].
+//!
+//! [`KvStore::put`]: crate::KvStore::put
+
+use std::fmt;
+
+use bc_components::ARID;
+use bc_ur::prelude::*;
+
+/// The kind of store that made a put.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// BitTorrent Mainline DHT.
+    Mainline,
+    /// IPFS, through IPNS.
+    Ipfs,
+    /// Hybrid storage: the DHT, with large envelopes moved to IPFS or a
+    /// fallback server.
+    Hybrid,
+    /// A Hubert server, reached over HTTP.
+    Server,
+    /// An in-process [`MemoryKv`](crate::MemoryKv).
+    Memory,
+    /// A local SQLite database.
+    Sqlite,
+    /// A [`ShardedKv`](crate::sharded::ShardedKv) across several stores.
+    Sharded,
+    /// A Gordian Depot server.
+    Depot,
+    /// A [`ThresholdKv`](crate::threshold::ThresholdKv), secret-shared
+    /// across several stores.
+    Threshold,
+}
+
+impl Backend {
+    /// The backend's name, as the CLI's `--storage` gives it where it has
+    /// one.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mainline => "mainline",
+            Self::Ipfs => "ipfs",
+            Self::Hybrid => "hybrid",
+            Self::Server => "server",
+            Self::Memory => "memory",
+            Self::Sqlite => "sqlite",
+            Self::Sharded => "sharded",
+            Self::Depot => "depot",
+            Self::Threshold => "threshold",
+        }
+    }
+}
+
+/// A successful put's receipt: where the envelope went, under what TTL,
+/// and how large it was.
+///
+/// Its [`Display`](fmt::Display) is the text receipt each backend gave
+/// before receipts were structured, such as
+/// `ipns://k51q... -> ipfs://bafy...` for IPFS.
+///
+/// ```
+/// # use bc_components::ARID;
+/// # use hubert::receipt::{Backend, Receipt};
+/// let receipt = Receipt::new(Backend::Memory, ARID::new(), "memory:", 42)
+///     .with_ttl(600, 1_760_000_600);
+/// assert_eq!(receipt.ttl, Some(600));
+/// assert_eq!(receipt.to_string(), "Stored in memory");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    pub backend: Backend,
+    /// The ARID the envelope was stored at.
+    pub arid: ARID,
+    /// Where the envelope lives: a `dht://` or `ipns://` URI, a server's
+    /// URL, or a database path.
+    pub location: String,
+    /// The IPFS content identifier of the stored bytes, if in IPFS.
+    pub cid: Option<String>,
+    /// The public key of the DHT item holding the envelope, or for hybrid
+    /// storage its reference, if in the DHT.
+    pub dht_pubkey: Option<[u8; 32]>,
+    /// For hybrid storage of a large envelope, the ARID of the content the
+    /// reference in the DHT points to.
+    pub reference: Option<ARID>,
+    /// The TTL applied, in seconds, which the store may have shortened
+    /// from the one asked for. `None` for stores that keep entries until
+    /// they drop them.
+    pub ttl: Option<u64>,
+    /// When the entry expires, in UNIX seconds, if known.
+    pub expires_at: Option<u64>,
+    /// Bytes in the stored envelope's dCBOR.
+    pub size: usize,
+    /// Whether the same envelope was already stored at the ARID, so that
+    /// the put, a repeat under idempotent puts, changed nothing.
+    pub already_stored: bool,
+    /// For an update, the entry's sequence number after it: 1 for the
+    /// first version, counting up. `None` for puts.
+    pub sequence: Option<u64>,
+    /// For sharded stores, the receipt from each shard; for threshold
+    /// custody, from each custodian that stored a share.
+    pub shards: Vec<Receipt>,
+}
+
+impl Receipt {
+    /// A receipt for an envelope of `size` bytes stored at `arid` in
+    /// `location`.
+    pub fn new(
+        backend: Backend,
+        arid: ARID,
+        location: impl Into<String>,
+        size: usize,
+    ) -> Self {
+        Self {
+            backend,
+            arid,
+            location: location.into(),
+            cid: None,
+            dht_pubkey: None,
+            reference: None,
+            ttl: None,
+            expires_at: None,
+            size,
+            already_stored: false,
+            sequence: None,
+            shards: Vec::new(),
+        }
+    }
+
+    pub fn with_cid(mut self, cid: impl Into<String>) -> Self {
+        self.cid = Some(cid.into());
+        self
+    }
+
+    pub fn with_dht_pubkey(mut self, pubkey: [u8; 32]) -> Self {
+        self.dht_pubkey = Some(pubkey);
+        self
+    }
+
+    pub fn with_reference(mut self, reference: ARID) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Record that the entry lasts `ttl` seconds, until `expires_at`.
+    pub fn with_ttl(mut self, ttl: u64, expires_at: u64) -> Self {
+        self.ttl = Some(ttl);
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Mark the put as a repeat of the one that stored the entry.
+    pub fn already_stored(mut self) -> Self {
+        self.already_stored = true;
+        self
+    }
+
+    /// Record that the put was an update to version `sequence`.
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    pub fn with_shards(mut self, shards: Vec<Receipt>) -> Self {
+        self.shards = shards;
+        self
+    }
+}
+
+impl fmt::Display for Receipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let already = if self.already_stored {
+            "Already stored"
+        } else {
+            "Stored"
+        };
+        match self.backend {
+            Backend::Mainline => f.write_str(&self.location),
+            Backend::Ipfs => write!(
+                f,
+                "{} -> ipfs://{}",
+                self.location,
+                self.cid.as_deref().unwrap_or_default()
+            ),
+            Backend::Hybrid => match self.reference {
+                None => write!(
+                    f,
+                    "Stored in DHT at ARID: {}",
+                    self.arid.ur_string()
+                ),
+                Some(reference) => {
+                    let stored_in = match self.cid {
+                        Some(_) => "IPFS",
+                        None => self.location.as_str(),
+                    };
+                    write!(
+                        f,
+                        "Stored in {} (ref: {}) via DHT at ARID: {} until {} \
+                         (UNIX time)",
+                        stored_in,
+                        reference.ur_string(),
+                        self.arid.ur_string(),
+                        self.expires_at.unwrap_or_default()
+                    )
+                }
+            },
+            // Servers that predate receipts in put responses report no
+            // expiry
+            Backend::Server => match self.expires_at {
+                Some(expires_at) => {
+                    write!(f, "Stored until {} (UNIX time)", expires_at)
+                }
+                None => f.write_str("Stored successfully"),
+            },
+            Backend::Memory => write!(f, "{} in memory", already),
+            Backend::Depot => write!(f, "Stored in depot at {}", self.location),
+            Backend::Sqlite => {
+                write!(f, "{} in SQLite: {}", already, self.location)
+            }
+            Backend::Sharded => {
+                let shards: Vec<String> =
+                    self.shards.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Stored on {} shards: {}",
+                    shards.len(),
+                    shards.join("; ")
+                )
+            }
+            Backend::Threshold => {
+                let shares: Vec<String> =
+                    self.shards.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Stored {} shares: {}",
+                    shares.len(),
+                    shares.join("; ")
+                )
+            }
+        }
+    }
+}
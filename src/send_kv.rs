@@ -0,0 +1,318 @@
+//! Stores with `Send` futures, for use inside `tokio::spawn`.
+//!
+//! [`KvStore`] futures are not `Send`, because the IPFS and DHT clients
+//! underneath hold non-`Send` state across awaits. [`SendKv`] runs any store
+//! on a thread of its own and hands its operations over a channel, so the
+//! futures callers await only wait for the answer and can move between
+//! threads.
+
+use std::{sync::Arc, thread};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use futures_util::{FutureExt, future::LocalBoxFuture};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Receipt, Result,
+    StoreCapabilities, ValidationError,
+};
+
+/// The operations of a [`KvStore`] with futures that are `Send`.
+///
+/// See [`KvStore`] for what each operation does.
+#[async_trait::async_trait]
+pub trait KvStoreSend: Send + Sync {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt>;
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>>;
+
+    async fn exists(&self, arid: &ARID) -> Result<bool>;
+
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool>;
+
+    async fn stat(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<EntryMeta>>;
+
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt>;
+
+    async fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>>;
+
+    async fn get_many(
+        &self,
+        arids: &[ARID],
+        verbose: bool,
+    ) -> Result<Vec<Option<Envelope>>>;
+
+    async fn health(&self) -> HealthReport;
+
+    fn capabilities(&self) -> StoreCapabilities;
+
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError>;
+}
+
+/// An operation for the worker thread, given the store to run it on.
+type Job =
+    Box<dyn FnOnce(Arc<dyn KvStore>) -> LocalBoxFuture<'static, ()> + Send>;
+
+/// A [`KvStoreSend`] that runs a [`KvStore`] on a dedicated thread.
+///
+/// The thread has its own single-threaded Tokio runtime and runs the
+/// operations it is handed concurrently. It stops when the last clone of
+/// the `SendKv` is dropped.
+///
+/// ```no_run
+/// # use bc_components::ARID;
+/// # use bc_envelope::Envelope;
+/// use hubert::{KvStoreSend, SendKv, ipfs::IpfsKv};
+///
+/// # async fn example() -> hubert::Result<()> {
+/// let store = SendKv::new(IpfsKv::new("http://127.0.0.1:5001"));
+/// let arid = ARID::new();
+/// tokio::spawn(async move {
+///     store.put(&arid, &Envelope::new("data"), None, false).await
+/// })
+/// .await
+/// .unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SendKv {
+    store: Arc<dyn KvStore>,
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl SendKv {
+    /// Run `store` on a new thread.
+    pub fn new(store: impl KvStore + 'static) -> Self {
+        Self::from_arc(Arc::new(store))
+    }
+
+    /// Run a shared `store` on a new thread. Other holders of `store` can
+    /// keep using it directly.
+    pub fn from_arc(store: Arc<dyn KvStore>) -> Self {
+        let (jobs, mut receiver) = mpsc::unbounded_channel::<Job>();
+        let worker = Arc::clone(&store);
+        thread::Builder::new()
+            .name("hubert-kv".to_string())
+            .spawn(move || {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("failed to start the store's runtime");
+                let local = tokio::task::LocalSet::new();
+                local.block_on(&runtime, async move {
+                    while let Some(job) = receiver.recv().await {
+                        tokio::task::spawn_local(job(Arc::clone(&worker)));
+                    }
+                });
+            })
+            .expect("failed to start the store's thread");
+        Self { store, jobs }
+    }
+
+    /// The store the thread runs.
+    pub fn inner(&self) -> &Arc<dyn KvStore> { &self.store }
+
+    /// Run `operation` on the worker thread and wait for its result.
+    async fn run<T, F>(&self, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Arc<dyn KvStore>) -> LocalBoxFuture<'static, Result<T>>
+            + Send
+            + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move |store| {
+            let result = operation(store);
+            async move {
+                // The caller may have stopped waiting
+                let _ = sender.send(result.await);
+            }
+            .boxed_local()
+        });
+        self.jobs.send(job).map_err(|_| Error::WorkerStopped)?;
+        receiver.await.map_err(|_| Error::WorkerStopped)?
+    }
+}
+
+#[async_trait::async_trait]
+impl KvStoreSend for SendKv {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let (arid, envelope) = (*arid, envelope.clone());
+        self.run(move |store| {
+            async move {
+                store.put(&arid, &envelope, ttl_seconds, verbose).await
+            }
+            .boxed_local()
+        })
+        .await
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        let arid = *arid;
+        self.run(move |store| {
+            async move { store.get(&arid, timeout_seconds, verbose).await }
+                .boxed_local()
+        })
+        .await
+    }
+
+    async fn exists(&self, arid: &ARID) -> Result<bool> {
+        let arid = *arid;
+        self.run(move |store| {
+            async move { store.exists(&arid).await }.boxed_local()
+        })
+        .await
+    }
+
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        let arid = *arid;
+        self.run(move |store| {
+            async move { store.delete(&arid, verbose).await }.boxed_local()
+        })
+        .await
+    }
+
+    async fn stat(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        let arid = *arid;
+        self.run(move |store| {
+            async move { store.stat(&arid, verbose).await }.boxed_local()
+        })
+        .await
+    }
+
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let (arid, envelope) = (*arid, envelope.clone());
+        self.run(move |store| {
+            async move {
+                store.update(&arid, &envelope, ttl_seconds, verbose).await
+            }
+            .boxed_local()
+        })
+        .await
+    }
+
+    async fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        let entries = entries.to_vec();
+        self.run(move |store| {
+            async move { store.put_many(&entries, ttl_seconds, verbose).await }
+                .boxed_local()
+        })
+        .await
+    }
+
+    async fn get_many(
+        &self,
+        arids: &[ARID],
+        verbose: bool,
+    ) -> Result<Vec<Option<Envelope>>> {
+        let arids = arids.to_vec();
+        self.run(move |store| {
+            async move { store.get_many(&arids, verbose).await }.boxed_local()
+        })
+        .await
+    }
+
+    async fn health(&self) -> HealthReport {
+        let report = self.run(move |store| {
+            async move { Ok(store.health().await) }.boxed_local()
+        });
+        match report.await {
+            Ok(report) => report,
+            Err(e) => HealthReport::probe("worker", async { Err(e) }).await,
+        }
+    }
+
+    fn capabilities(&self) -> StoreCapabilities { self.store.capabilities() }
+
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        self.store.validate(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryKv;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawned_operations() {
+        let store = SendKv::new(MemoryKv::new());
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let arid = ARID::new();
+                    let envelope = Envelope::new(format!("Message {}", i));
+                    store.put(&arid, &envelope, None, false).await?;
+                    let found = store.get(&arid, Some(0), false).await?;
+                    assert_eq!(found, Some(envelope));
+                    store.delete(&arid, false).await
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.await.unwrap().unwrap());
+        }
+        assert!(store.health().await.error.is_none());
+    }
+}
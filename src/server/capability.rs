@@ -0,0 +1,270 @@
+//! Capability tokens for server authorization.
+//!
+//! A capability is an envelope granting `put`, `get`, and `admin` rights,
+//! optionally limited to ARIDs starting with given bytes and to an expiry
+//! date, signed by a key the server trusts:
+//!
+//! ```text
+//! {
+//!     "hubertCapability" [
+//!         "allow": "put"
+//!         "allow": "get"
+//!         "aridPrefix": h'a1b2'
+//!         "expires": 2026-01-01
+//!         "holder": PublicKeys
+//!     ]
+//! } [
+//!     'signed': Signature
+//! ]
+//! ```
+//!
+//! Clients present the token's UR in the `Hubert-Capability` header. A
+//! token naming a `holder` is only honored alongside a fresh proof signed by
+//! the holder (see [`request_proof`](super::request_proof)), so a captured
+//! request cannot be replayed.
+
+use bc_components::{ARID, PublicKeys, Signer};
+use bc_envelope::prelude::*;
+use dcbor::Date;
+use serde::{Deserialize, Serialize};
+
+use super::Error as ServerError;
+use crate::Result;
+
+/// HTTP header carrying a capability token.
+pub const CAPABILITY_HEADER: &str = "Hubert-Capability";
+
+const SUBJECT: &str = "hubertCapability";
+const ALLOW: &str = "allow";
+const ARID_PREFIX: &str = "aridPrefix";
+const EXPIRES: &str = "expires";
+const HOLDER: &str = "holder";
+
+/// An operation a capability or client policy can grant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Right {
+    Put,
+    Get,
+    /// Operator endpoints such as `/admin/drain`.
+    Admin,
+}
+
+impl Right {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Right::Put => "put",
+            Right::Get => "get",
+            Right::Admin => "admin",
+        }
+    }
+}
+
+/// Rights granted to the bearer of a signed capability token.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capability {
+    rights: Vec<Right>,
+    arid_prefixes: Vec<Vec<u8>>,
+    expires: Option<Date>,
+    holder: Option<PublicKeys>,
+}
+
+impl Capability {
+    /// A capability granting nothing; add rights with [`Self::allow`].
+    pub fn new() -> Self { Self::default() }
+
+    /// Grant `right`.
+    pub fn allow(mut self, right: Right) -> Self {
+        if !self.rights.contains(&right) {
+            self.rights.push(right);
+        }
+        self
+    }
+
+    /// Limit the capability to ARIDs whose bytes start with `prefix`.
+    ///
+    /// May be called more than once; an ARID matching any prefix is allowed.
+    pub fn with_arid_prefix(mut self, prefix: impl Into<Vec<u8>>) -> Self {
+        self.arid_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Make the capability invalid after `expires`.
+    pub fn with_expiry(mut self, expires: Date) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Bind the capability to `holder`, whose private keys must sign a
+    /// fresh request proof for every request presenting it.
+    ///
+    /// Without a holder the token is a bearer token, and a request that
+    /// carries it can be replayed by anyone who captures it.
+    pub fn with_holder(mut self, holder: PublicKeys) -> Self {
+        self.holder = Some(holder);
+        self
+    }
+
+    /// The keys that must sign request proofs, if the capability is bound
+    /// to a holder.
+    pub fn holder(&self) -> Option<&PublicKeys> { self.holder.as_ref() }
+
+    /// Sign the capability, producing a token to hand to a client.
+    pub fn sign(&self, signer: &dyn Signer) -> Envelope {
+        let mut envelope = Envelope::new(SUBJECT);
+        for right in &self.rights {
+            envelope = envelope.add_assertion(ALLOW, right.as_str());
+        }
+        for prefix in &self.arid_prefixes {
+            envelope = envelope
+                .add_assertion(ARID_PREFIX, ByteString::new(prefix.clone()));
+        }
+        if let Some(expires) = &self.expires {
+            envelope = envelope.add_assertion(EXPIRES, expires.clone());
+        }
+        if let Some(holder) = &self.holder {
+            envelope = envelope.add_assertion(HOLDER, holder.clone());
+        }
+        envelope.sign(signer)
+    }
+
+    /// Verify a token's signature against `trust_roots` and decode it.
+    pub fn verify(
+        token: &Envelope,
+        trust_roots: &[PublicKeys],
+    ) -> Result<Self> {
+        let unauthorized =
+            |reason: &str| ServerError::Unauthorized(reason.to_string());
+
+        let envelope = trust_roots
+            .iter()
+            .find_map(|root| token.verify(root).ok())
+            .ok_or_else(|| unauthorized("not signed by a trusted key"))?;
+
+        if envelope.extract_subject::<String>().ok().as_deref() != Some(SUBJECT)
+        {
+            return Err(unauthorized("not a capability").into());
+        }
+
+        let mut capability = Capability::new();
+        for object in envelope.objects_for_predicate(ALLOW) {
+            match object.extract_subject::<String>().ok().as_deref() {
+                Some("put") => capability = capability.allow(Right::Put),
+                Some("get") => capability = capability.allow(Right::Get),
+                Some("admin") => capability = capability.allow(Right::Admin),
+                _ => return Err(unauthorized("unknown right").into()),
+            }
+        }
+        for object in envelope.objects_for_predicate(ARID_PREFIX) {
+            let prefix: ByteString = object
+                .extract_subject()
+                .map_err(|_| unauthorized("invalid ARID prefix"))?;
+            capability = capability.with_arid_prefix(prefix.data().to_vec());
+        }
+        if let Some(object) = envelope.objects_for_predicate(EXPIRES).first() {
+            let expires: Date = object
+                .extract_subject()
+                .map_err(|_| unauthorized("invalid expiry"))?;
+            capability = capability.with_expiry(expires);
+        }
+        if let Some(object) = envelope.objects_for_predicate(HOLDER).first() {
+            let holder: PublicKeys = object
+                .extract_subject()
+                .map_err(|_| unauthorized("invalid holder"))?;
+            capability = capability.with_holder(holder);
+        }
+        Ok(capability)
+    }
+
+    /// Whether the capability allows `right` on `arid` at time `now`.
+    pub fn permits(&self, right: Right, arid: &ARID, now: &Date) -> bool {
+        let expired = self.is_expired(now);
+        let in_scope = self.arid_prefixes.is_empty()
+            || self
+                .arid_prefixes
+                .iter()
+                .any(|prefix| arid.data().starts_with(prefix));
+        !expired && in_scope && self.rights.contains(&right)
+    }
+
+    /// Whether the capability allows `right` on the whole server at time
+    /// `now`, as needed for rights not tied to an ARID. Capabilities limited
+    /// to ARID prefixes never do.
+    pub fn grants(&self, right: Right, now: &Date) -> bool {
+        !self.is_expired(now)
+            && self.arid_prefixes.is_empty()
+            && self.rights.contains(&right)
+    }
+
+    fn is_expired(&self, now: &Date) -> bool {
+        self.expires
+            .as_ref()
+            .is_some_and(|expires| now.timestamp() >= expires.timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::keypair;
+
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (private_keys, public_keys) = keypair();
+        let (_, holder) = keypair();
+        let capability = Capability::new()
+            .allow(Right::Get)
+            .with_arid_prefix(vec![0xab])
+            .with_holder(holder);
+
+        let token = capability.sign(&private_keys);
+        let verified = Capability::verify(&token, &[public_keys]).unwrap();
+        assert_eq!(verified, capability);
+    }
+
+    #[test]
+    fn test_untrusted_signer_rejected() {
+        let (private_keys, _) = keypair();
+        let (_, other_public_keys) = keypair();
+        let token = Capability::new().allow(Right::Put).sign(&private_keys);
+        assert!(Capability::verify(&token, &[other_public_keys]).is_err());
+    }
+
+    #[test]
+    fn test_permits() {
+        let now = Date::now();
+        let arid = ARID::new();
+        let prefix = arid.data()[..2].to_vec();
+
+        let get_only = Capability::new().allow(Right::Get);
+        assert!(get_only.permits(Right::Get, &arid, &now));
+        assert!(!get_only.permits(Right::Put, &arid, &now));
+
+        let scoped = get_only.clone().with_arid_prefix(prefix);
+        assert!(scoped.permits(Right::Get, &arid, &now));
+        assert!(!scoped.permits(Right::Get, &ARID::from_data([0; 32]), &now));
+
+        let expired = get_only.with_expiry(Date::from_timestamp(0.0));
+        assert!(!expired.permits(Right::Get, &arid, &now));
+    }
+
+    #[test]
+    fn test_grants() {
+        let now = Date::now();
+        let admin = Capability::new().allow(Right::Admin);
+        assert!(admin.grants(Right::Admin, &now));
+        assert!(!admin.grants(Right::Put, &now));
+        assert!(
+            !admin
+                .clone()
+                .with_arid_prefix(vec![0])
+                .grants(Right::Admin, &now)
+        );
+        assert!(
+            !admin
+                .with_expiry(Date::from_timestamp(0.0))
+                .grants(Right::Admin, &now)
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How long the server clock runs on the monotonic clock alone before it is
+/// compared with the wall clock again.
+const REANCHOR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The server clock is steered toward the wall clock by at most this
+/// fraction of the time since it was last compared: a tenth.
+const MAX_SLEW_DIVISOR: u32 = 10;
+
+/// The clock every server-side expiry is measured by.
+static CLOCK: LazyLock<Clock> =
+    LazyLock::new(|| Clock::new(wall_clock(), Instant::now()));
+
+/// Current server time in UNIX seconds.
+///
+/// Read from [`Clock`] rather than the wall clock, so expiries, receipts,
+/// and tombstones are unaffected by the wall clock being stepped.
+pub(super) fn unix_now() -> u64 { CLOCK.now().as_secs() }
+
+/// UNIX time that advances with the monotonic clock.
+///
+/// When the wall clock is stepped, by NTP or by hand, the server clock does
+/// not follow, so entries neither vanish early nor linger for the size of
+/// the step. Whenever it is read at least [`REANCHOR_INTERVAL`] after the
+/// last comparison, it is steered toward the wall clock by at most a tenth
+/// of the time elapsed, so it converges on a corrected wall clock while any
+/// TTL measured by it stays within 10% of true. It never runs backward.
+///
+/// On some platforms, Linux among them, the monotonic clock stops while the
+/// machine is suspended, so after a suspension the server clock catches up
+/// the same gradual way.
+pub(super) struct Clock {
+    state: Mutex<State>,
+}
+
+struct State {
+    /// The UNIX time at `instant`.
+    anchor: Duration,
+    instant: Instant,
+    /// The latest time reported, which later readings never go below.
+    latest: Duration,
+}
+
+impl Clock {
+    /// A clock reading `wall` at `instant`.
+    pub(super) fn new(wall: Duration, instant: Instant) -> Self {
+        Self {
+            state: Mutex::new(State { anchor: wall, instant, latest: wall }),
+        }
+    }
+
+    /// The current time since the UNIX epoch.
+    pub(super) fn now(&self) -> Duration {
+        self.now_at(Instant::now(), wall_clock())
+    }
+
+    /// The time at `instant`, when the wall clock reads `wall`.
+    fn now_at(&self, instant: Instant, wall: Duration) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let elapsed = instant.saturating_duration_since(state.instant);
+        let mut now = state.anchor + elapsed;
+        if elapsed >= REANCHOR_INTERVAL {
+            let max_slew = elapsed / MAX_SLEW_DIVISOR;
+            now = if wall > now {
+                now + (wall - now).min(max_slew)
+            } else {
+                now - (now - wall).min(max_slew)
+            };
+            state.anchor = now;
+            state.instant = instant;
+        }
+        state.latest = state.latest.max(now);
+        state.latest
+    }
+}
+
+/// The wall-clock time since the UNIX epoch.
+fn wall_clock() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const MINUTE: Duration = Duration::from_secs(60);
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_wall_clock_steps() {
+        let (start, wall) = (Instant::now(), Duration::from_secs(1_000_000));
+        let clock = Clock::new(wall, start);
+
+        // Between comparisons the monotonic clock alone counts
+        assert_eq!(
+            clock.now_at(start + 30 * SECOND, wall + HOUR),
+            wall + 30 * SECOND
+        );
+
+        // A step forward is followed by a tenth of the time elapsed, so a
+        // 10-minute TTL loses at most a minute
+        assert_eq!(
+            clock.now_at(start + 10 * MINUTE, wall + 10 * MINUTE + HOUR),
+            wall + 11 * MINUTE
+        );
+
+        // A step backward slows the clock but never reverses it
+        let before = clock.now_at(start + 10 * MINUTE + 59 * SECOND, wall);
+        assert_eq!(before, wall + 11 * MINUTE + 59 * SECOND);
+        assert_eq!(clock.now_at(start + 11 * MINUTE, wall), before);
+        assert_eq!(
+            clock.now_at(start + 21 * MINUTE, wall),
+            wall + 20 * MINUTE + 54 * SECOND
+        );
+    }
+
+    #[test]
+    fn test_small_drift_is_adopted() {
+        let (start, wall) = (Instant::now(), Duration::from_secs(1_000_000));
+        let clock = Clock::new(wall, start);
+
+        let drifted = wall + MINUTE + 2 * SECOND;
+        assert_eq!(clock.now_at(start + MINUTE, drifted), drifted);
+        assert_eq!(
+            clock.now_at(start + 2 * MINUTE, drifted + MINUTE),
+            drifted + MINUTE
+        );
+    }
+}
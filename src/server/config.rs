@@ -0,0 +1,788 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use bc_components::{PrivateKeys, PublicKeys};
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{Cidr, Error as ServerError, Right, ServerKvClient};
+use crate::{Mutability, Result};
+
+/// Configuration for the Hubert server.
+///
+/// A configuration can be built in code, loaded from a TOML file with
+/// [`ServerConfig::from_file`], and overridden by `HUBERT_*` environment
+/// variables with [`ServerConfig::apply_env`]. Every field is optional in the
+/// file; missing fields take their default values. TTLs and other durations
+/// are seconds, and may be written in either place as a string such as
+/// `"90m"` or `"2h30m"` (see [`crate::duration`]).
+///
+/// # Example File
+///
+/// ```toml
+/// bind = "0.0.0.0"
+/// port = 45678
+/// max_ttl = "1h"
+/// sqlite = "/var/lib/hubert/hubert.sqlite"
+/// max_connections = 4096
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address to bind to (default: 127.0.0.1).
+    pub bind: String,
+    pub port: u16,
+    /// Maximum TTL in seconds allowed.
+    /// If a put() specifies a TTL higher than this, it will be clamped.
+    /// Hubert is intended for coordination, not long-term storage.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub max_ttl: u64,
+    /// TTL in seconds of puts that do not specify one, clamped to
+    /// `max_ttl`. If not set, `max_ttl` is used.
+    #[serde(deserialize_with = "crate::duration::deserialize_option")]
+    pub default_ttl: Option<u64>,
+    /// Enable verbose logging with timestamps
+    pub verbose: bool,
+    /// Don't announce the listening address on stderr at startup.
+    pub quiet: bool,
+    /// SQLite database file for persistent storage.
+    /// If not set, the server uses in-memory storage.
+    pub sqlite: Option<PathBuf>,
+    /// Maximum time in seconds to read and handle a single request.
+    /// Requests exceeding this receive 408 Request Timeout.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub request_timeout: u64,
+    /// Maximum number of simultaneously open client connections.
+    /// Further clients wait in the listen backlog until a slot frees up.
+    pub max_connections: usize,
+    /// Seconds a connection may go without sending or receiving any bytes
+    /// before it is closed. Bounds keep-alive reuse and slow clients.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub idle_timeout: u64,
+    /// Seconds to let in-flight requests finish after a shutdown signal
+    /// before remaining connections are dropped.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub shutdown_grace: u64,
+    /// Seconds to remember a put after its entry expires, so the putter can
+    /// learn from `/receipts` that the entry was pruned.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub receipt_retention: u64,
+    /// Public keys (`ur:crypto-pubkeys`) trusted to sign capability tokens.
+    /// When set, `/put`, `/get`, and `/receipts` require a token signed by
+    /// one of them; when empty, the server is open to all clients.
+    pub capability_roots: Vec<String>,
+    /// Seconds a request proof's timestamp may differ from the server
+    /// clock. Capabilities bound to a holder need a fresh proof with every
+    /// request, and each proof's nonce is accepted only once.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub replay_window: u64,
+    /// Reverse proxies whose `X-Forwarded-For` header names the client.
+    /// Requests through them are logged and filtered by the client's
+    /// address rather than the proxy's.
+    pub trusted_proxies: Vec<Cidr>,
+    /// Client addresses allowed to use the server. When empty, every
+    /// address not in `denied_ips` is allowed.
+    pub allowed_ips: Vec<Cidr>,
+    /// Client addresses refused with 403, even if also allowed.
+    pub denied_ips: Vec<Cidr>,
+    /// Serve HTTPS and require client certificates. Needs the `tls` feature.
+    pub tls: Option<TlsConfig>,
+    /// Reject every put with 403 while still serving gets, for mirrors,
+    /// archives, and freezes during incident response.
+    pub read_only: bool,
+    /// Seconds to keep a tombstone of each entry's ARID, expiry, and digest
+    /// after the entry expires. While kept, gets of the ARID receive 410 Gone
+    /// and puts to it are refused. Off when not set.
+    #[serde(deserialize_with = "crate::duration::deserialize_option")]
+    pub tombstone_retention: Option<u64>,
+    /// Accept a put of the envelope already stored at its ARID, so clients
+    /// can retry puts whose response they never saw. A different envelope
+    /// is still refused with 409 Conflict.
+    pub idempotent_puts: bool,
+    /// Whether `/update` may replace entries: `write-once` (the default)
+    /// refuses updates with 501 Not Implemented, and `updatable` accepts
+    /// them, numbering each entry's versions. Puts stay write-once.
+    pub mutability: Mutability,
+    /// Store envelopes whose CBOR encoding is at least this many bytes
+    /// zstd-compressed in SQLite storage. Off when not set; ignored with
+    /// in-memory storage.
+    pub compression_threshold: Option<usize>,
+    /// Directory that SQLite storage moves entries to once they have been
+    /// stored for `cold_after` seconds, leaving stub rows that gets follow.
+    /// Off when not set; ignored with in-memory storage.
+    pub cold_storage: Option<PathBuf>,
+    /// Seconds after a put that its entry moves to `cold_storage`.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub cold_after: u64,
+    /// This server's private keys (`ur:crypto-prvkeys`), which sign and
+    /// decrypt what it exchanges with `peers`. Needed when `peers` is set.
+    pub identity: Option<String>,
+    /// Servers this one keeps in sync with. Every `sync_interval` seconds
+    /// it sends each peer a filter of the ARIDs it holds, and stores the
+    /// entries the peer answers that it is missing, catching up on puts
+    /// made while it was down.
+    pub peers: Vec<PeerConfig>,
+    /// Seconds between anti-entropy passes with `peers`.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub sync_interval: u64,
+    /// Entries with fewer than this many seconds left to live are not
+    /// synced.
+    #[serde(deserialize_with = "crate::duration::deserialize")]
+    pub sync_min_ttl: u64,
+    /// Serve totals of the figures `/admin/stats` and `/admin/storage`
+    /// report at `/metrics`, in the Prometheus text format. The endpoint
+    /// needs no authorization and names no ARIDs.
+    pub metrics: bool,
+}
+
+/// A server to keep in sync with.
+///
+/// Both servers list each other, each with its own `identity`:
+///
+/// ```toml
+/// identity = "ur:crypto-prvkeys/..."
+///
+/// [[peers]]
+/// url = "https://hubert-b.example.com:45678"
+/// public_keys = "ur:crypto-pubkeys/..."
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PeerConfig {
+    /// Base URL of the peer.
+    pub url: String,
+    /// The peer's public keys (`ur:crypto-pubkeys`), which verify what it
+    /// sends and encrypt what it is sent.
+    pub public_keys: String,
+}
+
+/// TLS settings for a server that authenticates clients by certificate.
+///
+/// Clients must present a certificate issued by `client_ca`. The subject
+/// common name of that certificate is the client's identity, which selects
+/// its entry in `clients`.
+///
+/// ```toml
+/// [tls]
+/// cert = "/etc/hubert/server.pem"
+/// key = "/etc/hubert/server.key"
+/// client_ca = "/etc/hubert/clients-ca.pem"
+///
+/// [tls.clients.coordinator-a]
+/// allow = ["put", "get"]
+/// max_puts_per_hour = 1000
+///
+/// [tls.clients.observer]
+/// allow = ["get"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file with the server's certificate chain.
+    pub cert: PathBuf,
+    /// PEM file with the server's private key.
+    pub key: PathBuf,
+    /// PEM file with the CA certificates that issue client certificates.
+    pub client_ca: PathBuf,
+    /// Policies by client identity. When empty, any client with a valid
+    /// certificate may put and get without limit; otherwise clients not
+    /// listed are refused with 403.
+    #[serde(default)]
+    pub clients: HashMap<String, ClientPolicy>,
+}
+
+/// What a client identified by its certificate may do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientPolicy {
+    /// Operations the client may perform. `/receipts` needs `put`.
+    pub allow: Vec<Right>,
+    /// Puts allowed per clock hour; further puts get 429.
+    #[serde(default)]
+    pub max_puts_per_hour: Option<u64>,
+}
+
+/// Every setting of a [`ServerKvClient`], for building one from a
+/// configuration file with [`ServerKvClient::with_config`].
+///
+/// Every field is optional in a file; missing fields take their default
+/// values.
+///
+/// ```toml
+/// base_url = "https://hubert.example.com:45678"
+/// tag = "coordinator"
+///
+/// [client_cert]
+/// identity = "/etc/hubert/client.pem"
+/// ca = "/etc/hubert/server-ca.pem"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerClientConfig {
+    /// URL of the server (default: http://127.0.0.1:45678).
+    pub base_url: String,
+    /// Capability token (`ur:envelope`) to present with every request.
+    pub capability: Option<String>,
+    /// Private keys (`ur:crypto-prvkeys`) of the capability's holder, to
+    /// sign a request proof for every request.
+    pub holder_keys: Option<String>,
+    /// Opaque label for every put.
+    pub tag: Option<String>,
+    /// Client certificate to authenticate with. Needs the `tls` feature.
+    pub client_cert: Option<ClientCertConfig>,
+}
+
+/// PEM files for a client that authenticates by certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientCertConfig {
+    /// PEM file with the client's certificate chain and private key.
+    pub identity: PathBuf,
+    /// PEM file with the certificates that issue the server's certificate.
+    pub ca: PathBuf,
+}
+
+impl Default for ServerClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://127.0.0.1:45678".to_string(),
+            capability: None,
+            holder_keys: None,
+            tag: None,
+            client_cert: None,
+        }
+    }
+}
+
+impl ServerKvClient {
+    /// Create a client with every setting taken from `config`.
+    ///
+    /// Fails if the capability token or holder keys are malformed, or if a
+    /// client certificate is configured but cannot be loaded.
+    pub fn with_config(config: ServerClientConfig) -> Result<Self> {
+        let mut client = Self::new(&config.base_url);
+        if let Some(token) = &config.capability {
+            bc_components::register_tags();
+            let token =
+                Envelope::from_ur_string(token.trim()).map_err(|e| {
+                    ServerError::Config(format!("invalid capability: {}", e))
+                })?;
+            client = client.with_capability(&token);
+        }
+        if let Some(keys) = &config.holder_keys {
+            bc_components::register_tags();
+            let keys =
+                PrivateKeys::from_ur_string(keys.trim()).map_err(|e| {
+                    ServerError::Config(format!("invalid holder keys: {}", e))
+                })?;
+            client = client.with_holder_keys(keys);
+        }
+        if let Some(tag) = config.tag {
+            client = client.with_tag(tag);
+        }
+        if let Some(cert) = &config.client_cert {
+            client = with_client_cert(client, cert)?;
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(feature = "tls")]
+fn with_client_cert(
+    client: ServerKvClient,
+    cert: &ClientCertConfig,
+) -> Result<ServerKvClient> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|e| {
+            ServerError::Config(format!("{}: {}", path.display(), e))
+        })
+    };
+    client.with_client_cert(&read(&cert.identity)?, &read(&cert.ca)?)
+}
+
+#[cfg(not(feature = "tls"))]
+fn with_client_cert(
+    _client: ServerKvClient,
+    _cert: &ClientCertConfig,
+) -> Result<ServerKvClient> {
+    Err(
+        ServerError::Config("client_cert requires the tls feature".to_string())
+            .into(),
+    )
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1".to_string(),
+            port: 45678,
+            max_ttl: 86400, // 24 hours max (and default)
+            default_ttl: None,
+            verbose: false,
+            quiet: false,
+            sqlite: None,
+            request_timeout: 30,
+            max_connections: 1024,
+            idle_timeout: 60,
+            shutdown_grace: 10,
+            receipt_retention: 86400,
+            capability_roots: Vec::new(),
+            replay_window: 300,
+            trusted_proxies: Vec::new(),
+            allowed_ips: Vec::new(),
+            denied_ips: Vec::new(),
+            tls: None,
+            read_only: false,
+            tombstone_retention: None,
+            idempotent_puts: false,
+            mutability: Mutability::WriteOnce,
+            compression_threshold: None,
+            cold_storage: None,
+            cold_after: 86400,
+            identity: None,
+            peers: Vec::new(),
+            sync_interval: 300,
+            sync_min_ttl: 60,
+            metrics: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Load a configuration from a TOML file.
+    ///
+    /// Fields absent from the file keep their default values. Unknown fields
+    /// are rejected so typos don't silently fall back to defaults.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            ServerError::Config(format!("{}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text).map_err(|e| {
+            ServerError::Config(format!("{}: {}", path.display(), e)).into()
+        })
+    }
+
+    /// Parse a configuration from a TOML string.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text)
+            .map_err(|e| ServerError::Config(e.to_string()).into())
+    }
+
+    /// Override fields from `HUBERT_*` environment variables.
+    ///
+    /// Recognized variables: `HUBERT_BIND`, `HUBERT_PORT`, `HUBERT_MAX_TTL`,
+    /// `HUBERT_DEFAULT_TTL` (empty to use `max_ttl`), `HUBERT_VERBOSE`,
+    /// `HUBERT_QUIET`, `HUBERT_SQLITE`, `HUBERT_REQUEST_TIMEOUT`,
+    /// `HUBERT_MAX_CONNECTIONS`, `HUBERT_IDLE_TIMEOUT`,
+    /// `HUBERT_SHUTDOWN_GRACE`, `HUBERT_RECEIPT_RETENTION`,
+    /// `HUBERT_CAPABILITY_ROOTS` (comma-separated), `HUBERT_REPLAY_WINDOW`,
+    /// `HUBERT_TRUSTED_PROXIES`, `HUBERT_ALLOWED_IPS`, `HUBERT_DENIED_IPS`
+    /// (comma-separated CIDR blocks), `HUBERT_READ_ONLY`,
+    /// `HUBERT_TOMBSTONE_RETENTION` (empty to turn tombstones off),
+    /// `HUBERT_IDEMPOTENT_PUTS`, `HUBERT_COMPRESSION_THRESHOLD` (empty to
+    /// turn compression off), `HUBERT_COLD_STORAGE` (empty to turn cold
+    /// storage off), `HUBERT_COLD_AFTER`, `HUBERT_IDENTITY` (empty to unset),
+    /// `HUBERT_PEERS` (comma-separated `url=ur:crypto-pubkeys/...` pairs),
+    /// `HUBERT_SYNC_INTERVAL`, `HUBERT_SYNC_MIN_TTL`, and `HUBERT_METRICS`.
+    /// TTLs, timeouts, and other durations may be given as duration
+    /// literals such as `90m`.
+    pub fn apply_env(&mut self) -> Result<()> {
+        self.apply_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Override fields using `lookup` to resolve `HUBERT_*` variable names.
+    fn apply_env_with(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<()> {
+        fn parse<T: std::str::FromStr>(name: &str, value: String) -> Result<T> {
+            value.trim().parse().map_err(|_| {
+                ServerError::Config(format!("invalid value for {}", name))
+                    .into()
+            })
+        }
+        fn duration(name: &str, value: String) -> Result<u64> {
+            crate::duration::parse_duration(&value).map_err(|e| {
+                ServerError::Config(format!("{}: {}", name, e)).into()
+            })
+        }
+        fn parse_list<T: std::str::FromStr>(
+            name: &str,
+            value: &str,
+        ) -> Result<Vec<T>> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| parse(name, s.to_string()))
+                .collect()
+        }
+
+        if let Some(v) = lookup("HUBERT_BIND") {
+            self.bind = v;
+        }
+        if let Some(v) = lookup("HUBERT_PORT") {
+            self.port = parse("HUBERT_PORT", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_MAX_TTL") {
+            self.max_ttl = duration("HUBERT_MAX_TTL", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_DEFAULT_TTL") {
+            self.default_ttl = if v.is_empty() {
+                None
+            } else {
+                Some(duration("HUBERT_DEFAULT_TTL", v)?)
+            };
+        }
+        if let Some(v) = lookup("HUBERT_VERBOSE") {
+            self.verbose = parse("HUBERT_VERBOSE", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_QUIET") {
+            self.quiet = parse("HUBERT_QUIET", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_SQLITE") {
+            self.sqlite = if v.is_empty() { None } else { Some(v.into()) };
+        }
+        if let Some(v) = lookup("HUBERT_REQUEST_TIMEOUT") {
+            self.request_timeout = duration("HUBERT_REQUEST_TIMEOUT", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_MAX_CONNECTIONS") {
+            self.max_connections = parse("HUBERT_MAX_CONNECTIONS", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_IDLE_TIMEOUT") {
+            self.idle_timeout = duration("HUBERT_IDLE_TIMEOUT", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_SHUTDOWN_GRACE") {
+            self.shutdown_grace = duration("HUBERT_SHUTDOWN_GRACE", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_RECEIPT_RETENTION") {
+            self.receipt_retention = duration("HUBERT_RECEIPT_RETENTION", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_CAPABILITY_ROOTS") {
+            self.capability_roots = v
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(v) = lookup("HUBERT_REPLAY_WINDOW") {
+            self.replay_window = duration("HUBERT_REPLAY_WINDOW", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_TRUSTED_PROXIES") {
+            self.trusted_proxies = parse_list("HUBERT_TRUSTED_PROXIES", &v)?;
+        }
+        if let Some(v) = lookup("HUBERT_ALLOWED_IPS") {
+            self.allowed_ips = parse_list("HUBERT_ALLOWED_IPS", &v)?;
+        }
+        if let Some(v) = lookup("HUBERT_DENIED_IPS") {
+            self.denied_ips = parse_list("HUBERT_DENIED_IPS", &v)?;
+        }
+        if let Some(v) = lookup("HUBERT_READ_ONLY") {
+            self.read_only = parse("HUBERT_READ_ONLY", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_TOMBSTONE_RETENTION") {
+            self.tombstone_retention = if v.is_empty() {
+                None
+            } else {
+                Some(duration("HUBERT_TOMBSTONE_RETENTION", v)?)
+            };
+        }
+        if let Some(v) = lookup("HUBERT_IDEMPOTENT_PUTS") {
+            self.idempotent_puts = parse("HUBERT_IDEMPOTENT_PUTS", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_MUTABILITY") {
+            self.mutability = parse("HUBERT_MUTABILITY", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_COMPRESSION_THRESHOLD") {
+            self.compression_threshold = if v.is_empty() {
+                None
+            } else {
+                Some(parse("HUBERT_COMPRESSION_THRESHOLD", v)?)
+            };
+        }
+        if let Some(v) = lookup("HUBERT_COLD_STORAGE") {
+            self.cold_storage =
+                if v.is_empty() { None } else { Some(v.into()) };
+        }
+        if let Some(v) = lookup("HUBERT_COLD_AFTER") {
+            self.cold_after = duration("HUBERT_COLD_AFTER", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_IDENTITY") {
+            self.identity = if v.is_empty() { None } else { Some(v) };
+        }
+        if let Some(v) = lookup("HUBERT_PEERS") {
+            self.peers = v
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|peer| {
+                    let (url, public_keys) =
+                        peer.rsplit_once('=').ok_or_else(|| {
+                            ServerError::Config(
+                                "invalid value for HUBERT_PEERS".to_string(),
+                            )
+                        })?;
+                    Ok(PeerConfig {
+                        url: url.to_string(),
+                        public_keys: public_keys.to_string(),
+                    })
+                })
+                .collect::<Result<_>>()?;
+        }
+        if let Some(v) = lookup("HUBERT_SYNC_INTERVAL") {
+            self.sync_interval = duration("HUBERT_SYNC_INTERVAL", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_SYNC_MIN_TTL") {
+            self.sync_min_ttl = duration("HUBERT_SYNC_MIN_TTL", v)?;
+        }
+        if let Some(v) = lookup("HUBERT_METRICS") {
+            self.metrics = parse("HUBERT_METRICS", v)?;
+        }
+        Ok(())
+    }
+
+    /// Decode [`Self::capability_roots`].
+    pub fn trust_roots(&self) -> Result<Vec<PublicKeys>> {
+        bc_components::register_tags();
+        self.capability_roots
+            .iter()
+            .map(|ur| {
+                PublicKeys::from_ur_string(ur.trim()).map_err(|e| {
+                    ServerError::Config(format!(
+                        "invalid capability root {}: {}",
+                        ur, e
+                    ))
+                    .into()
+                })
+            })
+            .collect()
+    }
+
+    /// Decode [`Self::identity`].
+    pub fn identity_keys(&self) -> Result<Option<PrivateKeys>> {
+        bc_components::register_tags();
+        self.identity
+            .as_deref()
+            .map(|ur| {
+                PrivateKeys::from_ur_string(ur.trim()).map_err(|e| {
+                    ServerError::Config(format!("invalid identity: {}", e))
+                        .into()
+                })
+            })
+            .transpose()
+    }
+
+    /// Decode the public keys of [`Self::peers`], paired with their URLs.
+    pub fn peer_keys(&self) -> Result<Vec<(String, PublicKeys)>> {
+        bc_components::register_tags();
+        self.peers
+            .iter()
+            .map(|peer| {
+                let keys = PublicKeys::from_ur_string(peer.public_keys.trim())
+                    .map_err(|e| {
+                        ServerError::Config(format!(
+                            "invalid public keys of peer {}: {}",
+                            peer.url, e
+                        ))
+                    })?;
+                Ok((peer.url.trim_end_matches('/').to_string(), keys))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_partial_file_uses_defaults() {
+        let config =
+            ServerConfig::from_toml("port = 9000\nmax_ttl = 60\n").unwrap();
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.max_ttl, 60);
+        assert_eq!(config.bind, "127.0.0.1");
+        assert_eq!(config.max_connections, 1024);
+        assert!(config.sqlite.is_none());
+    }
+
+    #[test]
+    fn test_duration_literals() {
+        let config = ServerConfig::from_toml(
+            "max_ttl = \"2h30m\"\ndefault_ttl = \"90m\"\nidle_timeout = 45\n",
+        )
+        .unwrap();
+        assert_eq!(config.max_ttl, 9000);
+        assert_eq!(config.default_ttl, Some(5400));
+        assert_eq!(config.idle_timeout, 45);
+        assert!(ServerConfig::from_toml("max_ttl = \"2 hours\"\n").is_err());
+
+        let mut config = ServerConfig::default();
+        config
+            .apply_env_with(|name| {
+                (name == "HUBERT_COLD_AFTER").then(|| "1w".to_string())
+            })
+            .unwrap();
+        assert_eq!(config.cold_after, 604800);
+    }
+
+    #[test]
+    fn test_unknown_field_rejected() {
+        assert!(ServerConfig::from_toml("prot = 9000\n").is_err());
+        assert!(ServerConfig::from_toml("mutability = \"mutable\"\n").is_err());
+        let config =
+            ServerConfig::from_toml("mutability = \"updatable\"\n").unwrap();
+        assert_eq!(config.mutability, Mutability::Updatable);
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars: HashMap<&str, &str> = [
+            ("HUBERT_PORT", "9001"),
+            ("HUBERT_BIND", "0.0.0.0"),
+            ("HUBERT_VERBOSE", "true"),
+            ("HUBERT_QUIET", "true"),
+            ("HUBERT_SQLITE", "/tmp/hubert.sqlite"),
+            ("HUBERT_READ_ONLY", "true"),
+            ("HUBERT_TOMBSTONE_RETENTION", "3600"),
+            ("HUBERT_DEFAULT_TTL", "600"),
+            ("HUBERT_REPLAY_WINDOW", "60"),
+            ("HUBERT_DENIED_IPS", "192.0.2.0/24, 2001:db8::/32"),
+            ("HUBERT_COMPRESSION_THRESHOLD", "4096"),
+            ("HUBERT_COLD_STORAGE", "/srv/hubert-cold"),
+            ("HUBERT_COLD_AFTER", "604800"),
+            ("HUBERT_MUTABILITY", "updatable"),
+            ("HUBERT_METRICS", "true"),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut config = ServerConfig::default();
+        config
+            .apply_env_with(|name| vars.get(name).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(config.port, 9001);
+        assert_eq!(config.bind, "0.0.0.0");
+        assert!(config.verbose);
+        assert!(config.quiet);
+        assert_eq!(config.sqlite, Some(PathBuf::from("/tmp/hubert.sqlite")));
+        assert!(config.read_only);
+        assert_eq!(config.tombstone_retention, Some(3600));
+        assert_eq!(config.default_ttl, Some(600));
+        assert_eq!(config.replay_window, 60);
+        assert_eq!(config.denied_ips.len(), 2);
+        assert_eq!(config.compression_threshold, Some(4096));
+        assert_eq!(
+            config.cold_storage,
+            Some(PathBuf::from("/srv/hubert-cold"))
+        );
+        assert_eq!(config.cold_after, 604800);
+        assert_eq!(config.mutability, Mutability::Updatable);
+        assert!(config.metrics);
+    }
+
+    #[test]
+    fn test_capability_roots() {
+        bc_components::register_tags();
+        let (_, public_keys) = bc_components::keypair();
+        let ur = public_keys.ur_string();
+        let config = ServerConfig::from_toml(&format!(
+            "capability_roots = [\"{}\"]\n",
+            ur
+        ))
+        .unwrap();
+        assert_eq!(config.trust_roots().unwrap(), vec![public_keys]);
+
+        let mut config = ServerConfig::default();
+        config
+            .apply_env_with(|name| {
+                (name == "HUBERT_CAPABILITY_ROOTS")
+                    .then(|| "ur:crypto-pubkeys/bogus".to_string())
+            })
+            .unwrap();
+        assert!(config.trust_roots().is_err());
+    }
+
+    #[test]
+    fn test_peers() {
+        bc_components::register_tags();
+        let (private_keys, _) = bc_components::keypair();
+        let (_, peer_public) = bc_components::keypair();
+        let config = ServerConfig::from_toml(&format!(
+            "identity = \"{}\"\nsync_interval = \"1m\"\n\n[[peers]]\n\
+             url = \"http://hubert-b:45678/\"\npublic_keys = \"{}\"\n",
+            private_keys.ur_string(),
+            peer_public.ur_string()
+        ))
+        .unwrap();
+        assert_eq!(config.sync_interval, 60);
+        assert_eq!(config.identity_keys().unwrap(), Some(private_keys));
+        assert_eq!(
+            config.peer_keys().unwrap(),
+            vec![("http://hubert-b:45678".to_string(), peer_public.clone())]
+        );
+
+        let mut config = ServerConfig::default();
+        let peers =
+            format!("http://hubert-c:45678={}", peer_public.ur_string());
+        config
+            .apply_env_with(|name| {
+                (name == "HUBERT_PEERS").then(|| peers.clone())
+            })
+            .unwrap();
+        assert_eq!(config.peers[0].url, "http://hubert-c:45678");
+        assert!(config.identity_keys().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_tls_clients() {
+        let config = ServerConfig::from_toml(
+            r#"
+            [tls]
+            cert = "server.pem"
+            key = "server.key"
+            client_ca = "ca.pem"
+
+            [tls.clients.observer]
+            allow = ["get"]
+            "#,
+        )
+        .unwrap();
+        let tls = config.tls.unwrap();
+        assert_eq!(
+            tls.clients["observer"],
+            ClientPolicy { allow: vec![Right::Get], max_puts_per_hour: None }
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_value() {
+        let mut config = ServerConfig::default();
+        let result = config.apply_env_with(|name| {
+            (name == "HUBERT_PORT").then(|| "not-a-port".to_string())
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_client_config() {
+        let config: ServerClientConfig =
+            toml::from_str("tag = \"coordinator\"").unwrap();
+        assert_eq!(config.base_url, "http://127.0.0.1:45678");
+        assert!(ServerKvClient::with_config(config).is_ok());
+
+        let bad = ServerClientConfig {
+            capability: Some("ur:envelope/nonsense".into()),
+            ..Default::default()
+        };
+        assert!(ServerKvClient::with_config(bad).is_err());
+    }
+}
@@ -0,0 +1,394 @@
+//! Conformance runs, which let the author of a Hubert client in another
+//! language check it against a live server.
+//!
+//! `POST /conformance` with a test token of the caller's choosing starts a
+//! run and answers with a [`ConformanceScript`]: an ARID, two envelopes, and
+//! a TTL. The client then performs each [`ConformanceStep`] in order with
+//! its own code:
+//!
+//! 1. `put`: put the envelope at the ARID with the TTL, which succeeds.
+//! 2. `duplicate_put`: put the second envelope at the same ARID, which fails
+//!    with 409 Conflict.
+//! 3. `get`: get the ARID, which returns the first envelope.
+//! 4. `expiry`: at least a second after the TTL has passed, get the ARID again,
+//!    which finds nothing.
+//!
+//! The server checks each request as it arrives, and
+//! `GET /conformance/{token}` reports which steps have passed.
+
+#[cfg(feature = "server")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "server")]
+use bc_components::{ARID, Digest, DigestProvider};
+#[cfg(feature = "server")]
+use bc_envelope::Envelope;
+#[cfg(feature = "server")]
+use bc_ur::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+#[cfg(feature = "server")]
+use super::ReceiptStatus;
+
+/// Longest test token accepted.
+pub const MAX_CONFORMANCE_TOKEN_LENGTH: usize = 64;
+
+/// TTL a conformance script asks for, in seconds, so the expiry step does
+/// not keep the client waiting.
+#[cfg(feature = "server")]
+const CONFORMANCE_TTL: u64 = 2;
+
+/// Most runs kept at once; starting another forgets the oldest.
+#[cfg(feature = "server")]
+const MAX_RUNS: usize = 100;
+
+/// Seconds a run is kept after it starts.
+#[cfg(feature = "server")]
+const RUN_LIFETIME: u64 = 3600;
+
+/// One request of a conformance run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ConformanceStep {
+    Put,
+    DuplicatePut,
+    Get,
+    Expiry,
+}
+
+impl ConformanceStep {
+    /// Every step, in the order the client performs them.
+    pub const ALL: [Self; 4] =
+        [Self::Put, Self::DuplicatePut, Self::Get, Self::Expiry];
+}
+
+/// What a conformance run asks the client to do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct ConformanceScript {
+    pub token: String,
+    /// ARID to put and get (ur:arid).
+    pub arid: String,
+    /// Envelope to put (ur:envelope).
+    pub envelope: String,
+    /// Envelope for the duplicate put (ur:envelope).
+    pub duplicate_envelope: String,
+    /// TTL to send with the put, in seconds.
+    pub ttl: u64,
+    pub steps: Vec<ConformanceStep>,
+}
+
+/// Where a step of a conformance run stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// The server has not yet seen the request.
+    Pending,
+    Passed,
+    Failed,
+}
+
+/// One step of a [`ConformanceReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct StepResult {
+    pub step: ConformanceStep,
+    pub status: StepStatus,
+    /// Why the step failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// How far a conformance run has got.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct ConformanceReport {
+    pub token: String,
+    /// Whether every step has passed.
+    pub passed: bool,
+    pub steps: Vec<StepResult>,
+}
+
+/// Whether `token` can name a run: 1 to
+/// [`MAX_CONFORMANCE_TOKEN_LENGTH`] ASCII letters, digits, `-`, or `_`.
+pub fn is_valid_conformance_token(token: &str) -> bool {
+    (1..=MAX_CONFORMANCE_TOKEN_LENGTH).contains(&token.len())
+        && token
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(feature = "server")]
+struct Run {
+    token: String,
+    started_at: u64,
+    envelope: Digest,
+    duplicate: Digest,
+    ttl: u64,
+    /// When the put's entry expires, once it has been stored.
+    expires_at: Option<u64>,
+    steps: Vec<StepResult>,
+}
+
+#[cfg(feature = "server")]
+impl Run {
+    fn status(&self, step: ConformanceStep) -> StepStatus {
+        self.steps[step as usize].status
+    }
+
+    fn pass(&mut self, step: ConformanceStep) {
+        self.steps[step as usize].status = StepStatus::Passed;
+    }
+
+    fn fail(&mut self, step: ConformanceStep, detail: impl Into<String>) {
+        let result = &mut self.steps[step as usize];
+        result.status = StepStatus::Failed;
+        result.detail = Some(detail.into());
+    }
+
+    fn report(&self) -> ConformanceReport {
+        ConformanceReport {
+            token: self.token.clone(),
+            passed: self
+                .steps
+                .iter()
+                .all(|result| result.status == StepStatus::Passed),
+            steps: self.steps.clone(),
+        }
+    }
+}
+
+/// The conformance runs in progress, by the ARID each one uses.
+#[cfg(feature = "server")]
+#[derive(Clone, Default)]
+pub(super) struct ConformanceLog {
+    runs: Arc<Mutex<HashMap<ARID, Run>>>,
+}
+
+#[cfg(feature = "server")]
+impl ConformanceLog {
+    pub(super) fn new() -> Self { Self::default() }
+
+    /// Start a run named `token`, replacing any earlier run of that name,
+    /// with the put's TTL clamped by `effective_ttl`.
+    pub(super) fn start(
+        &self,
+        token: &str,
+        effective_ttl: impl FnOnce(u64) -> u64,
+        now: u64,
+    ) -> ConformanceScript {
+        let arid = ARID::new();
+        let envelope = Envelope::new(format!("Hubert conformance {}", token));
+        let duplicate =
+            Envelope::new(format!("Hubert conformance {} duplicate", token));
+        let ttl = effective_ttl(CONFORMANCE_TTL);
+
+        let mut runs = self.runs.lock().unwrap();
+        runs.retain(|_, run| {
+            run.token != token
+                && now < run.started_at.saturating_add(RUN_LIFETIME)
+        });
+        if runs.len() >= MAX_RUNS
+            && let Some(oldest) = runs
+                .iter()
+                .min_by_key(|(_, run)| run.started_at)
+                .map(|(arid, _)| *arid)
+        {
+            runs.remove(&oldest);
+        }
+        runs.insert(
+            arid,
+            Run {
+                token: token.to_string(),
+                started_at: now,
+                envelope: envelope.digest(),
+                duplicate: duplicate.digest(),
+                ttl,
+                expires_at: None,
+                steps: ConformanceStep::ALL
+                    .into_iter()
+                    .map(|step| StepResult {
+                        step,
+                        status: StepStatus::Pending,
+                        detail: None,
+                    })
+                    .collect(),
+            },
+        );
+
+        ConformanceScript {
+            token: token.to_string(),
+            arid: arid.ur_string(),
+            envelope: envelope.ur_string(),
+            duplicate_envelope: duplicate.ur_string(),
+            ttl,
+            steps: ConformanceStep::ALL.to_vec(),
+        }
+    }
+
+    /// Check a put of the envelope with `digest` at `arid`, which asked for
+    /// `ttl` seconds and was stored with `receipt` or refused.
+    pub(super) fn observe_put(
+        &self,
+        arid: &ARID,
+        digest: &Digest,
+        ttl: Option<u64>,
+        receipt: Option<&ReceiptStatus>,
+    ) {
+        use ConformanceStep::*;
+
+        let mut runs = self.runs.lock().unwrap();
+        let Some(run) = runs.get_mut(arid) else {
+            return;
+        };
+        if run.status(Put) == StepStatus::Pending {
+            if *digest != run.envelope {
+                run.fail(Put, "put a different envelope than the script's");
+            } else if ttl != Some(run.ttl) {
+                run.fail(
+                    Put,
+                    format!("asked for TTL {:?} instead of {}", ttl, run.ttl),
+                );
+            } else if let Some(ReceiptStatus::Stored { expires_at, .. }) =
+                receipt
+            {
+                run.expires_at = Some(*expires_at);
+                run.pass(Put);
+            } else {
+                run.fail(Put, "the put was refused");
+            }
+        } else if run.status(DuplicatePut) == StepStatus::Pending {
+            if *digest != run.duplicate {
+                run.fail(
+                    DuplicatePut,
+                    "put a different envelope than the script's duplicate",
+                );
+            } else if receipt.is_some() {
+                run.fail(DuplicatePut, "the duplicate put was stored");
+            } else {
+                run.pass(DuplicatePut);
+            }
+        }
+    }
+
+    /// Check a get of `arid` at `now` that found the envelope with digest
+    /// `found`, or nothing.
+    pub(super) fn observe_get(
+        &self,
+        arid: &ARID,
+        found: Option<&Digest>,
+        now: u64,
+    ) {
+        use ConformanceStep::*;
+
+        let mut runs = self.runs.lock().unwrap();
+        let Some(run) = runs.get_mut(arid) else {
+            return;
+        };
+        // Gets before the put may be a client polling; they prove nothing
+        let Some(expires_at) = run.expires_at else {
+            return;
+        };
+        // Expiry is kept to the second, so the second it falls in proves
+        // nothing either way for a get that finds nothing
+        if now <= expires_at {
+            if run.status(Get) == StepStatus::Pending {
+                match found {
+                    Some(digest) if *digest == run.envelope => run.pass(Get),
+                    None if now == expires_at => {}
+                    _ => run.fail(Get, "the get did not return the envelope"),
+                }
+            }
+            return;
+        }
+        if run.status(Get) == StepStatus::Pending {
+            run.fail(Get, "no get arrived before the entry expired");
+        }
+        if run.status(Expiry) == StepStatus::Pending {
+            match found {
+                None => run.pass(Expiry),
+                Some(_) => run.fail(Expiry, "the expired entry was returned"),
+            }
+        }
+    }
+
+    /// The report of the run named `token`, if it is still kept.
+    pub(super) fn report(&self, token: &str) -> Option<ConformanceReport> {
+        let runs = self.runs.lock().unwrap();
+        runs.values()
+            .find(|run| run.token == token)
+            .map(Run::report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_validation() {
+        assert!(is_valid_conformance_token("swift-client_1"));
+        assert!(!is_valid_conformance_token(""));
+        assert!(!is_valid_conformance_token("has space"));
+        assert!(!is_valid_conformance_token(&"a".repeat(65)));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_run_passes_in_order() {
+        bc_components::register_tags();
+        let log = ConformanceLog::new();
+        let script = log.start("t", |ttl| ttl, 100);
+        let arid = ARID::from_ur_string(&script.arid).unwrap();
+        let envelope =
+            Envelope::from_ur_string(&script.envelope).unwrap().digest();
+        let duplicate = Envelope::from_ur_string(&script.duplicate_envelope)
+            .unwrap()
+            .digest();
+        let stored = ReceiptStatus::Stored { stored_at: 100, expires_at: 102 };
+
+        // A get before the put is ignored
+        log.observe_get(&arid, None, 100);
+        log.observe_put(&arid, &envelope, Some(2), Some(&stored));
+        log.observe_put(&arid, &duplicate, Some(2), None);
+        log.observe_get(&arid, Some(&envelope), 101);
+        assert!(!log.report("t").unwrap().passed);
+        // Within the second of expiry, finding nothing is ignored
+        log.observe_get(&arid, None, 102);
+        assert!(!log.report("t").unwrap().passed);
+        log.observe_get(&arid, None, 103);
+
+        let report = log.report("t").unwrap();
+        assert!(report.passed, "{:?}", report);
+        assert!(log.report("other").is_none());
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_run_reports_failures() {
+        bc_components::register_tags();
+        let log = ConformanceLog::new();
+        let script = log.start("t", |ttl| ttl, 100);
+        let arid = ARID::from_ur_string(&script.arid).unwrap();
+        let envelope =
+            Envelope::from_ur_string(&script.envelope).unwrap().digest();
+
+        // Without the TTL line
+        log.observe_put(&arid, &envelope, None, None);
+        let report = log.report("t").unwrap();
+        assert_eq!(report.steps[0].status, StepStatus::Failed);
+        assert!(report.steps[0].detail.as_deref().unwrap().contains("TTL"));
+
+        // Restarting the run forgets the failure
+        log.start("t", |ttl| ttl, 101);
+        let report = log.report("t").unwrap();
+        assert_eq!(report.steps[0].status, StepStatus::Pending);
+    }
+}
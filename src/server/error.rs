@@ -4,18 +4,35 @@ pub enum Error {
     #[error("Server error: {0}")]
     General(String),
 
+    #[error("Configuration error: {0}")]
+    Config(String),
+
     #[error("Network error: {0}")]
     NetworkError(String),
 
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Entry expired at {} (UNIX time)", .0.expired_at)]
+    Gone(super::Tombstone),
+
+    #[error("Request of {size} bytes is larger than the server accepts")]
+    PayloadTooLarge { size: usize },
+
+    #[error("No live reservation of {arid} matches the secret")]
+    NotReserved { arid: String },
+
+    #[cfg(feature = "server-client")]
     #[error("Reqwest error: {0}")]
     ReqwestError(#[from] reqwest::Error),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[cfg(feature = "server")]
     #[error("SQLite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
 
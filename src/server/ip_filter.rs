@@ -0,0 +1,213 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use super::Error as ServerError;
+
+/// Header in which reverse proxies list the addresses a request came from.
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// A block of IP addresses in CIDR notation, such as `10.0.0.0/8` or
+/// `2001:db8::/32`. A bare address is a block of one.
+///
+/// IPv4 addresses mapped into IPv6, as a dual-stack listener reports them,
+/// match the IPv4 blocks they belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Whether `ip` is in the block.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(block), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(block) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(block), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(block) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ServerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || ServerError::Config(format!("invalid CIDR block {}", s));
+        let (addr, prefix_len) = match s.trim().split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s.trim(), None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| invalid())?
+            .to_canonical();
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len.parse().map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl TryFrom<String> for Cidr {
+    type Error = ServerError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> { s.parse() }
+}
+
+impl From<Cidr> for String {
+    fn from(cidr: Cidr) -> Self { cidr.to_string() }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// The client address forwarded by the proxy at `peer`, if `peer` is one
+/// of `trusted_proxies`.
+///
+/// Each proxy appends the address it received the request from to
+/// `X-Forwarded-For`, so the list is read from the end, past every trusted
+/// proxy, to the first address that is not one. Addresses before it may
+/// have been forged by the client and are ignored.
+pub(super) fn forwarded_client(
+    peer: IpAddr,
+    headers: &HeaderMap,
+    trusted_proxies: &[Cidr],
+) -> Option<IpAddr> {
+    let is_trusted =
+        |ip: &IpAddr| trusted_proxies.iter().any(|p| p.contains(ip));
+    if !is_trusted(&peer) {
+        return None;
+    }
+    let hops: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let mut client = None;
+    for hop in hops.into_iter().rev() {
+        // A proxy may include the port; anything unparsable ends the trail
+        let Some(ip) = hop
+            .parse::<IpAddr>()
+            .or_else(|_| hop.parse::<SocketAddr>().map(|addr| addr.ip()))
+            .ok()
+        else {
+            break;
+        };
+        client = Some(ip.to_canonical());
+        if !is_trusted(&ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// Whether a client at `ip` may use the server: it must not be in `denied`,
+/// and must be in `allowed` unless that is empty.
+pub(super) fn is_allowed(
+    ip: IpAddr,
+    allowed: &[Cidr],
+    denied: &[Cidr],
+) -> bool {
+    !denied.iter().any(|block| block.contains(&ip))
+        && (allowed.is_empty()
+            || allowed.iter().any(|block| block.contains(&ip)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr { s.parse().unwrap() }
+
+    fn cidrs(blocks: &[&str]) -> Vec<Cidr> {
+        blocks.iter().map(|block| block.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let private: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert!(private.contains(&ip("10.20.30.40")));
+        assert!(private.contains(&ip("::ffff:10.1.2.3")));
+        assert!(!private.contains(&ip("11.0.0.1")));
+        assert_eq!(private.to_string(), "10.0.0.0/8");
+
+        let single: Cidr = "2001:db8::1".parse().unwrap();
+        assert!(single.contains(&ip("2001:db8::1")));
+        assert!(!single.contains(&ip("2001:db8::2")));
+        assert!(
+            "0.0.0.0/0"
+                .parse::<Cidr>()
+                .unwrap()
+                .contains(&ip("1.2.3.4"))
+        );
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("example.com".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_forwarded_client() {
+        let trusted = cidrs(&["10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.append(
+            X_FORWARDED_FOR,
+            HeaderValue::from_static("6.6.6.6, 203.0.113.7"),
+        );
+        headers.append(X_FORWARDED_FOR, HeaderValue::from_static("10.1.1.1"));
+
+        // The forged first hop is ignored, as is the trusted inner proxy
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), &headers, &trusted),
+            Some(ip("203.0.113.7"))
+        );
+        // Headers from untrusted peers are ignored entirely
+        assert_eq!(
+            forwarded_client(ip("198.51.100.1"), &headers, &trusted),
+            None
+        );
+        assert_eq!(
+            forwarded_client(ip("10.0.0.1"), &HeaderMap::new(), &trusted),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let allowed = cidrs(&["192.0.2.0/24"]);
+        let denied = cidrs(&["192.0.2.66"]);
+        assert!(is_allowed(ip("192.0.2.1"), &allowed, &denied));
+        assert!(!is_allowed(ip("192.0.2.66"), &allowed, &denied));
+        assert!(!is_allowed(ip("198.51.100.1"), &allowed, &denied));
+        assert!(is_allowed(ip("198.51.100.1"), &[], &denied));
+    }
+}
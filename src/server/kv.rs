@@ -1,15 +1,83 @@
-use bc_components::ARID;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use bc_components::{ARID, Digest, PrivateKeys};
 use bc_envelope::Envelope;
 use bc_ur::prelude::*;
+use futures_util::{
+    StreamExt,
+    stream::{self, LocalBoxStream},
+};
+
+use super::{
+    AccessStats, AccessSummary, CAPABILITY_HEADER, ConformanceReport,
+    ConformanceScript, ListedEntry, REQUEST_PROOF_HEADER, RESERVATION_HEADER,
+    ReceiptStatus, Reservation, StorageStats, TAG_HEADER, Tombstone,
+    VERSION_HEADER,
+    clock::unix_now,
+    error::Error as ServerError,
+    request_proof::{ProvenRequest, sign_request_proof},
+    wire::{
+        GetManyBody, GetManyResponse, MAX_GET_MANY_ARIDS, MAX_GET_WAIT,
+        MAX_PUT_MANY_BODY_LENGTH, MAX_PUT_MANY_ENTRIES, PutBody, PutManyBody,
+        ReserveBody, WatchEvent, entity_tag,
+    },
+};
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Mutability, Receipt, Result,
+    StoreCapabilities, expiry,
+    kv_store::{poll_all, poll_watch},
+    receipt::Backend,
+    ur_input::{MAX_ENVELOPE_UR_LENGTH, parse_envelope_ur},
+    wire::{Codec, stored_size},
+};
 
-use super::error::Error as ServerError;
-use crate::{Error, KvStore, Result};
+/// Largest envelope a Hubert server accepts, in bytes of dCBOR: what fits
+/// in a `ur:envelope` of [`MAX_ENVELOPE_UR_LENGTH`] characters, at two per
+/// byte after the `ur:envelope/` prefix and before the 4-byte checksum.
+pub const MAX_ENVELOPE_SIZE: usize =
+    (MAX_ENVELOPE_UR_LENGTH - "ur:envelope/".len()) / 2 - 4;
+
+/// How long a health probe waits for the server to answer.
+const HEALTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// What [`ServerKvClient::get_detailed`] found at an ARID.
+///
+/// Separates an entry that expired from one that never appeared, which
+/// [`KvStore::get`] reports alike as `None`. Times are UNIX seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GetOutcome {
+    /// The envelope stored at the ARID.
+    Found(Envelope),
+    /// The entry expired at `expired_at`. The digest of the envelope that was
+    /// stored is known if the server kept a tombstone.
+    Expired { expired_at: u64, digest: Option<Digest> },
+    /// The server has no record of a put to the ARID. Receipts do not
+    /// survive a server restart, so an entry stored before one is reported
+    /// here too.
+    NeverExisted,
+    /// Nothing appeared before the timeout, and the server could not say
+    /// why. The entry may not have been stored yet.
+    NotFound,
+    /// The stored envelope has the digest given to
+    /// [`ServerKvClient::get_if_digest_differs`], so it was not sent again.
+    NotModified,
+}
 
 /// Server-backed key-value store using HTTP API.
 ///
 /// This implementation communicates with a Hubert server via HTTP POST
 /// requests.
 ///
+/// Gets long-poll: each `/get` request asks the server to hold it for up to
+/// 20 seconds, and the server answers as soon as a put stores the envelope.
+/// Servers that answer at once are polled every second instead.
+///
+/// A get of an entry that expired on a server keeping tombstones fails with
+/// [`Error::Gone`](ServerError::Gone) rather than waiting out the timeout.
+///
 /// # Example
 ///
 /// ```no_run
@@ -34,6 +102,10 @@ use crate::{Error, KvStore, Result};
 pub struct ServerKvClient {
     base_url: String,
     client: reqwest::Client,
+    capability: Option<String>,
+    holder_keys: Option<PrivateKeys>,
+    tag: Option<String>,
+    mutability: Mutability,
 }
 
 impl ServerKvClient {
@@ -42,9 +114,233 @@ impl ServerKvClient {
         Self {
             base_url: base_url.to_string(),
             client: reqwest::Client::new(),
+            capability: None,
+            holder_keys: None,
+            tag: None,
+            mutability: Mutability::WriteOnce,
+        }
+    }
+
+    /// The base URL of the server this client talks to.
+    pub fn base_url(&self) -> &str { &self.base_url }
+
+    /// Label every put with `tag`, an opaque name such as an application or
+    /// test run, so operators can list, count, and purge its entries
+    /// without knowing their ARIDs. Servers accept tags of up to 64 bytes.
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Send [`KvStore::update`] to the server's `/update` endpoint if
+    /// `mutability` is [`Mutability::Updatable`]. The server must be
+    /// configured as updatable too, or updates fail with
+    /// [`Error::Unsupported`].
+    pub fn with_mutability(mut self, mutability: Mutability) -> Self {
+        self.mutability = mutability;
+        self
+    }
+
+    /// Present `token`, a signed [`Capability`](super::Capability), with
+    /// every request, for servers that require one.
+    pub fn with_capability(mut self, token: &Envelope) -> Self {
+        bc_components::register_tags();
+        self.capability = Some(token.ur_string());
+        self
+    }
+
+    /// Sign a fresh request proof with `keys` for every request, as
+    /// capabilities bound to a holder require. `keys` are the private keys
+    /// of the holder the capability names.
+    pub fn with_holder_keys(mut self, keys: PrivateKeys) -> Self {
+        bc_components::register_tags();
+        self.holder_keys = Some(keys);
+        self
+    }
+
+    /// Authenticate to the server with a client certificate.
+    ///
+    /// `identity_pem` holds the client's certificate chain and private key;
+    /// `ca_pem` holds the certificates that issue the server's certificate,
+    /// which replace the built-in web roots. Use an `https://` base URL.
+    #[cfg(feature = "tls")]
+    pub fn with_client_cert(
+        mut self,
+        identity_pem: &[u8],
+        ca_pem: &[u8],
+    ) -> Result<Self> {
+        let identity = reqwest::Identity::from_pem(identity_pem)
+            .map_err(ServerError::from)?;
+        let mut builder = reqwest::Client::builder()
+            .use_rustls_tls()
+            .tls_built_in_root_certs(false)
+            .identity(identity);
+        for ca in reqwest::Certificate::from_pem_bundle(ca_pem)
+            .map_err(ServerError::from)?
+        {
+            builder = builder.add_root_certificate(ca);
+        }
+        self.client = builder.build().map_err(ServerError::from)?;
+        Ok(self)
+    }
+
+    /// Start a POST request to `endpoint`, carrying the capability if set.
+    fn post(&self, endpoint: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, endpoint)
+    }
+
+    /// Start a request to `endpoint`, carrying the capability if set.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+    ) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .request(method, format!("{}/{}", self.base_url, endpoint));
+        if let Some(token) = &self.capability {
+            request = request.header(CAPABILITY_HEADER, token);
+        }
+        request
+    }
+
+    /// Send `request`, acting on `arids`, with a fresh request proof bound
+    /// to its method, path, ARIDs, and body if holder keys are set.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        arids: &[ARID],
+    ) -> Result<reqwest::Response> {
+        let (client, request) = request.build_split();
+        let mut request = request.map_err(ServerError::from)?;
+        if let Some(keys) = &self.holder_keys {
+            // The server sees paths below any prefix in the base URL
+            let prefix = reqwest::Url::parse(&self.base_url)
+                .map(|url| url.path().trim_end_matches('/').to_string())
+                .unwrap_or_default();
+            let path = request.url().path();
+            let proven = ProvenRequest {
+                method: request.method().as_str(),
+                path: path.strip_prefix(prefix.as_str()).unwrap_or(path),
+                arids,
+                body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .unwrap_or_default(),
+            };
+            let proof = sign_request_proof(keys, &proven).ur_string();
+            let value = reqwest::header::HeaderValue::try_from(proof)
+                .map_err(|e| ServerError::General(e.to_string()))?;
+            request.headers_mut().insert(REQUEST_PROOF_HEADER, value);
+        }
+        Ok(client.execute(request).await.map_err(ServerError::from)?)
+    }
+
+    /// Send an admin request and parse its JSON response.
+    async fn admin_response<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let response = self.send(request, &[]).await?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        match status {
+            reqwest::StatusCode::OK => serde_json::from_str(&text)
+                .map_err(|e| ServerError::ParseError(e.to_string()).into()),
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                Err(ServerError::Unauthorized(text).into())
+            }
+            _ => Err(ServerError::General(text).into()),
+        }
+    }
+
+    /// Open a `/watch` stream for `arid`, or return `None` if the server
+    /// predates the endpoint.
+    async fn open_watch(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<reqwest::Response>> {
+        use crate::logging::verbose_println;
+
+        bc_components::register_tags();
+
+        if verbose {
+            verbose_println("Opening watch stream on server");
+        }
+
+        let request = self.post("watch").body(arid.ur_string());
+        let response =
+            self.send(request, std::slice::from_ref(arid)).await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::OK {
+            return Ok(Some(response));
+        }
+        let text = response.text().await.unwrap_or_default();
+        match status {
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                Err(ServerError::Unauthorized(text).into())
+            }
+            _ => Err(ServerError::General(text).into()),
         }
     }
 
+    /// The envelopes pushed on an open `/watch` stream.
+    ///
+    /// A write-once client ends the stream after the first. A `gone` event
+    /// is yielded as [`ServerError::Gone`], and a stream the server closes
+    /// as [`ServerError::NetworkError`]; either ends it.
+    fn watch_events<'a>(
+        &'a self,
+        response: reqwest::Response,
+    ) -> LocalBoxStream<'a, Result<Envelope>> {
+        let updates = self.mutability.is_updatable();
+        let watching = Some((response, Vec::new()));
+        stream::unfold(watching, move |watching| async move {
+            let (mut response, mut buffer) = watching?;
+            loop {
+                // Each event ends with a blank line
+                if let Some(end) =
+                    buffer.windows(2).position(|pair| pair == b"\n\n")
+                {
+                    let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                    let event = String::from_utf8_lossy(&event);
+                    let error = match WatchEvent::decode(&event) {
+                        Ok(Some(WatchEvent::Envelope(envelope))) => {
+                            let next = updates.then_some((response, buffer));
+                            return Some((Ok(envelope), next));
+                        }
+                        Ok(Some(WatchEvent::Gone(tombstone))) => {
+                            ServerError::Gone(tombstone)
+                        }
+                        Ok(None) => continue,
+                        Err(e) => e,
+                    };
+                    return Some((Err(error.into()), None));
+                }
+                let error = match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        buffer.extend_from_slice(&chunk);
+                        continue;
+                    }
+                    Ok(None) => ServerError::NetworkError(
+                        "Server closed the watch stream".to_string(),
+                    ),
+                    Err(e) => ServerError::NetworkError(e.to_string()),
+                };
+                return Some((Err(error.into()), None));
+            }
+        })
+        .boxed_local()
+    }
+
     /// Put an envelope with a TTL (time-to-live).
     ///
     /// Deprecated: Use `KvStore::put(arid, envelope, Some(ttl_seconds))`
@@ -58,150 +354,300 @@ impl ServerKvClient {
         arid: &ARID,
         envelope: &Envelope,
         ttl_seconds: u64,
-    ) -> Result<String> {
+    ) -> Result<Receipt> {
         self.put(arid, envelope, Some(ttl_seconds), false).await
     }
-}
 
-#[async_trait::async_trait(?Send)]
-impl KvStore for ServerKvClient {
-    async fn put(
+    /// Ask the server what became of a put to `arid`.
+    ///
+    /// Reports whether the entry is still stored, has expired and been
+    /// pruned, or was never stored (or expired longer ago than the server's
+    /// receipt retention).
+    pub async fn receipt(&self, arid: &ARID) -> Result<ReceiptStatus> {
+        bc_components::register_tags();
+
+        let request = self.post("receipts").body(arid.ur_string());
+        let response =
+            self.send(request, std::slice::from_ref(arid)).await?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        if status != reqwest::StatusCode::OK {
+            return Err(ServerError::General(text).into());
+        }
+        serde_json::from_str(&text)
+            .map_err(|e| ServerError::ParseError(e.to_string()).into())
+    }
+
+    /// Start a conformance run named `token`, returning the script a client
+    /// under test should follow against this server.
+    pub async fn start_conformance(
+        &self,
+        token: &str,
+    ) -> Result<ConformanceScript> {
+        let request = self.post("conformance").body(token.to_string());
+        self.admin_response(request).await
+    }
+
+    /// How far the conformance run named `token` has got.
+    pub async fn conformance_report(
+        &self,
+        token: &str,
+    ) -> Result<ConformanceReport> {
+        let path = format!("conformance/{}", token);
+        let request = self.request(reqwest::Method::GET, &path);
+        self.admin_response(request).await
+    }
+
+    /// Put `envelope` at `arid` like [`KvStore::put`], but return the
+    /// server's receipt, whose `expires_at` shows whether the requested TTL
+    /// was shortened to the server's maximum.
+    ///
+    /// Servers that predate receipts in put responses report
+    /// [`ReceiptStatus::Unknown`].
+    pub async fn put_detailed(
         &self,
         arid: &ARID,
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<String> {
+    ) -> Result<ReceiptStatus> {
+        self.store(arid, envelope, ttl_seconds, None, verbose).await
+    }
+
+    /// Put several envelopes like [`KvStore::put_many`], returning the
+    /// server's receipt for each.
+    ///
+    /// Entries are sent in one `/put_many` request per hundred entries or
+    /// 64 MB of body, and the server stores each request's entries all or
+    /// none. If a later request fails, the earlier ones remain stored.
+    pub async fn put_many_detailed(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<ReceiptStatus>> {
         use crate::logging::verbose_println;
 
         bc_components::register_tags();
 
-        if verbose {
-            verbose_println("Starting server put operation");
-        }
-
-        // Format body with optional TTL on third line
-        let body = if let Some(ttl) = ttl_seconds {
-            format!("{}\n{}\n{}", arid.ur_string(), envelope.ur_string(), ttl)
-        } else {
-            format!("{}\n{}", arid.ur_string(), envelope.ur_string())
-        };
-
-        if verbose {
-            verbose_println("Sending PUT request to server");
-        }
-
-        let response = self
-            .client
-            .post(format!("{}/put", self.base_url))
-            .body(body)
-            .send()
-            .await
-            .map_err(ServerError::from)?;
+        let mut receipts = Vec::with_capacity(entries.len());
+        for body in put_many_bodies(entries, ttl_seconds) {
+            if verbose {
+                verbose_println(&format!(
+                    "Sending PUT_MANY request with {} entries to server",
+                    body.entries.len()
+                ));
+            }
 
-        let result = match response.status() {
-            reqwest::StatusCode::OK => Ok("Stored successfully".to_string()),
-            reqwest::StatusCode::CONFLICT => {
-                Err(Error::AlreadyExists { arid: arid.ur_string() })
+            let mut request = self.post("put_many");
+            if let Some(tag) = &self.tag {
+                request = request.header(TAG_HEADER, tag);
             }
-            _ => {
-                let error_msg = response.text().await.unwrap_or_default();
-                Err(ServerError::General(error_msg).into())
+            let encoded = body.encode();
+            let size = encoded.len();
+            let arids: Vec<ARID> =
+                body.entries.iter().map(|(arid, _)| *arid).collect();
+            let response = self.send(request.body(encoded), &arids).await?;
+
+            let status = response.status();
+            let text = response
+                .text()
+                .await
+                .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+            match status {
+                reqwest::StatusCode::OK => {
+                    let batch: Vec<ReceiptStatus> = serde_json::from_str(&text)
+                        .map_err(|e| ServerError::ParseError(e.to_string()))?;
+                    receipts.extend(batch);
+                }
+                reqwest::StatusCode::CONFLICT => {
+                    // The server names the ARID that was taken
+                    let taken = body
+                        .entries
+                        .iter()
+                        .find(|(arid, _)| text.contains(&arid.ur_string()));
+                    return Err(match taken {
+                        Some((arid, _)) => {
+                            Error::AlreadyExists { arid: arid.ur_string() }
+                        }
+                        None => ServerError::General(text).into(),
+                    });
+                }
+                reqwest::StatusCode::PAYLOAD_TOO_LARGE => {
+                    return Err(ServerError::PayloadTooLarge { size }.into());
+                }
+                reqwest::StatusCode::UNAUTHORIZED
+                | reqwest::StatusCode::FORBIDDEN => {
+                    return Err(ServerError::Unauthorized(text).into());
+                }
+                _ => return Err(ServerError::General(text).into()),
             }
-        };
+        }
 
         if verbose {
-            if result.is_ok() {
-                verbose_println("Server put operation completed");
-            } else {
-                verbose_println("Server put operation failed");
-            }
+            verbose_println("Server put_many operation completed");
         }
+        Ok(receipts)
+    }
 
-        result
+    /// Get the envelope at `arid` like [`KvStore::get`], but on failure
+    /// report why nothing was found.
+    ///
+    /// A server keeping tombstones answers 410 Gone for an expired entry,
+    /// reported as [`GetOutcome::Expired`] with the stored envelope's digest.
+    /// When the timeout passes with nothing found, the server's
+    /// [`receipt`](Self::receipt) for `arid` is consulted to tell an entry
+    /// that expired from one never stored. Receipts need the `put` right;
+    /// without it the outcome is [`GetOutcome::NotFound`].
+    pub async fn get_detailed(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<GetOutcome> {
+        let deadline = deadline(timeout_seconds);
+        let outcome = self.poll_get(arid, None, deadline, verbose).await?;
+        if outcome != GetOutcome::NotFound {
+            return Ok(outcome);
+        }
+        Ok(match self.receipt(arid).await {
+            Ok(ReceiptStatus::Expired { expired_at, .. }) => {
+                GetOutcome::Expired { expired_at, digest: None }
+            }
+            Ok(ReceiptStatus::Unknown) => GetOutcome::NeverExisted,
+            _ => GetOutcome::NotFound,
+        })
     }
 
-    async fn get(
+    /// Get the envelope at `arid` unless it is the one with `digest`, which
+    /// the caller already holds.
+    ///
+    /// The server compares digests through `If-None-Match`, and answers
+    /// [`GetOutcome::NotModified`] without sending the envelope again. Since
+    /// entries are write-once, this confirms cheaply that an ARID still holds
+    /// what was fetched before. Otherwise this behaves like
+    /// [`KvStore::get`], waiting up to `timeout_seconds` for an envelope to
+    /// appear, and reports [`GetOutcome::Found`], [`GetOutcome::Expired`]
+    /// for a tombstoned entry, or [`GetOutcome::NotFound`].
+    pub async fn get_if_digest_differs(
         &self,
         arid: &ARID,
+        digest: &Digest,
         timeout_seconds: Option<u64>,
         verbose: bool,
-    ) -> Result<Option<Envelope>> {
-        use tokio::time::{Duration, Instant, sleep};
+    ) -> Result<GetOutcome> {
+        self.poll_get(arid, Some(digest), deadline(timeout_seconds), verbose)
+            .await
+    }
 
-        use crate::logging::{
-            verbose_newline, verbose_print_dot, verbose_println,
-        };
+    /// Poll `/get` until the envelope appears, the server reports it gone,
+    /// or `deadline` passes. With `if_none_match`, an envelope with that
+    /// digest is reported as [`GetOutcome::NotModified`].
+    async fn poll_get(
+        &self,
+        arid: &ARID,
+        if_none_match: Option<&Digest>,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<GetOutcome> {
+        use tokio::time::sleep;
 
-        bc_components::register_tags();
+        use crate::logging::{PollProgress, verbose_println};
 
-        let mut printed_dot = false;
+        bc_components::register_tags();
 
         if verbose {
             verbose_println("Starting server get operation");
         }
 
-        let timeout = timeout_seconds.unwrap_or(30); // Default 30 seconds
-        let deadline = Instant::now() + Duration::from_secs(timeout);
         // Changed to 1000ms for verbose mode polling
         let poll_interval = Duration::from_millis(1000);
 
         if verbose {
             verbose_println("Polling server for value");
         }
+        let mut progress = PollProgress::new("server", deadline, verbose);
 
         loop {
             let body = arid.ur_string();
 
+            // Ask the server to hold the request until a put or the deadline
+            let wait = deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs()
+                .min(MAX_GET_WAIT);
+            let sent = Instant::now();
+            let mut request = self.post("get").query(&[("wait", wait)]);
+            if let Some(digest) = if_none_match {
+                request = request
+                    .header(reqwest::header::IF_NONE_MATCH, entity_tag(digest));
+            }
             let response = self
-                .client
-                .post(format!("{}/get", self.base_url))
-                .body(body)
-                .send()
-                .await
-                .map_err(ServerError::from)?;
+                .send(request.body(body), std::slice::from_ref(arid))
+                .await?;
 
             match response.status() {
                 reqwest::StatusCode::OK => {
-                    if verbose && printed_dot {
-                        verbose_newline();
-                    }
                     if verbose {
                         verbose_println("Value found on server");
                     }
                     let envelope_str = response.text().await.map_err(|e| {
                         ServerError::NetworkError(e.to_string())
                     })?;
-                    let envelope = Envelope::from_ur_string(&envelope_str)
+                    let envelope = parse_envelope_ur(&envelope_str)
                         .map_err(|e| ServerError::ParseError(e.to_string()))?;
 
                     if verbose {
                         verbose_println("Server get operation completed");
                     }
 
-                    return Ok(Some(envelope));
+                    return Ok(GetOutcome::Found(envelope));
+                }
+                reqwest::StatusCode::NOT_MODIFIED => {
+                    if verbose {
+                        verbose_println("Value on server is unchanged");
+                    }
+                    return Ok(GetOutcome::NotModified);
                 }
                 reqwest::StatusCode::NOT_FOUND => {
                     // Not found yet - check if we should keep polling
                     if Instant::now() >= deadline {
                         // Timeout reached
-                        if verbose && printed_dot {
-                            verbose_newline();
-                        }
                         if verbose {
                             verbose_println("Timeout reached, value not found");
                         }
-                        return Ok(None);
+                        return Ok(GetOutcome::NotFound);
                     }
 
-                    // Print polling dot if verbose
+                    progress.missed();
+
+                    // A server that answered without waiting is polled
+                    if wait == 0 || sent.elapsed() < Duration::from_secs(wait) {
+                        let left =
+                            deadline.saturating_duration_since(Instant::now());
+                        sleep(poll_interval.min(left)).await;
+                    }
+                }
+                reqwest::StatusCode::GONE => {
                     if verbose {
-                        verbose_print_dot();
-                        printed_dot = true;
+                        verbose_println("Value expired on server");
                     }
-
-                    // Wait before retrying (now 1000ms)
-                    sleep(poll_interval).await;
+                    let text = response.text().await.unwrap_or_default();
+                    let tombstone: Tombstone = serde_json::from_str(&text)
+                        .map_err(|e| ServerError::ParseError(e.to_string()))?;
+                    return Ok(GetOutcome::Expired {
+                        expired_at: tombstone.expired_at,
+                        digest: Some(tombstone.digest),
+                    });
+                }
+                reqwest::StatusCode::UNAUTHORIZED
+                | reqwest::StatusCode::FORBIDDEN => {
+                    let error_msg = response.text().await.unwrap_or_default();
+                    return Err(ServerError::Unauthorized(error_msg).into());
                 }
                 _ => {
                     let error_msg = response.text().await.unwrap_or_default();
@@ -211,8 +657,637 @@ impl KvStore for ServerKvClient {
         }
     }
 
+    /// List up to `limit` of the server's stored entries, skipping the first
+    /// `offset`, in a stable order.
+    ///
+    /// Expired entries that have not been pruned yet are included only if
+    /// `include_expired` is set, only entries put with `tag` if given, and
+    /// only those whose ARID's UR starts with `prefix` if given. The server
+    /// caps `limit` at 1000. Requires the `admin` right, by capability or
+    /// client certificate.
+    pub async fn list(
+        &self,
+        offset: usize,
+        limit: usize,
+        include_expired: bool,
+        tag: Option<&str>,
+        prefix: Option<&str>,
+    ) -> Result<Vec<ListedEntry>> {
+        bc_components::register_tags();
+
+        let mut query = vec![
+            ("offset", offset.to_string()),
+            ("limit", limit.to_string()),
+            ("include_expired", include_expired.to_string()),
+        ];
+        query.extend(tag.map(|tag| ("tag", tag.to_string())));
+        query.extend(prefix.map(|prefix| ("prefix", prefix.to_string())));
+        let request = self
+            .request(reqwest::Method::GET, "admin/list")
+            .query(&query);
+        self.admin_response(request).await
+    }
+
+    /// Count the server's unexpired entries, only those put with `tag` if
+    /// given. Requires the `admin` right.
+    pub async fn count(&self, tag: Option<&str>) -> Result<usize> {
+        let request = self
+            .request(reqwest::Method::GET, "admin/count")
+            .query(&[("tag", tag)]);
+        let count: serde_json::Value = self.admin_response(request).await?;
+        count["count"].as_u64().map(|n| n as usize).ok_or_else(|| {
+            ServerError::ParseError("invalid count response".into()).into()
+        })
+    }
+
+    /// Remove every entry put with `tag`, expired or not, returning the
+    /// number removed. Requires the `admin` right.
+    pub async fn purge(&self, tag: &str) -> Result<usize> {
+        let request = self.post("admin/purge").body(tag.to_string());
+        let purged: serde_json::Value = self.admin_response(request).await?;
+        purged["purged"]
+            .as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| {
+                ServerError::ParseError("invalid purge response".into()).into()
+            })
+    }
+
+    /// How often `arid` has been asked for and found, and when. Requires the
+    /// `admin` right.
+    pub async fn stats(&self, arid: &ARID) -> Result<AccessStats> {
+        bc_components::register_tags();
+
+        let path = format!("admin/stats/{}", arid.ur_string());
+        let request = self.request(reqwest::Method::GET, &path);
+        self.admin_response(request).await
+    }
+
+    /// Get totals across every ARID the server has been asked for
+    /// recently, including how many expired entries were never read.
+    /// Requires the `admin` right.
+    pub async fn stats_summary(&self) -> Result<AccessSummary> {
+        let request = self.request(reqwest::Method::GET, "admin/stats");
+        self.admin_response(request).await
+    }
+
+    /// Get how many entries the server stores and the space they take,
+    /// including the compression ratio achieved on large envelopes.
+    /// Requires the `admin` right.
+    pub async fn storage_stats(&self) -> Result<StorageStats> {
+        let request = self.request(reqwest::Method::GET, "admin/storage");
+        self.admin_response(request).await
+    }
+
+    /// Reserve `arid` for `ttl_seconds` (the server's default TTL if not
+    /// given), before the envelope to store there exists.
+    ///
+    /// Until the reservation is filled with [`commit`](Self::commit) or
+    /// lapses, puts to the ARID fail with [`Error::AlreadyExists`], as does
+    /// reserving an ARID that is stored or already reserved.
+    pub async fn reserve(
+        &self,
+        arid: &ARID,
+        ttl_seconds: Option<u64>,
+    ) -> Result<Reservation> {
+        bc_components::register_tags();
+
+        let body = ReserveBody { arid: *arid, ttl: ttl_seconds }.encode();
+        let request = self.post("reserve").body(body);
+        let response =
+            self.send(request, std::slice::from_ref(arid)).await?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        match status {
+            reqwest::StatusCode::OK => serde_json::from_str(&text)
+                .map_err(|e| ServerError::ParseError(e.to_string()).into()),
+            reqwest::StatusCode::CONFLICT => {
+                Err(Error::AlreadyExists { arid: arid.ur_string() })
+            }
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                Err(ServerError::Unauthorized(text).into())
+            }
+            _ => Err(ServerError::General(text).into()),
+        }
+    }
+
+    /// Store `envelope` at the ARID held by `reservation`, like
+    /// [`put_detailed`](Self::put_detailed). Fails with
+    /// [`server::Error::NotReserved`](ServerError::NotReserved) if the
+    /// reservation has lapsed, and with [`Error::AlreadyExists`] if it was
+    /// already filled.
+    pub async fn commit(
+        &self,
+        reservation: &Reservation,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<ReceiptStatus> {
+        self.store(
+            &reservation.arid,
+            envelope,
+            ttl_seconds,
+            Some(&reservation.secret),
+            verbose,
+        )
+        .await
+    }
+
+    /// Send a `/put`, or a `/commit` of the reservation with secret
+    /// `reservation` if given.
+    async fn store(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        reservation: Option<&str>,
+        verbose: bool,
+    ) -> Result<ReceiptStatus> {
+        use crate::logging::verbose_println;
+
+        bc_components::register_tags();
+
+        if verbose {
+            verbose_println("Starting server put operation");
+        }
+
+        let body = PutBody {
+            arid: *arid,
+            envelope: envelope.clone(),
+            ttl: ttl_seconds,
+        }
+        .encode();
+        let size = body.len();
+
+        if verbose {
+            verbose_println("Sending PUT request to server");
+        }
+
+        let mut request = match reservation {
+            Some(secret) => {
+                self.post("commit").header(RESERVATION_HEADER, secret)
+            }
+            None => self.post("put"),
+        };
+        if let Some(tag) = &self.tag {
+            request = request.header(TAG_HEADER, tag);
+        }
+        let response = self
+            .send(request.body(body), std::slice::from_ref(arid))
+            .await?;
+
+        let result = match response.status() {
+            // Older servers answer a bare "OK"
+            reqwest::StatusCode::OK => Ok(serde_json::from_str(
+                &response.text().await.unwrap_or_default(),
+            )
+            .unwrap_or(ReceiptStatus::Unknown)),
+            reqwest::StatusCode::CONFLICT => {
+                Err(Error::AlreadyExists { arid: arid.ur_string() })
+            }
+            reqwest::StatusCode::PRECONDITION_FAILED => {
+                Err(ServerError::NotReserved { arid: arid.ur_string() }.into())
+            }
+            reqwest::StatusCode::PAYLOAD_TOO_LARGE => {
+                Err(ServerError::PayloadTooLarge { size }.into())
+            }
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                let error_msg = response.text().await.unwrap_or_default();
+                Err(ServerError::Unauthorized(error_msg).into())
+            }
+            _ => {
+                let error_msg = response.text().await.unwrap_or_default();
+                Err(ServerError::General(error_msg).into())
+            }
+        };
+
+        if verbose {
+            if result.is_ok() {
+                verbose_println("Server put operation completed");
+            } else {
+                verbose_println("Server put operation failed");
+            }
+        }
+
+        result
+    }
+
+    /// Fetch whichever of `arids` the server holds, without waiting, in one
+    /// `/get_many` request per [`MAX_GET_MANY_ARIDS`] ARIDs.
+    async fn fetch_many(
+        &self,
+        arids: Vec<ARID>,
+    ) -> Result<HashMap<ARID, Envelope>> {
+        bc_components::register_tags();
+
+        let mut found = HashMap::new();
+        for chunk in arids.chunks(MAX_GET_MANY_ARIDS) {
+            let body = GetManyBody { arids: chunk.to_vec() }.encode();
+            let request = self.post("get_many").body(body);
+            let response = self.send(request, chunk).await?;
+
+            let status = response.status();
+            let text = response
+                .text()
+                .await
+                .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+            match status {
+                reqwest::StatusCode::OK => {}
+                reqwest::StatusCode::UNAUTHORIZED
+                | reqwest::StatusCode::FORBIDDEN => {
+                    return Err(ServerError::Unauthorized(text).into());
+                }
+                _ => return Err(ServerError::General(text).into()),
+            }
+
+            found.extend(GetManyResponse::decode(&text)?.found);
+        }
+        Ok(found)
+    }
+
+    /// Start or stop draining the server for maintenance.
+    ///
+    /// A draining server refuses new puts and keeps serving gets. Requires
+    /// the `admin` right, by capability or client certificate.
+    pub async fn set_draining(&self, draining: bool) -> Result<()> {
+        let request = self
+            .post("admin/drain")
+            .body(if draining { "on" } else { "off" });
+        let response = self.send(request, &[]).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                let error_msg = response.text().await.unwrap_or_default();
+                Err(ServerError::Unauthorized(error_msg).into())
+            }
+            _ => {
+                let error_msg = response.text().await.unwrap_or_default();
+                Err(ServerError::General(error_msg).into())
+            }
+        }
+    }
+}
+
+/// When a get given `timeout_seconds` (default: 30) gives up.
+fn deadline(timeout_seconds: Option<u64>) -> Instant {
+    Instant::now() + Duration::from_secs(timeout_seconds.unwrap_or(30))
+}
+
+/// The receipt [`KvStore::put`] returns for a put of `envelope` at `arid`
+/// that the server at `url` answered with `status`.
+fn put_receipt(
+    url: &str,
+    arid: &ARID,
+    envelope: &Envelope,
+    status: ReceiptStatus,
+) -> Receipt {
+    let size = stored_size(envelope, Codec::Dcbor);
+    let receipt = Receipt::new(Backend::Server, *arid, url, size);
+    match status {
+        ReceiptStatus::Stored { stored_at, expires_at } => {
+            receipt.with_ttl(expires_at.saturating_sub(stored_at), expires_at)
+        }
+        _ => receipt,
+    }
+}
+
+/// Split `entries` into `/put_many` bodies within the server's limits on
+/// entries and body length. An entry too large to share a body goes alone.
+fn put_many_bodies(
+    entries: &[(ARID, Envelope)],
+    ttl: Option<u64>,
+) -> Vec<PutManyBody> {
+    let mut bodies = Vec::new();
+    let mut current = PutManyBody { entries: Vec::new(), ttl };
+    let mut length = 0;
+    for (arid, envelope) in entries {
+        // The ARID, a space, the envelope, and a newline
+        let entry_length =
+            arid.ur_string().len() + envelope.ur_string().len() + 2;
+        if !current.entries.is_empty()
+            && (current.entries.len() == MAX_PUT_MANY_ENTRIES
+                || length + entry_length > MAX_PUT_MANY_BODY_LENGTH)
+        {
+            bodies.push(std::mem::replace(
+                &mut current,
+                PutManyBody { entries: Vec::new(), ttl },
+            ));
+            length = 0;
+        }
+        current.entries.push((*arid, envelope.clone()));
+        length += entry_length;
+    }
+    if !current.entries.is_empty() {
+        bodies.push(current);
+    }
+    bodies
+}
+
+#[async_trait::async_trait(?Send)]
+impl KvStore for ServerKvClient {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        let status = self.put_detailed(arid, envelope, ttl_seconds, verbose);
+        Ok(put_receipt(self.base_url(), arid, envelope, status.await?))
+    }
+
+    async fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        // Refuse an expired entry as put does; the server takes the others'
+        // TTLs from their expiry itself
+        expiry::ttls_for(entries, ttl_seconds)?;
+        let statuses =
+            self.put_many_detailed(entries, ttl_seconds, verbose).await?;
+        Ok(entries
+            .iter()
+            .zip(statuses)
+            .map(|((arid, envelope), status)| {
+                put_receipt(self.base_url(), arid, envelope, status)
+            })
+            .collect())
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.get_until(arid, deadline(timeout_seconds), verbose)
+            .await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        match self.poll_get(arid, None, deadline, verbose).await? {
+            GetOutcome::Found(envelope) => Ok(Some(envelope)),
+            GetOutcome::Expired { expired_at, digest: Some(digest) } => {
+                Err(ServerError::Gone(Tombstone {
+                    arid: *arid,
+                    expired_at,
+                    digest,
+                })
+                .into())
+            }
+            _ => Ok(None),
+        }
+    }
+
     async fn exists(&self, arid: &ARID) -> Result<bool> {
-        // Use a short timeout for exists check (1 second), no verbose
-        Ok(self.get(arid, Some(1), false).await?.is_some())
+        // A single request, without polling
+        match self.get(arid, Some(0), false).await {
+            Ok(envelope) => Ok(envelope.is_some()),
+            Err(Error::Server(ServerError::Gone(_))) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        use crate::logging::verbose_println;
+
+        bc_components::register_tags();
+
+        if verbose {
+            verbose_println("Sending DELETE request to server");
+        }
+
+        let request = self.post("delete").body(arid.ur_string());
+        let response =
+            self.send(request, std::slice::from_ref(arid)).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                let error_msg = response.text().await.unwrap_or_default();
+                Err(ServerError::Unauthorized(error_msg).into())
+            }
+            _ => {
+                let error_msg = response.text().await.unwrap_or_default();
+                Err(ServerError::General(error_msg).into())
+            }
+        }
+    }
+
+    /// Sends the server's `/update` endpoint, with this client's tag if set.
+    /// Servers that predate updates, or are not configured as updatable,
+    /// fail with [`Error::Unsupported`].
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        use crate::logging::verbose_println;
+
+        self.mutability.check_update()?;
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        bc_components::register_tags();
+
+        if verbose {
+            verbose_println("Sending UPDATE request to server");
+        }
+
+        let body = PutBody {
+            arid: *arid,
+            envelope: envelope.clone(),
+            ttl: ttl_seconds,
+        }
+        .encode();
+        let size = body.len();
+        let mut request = self.post("update");
+        if let Some(tag) = &self.tag {
+            request = request.header(TAG_HEADER, tag);
+        }
+        let response = self
+            .send(request.body(body), std::slice::from_ref(arid))
+            .await?;
+
+        let status = response.status();
+        let version = response
+            .headers()
+            .get(VERSION_HEADER)
+            .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        match status {
+            reqwest::StatusCode::OK => {
+                let receipt: ReceiptStatus = serde_json::from_str(&text)
+                    .map_err(|e| ServerError::ParseError(e.to_string()))?;
+                let receipt =
+                    put_receipt(self.base_url(), arid, envelope, receipt);
+                Ok(match version {
+                    Some(version) => receipt.with_sequence(version),
+                    None => receipt,
+                })
+            }
+            // Servers older than the endpoint answer 404, and servers that
+            // are not updatable 501
+            reqwest::StatusCode::NOT_FOUND
+            | reqwest::StatusCode::NOT_IMPLEMENTED => {
+                Err(Error::Unsupported { operation: "update" })
+            }
+            reqwest::StatusCode::CONFLICT => {
+                Err(Error::AlreadyExists { arid: arid.ur_string() })
+            }
+            reqwest::StatusCode::PAYLOAD_TOO_LARGE => {
+                Err(ServerError::PayloadTooLarge { size }.into())
+            }
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                Err(ServerError::Unauthorized(text).into())
+            }
+            _ => Err(ServerError::General(text).into()),
+        }
+    }
+
+    /// Asks the server's `/stat` endpoint. The TTL remaining is reckoned
+    /// from the expiry the server reports by this machine's clock.
+    async fn stat(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        use crate::logging::verbose_println;
+
+        bc_components::register_tags();
+
+        if verbose {
+            verbose_println("Sending STAT request to server");
+        }
+
+        let request = self.post("stat").body(arid.ur_string());
+        let response =
+            self.send(request, std::slice::from_ref(arid)).await?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        match status {
+            reqwest::StatusCode::OK => {
+                let entry: ListedEntry = serde_json::from_str(&text)
+                    .map_err(|e| ServerError::ParseError(e.to_string()))?;
+                Ok(Some(EntryMeta::from_listed(
+                    Backend::Server,
+                    self.base_url(),
+                    entry,
+                    unix_now(),
+                )))
+            }
+            // Servers older than the endpoint answer with an empty 404
+            reqwest::StatusCode::NOT_FOUND if text.is_empty() => {
+                Err(Error::Unsupported { operation: "stat" })
+            }
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            reqwest::StatusCode::UNAUTHORIZED
+            | reqwest::StatusCode::FORBIDDEN => {
+                Err(ServerError::Unauthorized(text).into())
+            }
+            _ => Err(ServerError::General(text).into()),
+        }
+    }
+
+    fn get_all<'a>(
+        &'a self,
+        arids: &'a [ARID],
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>> {
+        poll_all(arids, timeout_seconds, verbose, move |pending| {
+            self.fetch_many(pending)
+        })
+    }
+
+    fn watch<'a>(
+        &'a self,
+        arid: &'a ARID,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<Envelope>> {
+        // One `/watch` stream, on which the server pushes each version
+        stream::once(self.open_watch(arid, verbose))
+            .flat_map(move |opened| match opened {
+                Ok(Some(response)) => self.watch_events(response),
+                // Servers older than the endpoint are long-polled for the
+                // first envelope, back to back
+                Ok(None) => poll_watch(Duration::ZERO, false, move |_| {
+                    self.get(arid, Some(MAX_GET_WAIT), verbose)
+                }),
+                Err(e) => stream::iter([Err(e)]).boxed_local(),
+            })
+            .boxed_local()
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            ttl: true,
+            delete: true,
+            update: self.mutability.is_updatable(),
+            stat: true,
+            max_value_size: Some(MAX_ENVELOPE_SIZE),
+            ..Default::default()
+        }
+    }
+
+    async fn health(&self) -> HealthReport {
+        HealthReport::probe("server", async {
+            let response = self
+                .client
+                .get(format!("{}/health", self.base_url))
+                .timeout(HEALTH_TIMEOUT)
+                .send()
+                .await
+                .map_err(ServerError::from)?;
+            if !response.status().is_success() {
+                return Err(ServerError::General(format!(
+                    "health check returned {}",
+                    response.status()
+                ))
+                .into());
+            }
+            let text = response.text().await.map_err(ServerError::from)?;
+            let health: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|_| {
+                    ServerError::ParseError("invalid health response".into())
+                })?;
+            if health.get("server").and_then(|v| v.as_str()) != Some("hubert") {
+                return Err(
+                    ServerError::General("not a Hubert server".into()).into()
+                );
+            }
+            Ok(health
+                .get("version")
+                .and_then(|v| v.as_str())
+                .map(str::to_string))
+        })
+        .await
+        .with_max_value_size(MAX_ENVELOPE_SIZE)
     }
 }
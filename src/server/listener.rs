@@ -0,0 +1,187 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    extract::connect_info::Connected,
+    serve::{IncomingStream, Listener},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{Instant, Sleep, sleep},
+};
+
+/// The remote end of a connection, as seen by request handlers.
+#[derive(Debug, Clone)]
+pub(super) struct Peer {
+    pub(super) addr: SocketAddr,
+    /// Common name of the client's certificate, on TLS connections.
+    pub(super) identity: Option<String>,
+    /// The client's address, when `addr` is a trusted proxy forwarding the
+    /// request.
+    pub(super) forwarded_for: Option<IpAddr>,
+}
+
+impl Peer {
+    pub(super) fn new(addr: SocketAddr, identity: Option<String>) -> Self {
+        Self { addr, identity, forwarded_for: None }
+    }
+
+    /// The client's IP address, looking past a trusted proxy.
+    pub(super) fn ip(&self) -> IpAddr {
+        self.forwarded_for.unwrap_or(self.addr.ip())
+    }
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.forwarded_for {
+            Some(client) => write!(f, "{} via {}", client, self.addr),
+            None => write!(f, "{}", self.addr),
+        }
+    }
+}
+
+impl Connected<IncomingStream<'_, LimitedListener>> for Peer {
+    fn connect_info(stream: IncomingStream<'_, LimitedListener>) -> Self {
+        Peer::new(*stream.remote_addr(), None)
+    }
+}
+
+/// A TCP listener that caps concurrent connections and closes idle ones.
+///
+/// Each accepted connection holds a semaphore permit for its lifetime, so once
+/// `max_connections` are open, further clients wait in the kernel backlog
+/// rather than consuming server resources. Connections that neither send nor
+/// receive a byte for `idle_timeout` are closed, which bounds both idle
+/// keep-alive connections and slowloris-style clients that trickle headers.
+pub(super) struct LimitedListener {
+    inner: TcpListener,
+    permits: Arc<Semaphore>,
+    idle_timeout: Duration,
+}
+
+impl LimitedListener {
+    pub(super) fn new(
+        inner: TcpListener,
+        max_connections: usize,
+        idle_timeout: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            permits: Arc::new(Semaphore::new(max_connections)),
+            idle_timeout,
+        }
+    }
+}
+
+impl Listener for LimitedListener {
+    type Io = LimitedStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .expect("connection semaphore is never closed");
+        let (stream, addr) = Listener::accept(&mut self.inner).await;
+        (LimitedStream::new(stream, self.idle_timeout, permit), addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> { self.inner.local_addr() }
+}
+
+/// A connection accepted by [`LimitedListener`].
+pub(super) struct LimitedStream {
+    inner: TcpStream,
+    idle_timeout: Duration,
+    idle_deadline: Pin<Box<Sleep>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl LimitedStream {
+    fn new(
+        inner: TcpStream,
+        idle_timeout: Duration,
+        permit: OwnedSemaphorePermit,
+    ) -> Self {
+        Self {
+            inner,
+            idle_timeout,
+            idle_deadline: Box::pin(sleep(idle_timeout)),
+            _permit: permit,
+        }
+    }
+
+    fn touch(&mut self) {
+        let deadline = Instant::now() + self.idle_timeout;
+        self.idle_deadline.as_mut().reset(deadline);
+    }
+
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        match self.idle_deadline.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "connection idle timeout",
+            )),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl AsyncRead for LimitedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                this.touch();
+                Poll::Ready(result)
+            }
+            Poll::Pending => this.poll_idle(cx).map(Err),
+        }
+    }
+}
+
+impl AsyncWrite for LimitedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_write(cx, buf) {
+            Poll::Ready(result) => {
+                this.touch();
+                Poll::Ready(result)
+            }
+            Poll::Pending => this.poll_idle(cx).map(Err),
+        }
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
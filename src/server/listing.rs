@@ -0,0 +1,113 @@
+use bc_components::ARID;
+use bc_ur::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+/// Header carrying the tag a put is labeled with.
+pub const TAG_HEADER: &str = "Hubert-Tag";
+
+/// Longest tag a put may carry, in bytes.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Most entries one `/admin/list` request may return.
+#[cfg(feature = "server")]
+pub(super) const MAX_LIST_LIMIT: usize = 1000;
+
+/// An entry held by a server-side store, as reported by
+/// [`MemoryKv::list`](super::MemoryKv::list),
+/// [`SqliteKv::list`](super::SqliteKv::list), and the server's `/admin/list`
+/// and `/stat` endpoints.
+///
+/// Times are UNIX seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct ListedEntry {
+    /// Where the entry is stored.
+    #[serde(with = "ur_string")]
+    #[cfg_attr(
+        feature = "server",
+        schema(value_type = String, example = "ur:arid/...")
+    )]
+    pub arid: ARID,
+    /// When the entry was stored, if the store recorded it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored_at: Option<u64>,
+    /// When the entry expires, or `None` if it never does.
+    pub expires_at: Option<u64>,
+    /// Whether the entry has expired but not yet been pruned.
+    pub expired: bool,
+    /// Size of the stored envelope in bytes, serialized as CBOR.
+    pub size: usize,
+    /// The tag the entry was put with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+impl ListedEntry {
+    pub(super) fn new(
+        arid: ARID,
+        expires_at: Option<u64>,
+        size: usize,
+        tag: Option<String>,
+        now: u64,
+    ) -> Self {
+        let expired = expires_at.is_some_and(|expiry| now >= expiry);
+        Self { arid, stored_at: None, expires_at, expired, size, tag }
+    }
+
+    pub(super) fn with_stored_at(mut self, stored_at: Option<u64>) -> Self {
+        self.stored_at = stored_at;
+        self
+    }
+}
+
+/// Serde support for values such as ARIDs and digests as UR strings.
+pub(super) mod ur_string {
+    use super::*;
+
+    pub fn serialize<T: UREncodable, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.ur_string())
+    }
+
+    pub fn deserialize<'de, T: URDecodable, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let ur = String::deserialize(deserializer)?;
+        T::from_ur_string(ur.trim()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_json_roundtrip() {
+        bc_components::register_tags();
+
+        let entry = ListedEntry::new(
+            ARID::new(),
+            Some(160),
+            42,
+            Some("test-run".to_string()),
+            200,
+        )
+        .with_stored_at(Some(100));
+        assert!(entry.expired);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(&format!("\"{}\"", entry.arid.ur_string())));
+        assert!(json.contains(r#""tag":"test-run""#));
+        assert!(json.contains(r#""stored_at":100"#));
+        assert_eq!(serde_json::from_str::<ListedEntry>(&json).unwrap(), entry);
+
+        let untagged = ListedEntry::new(ARID::new(), None, 1, None, 200);
+        assert!(!untagged.expired);
+        let json = serde_json::to_string(&untagged).unwrap();
+        assert!(!json.contains("tag"));
+        assert!(!json.contains("stored_at"));
+    }
+}
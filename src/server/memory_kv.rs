@@ -4,37 +4,195 @@ use std::{
     time::{Duration, Instant},
 };
 
-use bc_components::ARID;
+use bc_components::{ARID, DigestProvider};
 use bc_envelope::Envelope;
 use bc_ur::prelude::*;
-use tokio::time::sleep;
+use futures_util::stream::LocalBoxStream;
 
-use crate::{Error, KvStore, Result};
+use super::{
+    Error as ServerError, ListedEntry, Reservation, Tombstone, Waiters,
+    clock::unix_now, reservation::Reservations,
+};
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Mutability, Receipt, Result,
+    StoreCapabilities, expiry,
+    kv_store::{check_distinct, poll_all, poll_watch},
+    receipt::Backend,
+};
+
+/// Entries in one shard, behind their own lock.
+type Shard = RwLock<HashMap<ARID, StorageEntry>>;
 
 /// In-memory key-value store for Gordian Envelopes.
 ///
 /// Provides volatile storage with TTL support and automatic cleanup of
 /// expired entries.
+///
+/// Entries are spread over [`DEFAULT_SHARDS`](Self::DEFAULT_SHARDS)
+/// independently locked shards by ARID, so puts to different ARIDs rarely wait
+/// on each other or on the long-polling gets a busy server serves.
 #[derive(Clone)]
 pub struct MemoryKv {
-    storage: Arc<RwLock<HashMap<ARID, StorageEntry>>>,
+    storage: Arc<[Shard]>,
+    tombstones: Arc<RwLock<HashMap<ARID, Tombstone>>>,
+    /// Seconds to keep tombstones, or `None` if they are not kept.
+    tombstone_retention: Option<u64>,
+    /// Gets waiting for a put, woken as soon as it is stored.
+    waiters: Waiters,
+    /// Whether a put of the envelope already stored succeeds.
+    idempotent_puts: bool,
+    /// ARIDs claimed before their envelope exists.
+    reservations: Reservations,
+    /// Whether updates may replace entries.
+    mutability: Mutability,
 }
 
 #[derive(Clone)]
 struct StorageEntry {
     envelope_cbor: Vec<u8>,
     expires_at: Option<Instant>,
+    tag: Option<String>,
+    /// When the entry was stored, in UNIX seconds.
+    stored_at: u64,
+    /// 1 when put, and one more with each update.
+    version: u64,
+}
+
+impl StorageEntry {
+    /// The entry at `arid` as [`MemoryKv::list`] reports it.
+    fn listed(&self, arid: &ARID, now: Instant, now_unix: u64) -> ListedEntry {
+        let mut listed = ListedEntry::new(
+            *arid,
+            self.expires_at.map(|at| to_unix(at, now, now_unix)),
+            self.envelope_cbor.len(),
+            self.tag.clone(),
+            now_unix,
+        )
+        .with_stored_at(Some(self.stored_at));
+        // Whole seconds are too coarse to tell
+        listed.expired = self.expires_at.is_some_and(|expiry| now >= expiry);
+        listed
+    }
 }
 
 impl MemoryKv {
+    /// Number of shards entries are spread over by default.
+    pub const DEFAULT_SHARDS: usize = 16;
+
     /// Create a new in-memory key-value store.
     pub fn new() -> Self {
-        Self { storage: Arc::new(RwLock::new(HashMap::new())) }
+        Self {
+            storage: new_shards(Self::DEFAULT_SHARDS),
+            tombstones: Arc::new(RwLock::new(HashMap::new())),
+            tombstone_retention: None,
+            waiters: Waiters::new(),
+            idempotent_puts: false,
+            reservations: Reservations::default(),
+            mutability: Mutability::WriteOnce,
+        }
+    }
+
+    /// Spread entries over `shards` locks instead of
+    /// [`DEFAULT_SHARDS`](Self::DEFAULT_SHARDS).
+    /// One shard serializes every put behind a single lock.
+    ///
+    /// This starts the store empty, so call it before storing anything.
+    pub fn with_shards(mut self, shards: usize) -> Self {
+        self.storage = new_shards(shards.max(1));
+        self
+    }
+
+    /// Wake gets waiting in `waiters` on puts to this store, and wake this
+    /// store's waiting gets on puts through others sharing it.
+    pub fn with_waiters(mut self, waiters: Waiters) -> Self {
+        self.waiters = waiters;
+        self
+    }
+
+    /// The registry of gets waiting on this store.
+    pub fn waiters(&self) -> &Waiters { &self.waiters }
+
+    /// Let a put of the same envelope as the live entry at its ARID succeed,
+    /// comparing envelope digests, so a client retrying a put whose response
+    /// it never saw does not get [`Error::AlreadyExists`]. A different
+    /// envelope is still refused, and an expired entry never matches.
+    pub fn with_idempotent_puts(mut self, enabled: bool) -> Self {
+        self.idempotent_puts = enabled;
+        self
+    }
+
+    /// Let [`KvStore::update`] replace entries if `mutability` is
+    /// [`Mutability::Updatable`], keeping a version number for each that
+    /// counts its updates. Puts stay write-once.
+    pub fn with_mutability(mut self, mutability: Mutability) -> Self {
+        self.mutability = mutability;
+        self
+    }
+
+    /// The shard holding `arid`.
+    fn shard(&self, arid: &ARID) -> &Shard {
+        &self.storage[self.shard_index(arid)]
+    }
+
+    fn shard_index(&self, arid: &ARID) -> usize {
+        // ARIDs are uniformly random, so their leading bytes spread evenly
+        let prefix: [u8; 8] = arid.data()[..8].try_into().unwrap();
+        (u64::from_le_bytes(prefix) % self.storage.len() as u64) as usize
+    }
+
+    /// Leave a [`Tombstone`] for each entry removed after expiring, kept for
+    /// `retention_seconds`. While it is kept, the ARID cannot be written
+    /// again.
+    pub fn with_tombstones(mut self, retention_seconds: u64) -> Self {
+        self.tombstone_retention = Some(retention_seconds);
+        self
+    }
+
+    /// Remove the expired entry at `arid`, leaving a tombstone if enabled.
+    fn bury(&self, storage: &mut HashMap<ARID, StorageEntry>, arid: &ARID) {
+        let Some(entry) = storage.remove(arid) else {
+            return;
+        };
+        let Some(retention) = self.tombstone_retention else {
+            return;
+        };
+        let now = unix_now();
+        let mut tombstones = self.tombstones.write().unwrap();
+        tombstones.retain(|_, tombstone| !tombstone.is_stale(retention, now));
+        if let (Some(expires_at), Ok(envelope)) = (
+            entry.expires_at,
+            Envelope::try_from_cbor_data(entry.envelope_cbor),
+        ) {
+            let expired_at = to_unix(expires_at, Instant::now(), now);
+            tombstones
+                .insert(*arid, Tombstone::new(*arid, expired_at, &envelope));
+        }
+    }
+
+    /// The tombstone left at `arid` when its entry expired, if tombstones are
+    /// enabled and it has not outlived the retention window.
+    pub fn tombstone(&self, arid: &ARID) -> Option<Tombstone> {
+        let retention = self.tombstone_retention?;
+        // An entry that expired but was never looked at again is buried now
+        self.check_exists(arid).ok()?;
+        let tombstone = self.tombstones.read().unwrap().get(arid).cloned()?;
+        (!tombstone.is_stale(retention, unix_now())).then_some(tombstone)
+    }
+
+    fn is_tombstoned(&self, arid: &ARID) -> bool {
+        self.tombstone_retention.is_some_and(|retention| {
+            self.tombstones
+                .read()
+                .unwrap()
+                .get(arid)
+                .is_some_and(|t| !t.is_stale(retention, unix_now()))
+        })
     }
 
     /// Check if an ARID exists and is not expired.
     fn check_exists(&self, arid: &ARID) -> Result<bool> {
-        let storage = self.storage.read().unwrap();
+        let shard = self.shard(arid);
+        let storage = shard.read().unwrap();
 
         if let Some(entry) = storage.get(arid) {
             if let Some(expires_at) = entry.expires_at
@@ -42,8 +200,8 @@ impl MemoryKv {
             {
                 drop(storage);
                 // Entry is expired, remove it
-                let mut storage = self.storage.write().unwrap();
-                storage.remove(arid);
+                let mut storage = shard.write().unwrap();
+                self.bury(&mut storage, arid);
                 return Ok(false);
             }
             Ok(true)
@@ -51,27 +209,135 @@ impl MemoryKv {
             Ok(false)
         }
     }
-}
 
-impl Default for MemoryKv {
-    fn default() -> Self { Self::new() }
-}
+    /// Remove the unexpired entry at `arid` as if it had just expired,
+    /// leaving a tombstone if enabled. Returns whether there was one.
+    pub fn remove(&self, arid: &ARID) -> bool {
+        let mut storage = self.shard(arid).write().unwrap();
+        let now = Instant::now();
+        let Some(entry) = storage.get_mut(arid) else {
+            return false;
+        };
+        if entry.expires_at.is_some_and(|expiry| now >= expiry) {
+            self.bury(&mut storage, arid);
+            return false;
+        }
+        entry.expires_at = Some(now);
+        self.bury(&mut storage, arid);
+        true
+    }
 
-#[async_trait::async_trait(?Send)]
-impl KvStore for MemoryKv {
-    async fn put(
+    /// The unexpired entry at `arid`, as [`list`](Self::list) would report
+    /// it.
+    pub fn entry(&self, arid: &ARID) -> Option<ListedEntry> {
+        let storage = self.shard(arid).read().unwrap();
+        let entry = storage.get(arid)?;
+        let listed = entry.listed(arid, Instant::now(), unix_now());
+        (!listed.expired).then_some(listed)
+    }
+
+    /// Store an envelope like [`KvStore::put`], labeled with an opaque `tag`
+    /// that [`list`](Self::list), [`count`](Self::count), and
+    /// [`purge`](Self::purge) can select by.
+    pub fn put_tagged(
         &self,
         arid: &ARID,
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
+        tag: Option<&str>,
         verbose: bool,
-    ) -> Result<String> {
+    ) -> Result<Receipt> {
+        self.store(arid, envelope, ttl_seconds, tag, None, verbose)
+    }
+
+    /// Reserve `arid` for `ttl_seconds`, so that only the holder of the
+    /// returned reservation's secret can store an envelope there, with
+    /// [`commit`](Self::commit). Until then puts to the ARID fail with
+    /// [`Error::AlreadyExists`], as does reserving an ARID that is stored,
+    /// tombstoned, or already reserved.
+    pub fn reserve(
+        &self,
+        arid: &ARID,
+        ttl_seconds: u64,
+        verbose: bool,
+    ) -> Result<Reservation> {
         use crate::logging::verbose_println;
 
-        let mut storage = self.storage.write().unwrap();
+        let storage = self.shard(arid).write().unwrap();
+        let now = unix_now();
+        let reservation =
+            if storage.contains_key(arid) || self.is_tombstoned(arid) {
+                None
+            } else {
+                self.reservations.reserve(
+                    arid,
+                    now.saturating_add(ttl_seconds),
+                    now,
+                )
+            };
+        drop(storage);
 
-        // Check if already exists
-        if storage.contains_key(arid) {
+        if verbose {
+            let status = if reservation.is_some() {
+                "OK"
+            } else {
+                "ALREADY_EXISTS"
+            };
+            verbose_println(&format!(
+                "RESERVE {} (TTL {}s) {}",
+                arid.ur_string(),
+                ttl_seconds,
+                status
+            ));
+        }
+        reservation
+            .ok_or_else(|| Error::AlreadyExists { arid: arid.ur_string() })
+    }
+
+    /// Store an envelope at an ARID reserved with [`reserve`](Self::reserve),
+    /// presenting the reservation's secret. Fails with
+    /// [`server::Error::NotReserved`](super::Error::NotReserved) if no live
+    /// reservation of the ARID has that secret.
+    pub fn commit(
+        &self,
+        arid: &ARID,
+        secret: &str,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.store(arid, envelope, ttl_seconds, tag, Some(secret), verbose)
+    }
+
+    /// Store an envelope, filling the reservation with `secret` if given.
+    fn store(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        secret: Option<&str>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        use crate::logging::verbose_println;
+
+        let mut storage = self.shard(arid).write().unwrap();
+        let now = unix_now();
+
+        // A retry of the put that stored the entry changes nothing
+        if self.is_repeat(&storage, arid, envelope) {
+            if verbose {
+                verbose_println(&format!(
+                    "PUT {} IDENTICAL (Memory)",
+                    arid.ur_string()
+                ));
+            }
+            return Ok(receipt(arid, envelope, None, now).already_stored());
+        }
+
+        // Check if already exists, did until it expired, or is reserved
+        if self.is_taken(&storage, arid, secret.is_some(), now) {
             if verbose {
                 verbose_println(&format!(
                     "PUT {} ALREADY_EXISTS",
@@ -80,12 +346,36 @@ impl KvStore for MemoryKv {
             }
             return Err(Error::AlreadyExists { arid: arid.ur_string() });
         }
+        if let Some(secret) = secret
+            && !self.reservations.take(arid, secret, now)
+        {
+            if verbose {
+                verbose_println(&format!(
+                    "COMMIT {} NOT_RESERVED",
+                    arid.ur_string()
+                ));
+            }
+            return Err(
+                ServerError::NotReserved { arid: arid.ur_string() }.into()
+            );
+        }
 
         let expires_at =
             ttl_seconds.map(|ttl| Instant::now() + Duration::from_secs(ttl));
         let envelope_cbor = envelope.to_cbor_data();
 
-        storage.insert(*arid, StorageEntry { envelope_cbor, expires_at });
+        storage.insert(
+            *arid,
+            StorageEntry {
+                envelope_cbor,
+                expires_at,
+                tag: tag.map(str::to_string),
+                stored_at: now,
+                version: 1,
+            },
+        );
+        drop(storage);
+        self.waiters.wake(arid);
 
         if verbose {
             let ttl_msg = ttl_seconds
@@ -98,7 +388,320 @@ impl KvStore for MemoryKv {
             ));
         }
 
-        Ok("Stored in memory".to_string())
+        Ok(receipt(arid, envelope, ttl_seconds, now))
+    }
+
+    /// Replace the live entry at `arid` like [`KvStore::update`], or store
+    /// `envelope` labeled with `tag` as a put would if there is none. The
+    /// entry keeps its tag when replaced.
+    pub fn update_tagged(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        use crate::logging::verbose_println;
+
+        self.mutability.check_update()?;
+        // An expired entry is buried first, leaving its tombstone
+        self.check_exists(arid)?;
+
+        let mut storage = self.shard(arid).write().unwrap();
+        let Some(entry) = storage.get_mut(arid) else {
+            drop(storage);
+            let receipt =
+                self.store(arid, envelope, ttl_seconds, tag, None, verbose)?;
+            return Ok(receipt.with_sequence(1));
+        };
+        let now = unix_now();
+        entry.envelope_cbor = envelope.to_cbor_data();
+        entry.expires_at =
+            ttl_seconds.map(|ttl| Instant::now() + Duration::from_secs(ttl));
+        entry.stored_at = now;
+        entry.version += 1;
+        let version = entry.version;
+        drop(storage);
+        self.waiters.wake(arid);
+
+        if verbose {
+            verbose_println(&format!(
+                "UPDATE {} to version {} OK (Memory)",
+                arid.ur_string(),
+                version
+            ));
+        }
+
+        Ok(receipt(arid, envelope, ttl_seconds, now).with_sequence(version))
+    }
+
+    /// Whether a put of `envelope` at `arid` repeats the live one stored
+    /// there, and so succeeds without change under idempotent puts.
+    fn is_repeat(
+        &self,
+        storage: &HashMap<ARID, StorageEntry>,
+        arid: &ARID,
+        envelope: &Envelope,
+    ) -> bool {
+        self.idempotent_puts
+            && storage.get(arid).is_some_and(|entry| {
+                entry
+                    .expires_at
+                    .is_none_or(|expiry| Instant::now() < expiry)
+                    && Envelope::try_from_cbor_data(
+                        entry.envelope_cbor.clone(),
+                    )
+                    .is_ok_and(|stored| stored.digest() == envelope.digest())
+            })
+    }
+
+    /// Whether `arid` is stored, tombstoned, or, unless the put fills a
+    /// reservation, reserved.
+    fn is_taken(
+        &self,
+        storage: &HashMap<ARID, StorageEntry>,
+        arid: &ARID,
+        filling_reservation: bool,
+        now: u64,
+    ) -> bool {
+        storage.contains_key(arid)
+            || self.is_tombstoned(arid)
+            || (!filling_reservation
+                && self.reservations.is_reserved(arid, now))
+    }
+
+    /// Store several envelopes like [`KvStore::put_many`], all labeled with
+    /// `tag`, each with the TTL at the same index of `ttls`. Every shard
+    /// involved is locked at once, so either all entries are stored or, if
+    /// any ARID is taken, none are.
+    pub fn put_many_tagged(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttls: &[Option<u64>],
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        use crate::logging::verbose_println;
+
+        check_distinct(entries)?;
+
+        // Lock in index order, so concurrent batches cannot deadlock
+        let mut indices: Vec<usize> =
+            entries.iter().map(|(arid, _)| self.shard_index(arid)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        let mut shards: HashMap<usize, _> = indices
+            .into_iter()
+            .map(|index| (index, self.storage[index].write().unwrap()))
+            .collect();
+
+        let now = unix_now();
+        let mut receipts = Vec::with_capacity(entries.len());
+        let mut fresh = Vec::with_capacity(entries.len());
+        for ((arid, envelope), &ttl_seconds) in entries.iter().zip(ttls) {
+            let storage = &shards[&self.shard_index(arid)];
+            if self.is_repeat(storage, arid, envelope) {
+                let repeat = receipt(arid, envelope, None, now);
+                receipts.push(repeat.already_stored());
+            } else if self.is_taken(storage, arid, false, now) {
+                if verbose {
+                    verbose_println(&format!(
+                        "PUT_MANY {} ALREADY_EXISTS",
+                        arid.ur_string()
+                    ));
+                }
+                return Err(Error::AlreadyExists { arid: arid.ur_string() });
+            } else {
+                receipts.push(receipt(arid, envelope, ttl_seconds, now));
+                fresh.push((arid, envelope, ttl_seconds));
+            }
+        }
+
+        for (arid, envelope, ttl_seconds) in &fresh {
+            let expires_at = ttl_seconds
+                .map(|ttl| Instant::now() + Duration::from_secs(ttl));
+            let storage = shards.get_mut(&self.shard_index(arid)).unwrap();
+            storage.insert(
+                **arid,
+                StorageEntry {
+                    envelope_cbor: envelope.to_cbor_data(),
+                    expires_at,
+                    tag: tag.map(str::to_string),
+                    stored_at: now,
+                    version: 1,
+                },
+            );
+        }
+        drop(shards);
+        for (arid, ..) in &fresh {
+            self.waiters.wake(arid);
+        }
+
+        if verbose {
+            verbose_println(&format!(
+                "PUT_MANY {} entries, {} new OK (Memory)",
+                entries.len(),
+                fresh.len()
+            ));
+        }
+        Ok(receipts)
+    }
+
+    /// List stored entries in a stable order, skipping the first `offset`
+    /// and returning at most `limit`.
+    ///
+    /// Only entries put with `tag` are listed, if given, and only those
+    /// whose ARID's UR starts with `prefix`, such as `ur:arid/hdcxlk`.
+    /// Expired entries that have not been removed yet are included only if
+    /// `include_expired` is set.
+    pub fn list(
+        &self,
+        offset: usize,
+        limit: usize,
+        include_expired: bool,
+        tag: Option<&str>,
+        prefix: Option<&str>,
+    ) -> Vec<ListedEntry> {
+        let (now, now_unix) = (Instant::now(), unix_now());
+        let mut entries: Vec<(String, ListedEntry)> = Vec::new();
+        for shard in self.storage.iter() {
+            let storage = shard.read().unwrap();
+            entries.extend(
+                storage
+                    .iter()
+                    .filter(|(_, entry)| {
+                        tag.is_none() || entry.tag.as_deref() == tag
+                    })
+                    .map(|(arid, entry)| {
+                        (arid.ur_string(), entry.listed(arid, now, now_unix))
+                    })
+                    .filter(|(ur, listed)| {
+                        (include_expired || !listed.expired)
+                            && prefix.is_none_or(|p| ur.starts_with(p))
+                    }),
+            );
+        }
+        // Same order as SQLite, which sorts by the ARID's UR
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(_, listed)| listed)
+            .collect()
+    }
+
+    /// The number of unexpired entries, only counting those put with `tag`
+    /// if given.
+    pub fn count(&self, tag: Option<&str>) -> usize {
+        let now = Instant::now();
+        self.storage
+            .iter()
+            .map(|shard| {
+                shard
+                    .read()
+                    .unwrap()
+                    .values()
+                    .filter(|entry| {
+                        tag.is_none() || entry.tag.as_deref() == tag
+                    })
+                    .filter(|entry| {
+                        entry.expires_at.is_none_or(|expiry| now < expiry)
+                    })
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Remove every entry put with `tag`, expired or not. Returns the number
+    /// removed.
+    pub fn purge(&self, tag: &str) -> usize {
+        self.storage
+            .iter()
+            .map(|shard| {
+                let mut storage = shard.write().unwrap();
+                let before = storage.len();
+                storage.retain(|_, entry| entry.tag.as_deref() != Some(tag));
+                before - storage.len()
+            })
+            .sum()
+    }
+
+    /// The unexpired envelopes stored at any of `arids`.
+    fn lookup_many(&self, arids: &[ARID]) -> HashMap<ARID, Envelope> {
+        let now = Instant::now();
+        arids
+            .iter()
+            .filter_map(|arid| {
+                let storage = self.shard(arid).read().unwrap();
+                let entry = storage.get(arid)?;
+                if entry.expires_at.is_some_and(|expiry| now >= expiry) {
+                    return None;
+                }
+                let envelope =
+                    Envelope::try_from_cbor_data(entry.envelope_cbor.clone())
+                        .ok()?;
+                Some((*arid, envelope))
+            })
+            .collect()
+    }
+}
+
+fn new_shards(count: usize) -> Arc<[Shard]> {
+    (0..count).map(|_| RwLock::new(HashMap::new())).collect()
+}
+
+/// `at` as UNIX seconds, given that `now` is `now_unix`.
+fn to_unix(at: Instant, now: Instant, now_unix: u64) -> u64 {
+    if at >= now {
+        now_unix.saturating_add((at - now).as_secs())
+    } else {
+        now_unix.saturating_sub((now - at).as_secs())
+    }
+}
+
+/// The receipt for a put of `envelope` at `arid` at `now`, lasting
+/// `ttl_seconds` if given.
+fn receipt(
+    arid: &ARID,
+    envelope: &Envelope,
+    ttl_seconds: Option<u64>,
+    now: u64,
+) -> Receipt {
+    let size = envelope.to_cbor_data().len();
+    let receipt = Receipt::new(Backend::Memory, *arid, "memory:", size);
+    match ttl_seconds {
+        Some(ttl) => receipt.with_ttl(ttl, now.saturating_add(ttl)),
+        None => receipt,
+    }
+}
+
+impl Default for MemoryKv {
+    fn default() -> Self { Self::new() }
+}
+
+#[async_trait::async_trait(?Send)]
+impl KvStore for MemoryKv {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        self.put_tagged(arid, envelope, ttl_seconds, None, verbose)
+    }
+
+    async fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        let ttls = expiry::ttls_for(entries, ttl_seconds)?;
+        self.put_many_tagged(entries, &ttls, None, verbose)
     }
 
     async fn get(
@@ -107,15 +710,25 @@ impl KvStore for MemoryKv {
         timeout_seconds: Option<u64>,
         verbose: bool,
     ) -> Result<Option<Envelope>> {
-        use crate::logging::verbose_println;
+        use crate::logging::{PollProgress, verbose_println};
 
         let timeout = timeout_seconds.unwrap_or(30);
         let start = std::time::Instant::now();
-        let mut first_attempt = true;
+        let deadline = start + Duration::from_secs(timeout);
+        let mut progress = PollProgress::new(
+            format!("GET {} (Memory)", arid.ur_string()),
+            deadline,
+            verbose,
+        );
 
         loop {
+            // Listen before looking, so a put in between is not missed
+            let mut wait = self.waiters.wait(arid);
+
             let result = {
-                let mut storage = self.storage.write().unwrap();
+                // Polls only read, so they never hold up puts to the shard
+                let shard = self.shard(arid);
+                let storage = shard.read().unwrap();
 
                 if let Some(entry) = storage.get(arid) {
                     // Check if expired
@@ -123,7 +736,8 @@ impl KvStore for MemoryKv {
                         && Instant::now() >= expires_at
                     {
                         // Entry is expired, remove it
-                        storage.remove(arid);
+                        drop(storage);
+                        self.bury(&mut shard.write().unwrap(), arid);
                         if verbose {
                             verbose_println(&format!(
                                 "GET {} EXPIRED",
@@ -163,24 +777,78 @@ impl KvStore for MemoryKv {
                 return Ok(None);
             }
 
-            if first_attempt && verbose {
-                verbose_println(&format!(
-                    "Polling for {} (timeout: {}s)",
-                    arid.ur_string(),
-                    timeout
-                ));
-                first_attempt = false;
-            } else if verbose {
-                print!(".");
-                use std::io::Write;
-                std::io::stdout().flush().ok();
-            }
-
-            sleep(Duration::from_millis(500)).await;
+            // Every put is made through this store, so there is nothing to
+            // poll for: wait for the next one
+            progress.missed();
+            wait.until(deadline).await;
         }
     }
 
     async fn exists(&self, arid: &ARID) -> Result<bool> {
         self.check_exists(arid)
     }
+
+    async fn delete(&self, arid: &ARID, _verbose: bool) -> Result<bool> {
+        Ok(self.remove(arid))
+    }
+
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        self.update_tagged(arid, envelope, ttl_seconds, None, verbose)
+    }
+
+    async fn stat(
+        &self,
+        arid: &ARID,
+        _verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        let now = unix_now();
+        Ok(self.entry(arid).map(|entry| {
+            EntryMeta::from_listed(Backend::Memory, "memory:", entry, now)
+        }))
+    }
+
+    fn get_all<'a>(
+        &'a self,
+        arids: &'a [ARID],
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>> {
+        poll_all(arids, timeout_seconds, verbose, move |pending| {
+            std::future::ready(Ok(self.lookup_many(&pending)))
+        })
+    }
+
+    fn watch<'a>(
+        &'a self,
+        arid: &'a ARID,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<Envelope>> {
+        // Each lookup waits on the registry, so a put or update ends it
+        let updates = self.mutability.is_updatable();
+        poll_watch(Duration::ZERO, updates, move |last| {
+            let lookup = move || self.get(arid, Some(0), verbose);
+            self.waiters.next_version(arid, last, None, lookup)
+        })
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            ttl: true,
+            delete: true,
+            update: self.mutability.is_updatable(),
+            stat: true,
+            ..Default::default()
+        }
+    }
+
+    async fn health(&self) -> HealthReport {
+        HealthReport::probe("memory", async { Ok(None) }).await
+    }
 }
@@ -1,15 +1,73 @@
+mod capability;
+mod clock;
+#[cfg(feature = "server")]
+mod config;
+mod conformance;
 mod error;
+#[cfg(feature = "server")]
+mod ip_filter;
+#[cfg(feature = "server-client")]
 mod kv;
+#[cfg(feature = "server")]
+mod listener;
+mod listing;
+#[cfg(feature = "server")]
+mod quota;
+mod receipts;
+mod replication;
+mod request_proof;
+mod reservation;
+#[cfg(feature = "server")]
 #[allow(clippy::module_inception)]
 mod server;
+#[cfg(feature = "server")]
 mod server_kv;
+mod sync;
+#[cfg(feature = "server")]
+mod systemd;
+#[cfg(feature = "tls")]
+mod tls;
+mod tombstone;
+mod waiters;
+#[cfg(feature = "server-client")]
+pub(crate) mod wire;
 
+pub use capability::{CAPABILITY_HEADER, Capability, Right};
+#[cfg(feature = "server")]
+pub use config::{
+    ClientCertConfig, ClientPolicy, PeerConfig, ServerClientConfig,
+    ServerConfig, TlsConfig,
+};
+pub use conformance::{
+    ConformanceReport, ConformanceScript, ConformanceStep,
+    MAX_CONFORMANCE_TOKEN_LENGTH, StepResult, StepStatus,
+    is_valid_conformance_token,
+};
 pub use error::Error;
-pub use kv::ServerKvClient;
-pub use server::{Server, ServerConfig};
+#[cfg(feature = "server")]
+pub use ip_filter::Cidr;
+#[cfg(feature = "server-client")]
+pub use kv::{GetOutcome, MAX_ENVELOPE_SIZE, ServerKvClient};
+pub use listing::{ListedEntry, TAG_HEADER};
+pub use receipts::{ReceiptStatus, VERSION_HEADER};
+pub use replication::{ReplicatedEntry, ReplicationBatch};
+pub use request_proof::{
+    ProvenRequest, REQUEST_PROOF_HEADER, sign_request_proof,
+};
+pub use reservation::{RESERVATION_HEADER, Reservation};
+#[cfg(feature = "server")]
+pub use server::Server;
+pub use sync::{AridFilter, SyncRequest};
+pub use tombstone::Tombstone;
+pub use waiters::{Wait, Waiters};
 
 mod memory_kv;
 pub use memory_kv::MemoryKv;
+#[cfg(feature = "server")]
 mod sqlite_kv;
+mod stats;
+#[cfg(feature = "server")]
 pub use server_kv::ServerKv;
+#[cfg(feature = "server")]
 pub use sqlite_kv::SqliteKv;
+pub use stats::{AccessStats, AccessSummary, StorageStats};
@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Length of a quota window in seconds.
+const QUOTA_WINDOW: u64 = 3600;
+
+/// Put counts per client identity over fixed hourly windows.
+///
+/// Only identities listed in the TLS client policies are tracked, so the map
+/// stays as small as the configuration.
+#[derive(Clone, Default)]
+pub(super) struct PutQuotas {
+    windows: Arc<Mutex<HashMap<String, (u64, u64)>>>,
+}
+
+impl PutQuotas {
+    pub(super) fn new() -> Self { Self::default() }
+
+    /// Count `count` puts by `identity` at `now`, unless they would take it
+    /// over `limit` puts in the current window, in which case none count.
+    pub(super) fn take(
+        &self,
+        identity: &str,
+        count: u64,
+        limit: u64,
+        now: u64,
+    ) -> bool {
+        let window = now / QUOTA_WINDOW;
+        let mut windows = self.windows.lock().unwrap();
+        let (start, used) =
+            windows.entry(identity.to_string()).or_insert((window, 0));
+        if *start != window {
+            *start = window;
+            *used = 0;
+        }
+        if used.saturating_add(count) > limit {
+            return false;
+        }
+        *used += count;
+        true
+    }
+
+    /// Uncount `count` puts by `identity` taken at `taken_at`, which did not
+    /// go through. Puts taken in an earlier window have already lapsed.
+    pub(super) fn refund(&self, identity: &str, count: u64, taken_at: u64) {
+        let mut windows = self.windows.lock().unwrap();
+        if let Some((start, used)) = windows.get_mut(identity)
+            && *start == taken_at / QUOTA_WINDOW
+        {
+            *used = used.saturating_sub(count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_resets_each_window() {
+        let quotas = PutQuotas::new();
+        assert!(quotas.take("alice", 1, 2, 0));
+        assert!(quotas.take("alice", 1, 2, 10));
+        assert!(!quotas.take("alice", 1, 2, 20));
+        assert!(quotas.take("bob", 1, 2, 20));
+        assert!(quotas.take("alice", 1, 2, QUOTA_WINDOW));
+    }
+
+    #[test]
+    fn test_batch_takes_all_or_nothing() {
+        let quotas = PutQuotas::new();
+        assert!(quotas.take("alice", 3, 4, 0));
+        assert!(!quotas.take("alice", 2, 4, 0));
+        assert!(quotas.take("alice", 1, 4, 0));
+        assert!(!quotas.take("alice", 1, 4, 0));
+    }
+
+    #[test]
+    fn test_refund_within_window() {
+        let quotas = PutQuotas::new();
+        assert!(quotas.take("alice", 2, 2, 0));
+        quotas.refund("alice", 1, 0);
+        assert!(quotas.take("alice", 1, 2, 10));
+        assert!(!quotas.take("alice", 1, 2, 10));
+
+        // A refund from a lapsed window leaves the current one alone
+        assert!(quotas.take("alice", 2, 2, QUOTA_WINDOW));
+        quotas.refund("alice", 2, 0);
+        assert!(!quotas.take("alice", 1, 2, QUOTA_WINDOW));
+    }
+}
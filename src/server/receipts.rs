@@ -0,0 +1,188 @@
+#[cfg(feature = "server")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+#[cfg(feature = "server")]
+use bc_components::ARID;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+/// Response header of `/update` carrying the entry's new version.
+pub const VERSION_HEADER: &str = "Hubert-Version";
+
+/// Status of a put, as reported by the server's `/receipts` endpoint.
+///
+/// Lets a putter distinguish "my counterparty never fetched the message
+/// before it expired" from "the message was never stored". Times are UNIX
+/// seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    /// The entry is stored and will expire at `expires_at`.
+    Stored { stored_at: u64, expires_at: u64 },
+    /// The entry expired at `expired_at` and has been or will shortly be
+    /// pruned.
+    Expired { stored_at: u64, expired_at: u64 },
+    /// The server has no record of a put to this ARID within its retention
+    /// window.
+    Unknown,
+}
+
+#[cfg(feature = "server")]
+#[derive(Clone, Copy)]
+struct PutRecord {
+    stored_at: u64,
+    expires_at: u64,
+}
+
+/// Log of recent puts, retained past expiry so clients can learn that their
+/// entry was pruned.
+#[cfg(feature = "server")]
+#[derive(Clone, Default)]
+pub(super) struct ReceiptLog {
+    records: Arc<RwLock<HashMap<ARID, PutRecord>>>,
+}
+
+#[cfg(feature = "server")]
+impl ReceiptLog {
+    pub(super) fn new() -> Self { Self::default() }
+
+    /// Record a successful put.
+    pub(super) fn record(&self, arid: ARID, stored_at: u64, expires_at: u64) {
+        let mut records = self.records.write().unwrap();
+        // A put that succeeds while an earlier one is live is an identical
+        // re-put, which stored nothing new
+        if records.get(&arid).is_some_and(|r| stored_at < r.expires_at) {
+            return;
+        }
+        records.insert(arid, PutRecord { stored_at, expires_at });
+    }
+
+    /// Record a successful update, which replaces any earlier record.
+    pub(super) fn replace(&self, arid: ARID, stored_at: u64, expires_at: u64) {
+        self.records
+            .write()
+            .unwrap()
+            .insert(arid, PutRecord { stored_at, expires_at });
+    }
+
+    /// Mark the put at `arid` as expired by `now`, after it was deleted.
+    pub(super) fn retract(&self, arid: &ARID, now: u64) {
+        if let Some(record) = self.records.write().unwrap().get_mut(arid) {
+            record.expires_at = record.expires_at.min(now);
+        }
+    }
+
+    /// Report the status of a put as of `now`.
+    pub(super) fn status(&self, arid: &ARID, now: u64) -> ReceiptStatus {
+        match self.records.read().unwrap().get(arid) {
+            Some(record) if now < record.expires_at => ReceiptStatus::Stored {
+                stored_at: record.stored_at,
+                expires_at: record.expires_at,
+            },
+            Some(record) => ReceiptStatus::Expired {
+                stored_at: record.stored_at,
+                expired_at: record.expires_at,
+            },
+            None => ReceiptStatus::Unknown,
+        }
+    }
+
+    /// The ARIDs whose entries had expired by `now`, among those still
+    /// recorded.
+    pub(super) fn expired(&self, now: u64) -> Vec<ARID> {
+        self.records
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, record)| now >= record.expires_at)
+            .map(|(arid, _)| *arid)
+            .collect()
+    }
+
+    /// Forget records that expired more than `retention` seconds before
+    /// `now`. Returns the number of records removed.
+    pub(super) fn prune(&self, now: u64, retention: u64) -> usize {
+        let mut records = self.records.write().unwrap();
+        let before = records.len();
+        records.retain(|_, record| {
+            record.expires_at.saturating_add(retention) > now
+        });
+        before - records.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_status_lifecycle() {
+        let log = ReceiptLog::new();
+        let arid = ARID::new();
+
+        assert_eq!(log.status(&arid, 100), ReceiptStatus::Unknown);
+
+        log.record(arid, 100, 160);
+        assert_eq!(
+            log.status(&arid, 120),
+            ReceiptStatus::Stored { stored_at: 100, expires_at: 160 }
+        );
+        assert_eq!(
+            log.status(&arid, 160),
+            ReceiptStatus::Expired { stored_at: 100, expired_at: 160 }
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_retract_expires_record() {
+        let log = ReceiptLog::new();
+        let arid = ARID::new();
+        log.record(arid, 100, 160);
+
+        log.retract(&arid, 120);
+        assert_eq!(
+            log.status(&arid, 120),
+            ReceiptStatus::Expired { stored_at: 100, expired_at: 120 }
+        );
+        // A new put may follow once the entry is gone
+        log.record(arid, 130, 190);
+        assert!(matches!(
+            log.status(&arid, 130),
+            ReceiptStatus::Stored { .. }
+        ));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_prune_respects_retention() {
+        let log = ReceiptLog::new();
+        let arid = ARID::new();
+        log.record(arid, 100, 160);
+
+        assert_eq!(log.prune(200, 60), 0);
+        assert!(matches!(
+            log.status(&arid, 200),
+            ReceiptStatus::Expired { .. }
+        ));
+
+        assert_eq!(log.prune(220, 60), 1);
+        assert_eq!(log.status(&arid, 220), ReceiptStatus::Unknown);
+    }
+
+    #[test]
+    fn test_status_json() {
+        let json = serde_json::to_string(&ReceiptStatus::Stored {
+            stored_at: 1,
+            expires_at: 2,
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"status":"stored","stored_at":1,"expires_at":2}"#);
+    }
+}
@@ -0,0 +1,182 @@
+//! Sealed envelopes for replicating entries between servers.
+//!
+//! A server forwarding puts to a peer bundles them into a batch, signs the
+//! batch with its own keys, and encrypts it to the peer's public keys:
+//!
+//! ```text
+//! ENCRYPTED [
+//!     'hasRecipient': SealedMessage
+//! ]
+//! ```
+//!
+//! where the plaintext is the signed batch:
+//!
+//! ```text
+//! {
+//!     "hubertReplication" [
+//!         "sentAt": Date
+//!         "entry": ARID [
+//!             "value": Envelope
+//!             "expiresAt": u64
+//!         ]
+//!     ]
+//! } [
+//!     'signed': Signature
+//! ]
+//! ```
+//!
+//! Proxies and other intermediaries between the peers, including ones that
+//! terminate TLS, see only ciphertext. The receiving peer learns nothing it
+//! could not already learn from a client fetching the same ARIDs.
+
+use bc_components::{ARID, PrivateKeys, PublicKeys};
+use bc_envelope::prelude::*;
+use dcbor::Date;
+
+use super::Error as ServerError;
+use crate::Result;
+
+const SUBJECT: &str = "hubertReplication";
+const SENT_AT: &str = "sentAt";
+const ENTRY: &str = "entry";
+const VALUE: &str = "value";
+const EXPIRES_AT: &str = "expiresAt";
+
+/// One stored entry being replicated to a peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicatedEntry {
+    pub arid: ARID,
+    pub envelope: Envelope,
+    /// UNIX seconds at which the entry expires on the originating server.
+    pub expires_at: u64,
+}
+
+/// A batch of entries sent from one server to a peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicationBatch {
+    pub entries: Vec<ReplicatedEntry>,
+    /// When the sender sealed the batch, so receivers can reject stale
+    /// replays.
+    pub sent_at: Date,
+}
+
+impl ReplicationBatch {
+    /// A batch of `entries`, timestamped now.
+    pub fn new(entries: Vec<ReplicatedEntry>) -> Self {
+        Self { entries, sent_at: Date::now() }
+    }
+
+    /// Sign the batch as `sender` and encrypt it to `recipient`.
+    pub fn seal(
+        &self,
+        sender: &PrivateKeys,
+        recipient: &PublicKeys,
+    ) -> Envelope {
+        let mut envelope =
+            Envelope::new(SUBJECT).add_assertion(SENT_AT, self.sent_at.clone());
+        for entry in &self.entries {
+            let entry = Envelope::new(entry.arid)
+                .add_assertion(VALUE, entry.envelope.clone())
+                .add_assertion(EXPIRES_AT, entry.expires_at);
+            envelope = envelope.add_assertion(ENTRY, entry);
+        }
+        envelope.seal(sender, recipient)
+    }
+
+    /// Decrypt a batch sealed to `recipient` and verify that `sender` signed
+    /// it.
+    pub fn unseal(
+        sealed: &Envelope,
+        sender: &PublicKeys,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let envelope = sealed.unseal(sender, recipient)?;
+        let invalid = |e: bc_envelope::Error| {
+            ServerError::ParseError(format!("replication batch: {}", e))
+        };
+
+        if envelope.extract_subject::<String>().ok().as_deref() != Some(SUBJECT)
+        {
+            return Err(ServerError::ParseError(
+                "not a replication batch".to_string(),
+            )
+            .into());
+        }
+        let sent_at = envelope
+            .extract_object_for_predicate(SENT_AT)
+            .map_err(invalid)?;
+        let entries = envelope
+            .objects_for_predicate(ENTRY)
+            .iter()
+            .map(|entry| -> Result<ReplicatedEntry> {
+                Ok(ReplicatedEntry {
+                    arid: entry.extract_subject().map_err(invalid)?,
+                    envelope: entry
+                        .object_for_predicate(VALUE)
+                        .map_err(invalid)?,
+                    expires_at: entry
+                        .extract_object_for_predicate(EXPIRES_AT)
+                        .map_err(invalid)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { entries, sent_at })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::keypair;
+
+    use super::*;
+
+    fn batch() -> ReplicationBatch {
+        ReplicationBatch::new(vec![
+            ReplicatedEntry {
+                arid: ARID::new(),
+                envelope: Envelope::new("first"),
+                expires_at: 1_800_000_000,
+            },
+            ReplicatedEntry {
+                arid: ARID::new(),
+                envelope: Envelope::new("second").wrap(),
+                expires_at: 1_800_000_060,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_seal_unseal() {
+        let (sender_private, sender_public) = keypair();
+        let (peer_private, peer_public) = keypair();
+        let batch = batch();
+
+        let sealed = batch.seal(&sender_private, &peer_public);
+        let unsealed =
+            ReplicationBatch::unseal(&sealed, &sender_public, &peer_private)
+                .unwrap();
+        assert_eq!(unsealed.sent_at, batch.sent_at);
+        let mut expected = batch.entries.clone();
+        let mut actual = unsealed.entries.clone();
+        expected.sort_by_key(|e| e.expires_at);
+        actual.sort_by_key(|e| e.expires_at);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_wrong_keys_rejected() {
+        let (sender_private, sender_public) = keypair();
+        let (peer_private, peer_public) = keypair();
+        let (other_private, other_public) = keypair();
+        let sealed = batch().seal(&sender_private, &peer_public);
+
+        assert!(
+            ReplicationBatch::unseal(&sealed, &sender_public, &other_private)
+                .is_err()
+        );
+        assert!(
+            ReplicationBatch::unseal(&sealed, &other_public, &peer_private)
+                .is_err()
+        );
+    }
+}
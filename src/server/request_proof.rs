@@ -0,0 +1,252 @@
+//! Signed request proofs, which keep captured requests from being replayed.
+//!
+//! A client presenting a capability bound to a holder key (see
+//! [`Capability::with_holder`](super::Capability::with_holder)) signs a
+//! fresh proof for every request with the holder's private keys, and sends
+//! its UR in the `Hubert-Request-Proof` header:
+//!
+//! ```text
+//! {
+//!     "hubertRequest" [
+//!         "nonce": h'9c1f...'
+//!         "timestamp": 2026-01-01T12:00:00Z
+//!         "method": "POST"
+//!         "path": "/put"
+//!         "arid": ARID
+//!         "body": Digest
+//!     ]
+//! } [
+//!     'signed': Signature
+//! ]
+//! ```
+//!
+//! The proof names the request it accompanies: its method, path, the ARIDs
+//! it acts on (one `arid` assertion each), and the digest of its body. The
+//! server accepts a proof only with that request, accepts each nonce once,
+//! and only while the timestamp is within its replay window, so a request
+//! captured in transit or from a log is refused when sent again, and its
+//! proof cannot be moved to another request.
+
+#[cfg(feature = "server")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "server")]
+use bc_components::PublicKeys;
+use bc_components::{ARID, Digest, Signer};
+use bc_envelope::prelude::*;
+use dcbor::Date;
+
+#[cfg(feature = "server")]
+use super::Error as ServerError;
+#[cfg(feature = "server")]
+use crate::Result;
+
+/// HTTP header carrying a signed request proof.
+pub const REQUEST_PROOF_HEADER: &str = "Hubert-Request-Proof";
+
+const SUBJECT: &str = "hubertRequest";
+const NONCE: &str = "nonce";
+const TIMESTAMP: &str = "timestamp";
+const METHOD: &str = "method";
+const PATH: &str = "path";
+const ARID_PREDICATE: &str = "arid";
+const BODY: &str = "body";
+
+/// Bytes in a proof's random nonce.
+const NONCE_LENGTH: usize = 16;
+
+/// The request a proof is signed for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProvenRequest<'a> {
+    /// The HTTP method, such as `POST`.
+    pub method: &'a str,
+    /// The path the server routes, such as `/put`, without any query.
+    pub path: &'a str,
+    /// The ARIDs the request acts on.
+    pub arids: &'a [ARID],
+    /// The request body, empty if there is none.
+    pub body: &'a [u8],
+}
+
+/// Sign a proof for `request`, with a random nonce and the current time.
+///
+/// [`ServerKvClient::with_holder_keys`](super::ServerKvClient::with_holder_keys)
+/// attaches one to every request; call this directly only when building
+/// requests by hand. A proof is good for a single request.
+pub fn sign_request_proof(
+    signer: &dyn Signer,
+    request: &ProvenRequest<'_>,
+) -> Envelope {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    bc_rand::fill_random_data(&mut nonce);
+    let mut proof = Envelope::new(SUBJECT)
+        .add_assertion(NONCE, ByteString::new(nonce.to_vec()))
+        .add_assertion(TIMESTAMP, Date::now())
+        .add_assertion(METHOD, request.method)
+        .add_assertion(PATH, request.path)
+        .add_assertion(BODY, Digest::from_image(request.body));
+    for arid in request.arids {
+        proof = proof.add_assertion(ARID_PREDICATE, *arid);
+    }
+    proof.sign(signer)
+}
+
+/// The nonces of accepted proofs, each kept until its timestamp has left
+/// the replay window and the proof would be refused anyway.
+#[cfg(feature = "server")]
+#[derive(Clone, Default)]
+pub(super) struct NonceLog {
+    nonces: Arc<Mutex<HashMap<Vec<u8>, u64>>>,
+}
+
+#[cfg(feature = "server")]
+impl NonceLog {
+    pub(super) fn new() -> Self { Self::default() }
+
+    /// Accept `proof` if `holder` signed it for `request`, its timestamp is
+    /// within `window` seconds of `now`, and its nonce has not been
+    /// accepted before.
+    pub(super) fn accept(
+        &self,
+        proof: &Envelope,
+        holder: &PublicKeys,
+        request: &ProvenRequest<'_>,
+        now: u64,
+        window: u64,
+    ) -> Result<()> {
+        let unauthorized =
+            |reason: &str| ServerError::Unauthorized(reason.to_string());
+
+        let proof = proof
+            .verify(holder)
+            .map_err(|_| unauthorized("request proof not signed by holder"))?;
+        if proof.extract_subject::<String>().ok().as_deref() != Some(SUBJECT) {
+            return Err(unauthorized("not a request proof").into());
+        }
+        let nonce: ByteString = proof
+            .object_for_predicate(NONCE)
+            .and_then(|object| object.extract_subject())
+            .map_err(|_| unauthorized("invalid request nonce"))?;
+        let timestamp: Date = proof
+            .object_for_predicate(TIMESTAMP)
+            .and_then(|object| object.extract_subject())
+            .map_err(|_| unauthorized("invalid request timestamp"))?;
+
+        let method: String = proof
+            .extract_object_for_predicate(METHOD)
+            .map_err(|_| unauthorized("invalid request method"))?;
+        let path: String = proof
+            .extract_object_for_predicate(PATH)
+            .map_err(|_| unauthorized("invalid request path"))?;
+        let body: Digest = proof
+            .extract_object_for_predicate(BODY)
+            .map_err(|_| unauthorized("invalid request body digest"))?;
+        let mut arids: Vec<ARID> = proof
+            .objects_for_predicate(ARID_PREDICATE)
+            .iter()
+            .map(|object| object.extract_subject())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| unauthorized("invalid request ARID"))?;
+        let mut expected_arids = request.arids.to_vec();
+        arids.sort_by_key(|arid| arid.data().to_vec());
+        expected_arids.sort_by_key(|arid| arid.data().to_vec());
+        if method != request.method
+            || path != request.path
+            || body != Digest::from_image(request.body)
+            || arids != expected_arids
+        {
+            return Err(
+                unauthorized("request proof is for another request").into()
+            );
+        }
+
+        // Negative timestamps saturate to 0, far outside any window
+        let at = timestamp.timestamp() as u64;
+        if at.abs_diff(now) > window {
+            return Err(
+                unauthorized("request proof outside replay window").into()
+            );
+        }
+
+        let mut nonces = self.nonces.lock().unwrap();
+        if nonces.contains_key(nonce.data()) {
+            return Err(unauthorized("request proof already used").into());
+        }
+        nonces.insert(nonce.data().to_vec(), at.saturating_add(window));
+        Ok(())
+    }
+
+    /// Forget nonces whose proofs can no longer be accepted at `now`.
+    /// Returns the number forgotten.
+    pub(super) fn prune(&self, now: u64) -> usize {
+        let mut nonces = self.nonces.lock().unwrap();
+        let before = nonces.len();
+        nonces.retain(|_, expires_at| *expires_at >= now);
+        before - nonces.len()
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use bc_components::keypair;
+
+    use super::*;
+
+    fn put(arids: &[ARID]) -> ProvenRequest<'_> {
+        ProvenRequest { method: "POST", path: "/put", arids, body: b"body" }
+    }
+
+    #[test]
+    fn test_proof_accepted_once() {
+        let (private_keys, public_keys) = keypair();
+        let (_, other_public_keys) = keypair();
+        let log = NonceLog::new();
+        let now = Date::now().timestamp() as u64;
+        let arids = [ARID::new()];
+        let request = put(&arids);
+
+        let proof = sign_request_proof(&private_keys, &request);
+        let accept = |proof: &Envelope, keys: &PublicKeys, now: u64| {
+            log.accept(proof, keys, &request, now, 60)
+        };
+        assert!(accept(&proof, &other_public_keys, now).is_err());
+        assert!(accept(&proof, &public_keys, now + 120).is_err());
+        assert!(accept(&proof, &public_keys, now).is_ok());
+        assert!(accept(&proof, &public_keys, now).is_err());
+
+        // A fresh proof is accepted even from the same holder
+        let fresh = sign_request_proof(&private_keys, &request);
+        assert!(accept(&fresh, &public_keys, now).is_ok());
+
+        assert_eq!(log.prune(now + 30), 0);
+        assert_eq!(log.prune(now + 120), 2);
+    }
+
+    #[test]
+    fn test_proof_bound_to_request() {
+        let (private_keys, public_keys) = keypair();
+        let log = NonceLog::new();
+        let now = Date::now().timestamp() as u64;
+        let arids = [ARID::new(), ARID::new()];
+        let request = put(&arids);
+        let proof = sign_request_proof(&private_keys, &request);
+
+        let other_arid = [ARID::new()];
+        let reordered = [arids[1], arids[0]];
+        for other in [
+            ProvenRequest { method: "GET", ..request },
+            ProvenRequest { path: "/delete", ..request },
+            ProvenRequest { arids: &other_arid, ..request },
+            ProvenRequest { arids: &arids[..1], ..request },
+            ProvenRequest { body: b"other body", ..request },
+        ] {
+            assert!(log.accept(&proof, &public_keys, &other, now, 60).is_err());
+        }
+        // The ARIDs may come in any order
+        let same = ProvenRequest { arids: &reordered, ..request };
+        assert!(log.accept(&proof, &public_keys, &same, now, 60).is_ok());
+    }
+}
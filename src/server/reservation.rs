@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use bc_components::{ARID, Digest};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use super::listing::ur_string;
+
+/// Header carrying the secret of the reservation a `/commit` fills.
+pub const RESERVATION_HEADER: &str = "Hubert-Reservation";
+
+/// A claim on an ARID, made before its envelope exists.
+///
+/// While a reservation is live, puts to its ARID are refused as if it were
+/// already stored, and only a commit presenting `secret` can store an
+/// envelope there. A reservation that is not filled by `expires_at` lapses,
+/// and the ARID is free again. Times are UNIX seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Reservation {
+    /// The reserved ARID.
+    #[serde(with = "ur_string")]
+    #[cfg_attr(
+        feature = "server",
+        schema(value_type = String, example = "ur:arid/...")
+    )]
+    pub arid: ARID,
+    /// Hex secret that commits an envelope to the ARID. Only its digest is
+    /// kept by the store.
+    pub secret: String,
+    /// When the reservation lapses if not filled.
+    pub expires_at: u64,
+}
+
+impl Reservation {
+    /// A new reservation of `arid` with a random secret.
+    pub(super) fn new(arid: ARID, expires_at: u64) -> Self {
+        let mut secret = [0u8; 32];
+        bc_rand::fill_random_data(&mut secret);
+        Self { arid, secret: hex::encode(secret), expires_at }
+    }
+}
+
+/// The digest a store keeps in place of a reservation's secret.
+pub(super) fn secret_digest(secret: &str) -> Digest {
+    Digest::from_image(secret.trim().as_bytes())
+}
+
+/// The live reservations of an in-memory store, by ARID.
+#[derive(Clone, Default)]
+pub(super) struct Reservations {
+    arids: Arc<RwLock<HashMap<ARID, (Digest, u64)>>>,
+}
+
+impl Reservations {
+    /// Reserve `arid` until `expires_at`, unless a live reservation already
+    /// holds it.
+    pub(super) fn reserve(
+        &self,
+        arid: &ARID,
+        expires_at: u64,
+        now: u64,
+    ) -> Option<Reservation> {
+        let mut arids = self.arids.write().unwrap();
+        arids.retain(|_, (_, expiry)| now < *expiry);
+        if arids.contains_key(arid) {
+            return None;
+        }
+        let reservation = Reservation::new(*arid, expires_at);
+        arids.insert(*arid, (secret_digest(&reservation.secret), expires_at));
+        Some(reservation)
+    }
+
+    /// Whether a live reservation holds `arid`.
+    pub(super) fn is_reserved(&self, arid: &ARID, now: u64) -> bool {
+        self.arids
+            .read()
+            .unwrap()
+            .get(arid)
+            .is_some_and(|(_, expiry)| now < *expiry)
+    }
+
+    /// Remove the live reservation of `arid` if `secret` is its secret.
+    /// Returns whether it was removed.
+    pub(super) fn take(&self, arid: &ARID, secret: &str, now: u64) -> bool {
+        let mut arids = self.arids.write().unwrap();
+        let matches = arids.get(arid).is_some_and(|(digest, expiry)| {
+            now < *expiry && *digest == secret_digest(secret)
+        });
+        if matches {
+            arids.remove(arid);
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_and_take() {
+        let reservations = Reservations::default();
+        let arid = ARID::new();
+
+        let reservation = reservations.reserve(&arid, 160, 100).unwrap();
+        assert_eq!(reservation.secret.len(), 64);
+        assert!(reservations.reserve(&arid, 160, 110).is_none());
+        assert!(reservations.is_reserved(&arid, 159));
+        assert!(!reservations.is_reserved(&arid, 160));
+
+        assert!(!reservations.take(&arid, "wrong", 120));
+        assert!(!reservations.take(&arid, &reservation.secret, 160));
+        assert!(reservations.take(&arid, &reservation.secret, 120));
+        assert!(!reservations.is_reserved(&arid, 120));
+
+        // A lapsed reservation frees the ARID
+        reservations.reserve(&arid, 130, 120).unwrap();
+        assert!(reservations.reserve(&arid, 200, 130).is_some());
+    }
+}
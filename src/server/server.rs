@@ -1,117 +1,586 @@
-use std::{net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::{Future, IntoFuture},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use axum::{
     Router,
     body::Bytes,
-    extract::{ConnectInfo, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    extract::{
+        ConnectInfo, DefaultBodyLimit, FromRequestParts, Path, Query, Request,
+        State,
+    },
+    http::{HeaderMap, StatusCode, header, request::Parts},
+    middleware::{self, Next},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
     routing::{get, post},
 };
-use bc_components::ARID;
+use bc_components::{ARID, Digest, DigestProvider, PrivateKeys, PublicKeys};
 use bc_envelope::Envelope;
 use bc_ur::prelude::*;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
+use tower_http::timeout::{RequestBodyTimeoutLayer, TimeoutLayer};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use super::{ServerKv, SqliteKv};
-use crate::Result;
+use super::{
+    AccessStats, AccessSummary, AridFilter, CAPABILITY_HEADER, Capability,
+    ListedEntry, RESERVATION_HEADER, ReceiptStatus, ReplicatedEntry,
+    ReplicationBatch, Reservation, Right, ServerConfig, ServerKv, SqliteKv,
+    StorageStats, SyncRequest, Tombstone,
+    clock::unix_now,
+    conformance::{
+        ConformanceLog, ConformanceReport, ConformanceScript, ConformanceStep,
+        StepResult, StepStatus, is_valid_conformance_token,
+    },
+    ip_filter::{forwarded_client, is_allowed},
+    listener::{LimitedListener, Peer},
+    listing::{MAX_LIST_LIMIT, MAX_TAG_LENGTH, TAG_HEADER},
+    quota::PutQuotas,
+    receipts::{ReceiptLog, VERSION_HEADER},
+    request_proof::{NonceLog, ProvenRequest, REQUEST_PROOF_HEADER},
+    stats::AccessLog,
+    systemd,
+    wire::{
+        GetManyBody, GetManyResponse, MAX_GET_MANY_ARIDS, MAX_GET_WAIT,
+        MAX_PUT_MANY_BODY_LENGTH, MAX_PUT_MANY_ENTRIES, PutBody, PutManyBody,
+        ReserveBody, WatchEvent, entity_tag,
+    },
+};
+use crate::{
+    Result, expiry,
+    ur_input::{
+        MAX_ARID_UR_LENGTH, MAX_ENVELOPE_UR_LENGTH, parse_arid_ur,
+        parse_envelope_ur,
+    },
+};
 
-/// Configuration for the Hubert server.
-#[derive(Debug, Clone)]
-pub struct ServerConfig {
-    pub port: u16,
-    /// Maximum TTL in seconds allowed.
-    /// If a put() specifies a TTL higher than this, it will be clamped.
-    /// If put() specifies None, this value will be used.
-    /// Hubert is intended for coordination, not long-term storage.
-    pub max_ttl: u64,
-    /// Enable verbose logging with timestamps
-    pub verbose: bool,
-}
+/// Maximum accepted request body size in bytes.
+///
+/// Large enough for a `ur:arid` line, a `ur:envelope` line, and a TTL line.
+/// Bodies beyond this are rejected with 413 before any parsing happens.
+const MAX_BODY_LENGTH: usize = MAX_ARID_UR_LENGTH + MAX_ENVELOPE_UR_LENGTH + 64;
 
-impl Default for ServerConfig {
-    fn default() -> Self {
-        Self {
-            port: 45678,
-            max_ttl: 86400, // 24 hours max (and default)
-            verbose: false,
-        }
-    }
-}
+/// How often expired put receipts and stale access statistics are pruned.
+const RECEIPT_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest a `/watch` stream waits without a put before looking again, so
+/// an entry that expires while watched is noticed and reported.
+const WATCH_RECHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Most entries one `/sync` answer carries; a peer missing more catches up
+/// over several passes.
+const MAX_SYNC_ENTRIES: usize = 1000;
+
+/// False positive rate of the filters sent to peers. Each false positive
+/// only delays an entry to a later pass.
+const SYNC_FALSE_POSITIVE_RATE: f64 = 0.001;
+
+/// OpenAPI description of the server protocol, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Hubert Server",
+        description = "Write-once storage of Gordian Envelopes at ARIDs. \
+            Request and response bodies are UR strings, one per line."
+    ),
+    paths(
+        handle_health,
+        handle_put,
+        handle_update,
+        handle_put_many,
+        handle_reserve,
+        handle_commit,
+        handle_get,
+        handle_get_many,
+        handle_watch,
+        handle_delete,
+        handle_receipts,
+        handle_stat,
+        handle_start_conformance,
+        handle_conformance_report,
+        handle_drain,
+        handle_list,
+        handle_count,
+        handle_purge,
+        handle_stats_summary,
+        handle_stats,
+        handle_storage_stats,
+        handle_sync
+    ),
+    components(schemas(
+        Health,
+        ReceiptStatus,
+        ConformanceScript,
+        ConformanceStep,
+        ConformanceReport,
+        StepResult,
+        StepStatus,
+        ListedEntry,
+        Count,
+        Purged,
+        Tombstone,
+        Reservation,
+        AccessStats,
+        AccessSummary,
+        StorageStats
+    ))
+)]
+struct ApiDoc;
 
 /// Shared server state.
 #[derive(Clone)]
 struct ServerState {
     storage: ServerKv,
     config: ServerConfig,
+    receipts: ReceiptLog,
+    access: AccessLog,
+    conformance: ConformanceLog,
+    /// Keys trusted to sign capabilities; empty when no token is required.
+    trust_roots: Arc<[PublicKeys]>,
+    /// Nonces of the request proofs already accepted.
+    nonces: NonceLog,
+    quotas: PutQuotas,
+    /// Set while draining for maintenance: puts are refused, gets served.
+    draining: Arc<AtomicBool>,
+    /// This server's keys for exchanging entries with `peers`.
+    identity: Option<PrivateKeys>,
+    /// The servers to sync with: their URLs and public keys.
+    peers: Arc<[(String, PublicKeys)]>,
 }
 
 impl ServerState {
     fn new(config: ServerConfig, storage: ServerKv) -> Self {
-        Self { storage, config }
+        Self {
+            storage,
+            config,
+            receipts: ReceiptLog::new(),
+            access: AccessLog::new(),
+            conformance: ConformanceLog::new(),
+            trust_roots: Arc::new([]),
+            nonces: NonceLog::new(),
+            quotas: PutQuotas::new(),
+            draining: Arc::new(AtomicBool::new(false)),
+            identity: None,
+            peers: Arc::new([]),
+        }
     }
 
-    fn put(
+    /// Apply the TLS client policy for the connection's certificate
+    /// identity, if the server has one.
+    fn check_client(
         &self,
-        arid: ARID,
-        envelope: Envelope,
-        requested_ttl: Option<Duration>,
-        client_ip: Option<SocketAddr>,
-    ) -> std::result::Result<(), String> {
-        use crate::logging::verbose_println;
+        peer: &Peer,
+        right: Right,
+    ) -> std::result::Result<(), ServerError> {
+        let Some(tls) = &self.config.tls else {
+            return Ok(());
+        };
+        if tls.clients.is_empty() {
+            return Ok(());
+        }
+        let allowed = peer
+            .identity
+            .as_ref()
+            .and_then(|identity| tls.clients.get(identity))
+            .is_some_and(|policy| policy.allow.contains(&right));
+        if !allowed {
+            return Err(ServerError::Forbidden(
+                "Client certificate does not allow this request".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse puts while the server is read-only or draining.
+    fn check_writable(&self) -> std::result::Result<(), ServerError> {
+        if self.config.read_only {
+            return Err(ServerError::Forbidden(
+                "Server is read-only".to_string(),
+            ));
+        }
+        if self.draining.load(Ordering::Relaxed) {
+            return Err(ServerError::Unavailable(
+                "Server is draining for maintenance".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run `put`, counting `count` puts against the client's hourly quota
+    /// if it has one. Puts that fail, such as conflicting ones, do not
+    /// count.
+    fn charge_puts<T>(
+        &self,
+        peer: &Peer,
+        count: u64,
+        put: impl FnOnce() -> std::result::Result<T, ServerError>,
+    ) -> std::result::Result<T, ServerError> {
+        let (Some(tls), Some(identity)) = (&self.config.tls, &peer.identity)
+        else {
+            return put();
+        };
+        let Some(limit) = tls
+            .clients
+            .get(identity)
+            .and_then(|policy| policy.max_puts_per_hour)
+        else {
+            return put();
+        };
+        // Taken up front, so concurrent puts cannot overrun the quota
+        let now = unix_now();
+        if !self.quotas.take(identity, count, limit, now) {
+            return Err(ServerError::TooManyRequests);
+        }
+        let result = put();
+        if result.is_err() {
+            self.quotas.refund(identity, count, now);
+        }
+        result
+    }
+
+    /// Check that the client may perform `right` on every ARID of
+    /// `request`: its certificate identity must allow it, and so must its
+    /// capability if the server requires capabilities.
+    fn authorize(
+        &self,
+        peer: &Peer,
+        headers: &HeaderMap,
+        request: &ProvenRequest<'_>,
+        right: Right,
+    ) -> std::result::Result<(), ServerError> {
+        self.check_client(peer, right)?;
+        let now = dcbor::Date::now();
+        match self.capability(headers, request)? {
+            Some(capability)
+                if !request
+                    .arids
+                    .iter()
+                    .all(|arid| capability.permits(right, arid, &now)) =>
+            {
+                Err(ServerError::Forbidden(
+                    "Capability does not allow this request".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the client may use admin endpoints.
+    ///
+    /// Unlike data requests these are never open to all: the server must
+    /// identify clients by capability or certificate policy, and the client
+    /// must hold the `admin` right under each mechanism configured.
+    fn authorize_admin(
+        &self,
+        peer: &Peer,
+        headers: &HeaderMap,
+        request: &ProvenRequest<'_>,
+    ) -> std::result::Result<(), ServerError> {
+        let has_policies = self
+            .config
+            .tls
+            .as_ref()
+            .is_some_and(|tls| !tls.clients.is_empty());
+        if self.trust_roots.is_empty() && !has_policies {
+            return Err(ServerError::Forbidden(
+                "Admin requests need capability roots or client policies"
+                    .to_string(),
+            ));
+        }
+        self.check_client(peer, Right::Admin)?;
+        match self.capability(headers, request)? {
+            Some(capability)
+                if !capability.grants(Right::Admin, &dcbor::Date::now()) =>
+            {
+                Err(ServerError::Forbidden(
+                    "Capability does not allow this request".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The request's verified capability, or `None` if the server does not
+    /// require capabilities. A capability bound to a holder also needs a
+    /// fresh proof signed for `request`, whose nonce this accepts.
+    fn capability(
+        &self,
+        headers: &HeaderMap,
+        request: &ProvenRequest<'_>,
+    ) -> std::result::Result<Option<Capability>, ServerError> {
+        if self.trust_roots.is_empty() {
+            return Ok(None);
+        }
+        let token = headers
+            .get(CAPABILITY_HEADER)
+            .ok_or_else(|| {
+                ServerError::Unauthorized("Missing capability".to_string())
+            })?
+            .to_str()
+            .map_err(|_| {
+                ServerError::Unauthorized("Invalid capability".to_string())
+            })?;
+        let capability = parse_envelope_ur(token)
+            .and_then(|token| Capability::verify(&token, &self.trust_roots))
+            .map_err(|e| ServerError::Unauthorized(e.to_string()))?;
+        if let Some(holder) = capability.holder() {
+            let proof = headers
+                .get(REQUEST_PROOF_HEADER)
+                .ok_or_else(|| {
+                    ServerError::Unauthorized(
+                        "Missing request proof".to_string(),
+                    )
+                })?
+                .to_str()
+                .map_err(|_| {
+                    ServerError::Unauthorized(
+                        "Invalid request proof".to_string(),
+                    )
+                })?;
+            parse_envelope_ur(proof)
+                .and_then(|proof| {
+                    self.nonces.accept(
+                        &proof,
+                        holder,
+                        request,
+                        unix_now(),
+                        self.config.replay_window,
+                    )
+                })
+                .map_err(|e| ServerError::Unauthorized(e.to_string()))?;
+        }
+        Ok(Some(capability))
+    }
 
+    /// The TTL in seconds of an entry or reservation requested to last
+    /// `requested_ttl`.
+    fn effective_ttl(&self, requested_ttl: Option<Duration>) -> u64 {
         // Determine effective TTL:
         // - If requested, use it (clamped to max_ttl)
-        // - If None requested, use max_ttl
+        // - If None requested, use default_ttl (clamped to max_ttl), or max_ttl
+        //   if there is none
         // All entries expire (hubert is for coordination, not long-term
         // storage)
         let max_duration = Duration::from_secs(self.config.max_ttl);
         let ttl = match requested_ttl {
-            Some(req) => {
-                if req > max_duration {
-                    max_duration
-                } else {
-                    req
-                }
-            }
-            None => max_duration,
+            Some(req) => req,
+            None => self
+                .config
+                .default_ttl
+                .map_or(max_duration, Duration::from_secs),
         };
+        ttl.min(max_duration).as_secs()
+    }
+
+    /// Store an envelope, filling the reservation with secret `reservation`
+    /// if given.
+    fn put(
+        &self,
+        arid: ARID,
+        envelope: Envelope,
+        requested_ttl: Option<Duration>,
+        tag: Option<&str>,
+        reservation: Option<&str>,
+        client: Option<&Peer>,
+    ) -> std::result::Result<ReceiptStatus, ServerError> {
+        use crate::logging::verbose_println;
 
-        let ttl_seconds = ttl.as_secs();
+        let ttl_seconds = self.effective_ttl(requested_ttl);
 
-        let result = self.storage.put_sync(arid, envelope, ttl_seconds);
+        let result = self.storage.put_sync(
+            arid,
+            envelope,
+            ttl_seconds,
+            tag,
+            reservation,
+        );
+        if result.is_ok() {
+            let now = unix_now();
+            self.receipts
+                .record(arid, now, now.saturating_add(ttl_seconds));
+        }
 
         if self.config.verbose {
             let ip_str =
-                client_ip.map(|ip| format!("{}: ", ip)).unwrap_or_default();
+                client.map(|peer| format!("{}: ", peer)).unwrap_or_default();
             let status = match &result {
                 Ok(_) => "OK".to_string(),
                 Err(e) => format!("ERROR: {}", e),
             };
+            let tag_str = tag.map(|t| format!(" [{}]", t)).unwrap_or_default();
             verbose_println(&format!(
-                "{}PUT {} (TTL {}s) {}",
+                "{}PUT {} (TTL {}s){} {}",
                 ip_str,
                 arid.ur_string(),
                 ttl_seconds,
+                tag_str,
                 status
             ));
         }
 
-        result
+        result.map_err(|e| match e {
+            crate::Error::Server(super::Error::NotReserved { .. }) => {
+                ServerError::PreconditionFailed(e.to_string())
+            }
+            e => ServerError::Conflict(e.to_string()),
+        })?;
+
+        // The receipt log holds the expiry applied, which for an identical
+        // re-put is that of the put it repeats
+        Ok(self.receipts.status(&arid, unix_now()))
+    }
+
+    /// Replace the entry at `arid`, or store it if there is none, returning
+    /// the receipt and the entry's new version.
+    fn update(
+        &self,
+        arid: ARID,
+        envelope: Envelope,
+        requested_ttl: Option<Duration>,
+        tag: Option<&str>,
+        client: &Peer,
+    ) -> std::result::Result<(ReceiptStatus, u64), ServerError> {
+        use crate::logging::verbose_println;
+
+        let ttl_seconds = self.effective_ttl(requested_ttl);
+
+        let result = self.storage.update_sync(arid, envelope, ttl_seconds, tag);
+        let now = unix_now();
+        if result.is_ok() {
+            self.receipts
+                .replace(arid, now, now.saturating_add(ttl_seconds));
+        }
+
+        if self.config.verbose {
+            let status = match &result {
+                Ok(version) => format!("OK (version {})", version),
+                Err(e) => format!("ERROR: {}", e),
+            };
+            verbose_println(&format!(
+                "{}: UPDATE {} (TTL {}s) {}",
+                client,
+                arid.ur_string(),
+                ttl_seconds,
+                status
+            ));
+        }
+
+        let version = result.map_err(|e| match e {
+            crate::Error::Unsupported { .. } => ServerError::NotImplemented(
+                "Server does not accept updates".to_string(),
+            ),
+            e => ServerError::Conflict(e.to_string()),
+        })?;
+        Ok((self.receipts.status(&arid, now), version))
+    }
+
+    /// Store several envelopes, every one or none, each for the TTL
+    /// requested at the same index of `requested_ttls`, returning their
+    /// receipts in order.
+    fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        requested_ttls: &[Option<Duration>],
+        tag: Option<&str>,
+        client: Option<&Peer>,
+    ) -> std::result::Result<Vec<ReceiptStatus>, ServerError> {
+        use crate::logging::verbose_println;
+
+        let ttls: Vec<u64> = requested_ttls
+            .iter()
+            .map(|&requested_ttl| self.effective_ttl(requested_ttl))
+            .collect();
+
+        let result = self.storage.put_many_sync(entries, &ttls, tag);
+        if result.is_ok() {
+            let now = unix_now();
+            for ((arid, _), &ttl_seconds) in entries.iter().zip(&ttls) {
+                self.receipts
+                    .record(*arid, now, now.saturating_add(ttl_seconds));
+            }
+        }
+
+        if self.config.verbose {
+            let ip_str =
+                client.map(|peer| format!("{}: ", peer)).unwrap_or_default();
+            let status = match &result {
+                Ok(_) => "OK".to_string(),
+                Err(e) => format!("ERROR: {}", e),
+            };
+            let tag_str = tag.map(|t| format!(" [{}]", t)).unwrap_or_default();
+            verbose_println(&format!(
+                "{}PUT_MANY {} entries{} {}",
+                ip_str,
+                entries.len(),
+                tag_str,
+                status
+            ));
+        }
+
+        result.map_err(|e| ServerError::Conflict(e.to_string()))?;
+
+        let now = unix_now();
+        Ok(entries
+            .iter()
+            .map(|(arid, _)| self.receipts.status(arid, now))
+            .collect())
     }
 
-    fn get(
+    /// Reserve `arid` for the requested TTL, clamped like a put's.
+    fn reserve(
         &self,
         arid: &ARID,
-        client_ip: Option<SocketAddr>,
-    ) -> Option<Envelope> {
+        requested_ttl: Option<Duration>,
+        client: Option<&Peer>,
+    ) -> std::result::Result<Reservation, ServerError> {
+        use crate::logging::verbose_println;
+
+        let ttl_seconds = self.effective_ttl(requested_ttl);
+        let result = self.storage.reserve_sync(arid, ttl_seconds);
+
+        if self.config.verbose {
+            let ip_str =
+                client.map(|peer| format!("{}: ", peer)).unwrap_or_default();
+            let status = match &result {
+                Ok(_) => "OK".to_string(),
+                Err(e) => format!("ERROR: {}", e),
+            };
+            verbose_println(&format!(
+                "{}RESERVE {} (TTL {}s) {}",
+                ip_str,
+                arid.ur_string(),
+                ttl_seconds,
+                status
+            ));
+        }
+
+        result.map_err(|e| match e {
+            crate::Error::AlreadyExists { .. } => {
+                ServerError::Conflict(e.to_string())
+            }
+            e => ServerError::Internal(e.to_string()),
+        })
+    }
+
+    fn get(&self, arid: &ARID, client: Option<&Peer>) -> Option<Envelope> {
         use crate::logging::verbose_println;
 
         let result = self.storage.get_sync(arid);
 
         if self.config.verbose {
             let ip_str =
-                client_ip.map(|ip| format!("{}: ", ip)).unwrap_or_default();
+                client.map(|peer| format!("{}: ", peer)).unwrap_or_default();
             let status = if result.is_some() { "OK" } else { "NOT_FOUND" };
             verbose_println(&format!(
                 "{}GET {} {}",
@@ -123,6 +592,61 @@ impl ServerState {
 
         result
     }
+
+    /// The unexpired entries covered by `filter` that it does not contain,
+    /// at most [`MAX_SYNC_ENTRIES`] of them.
+    fn missing_entries(
+        &self,
+        filter: &AridFilter,
+    ) -> crate::Result<Vec<ReplicatedEntry>> {
+        let expiries: HashMap<ARID, u64> = self
+            .storage
+            .list(0, usize::MAX, false, None, None)?
+            .into_iter()
+            .map(|entry| (entry.arid, entry.expires_at.unwrap_or(u64::MAX)))
+            .collect();
+        Ok(filter
+            .missing(expiries.iter().map(|(arid, expiry)| (*arid, *expiry)))
+            .into_iter()
+            .filter_map(|arid| {
+                let envelope = self.storage.get_sync(&arid)?;
+                Some(ReplicatedEntry {
+                    arid,
+                    envelope,
+                    expires_at: expiries[&arid],
+                })
+            })
+            .take(MAX_SYNC_ENTRIES)
+            .collect())
+    }
+
+    /// Store the entries a peer sent that this server lacks, keeping their
+    /// expiry but no more than `max_ttl` from now. Entries with less than
+    /// `sync_min_ttl` left, and ARIDs already used here, are skipped.
+    /// Returns the number stored.
+    fn store_synced(&self, entries: Vec<ReplicatedEntry>) -> usize {
+        let now = unix_now();
+        let min_expiry = now.saturating_add(self.config.sync_min_ttl);
+        entries
+            .into_iter()
+            .filter(|entry| entry.expires_at > min_expiry)
+            .filter(|entry| {
+                let ttl = (entry.expires_at - now).min(self.config.max_ttl);
+                let envelope = entry.envelope.clone();
+                self.storage
+                    .put_sync(entry.arid, envelope, ttl, None, None)
+                    .is_ok()
+            })
+            .count()
+    }
+
+    /// Whether a request or batch from a peer sealed at `sent_at` is within
+    /// the replay window.
+    fn is_fresh(&self, sent_at: &dcbor::Date) -> bool {
+        // Negative timestamps saturate to 0, far outside any window
+        let at = sent_at.timestamp() as u64;
+        at.abs_diff(unix_now()) <= self.config.replay_window
+    }
 }
 
 /// Hubert HTTP server.
@@ -133,7 +657,26 @@ pub struct Server {
 
 impl Server {
     /// Create a new server with the given configuration and storage backend.
+    ///
+    /// The backend keeps tombstones if `config.tombstone_retention` is set,
+    /// accepts identical re-puts if `config.idempotent_puts` is, and
+    /// compresses large envelopes if `config.compression_threshold` is, and
+    /// moves old entries to `config.cold_storage` if that is.
     pub fn new(config: ServerConfig, storage: ServerKv) -> Self {
+        let storage = match config.tombstone_retention {
+            Some(retention) => storage.with_tombstones(retention),
+            None => storage,
+        };
+        let storage = storage.with_idempotent_puts(config.idempotent_puts);
+        let storage = storage.with_mutability(config.mutability);
+        let storage = match config.compression_threshold {
+            Some(threshold) => storage.with_compression(threshold),
+            None => storage,
+        };
+        let storage = match &config.cold_storage {
+            Some(dir) => storage.with_cold_storage(dir, config.cold_after),
+            None => storage,
+        };
         let state = ServerState::new(config.clone(), storage);
         Self { config, state }
     }
@@ -148,132 +691,1873 @@ impl Server {
         Self::new(config, ServerKv::sqlite(storage))
     }
 
-    /// Run the server.
+    /// Create a new server whose storage backend is chosen by the
+    /// configuration: SQLite if `config.sqlite` is set, otherwise in-memory.
+    ///
+    /// The SQLite path is resolved with [`crate::paths::resolve_sqlite_path`],
+    /// so it may name a directory and missing directories are created.
+    pub fn from_config(config: ServerConfig) -> Result<Self> {
+        match &config.sqlite {
+            Some(path) => {
+                let path = crate::paths::resolve_sqlite_path(path)?;
+                let store = SqliteKv::new(path)?;
+                Ok(Self::new_sqlite(config, store))
+            }
+            None => Ok(Self::new_memory(config)),
+        }
+    }
+
+    /// Run the server until Ctrl-C or SIGTERM is received.
     pub async fn run(self) -> Result<()> {
+        self.run_with_shutdown(shutdown_signal()).await
+    }
+
+    /// Run the server until `signal` completes.
+    ///
+    /// After the signal fires the server stops accepting connections and
+    /// waits up to `shutdown_grace` seconds for in-flight requests to drain.
+    pub async fn run_with_shutdown<F>(mut self, signal: F) -> Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        use crate::logging::verbose_println;
+
+        self.state.trust_roots = self.config.trust_roots()?.into();
+        self.state.identity = self.config.identity_keys()?;
+        self.state.peers = self.config.peer_keys()?.into();
+        if !self.state.peers.is_empty() && self.state.identity.is_none() {
+            return Err(super::Error::Config(
+                "syncing with peers needs an identity".to_string(),
+            )
+            .into());
+        }
+        #[cfg(feature = "tls")]
+        let tls = self
+            .config
+            .tls
+            .as_ref()
+            .map(super::tls::acceptor)
+            .transpose()?;
+        #[cfg(not(feature = "tls"))]
+        if self.config.tls.is_some() {
+            return Err(super::Error::Config(
+                "TLS requires hubert to be built with the `tls` feature"
+                    .to_string(),
+            )
+            .into());
+        }
+
+        let request_timeout = Duration::from_secs(self.config.request_timeout);
         let app = Router::new()
             .route("/health", get(handle_health))
             .route("/put", post(handle_put))
+            .route("/update", post(handle_update))
+            .route(
+                "/put_many",
+                post(handle_put_many)
+                    .layer(DefaultBodyLimit::max(MAX_PUT_MANY_BODY_LENGTH)),
+            )
+            .route("/reserve", post(handle_reserve))
+            .route("/commit", post(handle_commit))
             .route("/get", post(handle_get))
-            .with_state(self.state);
+            .route("/get_many", post(handle_get_many))
+            .route("/watch", post(handle_watch))
+            .route("/delete", post(handle_delete))
+            .route("/receipts", post(handle_receipts))
+            .route("/stat", post(handle_stat))
+            .route("/conformance", post(handle_start_conformance))
+            .route("/conformance/{token}", get(handle_conformance_report))
+            .route("/admin/drain", post(handle_drain))
+            .route("/admin/list", get(handle_list))
+            .route("/admin/count", get(handle_count))
+            .route("/admin/purge", post(handle_purge))
+            .route("/admin/stats", get(handle_stats_summary))
+            .route("/admin/stats/{*arid}", get(handle_stats))
+            .route("/admin/storage", get(handle_storage_stats))
+            .route("/sync", post(handle_sync));
+        let app = if self.config.metrics {
+            app.route("/metrics", get(handle_metrics))
+        } else {
+            app
+        };
+
+        // With the Swagger UI enabled, it serves the OpenAPI document too
+        #[cfg(feature = "swagger-ui")]
+        let app = app.merge(
+            utoipa_swagger_ui::SwaggerUi::new("/docs")
+                .url("/openapi.json", ApiDoc::openapi()),
+        );
+        #[cfg(not(feature = "swagger-ui"))]
+        let app = app.route("/openapi.json", get(handle_openapi));
 
-        let addr = format!("127.0.0.1:{}", self.config.port);
-        let listener = TcpListener::bind(&addr).await?;
-        println!("✓ Hubert server listening on {}", addr);
+        let app = app
+            .layer(DefaultBodyLimit::max(MAX_BODY_LENGTH))
+            .layer(RequestBodyTimeoutLayer::new(request_timeout))
+            .layer(TimeoutLayer::with_status_code(
+                StatusCode::REQUEST_TIMEOUT,
+                request_timeout,
+            ))
+            .layer(middleware::from_fn_with_state(
+                self.state.clone(),
+                filter_clients,
+            ))
+            .with_state(self.state.clone());
 
-        axum::serve(
+        // Prefer a socket passed by systemd socket activation
+        let listener = match systemd::take_listener()? {
+            Some(listener) => TcpListener::from_std(listener)?,
+            None => {
+                TcpListener::bind((self.config.bind.as_str(), self.config.port))
+                    .await?
+            }
+        };
+        if !self.config.quiet {
+            eprintln!(
+                "✓ Hubert server listening on {}",
+                listener.local_addr()?
+            );
+        }
+        let _ = systemd::notify("READY=1");
+
+        let listener = LimitedListener::new(
             listener,
-            app.into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .await?;
+            self.config.max_connections,
+            Duration::from_secs(self.config.idle_timeout),
+        );
+
+        // Forget receipts and access statistics once they have outlived the
+        // retention window, and nonces once their proofs have
+        let receipts = self.state.receipts.clone();
+        let access = self.state.access.clone();
+        let nonces = self.state.nonces.clone();
+        let retention = self.config.receipt_retention;
+        let pruner = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RECEIPT_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                receipts.prune(unix_now(), retention);
+                access.prune(unix_now(), retention);
+                nonces.prune(unix_now());
+            }
+        });
+
+        // Catch up on entries put at peers while this server was down
+        let syncer = (!self.state.peers.is_empty()).then(|| {
+            let state = self.state.clone();
+            let period = Duration::from_secs(self.config.sync_interval.max(1));
+            tokio::spawn(async move {
+                let client = reqwest::Client::new();
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    sync_with_peers(&state, &client).await;
+                }
+            })
+        });
+
+        let (signalled_tx, signalled_rx) = tokio::sync::oneshot::channel();
+        let shutdown = async move {
+            signal.await;
+            let _ = systemd::notify("STOPPING=1");
+            let _ = signalled_tx.send(());
+        };
+        let app = app.into_make_service_with_connect_info::<Peer>();
+        #[cfg(feature = "tls")]
+        let serve = match tls {
+            Some(acceptor) => {
+                let listener =
+                    super::tls::TlsListener::new(listener, acceptor)?;
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(shutdown)
+                    .into_future()
+            }
+            None => axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .into_future(),
+        };
+        #[cfg(not(feature = "tls"))]
+        let serve = axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .into_future();
+
+        let grace = Duration::from_secs(self.config.shutdown_grace);
+        let grace_elapsed = async move {
+            match signalled_rx.await {
+                Ok(()) => tokio::time::sleep(grace).await,
+                Err(_) => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            result = serve => result?,
+            _ = grace_elapsed => {
+                if self.config.verbose {
+                    verbose_println(
+                        "Shutdown grace period elapsed, dropping open connections",
+                    );
+                }
+            }
+        }
+        pruner.abort();
+        if let Some(syncer) = syncer {
+            syncer.abort();
+        }
 
         Ok(())
     }
 
     /// Get the port the server is configured to listen on.
     pub fn port(&self) -> u16 { self.config.port }
+
+    /// The server's OpenAPI document as JSON, for generating clients.
+    pub fn openapi_json() -> String {
+        ApiDoc::openapi()
+            .to_pretty_json()
+            .expect("OpenAPI document serializes to JSON")
+    }
+}
+
+/// One anti-entropy pass: ask each peer for the entries this server is
+/// missing, and store them.
+async fn sync_with_peers(state: &ServerState, client: &reqwest::Client) {
+    use crate::logging::verbose_println;
+
+    let Some(identity) = &state.identity else {
+        return;
+    };
+    for (url, peer_keys) in state.peers.iter() {
+        let result =
+            sync_with_peer(state, client, identity, url, peer_keys).await;
+        if state.config.verbose {
+            verbose_println(&match result {
+                Ok(stored) => format!("SYNC {} stored {}", url, stored),
+                Err(e) => format!("SYNC {} ERROR: {}", url, e),
+            });
+        }
+    }
+}
+
+/// Send the peer at `url` a filter of the ARIDs this server holds, and
+/// store the entries it answers are missing. Returns the number stored.
+async fn sync_with_peer(
+    state: &ServerState,
+    client: &reqwest::Client,
+    identity: &PrivateKeys,
+    url: &str,
+    peer_keys: &PublicKeys,
+) -> Result<usize> {
+    // Entries about to expire are neither worth sending nor asking for
+    let expires_after = unix_now().saturating_add(state.config.sync_min_ttl);
+    let held: Vec<ARID> = state
+        .storage
+        .list(0, usize::MAX, false, None, None)?
+        .into_iter()
+        .filter(|entry| entry.expires_at.is_none_or(|at| at > expires_after))
+        .map(|entry| entry.arid)
+        .collect();
+    let mut filter =
+        AridFilter::new(held.len(), SYNC_FALSE_POSITIVE_RATE, expires_after);
+    for arid in &held {
+        filter.insert(arid);
+    }
+
+    let request = SyncRequest::new(filter).seal(identity, peer_keys);
+    let response = client
+        .post(format!("{}/sync", url))
+        .body(request.ur_string())
+        .send()
+        .await
+        .map_err(super::Error::from)?;
+    let status = response.status();
+    let text = response.text().await.map_err(super::Error::from)?;
+    if !status.is_success() {
+        return Err(super::Error::General(format!(
+            "{}: {}",
+            status,
+            text.trim()
+        ))
+        .into());
+    }
+
+    let sealed = parse_envelope_ur(&text)?;
+    let batch = ReplicationBatch::unseal(&sealed, peer_keys, identity)?;
+    if !state.is_fresh(&batch.sent_at) {
+        return Err(super::Error::General(
+            "sync answer outside replay window".to_string(),
+        )
+        .into());
+    }
+    Ok(state.store_synced(batch.entries))
+}
+
+/// Resolve when the process receives Ctrl-C or (on Unix) SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Response body of `/health`.
+#[derive(Serialize, ToSchema)]
+struct Health {
+    /// Always `hubert`.
+    server: &'static str,
+    /// Server version.
+    version: &'static str,
+    /// `ok`, or `draining` while puts are refused for maintenance.
+    status: &'static str,
 }
 
 /// Handle health check requests.
 ///
 /// Returns JSON with server identification and version.
-async fn handle_health() -> impl IntoResponse {
-    let version = env!("CARGO_PKG_VERSION");
-    let response = serde_json::json!({
-        "server": "hubert",
-        "version": version,
-        "status": "ok"
-    });
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Server identification", body = Health)
+    )
+)]
+async fn handle_health(State(state): State<ServerState>) -> impl IntoResponse {
+    let draining = state.draining.load(Ordering::Relaxed);
+    let response = Health {
+        server: "hubert",
+        version: env!("CARGO_PKG_VERSION"),
+        status: if draining { "draining" } else { "ok" },
+    };
     (StatusCode::OK, serde_json::to_string(&response).unwrap())
 }
 
+/// Serve the server's totals in the Prometheus text format, on servers
+/// with `metrics` on.
+async fn handle_metrics(
+    State(state): State<ServerState>,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    let storage = state
+        .storage
+        .storage_stats()
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+    let expired = state.receipts.expired(unix_now());
+    let access = state.access.summary(&expired);
+    let draining = state.draining.load(Ordering::Relaxed);
+    let gauges = [
+        (
+            "hubert_entries",
+            "Entries stored, including expired ones not yet pruned",
+            storage.entries as u64,
+        ),
+        (
+            "hubert_stored_bytes",
+            "Bytes the stored envelopes take",
+            storage.stored_bytes,
+        ),
+        (
+            "hubert_cold_entries",
+            "Entries moved to cold storage",
+            storage.cold_entries as u64,
+        ),
+        (
+            "hubert_recent_arids",
+            "ARIDs asked for within the receipt retention window",
+            access.arids as u64,
+        ),
+        ("hubert_recent_gets", "Gets of those ARIDs", access.gets),
+        (
+            "hubert_recent_gets_found",
+            "Gets of those ARIDs that returned the envelope",
+            access.found,
+        ),
+        (
+            "hubert_expired_unread",
+            "Entries that expired without any get returning them",
+            access.expired_unread as u64,
+        ),
+        (
+            "hubert_draining",
+            "1 while puts are refused for maintenance",
+            u64::from(draining),
+        ),
+    ];
+    let body: String = gauges
+        .iter()
+        .map(|(name, help, value)| {
+            format!(
+                "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"
+            )
+        })
+        .collect();
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    ))
+}
+
+/// Serve the OpenAPI document.
+#[cfg(not(feature = "swagger-ui"))]
+async fn handle_openapi() -> impl IntoResponse {
+    (
+        [(axum::http::header::CONTENT_TYPE, "application/json")],
+        Server::openapi_json(),
+    )
+}
+
+/// The method and path of a request, which a request proof must name.
+struct RequestLine {
+    method: String,
+    path: String,
+}
+
+impl RequestLine {
+    /// The request, acting on `arids` with `body`, that a proof must be
+    /// signed for.
+    fn proven<'a>(
+        &'a self,
+        arids: &'a [ARID],
+        body: &'a [u8],
+    ) -> ProvenRequest<'a> {
+        ProvenRequest { method: &self.method, path: &self.path, arids, body }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestLine {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Ok(Self {
+            method: parts.method.to_string(),
+            path: parts.uri.path().to_string(),
+        })
+    }
+}
+
+/// Find the client's address behind a trusted proxy, and refuse clients
+/// outside the allowed addresses before any handler runs.
+async fn filter_clients(
+    State(state): State<ServerState>,
+    ConnectInfo(mut peer): ConnectInfo<Peer>,
+    mut request: Request,
+    next: Next,
+) -> std::result::Result<Response, ServerError> {
+    let config = &state.config;
+    if let Some(client) = forwarded_client(
+        peer.addr.ip(),
+        request.headers(),
+        &config.trusted_proxies,
+    ) {
+        // Handlers see the client, not the proxy
+        peer.forwarded_for = Some(client);
+        request.extensions_mut().insert(ConnectInfo(peer.clone()));
+    }
+    if !is_allowed(peer.ip(), &config.allowed_ips, &config.denied_ips) {
+        return Err(ServerError::Forbidden(
+            "Client address not allowed".to_string(),
+        ));
+    }
+    Ok(next.run(request).await)
+}
+
 /// Handle PUT requests.
 ///
 /// Body format:
 /// Line 1: ur:arid
 /// Line 2: ur:envelope
 /// Line 3 (optional): TTL in seconds
+///
+/// An optional `Hubert-Tag` header labels the entry for admin queries.
+///
+/// Returns the put's receipt as JSON, whose `expires_at` shows whether the
+/// requested TTL was shortened.
+#[utoipa::path(
+    post,
+    path = "/put",
+    params(
+        ("Hubert-Tag" = Option<String>, Header,
+            description = "Opaque label of at most 64 bytes, such as an \
+                application or test run name, for admin queries")
+    ),
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "Line 1: ur:arid. Line 2: ur:envelope. \
+            Line 3 (optional): TTL in seconds."
+    ),
+    responses(
+        (status = 200, description = "Stored, with the expiry the server \
+            applied", body = ReceiptStatus),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate, or the server is read-only",
+            body = String, content_type = "text/plain"),
+        (status = 409, description = "ARID already exists", body = String,
+            content_type = "text/plain"),
+        (status = 429, description = "Client put quota exceeded",
+            body = String, content_type = "text/plain"),
+        (status = 503, description = "Server is draining for maintenance",
+            body = String, content_type = "text/plain"),
+        (status = 413, description = "Request body too large")
+    )
+)]
 async fn handle_put(
     State(state): State<ServerState>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
     body: Bytes,
 ) -> std::result::Result<impl IntoResponse, ServerError> {
+    let receipt = store_request(&state, &peer, &headers, &line, &body, None)?;
+    Ok((StatusCode::OK, serde_json::to_string(&receipt).unwrap()))
+}
+
+/// Store the envelope in a `/put` or `/commit` request body, filling the
+/// reservation with secret `reservation` if given. Returns the put's
+/// receipt.
+fn store_request(
+    state: &ServerState,
+    peer: &Peer,
+    headers: &HeaderMap,
+    line: &RequestLine,
+    body: &Bytes,
+    reservation: Option<&str>,
+) -> std::result::Result<ReceiptStatus, ServerError> {
     // Register tags for UR parsing
     bc_components::register_tags();
 
+    state.check_writable()?;
+
     let body_str = String::from_utf8(body.to_vec())
         .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
 
-    let lines: Vec<&str> = body_str.lines().collect();
-    if lines.len() < 2 {
-        return Err(ServerError::BadRequest(
-            "Expected at least 2 lines: ur:arid and ur:envelope".to_string(),
-        ));
-    }
-
-    // Parse ARID
-    let arid = ARID::from_ur_string(lines[0])
-        .map_err(|_| ServerError::BadRequest("Invalid ur:arid".to_string()))?;
-
-    // Parse Envelope
-    let envelope = Envelope::from_ur_string(lines[1]).map_err(|_| {
-        ServerError::BadRequest("Invalid ur:envelope".to_string())
-    })?;
-
-    // Parse optional TTL
-    let ttl = if lines.len() > 2 {
-        let seconds: u64 = lines[2]
-            .parse()
-            .map_err(|_| ServerError::BadRequest("Invalid TTL".to_string()))?;
-        Some(Duration::from_secs(seconds))
-    } else {
-        None
-    };
+    let PutBody { arid, envelope, ttl } =
+        PutBody::decode(&body_str).map_err(bad_body)?;
+    let proven = line.proven(std::slice::from_ref(&arid), body);
+    state.authorize(peer, headers, &proven, Right::Put)?;
+    let requested_ttl = ttl;
+    // An envelope's own expiry stands in for a TTL the client left out
+    let ttl = expiry::ttl_for(&envelope, ttl)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?
+        .map(Duration::from_secs);
 
-    // Store the envelope
-    state
-        .put(arid, envelope, ttl, Some(addr))
-        .map_err(ServerError::Conflict)?;
+    let tag = parse_tag(headers)?;
 
-    Ok((StatusCode::OK, "OK"))
+    // Store the envelope, charging the quota for the reservation instead
+    let puts = u64::from(reservation.is_none());
+    let digest = envelope.digest();
+    let result = state.charge_puts(peer, puts, || {
+        state.put(arid, envelope, ttl, tag, reservation, Some(peer))
+    });
+    state.conformance.observe_put(
+        &arid,
+        &digest,
+        requested_ttl,
+        result.as_ref().ok(),
+    );
+    result
 }
 
-/// Handle GET requests.
+/// Handle UPDATE requests, which replace the envelope at an ARID on a
+/// server whose `mutability` is `updatable`.
 ///
-/// Body format:
-/// Line 1: ur:arid
-async fn handle_get(
+/// Body format: as for `/put`. An ARID with no live entry is stored as by
+/// a put, labeled by the optional `Hubert-Tag` header; a replaced entry
+/// keeps its tag.
+///
+/// Returns the update's receipt as JSON, and the entry's new version, 1 for
+/// a new entry, in the `Hubert-Version` header.
+#[utoipa::path(
+    post,
+    path = "/update",
+    params(
+        ("Hubert-Tag" = Option<String>, Header,
+            description = "Opaque label of at most 64 bytes, for admin \
+                queries, applied if the entry is new")
+    ),
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "Line 1: ur:arid. Line 2: ur:envelope. \
+            Line 3 (optional): TTL in seconds."
+    ),
+    responses(
+        (status = 200, description = "Stored, with the expiry the server \
+            applied, and the version in the Hubert-Version header",
+            body = ReceiptStatus),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate, or the server is read-only",
+            body = String, content_type = "text/plain"),
+        (status = 409, description = "ARID is tombstoned or reserved",
+            body = String, content_type = "text/plain"),
+        (status = 429, description = "Client put quota exceeded",
+            body = String, content_type = "text/plain"),
+        (status = 501, description = "Server does not accept updates",
+            body = String, content_type = "text/plain"),
+        (status = 503, description = "Server is draining for maintenance",
+            body = String, content_type = "text/plain"),
+        (status = 413, description = "Request body too large")
+    )
+)]
+async fn handle_update(
     State(state): State<ServerState>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
     body: Bytes,
 ) -> std::result::Result<impl IntoResponse, ServerError> {
     // Register tags for UR parsing
     bc_components::register_tags();
 
+    state.check_writable()?;
+
     let body_str = String::from_utf8(body.to_vec())
         .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
 
-    let arid_str = body_str.trim();
-    if arid_str.is_empty() {
-        return Err(ServerError::BadRequest("Expected ur:arid".to_string()));
-    }
-
-    // Parse ARID
-    let arid = ARID::from_ur_string(arid_str)
-        .map_err(|_| ServerError::BadRequest("Invalid ur:arid".to_string()))?;
+    let PutBody { arid, envelope, ttl } =
+        PutBody::decode(&body_str).map_err(bad_body)?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Put)?;
+    let ttl = expiry::ttl_for(&envelope, ttl)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?
+        .map(Duration::from_secs);
+    let tag = parse_tag(&headers)?;
 
-    // Retrieve the envelope
-    match state.get(&arid, Some(addr)) {
-        Some(envelope) => Ok((StatusCode::OK, envelope.ur_string())),
-        None => Err(ServerError::NotFound),
+    let (receipt, version) = state.charge_puts(&peer, 1, || {
+        state.update(arid, envelope, ttl, tag, &peer)
+    })?;
+    Ok((
+        StatusCode::OK,
+        [(VERSION_HEADER, version.to_string())],
+        serde_json::to_string(&receipt).unwrap(),
+    ))
+}
+
+/// Handle batched PUT requests, which store every entry or none.
+///
+/// Body format:
+/// Line 1 (optional): TTL in seconds, shared by every entry
+/// Following lines: ur:arid, a space, and ur:envelope, at most
+/// `MAX_PUT_MANY_ENTRIES` lines
+///
+/// Without a TTL, each entry is kept until its envelope's expiry, if it has
+/// one. An optional `Hubert-Tag` header labels every entry. Returns the
+/// puts' receipts as a JSON array, in the order of the entries.
+#[utoipa::path(
+    post,
+    path = "/put_many",
+    params(
+        ("Hubert-Tag" = Option<String>, Header,
+            description = "Opaque label of at most 64 bytes, for admin \
+                queries")
+    ),
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "Line 1 (optional): TTL in seconds. Then one \
+            `ur:arid ur:envelope` line per entry, at most 100 entries."
+    ),
+    responses(
+        (status = 200, description = "All stored, with the expiry the \
+            server applied to each", body = Vec<ReceiptStatus>),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate for some ARID, or the server is read-only",
+            body = String, content_type = "text/plain"),
+        (status = 409, description = "Some ARID already exists or appears \
+            twice; nothing was stored", body = String,
+            content_type = "text/plain"),
+        (status = 429, description = "Client put quota exceeded",
+            body = String, content_type = "text/plain"),
+        (status = 503, description = "Server is draining for maintenance",
+            body = String, content_type = "text/plain"),
+        (status = 413, description = "Request body too large")
+    )
+)]
+async fn handle_put_many(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    state.check_writable()?;
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+
+    let PutManyBody { entries, ttl } =
+        PutManyBody::decode(&body_str).map_err(bad_body)?;
+    if entries.len() > MAX_PUT_MANY_ENTRIES {
+        return Err(ServerError::BadRequest(format!(
+            "Expected at most {} entries",
+            MAX_PUT_MANY_ENTRIES
+        )));
+    }
+    let arids: Vec<ARID> = entries.iter().map(|(arid, _)| *arid).collect();
+    let proven = line.proven(&arids, &body);
+    state.authorize(&peer, &headers, &proven, Right::Put)?;
+    let tag = parse_tag(&headers)?;
+    // Each envelope's own expiry stands in for a TTL the client left out
+    let ttls = entries
+        .iter()
+        .map(|(_, envelope)| {
+            expiry::ttl_for(envelope, ttl)
+                .map(|ttl| ttl.map(Duration::from_secs))
+                .map_err(|e| ServerError::BadRequest(e.to_string()))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let result = state.charge_puts(&peer, entries.len() as u64, || {
+        state.put_many(&entries, &ttls, tag, Some(&peer))
+    });
+    for (index, (arid, envelope)) in entries.iter().enumerate() {
+        let receipt = result.as_ref().ok().map(|receipts| &receipts[index]);
+        state
+            .conformance
+            .observe_put(arid, &envelope.digest(), ttl, receipt);
+    }
+    let receipts = result?;
+    Ok((StatusCode::OK, serde_json::to_string(&receipts).unwrap()))
+}
+
+/// Handle reservation requests.
+///
+/// Body format:
+/// Line 1: ur:arid
+/// Line 2 (optional): TTL in seconds
+///
+/// Returns the reservation as JSON, with the secret that `/commit` needs.
+/// Until it is committed or lapses, puts to the ARID receive 409.
+#[utoipa::path(
+    post,
+    path = "/reserve",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "Line 1: ur:arid. Line 2 (optional): TTL of the \
+            reservation in seconds."
+    ),
+    responses(
+        (status = 200, description = "Reserved", body = Reservation),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate, or the server is read-only",
+            body = String, content_type = "text/plain"),
+        (status = 409, description = "ARID already stored or reserved",
+            body = String, content_type = "text/plain"),
+        (status = 429, description = "Client put quota exceeded",
+            body = String, content_type = "text/plain"),
+        (status = 503, description = "Server is draining for maintenance",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_reserve(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    state.check_writable()?;
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+    let ReserveBody { arid, ttl } =
+        ReserveBody::decode(&body_str).map_err(bad_body)?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Put)?;
+    let ttl = ttl.map(Duration::from_secs);
+
+    let reservation = state
+        .charge_puts(&peer, 1, || state.reserve(&arid, ttl, Some(&peer)))?;
+    Ok((StatusCode::OK, serde_json::to_string(&reservation).unwrap()))
+}
+
+/// Handle commit requests, which fill a reservation.
+///
+/// The body is that of `/put`, and the `Hubert-Reservation` header carries
+/// the secret returned by `/reserve`.
+#[utoipa::path(
+    post,
+    path = "/commit",
+    params(
+        ("Hubert-Reservation" = String, Header,
+            description = "Secret of the reservation being filled"),
+        ("Hubert-Tag" = Option<String>, Header,
+            description = "Opaque label of at most 64 bytes, for admin \
+                queries")
+    ),
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "Line 1: ur:arid. Line 2: ur:envelope. \
+            Line 3 (optional): TTL in seconds."
+    ),
+    responses(
+        (status = 200, description = "Stored, with the expiry the server \
+            applied", body = ReceiptStatus),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate, or the server is read-only",
+            body = String, content_type = "text/plain"),
+        (status = 409, description = "ARID already exists", body = String,
+            content_type = "text/plain"),
+        (status = 412, description = "No live reservation of the ARID has \
+            the secret", body = String, content_type = "text/plain"),
+        (status = 503, description = "Server is draining for maintenance",
+            body = String, content_type = "text/plain"),
+        (status = 413, description = "Request body too large")
+    )
+)]
+async fn handle_commit(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    let secret = headers
+        .get(RESERVATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| {
+            ServerError::BadRequest(format!(
+                "Missing {} header",
+                RESERVATION_HEADER
+            ))
+        })?;
+    let receipt =
+        store_request(&state, &peer, &headers, &line, &body, Some(secret))?;
+    Ok((StatusCode::OK, serde_json::to_string(&receipt).unwrap()))
+}
+
+/// A 400 response for a request body that failed to decode.
+fn bad_body(error: super::Error) -> ServerError {
+    match error {
+        super::Error::ParseError(message) => ServerError::BadRequest(message),
+        error => ServerError::BadRequest(error.to_string()),
+    }
+}
+
+/// The put's tag from the `Hubert-Tag` header, if present.
+fn parse_tag(
+    headers: &HeaderMap,
+) -> std::result::Result<Option<&str>, ServerError> {
+    let Some(value) = headers.get(TAG_HEADER) else {
+        return Ok(None);
+    };
+    let tag = value
+        .to_str()
+        .map_err(|_| ServerError::BadRequest("Invalid tag".to_string()))?;
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return Err(ServerError::BadRequest(format!(
+            "Tag must be 1 to {} bytes",
+            MAX_TAG_LENGTH
+        )));
+    }
+    Ok(Some(tag))
+}
+
+/// Query parameters of `/get`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GetParams {
+    /// Seconds to hold the request open until a put stores the envelope,
+    /// at most 20. Without it, the server answers at once.
+    #[serde(default)]
+    wait: u64,
+}
+
+/// Handle GET requests.
+///
+/// Body format:
+/// Line 1: ur:arid
+///
+/// With `?wait=SECONDS`, a request for an ARID with nothing stored is held
+/// open as a long poll, answered as soon as a put stores the envelope.
+///
+/// The response's `ETag` is the stored envelope's digest. A request whose
+/// `If-None-Match` names it receives 304 with no body, so a client that
+/// already holds the envelope can confirm it cheaply.
+#[utoipa::path(
+    post,
+    path = "/get",
+    params(
+        GetParams,
+        ("If-None-Match" = Option<String>, Header,
+            description = "ETag of an envelope the client already holds")
+    ),
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "ur:arid"
+    ),
+    responses(
+        (status = 200, description = "The stored ur:envelope", body = String,
+            content_type = "text/plain",
+            headers(("ETag" = String, description = "Digest of the envelope"))),
+        (status = 304, description = "The stored envelope matches \
+            If-None-Match"),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or certificate",
+            body = String, content_type = "text/plain"),
+        (status = 404, description = "Nothing stored at the ARID, or none \
+            stored while waiting"),
+        (status = 410, description = "The entry expired and its tombstone \
+            is still kept", body = Tombstone)
+    )
+)]
+async fn handle_get(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    Query(params): Query<GetParams>,
+    body: Bytes,
+) -> std::result::Result<Response, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+
+    // Parse ARID
+    let arid = parse_arid_ur(&body_str)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Get)?;
+
+    // Answer before the request timeout cuts the wait short
+    let wait = params
+        .wait
+        .min(MAX_GET_WAIT)
+        .min(state.config.request_timeout.saturating_sub(1));
+    let deadline = Instant::now() + Duration::from_secs(wait);
+
+    loop {
+        // Listen before looking, so a put in between is not missed
+        let mut put = state.storage.waiters().wait(&arid);
+
+        // Retrieve the envelope
+        if let Some(envelope) = state.get(&arid, Some(&peer)) {
+            state.access.record(&arid, true, unix_now());
+            let digest = envelope.digest();
+            state
+                .conformance
+                .observe_get(&arid, Some(&digest), unix_now());
+            let etag = entity_tag(&digest);
+            if matches_if_none_match(&headers, &etag) {
+                return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)])
+                    .into_response());
+            }
+            return Ok((
+                StatusCode::OK,
+                [(header::ETAG, etag)],
+                envelope.ur_string(),
+            )
+                .into_response());
+        }
+        if let Ok(Some(tombstone)) = state.storage.tombstone(&arid) {
+            state.access.record(&arid, false, unix_now());
+            state.conformance.observe_get(&arid, None, unix_now());
+            return Err(ServerError::Gone(
+                serde_json::to_string(&tombstone).unwrap(),
+            ));
+        }
+        if Instant::now() >= deadline {
+            state.access.record(&arid, false, unix_now());
+            state.conformance.observe_get(&arid, None, unix_now());
+            return Err(ServerError::NotFound);
+        }
+        put.until(deadline).await;
+    }
+}
+
+/// Whether the request's `If-None-Match` header names `etag`.
+fn matches_if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// Handle WATCH requests.
+///
+/// Body format:
+/// Line 1: ur:arid
+///
+/// Answers with a stream of server-sent events that stays open. An
+/// `envelope` event carries the stored envelope when the watch begins, or
+/// as soon as a put stores it, and then each new version an update stores.
+/// Once the entry has expired, a `gone` event carries its tombstone and the
+/// stream ends; without tombstones kept, the stream just goes quiet.
+#[utoipa::path(
+    post,
+    path = "/watch",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "ur:arid"
+    ),
+    responses(
+        (status = 200, description = "`envelope` events with the ur:envelope \
+            and its ETag as id, then a `gone` event with the tombstone as \
+            JSON if the entry expires", body = String,
+            content_type = "text/event-stream"),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or certificate",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_watch(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+
+    // Parse ARID
+    let arid = parse_arid_ur(&body_str)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Get)?;
+
+    let events = watch_events(state, peer, arid);
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// The events of a `/watch` stream for `arid`: each version of its envelope
+/// as it is stored, then its tombstone once it expires.
+fn watch_events(
+    state: ServerState,
+    peer: Peer,
+    arid: ARID,
+) -> impl Stream<Item = std::result::Result<Event, Infallible>> {
+    stream::unfold(Some((state, None::<Digest>)), move |watching| {
+        let peer = peer.clone();
+        async move {
+            let (state, last) = watching?;
+            loop {
+                // Listen before looking, so a put in between is not missed
+                let mut put = state.storage.waiters().wait(&arid);
+
+                if let Some(envelope) = state.get(&arid, Some(&peer)) {
+                    let digest = envelope.digest();
+                    if last.as_ref() != Some(&digest) {
+                        state.access.record(&arid, true, unix_now());
+                        let version = WatchEvent::Envelope(envelope);
+                        let event = Event::default()
+                            .id(entity_tag(&digest))
+                            .event(version.name())
+                            .data(version.data());
+                        return Some((Ok(event), Some((state, Some(digest)))));
+                    }
+                } else if let Ok(Some(tombstone)) =
+                    state.storage.tombstone(&arid)
+                {
+                    state.access.record(&arid, false, unix_now());
+                    let gone = WatchEvent::Gone(tombstone);
+                    let event =
+                        Event::default().event(gone.name()).data(gone.data());
+                    return Some((Ok(event), None));
+                }
+                put.until(Instant::now() + WATCH_RECHECK_INTERVAL).await;
+            }
+        }
+    })
+}
+
+/// Handle batched GET requests.
+///
+/// Body format:
+/// One ur:arid per line, at most `MAX_GET_MANY_ARIDS` lines
+///
+/// Returns one line per ARID that holds an envelope: the ur:arid, a space,
+/// and the ur:envelope. ARIDs with nothing stored are left out.
+#[utoipa::path(
+    post,
+    path = "/get_many",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "One ur:arid per line, at most 100 lines"
+    ),
+    responses(
+        (status = 200, description = "One `ur:arid ur:envelope` line per \
+            stored envelope", body = String, content_type = "text/plain"),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate for some ARID", body = String,
+            content_type = "text/plain")
+    )
+)]
+async fn handle_get_many(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+
+    if body_str.lines().count() > MAX_GET_MANY_ARIDS {
+        return Err(ServerError::BadRequest(format!(
+            "Expected at most {} ARIDs",
+            MAX_GET_MANY_ARIDS
+        )));
+    }
+    let GetManyBody { arids } =
+        GetManyBody::decode(&body_str).map_err(bad_body)?;
+    let proven = line.proven(&arids, &body);
+    state.authorize(&peer, &headers, &proven, Right::Get)?;
+
+    let found = arids
+        .iter()
+        .filter_map(|arid| {
+            let envelope = state.get(arid, Some(&peer));
+            state.access.record(arid, envelope.is_some(), unix_now());
+            Some((*arid, envelope?))
+        })
+        .collect();
+    Ok((StatusCode::OK, GetManyResponse { found }.encode()))
+}
+
+/// Handle delete requests, which retract an entry before it expires.
+///
+/// Body format:
+/// Line 1: ur:arid
+///
+/// Knowing the ARID is the proof of ownership, so this needs the same
+/// right as a put. The entry is removed as if it had expired, leaving a
+/// tombstone if they are kept, and its receipt reports it expired.
+#[utoipa::path(
+    post,
+    path = "/delete",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "ur:arid"
+    ),
+    responses(
+        (status = 200, description = "Deleted"),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or \
+            certificate, or the server is read-only",
+            body = String, content_type = "text/plain"),
+        (status = 404, description = "No unexpired entry at the ARID",
+            body = String, content_type = "text/plain"),
+        (status = 503, description = "Server is draining for maintenance",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_delete(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    state.check_writable()?;
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+    let arid = parse_arid_ur(&body_str)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Put)?;
+
+    let removed = state
+        .storage
+        .remove(&arid)
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+    if !removed {
+        return Err(ServerError::NotFound);
+    }
+    state.receipts.retract(&arid, unix_now());
+    Ok(StatusCode::OK)
+}
+
+/// Handle receipt requests.
+///
+/// Body format:
+/// Line 1: ur:arid
+///
+/// Returns JSON describing whether a put to the ARID is still stored, has
+/// expired, or is unknown to the server. Expired receipts are kept for
+/// `receipt_retention` seconds after expiry.
+#[utoipa::path(
+    post,
+    path = "/receipts",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "ur:arid"
+    ),
+    responses(
+        (status = 200, description = "Status of the put", body = ReceiptStatus),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or certificate",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_receipts(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+
+    // Parse ARID
+    let arid = parse_arid_ur(&body_str)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Put)?;
+
+    let status = state.receipts.status(&arid, unix_now());
+    Ok((StatusCode::OK, serde_json::to_string(&status).unwrap()))
+}
+
+/// Handle entry metadata requests.
+///
+/// Body format:
+/// Line 1: ur:arid
+///
+/// Returns the size of the unexpired entry at the ARID, when it was stored,
+/// and when it expires, without the envelope. Needs the same right as a
+/// get, and is not counted as one in the access statistics.
+#[utoipa::path(
+    post,
+    path = "/stat",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "ur:arid"
+    ),
+    responses(
+        (status = 200, description = "The entry's metadata",
+            body = ListedEntry),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Not allowed by capability or certificate",
+            body = String, content_type = "text/plain"),
+        (status = 404, description = "No unexpired entry at the ARID",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_stat(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+    let arid = parse_arid_ur(&body_str)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    let proven = line.proven(std::slice::from_ref(&arid), &body);
+    state.authorize(&peer, &headers, &proven, Right::Get)?;
+
+    let entry = state
+        .storage
+        .entry(&arid)
+        .map_err(|e| ServerError::Internal(e.to_string()))?
+        .ok_or(ServerError::NotFound)?;
+    Ok((StatusCode::OK, serde_json::to_string(&entry).unwrap()))
+}
+
+/// Handle requests to start a conformance run.
+///
+/// Body format:
+/// Line 1: test token, 1 to 64 ASCII letters, digits, `-`, or `_`
+///
+/// Returns the script the client under test follows with ordinary `/put`
+/// and `/get` requests; `/conformance/{token}` reports how it did. Starting
+/// a run with a token already in use replaces that run.
+#[utoipa::path(
+    post,
+    path = "/conformance",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "Test token naming the run"
+    ),
+    responses(
+        (status = 200, description = "The steps to perform",
+            body = ConformanceScript),
+        (status = 400, description = "Malformed token", body = String,
+            content_type = "text/plain")
+    )
+)]
+async fn handle_start_conformance(
+    State(state): State<ServerState>,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR encoding
+    bc_components::register_tags();
+
+    let token = std::str::from_utf8(&body)
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?
+        .trim();
+    if !is_valid_conformance_token(token) {
+        return Err(ServerError::BadRequest(
+            "Token must be 1 to 64 ASCII letters, digits, '-', or '_'"
+                .to_string(),
+        ));
+    }
+
+    let script = state.conformance.start(
+        token,
+        |ttl| state.effective_ttl(Some(Duration::from_secs(ttl))),
+        unix_now(),
+    );
+    Ok((StatusCode::OK, serde_json::to_string(&script).unwrap()))
+}
+
+/// Handle conformance report requests.
+///
+/// Returns which steps of the run named by the token have passed, failed,
+/// or not yet been seen. Runs are forgotten an hour after they start.
+#[utoipa::path(
+    get,
+    path = "/conformance/{token}",
+    params(("token" = String, Path, description = "Test token of the run")),
+    responses(
+        (status = 200, description = "Progress of the run",
+            body = ConformanceReport),
+        (status = 404, description = "No run has the token")
+    )
+)]
+async fn handle_conformance_report(
+    State(state): State<ServerState>,
+    Path(token): Path<String>,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    let report = state
+        .conformance
+        .report(&token)
+        .ok_or(ServerError::NotFound)?;
+    Ok((StatusCode::OK, serde_json::to_string(&report).unwrap()))
+}
+
+/// Handle drain toggle requests.
+///
+/// Body format:
+/// Line 1: `on` or `off`
+///
+/// While draining, puts are refused with 503 and gets are still served, so
+/// in-flight coordinations can finish before the server is stopped.
+#[utoipa::path(
+    post,
+    path = "/admin/drain",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "`on` to start draining, `off` to resume puts"
+    ),
+    responses(
+        (status = 200, description = "Drain mode updated", body = Health),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_drain(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    use crate::logging::verbose_println;
+
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &body))?;
+
+    let draining = match std::str::from_utf8(&body).map(str::trim) {
+        Ok("on") => true,
+        Ok("off") => false,
+        _ => {
+            return Err(ServerError::BadRequest(
+                "Expected `on` or `off`".to_string(),
+            ));
+        }
+    };
+    state.draining.store(draining, Ordering::Relaxed);
+
+    if state.config.verbose {
+        verbose_println(&format!(
+            "{}: DRAIN {}",
+            peer,
+            if draining { "ON" } else { "OFF" }
+        ));
+    }
+
+    Ok(handle_health(State(state)).await)
+}
+
+/// Query parameters of `/admin/list`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListParams {
+    /// Number of entries to skip.
+    #[serde(default)]
+    offset: usize,
+    /// Most entries to return, at most 1000.
+    #[serde(default = "default_list_limit")]
+    limit: usize,
+    /// Also list expired entries that have not been pruned yet.
+    #[serde(default)]
+    include_expired: bool,
+    /// Only list entries put with this tag.
+    tag: Option<String>,
+    /// Only list entries whose ARID's UR starts with this, such as
+    /// `ur:arid/hdcxlk`.
+    prefix: Option<String>,
+}
+
+fn default_list_limit() -> usize { 100 }
+
+/// Handle listing requests.
+///
+/// Returns a JSON array of stored entries in a stable order, for
+/// operational tooling. Page through the store by raising `offset` until
+/// fewer than `limit` entries come back.
+#[utoipa::path(
+    get,
+    path = "/admin/list",
+    params(ListParams),
+    responses(
+        (status = 200, description = "One page of stored entries",
+            body = Vec<ListedEntry>),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain"),
+        (status = 500, description = "Storage failed", body = String,
+            content_type = "text/plain")
+    )
+)]
+async fn handle_list(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    Query(params): Query<ListParams>,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &[]))?;
+
+    if params.limit > MAX_LIST_LIMIT {
+        return Err(ServerError::BadRequest(format!(
+            "Expected a limit of at most {}",
+            MAX_LIST_LIMIT
+        )));
+    }
+    let entries = state
+        .storage
+        .list(
+            params.offset,
+            params.limit,
+            params.include_expired,
+            params.tag.as_deref(),
+            params.prefix.as_deref(),
+        )
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+    Ok((StatusCode::OK, serde_json::to_string(&entries).unwrap()))
+}
+
+/// Query parameters of `/admin/count`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CountParams {
+    /// Only count entries put with this tag.
+    tag: Option<String>,
+}
+
+/// Response body of `/admin/count`.
+#[derive(Serialize, ToSchema)]
+struct Count {
+    /// Number of unexpired entries.
+    count: usize,
+}
+
+/// Handle counting requests.
+///
+/// Returns the number of unexpired entries, optionally only those put with
+/// a given tag.
+#[utoipa::path(
+    get,
+    path = "/admin/count",
+    params(CountParams),
+    responses(
+        (status = 200, description = "Number of entries", body = Count),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain"),
+        (status = 500, description = "Storage failed", body = String,
+            content_type = "text/plain")
+    )
+)]
+async fn handle_count(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    Query(params): Query<CountParams>,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &[]))?;
+
+    let count = state
+        .storage
+        .count(params.tag.as_deref())
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+    Ok((
+        StatusCode::OK,
+        serde_json::to_string(&Count { count }).unwrap(),
+    ))
+}
+
+/// Response body of `/admin/purge`.
+#[derive(Serialize, ToSchema)]
+struct Purged {
+    /// Number of entries removed.
+    purged: usize,
+}
+
+/// Handle purge requests.
+///
+/// Body format:
+/// Line 1: tag
+///
+/// Removes every entry put with the tag, expired or not, so an operator can
+/// clean up after an application or test run without knowing its ARIDs.
+#[utoipa::path(
+    post,
+    path = "/admin/purge",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "The tag whose entries to remove"
+    ),
+    responses(
+        (status = 200, description = "Entries removed", body = Purged),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain"),
+        (status = 500, description = "Storage failed", body = String,
+            content_type = "text/plain")
+    )
+)]
+async fn handle_purge(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    use crate::logging::verbose_println;
+
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &body))?;
+
+    let tag = std::str::from_utf8(&body)
+        .map(str::trim)
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return Err(ServerError::BadRequest(format!(
+            "Tag must be 1 to {} bytes",
+            MAX_TAG_LENGTH
+        )));
+    }
+    let purged = state
+        .storage
+        .purge(tag)
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+
+    if state.config.verbose {
+        verbose_println(&format!(
+            "{}: PURGE [{}] {} entries",
+            peer, tag, purged
+        ));
+    }
+
+    Ok((
+        StatusCode::OK,
+        serde_json::to_string(&Purged { purged }).unwrap(),
+    ))
+}
+
+/// Handle access statistics summary requests.
+///
+/// Returns get totals across every ARID asked for within the receipt
+/// retention window, and how many expired entries no get ever returned.
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    responses(
+        (status = 200, description = "Access totals", body = AccessSummary),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_stats_summary(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &[]))?;
+
+    let expired = state.receipts.expired(unix_now());
+    let summary = state.access.summary(&expired);
+    Ok((StatusCode::OK, serde_json::to_string(&summary).unwrap()))
+}
+
+/// Handle per-ARID access statistics requests.
+///
+/// Returns how often the ARID was asked for, how often a get returned its
+/// envelope, and when. An ARID nobody asked for reports zero gets.
+#[utoipa::path(
+    get,
+    path = "/admin/stats/{arid}",
+    params(("arid" = String, Path, description = "The ur:arid")),
+    responses(
+        (status = 200, description = "Access statistics of the ARID",
+            body = AccessStats),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_stats(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+    Path(arid): Path<String>,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &[]))?;
+
+    let arid = parse_arid_ur(&arid)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+    let stats = state.access.stats(&arid);
+    Ok((StatusCode::OK, serde_json::to_string(&stats).unwrap()))
+}
+
+/// Handle storage statistics requests.
+///
+/// Returns how many entries are stored and the space their envelopes take,
+/// including how much compression saved.
+#[utoipa::path(
+    get,
+    path = "/admin/storage",
+    responses(
+        (status = 200, description = "Storage use", body = StorageStats),
+        (status = 401, description = "Missing or untrusted capability",
+            body = String, content_type = "text/plain"),
+        (status = 403, description = "Client lacks the admin right",
+            body = String, content_type = "text/plain")
+    )
+)]
+async fn handle_storage_stats(
+    State(state): State<ServerState>,
+    ConnectInfo(peer): ConnectInfo<Peer>,
+    headers: HeaderMap,
+    line: RequestLine,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    state.authorize_admin(&peer, &headers, &line.proven(&[], &[]))?;
+
+    let stats = state
+        .storage
+        .storage_stats()
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+    Ok((StatusCode::OK, serde_json::to_string(&stats).unwrap()))
+}
+
+/// Handle anti-entropy requests from peers.
+///
+/// Body format:
+/// Line 1: ur:envelope, a sync request signed by one of the server's
+/// `peers` and encrypted to its `identity`
+///
+/// Returns a replication batch of the unexpired entries the request's
+/// filter covers but does not contain, signed by the server and encrypted
+/// to the peer, as a ur:envelope.
+#[utoipa::path(
+    post,
+    path = "/sync",
+    request_body(
+        content = String,
+        content_type = "text/plain",
+        description = "ur:envelope of a sealed sync request"
+    ),
+    responses(
+        (status = 200, description = "ur:envelope of the sealed batch of \
+            missing entries", body = String, content_type = "text/plain"),
+        (status = 400, description = "Malformed request", body = String,
+            content_type = "text/plain"),
+        (status = 401, description = "Not sealed by a peer, or stale",
+            body = String, content_type = "text/plain"),
+        (status = 501, description = "Server has no peers", body = String,
+            content_type = "text/plain")
+    )
+)]
+async fn handle_sync(
+    State(state): State<ServerState>,
+    body: Bytes,
+) -> std::result::Result<impl IntoResponse, ServerError> {
+    // Register tags for UR parsing
+    bc_components::register_tags();
+
+    let Some(identity) = &state.identity else {
+        return Err(ServerError::NotImplemented(
+            "Server has no peers".to_string(),
+        ));
+    };
+    let body_str = String::from_utf8(body.to_vec())
+        .map_err(|_| ServerError::BadRequest("Invalid UTF-8".to_string()))?;
+    let sealed = parse_envelope_ur(&body_str)
+        .map_err(|e| ServerError::BadRequest(e.to_string()))?;
+
+    let (peer_keys, request) = state
+        .peers
+        .iter()
+        .find_map(|(_, keys)| {
+            let request = SyncRequest::unseal(&sealed, keys, identity).ok()?;
+            Some((keys, request))
+        })
+        .ok_or_else(|| {
+            ServerError::Unauthorized("Not sealed by a peer".to_string())
+        })?;
+    if !state.is_fresh(&request.sent_at) {
+        return Err(ServerError::Unauthorized(
+            "Sync request outside replay window".to_string(),
+        ));
     }
+
+    let entries = state
+        .missing_entries(&request.filter)
+        .map_err(|e| ServerError::Internal(e.to_string()))?;
+    let batch = ReplicationBatch::new(entries).seal(identity, peer_keys);
+    Ok((StatusCode::OK, batch.ur_string()))
 }
 
 /// Server error type for HTTP responses.
 #[derive(Debug)]
 enum ServerError {
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
     Conflict(String),
+    PreconditionFailed(String),
+    TooManyRequests,
     NotFound,
+    Gone(String),
+    NotImplemented(String),
+    Unavailable(String),
+    Internal(String),
 }
 
 impl IntoResponse for ServerError {
@@ -282,12 +2566,40 @@ impl IntoResponse for ServerError {
             ServerError::BadRequest(msg) => {
                 (StatusCode::BAD_REQUEST, msg).into_response()
             }
+            ServerError::Unauthorized(msg) => {
+                (StatusCode::UNAUTHORIZED, msg).into_response()
+            }
+            ServerError::Forbidden(msg) => {
+                (StatusCode::FORBIDDEN, msg).into_response()
+            }
             ServerError::Conflict(msg) => {
                 (StatusCode::CONFLICT, msg).into_response()
             }
+            ServerError::PreconditionFailed(msg) => {
+                (StatusCode::PRECONDITION_FAILED, msg).into_response()
+            }
+            ServerError::TooManyRequests => {
+                (StatusCode::TOO_MANY_REQUESTS, "Put quota exceeded")
+                    .into_response()
+            }
             ServerError::NotFound => {
                 (StatusCode::NOT_FOUND, "Not found").into_response()
             }
+            ServerError::Gone(json) => (
+                StatusCode::GONE,
+                [(axum::http::header::CONTENT_TYPE, "application/json")],
+                json,
+            )
+                .into_response(),
+            ServerError::NotImplemented(msg) => {
+                (StatusCode::NOT_IMPLEMENTED, msg).into_response()
+            }
+            ServerError::Unavailable(msg) => {
+                (StatusCode::SERVICE_UNAVAILABLE, msg).into_response()
+            }
+            ServerError::Internal(msg) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+            }
         }
     }
 }
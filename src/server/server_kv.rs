@@ -1,8 +1,13 @@
+use std::path::Path;
+
 use bc_components::ARID;
 use bc_envelope::Envelope;
 
-use super::{MemoryKv, SqliteKv};
-use crate::KvStore;
+use super::{
+    ListedEntry, MemoryKv, Reservation, SqliteKv, StorageStats, Tombstone,
+    Waiters,
+};
+use crate::{KvStore, Mutability};
 
 /// Server-side key-value storage backend.
 ///
@@ -21,34 +26,239 @@ impl ServerKv {
     /// Create a new SQLite-backed server KV store.
     pub fn sqlite(store: SqliteKv) -> Self { Self::Sqlite(store) }
 
-    /// Synchronously put an envelope into the store.
-    ///
-    /// This method wraps the async KvStore trait implementation.
+    /// Keep tombstones of expired entries for `retention_seconds`; see
+    /// [`MemoryKv::with_tombstones`].
+    pub fn with_tombstones(self, retention_seconds: u64) -> Self {
+        match self {
+            ServerKv::Memory(store) => {
+                ServerKv::Memory(store.with_tombstones(retention_seconds))
+            }
+            ServerKv::Sqlite(store) => {
+                ServerKv::Sqlite(store.with_tombstones(retention_seconds))
+            }
+        }
+    }
+
+    /// Let a put of the envelope already stored at its ARID succeed; see
+    /// [`MemoryKv::with_idempotent_puts`].
+    pub fn with_idempotent_puts(self, enabled: bool) -> Self {
+        match self {
+            ServerKv::Memory(store) => {
+                ServerKv::Memory(store.with_idempotent_puts(enabled))
+            }
+            ServerKv::Sqlite(store) => {
+                ServerKv::Sqlite(store.with_idempotent_puts(enabled))
+            }
+        }
+    }
+
+    /// Let updates replace entries if `mutability` allows; see
+    /// [`MemoryKv::with_mutability`].
+    pub fn with_mutability(self, mutability: Mutability) -> Self {
+        match self {
+            ServerKv::Memory(store) => {
+                ServerKv::Memory(store.with_mutability(mutability))
+            }
+            ServerKv::Sqlite(store) => {
+                ServerKv::Sqlite(store.with_mutability(mutability))
+            }
+        }
+    }
+
+    /// Store large envelopes compressed; see [`SqliteKv::with_compression`].
+    /// In-memory storage is left as it is.
+    pub fn with_compression(self, threshold_bytes: usize) -> Self {
+        match self {
+            ServerKv::Memory(store) => ServerKv::Memory(store),
+            ServerKv::Sqlite(store) => {
+                ServerKv::Sqlite(store.with_compression(threshold_bytes))
+            }
+        }
+    }
+
+    /// Move entries stored for `after_seconds` to files in `dir`; see
+    /// [`SqliteKv::with_cold_storage`]. In-memory storage is left as it is.
+    pub fn with_cold_storage(self, dir: &Path, after_seconds: u64) -> Self {
+        match self {
+            ServerKv::Memory(store) => ServerKv::Memory(store),
+            ServerKv::Sqlite(store) => {
+                ServerKv::Sqlite(store.with_cold_storage(dir, after_seconds))
+            }
+        }
+    }
+
+    /// The registry of gets waiting on the store.
+    pub(super) fn waiters(&self) -> &Waiters {
+        match self {
+            ServerKv::Memory(store) => store.waiters(),
+            ServerKv::Sqlite(store) => store.waiters(),
+        }
+    }
+
+    /// The tombstone left at `arid`, if any.
+    pub(super) fn tombstone(
+        &self,
+        arid: &ARID,
+    ) -> crate::Result<Option<Tombstone>> {
+        match self {
+            ServerKv::Memory(store) => Ok(store.tombstone(arid)),
+            ServerKv::Sqlite(store) => store.tombstone(arid),
+        }
+    }
+
+    /// Synchronously put an envelope into the store, labeled with `tag`,
+    /// filling the reservation with secret `reservation` if given.
     pub(super) fn put_sync(
         &self,
         arid: ARID,
         envelope: Envelope,
         ttl_seconds: u64,
-    ) -> Result<(), String> {
+        tag: Option<&str>,
+        reservation: Option<&str>,
+    ) -> crate::Result<()> {
+        let ttl = Some(ttl_seconds);
+        let result = match (self, reservation) {
+            (ServerKv::Memory(store), None) => {
+                store.put_tagged(&arid, &envelope, ttl, tag, false)
+            }
+            (ServerKv::Memory(store), Some(secret)) => {
+                store.commit(&arid, secret, &envelope, ttl, tag, false)
+            }
+            (ServerKv::Sqlite(store), None) => {
+                store.put_tagged(&arid, &envelope, ttl, tag, false)
+            }
+            (ServerKv::Sqlite(store), Some(secret)) => {
+                store.commit(&arid, secret, &envelope, ttl, tag, false)
+            }
+        };
+        result.map(|_| ())
+    }
+
+    /// Synchronously replace the entry at `arid`, or store it labeled with
+    /// `tag` if there is none; see [`MemoryKv::update_tagged`]. Returns the
+    /// entry's new version.
+    pub(super) fn update_sync(
+        &self,
+        arid: ARID,
+        envelope: Envelope,
+        ttl_seconds: u64,
+        tag: Option<&str>,
+    ) -> crate::Result<u64> {
+        let ttl = Some(ttl_seconds);
+        let receipt = match self {
+            ServerKv::Memory(store) => {
+                store.update_tagged(&arid, &envelope, ttl, tag, false)
+            }
+            ServerKv::Sqlite(store) => {
+                store.update_tagged(&arid, &envelope, ttl, tag, false)
+            }
+        }?;
+        Ok(receipt.sequence.unwrap_or(1))
+    }
+
+    /// Synchronously put several envelopes, all labeled with `tag`, each
+    /// for the TTL at the same index of `ttls`, storing every one or none;
+    /// see [`MemoryKv::put_many_tagged`].
+    pub(super) fn put_many_sync(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttls: &[u64],
+        tag: Option<&str>,
+    ) -> crate::Result<()> {
+        let ttls: Vec<Option<u64>> = ttls.iter().copied().map(Some).collect();
+        let result = match self {
+            ServerKv::Memory(store) => {
+                store.put_many_tagged(entries, &ttls, tag, false)
+            }
+            ServerKv::Sqlite(store) => {
+                store.put_many_tagged(entries, &ttls, tag, false)
+            }
+        };
+        result.map(|_| ())
+    }
+
+    /// Reserve `arid` for `ttl_seconds`; see [`MemoryKv::reserve`].
+    pub(super) fn reserve_sync(
+        &self,
+        arid: &ARID,
+        ttl_seconds: u64,
+    ) -> crate::Result<Reservation> {
         match self {
-            ServerKv::Memory(store) => tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    store
-                        .put(&arid, &envelope, Some(ttl_seconds), false)
-                        .await
-                        .map(|_| ())
-                        .map_err(|e| e.to_string())
-                })
-            }),
-            ServerKv::Sqlite(store) => tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    store
-                        .put(&arid, &envelope, Some(ttl_seconds), false)
-                        .await
-                        .map(|_| ())
-                        .map_err(|e| e.to_string())
+            ServerKv::Memory(store) => store.reserve(arid, ttl_seconds, false),
+            ServerKv::Sqlite(store) => store.reserve(arid, ttl_seconds, false),
+        }
+    }
+
+    /// List stored entries; see [`SqliteKv::list`].
+    pub(super) fn list(
+        &self,
+        offset: usize,
+        limit: usize,
+        include_expired: bool,
+        tag: Option<&str>,
+        prefix: Option<&str>,
+    ) -> crate::Result<Vec<ListedEntry>> {
+        match self {
+            ServerKv::Memory(store) => {
+                Ok(store.list(offset, limit, include_expired, tag, prefix))
+            }
+            ServerKv::Sqlite(store) => {
+                store.list(offset, limit, include_expired, tag, prefix)
+            }
+        }
+    }
+
+    /// The unexpired entry at `arid`; see [`SqliteKv::entry`].
+    pub(super) fn entry(
+        &self,
+        arid: &ARID,
+    ) -> crate::Result<Option<ListedEntry>> {
+        match self {
+            ServerKv::Memory(store) => Ok(store.entry(arid)),
+            ServerKv::Sqlite(store) => store.entry(arid),
+        }
+    }
+
+    /// Count unexpired entries, optionally only those with `tag`.
+    pub(super) fn count(&self, tag: Option<&str>) -> crate::Result<usize> {
+        match self {
+            ServerKv::Memory(store) => Ok(store.count(tag)),
+            ServerKv::Sqlite(store) => store.count(tag),
+        }
+    }
+
+    /// How much space stored envelopes take; see
+    /// [`SqliteKv::storage_stats`].
+    pub(super) fn storage_stats(&self) -> crate::Result<StorageStats> {
+        match self {
+            ServerKv::Memory(store) => {
+                let entries = store.list(0, usize::MAX, true, None, None);
+                Ok(StorageStats {
+                    entries: entries.len(),
+                    stored_bytes: entries
+                        .iter()
+                        .map(|entry| entry.size as u64)
+                        .sum(),
+                    ..Default::default()
                 })
-            }),
+            }
+            ServerKv::Sqlite(store) => store.storage_stats(),
+        }
+    }
+
+    /// Remove the unexpired entry at `arid`, returning whether there was one.
+    pub(super) fn remove(&self, arid: &ARID) -> crate::Result<bool> {
+        match self {
+            ServerKv::Memory(store) => Ok(store.remove(arid)),
+            ServerKv::Sqlite(store) => store.remove(arid),
+        }
+    }
+
+    /// Remove every entry with `tag`, returning the number removed.
+    pub(super) fn purge(&self, tag: &str) -> crate::Result<usize> {
+        match self {
+            ServerKv::Memory(store) => Ok(store.purge(tag)),
+            ServerKv::Sqlite(store) => store.purge(tag),
         }
     }
 
@@ -1,26 +1,75 @@
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
-use bc_components::ARID;
+use bc_components::{ARID, Digest, DigestProvider};
 use bc_envelope::Envelope;
 use bc_ur::prelude::*;
-use rusqlite::{params, Connection, OptionalExtension};
+use futures_util::stream::LocalBoxStream;
+use rusqlite::{
+    Connection, OptionalExtension, Row, ToSql, params, params_from_iter,
+    types::ToSqlOutput,
+};
 use tokio::time::sleep;
 
-use super::Error as ServerError;
-use crate::{Error, KvStore, Result};
+use super::{
+    Error as ServerError, ListedEntry, Reservation, StorageStats, Tombstone,
+    Waiters, clock::unix_now, reservation::secret_digest,
+};
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Mutability, Receipt, Result,
+    StoreCapabilities, expiry,
+    kv_store::{check_distinct, poll_all, poll_watch},
+    receipt::Backend,
+    ur_input::parse_arid_ur,
+};
+
+/// Most ARIDs looked up by one `IN` query, well under SQLite's limit on
+/// bound parameters.
+const LOOKUP_CHUNK: usize = 500;
+
+/// How often a waiting get looks again without being woken, to notice puts
+/// made by other processes sharing the database file.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// SQLite-backed key-value store for Gordian Envelopes.
 ///
 /// Provides persistent storage with TTL support and automatic cleanup of
-/// expired entries.
+/// expired entries. Large envelopes can be stored zstd-compressed, and old
+/// ones moved out of the database; see
+/// [`with_compression`](Self::with_compression) and
+/// [`with_cold_storage`](Self::with_cold_storage).
 #[derive(Clone)]
 pub struct SqliteKv {
     db_path: PathBuf,
     connection: Arc<Mutex<Connection>>,
+    /// Seconds to keep tombstones, or 0 if they are not kept.
+    tombstone_retention: Arc<AtomicU64>,
+    /// Gets waiting for a put, woken as soon as it is stored through this
+    /// store.
+    waiters: Waiters,
+    /// Whether a put of the envelope already stored succeeds.
+    idempotent_puts: bool,
+    /// CBOR size from which envelopes are stored compressed, if at all.
+    compression_threshold: Option<usize>,
+    /// Where and when entries move out of the database, if they do.
+    cold_storage: Arc<Mutex<Option<ColdStorage>>>,
+    /// Whether updates may replace entries.
+    mutability: Mutability,
+}
+
+/// A directory that entries stored for `after` seconds are moved to, each
+/// leaving a stub row naming its file.
+#[derive(Clone)]
+struct ColdStorage {
+    dir: PathBuf,
+    after: u64,
 }
 
 impl SqliteKv {
@@ -51,17 +100,68 @@ impl SqliteKv {
             CREATE TABLE IF NOT EXISTS hubert_store (
                 arid TEXT PRIMARY KEY,
                 envelope TEXT NOT NULL,
-                expires_at INTEGER
+                expires_at INTEGER,
+                tag TEXT,
+                compressed INTEGER NOT NULL DEFAULT 0,
+                size INTEGER,
+                stored_at INTEGER,
+                cold_path TEXT,
+                version INTEGER NOT NULL DEFAULT 1
             );
             CREATE INDEX IF NOT EXISTS idx_expires_at ON hubert_store(expires_at);
+            CREATE TABLE IF NOT EXISTS hubert_tombstones (
+                arid TEXT PRIMARY KEY,
+                expired_at INTEGER NOT NULL,
+                digest TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS hubert_reservations (
+                arid TEXT PRIMARY KEY,
+                secret_digest TEXT NOT NULL,
+                expires_at INTEGER NOT NULL
+            );
         ";
         connection
             .execute_batch(schema)
             .map_err(ServerError::from)?;
 
+        // Databases created by earlier versions lack the later columns
+        add_missing_column(&connection, "tag", "TEXT")?;
+        add_missing_column(
+            &connection,
+            "compressed",
+            "INTEGER NOT NULL DEFAULT 0",
+        )?;
+        add_missing_column(&connection, "size", "INTEGER")?;
+        add_missing_column(&connection, "stored_at", "INTEGER")?;
+        add_missing_column(&connection, "cold_path", "TEXT")?;
+        add_missing_column(
+            &connection,
+            "version",
+            "INTEGER NOT NULL DEFAULT 1",
+        )?;
+        // Entries from before put times were recorded count from now
+        connection
+            .execute(
+                "UPDATE hubert_store SET stored_at = ?1 \
+                 WHERE stored_at IS NULL",
+                params![unix_now() as i64],
+            )
+            .map_err(ServerError::from)?;
+        connection
+            .execute_batch(
+                "CREATE INDEX IF NOT EXISTS idx_tag ON hubert_store(tag)",
+            )
+            .map_err(ServerError::from)?;
+
         let kv = Self {
             db_path,
             connection: Arc::new(Mutex::new(connection)),
+            tombstone_retention: Arc::new(AtomicU64::new(0)),
+            waiters: Waiters::new(),
+            idempotent_puts: false,
+            compression_threshold: None,
+            cold_storage: Arc::new(Mutex::new(None)),
+            mutability: Mutability::WriteOnce,
         };
 
         // Start background cleanup task
@@ -70,53 +170,138 @@ impl SqliteKv {
         Ok(kv)
     }
 
-    /// Start a background task that prunes expired entries every minute.
+    /// Leave a [`Tombstone`] for each entry removed after expiring, kept for
+    /// `retention_seconds`. While it is kept, the ARID cannot be written
+    /// again.
+    pub fn with_tombstones(self, retention_seconds: u64) -> Self {
+        self.tombstone_retention
+            .store(retention_seconds, Ordering::Relaxed);
+        self
+    }
+
+    /// Wake gets waiting in `waiters` on puts to this store, and wake this
+    /// store's waiting gets on puts through others sharing it.
+    pub fn with_waiters(mut self, waiters: Waiters) -> Self {
+        self.waiters = waiters;
+        self
+    }
+
+    /// The registry of gets waiting on this store.
+    pub fn waiters(&self) -> &Waiters {
+        &self.waiters
+    }
+
+    /// Let a put of the same envelope as the live entry at its ARID succeed,
+    /// as with
+    /// [`MemoryKv::with_idempotent_puts`](super::MemoryKv::with_idempotent_puts).
+    pub fn with_idempotent_puts(mut self, enabled: bool) -> Self {
+        self.idempotent_puts = enabled;
+        self
+    }
+
+    /// Let [`KvStore::update`] replace entries, as with
+    /// [`MemoryKv::with_mutability`](super::MemoryKv::with_mutability).
+    /// Each row's `version` column counts its updates.
+    pub fn with_mutability(mut self, mutability: Mutability) -> Self {
+        self.mutability = mutability;
+        self
+    }
+
+    /// Store envelopes whose CBOR encoding is at least `threshold_bytes` as
+    /// zstd-compressed CBOR rather than UR text, when that is smaller.
+    ///
+    /// Each row records whether it is compressed, so entries stored before
+    /// compression was turned on, or after it is turned off, read back the
+    /// same. Versions of hubert before compression was supported cannot
+    /// read compressed entries.
+    pub fn with_compression(mut self, threshold_bytes: usize) -> Self {
+        self.compression_threshold = Some(threshold_bytes);
+        self
+    }
+
+    /// Move entries out of the database into files in `dir` once they have
+    /// been stored for `after_seconds`, leaving stub rows that name the
+    /// files. Gets read them back from there.
+    ///
+    /// For servers with a generous `max_ttl`, this keeps the database small
+    /// while old entries stay available from slower, cheaper storage, such
+    /// as a network filesystem. The background task moves entries every
+    /// minute; [`move_to_cold_storage`](Self::move_to_cold_storage) does so
+    /// at once. `dir` is created when first needed.
+    pub fn with_cold_storage<P: AsRef<Path>>(
+        self,
+        dir: P,
+        after_seconds: u64,
+    ) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        *self.cold_storage.lock().unwrap() =
+            Some(ColdStorage { dir, after: after_seconds });
+        self
+    }
+
+    /// Move the unexpired entries stored longer than the cold storage
+    /// threshold out of the database now. Returns the number moved, which
+    /// is 0 if cold storage is not configured.
+    pub fn move_to_cold_storage(&self) -> Result<usize> {
+        let Some(cold) = self.cold_storage.lock().unwrap().clone() else {
+            return Ok(0);
+        };
+        let conn = self.connection.lock().unwrap();
+        let moved = move_to_cold(&conn, &cold, unix_now() as i64)?;
+        Ok(moved.len())
+    }
+
+    /// Start a background task that prunes expired entries and stale
+    /// tombstones, and moves old entries to cold storage, every minute.
     fn start_cleanup_task(&self) {
         let connection = Arc::clone(&self.connection);
+        let retention = Arc::clone(&self.tombstone_retention);
+        let cold_storage = Arc::clone(&self.cold_storage);
         tokio::spawn(async move {
             loop {
                 sleep(Duration::from_secs(60)).await;
 
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() as i64;
+                let now = unix_now() as i64;
 
                 if let Ok(conn) = connection.lock() {
-                    // First collect the ARIDs that will be deleted
-                    let select_query = "SELECT arid FROM hubert_store WHERE expires_at IS NOT NULL AND expires_at <= ?1";
-                    let arids: Vec<String> = conn
-                        .prepare(select_query)
-                        .and_then(|mut stmt| {
-                            let rows = stmt.query_map(
-                                params![now],
-                                |row| row.get(0),
-                            )?;
-                            Ok(rows.filter_map(|r| r.ok()).collect())
-                        })
+                    let retention = retention.load(Ordering::Relaxed);
+                    let arids = remove_expired(&conn, None, now, retention)
                         .unwrap_or_default();
 
                     if !arids.is_empty() {
-                        // Now delete them
-                        let delete_query = "DELETE FROM hubert_store WHERE expires_at IS NOT NULL AND expires_at <= ?1";
-                        if conn
-                            .execute(delete_query, params![now])
-                            .is_ok()
-                        {
-                            use crate::logging::verbose_println;
-                            let count = arids.len();
-                            let arid_list = arids.join(" ");
-                            verbose_println(&format!(
-                                "Pruned {} expired {}: {}",
-                                count,
-                                if count == 1 {
-                                    "entry"
-                                } else {
-                                    "entries"
-                                },
-                                arid_list
-                            ));
-                        }
+                        use crate::logging::verbose_println;
+                        let count = arids.len();
+                        let arid_list = arids.join(" ");
+                        verbose_println(&format!(
+                            "Pruned {} expired {}: {}",
+                            count,
+                            if count == 1 { "entry" } else { "entries" },
+                            arid_list
+                        ));
+                    }
+
+                    let stale_query = "DELETE FROM hubert_tombstones \
+                                       WHERE expired_at + ?1 <= ?2";
+                    conn.execute(stale_query, params![retention as i64, now])
+                        .ok();
+
+                    let lapsed_query = "DELETE FROM hubert_reservations \
+                                        WHERE expires_at <= ?1";
+                    conn.execute(lapsed_query, params![now]).ok();
+
+                    let cold = cold_storage.lock().unwrap().clone();
+                    let moved = cold
+                        .and_then(|cold| move_to_cold(&conn, &cold, now).ok())
+                        .unwrap_or_default();
+                    if !moved.is_empty() {
+                        use crate::logging::verbose_println;
+                        let count = moved.len();
+                        verbose_println(&format!(
+                            "Moved {} {} to cold storage: {}",
+                            count,
+                            if count == 1 { "entry" } else { "entries" },
+                            moved.join(" ")
+                        ));
                     }
                 }
             }
@@ -126,10 +311,7 @@ impl SqliteKv {
     /// Check if an ARID exists and is not expired.
     fn check_exists(&self, arid: &ARID) -> Result<bool> {
         let arid_str = arid.ur_string();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(ServerError::from)?
-            .as_secs() as i64;
+        let now = unix_now() as i64;
 
         let conn = self.connection.lock().unwrap();
         let query =
@@ -145,10 +327,9 @@ impl SqliteKv {
                 if let Some(expiry) = expires_at {
                     if now >= expiry {
                         // Entry is expired, remove it
-                        let delete_query =
-                            "DELETE FROM hubert_store \
-                             WHERE arid = ?1";
-                        conn.execute(delete_query, params![arid_str])
+                        let retention =
+                            self.tombstone_retention.load(Ordering::Relaxed);
+                        remove_expired(&conn, Some(&arid_str), now, retention)
                             .map_err(ServerError::from)?;
                         Ok(false)
                     } else {
@@ -161,21 +342,127 @@ impl SqliteKv {
             None => Ok(false),
         }
     }
-}
 
-#[async_trait::async_trait(?Send)]
-impl KvStore for SqliteKv {
-    async fn put(
+    /// Store an envelope like [`KvStore::put`], labeled with an opaque `tag`
+    /// that [`list`](Self::list), [`count`](Self::count), and
+    /// [`purge`](Self::purge) can select by.
+    pub fn put_tagged(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.store(arid, envelope, ttl_seconds, tag, None, verbose)
+    }
+
+    /// Reserve `arid` for `ttl_seconds`; see
+    /// [`MemoryKv::reserve`](super::MemoryKv::reserve). Reservations survive
+    /// restarts.
+    pub fn reserve(
+        &self,
+        arid: &ARID,
+        ttl_seconds: u64,
+        verbose: bool,
+    ) -> Result<Reservation> {
+        use crate::logging::verbose_println;
+
+        let mut reservation = None;
+        if !self.check_exists(arid)? && self.tombstone(arid)?.is_none() {
+            let now = unix_now();
+            let candidate =
+                Reservation::new(*arid, now.saturating_add(ttl_seconds));
+            let arid_str = arid.ur_string();
+
+            let conn = self.connection.lock().unwrap();
+            conn.execute(
+                "DELETE FROM hubert_reservations \
+                 WHERE arid = ?1 AND expires_at <= ?2",
+                params![arid_str, now as i64],
+            )
+            .map_err(ServerError::from)?;
+            let inserted = conn
+                .execute(
+                    "INSERT OR IGNORE INTO hubert_reservations \
+                     (arid, secret_digest, expires_at) VALUES (?1, ?2, ?3)",
+                    params![
+                        arid_str,
+                        secret_digest(&candidate.secret).ur_string(),
+                        candidate.expires_at as i64
+                    ],
+                )
+                .map_err(ServerError::from)?;
+            if inserted == 1 {
+                reservation = Some(candidate);
+            }
+        }
+
+        if verbose {
+            let status = if reservation.is_some() {
+                "OK"
+            } else {
+                "ALREADY_EXISTS"
+            };
+            verbose_println(&format!(
+                "RESERVE {} (TTL {}s) {}",
+                arid.ur_string(),
+                ttl_seconds,
+                status
+            ));
+        }
+        reservation.ok_or_else(|| Error::AlreadyExists {
+            arid: arid.ur_string(),
+        })
+    }
+
+    /// Store an envelope at an ARID reserved with [`reserve`](Self::reserve),
+    /// presenting the reservation's secret; see
+    /// [`MemoryKv::commit`](super::MemoryKv::commit).
+    pub fn commit(
+        &self,
+        arid: &ARID,
+        secret: &str,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.store(arid, envelope, ttl_seconds, tag, Some(secret), verbose)
+    }
+
+    /// Store an envelope, filling the reservation with `secret` if given.
+    fn store(
         &self,
         arid: &ARID,
         envelope: &Envelope,
         ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        secret: Option<&str>,
         verbose: bool,
-    ) -> Result<String> {
+    ) -> Result<Receipt> {
         use crate::logging::verbose_println;
 
-        // Check if already exists
-        if self.check_exists(arid)? {
+        let exists = self.check_exists(arid)?;
+
+        // A retry of the put that stored the entry changes nothing
+        if exists
+            && self.idempotent_puts
+            && self.stored_digest(arid)? == Some(envelope.digest())
+        {
+            if verbose {
+                verbose_println(&format!(
+                    "PUT {} IDENTICAL (SQLite: {})",
+                    arid.ur_string(),
+                    self.db_path.display()
+                ));
+            }
+            let size = envelope.to_cbor_data().len();
+            return Ok(self.receipt(arid, size, None).already_stored());
+        }
+
+        // Check if already exists, or did until it expired
+        if exists || self.tombstone(arid)?.is_some() {
             if verbose {
                 verbose_println(&format!(
                     "PUT {} ALREADY_EXISTS",
@@ -188,25 +475,79 @@ impl KvStore for SqliteKv {
         }
 
         let arid_str = arid.ur_string();
-        let envelope_str = envelope.ur_string();
-
-        let expires_at = ttl_seconds.map(|ttl| {
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs()
-                .saturating_add(ttl) as i64
-        });
+        let (stored, size) =
+            StoredEnvelope::encode(envelope, self.compression_threshold)?;
+
+        let now = unix_now();
+        let expires_at =
+            ttl_seconds.map(|ttl| now.saturating_add(ttl) as i64);
 
         let conn = self.connection.lock().unwrap();
+
+        // A reserved ARID takes only its reserver's envelope
+        if let Some(secret) = secret {
+            let taken = conn
+                .execute(
+                    "DELETE FROM hubert_reservations WHERE arid = ?1 \
+                     AND secret_digest = ?2 AND expires_at > ?3",
+                    params![
+                        arid_str,
+                        secret_digest(secret).ur_string(),
+                        now as i64
+                    ],
+                )
+                .map_err(ServerError::from)?;
+            if taken == 0 {
+                drop(conn);
+                if verbose {
+                    verbose_println(&format!(
+                        "COMMIT {} NOT_RESERVED",
+                        arid_str
+                    ));
+                }
+                return Err(ServerError::NotReserved { arid: arid_str }.into());
+            }
+        } else {
+            let reserved = conn
+                .query_row(
+                    "SELECT 1 FROM hubert_reservations \
+                     WHERE arid = ?1 AND expires_at > ?2",
+                    params![arid_str, now as i64],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(ServerError::from)?
+                .is_some();
+            if reserved {
+                drop(conn);
+                if verbose {
+                    verbose_println(&format!(
+                        "PUT {} ALREADY_EXISTS",
+                        arid_str
+                    ));
+                }
+                return Err(Error::AlreadyExists { arid: arid_str });
+            }
+        }
+
         let query = "INSERT INTO hubert_store \
-                     (arid, envelope, expires_at) \
-                     VALUES (?1, ?2, ?3)";
+                     (arid, envelope, expires_at, tag, compressed, size, \
+                     stored_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
         conn.execute(
             query,
-            params![arid_str, envelope_str, expires_at],
+            params![
+                arid_str,
+                stored,
+                expires_at,
+                tag,
+                stored.is_compressed(),
+                size as i64,
+                now as i64
+            ],
         )
         .map_err(ServerError::from)?;
+        drop(conn);
+        self.waiters.wake(arid);
 
         if verbose {
             let ttl_msg = ttl_seconds
@@ -220,7 +561,728 @@ impl KvStore for SqliteKv {
             ));
         }
 
-        Ok(format!("Stored in SQLite: {}", self.db_path.display()))
+        Ok(self.receipt(arid, size, ttl_seconds.map(|ttl| (ttl, now))))
+    }
+
+    /// Replace the live entry at `arid` like [`KvStore::update`], or store
+    /// `envelope` labeled with `tag` as a put would if there is none; see
+    /// [`MemoryKv::update_tagged`](super::MemoryKv::update_tagged).
+    pub fn update_tagged(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        use crate::logging::verbose_println;
+
+        self.mutability.check_update()?;
+
+        let arid_str = arid.ur_string();
+        let (stored, size) =
+            StoredEnvelope::encode(envelope, self.compression_threshold)?;
+        let now = unix_now();
+        let expires_at =
+            ttl_seconds.map(|ttl| now.saturating_add(ttl) as i64);
+
+        let conn = self.connection.lock().unwrap();
+        let query = "SELECT cold_path, version FROM hubert_store \
+                     WHERE arid = ?1 \
+                     AND (expires_at IS NULL OR expires_at > ?2)";
+        let replaced: Option<(Option<String>, i64)> = conn
+            .query_row(
+                query,
+                params![arid_str, now as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(ServerError::from)?;
+        let Some((cold_path, version)) = replaced else {
+            drop(conn);
+            let receipt =
+                self.store(arid, envelope, ttl_seconds, tag, None, verbose)?;
+            return Ok(receipt.with_sequence(1));
+        };
+        let version = version + 1;
+        let query = "UPDATE hubert_store SET envelope = ?3, expires_at = ?4, \
+                     compressed = ?5, size = ?6, stored_at = ?2, \
+                     cold_path = NULL, version = ?7 WHERE arid = ?1";
+        conn.execute(
+            query,
+            params![
+                arid_str,
+                now as i64,
+                stored,
+                expires_at,
+                stored.is_compressed(),
+                size as i64,
+                version
+            ],
+        )
+        .map_err(ServerError::from)?;
+        drop(conn);
+        // The replaced envelope's cold storage file is no longer named
+        if let Some(path) = cold_path {
+            std::fs::remove_file(path).ok();
+        }
+        self.waiters.wake(arid);
+
+        if verbose {
+            verbose_println(&format!(
+                "UPDATE {} to version {} OK (SQLite: {})",
+                arid_str,
+                version,
+                self.db_path.display()
+            ));
+        }
+
+        let ttl = ttl_seconds.map(|ttl| (ttl, now));
+        Ok(self.receipt(arid, size, ttl).with_sequence(version as u64))
+    }
+
+    /// The receipt for a put of `size` bytes at `arid`, lasting `ttl`
+    /// seconds from `now` if given as `(ttl, now)`.
+    fn receipt(
+        &self,
+        arid: &ARID,
+        size: usize,
+        ttl: Option<(u64, u64)>,
+    ) -> Receipt {
+        let location = self.db_path.display().to_string();
+        let receipt = Receipt::new(Backend::Sqlite, *arid, location, size);
+        match ttl {
+            Some((ttl, now)) => receipt.with_ttl(ttl, now.saturating_add(ttl)),
+            None => receipt,
+        }
+    }
+
+    /// Store several envelopes like [`KvStore::put_many`], all labeled with
+    /// `tag`, each with the TTL at the same index of `ttls`, in one
+    /// transaction: either all entries are stored or, if any ARID is taken,
+    /// none are.
+    pub fn put_many_tagged(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttls: &[Option<u64>],
+        tag: Option<&str>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        use crate::logging::verbose_println;
+
+        check_distinct(entries)?;
+
+        // Compress before taking the lock
+        let encoded = entries
+            .iter()
+            .map(|(_, envelope)| {
+                StoredEnvelope::encode(envelope, self.compression_threshold)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let now = unix_now();
+        let retention = self.tombstone_retention.load(Ordering::Relaxed);
+
+        let mut conn = self.connection.lock().unwrap();
+        // Dropped without committing on any failure, which rolls it back
+        let tx = conn.transaction().map_err(ServerError::from)?;
+        let mut receipts = Vec::with_capacity(entries.len());
+        let mut fresh = Vec::with_capacity(entries.len());
+        for (((arid, envelope), (stored, size)), &ttl_seconds) in
+            entries.iter().zip(&encoded).zip(ttls)
+        {
+            let arid_str = arid.ur_string();
+            remove_expired(&tx, Some(&arid_str), now as i64, retention)
+                .map_err(ServerError::from)?;
+
+            let existing: Option<StoredEnvelope> = tx
+                .query_row(
+                    "SELECT envelope, compressed, cold_path \
+                     FROM hubert_store WHERE arid = ?1",
+                    params![arid_str],
+                    |row| StoredEnvelope::from_row(row, 0),
+                )
+                .optional()
+                .map_err(ServerError::from)?;
+            let repeat = existing.as_ref().is_some_and(|existing| {
+                self.idempotent_puts
+                    && existing.decode().is_ok_and(|existing| {
+                        existing.digest() == envelope.digest()
+                    })
+            });
+            if repeat {
+                receipts.push(self.receipt(arid, *size, None).already_stored());
+                continue;
+            }
+
+            let tombstoned = retention > 0
+                && tx
+                    .query_row(
+                        "SELECT 1 FROM hubert_tombstones \
+                         WHERE arid = ?1 AND expired_at + ?2 > ?3",
+                        params![arid_str, retention as i64, now as i64],
+                        |_| Ok(()),
+                    )
+                    .optional()
+                    .map_err(ServerError::from)?
+                    .is_some();
+            let reserved = tx
+                .query_row(
+                    "SELECT 1 FROM hubert_reservations \
+                     WHERE arid = ?1 AND expires_at > ?2",
+                    params![arid_str, now as i64],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(ServerError::from)?
+                .is_some();
+            if existing.is_some() || tombstoned || reserved {
+                if verbose {
+                    verbose_println(&format!(
+                        "PUT_MANY {} ALREADY_EXISTS",
+                        arid_str
+                    ));
+                }
+                return Err(Error::AlreadyExists { arid: arid_str });
+            }
+
+            let expires_at =
+                ttl_seconds.map(|ttl| now.saturating_add(ttl) as i64);
+            tx.execute(
+                "INSERT INTO hubert_store \
+                 (arid, envelope, expires_at, tag, compressed, size, \
+                 stored_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    arid_str,
+                    stored,
+                    expires_at,
+                    tag,
+                    stored.is_compressed(),
+                    *size as i64,
+                    now as i64
+                ],
+            )
+            .map_err(ServerError::from)?;
+            let ttl = ttl_seconds.map(|ttl| (ttl, now));
+            receipts.push(self.receipt(arid, *size, ttl));
+            fresh.push(arid);
+        }
+        tx.commit().map_err(ServerError::from)?;
+        drop(conn);
+        for arid in &fresh {
+            self.waiters.wake(arid);
+        }
+
+        if verbose {
+            verbose_println(&format!(
+                "PUT_MANY {} entries, {} new OK (SQLite: {})",
+                entries.len(),
+                fresh.len(),
+                self.db_path.display()
+            ));
+        }
+        Ok(receipts)
+    }
+
+    /// The digest of the envelope stored at `arid`, if any.
+    fn stored_digest(&self, arid: &ARID) -> Result<Option<Digest>> {
+        let conn = self.connection.lock().unwrap();
+        let query = "SELECT envelope, compressed, cold_path \
+                     FROM hubert_store WHERE arid = ?1";
+        let stored: Option<StoredEnvelope> = conn
+            .query_row(query, params![arid.ur_string()], |row| {
+                StoredEnvelope::from_row(row, 0)
+            })
+            .optional()
+            .map_err(ServerError::from)?;
+        Ok(stored
+            .and_then(|stored| stored.decode().ok())
+            .map(|envelope| envelope.digest()))
+    }
+
+    /// The tombstone left at `arid` when its entry expired, if tombstones are
+    /// enabled and it has not outlived the retention window.
+    pub fn tombstone(&self, arid: &ARID) -> Result<Option<Tombstone>> {
+        let retention = self.tombstone_retention.load(Ordering::Relaxed);
+        if retention == 0 {
+            return Ok(None);
+        }
+        let arid_str = arid.ur_string();
+        let now = unix_now() as i64;
+
+        let conn = self.connection.lock().unwrap();
+        // An entry that expired but was never looked at again is buried now
+        remove_expired(&conn, Some(&arid_str), now, retention)
+            .map_err(ServerError::from)?;
+        let query = "SELECT expired_at, digest FROM hubert_tombstones \
+                     WHERE arid = ?1 AND expired_at + ?2 > ?3";
+        let row: Option<(i64, String)> = conn
+            .query_row(
+                query,
+                params![arid_str, retention as i64, now],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(ServerError::from)?;
+
+        match row {
+            Some((expired_at, digest)) => Ok(Some(Tombstone {
+                arid: *arid,
+                expired_at: expired_at.max(0) as u64,
+                digest: Digest::from_ur_string(&digest)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// List stored entries ordered by ARID, skipping the first `offset` and
+    /// returning at most `limit`.
+    ///
+    /// Only entries put with `tag` are listed, if given, and only those
+    /// whose ARID's UR starts with `prefix`, such as `ur:arid/hdcxlk`.
+    /// Expired entries not yet pruned by the background task are included
+    /// only if `include_expired` is set.
+    pub fn list(
+        &self,
+        offset: usize,
+        limit: usize,
+        include_expired: bool,
+        tag: Option<&str>,
+        prefix: Option<&str>,
+    ) -> Result<Vec<ListedEntry>> {
+        let now = unix_now() as i64;
+        let query = "SELECT arid, envelope, compressed, cold_path, \
+                     expires_at, tag, size, stored_at FROM hubert_store \
+                     WHERE (?3 OR expires_at IS NULL OR expires_at > ?4) \
+                     AND (?5 IS NULL OR tag = ?5) \
+                     AND (?6 IS NULL OR substr(arid, 1, length(?6)) = ?6) \
+                     ORDER BY arid LIMIT ?1 OFFSET ?2";
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let offset = i64::try_from(offset).unwrap_or(i64::MAX);
+
+        let conn = self.connection.lock().unwrap();
+        let mut stmt = conn.prepare(query).map_err(ServerError::from)?;
+        let rows = stmt
+            .query_map(
+                params![limit, offset, include_expired, now, tag, prefix],
+                |row| Ok((row.get::<_, String>(0)?, EntryRow::from_row(row)?)),
+            )
+            .map_err(ServerError::from)?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (arid_str, entry) = row.map_err(ServerError::from)?;
+            let arid = parse_arid_ur(&arid_str)?;
+            entries.push(entry.listed(arid, now as u64)?);
+        }
+        Ok(entries)
+    }
+
+    /// The unexpired entry at `arid`, as [`list`](Self::list) would report
+    /// it.
+    pub fn entry(&self, arid: &ARID) -> Result<Option<ListedEntry>> {
+        let now = unix_now() as i64;
+        let query = "SELECT arid, envelope, compressed, cold_path, \
+                     expires_at, tag, size, stored_at FROM hubert_store \
+                     WHERE arid = ?1 \
+                     AND (expires_at IS NULL OR expires_at > ?2)";
+        let row = {
+            let conn = self.connection.lock().unwrap();
+            conn.query_row(query, params![arid.ur_string(), now], |row| {
+                EntryRow::from_row(row)
+            })
+            .optional()
+            .map_err(ServerError::from)?
+        };
+        row.map(|entry| entry.listed(*arid, now as u64)).transpose()
+    }
+
+    /// The number of unexpired entries, only counting those put with `tag`
+    /// if given.
+    pub fn count(&self, tag: Option<&str>) -> Result<usize> {
+        let now = unix_now() as i64;
+        let query = "SELECT count(*) FROM hubert_store \
+                     WHERE (expires_at IS NULL OR expires_at > ?1) \
+                     AND (?2 IS NULL OR tag = ?2)";
+        let conn = self.connection.lock().unwrap();
+        let count: i64 = conn
+            .query_row(query, params![now, tag], |row| row.get(0))
+            .map_err(ServerError::from)?;
+        Ok(count as usize)
+    }
+
+    /// Remove the unexpired entry at `arid` as if it had just expired,
+    /// leaving a tombstone if enabled. Returns whether there was one.
+    pub fn remove(&self, arid: &ARID) -> Result<bool> {
+        let arid_str = arid.ur_string();
+        let now = unix_now() as i64;
+        let retention = self.tombstone_retention.load(Ordering::Relaxed);
+
+        let conn = self.connection.lock().unwrap();
+        let query = "UPDATE hubert_store SET expires_at = ?2 \
+                     WHERE arid = ?1 \
+                     AND (expires_at IS NULL OR expires_at > ?2)";
+        let updated = conn
+            .execute(query, params![arid_str, now])
+            .map_err(ServerError::from)?;
+        remove_expired(&conn, Some(&arid_str), now, retention)
+            .map_err(ServerError::from)?;
+        Ok(updated > 0)
+    }
+
+    /// Remove every entry put with `tag`, expired or not. Returns the number
+    /// removed.
+    pub fn purge(&self, tag: &str) -> Result<usize> {
+        let conn = self.connection.lock().unwrap();
+        let cold_paths: Vec<String> = conn
+            .prepare(
+                "SELECT cold_path FROM hubert_store \
+                 WHERE tag = ?1 AND cold_path IS NOT NULL",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map(params![tag], |row| row.get(0))?
+                    .collect::<rusqlite::Result<_>>()
+            })
+            .map_err(ServerError::from)?;
+        let removed = conn
+            .execute("DELETE FROM hubert_store WHERE tag = ?1", params![tag])
+            .map_err(ServerError::from)?;
+        for path in cold_paths {
+            std::fs::remove_file(path).ok();
+        }
+        Ok(removed)
+    }
+
+    /// How much space the stored envelopes take in the database, and how
+    /// well compression has worked on those stored compressed there.
+    pub fn storage_stats(&self) -> Result<StorageStats> {
+        let query = "SELECT count(*), \
+                     coalesce(sum(length(CAST(envelope AS BLOB))), 0), \
+                     coalesce(sum(compressed AND cold_path IS NULL), 0), \
+                     coalesce(sum(CASE WHEN compressed AND cold_path IS NULL \
+                         THEN size END), 0), \
+                     coalesce(sum(CASE WHEN compressed AND cold_path IS NULL \
+                         THEN length(envelope) END), 0), \
+                     count(cold_path) \
+                     FROM hubert_store";
+        let conn = self.connection.lock().unwrap();
+        let stats = conn
+            .query_row(query, [], |row| {
+                Ok(StorageStats {
+                    entries: row.get::<_, i64>(0)? as usize,
+                    stored_bytes: row.get::<_, i64>(1)? as u64,
+                    compressed_entries: row.get::<_, i64>(2)? as usize,
+                    compressed_original_bytes: row.get::<_, i64>(3)? as u64,
+                    compressed_stored_bytes: row.get::<_, i64>(4)? as u64,
+                    cold_entries: row.get::<_, i64>(5)? as usize,
+                })
+            })
+            .map_err(ServerError::from)?;
+        Ok(stats)
+    }
+
+    /// The unexpired envelopes stored at any of `arids`, found with one
+    /// query per [`LOOKUP_CHUNK`] ARIDs.
+    fn lookup_many(&self, arids: &[ARID]) -> Result<HashMap<ARID, Envelope>> {
+        let now = unix_now() as i64;
+        let by_ur: HashMap<String, ARID> =
+            arids.iter().map(|arid| (arid.ur_string(), *arid)).collect();
+        let keys: Vec<&String> = by_ur.keys().collect();
+
+        let conn = self.connection.lock().unwrap();
+        let mut found = HashMap::new();
+        for chunk in keys.chunks(LOOKUP_CHUNK) {
+            let query = format!(
+                "SELECT arid, envelope, compressed, cold_path \
+                 FROM hubert_store \
+                 WHERE (expires_at IS NULL OR expires_at > {}) \
+                 AND arid IN ({})",
+                now,
+                vec!["?"; chunk.len()].join(", ")
+            );
+            let mut stmt =
+                conn.prepare(&query).map_err(ServerError::from)?;
+            let rows = stmt
+                .query_map(params_from_iter(chunk), |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        StoredEnvelope::from_row(row, 1)?,
+                    ))
+                })
+                .map_err(ServerError::from)?;
+            for row in rows {
+                let (arid_str, stored) = row.map_err(ServerError::from)?;
+                found.insert(by_ur[&arid_str], stored.decode()?);
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Delete the entries that expired by `now`, or only the one at `arid` if
+/// given, first recording tombstones for them if `retention` is nonzero.
+/// Returns the ARIDs removed.
+fn remove_expired(
+    conn: &Connection,
+    arid: Option<&str>,
+    now: i64,
+    retention: u64,
+) -> rusqlite::Result<Vec<String>> {
+    let condition = "expires_at IS NOT NULL AND expires_at <= ?1 \
+                     AND (?2 IS NULL OR arid = ?2)";
+    let expired: Vec<(String, StoredEnvelope, i64)> = conn
+        .prepare(&format!(
+            "SELECT arid, envelope, compressed, cold_path, expires_at \
+             FROM hubert_store WHERE {}",
+            condition
+        ))?
+        .query_map(params![now, arid], |row| {
+            Ok((row.get(0)?, StoredEnvelope::from_row(row, 1)?, row.get(4)?))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if retention > 0 {
+        let insert_query = "INSERT OR REPLACE INTO hubert_tombstones \
+                            (arid, expired_at, digest) VALUES (?1, ?2, ?3)";
+        for (arid_str, stored, expires_at) in &expired {
+            // An envelope that no longer parses leaves no tombstone
+            let Ok(envelope) = stored.decode() else {
+                continue;
+            };
+            conn.execute(
+                insert_query,
+                params![arid_str, expires_at, envelope.digest().ur_string()],
+            )?;
+        }
+    }
+    conn.execute(
+        &format!("DELETE FROM hubert_store WHERE {}", condition),
+        params![now, arid],
+    )?;
+    for (_, stored, _) in &expired {
+        if let StoredEnvelope::Cold { path, .. } = stored {
+            std::fs::remove_file(path).ok();
+        }
+    }
+    Ok(expired.into_iter().map(|(arid, ..)| arid).collect())
+}
+
+/// Move the unexpired entries stored for `cold.after` seconds by `now` into
+/// files in `cold.dir`, leaving stub rows naming the files. Returns the
+/// ARIDs moved.
+fn move_to_cold(
+    conn: &Connection,
+    cold: &ColdStorage,
+    now: i64,
+) -> Result<Vec<String>> {
+    let query = "SELECT arid, CAST(envelope AS BLOB) FROM hubert_store \
+                 WHERE cold_path IS NULL AND stored_at <= ?1 \
+                 AND (expires_at IS NULL OR expires_at > ?2)";
+    let cutoff = now.saturating_sub(cold.after as i64);
+    let due: Vec<(String, Vec<u8>)> = conn
+        .prepare(query)
+        .and_then(|mut stmt| {
+            stmt.query_map(params![cutoff, now], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()
+        })
+        .map_err(ServerError::from)?;
+    if !due.is_empty() {
+        std::fs::create_dir_all(&cold.dir).map_err(ServerError::from)?;
+    }
+
+    let mut moved = Vec::new();
+    for (arid_str, data) in due {
+        let path = cold.dir.join(parse_arid_ur(&arid_str)?.hex());
+        // Written aside and renamed, so no stub names a partial file
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, &data).map_err(ServerError::from)?;
+        std::fs::rename(&partial, &path).map_err(ServerError::from)?;
+        conn.execute(
+            "UPDATE hubert_store SET envelope = '', cold_path = ?2 \
+             WHERE arid = ?1",
+            params![arid_str, path.to_string_lossy()],
+        )
+        .map_err(ServerError::from)?;
+        moved.push(arid_str);
+    }
+    Ok(moved)
+}
+
+/// Add `column` to `hubert_store` with `definition` if it is not there yet.
+fn add_missing_column(
+    conn: &Connection,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM pragma_table_info('hubert_store') \
+             WHERE name = ?1",
+            params![column],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(ServerError::from)?
+        .is_some();
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE hubert_store ADD COLUMN {} {}",
+            column, definition
+        ))
+        .map_err(ServerError::from)?;
+    }
+    Ok(())
+}
+
+/// An envelope as stored in a row's `envelope` column: UR text, or
+/// zstd-compressed CBOR if the row's `compressed` flag is set. Either may
+/// have been moved to the cold storage file named by `cold_path`.
+enum StoredEnvelope {
+    Ur(String),
+    Compressed(Vec<u8>),
+    Cold { path: PathBuf, compressed: bool },
+}
+
+/// The columns of a row that [`SqliteKv::list`] and [`SqliteKv::entry`]
+/// report, read from the second column on.
+struct EntryRow {
+    stored: StoredEnvelope,
+    expires_at: Option<i64>,
+    tag: Option<String>,
+    size: Option<i64>,
+    stored_at: Option<i64>,
+}
+
+impl EntryRow {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            stored: StoredEnvelope::from_row(row, 1)?,
+            expires_at: row.get(4)?,
+            tag: row.get(5)?,
+            size: row.get(6)?,
+            stored_at: row.get(7)?,
+        })
+    }
+
+    fn listed(self, arid: ARID, now: u64) -> Result<ListedEntry> {
+        // Rows stored before sizes were recorded are measured now
+        let size = match self.size {
+            Some(size) => size.max(0) as usize,
+            None => self.stored.decode()?.to_cbor_data().len(),
+        };
+        let entry = ListedEntry::new(
+            arid,
+            self.expires_at.map(|expiry| expiry.max(0) as u64),
+            size,
+            self.tag,
+            now,
+        );
+        Ok(entry.with_stored_at(self.stored_at.map(|at| at.max(0) as u64)))
+    }
+}
+
+impl StoredEnvelope {
+    /// Encode `envelope` for storage, compressed if its CBOR is at least
+    /// `threshold` bytes and compressing makes it smaller. Returns the size
+    /// of the CBOR too.
+    fn encode(
+        envelope: &Envelope,
+        threshold: Option<usize>,
+    ) -> Result<(Self, usize)> {
+        let cbor = envelope.to_cbor_data();
+        if let Some(threshold) = threshold
+            && cbor.len() >= threshold
+        {
+            let compressed = zstd::encode_all(cbor.as_slice(), 0)
+                .map_err(ServerError::from)?;
+            if compressed.len() < cbor.len() {
+                return Ok((Self::Compressed(compressed), cbor.len()));
+            }
+        }
+        Ok((Self::Ur(envelope.ur_string()), cbor.len()))
+    }
+
+    /// Read the envelope in column `index`, flagged by the `compressed`
+    /// column after it, or the stub pointing at it from the `cold_path`
+    /// column after that.
+    fn from_row(row: &Row<'_>, index: usize) -> rusqlite::Result<Self> {
+        let compressed = row.get::<_, bool>(index + 1)?;
+        if let Some(path) = row.get::<_, Option<String>>(index + 2)? {
+            Ok(Self::Cold { path: PathBuf::from(path), compressed })
+        } else if compressed {
+            Ok(Self::Compressed(row.get(index)?))
+        } else {
+            Ok(Self::Ur(row.get(index)?))
+        }
+    }
+
+    fn is_compressed(&self) -> bool {
+        matches!(self, Self::Compressed(_))
+    }
+
+    fn decode(&self) -> Result<Envelope> {
+        match self {
+            Self::Ur(ur) => Ok(Envelope::from_ur_string(ur)?),
+            Self::Compressed(data) => {
+                let cbor = zstd::decode_all(data.as_slice())
+                    .map_err(ServerError::from)?;
+                Ok(Envelope::try_from_cbor_data(cbor)?)
+            }
+            Self::Cold { path, compressed } => {
+                let data = std::fs::read(path).map_err(ServerError::from)?;
+                if *compressed {
+                    Self::Compressed(data).decode()
+                } else {
+                    let ur = String::from_utf8(data).map_err(|_| {
+                        ServerError::General(format!(
+                            "cold storage file {} is not UR text",
+                            path.display()
+                        ))
+                    })?;
+                    Self::Ur(ur).decode()
+                }
+            }
+        }
+    }
+}
+
+impl ToSql for StoredEnvelope {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        match self {
+            Self::Ur(ur) => ur.to_sql(),
+            Self::Compressed(data) => data.to_sql(),
+            // A stub row keeps an empty envelope
+            Self::Cold { .. } => "".to_sql(),
+        }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl KvStore for SqliteKv {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        self.put_tagged(arid, envelope, ttl_seconds, None, verbose)
+    }
+
+    async fn put_many(
+        &self,
+        entries: &[(ARID, Envelope)],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Vec<Receipt>> {
+        let ttls = expiry::ttls_for(entries, ttl_seconds)?;
+        self.put_many_tagged(entries, &ttls, None, verbose)
     }
 
     async fn get(
@@ -229,44 +1291,46 @@ impl KvStore for SqliteKv {
         timeout_seconds: Option<u64>,
         verbose: bool,
     ) -> Result<Option<Envelope>> {
-        use crate::logging::verbose_println;
+        use crate::logging::{PollProgress, verbose_println};
 
         let timeout = timeout_seconds.unwrap_or(30);
         let start = std::time::Instant::now();
-        let mut first_attempt = true;
+        let deadline = start + Duration::from_secs(timeout);
+        let mut progress = PollProgress::new(
+            format!("GET {} (SQLite)", arid.ur_string()),
+            deadline,
+            verbose,
+        );
 
         loop {
+            // Listen before looking, so a put in between is not missed
+            let mut wait = self.waiters.wait(arid);
+
             let arid_str = arid.ur_string();
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map_err(ServerError::from)?
-                .as_secs() as i64;
+            let now = unix_now() as i64;
 
             let result = {
                 let conn = self.connection.lock().unwrap();
-                let query = "SELECT envelope, expires_at \
-                             FROM hubert_store WHERE arid = ?1";
-                let row: Option<(String, Option<i64>)> = conn
+                let query = "SELECT envelope, compressed, cold_path, \
+                             expires_at FROM hubert_store WHERE arid = ?1";
+                let row: Option<(StoredEnvelope, Option<i64>)> = conn
                     .query_row(query, params![arid_str], |row| {
-                        Ok((row.get(0)?, row.get(1)?))
+                        Ok((StoredEnvelope::from_row(row, 0)?, row.get(3)?))
                     })
                     .optional()
                     .map_err(ServerError::from)?;
 
                 match row {
-                    Some((envelope_str, expires_at)) => {
+                    Some((stored, expires_at)) => {
                         // Check if expired
                         if let Some(expiry) = expires_at {
                             if now >= expiry {
                                 Some((None, true)) // expired
                             } else {
-                                Some((
-                                    Some(envelope_str),
-                                    false,
-                                )) // valid
+                                Some((Some(stored), false)) // valid
                             }
                         } else {
-                            Some((Some(envelope_str), false)) // no expiry
+                            Some((Some(stored), false)) // no expiry
                         }
                     }
                     None => None, // not found
@@ -274,10 +1338,9 @@ impl KvStore for SqliteKv {
             };
 
             match result {
-                Some((Some(envelope_str), false)) => {
+                Some((Some(stored), false)) => {
                     // Entry found and not expired
-                    let envelope =
-                        Envelope::from_ur_string(&envelope_str)?;
+                    let envelope = stored.decode()?;
 
                     if verbose {
                         verbose_println(&format!(
@@ -292,14 +1355,10 @@ impl KvStore for SqliteKv {
                 Some((None, true)) => {
                     // Entry is expired, remove it
                     let conn = self.connection.lock().unwrap();
-                    let delete_query =
-                        "DELETE FROM hubert_store \
-                         WHERE arid = ?1";
-                    conn.execute(
-                        delete_query,
-                        params![arid_str],
-                    )
-                    .map_err(ServerError::from)?;
+                    let retention =
+                        self.tombstone_retention.load(Ordering::Relaxed);
+                    remove_expired(&conn, Some(&arid_str), now, retention)
+                        .map_err(ServerError::from)?;
 
                     if verbose {
                         verbose_println(&format!(
@@ -323,20 +1382,9 @@ impl KvStore for SqliteKv {
                         return Ok(None);
                     }
 
-                    if first_attempt && verbose {
-                        verbose_println(&format!(
-                            "Polling for {} (timeout: {}s)",
-                            arid.ur_string(),
-                            timeout
-                        ));
-                        first_attempt = false;
-                    } else if verbose {
-                        print!(".");
-                        use std::io::Write;
-                        std::io::stdout().flush().ok();
-                    }
-
-                    sleep(Duration::from_millis(500)).await;
+                    progress.missed();
+                    let poll_at = std::time::Instant::now() + POLL_INTERVAL;
+                    wait.until(deadline.min(poll_at)).await;
                 }
                 _ => unreachable!(), // Invalid states
             }
@@ -346,4 +1394,75 @@ impl KvStore for SqliteKv {
     async fn exists(&self, arid: &ARID) -> Result<bool> {
         self.check_exists(arid)
     }
+
+    async fn delete(&self, arid: &ARID, _verbose: bool) -> Result<bool> {
+        self.remove(arid)
+    }
+
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let ttl_seconds = expiry::ttl_for(envelope, ttl_seconds)?;
+        self.update_tagged(arid, envelope, ttl_seconds, None, verbose)
+    }
+
+    async fn stat(
+        &self,
+        arid: &ARID,
+        _verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        let location = self.db_path.display().to_string();
+        let now = unix_now();
+        Ok(self.entry(arid)?.map(|entry| {
+            EntryMeta::from_listed(Backend::Sqlite, location, entry, now)
+        }))
+    }
+
+    fn get_all<'a>(
+        &'a self,
+        arids: &'a [ARID],
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<(ARID, Option<Envelope>)>> {
+        poll_all(arids, timeout_seconds, verbose, move |pending| {
+            std::future::ready(self.lookup_many(&pending))
+        })
+    }
+
+    fn watch<'a>(
+        &'a self,
+        arid: &'a ARID,
+        verbose: bool,
+    ) -> LocalBoxStream<'a, Result<Envelope>> {
+        // Each lookup waits on the registry, so a put or update ends it
+        let updates = self.mutability.is_updatable();
+        poll_watch(Duration::ZERO, updates, move |last| {
+            let lookup = move || self.get(arid, Some(0), verbose);
+            self.waiters.next_version(arid, last, Some(POLL_INTERVAL), lookup)
+        })
+    }
+
+    fn capabilities(&self) -> StoreCapabilities {
+        StoreCapabilities {
+            ttl: true,
+            delete: true,
+            update: self.mutability.is_updatable(),
+            stat: true,
+            ..Default::default()
+        }
+    }
+
+    async fn health(&self) -> HealthReport {
+        HealthReport::probe("sqlite", async {
+            let conn = self.connection.lock().unwrap();
+            conn.query_row("SELECT count(*) FROM hubert_store", [], |_| Ok(()))
+                .map_err(ServerError::from)?;
+            Ok(None)
+        })
+        .await
+    }
 }
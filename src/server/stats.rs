@@ -0,0 +1,193 @@
+#[cfg(feature = "server")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+#[cfg(feature = "server")]
+use bc_components::ARID;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+/// Most ARIDs whose gets are tracked at once. Gets of further ARIDs are
+/// not tracked until pruning makes room, which bounds the memory a client
+/// asking for random ARIDs can use.
+#[cfg(feature = "server")]
+const MAX_TRACKED_ARIDS: usize = 100_000;
+
+/// How one ARID has been read, as reported by the server's
+/// `/admin/stats/{arid}` endpoint.
+///
+/// Lets a protocol author see whether a counterparty ever picked up a
+/// message, and how long after it was put. Times are UNIX seconds.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct AccessStats {
+    /// Get requests for the ARID, found or not.
+    pub gets: u64,
+    /// Get requests that returned the envelope.
+    pub found: u64,
+    /// When the ARID was last asked for.
+    pub last_get_at: Option<u64>,
+    /// When a get first returned the envelope.
+    pub first_found_at: Option<u64>,
+}
+
+/// Access counts across every tracked ARID, as reported by the server's
+/// `/admin/stats` endpoint.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct AccessSummary {
+    /// ARIDs asked for within the retention window.
+    pub arids: usize,
+    /// Get requests for them, found or not.
+    pub gets: u64,
+    /// Get requests that returned the envelope.
+    pub found: u64,
+    /// Entries that expired, within the receipt retention window, without
+    /// any get returning them: messages nobody picked up.
+    pub expired_unread: usize,
+}
+
+/// How much space stored envelopes take, as reported by the server's
+/// `/admin/storage` endpoint.
+///
+/// Only SQLite storage compresses envelopes or moves them to cold storage;
+/// in memory, every entry is counted as stored uncompressed.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct StorageStats {
+    /// Entries stored, including expired ones not yet pruned.
+    pub entries: usize,
+    /// Bytes the envelopes in the database take, compressed or not.
+    pub stored_bytes: u64,
+    /// Entries stored zstd-compressed in the database.
+    pub compressed_entries: usize,
+    /// CBOR bytes of the compressed entries' envelopes.
+    pub compressed_original_bytes: u64,
+    /// Bytes the compressed entries' envelopes take.
+    pub compressed_stored_bytes: u64,
+    /// Entries moved out of the database to cold storage, which take no
+    /// space there but a stub row.
+    #[serde(default)]
+    pub cold_entries: usize,
+}
+
+impl StorageStats {
+    /// How many times smaller compression made the envelopes it was applied
+    /// to, or `None` if none were compressed.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.compressed_stored_bytes > 0).then(|| {
+            self.compressed_original_bytes as f64
+                / self.compressed_stored_bytes as f64
+        })
+    }
+}
+
+/// Per-ARID access statistics, kept in memory for `receipt_retention`
+/// seconds after each ARID's last get.
+#[cfg(feature = "server")]
+#[derive(Clone, Default)]
+pub(super) struct AccessLog {
+    stats: Arc<RwLock<HashMap<ARID, AccessStats>>>,
+}
+
+#[cfg(feature = "server")]
+impl AccessLog {
+    pub(super) fn new() -> Self { Self::default() }
+
+    /// Record a get of `arid` at `now`, and whether it was found.
+    pub(super) fn record(&self, arid: &ARID, found: bool, now: u64) {
+        let mut stats = self.stats.write().unwrap();
+        if stats.len() >= MAX_TRACKED_ARIDS && !stats.contains_key(arid) {
+            return;
+        }
+        let entry = stats.entry(*arid).or_default();
+        entry.gets += 1;
+        entry.last_get_at = Some(now);
+        if found {
+            entry.found += 1;
+            entry.first_found_at.get_or_insert(now);
+        }
+    }
+
+    /// The statistics of `arid`, all zero if it has not been asked for.
+    pub(super) fn stats(&self, arid: &ARID) -> AccessStats {
+        self.stats
+            .read()
+            .unwrap()
+            .get(arid)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Totals across every tracked ARID. `expired` lists the entries known
+    /// to have expired, to count those never read.
+    pub(super) fn summary(&self, expired: &[ARID]) -> AccessSummary {
+        let stats = self.stats.read().unwrap();
+        AccessSummary {
+            arids: stats.len(),
+            gets: stats.values().map(|s| s.gets).sum(),
+            found: stats.values().map(|s| s.found).sum(),
+            expired_unread: expired
+                .iter()
+                .filter(|arid| stats.get(arid).is_none_or(|s| s.found == 0))
+                .count(),
+        }
+    }
+
+    /// Forget ARIDs last asked for more than `retention` seconds before
+    /// `now`. Returns the number forgotten.
+    pub(super) fn prune(&self, now: u64, retention: u64) -> usize {
+        let mut stats = self.stats.write().unwrap();
+        let before = stats.len();
+        stats.retain(|_, s| {
+            s.last_get_at
+                .is_some_and(|at| at.saturating_add(retention) > now)
+        });
+        before - stats.len()
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_summarize() {
+        let log = AccessLog::new();
+        let (read, unread, untouched) = (ARID::new(), ARID::new(), ARID::new());
+
+        assert_eq!(log.stats(&read), AccessStats::default());
+        log.record(&read, false, 100);
+        log.record(&read, true, 110);
+        log.record(&read, true, 120);
+        log.record(&unread, false, 130);
+        assert_eq!(
+            log.stats(&read),
+            AccessStats {
+                gets: 3,
+                found: 2,
+                last_get_at: Some(120),
+                first_found_at: Some(110),
+            }
+        );
+
+        let expired = [read, unread, untouched];
+        assert_eq!(
+            log.summary(&expired),
+            AccessSummary { arids: 2, gets: 4, found: 2, expired_unread: 2 }
+        );
+
+        assert_eq!(log.prune(180, 60), 1);
+        assert_eq!(log.stats(&read), AccessStats::default());
+        assert_eq!(log.stats(&unread).gets, 1);
+    }
+}
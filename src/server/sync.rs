@@ -0,0 +1,290 @@
+//! Compact summaries of stored ARIDs for anti-entropy between servers.
+//!
+//! Forwarding misses entries put while a peer was down. To catch up, a peer
+//! summarizes the ARIDs it holds in an [`AridFilter`] and sends it to a
+//! server, which answers with the entries the filter does not contain,
+//! sealed in a [`ReplicationBatch`](super::ReplicationBatch). A Bloom filter
+//! has no false negatives, so every entry the server reports missing really
+//! is missing; false positives only delay an entry to a later pass, whose
+//! filter uses a fresh seed.
+//!
+//! A filter covers only entries expiring after its `expires_after` time, so
+//! entries about to expire are not worth sending and the filter stays sized
+//! to the live set.
+//!
+//! The filter travels in a [`SyncRequest`], sealed like a replication batch:
+//!
+//! ```text
+//! {
+//!     "hubertSync" [
+//!         "sentAt": Date
+//!         "filter": AridFilter
+//!     ]
+//! } [
+//!     'signed': Signature
+//! ]
+//! ```
+//!
+//! and encrypted to the server asked, which answers at `/sync`.
+
+use bc_components::{ARID, PrivateKeys, PublicKeys};
+use bc_crypto::hmac_sha256;
+use bc_envelope::prelude::*;
+use dcbor::Date;
+
+use super::Error as ServerError;
+use crate::Result;
+
+const SUBJECT: &str = "hubertSync";
+const SENT_AT: &str = "sentAt";
+const FILTER: &str = "filter";
+
+/// Largest filter accepted from a peer, in bytes.
+const MAX_FILTER_BYTES: usize = 1 << 22;
+
+/// Most hash functions a filter may use.
+const MAX_HASHES: u32 = 32;
+
+/// A Bloom filter over ARIDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AridFilter {
+    seed: [u8; 32],
+    hashes: u32,
+    bits: Vec<u8>,
+    expires_after: u64,
+}
+
+impl AridFilter {
+    /// An empty filter sized for `expected` ARIDs at the given false positive
+    /// rate, covering entries that expire after `expires_after` (UNIX
+    /// seconds).
+    pub fn new(
+        expected: usize,
+        false_positive_rate: f64,
+        expires_after: u64,
+    ) -> Self {
+        let n = expected.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-n * p.ln() / (ln2 * ln2)).ceil().max(8.0);
+        let bytes = ((bit_count / 8.0).ceil() as usize).min(MAX_FILTER_BYTES);
+        let hashes = ((bytes * 8) as f64 / n * ln2).round() as u32;
+
+        let mut seed = [0u8; 32];
+        bc_rand::fill_random_data(&mut seed);
+        Self {
+            seed,
+            hashes: hashes.clamp(1, MAX_HASHES),
+            bits: vec![0; bytes],
+            expires_after,
+        }
+    }
+
+    /// Entries expiring at or before this time (UNIX seconds) are outside
+    /// the filter.
+    pub fn expires_after(&self) -> u64 { self.expires_after }
+
+    /// Add `arid` to the filter.
+    pub fn insert(&mut self, arid: &ARID) {
+        for bit in self.bit_indexes(arid) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Whether `arid` may have been inserted. Never false for an ARID that
+    /// was.
+    pub fn contains(&self, arid: &ARID) -> bool {
+        self.bit_indexes(arid)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// The ARIDs among `entries` (ARID and expiry time) that the filter
+    /// covers but does not contain: the ones its owner is missing.
+    pub fn missing(
+        &self,
+        entries: impl IntoIterator<Item = (ARID, u64)>,
+    ) -> Vec<ARID> {
+        entries
+            .into_iter()
+            .filter(|(arid, expires_at)| {
+                *expires_at > self.expires_after && !self.contains(arid)
+            })
+            .map(|(arid, _)| arid)
+            .collect()
+    }
+
+    /// Bit positions for `arid`, by double hashing a keyed digest.
+    fn bit_indexes(&self, arid: &ARID) -> impl Iterator<Item = usize> + '_ {
+        let digest = hmac_sha256(self.seed, arid.data());
+        let h1 = u64::from_le_bytes(digest[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap()) | 1;
+        let bit_count = (self.bits.len() * 8) as u64;
+        (0..self.hashes as u64).map(move |i| {
+            (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize
+        })
+    }
+}
+
+impl From<AridFilter> for CBOR {
+    fn from(filter: AridFilter) -> Self {
+        vec![
+            CBOR::to_byte_string(filter.seed),
+            filter.hashes.into(),
+            CBOR::to_byte_string(filter.bits),
+            filter.expires_after.into(),
+        ]
+        .into()
+    }
+}
+
+impl TryFrom<CBOR> for AridFilter {
+    type Error = crate::Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        let invalid = |reason: &str| {
+            ServerError::ParseError(format!("ARID filter: {}", reason))
+        };
+        let fields = cbor.try_into_array()?;
+        let [seed, hashes, bits, expires_after] = <[CBOR; 4]>::try_from(fields)
+            .map_err(|_| invalid("expected 4 fields"))?;
+
+        let seed: [u8; 32] = seed
+            .try_into_byte_string()?
+            .try_into()
+            .map_err(|_| invalid("seed must be 32 bytes"))?;
+        let hashes: u32 = hashes.try_into()?;
+        let bits = bits.try_into_byte_string()?;
+        if !(1..=MAX_HASHES).contains(&hashes) {
+            return Err(invalid("bad hash count").into());
+        }
+        if bits.is_empty() || bits.len() > MAX_FILTER_BYTES {
+            return Err(invalid("bad size").into());
+        }
+        Ok(Self {
+            seed,
+            hashes,
+            bits,
+            expires_after: expires_after.try_into()?,
+        })
+    }
+}
+
+/// A peer's request for the entries it is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncRequest {
+    /// The ARIDs the peer holds.
+    pub filter: AridFilter,
+    /// When the peer sealed the request, so servers can reject stale
+    /// replays.
+    pub sent_at: Date,
+}
+
+impl SyncRequest {
+    /// A request carrying `filter`, timestamped now.
+    pub fn new(filter: AridFilter) -> Self {
+        Self { filter, sent_at: Date::now() }
+    }
+
+    /// Sign the request as `sender` and encrypt it to `recipient`.
+    pub fn seal(
+        &self,
+        sender: &PrivateKeys,
+        recipient: &PublicKeys,
+    ) -> Envelope {
+        Envelope::new(SUBJECT)
+            .add_assertion(SENT_AT, self.sent_at.clone())
+            .add_assertion(FILTER, CBOR::from(self.filter.clone()))
+            .seal(sender, recipient)
+    }
+
+    /// Decrypt a request sealed to `recipient` and verify that `sender`
+    /// signed it.
+    pub fn unseal(
+        sealed: &Envelope,
+        sender: &PublicKeys,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let envelope = sealed.unseal(sender, recipient)?;
+        let invalid = |e: bc_envelope::Error| {
+            ServerError::ParseError(format!("sync request: {}", e))
+        };
+
+        if envelope.extract_subject::<String>().ok().as_deref() != Some(SUBJECT)
+        {
+            return Err(ServerError::ParseError(
+                "not a sync request".to_string(),
+            )
+            .into());
+        }
+        let sent_at = envelope
+            .extract_object_for_predicate(SENT_AT)
+            .map_err(invalid)?;
+        let filter = envelope
+            .object_for_predicate(FILTER)
+            .and_then(|filter| filter.subject().try_leaf())
+            .map_err(invalid)?;
+        Ok(Self { filter: filter.try_into()?, sent_at })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_components::keypair;
+
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let arids: Vec<ARID> = (0..1000).map(|_| ARID::new()).collect();
+        let mut filter = AridFilter::new(arids.len(), 0.01, 0);
+        for arid in &arids {
+            filter.insert(arid);
+        }
+        assert!(arids.iter().all(|arid| filter.contains(arid)));
+
+        let false_positives =
+            (0..1000).filter(|_| filter.contains(&ARID::new())).count();
+        assert!(false_positives < 50, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_missing_respects_expiry_window() {
+        let held = ARID::new();
+        let absent = ARID::new();
+        let expiring = ARID::new();
+        let mut filter = AridFilter::new(10, 0.000_001, 100);
+        filter.insert(&held);
+
+        let missing =
+            filter.missing([(held, 200), (absent, 200), (expiring, 100)]);
+        assert_eq!(missing, vec![absent]);
+    }
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let mut filter = AridFilter::new(10, 0.01, 42);
+        filter.insert(&ARID::new());
+        let cbor: CBOR = filter.clone().into();
+        assert_eq!(AridFilter::try_from(cbor).unwrap(), filter);
+    }
+
+    #[test]
+    fn test_sync_request_seal_unseal() {
+        let (sender_private, sender_public) = keypair();
+        let (server_private, server_public) = keypair();
+        let (other_private, _) = keypair();
+        let mut filter = AridFilter::new(10, 0.01, 42);
+        filter.insert(&ARID::new());
+        let request = SyncRequest::new(filter);
+
+        let sealed = request.seal(&sender_private, &server_public);
+        let unsealed =
+            SyncRequest::unseal(&sealed, &sender_public, &server_private)
+                .unwrap();
+        assert_eq!(unsealed, request);
+        assert!(
+            SyncRequest::unseal(&sealed, &sender_public, &other_private)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Minimal systemd integration: socket activation and readiness notification.
+//!
+//! Implements just enough of the `sd_listen_fds(3)` and `sd_notify(3)`
+//! protocols to run the server from a `.socket` unit and a `Type=notify`
+//! service without a wrapper script or a libsystemd dependency. Both are
+//! no-ops when the relevant environment variables are absent.
+
+use std::io;
+
+/// First file descriptor passed by systemd (`SD_LISTEN_FDS_START`).
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Number of descriptors systemd passed to this process, if any.
+///
+/// `LISTEN_PID` must match our PID; otherwise the variables were inherited
+/// from a parent and are not meant for us.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn listen_fds(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    pid: u32,
+) -> Option<usize> {
+    let listen_pid: u32 = listen_pid?.trim().parse().ok()?;
+    if listen_pid != pid {
+        return None;
+    }
+    let count: usize = listen_fds?.trim().parse().ok()?;
+    (count > 0).then_some(count)
+}
+
+/// Take the TCP listener passed by systemd socket activation, if any.
+///
+/// Only the first passed descriptor is used. Returns `Ok(None)` when the
+/// process was not socket-activated, or when the listener was already taken.
+#[cfg(unix)]
+pub(super) fn take_listener() -> io::Result<Option<std::net::TcpListener>> {
+    use std::{
+        os::fd::FromRawFd,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    // Guards against wrapping the inherited descriptor more than once.
+    static LISTENER_TAKEN: AtomicBool = AtomicBool::new(false);
+
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    if listen_fds(pid.as_deref(), fds.as_deref(), std::process::id()).is_none()
+    {
+        return Ok(None);
+    }
+    if LISTENER_TAKEN.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+
+    // SAFETY: systemd guarantees LISTEN_FDS_START is an open socket owned by
+    // this process, and LISTENER_TAKEN ensures we wrap it only once.
+    let listener =
+        unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+pub(super) fn take_listener() -> io::Result<Option<std::net::TcpListener>> {
+    Ok(None)
+}
+
+/// Send a state string such as `READY=1` to the systemd notification socket.
+///
+/// Returns `Ok(false)` if `NOTIFY_SOCKET` is not set.
+#[cfg(unix)]
+pub(super) fn notify(state: &str) -> io::Result<bool> {
+    use std::os::unix::{ffi::OsStrExt, net::UnixDatagram};
+
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    let socket = UnixDatagram::unbound()?;
+
+    match path.as_bytes().strip_prefix(b"@") {
+        // Abstract namespace socket (Linux only)
+        Some(name) => {
+            #[cfg(target_os = "linux")]
+            {
+                use std::os::{
+                    linux::net::SocketAddrExt, unix::net::SocketAddr,
+                };
+                let addr = SocketAddr::from_abstract_name(name)?;
+                socket.send_to_addr(state.as_bytes(), &addr)?;
+            }
+            #[cfg(not(target_os = "linux"))]
+            {
+                let _ = name;
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "abstract NOTIFY_SOCKET requires Linux",
+                ));
+            }
+        }
+        None => {
+            socket.send_to(state.as_bytes(), &path)?;
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+pub(super) fn notify(_state: &str) -> io::Result<bool> { Ok(false) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds_matching_pid() {
+        assert_eq!(listen_fds(Some("42"), Some("1"), 42), Some(1));
+        assert_eq!(listen_fds(Some("42"), Some("2"), 42), Some(2));
+    }
+
+    #[test]
+    fn test_listen_fds_other_pid() {
+        assert_eq!(listen_fds(Some("41"), Some("1"), 42), None);
+    }
+
+    #[test]
+    fn test_listen_fds_missing_or_invalid() {
+        assert_eq!(listen_fds(None, Some("1"), 42), None);
+        assert_eq!(listen_fds(Some("42"), None, 42), None);
+        assert_eq!(listen_fds(Some("42"), Some("0"), 42), None);
+        assert_eq!(listen_fds(Some("x"), Some("1"), 42), None);
+    }
+}
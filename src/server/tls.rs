@@ -0,0 +1,135 @@
+use std::{fmt::Display, io, net::SocketAddr, path::Path, sync::Arc};
+
+use axum::{
+    extract::connect_info::Connected,
+    serve::{IncomingStream, Listener},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self, RootCertStore,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+        server::WebPkiClientVerifier,
+    },
+    server::TlsStream,
+};
+use x509_parser::prelude::*;
+
+use super::{
+    Error as ServerError, TlsConfig,
+    listener::{LimitedListener, LimitedStream, Peer},
+};
+use crate::Result;
+
+/// Build a TLS acceptor that requires client certificates issued by the
+/// configured CA.
+pub(super) fn acceptor(config: &TlsConfig) -> Result<TlsAcceptor> {
+    fn invalid(path: &Path, e: impl Display) -> ServerError {
+        ServerError::Config(format!("{}: {}", path.display(), e))
+    }
+    fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+        let certs = CertificateDer::pem_file_iter(path)
+            .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+            .map_err(|e| invalid(path, e))?;
+        Ok(certs)
+    }
+
+    let certs = read_certs(&config.cert)?;
+    let key = PrivateKeyDer::from_pem_file(&config.key)
+        .map_err(|e| invalid(&config.key, e))?;
+    let mut roots = RootCertStore::empty();
+    for cert in read_certs(&config.client_ca)? {
+        roots.add(cert).map_err(|e| invalid(&config.client_ca, e))?;
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(
+        Arc::new(roots),
+        provider.clone(),
+    )
+    .build()
+    .map_err(|e| invalid(&config.client_ca, e))?;
+    let server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| {
+            builder
+                .with_client_cert_verifier(verifier)
+                .with_single_cert(certs, key)
+        })
+        .map_err(|e| invalid(&config.cert, e))?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+/// A listener that completes a TLS handshake on each connection accepted by
+/// a [`LimitedListener`].
+///
+/// Handshakes run concurrently so a slow client cannot stall the accept loop.
+/// A handshake holds its connection's permit and is bounded by the idle
+/// timeout; connections that fail it, including clients without a trusted
+/// certificate, are dropped.
+pub(super) struct TlsListener {
+    local_addr: SocketAddr,
+    handshaken: mpsc::Receiver<(TlsStream<LimitedStream>, SocketAddr)>,
+    accept_task: JoinHandle<()>,
+}
+
+impl TlsListener {
+    pub(super) fn new(
+        mut inner: LimitedListener,
+        acceptor: TlsAcceptor,
+    ) -> io::Result<Self> {
+        let local_addr = inner.local_addr()?;
+        let (tx, handshaken) = mpsc::channel(1);
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let (stream, addr) = inner.accept().await;
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        let _ = tx.send((stream, addr)).await;
+                    }
+                });
+            }
+        });
+        Ok(Self { local_addr, handshaken, accept_task })
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) { self.accept_task.abort(); }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<LimitedStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.handshaken.recv().await {
+            Some(connection) => connection,
+            // The accept task runs until we drop it
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> { Ok(self.local_addr) }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for Peer {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        let (_, connection) = stream.io().get_ref();
+        let identity = connection
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .and_then(common_name);
+        Peer::new(*stream.remote_addr(), identity)
+    }
+}
+
+/// The subject common name of a DER-encoded certificate.
+fn common_name(cert: &CertificateDer<'_>) -> Option<String> {
+    let (_, cert) = X509Certificate::from_der(cert.as_ref()).ok()?;
+    let name = cert.subject().iter_common_name().next()?;
+    name.as_str().ok().map(str::to_string)
+}
@@ -0,0 +1,77 @@
+use bc_components::{ARID, Digest, DigestProvider};
+use bc_envelope::Envelope;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use super::listing::ur_string;
+
+/// What a server-side store remembers about an expired entry when
+/// tombstones are enabled.
+///
+/// A get of a tombstoned ARID receives 410 Gone from the server instead of
+/// 404, so a reader can tell "it expired" from "it was never stored", and
+/// the digest lets the putter confirm which envelope it was. A tombstoned
+/// ARID cannot be written again until the tombstone is dropped. Times are
+/// UNIX seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Tombstone {
+    /// Where the entry was stored.
+    #[serde(with = "ur_string")]
+    #[cfg_attr(
+        feature = "server",
+        schema(value_type = String, example = "ur:arid/...")
+    )]
+    pub arid: ARID,
+    /// When the entry expired.
+    pub expired_at: u64,
+    /// Digest of the envelope that was stored.
+    #[serde(with = "ur_string")]
+    #[cfg_attr(
+        feature = "server",
+        schema(value_type = String, example = "ur:digest/...")
+    )]
+    pub digest: Digest,
+}
+
+impl Tombstone {
+    pub(super) fn new(
+        arid: ARID,
+        expired_at: u64,
+        envelope: &Envelope,
+    ) -> Self {
+        Self { arid, expired_at, digest: envelope.digest() }
+    }
+
+    /// Whether the tombstone has outlived `retention` seconds as of `now`.
+    pub(super) fn is_stale(&self, retention: u64, now: u64) -> bool {
+        self.expired_at.saturating_add(retention) <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bc_ur::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_tombstone_json_roundtrip() {
+        bc_components::register_tags();
+
+        let envelope = Envelope::new("Expired");
+        let tombstone = Tombstone::new(ARID::new(), 100, &envelope);
+        assert_eq!(tombstone.digest, envelope.digest());
+
+        let json = serde_json::to_string(&tombstone).unwrap();
+        assert!(json.contains(&tombstone.digest.ur_string()));
+        assert_eq!(
+            serde_json::from_str::<Tombstone>(&json).unwrap(),
+            tombstone
+        );
+
+        assert!(!tombstone.is_stale(60, 159));
+        assert!(tombstone.is_stale(60, 160));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bc_components::{ARID, Digest, DigestProvider};
+use bc_envelope::Envelope;
+use tokio::sync::watch;
+
+use crate::Result;
+
+/// The gets waiting for each ARID, woken when a put stores it.
+///
+/// [`MemoryKv`](super::MemoryKv) and [`SqliteKv`](super::SqliteKv) each
+/// keep one, and the server's `/get` long poll waits on its store's. Stores
+/// given the same registry with `with_waiters` wake each other's waiters,
+/// so a put through any path reaches every get waiting in the process.
+///
+/// Clones share the registry.
+#[derive(Clone, Default)]
+pub struct Waiters {
+    arids: Arc<Mutex<HashMap<ARID, watch::Sender<()>>>>,
+}
+
+impl Waiters {
+    /// Create an empty registry.
+    pub fn new() -> Self { Self::default() }
+
+    /// Start waiting for a put to `arid`.
+    ///
+    /// Call this before looking the ARID up, so a put that lands between
+    /// the lookup and [`Wait::until`] still ends the wait.
+    pub fn wait(&self, arid: &ARID) -> Wait {
+        let mut arids = self.arids.lock().unwrap();
+        let sender = arids.entry(*arid).or_insert_with(|| watch::channel(()).0);
+        Wait {
+            arid: *arid,
+            receiver: sender.subscribe(),
+            waiters: self.clone(),
+        }
+    }
+
+    /// Look `arid` up with `lookup` until it finds an envelope other than
+    /// the one with digest `last`, looking again each time a put or update
+    /// to the ARID wakes the registry.
+    ///
+    /// `recheck` also bounds each wait, for a store that other processes
+    /// write to without waking this registry. Backs
+    /// [`KvStore::watch`](crate::KvStore::watch) for the stores that keep
+    /// a registry, with `lookup` a get that does not wait.
+    pub(crate) async fn next_version<F, Fut>(
+        &self,
+        arid: &ARID,
+        last: Option<Digest>,
+        recheck: Option<Duration>,
+        mut lookup: F,
+    ) -> Result<Option<Envelope>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Option<Envelope>>>,
+    {
+        loop {
+            // Listen before looking, so a put in between is not missed
+            let mut wait = self.wait(arid);
+            if let Some(envelope) = lookup().await?
+                && last != Some(envelope.digest())
+            {
+                return Ok(Some(envelope));
+            }
+            match recheck {
+                Some(recheck) => {
+                    wait.until(Instant::now() + recheck).await;
+                }
+                None => {
+                    let _ = wait.receiver.changed().await;
+                }
+            }
+        }
+    }
+
+    /// Wake every get waiting for `arid`.
+    pub fn wake(&self, arid: &ARID) {
+        // A later get or watch registers the ARID again
+        if let Some(sender) = self.arids.lock().unwrap().remove(arid) {
+            sender.send_replace(());
+        }
+    }
+
+    /// The number of ARIDs with gets waiting on them.
+    pub fn len(&self) -> usize { self.arids.lock().unwrap().len() }
+
+    /// Whether no get is waiting.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+/// A get's wait for a put to one ARID, from [`Waiters::wait`].
+pub struct Wait {
+    arid: ARID,
+    receiver: watch::Receiver<()>,
+    waiters: Waiters,
+}
+
+impl Wait {
+    /// Wait for a put to the ARID or for `deadline`, whichever comes first.
+    /// Returns `true` if a put ended the wait.
+    ///
+    /// Once a put has ended it, a wait returns at once; start a new one to
+    /// wait again.
+    pub async fn until(&mut self, deadline: Instant) -> bool {
+        tokio::time::timeout_at(deadline.into(), self.receiver.changed())
+            .await
+            .is_ok()
+    }
+}
+
+impl Drop for Wait {
+    fn drop(&mut self) {
+        // The last waiter to give up removes the ARID, unless a put already
+        // did and a newer wait took its place
+        let mut arids = self.waiters.arids.lock().unwrap();
+        if arids.get(&self.arid).is_some_and(|sender| {
+            sender.receiver_count() <= 1
+                && sender.subscribe().same_channel(&self.receiver)
+        }) {
+            arids.remove(&self.arid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wake_and_cleanup() {
+        let waiters = Waiters::new();
+        let (arid, other) = (ARID::new(), ARID::new());
+        let soon = || Instant::now() + Duration::from_millis(50);
+
+        let mut first = waiters.wait(&arid);
+        let mut second = waiters.wait(&arid);
+        let mut unrelated = waiters.wait(&other);
+        assert_eq!(waiters.len(), 2);
+
+        // A put before the wait begins is not missed
+        waiters.wake(&arid);
+        assert!(first.until(soon()).await);
+        assert!(second.until(soon()).await);
+        assert!(!unrelated.until(soon()).await);
+
+        drop((first, second));
+        assert_eq!(waiters.len(), 1);
+        drop(unrelated);
+        assert!(waiters.is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+//! Limits and encodings the server and
+//! [`ServerKvClient`](super::ServerKvClient) must agree on.
+//!
+//! Request and response bodies are UTF-8 text, one field per line, with
+//! ARIDs and envelopes as URs. Each has a type here with the one encoder and
+//! decoder both sides use; see [`crate::wire`] for the formats other than the
+//! server's.
+
+use bc_components::{ARID, Digest};
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+
+use super::{Error, Tombstone};
+use crate::ur_input::{parse_arid_ur, parse_envelope_ur};
+
+/// Most ARIDs one `/get_many` request may ask for.
+pub(super) const MAX_GET_MANY_ARIDS: usize = 100;
+
+/// Most entries one `/put_many` request may store.
+pub(super) const MAX_PUT_MANY_ENTRIES: usize = 100;
+
+/// Most bytes one `/put_many` request body may take, shared by its entries.
+pub(super) const MAX_PUT_MANY_BODY_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Most seconds a `/get` request may wait for a put.
+pub(super) const MAX_GET_WAIT: u64 = 20;
+
+/// The `ETag` of an envelope with `digest`.
+pub(super) fn entity_tag(digest: &Digest) -> String {
+    format!("\"{}\"", hex::encode(digest.data()))
+}
+
+/// The body of a `/put` or `/commit` request.
+///
+/// ```text
+/// ur:arid/...
+/// ur:envelope/...
+/// 3600
+/// ```
+///
+/// The third line, the TTL in seconds, is optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PutBody {
+    pub arid: ARID,
+    pub envelope: Envelope,
+    pub ttl: Option<u64>,
+}
+
+impl PutBody {
+    pub fn encode(&self) -> String {
+        let mut body =
+            format!("{}\n{}", self.arid.ur_string(), self.envelope.ur_string());
+        if let Some(ttl) = self.ttl {
+            body.push_str(&format!("\n{}", ttl));
+        }
+        body
+    }
+
+    pub fn decode(body: &str) -> Result<Self, Error> {
+        let lines: Vec<&str> = body.lines().collect();
+        if lines.len() < 2 {
+            return Err(parse_error(
+                "Expected at least 2 lines: ur:arid and ur:envelope",
+            ));
+        }
+        if lines.len() > 3 {
+            return Err(parse_error(
+                "Expected at most 3 lines: ur:arid, ur:envelope, and TTL",
+            ));
+        }
+        Ok(Self {
+            arid: decode_arid(lines[0])?,
+            envelope: decode_envelope(lines[1])?,
+            ttl: lines.get(2).copied().map(decode_ttl).transpose()?,
+        })
+    }
+}
+
+/// The body of a `/reserve` request: the ARID, and optionally the
+/// reservation's TTL in seconds on a second line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReserveBody {
+    pub arid: ARID,
+    pub ttl: Option<u64>,
+}
+
+impl ReserveBody {
+    pub fn encode(&self) -> String {
+        match self.ttl {
+            Some(ttl) => format!("{}\n{}", self.arid.ur_string(), ttl),
+            None => self.arid.ur_string(),
+        }
+    }
+
+    pub fn decode(body: &str) -> Result<Self, Error> {
+        let lines: Vec<&str> = body.lines().collect();
+        if lines.is_empty() || lines.len() > 2 {
+            return Err(parse_error("Expected ur:arid and an optional TTL"));
+        }
+        Ok(Self {
+            arid: decode_arid(lines[0])?,
+            ttl: lines.get(1).copied().map(decode_ttl).transpose()?,
+        })
+    }
+}
+
+/// The body of a `/put_many` request: optionally the TTL in seconds shared
+/// by every entry, then one line per entry with the ARID and its envelope
+/// separated by a space.
+///
+/// ```text
+/// 3600
+/// ur:arid/... ur:envelope/...
+/// ur:arid/... ur:envelope/...
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PutManyBody {
+    pub entries: Vec<(ARID, Envelope)>,
+    pub ttl: Option<u64>,
+}
+
+impl PutManyBody {
+    pub fn encode(&self) -> String {
+        let mut lines: Vec<String> =
+            self.ttl.iter().map(|ttl| ttl.to_string()).collect();
+        lines.extend(self.entries.iter().map(|(arid, envelope)| {
+            format!("{} {}", arid.ur_string(), envelope.ur_string())
+        }));
+        lines.join("\n")
+    }
+
+    pub fn decode(body: &str) -> Result<Self, Error> {
+        let mut lines = body.lines().peekable();
+        let ttl = lines
+            .next_if(|line| !line.contains(' '))
+            .map(decode_ttl)
+            .transpose()?;
+        let entries = lines
+            .map(|line| {
+                let (arid, envelope) =
+                    line.split_once(' ').ok_or_else(|| {
+                        parse_error(format!("Invalid put_many line: {}", line))
+                    })?;
+                Ok((decode_arid(arid)?, decode_envelope(envelope)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if entries.is_empty() {
+            return Err(parse_error("Expected at least one entry"));
+        }
+        Ok(Self { entries, ttl })
+    }
+}
+
+/// The body of a `/get_many` request: one ARID per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetManyBody {
+    pub arids: Vec<ARID>,
+}
+
+impl GetManyBody {
+    pub fn encode(&self) -> String {
+        let lines: Vec<String> =
+            self.arids.iter().map(|arid| arid.ur_string()).collect();
+        lines.join("\n")
+    }
+
+    pub fn decode(body: &str) -> Result<Self, Error> {
+        let arids = body.lines().map(decode_arid).collect::<Result<_, _>>()?;
+        Ok(Self { arids })
+    }
+}
+
+/// The body of a `/get_many` response: one line per ARID found, with the
+/// ARID and its envelope separated by a space. ARIDs not found are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetManyResponse {
+    pub found: Vec<(ARID, Envelope)>,
+}
+
+impl GetManyResponse {
+    pub fn encode(&self) -> String {
+        let lines: Vec<String> = self
+            .found
+            .iter()
+            .map(|(arid, envelope)| {
+                format!("{} {}", arid.ur_string(), envelope.ur_string())
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    pub fn decode(body: &str) -> Result<Self, Error> {
+        let found = body
+            .lines()
+            .map(|line| {
+                let (arid, envelope) =
+                    line.split_once(' ').ok_or_else(|| {
+                        parse_error(format!("Invalid get_many line: {}", line))
+                    })?;
+                Ok((decode_arid(arid)?, decode_envelope(envelope)?))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { found })
+    }
+}
+
+/// One event of a `/watch` response, a stream of server-sent events. Each
+/// event ends with a blank line, and lines starting with `:` are
+/// keep-alive comments.
+///
+/// ```text
+/// event: envelope
+/// id: "<digest hex>"
+/// data: ur:envelope/...
+///
+/// event: gone
+/// data: {"arid":"ur:arid/...","expired_at":...,"digest":"ur:digest/..."}
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchEvent {
+    /// A version of the watched envelope, sent when the watch begins and
+    /// each time a put or update stores a new one. Its `id` is the ETag.
+    Envelope(Envelope),
+    /// The entry expired and left this tombstone. The server ends the
+    /// stream after it.
+    Gone(Tombstone),
+}
+
+impl WatchEvent {
+    /// The name in the event's `event:` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Envelope(_) => "envelope",
+            Self::Gone(_) => "gone",
+        }
+    }
+
+    /// The contents of the event's `data:` field.
+    pub fn data(&self) -> String {
+        match self {
+            Self::Envelope(envelope) => envelope.ur_string(),
+            Self::Gone(tombstone) => serde_json::to_string(tombstone).unwrap(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("event: {}\ndata: {}", self.name(), self.data())
+    }
+
+    /// Decode one event from its lines, without the blank line ending it.
+    /// A keep-alive comment, or an event of a kind this client does not
+    /// know, decodes to `None`.
+    pub fn decode(event: &str) -> Result<Option<Self>, Error> {
+        let mut name = None;
+        let mut data = Vec::new();
+        for line in event.lines() {
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => name = Some(value),
+                "data" => data.push(value),
+                _ => {}
+            }
+        }
+        let data = data.join("\n");
+        match name {
+            Some("envelope") => {
+                Ok(Some(Self::Envelope(decode_envelope(&data)?)))
+            }
+            Some("gone") => serde_json::from_str(&data)
+                .map(|tombstone| Some(Self::Gone(tombstone)))
+                .map_err(|e| parse_error(e.to_string())),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn parse_error(message: impl Into<String>) -> Error {
+    Error::ParseError(message.into())
+}
+
+fn decode_arid(line: &str) -> Result<ARID, Error> {
+    parse_arid_ur(line).map_err(|e| parse_error(e.to_string()))
+}
+
+fn decode_envelope(line: &str) -> Result<Envelope, Error> {
+    parse_envelope_ur(line).map_err(|e| parse_error(e.to_string()))
+}
+
+fn decode_ttl(line: &str) -> Result<u64, Error> {
+    line.trim().parse().map_err(|_| parse_error("Invalid TTL"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_body_roundtrip() {
+        bc_components::register_tags();
+        let body = PutBody {
+            arid: ARID::new(),
+            envelope: Envelope::new("Hello"),
+            ttl: Some(60),
+        };
+        assert_eq!(PutBody::decode(&body.encode()).unwrap(), body);
+
+        let no_ttl = PutBody { ttl: None, ..body };
+        assert_eq!(no_ttl.encode().lines().count(), 2);
+        assert_eq!(PutBody::decode(&no_ttl.encode()).unwrap(), no_ttl);
+
+        assert!(PutBody::decode(&no_ttl.arid.ur_string()).is_err());
+        let bad_ttl = format!("{}\nsoon", no_ttl.encode());
+        assert!(PutBody::decode(&bad_ttl).is_err());
+    }
+
+    #[test]
+    fn test_put_many_roundtrip() {
+        bc_components::register_tags();
+        let body = PutManyBody {
+            entries: vec![
+                (ARID::new(), Envelope::new("One")),
+                (ARID::new(), Envelope::new("Two")),
+            ],
+            ttl: Some(60),
+        };
+        assert_eq!(PutManyBody::decode(&body.encode()).unwrap(), body);
+
+        let no_ttl = PutManyBody { ttl: None, ..body };
+        assert_eq!(no_ttl.encode().lines().count(), 2);
+        assert_eq!(PutManyBody::decode(&no_ttl.encode()).unwrap(), no_ttl);
+
+        assert!(PutManyBody::decode("60").is_err());
+        assert!(PutManyBody::decode("soon\nur:arid/x ur:envelope/y").is_err());
+    }
+
+    #[test]
+    fn test_get_many_roundtrip() {
+        bc_components::register_tags();
+        let arids = vec![ARID::new(), ARID::new()];
+        let request = GetManyBody { arids: arids.clone() };
+        assert_eq!(GetManyBody::decode(&request.encode()).unwrap(), request);
+
+        let response =
+            GetManyResponse { found: vec![(arids[1], Envelope::new("Found"))] };
+        assert_eq!(
+            GetManyResponse::decode(&response.encode()).unwrap(),
+            response
+        );
+        assert!(GetManyResponse::decode("no-space-here").is_err());
+        assert!(GetManyResponse::decode("").unwrap().found.is_empty());
+    }
+
+    #[test]
+    fn test_watch_event_roundtrip() {
+        bc_components::register_tags();
+        let envelope = Envelope::new("Version 2");
+        let event = WatchEvent::Envelope(envelope.clone());
+        assert_eq!(WatchEvent::decode(&event.encode()).unwrap(), Some(event));
+
+        let gone = WatchEvent::Gone(Tombstone::new(ARID::new(), 60, &envelope));
+        assert_eq!(WatchEvent::decode(&gone.encode()).unwrap(), Some(gone));
+
+        // Ids and keep-alive comments are skipped
+        let with_id = format!(
+            "event: envelope\nid: \"00\"\ndata:{}",
+            envelope.ur_string()
+        );
+        assert_eq!(
+            WatchEvent::decode(&with_id).unwrap(),
+            Some(WatchEvent::Envelope(envelope))
+        );
+        assert_eq!(WatchEvent::decode(":").unwrap(), None);
+        assert!(WatchEvent::decode("event: envelope\ndata: ur:x").is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Sessions: groups of related ARIDs with a shared lifetime.
+//!
+//! A [`Session`] owns a root ARID from which it derives one ARID per
+//! [`SessionSlot`]. Sharing the root ARID (and expiry) with a counterparty
+//! gives both sides the same set of slots without exchanging each ARID
+//! separately, and every entry published through the session expires when
+//! the session does.
+
+use std::time::{Duration, SystemTime};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+
+use crate::{Error, KvStore, Receipt, Result, arid_derivation::derive_key};
+
+/// A named position within a [`Session`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionSlot {
+    /// The initiator's request.
+    Request,
+    /// The responder's reply.
+    Response,
+    /// Progress or status updates.
+    Status,
+    /// Additional payloads, numbered from zero.
+    Attachment(u32),
+}
+
+impl SessionSlot {
+    fn salt(&self) -> Vec<u8> {
+        match self {
+            SessionSlot::Request => b"hubert-session-request-v1".to_vec(),
+            SessionSlot::Response => b"hubert-session-response-v1".to_vec(),
+            SessionSlot::Status => b"hubert-session-status-v1".to_vec(),
+            SessionSlot::Attachment(index) => {
+                format!("hubert-session-attachment-{}-v1", index).into_bytes()
+            }
+        }
+    }
+}
+
+/// A root ARID, the slots derived from it, and their common expiry.
+///
+/// # Example
+///
+/// ```no_run
+/// use bc_envelope::Envelope;
+/// use hubert::{MemoryKv, Session};
+///
+/// # async fn example() -> hubert::Result<()> {
+/// let store = MemoryKv::new();
+///
+/// // Initiator: create a one-hour session and share root + expiry
+/// let session = Session::new(3600);
+/// session
+///     .publish_request(&store, &Envelope::new("Please sign"), false)
+///     .await?;
+///
+/// // Responder: join using the shared values
+/// let joined = Session::join(*session.root(), session.expires_at());
+/// let request = joined.await_request(&store, Some(30), false).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    root: ARID,
+    expires_at: SystemTime,
+}
+
+impl Session {
+    /// Start a new session with a fresh root ARID, expiring after
+    /// `ttl_seconds`.
+    pub fn new(ttl_seconds: u64) -> Self {
+        Self::join(
+            ARID::new(),
+            SystemTime::now() + Duration::from_secs(ttl_seconds),
+        )
+    }
+
+    /// Join an existing session from its root ARID and expiry.
+    pub fn join(root: ARID, expires_at: SystemTime) -> Self {
+        Self { root, expires_at }
+    }
+
+    /// The root ARID that all slots are derived from.
+    pub fn root(&self) -> &ARID { &self.root }
+
+    /// When the session and all of its entries expire.
+    pub fn expires_at(&self) -> SystemTime { self.expires_at }
+
+    /// Time left before the session expires, or `None` if it has expired.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expires_at
+            .duration_since(SystemTime::now())
+            .ok()
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Whether the session has expired.
+    pub fn is_expired(&self) -> bool { self.remaining().is_none() }
+
+    /// The ARID for `slot`.
+    ///
+    /// Derivation is deterministic, so both parties compute the same ARID
+    /// from the same root, and slot ARIDs reveal nothing about the root.
+    pub fn arid(&self, slot: SessionSlot) -> ARID {
+        let data: [u8; 32] = derive_key(&slot.salt(), &self.root, 32)
+            .try_into()
+            .expect("derive_key produces exactly 32 bytes");
+        ARID::from_data(data)
+    }
+
+    /// Publish `envelope` at `slot`, expiring with the session.
+    ///
+    /// Returns [`Error::SessionExpired`] if the session has expired.
+    pub async fn publish(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        slot: SessionSlot,
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let remaining = self.remaining().ok_or(Error::SessionExpired)?;
+        let ttl_seconds = remaining.as_secs().max(1);
+        store
+            .put(&self.arid(slot), envelope, Some(ttl_seconds), verbose)
+            .await
+    }
+
+    /// Wait for an envelope at `slot`.
+    ///
+    /// A `timeout_seconds` longer than the session's remaining lifetime is
+    /// shortened to it. Returns [`Error::SessionExpired`] if the session has
+    /// expired.
+    pub async fn await_slot(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        slot: SessionSlot,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        let remaining = self.remaining().ok_or(Error::SessionExpired)?;
+        let limit = remaining.as_secs().max(1);
+        let timeout_seconds = timeout_seconds.map(|t| t.min(limit));
+        store.get(&self.arid(slot), timeout_seconds, verbose).await
+    }
+
+    /// Publish the request.
+    pub async fn publish_request(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.publish(store, SessionSlot::Request, envelope, verbose)
+            .await
+    }
+
+    /// Wait for the request.
+    pub async fn await_request(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.await_slot(store, SessionSlot::Request, timeout_seconds, verbose)
+            .await
+    }
+
+    /// Publish the response.
+    pub async fn publish_response(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.publish(store, SessionSlot::Response, envelope, verbose)
+            .await
+    }
+
+    /// Wait for the response.
+    pub async fn await_response(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.await_slot(store, SessionSlot::Response, timeout_seconds, verbose)
+            .await
+    }
+
+    /// Publish the status.
+    pub async fn publish_status(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.publish(store, SessionSlot::Status, envelope, verbose)
+            .await
+    }
+
+    /// Wait for the status.
+    pub async fn await_status(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.await_slot(store, SessionSlot::Status, timeout_seconds, verbose)
+            .await
+    }
+
+    /// Publish attachment number `index`.
+    pub async fn publish_attachment(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        index: u32,
+        envelope: &Envelope,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        self.publish(store, SessionSlot::Attachment(index), envelope, verbose)
+            .await
+    }
+
+    /// Wait for attachment number `index`.
+    pub async fn await_attachment(
+        &self,
+        store: &(impl KvStore + ?Sized),
+        index: u32,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.await_slot(
+            store,
+            SessionSlot::Attachment(index),
+            timeout_seconds,
+            verbose,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_arids_are_deterministic_and_distinct() {
+        let session = Session::new(60);
+        let joined = Session::join(*session.root(), session.expires_at());
+
+        let slots = [
+            SessionSlot::Request,
+            SessionSlot::Response,
+            SessionSlot::Status,
+            SessionSlot::Attachment(0),
+            SessionSlot::Attachment(1),
+        ];
+        for slot in slots {
+            assert_eq!(session.arid(slot), joined.arid(slot));
+            assert_ne!(session.arid(slot), *session.root());
+        }
+        for (i, a) in slots.iter().enumerate() {
+            for b in &slots[i + 1..] {
+                assert_ne!(session.arid(*a), session.arid(*b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_expiry() {
+        assert!(!Session::new(60).is_expired());
+        let past = SystemTime::now() - Duration::from_secs(1);
+        assert!(Session::join(ARID::new(), past).is_expired());
+    }
+}
@@ -0,0 +1,467 @@
+//! Client-side sharding across a tier of stores.
+//!
+//! A [`ShardedKv`] spreads entries over named shards, typically
+//! [`ServerKvClient`](crate::server::ServerKvClient)s for separate servers,
+//! by placing each shard at many points on a hash ring and storing each ARID
+//! on the first `replication` distinct shards clockwise from the ARID's own
+//! position. Positions come from ARID-derived keys, so a shard's placement
+//! reveals nothing about the ARIDs it holds.
+//!
+//! Adding or removing a shard moves only the entries whose placement
+//! touches that shard. [`ShardedKv::rebalance`] copies such entries to their
+//! new shards.
+
+use std::{collections::HashMap, time::Instant};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use futures_util::{StreamExt, future::join_all, stream::FuturesUnordered};
+
+use crate::{
+    EntryMeta, Error, HealthReport, KvStore, Receipt, Result,
+    StoreCapabilities, ValidationError,
+    arid_derivation::derive_key,
+    receipt::Backend,
+    wire::{Codec, stored_size},
+};
+
+/// Points each shard occupies on the ring, to even out load.
+const POINTS_PER_SHARD: u32 = 64;
+
+/// Position of `arid` on the ring.
+fn arid_position(arid: &ARID) -> u64 {
+    const SALT: &[u8] = b"hubert-shard-ring-v1";
+    let key: [u8; 8] = derive_key(SALT, arid, 8).try_into().unwrap();
+    u64::from_be_bytes(key)
+}
+
+/// Position of one of a shard's points on the ring.
+fn shard_position(name: &str, point: u32) -> u64 {
+    let digest = bc_crypto::sha256(format!("{}#{}", name, point).as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+/// Placement of ARIDs onto named shards by consistent hashing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardRing {
+    names: Vec<String>,
+    replication: usize,
+    points: Vec<(u64, usize)>,
+}
+
+impl ShardRing {
+    /// A ring over `names`, placing each ARID on `replication` shards (at
+    /// least one, and at most the number of shards).
+    pub fn new<S: Into<String>>(
+        names: impl IntoIterator<Item = S>,
+        replication: usize,
+    ) -> Self {
+        let mut names: Vec<String> =
+            names.into_iter().map(Into::into).collect();
+        names.sort();
+        names.dedup();
+        let mut points: Vec<(u64, usize)> = names
+            .iter()
+            .enumerate()
+            .flat_map(|(index, name)| {
+                (0..POINTS_PER_SHARD)
+                    .map(move |point| (shard_position(name, point), index))
+            })
+            .collect();
+        points.sort();
+        Self { names, replication: replication.max(1), points }
+    }
+
+    /// The shard names, sorted.
+    pub fn names(&self) -> &[String] { &self.names }
+
+    /// The number of shards each ARID is stored on.
+    pub fn replication(&self) -> usize {
+        self.replication.min(self.names.len())
+    }
+
+    /// The shards `arid` is stored on, primary first.
+    pub fn shards_for(&self, arid: &ARID) -> Vec<&str> {
+        let wanted = self.replication();
+        let position = arid_position(arid);
+        let start = self.points.partition_point(|(p, _)| *p < position);
+        let mut shards: Vec<usize> = Vec::with_capacity(wanted);
+        for (_, index) in
+            self.points[start..].iter().chain(&self.points[..start])
+        {
+            if shards.len() == wanted {
+                break;
+            }
+            if !shards.contains(index) {
+                shards.push(*index);
+            }
+        }
+        shards.iter().map(|&i| self.names[i].as_str()).collect()
+    }
+}
+
+/// A [`KvStore`] that shards entries across named stores.
+///
+/// Puts go to every shard in an ARID's placement; gets poll them all and
+/// return the first envelope found, so a get succeeds while any replica is
+/// reachable.
+///
+/// # Example
+///
+/// ```no_run
+/// use hubert::{server::ServerKvClient, sharded::ShardedKv};
+///
+/// let store = ShardedKv::new(2)
+///     .with_shard("a", ServerKvClient::new("http://hubert-a:45678"))
+///     .with_shard("b", ServerKvClient::new("http://hubert-b:45678"))
+///     .with_shard("c", ServerKvClient::new("http://hubert-c:45678"));
+/// ```
+pub struct ShardedKv {
+    ring: ShardRing,
+    shards: HashMap<String, Box<dyn KvStore>>,
+}
+
+impl ShardedKv {
+    /// A router with no shards, storing each entry on `replication` shards.
+    pub fn new(replication: usize) -> Self {
+        Self {
+            ring: ShardRing::new(Vec::<String>::new(), replication),
+            shards: HashMap::new(),
+        }
+    }
+
+    /// Add a shard, replacing any shard with the same name.
+    pub fn with_shard(
+        mut self,
+        name: impl Into<String>,
+        store: impl KvStore + 'static,
+    ) -> Self {
+        self.shards.insert(name.into(), Box::new(store));
+        self.ring =
+            ShardRing::new(self.shards.keys().cloned(), self.ring.replication);
+        self
+    }
+
+    /// The ring placing ARIDs onto this router's shards.
+    pub fn ring(&self) -> &ShardRing { &self.ring }
+
+    fn replicas(&self, arid: &ARID) -> Result<Vec<&dyn KvStore>> {
+        let names = self.ring.shards_for(arid);
+        if names.is_empty() {
+            return Err(Error::NoShards);
+        }
+        Ok(names
+            .into_iter()
+            .map(|name| self.shards[name].as_ref())
+            .collect())
+    }
+
+    /// Copy entries to the shards they belong on after the shard set changed.
+    ///
+    /// `previous` is the ring before the change, and `arids` the entries to
+    /// check. Every shard in either placement must still be attached to this
+    /// router, so add new shards before rebalancing and remove old ones
+    /// after. Each entry whose placement changed is read from its old shards
+    /// and written with `ttl_seconds` to new shards that lack it. Returns the
+    /// number of copies made.
+    pub async fn rebalance(
+        &self,
+        previous: &ShardRing,
+        arids: &[ARID],
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<usize> {
+        let mut copies = 0;
+        for arid in arids {
+            let old = previous.shards_for(arid);
+            let new = self.ring.shards_for(arid);
+            let targets: Vec<&str> =
+                new.iter().filter(|s| !old.contains(s)).copied().collect();
+            if targets.is_empty() {
+                continue;
+            }
+
+            let mut envelope = None;
+            for name in &old {
+                let shard = self.shards.get(*name).ok_or(Error::NoShards)?;
+                envelope = shard.get(arid, Some(0), verbose).await?;
+                if envelope.is_some() {
+                    break;
+                }
+            }
+            let Some(envelope) = envelope else {
+                continue;
+            };
+
+            for name in targets {
+                match self.shards[name]
+                    .put(arid, &envelope, ttl_seconds, verbose)
+                    .await
+                {
+                    Ok(_) => copies += 1,
+                    Err(Error::AlreadyExists { .. }) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(copies)
+    }
+}
+
+/// The receipt for storing `envelope` at `arid` in the replicas that
+/// returned `receipts`.
+fn sharded_receipt(
+    arid: &ARID,
+    envelope: &Envelope,
+    receipts: Vec<Receipt>,
+) -> Receipt {
+    let mut receipt = Receipt::new(
+        Backend::Sharded,
+        *arid,
+        "sharded:",
+        stored_size(envelope, Codec::Dcbor),
+    );
+    // Every replica applies its own TTL; report the shortest
+    if let Some(shortest) =
+        receipts.iter().filter(|r| r.ttl.is_some()).min_by_key(|r| r.ttl)
+    {
+        receipt.ttl = shortest.ttl;
+        receipt.expires_at = shortest.expires_at;
+    }
+    receipt.with_shards(receipts)
+}
+
+/// The first envelope any of the replica gets in `pending` finds.
+async fn first_found<F>(
+    mut pending: FuturesUnordered<F>,
+) -> Result<Option<Envelope>>
+where
+    F: Future<Output = Result<Option<Envelope>>>,
+{
+    // Only fail if no replica could be asked at all
+    let mut reachable = false;
+    let mut last_error = None;
+    while let Some(result) = pending.next().await {
+        match result {
+            Ok(Some(envelope)) => return Ok(Some(envelope)),
+            Ok(None) => reachable = true,
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(e) if !reachable => Err(e),
+        _ => Ok(None),
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl KvStore for ShardedKv {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let replicas = self.replicas(arid)?;
+
+        // Check every replica first so a put never half-overwrites
+        for replica in &replicas {
+            if replica.exists(arid).await? {
+                return Err(Error::AlreadyExists { arid: arid.ur_string() });
+            }
+        }
+
+        let results = join_all(
+            replicas
+                .iter()
+                .map(|r| r.put(arid, envelope, ttl_seconds, verbose)),
+        )
+        .await;
+        let receipts = results.into_iter().collect::<Result<Vec<_>>>()?;
+        Ok(sharded_receipt(arid, envelope, receipts))
+    }
+
+    /// Updates every replica. Replicas that missed an earlier update count
+    /// versions of their own; the receipt reports the highest.
+    async fn update(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let results = join_all(
+            self.replicas(arid)?
+                .iter()
+                .map(|r| r.update(arid, envelope, ttl_seconds, verbose)),
+        )
+        .await;
+        let receipts = results.into_iter().collect::<Result<Vec<_>>>()?;
+        let sequence = receipts.iter().filter_map(|r| r.sequence).max();
+        let receipt = sharded_receipt(arid, envelope, receipts);
+        Ok(match sequence {
+            Some(sequence) => receipt.with_sequence(sequence),
+            None => receipt,
+        })
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        first_found(
+            self.replicas(arid)?
+                .into_iter()
+                .map(|r| r.get(arid, timeout_seconds, verbose))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        first_found(
+            self.replicas(arid)?
+                .into_iter()
+                .map(|r| r.get_until(arid, deadline, verbose))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn exists(&self, arid: &ARID) -> Result<bool> {
+        for replica in self.replicas(arid)? {
+            if replica.exists(arid).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Deletes from every replica, reporting a removal if any had the
+    /// envelope.
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        let results = join_all(
+            self.replicas(arid)?
+                .iter()
+                .map(|replica| replica.delete(arid, verbose)),
+        )
+        .await;
+        let removed = results.into_iter().collect::<Result<Vec<_>>>()?;
+        Ok(removed.into_iter().any(|removed| removed))
+    }
+
+    /// Reports the replica whose copy lasts longest, since gets find the
+    /// envelope until every copy is gone.
+    async fn stat(
+        &self,
+        arid: &ARID,
+        verbose: bool,
+    ) -> Result<Option<EntryMeta>> {
+        let results = join_all(
+            self.replicas(arid)?
+                .iter()
+                .map(|replica| replica.stat(arid, verbose)),
+        )
+        .await;
+        let metas = results.into_iter().collect::<Result<Vec<_>>>()?;
+        Ok(metas
+            .into_iter()
+            .flatten()
+            .max_by_key(|meta| meta.expires_at.unwrap_or(u64::MAX)))
+    }
+
+    /// Puts go to several shards, so only what every shard supports.
+    fn capabilities(&self) -> StoreCapabilities {
+        self.shards
+            .values()
+            .map(|shard| shard.capabilities())
+            .reduce(StoreCapabilities::intersect)
+            .unwrap_or_default()
+    }
+
+    /// Checks every shard, since any of them may be asked to hold the
+    /// envelope.
+    fn validate(
+        &self,
+        envelope: &Envelope,
+    ) -> std::result::Result<(), ValidationError> {
+        self.shards
+            .values()
+            .try_for_each(|shard| shard.validate(envelope))
+    }
+
+    /// Probes every shard; the tier is healthy only if all of them are,
+    /// since puts need every replica.
+    async fn health(&self) -> HealthReport {
+        let names = self.ring.names();
+        let reports = join_all(names.iter().map(|name| async move {
+            let mut report = self.shards[name].health().await;
+            report.backend = format!("{} ({})", name, report.backend);
+            report
+        }))
+        .await;
+        let mut report = HealthReport::combine("sharded", reports);
+        if names.is_empty() {
+            report.error = Some(Error::NoShards.to_string());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement_is_stable_and_distinct() {
+        let ring = ShardRing::new(["a", "b", "c", "d"], 3);
+        for _ in 0..100 {
+            let arid = ARID::new();
+            let shards = ring.shards_for(&arid);
+            assert_eq!(shards.len(), 3);
+            assert_eq!(shards, ring.shards_for(&arid));
+            let mut unique = shards.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_adding_shard_moves_few_entries() {
+        let before = ShardRing::new(["a", "b", "c", "d"], 1);
+        let after = ShardRing::new(["a", "b", "c", "d", "e"], 1);
+        let arids: Vec<ARID> = (0..2000).map(|_| ARID::new()).collect();
+
+        let moved = arids
+            .iter()
+            .filter(|arid| before.shards_for(arid) != after.shards_for(arid))
+            .count();
+        // Ideally one fifth move; all moves go to the new shard
+        assert!(moved < 700, "{} of 2000 entries moved", moved);
+        assert!(arids.iter().all(|arid| {
+            before.shards_for(arid) == after.shards_for(arid)
+                || after.shards_for(arid) == ["e"]
+        }));
+    }
+
+    #[test]
+    fn test_replication_capped_by_shard_count() {
+        let ring = ShardRing::new(["a", "b"], 5);
+        assert_eq!(ring.replication(), 2);
+        assert!(
+            ShardRing::new(Vec::<String>::new(), 2)
+                .shards_for(&ARID::new())
+                .is_empty()
+        );
+    }
+}
@@ -1,42 +1,89 @@
-/// Unified test suite for KvStore implementations.
-use std::{sync::Arc, thread};
+//! The conformance suite every [`KvStore`] must pass, for authors of
+//! backends outside this crate.
+//!
+//! These are the checks Hubert runs against its own backends. Each
+//! function panics on the first violation, so call them from tests:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use hubert::{MemoryKv, testkit};
+//!
+//! #[tokio::test(flavor = "multi_thread")]
+//! async fn conformance() {
+//!     let store = MemoryKv::new();
+//!     let setup = || std::future::ready(store.clone());
+//!     testkit::run_suite(setup, Duration::from_secs(1), None).await;
+//! }
+//! ```
+//!
+//! Requires the `testkit` feature.
+
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use bc_components::ARID;
 use bc_envelope::Envelope;
-use futures_util::future;
-use hubert::KvStore;
+use futures_util::{StreamExt, future};
 use tokio::sync::mpsc;
 
-/// Poll for an envelope with retries.
+use crate::{ErrorCategory, KvStore};
+
+/// Run every check against stores made by `setup`, which must return
+/// handles onto the same underlying storage.
+///
+/// A get of a missing ARID with a zero timeout must return within
+/// `zero_timeout`. If the store has a size limit, pass it as `max_size` to
+/// check that larger envelopes are rejected.
+pub async fn run_suite<S, F, Fut>(
+    setup: F,
+    zero_timeout: Duration,
+    max_size: Option<usize>,
+) where
+    S: KvStore + 'static,
+    F: Fn() -> Fut,
+    Fut: Future<Output = S>,
+{
+    test_basic_roundtrip(&setup().await).await;
+    test_write_once(&setup().await).await;
+    test_nonexistent_arid(&setup().await).await;
+    test_multiple_arids(&setup().await).await;
+    test_zero_timeout(&setup().await, zero_timeout).await;
+    test_watch(&setup().await).await;
+    if let Some(max_size) = max_size {
+        test_size_limit(&setup().await, max_size).await;
+    }
+    test_concurrent_operations(
+        Arc::new(setup().await),
+        Arc::new(setup().await),
+    )
+    .await;
+}
+
+/// Wait for an envelope and send its subject to `result_tx`.
 async fn poll_for_envelope(
     store: &impl KvStore,
     arid: ARID,
     index: usize,
     result_tx: mpsc::Sender<(ARID, String)>,
 ) {
-    const MAX_ATTEMPTS: u32 = 30;
-    const RETRY_DELAY_MS: u64 = 500;
-
-    for attempt in 1..=MAX_ATTEMPTS {
-        match store.get(&arid, Some(30), false).await {
-            Ok(Some(envelope)) => {
-                let subject: String = envelope.extract_subject().unwrap();
-                result_tx.send((arid, subject)).await.unwrap();
-                return;
-            }
-            Ok(None) if attempt < MAX_ATTEMPTS => {
-                tokio::time::sleep(tokio::time::Duration::from_millis(
-                    RETRY_DELAY_MS,
-                ))
-                .await;
-            }
-            _ => {
-                panic!("Get failed for ARID {}", index + 1);
-            }
+    const TIMEOUT: Duration = Duration::from_secs(45);
+
+    let mut watch = store.watch(&arid, false);
+    match tokio::time::timeout(TIMEOUT, watch.next()).await {
+        Ok(Some(Ok(envelope))) => {
+            let subject: String = envelope.extract_subject().unwrap();
+            result_tx.send((arid, subject)).await.unwrap();
         }
+        _ => panic!("Get failed for ARID {}", index + 1),
     }
 }
 
+/// Check that a put envelope can be read back and is reported by
+/// `exists`.
 pub async fn test_basic_roundtrip(store: &impl KvStore) {
     let arid = ARID::new();
     let envelope = Envelope::new("Test").add_assertion("key", "value");
@@ -47,31 +94,51 @@ pub async fn test_basic_roundtrip(store: &impl KvStore) {
 
     let retrieved = store.get(&arid, Some(30), false).await.unwrap().unwrap();
     assert_eq!(retrieved, envelope);
-    println!("✓ Basic roundtrip test passed");
 }
 
+/// Check that a watch yields an envelope put while it waits, and then
+/// ends.
+pub async fn test_watch(store: &impl KvStore) {
+    let arid = ARID::new();
+    let envelope = Envelope::new("Watched");
+    let watch = async {
+        let mut watch = store.watch(&arid, false);
+        let wait = Duration::from_secs(60);
+        let found = tokio::time::timeout(wait, watch.next()).await;
+        let found = found.expect("Watch timed out").expect("Watch ended");
+        (found.unwrap(), watch.next().await.is_none())
+    };
+    let put = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        store.put(&arid, &envelope, None, false).await.unwrap();
+    };
+    let ((found, ended), ()) = tokio::join!(watch, put);
+    assert_eq!(found, envelope);
+    assert!(ended, "Watch yielded a second envelope");
+}
+
+/// Check that a second put to the same ARID fails as already existing.
 pub async fn test_write_once(store: &impl KvStore) {
     let arid = ARID::new();
     store
         .put(&arid, &Envelope::new("First"), None, false)
         .await
         .unwrap();
-    assert!(
-        store
-            .put(&arid, &Envelope::new("Second"), None, false)
-            .await
-            .is_err()
-    );
-    println!("✓ Write-once test passed");
+    let error = store
+        .put(&arid, &Envelope::new("Second"), None, false)
+        .await
+        .unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::AlreadyExists, "{}", error);
 }
 
+/// Check that an ARID never written neither exists nor returns a value.
 pub async fn test_nonexistent_arid(store: &impl KvStore) {
     let arid = ARID::new();
     assert!(!store.exists(&arid).await.unwrap());
     assert!(store.get(&arid, Some(30), false).await.unwrap().is_none());
-    println!("✓ Non-existent ARID test passed");
 }
 
+/// Check that puts to several distinct ARIDs all succeed.
 pub async fn test_multiple_arids(store: &impl KvStore) {
     let arids: Vec<_> = (0..5).map(|_| ARID::new()).collect();
     for (i, arid) in arids.iter().enumerate() {
@@ -85,18 +152,36 @@ pub async fn test_multiple_arids(store: &impl KvStore) {
             .await
             .unwrap();
     }
-    println!("✓ Multiple ARIDs test passed");
 }
 
-#[allow(dead_code)]
+/// Check that a zero timeout makes one lookup instead of polling, taking
+/// less than `max_elapsed` for a missing ARID.
+pub async fn test_zero_timeout(store: &impl KvStore, max_elapsed: Duration) {
+    let arid = ARID::new();
+    let start = Instant::now();
+    assert!(store.get(&arid, Some(0), false).await.unwrap().is_none());
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < max_elapsed,
+        "Zero timeout polled for {:?}",
+        elapsed
+    );
+
+    let envelope = Envelope::new("Immediate");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+    let retrieved = store.get(&arid, Some(0), false).await.unwrap();
+    assert_eq!(retrieved, Some(envelope));
+}
+
+/// Check that an envelope larger than `max_size` is rejected as too large.
 pub async fn test_size_limit(store: &impl KvStore, max_size: usize) {
     let arid = ARID::new();
     let large = Envelope::new("x".repeat(max_size + 1000).as_str());
-    assert!(store.put(&arid, &large, None, false).await.is_err());
-    println!("✓ Size limit test passed");
+    let error = store.put(&arid, &large, None, false).await.unwrap_err();
+    assert_eq!(error.category(), ErrorCategory::TooLarge, "{}", error);
 }
 
-/// Test multi-threaded concurrent operations.
+/// Check multi-threaded concurrent operations.
 ///
 /// This test demonstrates the thread safety and concurrency model of KvStore:
 ///
@@ -168,7 +253,7 @@ where
             let arids = arid_rx.recv().await.expect("Failed to receive ARIDs");
 
             // Small delay for propagation
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            tokio::time::sleep(Duration::from_millis(500)).await;
 
             let local_set = tokio::task::LocalSet::new();
             local_set
@@ -220,6 +305,4 @@ where
             .expect("ARID not found");
         assert_eq!(found.1, expected_subject);
     }
-
-    println!("✓ Concurrent operations test passed");
 }
@@ -0,0 +1,416 @@
+//! Threshold custody: envelopes secret-shared across several stores.
+//!
+//! A [`ThresholdKv`] encrypts each envelope under a fresh key, splits the
+//! key with SSKR into one share per custodian store, typically a
+//! [`ServerKvClient`](crate::server::ServerKvClient) for each of several
+//! independently operated servers, and stores each custodian's share at an
+//! ARID derived from the entry's ARID and the custodian's name. No operator
+//! holding fewer than the threshold of shares can read the envelope, or
+//! tell which of their entries belong together, and a get succeeds while
+//! that many custodians answer.
+
+use std::{fmt, time::Instant};
+
+use bc_components::{ARID, SSKRGroupSpec, SSKRSpec, SymmetricKey};
+use bc_envelope::prelude::*;
+use futures_util::{StreamExt, future::join_all, stream::FuturesUnordered};
+
+use crate::{
+    Error, HealthReport, KvStore, Receipt, Result, StoreCapabilities,
+    arid_derivation::derive_key,
+    receipt::Backend,
+    wire::{Codec, stored_size},
+};
+
+/// The most custodians SSKR can split a key among.
+pub const MAX_CUSTODIANS: usize = 16;
+
+/// The ARID a custodian named `custodian` stores its share of `arid` at.
+pub fn share_arid(arid: &ARID, custodian: &str) -> ARID {
+    let salt = format!("hubert-threshold-share-v1:{}", custodian);
+    let key: [u8; 32] = derive_key(salt.as_bytes(), arid, 32)
+        .try_into()
+        .unwrap();
+    ARID::from_data(key)
+}
+
+/// Why one custodian's share could not be stored or fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareFailure {
+    /// The custodian's name.
+    pub custodian: String,
+    /// What went wrong.
+    pub reason: FailureReason,
+}
+
+/// What went wrong with one custodian's share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureReason {
+    /// The custodian answered, but holds no share.
+    NotFound,
+    /// The custodian failed with this error.
+    Error(String),
+}
+
+impl fmt::Display for ShareFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.custodian, self.reason)
+    }
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "not found"),
+            Self::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The failures of `failures`, for an error message.
+pub(crate) fn describe_failures(failures: &[ShareFailure]) -> String {
+    let failures: Vec<String> =
+        failures.iter().map(ToString::to_string).collect();
+    failures.join("; ")
+}
+
+/// A [`KvStore`] that keeps each envelope in threshold custody across
+/// named stores.
+///
+/// Puts store a share with every custodian, and fail unless all of them
+/// took it, deleting the shares already stored so the put can be retried.
+/// Gets ask every custodian at once and reconstruct the envelope
+/// from the first `threshold` shares returned; if too few arrive, the error
+/// reports each custodian that failed and why.
+///
+/// # Example
+///
+/// ```no_run
+/// use hubert::{server::ServerKvClient, threshold::ThresholdKv};
+///
+/// // Any two of the three operators' servers recover an envelope
+/// let store = ThresholdKv::new(2)
+///     .with_custodian("a", ServerKvClient::new("http://hubert-a:45678"))
+///     .with_custodian("b", ServerKvClient::new("http://hubert-b:45678"))
+///     .with_custodian("c", ServerKvClient::new("http://hubert-c:45678"));
+/// ```
+pub struct ThresholdKv {
+    threshold: usize,
+    /// The custodians, sorted by name.
+    custodians: Vec<(String, Box<dyn KvStore>)>,
+}
+
+impl ThresholdKv {
+    /// A store with no custodians yet, needing `threshold` shares to
+    /// reconstruct an envelope.
+    pub fn new(threshold: usize) -> Self {
+        Self { threshold, custodians: Vec::new() }
+    }
+
+    /// Add a custodian, replacing any custodian with the same name.
+    ///
+    /// The name places the custodian's shares, so give it the same name
+    /// whenever it is attached to read what was stored with it.
+    pub fn with_custodian(
+        mut self,
+        name: impl Into<String>,
+        store: impl KvStore + 'static,
+    ) -> Self {
+        let name = name.into();
+        self.custodians.retain(|(existing, _)| *existing != name);
+        self.custodians.push((name, Box::new(store)));
+        self.custodians.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self
+    }
+
+    /// The number of shares needed to reconstruct an envelope.
+    pub fn threshold(&self) -> usize { self.threshold }
+
+    /// The custodians' names, sorted.
+    pub fn custodians(&self) -> impl Iterator<Item = &str> {
+        self.custodians.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The SSKR spec splitting a key among the custodians, failing with
+    /// [`Error::InvalidThreshold`] if they cannot meet the threshold.
+    fn spec(&self) -> Result<SSKRSpec> {
+        let invalid = || Error::InvalidThreshold {
+            threshold: self.threshold,
+            custodians: self.custodians.len(),
+        };
+        if self.threshold == 0
+            || self.custodians.len() < self.threshold
+            || self.custodians.len() > MAX_CUSTODIANS
+        {
+            return Err(invalid());
+        }
+        let group = SSKRGroupSpec::new(self.threshold, self.custodians.len())
+            .map_err(|_| invalid())?;
+        SSKRSpec::new(1, vec![group]).map_err(|_| invalid())
+    }
+
+    /// Collect shares from the custodian gets in `pending` until
+    /// `threshold` have arrived, and reconstruct the envelope from them.
+    async fn gather<'a, F>(
+        &self,
+        mut pending: FuturesUnordered<F>,
+    ) -> Result<Option<Envelope>>
+    where
+        F: Future<Output = (&'a str, Result<Option<Envelope>>)>,
+    {
+        self.spec()?;
+        let mut shares = Vec::with_capacity(self.threshold);
+        let mut failures = Vec::new();
+        while shares.len() < self.threshold
+            && let Some((custodian, result)) = pending.next().await
+        {
+            let reason = match result {
+                Ok(Some(share)) => {
+                    shares.push(share);
+                    continue;
+                }
+                Ok(None) => FailureReason::NotFound,
+                Err(e) => FailureReason::Error(e.to_string()),
+            };
+            failures.push(ShareFailure { custodian: custodian.into(), reason });
+        }
+
+        if shares.len() < self.threshold {
+            // Nothing stored anywhere is a miss, not a failure
+            if shares.is_empty()
+                && failures
+                    .iter()
+                    .all(|f| matches!(f.reason, FailureReason::NotFound))
+            {
+                return Ok(None);
+            }
+            return Err(Error::ThresholdNotMet {
+                action: "found",
+                succeeded: shares.len(),
+                needed: self.threshold,
+                failures,
+            });
+        }
+        let shares: Vec<&Envelope> = shares.iter().collect();
+        Ok(Some(Envelope::sskr_join(&shares)?.try_unwrap()?))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl KvStore for ThresholdKv {
+    async fn put(
+        &self,
+        arid: &ARID,
+        envelope: &Envelope,
+        ttl_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Receipt> {
+        let spec = self.spec()?;
+
+        // Check every custodian first so a put never half-overwrites
+        for (name, custodian) in &self.custodians {
+            if custodian.exists(&share_arid(arid, name)).await? {
+                return Err(Error::AlreadyExists { arid: arid.ur_string() });
+            }
+        }
+
+        let content_key = SymmetricKey::new();
+        let shares = envelope
+            .encrypt(&content_key)
+            .sskr_split_flattened(&spec, &content_key)?;
+        let results = join_all(self.custodians.iter().zip(&shares).map(
+            |((name, custodian), share)| {
+                let share_arid = share_arid(arid, name);
+                async move {
+                    custodian
+                        .put(&share_arid, share, ttl_seconds, verbose)
+                        .await
+                }
+            },
+        ))
+        .await;
+
+        let mut receipts = Vec::with_capacity(results.len());
+        let mut stored = Vec::with_capacity(results.len());
+        let mut failures = Vec::new();
+        for ((name, custodian), result) in self.custodians.iter().zip(results)
+        {
+            match result {
+                Ok(receipt) => {
+                    receipts.push(receipt);
+                    stored.push((name, custodian));
+                }
+                Err(e) => failures.push(ShareFailure {
+                    custodian: name.clone(),
+                    reason: FailureReason::Error(e.to_string()),
+                }),
+            }
+        }
+        if !failures.is_empty() {
+            // Best effort: a share left behind would make every retry fail
+            // the existence check above
+            join_all(stored.into_iter().map(|(name, custodian)| {
+                let share_arid = share_arid(arid, name);
+                async move {
+                    let _ = custodian.delete(&share_arid, verbose).await;
+                }
+            }))
+            .await;
+            return Err(Error::ThresholdNotMet {
+                action: "stored",
+                succeeded: receipts.len(),
+                needed: self.custodians.len(),
+                failures,
+            });
+        }
+
+        let mut receipt = Receipt::new(
+            Backend::Threshold,
+            *arid,
+            "threshold:",
+            stored_size(envelope, Codec::Dcbor),
+        );
+        // Every custodian applies its own TTL; report the shortest
+        if let Some(shortest) =
+            receipts.iter().filter(|r| r.ttl.is_some()).min_by_key(|r| r.ttl)
+        {
+            receipt.ttl = shortest.ttl;
+            receipt.expires_at = shortest.expires_at;
+        }
+        Ok(receipt.with_shards(receipts))
+    }
+
+    async fn get(
+        &self,
+        arid: &ARID,
+        timeout_seconds: Option<u64>,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.gather(
+            self.custodians
+                .iter()
+                .map(|(name, custodian)| async move {
+                    let share_arid = share_arid(arid, name);
+                    let result =
+                        custodian.get(&share_arid, timeout_seconds, verbose);
+                    (name.as_str(), result.await)
+                })
+                .collect(),
+        )
+        .await
+    }
+
+    async fn get_until(
+        &self,
+        arid: &ARID,
+        deadline: Instant,
+        verbose: bool,
+    ) -> Result<Option<Envelope>> {
+        self.gather(
+            self.custodians
+                .iter()
+                .map(|(name, custodian)| async move {
+                    let share_arid = share_arid(arid, name);
+                    let result =
+                        custodian.get_until(&share_arid, deadline, verbose);
+                    (name.as_str(), result.await)
+                })
+                .collect(),
+        )
+        .await
+    }
+
+    /// Whether enough custodians hold shares to reconstruct the envelope.
+    ///
+    /// Custodians that fail are not counted, so this succeeds while
+    /// `threshold` of them answer that they hold shares. It fails only when
+    /// the custodians that failed decide the answer: too few hold shares,
+    /// but enough might with them.
+    async fn exists(&self, arid: &ARID) -> Result<bool> {
+        let results =
+            join_all(self.custodians.iter().map(|(name, custodian)| {
+                let share_arid = share_arid(arid, name);
+                async move { custodian.exists(&share_arid).await }
+            }))
+            .await;
+
+        let mut held = 0;
+        let mut failures = Vec::new();
+        for ((name, _), result) in self.custodians.iter().zip(results) {
+            match result {
+                Ok(true) => held += 1,
+                Ok(false) => {}
+                Err(e) => failures.push(ShareFailure {
+                    custodian: name.clone(),
+                    reason: FailureReason::Error(e.to_string()),
+                }),
+            }
+        }
+        let needed = self.threshold.max(1);
+        if held < needed && held + failures.len() >= needed {
+            return Err(Error::ThresholdNotMet {
+                action: "found",
+                succeeded: held,
+                needed,
+                failures,
+            });
+        }
+        Ok(held >= needed)
+    }
+
+    /// Deletes every share, reporting a removal if any custodian had one.
+    async fn delete(&self, arid: &ARID, verbose: bool) -> Result<bool> {
+        let results =
+            join_all(self.custodians.iter().map(|(name, custodian)| {
+                let share_arid = share_arid(arid, name);
+                async move { custodian.delete(&share_arid, verbose).await }
+            }))
+            .await;
+        let removed = results.into_iter().collect::<Result<Vec<_>>>()?;
+        Ok(removed.into_iter().any(|removed| removed))
+    }
+
+    /// Puts go to every custodian, so only what every custodian supports,
+    /// without updates. Size limits apply to the shares, which are the
+    /// encrypted envelope plus an SSKR share.
+    fn capabilities(&self) -> StoreCapabilities {
+        let capabilities = self
+            .custodians
+            .iter()
+            .map(|(_, custodian)| custodian.capabilities())
+            .reduce(StoreCapabilities::intersect)
+            .unwrap_or_default();
+        StoreCapabilities { update: false, stat: false, ..capabilities }
+    }
+
+    /// Probes every custodian; the store is healthy only if all of them
+    /// are, since puts need every one.
+    async fn health(&self) -> HealthReport {
+        let reports = join_all(self.custodians.iter().map(
+            |(name, custodian)| async move {
+                let mut report = custodian.health().await;
+                report.backend = format!("{} ({})", name, report.backend);
+                report
+            },
+        ))
+        .await;
+        let mut report = HealthReport::combine("threshold", reports);
+        if let Err(e) = self.spec() {
+            report.error = Some(e.to_string());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_arids_are_distinct() {
+        let arid = ARID::new();
+        assert_eq!(share_arid(&arid, "a"), share_arid(&arid, "a"));
+        assert_ne!(share_arid(&arid, "a"), share_arid(&arid, "b"));
+        assert_ne!(share_arid(&arid, "a"), arid);
+    }
+}
@@ -0,0 +1,184 @@
+//! Validation of untrusted UR strings before they reach the UR decoders.
+//!
+//! ARIDs and envelopes arrive as `ur:` strings from HTTP bodies and command
+//! lines. These helpers reject over-long, empty, or non-UR input before it is
+//! handed to `ARID::from_ur_string` / `Envelope::from_ur_string`, so a hostile
+//! body costs a length check rather than a full bytewords decode.
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+
+use crate::{Error, Result};
+
+/// Maximum accepted length of a `ur:arid` string.
+///
+/// A canonical `ur:arid` is 84 characters; the slack allows for uppercase
+/// (QR-friendly) variants and surrounding whitespace.
+pub const MAX_ARID_UR_LENGTH: usize = 128;
+
+/// Maximum accepted length of a `ur:envelope` string.
+///
+/// Bytewords minimal encoding uses two characters per byte, so this admits
+/// envelopes somewhat larger than the 10 MB IPFS limit.
+pub const MAX_ENVELOPE_UR_LENGTH: usize = 21 * 1024 * 1024;
+
+/// Check that `input` looks like a `ur:<ur_type>/...` string of acceptable
+/// length, without decoding it.
+///
+/// Leading and trailing whitespace is ignored. Returns the trimmed input.
+///
+/// # Errors
+///
+/// - `Error::InputTooLong` if the trimmed input exceeds `max_length` bytes
+/// - `Error::InvalidUrInput` if the input is empty, contains characters that
+///   cannot appear in a UR, or does not carry the expected `ur:<ur_type>/`
+///   prefix
+pub fn check_ur_input<'a>(
+    input: &'a str,
+    ur_type: &'static str,
+    max_length: usize,
+) -> Result<&'a str> {
+    // Length is checked before trimming so padding can't be used to smuggle
+    // an unbounded body past the limit.
+    if input.len() > max_length + 2 {
+        return Err(Error::InputTooLong {
+            ur_type,
+            length: input.len(),
+            limit: max_length,
+        });
+    }
+
+    let input = input.trim();
+    if input.len() > max_length {
+        return Err(Error::InputTooLong {
+            ur_type,
+            length: input.len(),
+            limit: max_length,
+        });
+    }
+
+    if input.is_empty() {
+        return Err(Error::InvalidUrInput {
+            ur_type,
+            reason: "input is empty".to_string(),
+        });
+    }
+
+    if let Some(position) = input
+        .bytes()
+        .position(|b| !(b.is_ascii_alphanumeric() || b == b':' || b == b'/'))
+    {
+        return Err(Error::InvalidUrInput {
+            ur_type,
+            reason: format!("invalid character at position {}", position),
+        });
+    }
+
+    let prefix = format!("ur:{}/", ur_type);
+    let has_prefix = input
+        .get(..prefix.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(&prefix));
+    if !has_prefix {
+        return Err(Error::InvalidUrInput {
+            ur_type,
+            reason: format!("expected {} prefix", prefix),
+        });
+    }
+
+    Ok(input)
+}
+
+/// Parse an untrusted `ur:arid` string after validating it.
+///
+/// # Errors
+///
+/// Returns `Error::InputTooLong` or `Error::InvalidUrInput` for malformed
+/// input, and `Error::InvalidArid` if the UR fails to decode.
+pub fn parse_arid_ur(input: &str) -> Result<ARID> {
+    let input = check_ur_input(input, "arid", MAX_ARID_UR_LENGTH)?;
+    ARID::from_ur_string(input).map_err(|_| Error::InvalidArid)
+}
+
+/// Parse an untrusted `ur:envelope` string after validating it.
+///
+/// # Errors
+///
+/// Returns `Error::InputTooLong` or `Error::InvalidUrInput` for malformed
+/// input, and `Error::InvalidEnvelope` if the UR fails to decode.
+pub fn parse_envelope_ur(input: &str) -> Result<Envelope> {
+    let input = check_ur_input(input, "envelope", MAX_ENVELOPE_UR_LENGTH)?;
+    Envelope::from_ur_string(input).map_err(|_| Error::InvalidEnvelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arid_roundtrip() {
+        bc_components::register_tags();
+        let arid = ARID::new();
+        let parsed = parse_arid_ur(&arid.ur_string()).unwrap();
+        assert_eq!(parsed, arid);
+    }
+
+    #[test]
+    fn test_surrounding_whitespace_accepted() {
+        bc_components::register_tags();
+        let arid = ARID::new();
+        let padded = format!("  {}\n", arid.ur_string());
+        assert_eq!(parse_arid_ur(&padded).unwrap(), arid);
+    }
+
+    #[test]
+    fn test_uppercase_accepted() {
+        bc_components::register_tags();
+        let arid = ARID::new();
+        let upper = arid.ur_string().to_uppercase();
+        assert_eq!(parse_arid_ur(&upper).unwrap(), arid);
+    }
+
+    #[test]
+    fn test_too_long_rejected() {
+        let long = format!("ur:arid/{}", "a".repeat(MAX_ARID_UR_LENGTH));
+        assert!(matches!(
+            parse_arid_ur(&long),
+            Err(Error::InputTooLong { ur_type: "arid", .. })
+        ));
+    }
+
+    #[test]
+    fn test_non_ascii_rejected() {
+        assert!(matches!(
+            parse_arid_ur("ur:arid/hdcx\u{00e9}"),
+            Err(Error::InvalidUrInput { ur_type: "arid", .. })
+        ));
+    }
+
+    #[test]
+    fn test_wrong_type_rejected() {
+        bc_components::register_tags();
+        let envelope = Envelope::new("test");
+        assert!(matches!(
+            parse_arid_ur(&envelope.ur_string()),
+            Err(Error::InvalidUrInput { ur_type: "arid", .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_rejected() {
+        assert!(matches!(
+            parse_envelope_ur("   "),
+            Err(Error::InvalidUrInput { ur_type: "envelope", .. })
+        ));
+    }
+
+    #[test]
+    fn test_undecodable_envelope_rejected() {
+        assert!(matches!(
+            parse_envelope_ur("ur:envelope/notbytewords"),
+            Err(Error::InvalidEnvelope)
+        ));
+    }
+}
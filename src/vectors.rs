@@ -0,0 +1,168 @@
+//! Deterministic test vectors for implementations of Hubert in other
+//! languages.
+//!
+//! Each vector takes a fixed ARID and envelope through every derivation and
+//! encoding in [`crate::wire`], so another implementation can check that it
+//! finds, obfuscates, and references the same bytes. `hubert vectors
+//! generate` writes them as JSON or CBOR; both have the shape of
+//! [`TestVectors`], with binary values as lowercase hex strings and ARIDs
+//! and envelopes as URs.
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_ur::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arid_derivation::derive_ipfs_key_name,
+    mainline::MainlineDhtKv,
+    wire::{Location, ObfuscatedPayload, Reference},
+};
+
+/// Version of the vector file format, bumped whenever a field changes.
+pub const TEST_VECTORS_VERSION: u64 = 1;
+
+/// A set of test vectors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u64,
+    pub vectors: Vec<TestVector>,
+}
+
+impl TestVectors {
+    /// The vectors as a deterministic CBOR map, with the same keys as the
+    /// JSON.
+    pub fn to_cbor_data(&self) -> Vec<u8> {
+        CBOR::from(self.clone()).to_cbor_data()
+    }
+}
+
+/// One ARID and envelope, and everything Hubert derives from them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    pub name: String,
+    /// The ARID's 32 bytes.
+    pub arid: String,
+    pub arid_ur: String,
+    pub envelope: String,
+    /// The envelope's tagged dCBOR.
+    pub envelope_cbor: String,
+    /// Key material for the Mainline DHT.
+    pub mainline_key: String,
+    /// Public key of the Mainline DHT item holding the envelope.
+    pub mainline_public_key: String,
+    /// Name of the IPNS key publishing the envelope.
+    pub ipfs_key_name: String,
+    /// The envelope as stored in the DHT item or IPFS block.
+    pub obfuscated_payload: String,
+    /// A reference envelope pointing to the envelope stored in IPFS at the
+    /// ARID.
+    pub reference_envelope: String,
+}
+
+/// Generate the test vectors. The result is the same on every run and
+/// every platform.
+pub fn generate() -> TestVectors {
+    bc_components::register_tags();
+
+    let mut sequential = [0u8; 32];
+    for (i, byte) in sequential.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let inputs = [
+        ("zero", [0u8; 32], Envelope::new("Hello")),
+        ("sequential", sequential, Envelope::new("Hello")),
+        (
+            "ones",
+            [0xffu8; 32],
+            Envelope::new("Hubert")
+                .add_assertion(known_values::NOTE, "test vector"),
+        ),
+    ];
+
+    let vectors = inputs
+        .into_iter()
+        .map(|(name, bytes, envelope)| vector(name, bytes, &envelope))
+        .collect();
+    TestVectors { version: TEST_VECTORS_VERSION, vectors }
+}
+
+fn vector(name: &str, bytes: [u8; 32], envelope: &Envelope) -> TestVector {
+    let arid = ARID::from_data(bytes);
+    let cbor = envelope.to_cbor_data();
+    let reference = Reference::new(arid, cbor.len(), Location::Ipfs);
+    TestVector {
+        name: name.to_string(),
+        arid: hex::encode(bytes),
+        arid_ur: arid.ur_string(),
+        envelope: envelope.ur_string(),
+        envelope_cbor: hex::encode(&cbor),
+        mainline_key: hex::encode(hubert_core::derive_mainline_key(&bytes)),
+        mainline_public_key: hex::encode(MainlineDhtKv::public_key(&arid)),
+        ipfs_key_name: derive_ipfs_key_name("", &arid),
+        obfuscated_payload: hex::encode(
+            ObfuscatedPayload::encode(&arid, envelope).as_bytes(),
+        ),
+        reference_envelope: reference.to_envelope().ur_string(),
+    }
+}
+
+impl From<TestVector> for CBOR {
+    fn from(vector: TestVector) -> Self {
+        let mut map = Map::new();
+        map.insert("name", vector.name);
+        map.insert("arid", vector.arid);
+        map.insert("arid_ur", vector.arid_ur);
+        map.insert("envelope", vector.envelope);
+        map.insert("envelope_cbor", vector.envelope_cbor);
+        map.insert("mainline_key", vector.mainline_key);
+        map.insert("mainline_public_key", vector.mainline_public_key);
+        map.insert("ipfs_key_name", vector.ipfs_key_name);
+        map.insert("obfuscated_payload", vector.obfuscated_payload);
+        map.insert("reference_envelope", vector.reference_envelope);
+        map.into()
+    }
+}
+
+impl From<TestVectors> for CBOR {
+    fn from(vectors: TestVectors) -> Self {
+        let mut map = Map::new();
+        map.insert("version", vectors.version);
+        let list: Vec<CBOR> =
+            vectors.vectors.into_iter().map(CBOR::from).collect();
+        map.insert("vectors", list);
+        map.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ur_input::{parse_arid_ur, parse_envelope_ur};
+
+    #[test]
+    fn test_vectors_deterministic() {
+        assert_eq!(generate(), generate());
+        assert_eq!(generate().to_cbor_data(), generate().to_cbor_data());
+    }
+
+    #[test]
+    fn test_vectors_consistent() {
+        for vector in generate().vectors {
+            let arid = parse_arid_ur(&vector.arid_ur).unwrap();
+            assert_eq!(hex::encode(arid.data()), vector.arid);
+
+            let envelope = parse_envelope_ur(&vector.envelope).unwrap();
+            let payload = ObfuscatedPayload::from_bytes(
+                hex::decode(&vector.obfuscated_payload).unwrap(),
+            );
+            assert_eq!(payload.decode(&arid).unwrap(), envelope);
+
+            let reference = parse_envelope_ur(&vector.reference_envelope)
+                .map(|e| Reference::from_envelope(&e).unwrap())
+                .unwrap();
+            assert_eq!(reference.arid, arid);
+            assert_eq!(reference.size * 2, vector.envelope_cbor.len());
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! The formats Hubert writes to the network, as types with one encoder and
+//! one decoder each, so implementations in other languages can match them
+//! byte for byte.
+//!
+//! - [`ObfuscatedPayload`]: the value of a Mainline DHT item or IPFS block,
+//!   whose size [`stored_size`] measures against backend limits.
+//! - [`Reference`]: the envelope hybrid storage leaves in the DHT in place of
+//!   one too large for it.
+//! - [`PutBody`], [`PutManyBody`], [`ReserveBody`], [`GetManyBody`], and
+//!   [`GetManyResponse`]: the text bodies of server requests and responses.
+//! - [`ReceiptStatus`]: the JSON a server answers a put or a `/receipts`
+//!   request with.
+//!
+//! The golden vectors in `tests/test_wire.rs` pin each format; a change that
+//! breaks one breaks every other implementation.
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+
+#[cfg(feature = "hybrid")]
+pub use crate::hybrid::{Location, Reference};
+pub use crate::server::ReceiptStatus;
+#[cfg(feature = "server-client")]
+pub use crate::server::wire::{
+    GetManyBody, GetManyResponse, PutBody, PutManyBody, ReserveBody,
+};
+use crate::{Result, arid_derivation::obfuscate_with_arid};
+
+/// How a backend lays out a stored envelope's bytes, for [`stored_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// An [`ObfuscatedPayload`] as is, the way IPFS blocks and Hubert servers
+    /// hold it.
+    Dcbor,
+    /// An [`ObfuscatedPayload`] as the value of a BEP-44 DHT item, which DHT
+    /// nodes measure bencoded: prefixed with its decimal length and a colon.
+    Bep44,
+}
+
+/// The bytes `envelope` takes stored with `codec`, counted the way the
+/// backend's size limit counts them.
+///
+/// This is the one measure of envelope size: the DHT's 1000-byte limit
+/// applies to [`Codec::Bep44`], so the largest envelope the DHT holds is
+/// 996 bytes of dCBOR.
+pub fn stored_size(envelope: &Envelope, codec: Codec) -> usize {
+    // Obfuscation preserves length
+    let len = envelope.to_cbor_data().len();
+    match codec {
+        Codec::Dcbor => len,
+        Codec::Bep44 => len + len.to_string().len() + 1,
+    }
+}
+
+/// The bytes Mainline DHT items and IPFS blocks hold: an envelope's tagged
+/// dCBOR, obfuscated with a keystream derived from the ARID it is stored at
+/// (see [`hubert_core::obfuscate_with_arid`]).
+///
+/// Without the ARID the payload is indistinguishable from random bytes, and
+/// it is exactly as long as the envelope's dCBOR. Stores given a derivation
+/// domain obfuscate with [`encode_in`](Self::encode_in) instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObfuscatedPayload(Vec<u8>);
+
+impl ObfuscatedPayload {
+    /// Obfuscate `envelope` for storage at `arid`.
+    pub fn encode(arid: &ARID, envelope: &Envelope) -> Self {
+        Self::encode_in("", arid, envelope)
+    }
+
+    /// Obfuscate `envelope` for storage at `arid` in deployment `domain`
+    /// (see [`hubert_core::domain_salt`]).
+    pub fn encode_in(domain: &str, arid: &ARID, envelope: &Envelope) -> Self {
+        Self(obfuscate_with_arid(domain, arid, envelope.to_cbor_data()))
+    }
+
+    /// Wrap bytes read from the network.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self { Self(bytes.into()) }
+
+    /// Recover the envelope stored at `arid`.
+    pub fn decode(&self, arid: &ARID) -> Result<Envelope> {
+        self.decode_in("", arid)
+    }
+
+    /// Recover the envelope stored at `arid` in deployment `domain`.
+    pub fn decode_in(&self, domain: &str, arid: &ARID) -> Result<Envelope> {
+        Ok(Envelope::try_from_cbor_data(obfuscate_with_arid(
+            domain, arid, &self.0,
+        ))?)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] { &self.0 }
+
+    pub fn into_bytes(self) -> Vec<u8> { self.0 }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
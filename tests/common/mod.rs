@@ -1 +0,0 @@
-pub mod kv_tests;
@@ -0,0 +1,73 @@
+use std::{process::Command, time::Duration};
+
+/// Kubo image the matrix runs against.
+const IMAGE: &str = "ipfs/kubo:v0.38.1";
+
+/// A Kubo (IPFS) daemon for the matrix, in a Docker container that is
+/// removed on drop, including when a test panics.
+pub struct Kubo {
+    container: Option<String>,
+    url: String,
+}
+
+impl Kubo {
+    /// Use the daemon at `HUBERT_E2E_IPFS` if set; otherwise start a
+    /// container and wait until its RPC API answers.
+    pub async fn start() -> Self {
+        if let Ok(url) = std::env::var("HUBERT_E2E_IPFS") {
+            return Self { container: None, url };
+        }
+
+        let port = super::free_port();
+        let output = Command::new("docker")
+            .args(["run", "--detach", "--rm", "--publish"])
+            .arg(format!("127.0.0.1:{}:5001", port))
+            .arg(IMAGE)
+            .output()
+            .expect("Failed to run docker");
+        assert!(
+            output.status.success(),
+            "docker run failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let kubo = Self {
+            container: Some(
+                String::from_utf8_lossy(&output.stdout).trim().into(),
+            ),
+            url: format!("http://127.0.0.1:{}", port),
+        };
+        kubo.wait_ready().await;
+        kubo
+    }
+
+    /// The daemon's RPC URL.
+    pub fn url(&self) -> &str { &self.url }
+
+    async fn wait_ready(&self) {
+        let client = reqwest::Client::new();
+        let version = format!("{}/api/v0/version", self.url);
+        for _ in 0..120 {
+            let response = client
+                .post(&version)
+                .timeout(Duration::from_secs(1))
+                .send()
+                .await;
+            if response.is_ok_and(|r| r.status().is_success()) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        panic!("Kubo did not answer at {} within 60s", self.url);
+    }
+}
+
+impl Drop for Kubo {
+    fn drop(&mut self) {
+        if let Some(container) = &self.container {
+            let _ = Command::new("docker")
+                .args(["rm", "--force", container])
+                .output();
+        }
+    }
+}
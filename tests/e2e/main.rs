@@ -0,0 +1,132 @@
+//! End-to-end matrix running the unified KvStore suite against every
+//! backend, with the services they need started in Docker containers.
+//!
+//! Skipped unless `HUBERT_E2E` is set, since it needs Docker and network
+//! access:
+//!
+//! ```text
+//! HUBERT_E2E=1 cargo test --test e2e -- --nocapture
+//! ```
+//!
+//! The IPFS and hybrid runs each start a Kubo container and remove it when
+//! they finish, pass or fail. Set `HUBERT_E2E_IPFS` to the RPC URL of a
+//! running daemon to use it instead.
+
+#![cfg(all(feature = "hybrid", feature = "server"))]
+
+mod kubo;
+
+use std::net::TcpListener;
+
+use hubert::{
+    hybrid::HybridKv,
+    ipfs::IpfsKv,
+    mainline::MainlineDhtKv,
+    server::{MemoryKv, Server, ServerConfig, ServerKvClient, SqliteKv},
+    testkit::run_suite,
+};
+use kubo::Kubo;
+use tokio::time::{Duration, sleep};
+
+/// Return early unless the matrix was asked for.
+macro_rules! require_e2e {
+    () => {
+        if std::env::var_os("HUBERT_E2E").is_none() {
+            eprintln!("⚠️  Skipping end-to-end test: set HUBERT_E2E=1");
+            return;
+        }
+        bc_components::register_tags();
+    };
+}
+
+/// A local TCP port nothing is listening on.
+fn free_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn e2e_memory() {
+    require_e2e!();
+    let store = MemoryKv::new();
+    let setup = || {
+        let store = store.clone();
+        async move { store }
+    };
+    run_suite(setup, Duration::from_secs(1), None).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn e2e_sqlite() {
+    require_e2e!();
+    let path = std::env::temp_dir()
+        .join(format!("hubert-e2e-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = SqliteKv::new(&path).unwrap();
+    let setup = || {
+        let store = store.clone();
+        async move { store }
+    };
+    run_suite(setup, Duration::from_secs(1), None).await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn e2e_server() {
+    require_e2e!();
+    let port = free_port();
+    let server = Server::new_memory(ServerConfig {
+        port,
+        quiet: true,
+        ..Default::default()
+    });
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", port);
+    run_suite(
+        || std::future::ready(ServerKvClient::new(&url)),
+        Duration::from_secs(1),
+        None,
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn e2e_mainline() {
+    require_e2e!();
+    run_suite(
+        || async { MainlineDhtKv::new().await.unwrap() },
+        Duration::from_secs(5),
+        Some(1000),
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn e2e_ipfs() {
+    require_e2e!();
+    let kubo = Kubo::start().await;
+    run_suite(
+        || std::future::ready(IpfsKv::new(kubo.url())),
+        Duration::from_secs(5),
+        Some(10 * 1024 * 1024),
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn e2e_hybrid() {
+    require_e2e!();
+    let kubo = Kubo::start().await;
+    let url = kubo.url().to_string();
+    run_suite(
+        || {
+            let url = url.clone();
+            async move { HybridKv::new(&url).await.unwrap() }
+        },
+        Duration::from_secs(5),
+        None,
+    )
+    .await;
+}
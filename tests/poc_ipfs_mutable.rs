@@ -1,3 +1,5 @@
+#![cfg(feature = "ipfs")]
+
 use std::{
     io::Cursor,
     time::{SystemTime, UNIX_EPOCH},
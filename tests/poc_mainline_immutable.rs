@@ -1,3 +1,5 @@
+#![cfg(feature = "mainline")]
+
 use anyhow::Result;
 use mainline::{Dht, Testnet, async_dht::AsyncDht};
 use tokio::time::{Duration, sleep};
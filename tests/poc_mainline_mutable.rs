@@ -1,3 +1,5 @@
+#![cfg(feature = "mainline")]
+
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
@@ -0,0 +1,111 @@
+use bc_components::{ARID, DigestProvider};
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use hubert::{
+    Error, KvStore, MemoryKv,
+    attachments::{
+        Attachment, add_attachment, attachment_arid, attachments,
+        fetch_attachment, fetch_attachments, store_attachment,
+    },
+};
+
+/// Test storing a message with attachments and fetching them back
+#[tokio::test]
+async fn test_attachments_roundtrip() {
+    let store = MemoryKv::new();
+    let document = Envelope::new("A large document");
+    let psbt = Envelope::new("A PSBT");
+
+    let mut message = Envelope::new("Please sign");
+    for payload in [&document, &psbt] {
+        let attachment = store_attachment(&store, payload, None, false)
+            .await
+            .unwrap();
+        message = add_attachment(message, &attachment);
+    }
+    assert_eq!(attachments(&message).unwrap().len(), 2);
+
+    let mut fetched = fetch_attachments(&store, &message, Some(1), false)
+        .await
+        .unwrap();
+    fetched.sort_by_key(|e| e.ur_string());
+    let mut expected = vec![document, psbt];
+    expected.sort_by_key(|e| e.ur_string());
+    assert_eq!(fetched, expected);
+}
+
+/// Test that an attachment not matching its digest is rejected
+#[tokio::test]
+async fn test_attachment_digest_mismatch() {
+    let store = MemoryKv::new();
+    let payload = Envelope::new("Original");
+    let attachment = store_attachment(&MemoryKv::new(), &payload, None, false)
+        .await
+        .unwrap();
+
+    // Someone else published different content at the attachment's ARID
+    store
+        .put(&attachment.arid, &Envelope::new("Substitute"), None, false)
+        .await
+        .unwrap();
+
+    let result = fetch_attachment(&store, &attachment, Some(1), false).await;
+    assert!(matches!(
+        result,
+        Err(Error::AttachmentDigestMismatch { .. })
+    ));
+}
+
+/// Test that a missing attachment is reported
+#[tokio::test]
+async fn test_attachment_not_found() {
+    let payload = Envelope::new("Never stored here");
+    let attachment = store_attachment(&MemoryKv::new(), &payload, None, false)
+        .await
+        .unwrap();
+
+    let result =
+        fetch_attachment(&MemoryKv::new(), &attachment, Some(1), false).await;
+    assert!(matches!(result, Err(Error::AttachmentNotFound { .. })));
+}
+
+/// Test that identical payloads share one entry at their digest's ARID
+#[tokio::test]
+async fn test_attachment_deduplicated() {
+    let store = MemoryKv::new();
+    let payload = Envelope::new("Shared document");
+    let first = store_attachment(&store, &payload, None, false)
+        .await
+        .unwrap();
+    let second = store_attachment(&store, &payload, None, false)
+        .await
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.arid, attachment_arid(&first.digest));
+
+    // A squatter's envelope at the ARID is not taken for the payload
+    let squatted = MemoryKv::new();
+    squatted
+        .put(&first.arid, &Envelope::new("Squatter"), None, false)
+        .await
+        .unwrap();
+    let result = store_attachment(&squatted, &payload, None, false).await;
+    assert!(matches!(
+        result,
+        Err(Error::AttachmentDigestMismatch { .. })
+    ));
+}
+
+/// Test that a locator whose ARID is not derived from its digest is
+/// rejected
+#[tokio::test]
+async fn test_attachment_wrong_location() {
+    let store = MemoryKv::new();
+    let payload = Envelope::new("Misplaced");
+    let arid = ARID::new();
+    store.put(&arid, &payload, None, false).await.unwrap();
+
+    let attachment = Attachment { arid, digest: payload.digest(), size: 0 };
+    let result = fetch_attachment(&store, &attachment, Some(1), false).await;
+    assert!(matches!(result, Err(Error::InvalidAttachment(_))));
+}
@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use hubert::{Error, KvStore, MemoryKv, chaos::ChaosKv, sharded::ShardedKv};
+
+/// Put with retries, treating a transient failure as possibly applied: if a
+/// retry finds the ARID taken, the put succeeded only if the stored envelope
+/// is ours.
+async fn put_with_retries(
+    store: &impl KvStore,
+    arid: &ARID,
+    envelope: &Envelope,
+    attempts: usize,
+) -> hubert::Result<()> {
+    let mut last_error = None;
+    for _ in 0..attempts {
+        match store.put(arid, envelope, None, false).await {
+            Ok(_) => return Ok(()),
+            Err(e @ Error::AlreadyExists { .. }) if last_error.is_some() => {
+                match store.get(arid, Some(0), false).await {
+                    Ok(Some(stored)) if stored == *envelope => return Ok(()),
+                    Ok(_) => return Err(e),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            Err(
+                e @ (Error::Injected { .. } | Error::ResponseDropped { .. }),
+            ) => last_error = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_error.unwrap())
+}
+
+/// Test that a wrapper without faults behaves like its inner store
+#[tokio::test]
+async fn test_chaos_passthrough() {
+    let store = ChaosKv::new(MemoryKv::new());
+    let arid = ARID::new();
+    let envelope = Envelope::new("Calm");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+    assert_eq!(
+        store.get(&arid, Some(0), false).await.unwrap(),
+        Some(envelope)
+    );
+    assert!(store.exists(&arid).await.unwrap());
+}
+
+/// Test that injected errors never reach the inner store
+#[tokio::test]
+async fn test_injected_errors_skip_store() {
+    let store = ChaosKv::new(MemoryKv::new()).with_error_rate(1.0);
+    let arid = ARID::new();
+    let result = store.put(&arid, &Envelope::new("Lost"), None, false).await;
+    assert!(matches!(result, Err(Error::Injected { operation: "put" })));
+    assert!(!store.inner().exists(&arid).await.unwrap());
+}
+
+/// Test that a dropped put response still leaves the entry stored, so a
+/// naive retry fails with AlreadyExists
+#[tokio::test]
+async fn test_dropped_put_took_effect() {
+    let store = ChaosKv::new(MemoryKv::new()).with_drop_rate(1.0);
+    let arid = ARID::new();
+    let envelope = Envelope::new("Applied");
+
+    let result = store.put(&arid, &envelope, None, false).await;
+    assert!(matches!(result, Err(Error::ResponseDropped { .. })));
+    assert_eq!(
+        store.inner().get(&arid, Some(0), false).await.unwrap(),
+        Some(envelope.clone())
+    );
+    let retry = store.inner().put(&arid, &envelope, None, false).await;
+    assert!(matches!(retry, Err(Error::AlreadyExists { .. })));
+}
+
+/// Test that retrying through seeded random faults stores every envelope
+/// exactly once, never mistaking another writer's entry for our own
+#[tokio::test]
+async fn test_retries_under_partial_failures() {
+    let store = ChaosKv::new(MemoryKv::new())
+        .with_error_rate(0.3)
+        .with_drop_rate(0.3)
+        .with_seed(2024);
+
+    for i in 0..50 {
+        let arid = ARID::new();
+        let envelope = Envelope::new(format!("Entry {}", i));
+        put_with_retries(&store, &arid, &envelope, 20)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.inner().get(&arid, Some(0), false).await.unwrap(),
+            Some(envelope)
+        );
+    }
+
+    // Someone else's entry is reported, not adopted
+    let arid = ARID::new();
+    store
+        .inner()
+        .put(&arid, &Envelope::new("Theirs"), None, false)
+        .await
+        .unwrap();
+    let flaky = ChaosKv::new(store.into_inner())
+        .with_drop_rate(1.0)
+        .with_seed(1);
+    let result = put_with_retries(&flaky, &arid, &Envelope::new("Ours"), 3);
+    assert!(result.await.is_err());
+}
+
+/// Test that a sharded tier keeps serving gets while a replica fails
+#[tokio::test]
+async fn test_sharded_survives_failing_replica() {
+    let (a, b, c) = (MemoryKv::new(), MemoryKv::new(), MemoryKv::new());
+    let healthy = ShardedKv::new(3)
+        .with_shard("a", a.clone())
+        .with_shard("b", b.clone())
+        .with_shard("c", c.clone());
+    let arid = ARID::new();
+    let envelope = Envelope::new("Replicated");
+    healthy.put(&arid, &envelope, None, false).await.unwrap();
+
+    let degraded = ShardedKv::new(3)
+        .with_shard("a", ChaosKv::new(a.clone()).with_error_rate(1.0))
+        .with_shard(
+            "b",
+            ChaosKv::new(b.clone()).with_delay(
+                Duration::from_millis(50),
+                Duration::from_millis(100),
+            ),
+        )
+        .with_shard("c", c.clone());
+    assert_eq!(
+        degraded.get(&arid, Some(0), false).await.unwrap(),
+        Some(envelope)
+    );
+
+    // With every replica failing the error surfaces
+    let down =
+        ShardedKv::new(1).with_shard("a", ChaosKv::new(a).with_error_rate(1.0));
+    assert!(down.get(&arid, Some(0), false).await.is_err());
+}
+
+/// Test that a deadline cuts off a put slowed by injected delay
+#[tokio::test]
+async fn test_deadline_under_delay() {
+    let store = ChaosKv::new(MemoryKv::new())
+        .with_delay(Duration::from_millis(500), Duration::from_millis(500));
+    let deadline = Instant::now() + Duration::from_millis(100);
+    let result = store
+        .put_until(&ARID::new(), &Envelope::new("Late"), None, deadline, false)
+        .await;
+    assert!(matches!(result, Err(Error::DeadlineExceeded)));
+    assert!(Instant::now() < deadline + Duration::from_millis(300));
+}
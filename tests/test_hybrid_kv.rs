@@ -1,10 +1,10 @@
-mod common;
+#![cfg(feature = "hybrid")]
 
 use std::sync::Arc;
 
 use bc_components::ARID;
 use bc_envelope::Envelope;
-use hubert::{KvStore, hybrid::HybridKv};
+use hubert::{KvStore, hybrid::HybridKv, testkit};
 use mainline::Testnet;
 
 /// Test Hybrid storage layer using the unified test suite.
@@ -28,28 +28,39 @@ async fn setup() -> HybridKv {
 #[ignore] // Requires IPFS daemon
 async fn hybrid_basic_roundtrip() {
     bc_components::register_tags();
-    common::kv_tests::test_basic_roundtrip(&setup().await).await;
+    testkit::test_basic_roundtrip(&setup().await).await;
 }
 
 #[tokio::test]
 #[ignore] // Requires IPFS daemon
 async fn hybrid_write_once() {
     bc_components::register_tags();
-    common::kv_tests::test_write_once(&setup().await).await;
+    testkit::test_write_once(&setup().await).await;
 }
 
 #[tokio::test]
 #[ignore] // Requires IPFS daemon
 async fn hybrid_nonexistent_arid() {
     bc_components::register_tags();
-    common::kv_tests::test_nonexistent_arid(&setup().await).await;
+    testkit::test_nonexistent_arid(&setup().await).await;
 }
 
 #[tokio::test]
 #[ignore] // Requires IPFS daemon
 async fn hybrid_multiple_arids() {
     bc_components::register_tags();
-    common::kv_tests::test_multiple_arids(&setup().await).await;
+    testkit::test_multiple_arids(&setup().await).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires IPFS daemon
+async fn hybrid_zero_timeout() {
+    bc_components::register_tags();
+    testkit::test_zero_timeout(
+        &setup().await,
+        tokio::time::Duration::from_secs(1),
+    )
+    .await;
 }
 
 #[tokio::test]
@@ -68,12 +79,12 @@ async fn hybrid_small_envelope_uses_dht_only() {
 
     let receipt = result.unwrap();
     assert!(
-        receipt.contains("DHT"),
+        receipt.location.starts_with("dht://"),
         "Receipt should indicate DHT storage: {}",
         receipt
     );
     assert!(
-        !receipt.contains("IPFS"),
+        receipt.cid.is_none() && receipt.reference.is_none(),
         "Receipt should not mention IPFS for small envelope: {}",
         receipt
     );
@@ -104,12 +115,12 @@ async fn hybrid_large_envelope_uses_ipfs_indirection() {
 
     let receipt = result.unwrap();
     assert!(
-        receipt.contains("IPFS"),
+        receipt.cid.is_some() && receipt.to_string().contains("IPFS"),
         "Receipt should indicate IPFS storage: {}",
         receipt
     );
     assert!(
-        receipt.contains("ref:"),
+        receipt.reference.is_some(),
         "Receipt should mention reference ARID: {}",
         receipt
     );
@@ -158,7 +169,7 @@ async fn hybrid_concurrent_operations() {
         Arc::new(HybridKv::new("http://127.0.0.1:5001").await.unwrap());
     let store2 =
         Arc::new(HybridKv::new("http://127.0.0.1:5001").await.unwrap());
-    common::kv_tests::test_concurrent_operations(store1, store2).await;
+    testkit::test_concurrent_operations(store1, store2).await;
 }
 
 #[tokio::test]
@@ -228,3 +239,65 @@ async fn hybrid_get_timeout() {
         elapsed.as_secs()
     );
 }
+
+#[tokio::test]
+#[ignore] // Requires IPFS daemon
+async fn hybrid_large_envelope_reports_expiry() {
+    bc_components::register_tags();
+    let store = setup().await;
+    let arid = ARID::new();
+    let large_envelope = Envelope::new("x".repeat(2000));
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let receipt = store
+        .put(&arid, &large_envelope, Some(7200), false)
+        .await
+        .expect("Put should succeed");
+
+    // The receipt reports the TTL's end, not the IPNS record's
+    let expires_at = receipt
+        .expires_at
+        .unwrap_or_else(|| panic!("Receipt should report expiry: {}", receipt));
+    assert!((now + 7200..=now + 7210).contains(&expires_at));
+    assert_eq!(receipt.ttl, Some(7200));
+    assert!(receipt.to_string().ends_with(" (UNIX time)"));
+}
+
+#[cfg(feature = "server")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore] // Requires DHT network access
+async fn hybrid_falls_back_to_server_without_ipfs() {
+    use hubert::server::{Server, ServerConfig, ServerKvClient};
+
+    bc_components::register_tags();
+    let config = ServerConfig { port: 45720, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+    tokio::spawn(async move { server.run().await });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    // Nothing listens at the IPFS port, so large envelopes go to the server
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let store = HybridKv::new("http://127.0.0.1:45705")
+        .await
+        .unwrap()
+        .with_fallback_server(ServerKvClient::new(&url));
+    let arid = ARID::new();
+    let large_envelope = Envelope::new("x".repeat(2000));
+
+    let receipt = store
+        .put(&arid, &large_envelope, None, false)
+        .await
+        .expect("Put should fall back to the server");
+    assert_eq!(receipt.location, url, "Receipt should name the server");
+
+    // A store without the fallback finds it through the reference
+    let reader = HybridKv::new("http://127.0.0.1:45705").await.unwrap();
+    let retrieved = reader
+        .get(&arid, Some(30), false)
+        .await
+        .expect("Get should not error");
+    assert_eq!(retrieved, Some(large_envelope));
+}
@@ -1,8 +1,8 @@
-mod common;
+#![cfg(feature = "ipfs")]
 
 use std::sync::Arc;
 
-use hubert::ipfs::IpfsKv;
+use hubert::{ipfs::IpfsKv, testkit};
 
 /// Test IPFS KV store using the unified test suite.
 ///
@@ -41,35 +41,43 @@ macro_rules! skip_if_no_ipfs {
 async fn ipfs_basic_roundtrip() {
     bc_components::register_tags();
     let store = skip_if_no_ipfs!(setup().await);
-    common::kv_tests::test_basic_roundtrip(&store).await;
+    testkit::test_basic_roundtrip(&store).await;
 }
 
 #[tokio::test]
 async fn ipfs_write_once() {
     bc_components::register_tags();
     let store = skip_if_no_ipfs!(setup().await);
-    common::kv_tests::test_write_once(&store).await;
+    testkit::test_write_once(&store).await;
 }
 
 #[tokio::test]
 async fn ipfs_nonexistent_arid() {
     bc_components::register_tags();
     let store = skip_if_no_ipfs!(setup().await);
-    common::kv_tests::test_nonexistent_arid(&store).await;
+    testkit::test_nonexistent_arid(&store).await;
 }
 
 #[tokio::test]
 async fn ipfs_multiple_arids() {
     bc_components::register_tags();
     let store = skip_if_no_ipfs!(setup().await);
-    common::kv_tests::test_multiple_arids(&store).await;
+    testkit::test_multiple_arids(&store).await;
+}
+
+#[tokio::test]
+async fn ipfs_zero_timeout() {
+    bc_components::register_tags();
+    let store = skip_if_no_ipfs!(setup().await);
+    testkit::test_zero_timeout(&store, tokio::time::Duration::from_secs(1))
+        .await;
 }
 
 #[tokio::test]
 async fn ipfs_size_limit() {
     bc_components::register_tags();
     let store = skip_if_no_ipfs!(setup().await);
-    common::kv_tests::test_size_limit(&store, 10 * 1024 * 1024).await;
+    testkit::test_size_limit(&store, 10 * 1024 * 1024).await;
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -84,5 +92,86 @@ async fn ipfs_concurrent_operations() {
 
     let store1 = Arc::new(IpfsKv::new("http://127.0.0.1:5001"));
     let store2 = Arc::new(IpfsKv::new("http://127.0.0.1:5001"));
-    common::kv_tests::test_concurrent_operations(store1, store2).await;
+    testkit::test_concurrent_operations(store1, store2).await;
+}
+
+#[tokio::test]
+async fn ipfs_get_and_pin_then_unpin() {
+    use bc_components::ARID;
+    use bc_envelope::Envelope;
+    use hubert::KvStore;
+
+    bc_components::register_tags();
+    let store = skip_if_no_ipfs!(setup().await);
+    let arid = ARID::new();
+    let envelope = Envelope::new("Keep me");
+    let receipt = store.put(&arid, &envelope, None, false).await.unwrap();
+
+    let (retrieved, cid) = store
+        .get_and_pin(&arid, Some(30), false)
+        .await
+        .unwrap()
+        .expect("envelope should be published");
+    assert_eq!(retrieved, envelope);
+    assert_eq!(receipt.cid.as_deref(), Some(cid.as_str()));
+    assert!(receipt.to_string().ends_with(&cid));
+
+    let unpinned = store.unpin(&arid, Some(30), false).await.unwrap();
+    assert_eq!(unpinned, Some(cid));
+    assert_eq!(
+        store.unpin(&ARID::new(), Some(1), false).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn ipfs_get_verified_checks_digest() {
+    use bc_components::{ARID, DigestProvider};
+    use bc_envelope::Envelope;
+    use hubert::{Error, KvStore, ipfs::Error as IpfsError};
+
+    bc_components::register_tags();
+    let store = skip_if_no_ipfs!(setup().await);
+    let arid = ARID::new();
+    let envelope = Envelope::new("Expected content");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+
+    let digest = envelope.digest();
+    let retrieved = store
+        .get_verified(&arid, &digest, Some(30), false)
+        .await
+        .unwrap();
+    assert_eq!(retrieved, Some(envelope));
+
+    let other = Envelope::new("Other content").digest();
+    let result = store.get_verified(&arid, &other, Some(30), false).await;
+    assert!(matches!(
+        result,
+        Err(Error::Ipfs(IpfsError::DigestMismatch { .. }))
+    ));
+}
+
+#[tokio::test]
+async fn ipfs_resolve_info() {
+    use bc_components::ARID;
+    use bc_envelope::Envelope;
+    use hubert::KvStore;
+
+    bc_components::register_tags();
+    let store = skip_if_no_ipfs!(setup().await);
+
+    let unknown = store.resolve_info(&ARID::new()).await.unwrap();
+    assert_eq!(unknown.peer_id, None);
+    assert_eq!(unknown.cid, None);
+
+    let arid = ARID::new();
+    let receipt = store
+        .put(&arid, &Envelope::new("Resolvable"), None, false)
+        .await
+        .unwrap();
+    let info = store.resolve_info(&arid).await.unwrap();
+    let ipns = format!("ipns://{}", info.peer_id.as_deref().unwrap());
+    assert_eq!(receipt.location, ipns);
+    assert_eq!(receipt.cid, info.cid);
+    assert!(info.dag_size.unwrap() > 0);
 }
@@ -1,8 +1,8 @@
-mod common;
+#![cfg(feature = "mainline")]
 
 use std::sync::Arc;
 
-use hubert::mainline::MainlineDhtKv;
+use hubert::{mainline::MainlineDhtKv, testkit};
 use mainline::Dht;
 
 /// Test Mainline DHT KV store on mainnet using the unified test suite.
@@ -61,35 +61,35 @@ macro_rules! skip_if_no_mainnet {
 async fn mainnet_basic_roundtrip() {
     bc_components::register_tags();
     let store = skip_if_no_mainnet!(setup().await);
-    common::kv_tests::test_basic_roundtrip(&store).await;
+    testkit::test_basic_roundtrip(&store).await;
 }
 
 #[tokio::test]
 async fn mainnet_write_once() {
     bc_components::register_tags();
     let store = skip_if_no_mainnet!(setup().await);
-    common::kv_tests::test_write_once(&store).await;
+    testkit::test_write_once(&store).await;
 }
 
 #[tokio::test]
 async fn mainnet_nonexistent_arid() {
     bc_components::register_tags();
     let store = skip_if_no_mainnet!(setup().await);
-    common::kv_tests::test_nonexistent_arid(&store).await;
+    testkit::test_nonexistent_arid(&store).await;
 }
 
 #[tokio::test]
 async fn mainnet_multiple_arids() {
     bc_components::register_tags();
     let store = skip_if_no_mainnet!(setup().await);
-    common::kv_tests::test_multiple_arids(&store).await;
+    testkit::test_multiple_arids(&store).await;
 }
 
 #[tokio::test]
 async fn mainnet_size_limit() {
     bc_components::register_tags();
     let store = skip_if_no_mainnet!(setup().await);
-    common::kv_tests::test_size_limit(&store, 1000).await;
+    testkit::test_size_limit(&store, 1000).await;
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -104,5 +104,5 @@ async fn mainnet_concurrent_operations() {
 
     let store1 = Arc::new(MainlineDhtKv::new().await.unwrap());
     let store2 = Arc::new(MainlineDhtKv::new().await.unwrap());
-    common::kv_tests::test_concurrent_operations(store1, store2).await;
+    testkit::test_concurrent_operations(store1, store2).await;
 }
@@ -1,8 +1,8 @@
-mod common;
+#![cfg(feature = "mainline")]
 
 use std::sync::Arc;
 
-use hubert::mainline::MainlineDhtKv;
+use hubert::{mainline::MainlineDhtKv, testkit};
 use mainline::Testnet;
 
 /// Test Mainline DHT KV store using the unified test suite.
@@ -21,31 +21,41 @@ async fn setup() -> MainlineDhtKv {
 #[tokio::test]
 async fn mainline_basic_roundtrip() {
     bc_components::register_tags();
-    common::kv_tests::test_basic_roundtrip(&setup().await).await;
+    testkit::test_basic_roundtrip(&setup().await).await;
 }
 
 #[tokio::test]
 async fn mainline_write_once() {
     bc_components::register_tags();
-    common::kv_tests::test_write_once(&setup().await).await;
+    testkit::test_write_once(&setup().await).await;
 }
 
 #[tokio::test]
 async fn mainline_nonexistent_arid() {
     bc_components::register_tags();
-    common::kv_tests::test_nonexistent_arid(&setup().await).await;
+    testkit::test_nonexistent_arid(&setup().await).await;
 }
 
 #[tokio::test]
 async fn mainline_multiple_arids() {
     bc_components::register_tags();
-    common::kv_tests::test_multiple_arids(&setup().await).await;
+    testkit::test_multiple_arids(&setup().await).await;
+}
+
+#[tokio::test]
+async fn mainline_zero_timeout() {
+    bc_components::register_tags();
+    testkit::test_zero_timeout(
+        &setup().await,
+        tokio::time::Duration::from_secs(1),
+    )
+    .await;
 }
 
 #[tokio::test]
 async fn mainline_size_limit() {
     bc_components::register_tags();
-    common::kv_tests::test_size_limit(&setup().await, 1000).await;
+    testkit::test_size_limit(&setup().await, 1000).await;
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -54,7 +64,7 @@ async fn mainline_concurrent_operations() {
     let _testnet = Testnet::new_async(5).await.unwrap();
     let store1 = Arc::new(MainlineDhtKv::new().await.unwrap());
     let store2 = Arc::new(MainlineDhtKv::new().await.unwrap());
-    common::kv_tests::test_concurrent_operations(store1, store2).await;
+    testkit::test_concurrent_operations(store1, store2).await;
 }
 
 #[tokio::test]
@@ -91,3 +101,59 @@ async fn mainline_get_timeout() {
         elapsed.as_secs()
     );
 }
+
+#[tokio::test]
+async fn mainline_keepalive_registry() {
+    bc_components::register_tags();
+    use bc_components::ARID;
+    use bc_envelope::Envelope;
+
+    let store = setup().await;
+    let arid = ARID::new();
+    store
+        .put_with_keepalive(&arid, &Envelope::new("Kept alive"), false)
+        .await
+        .unwrap();
+    assert_eq!(store.kept_alive(), vec![arid]);
+
+    // Entries that do not exist cannot be kept alive
+    assert!(!store.keepalive(&ARID::new()).await.unwrap());
+
+    assert!(store.stop_keepalive(&arid));
+    assert!(!store.stop_keepalive(&arid));
+    assert!(store.kept_alive().is_empty());
+}
+
+#[tokio::test]
+async fn mainline_verified_put() {
+    bc_components::register_tags();
+    use bc_components::ARID;
+    use bc_envelope::Envelope;
+    use hubert::KvStore;
+
+    let store = setup().await.with_verify_put(true);
+    let arid = ARID::new();
+    let envelope = Envelope::new("Verified");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+
+    // A verified put is immediately readable
+    let retrieved = store.get(&arid, Some(0), false).await.unwrap();
+    assert_eq!(retrieved, Some(envelope));
+}
+
+#[tokio::test]
+async fn mainline_immutable_roundtrip() {
+    bc_components::register_tags();
+    use bc_envelope::Envelope;
+
+    let store = setup().await;
+    let envelope = Envelope::new("Immutable");
+    let target = store.put_immutable(&envelope, false).await.unwrap();
+
+    // Content-addressed: the same envelope always lands at the same target
+    let again = store.put_immutable(&envelope, false).await.unwrap();
+    assert_eq!(target, again);
+
+    let retrieved = store.get_immutable(target, Some(5), false).await.unwrap();
+    assert_eq!(retrieved, Some(envelope));
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "server-client")]
+
+use bc_components::ARID;
+use bc_ur::prelude::*;
+use hubert::{
+    Error,
+    names::{HubertName, NameResolver, WellKnownResolver, resolve_arid},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Serve `arid` as the well-known document for the label `invoice`, and
+/// 404 for every other path.
+async fn serve_well_known(port: u16, arid: ARID) {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let n = stream.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..n]);
+            let response =
+                if request.starts_with("GET /.well-known/hubert/invoice ") {
+                    let body = format!("{}\n", arid.ur_string());
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+                 Connection: close\r\n\r\n"
+                        .to_string()
+                };
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+}
+
+#[tokio::test]
+async fn test_well_known_resolver() {
+    bc_components::register_tags();
+
+    let arid = ARID::new();
+    serve_well_known(45719, arid).await;
+    let resolver = WellKnownResolver::new().with_insecure_http();
+
+    let name: HubertName = "hubert:127.0.0.1:45719/invoice".parse().unwrap();
+    assert_eq!(
+        resolver.url(&name),
+        "http://127.0.0.1:45719/.well-known/hubert/invoice"
+    );
+    assert_eq!(resolver.resolve(&name).await.unwrap(), arid);
+    assert_eq!(
+        resolve_arid("hubert:127.0.0.1:45719/invoice", &resolver)
+            .await
+            .unwrap(),
+        arid
+    );
+
+    let missing: HubertName = "hubert:127.0.0.1:45719/receipt".parse().unwrap();
+    assert!(matches!(
+        resolver.resolve(&missing).await,
+        Err(Error::NameNotFound { .. })
+    ));
+
+    // Nothing listening
+    let unreachable: HubertName =
+        "hubert:127.0.0.1:45705/invoice".parse().unwrap();
+    assert!(matches!(
+        resolver.resolve(&unreachable).await,
+        Err(Error::NameResolution(_))
+    ));
+}
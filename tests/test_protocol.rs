@@ -0,0 +1,76 @@
+use bc_envelope::Envelope;
+use hubert::{
+    Error, MemoryKv, Session, SessionSlot,
+    protocol::{Protocol, Round},
+};
+
+fn rounds() -> Vec<Round> {
+    vec![
+        Round::new("request", "alice", SessionSlot::Request),
+        Round::new("response", "bob", SessionSlot::Response),
+        Round::new("confirm", "alice", SessionSlot::Status),
+    ]
+}
+
+/// Test a three-round exchange, with one party restarting midway
+#[tokio::test]
+async fn test_protocol_rounds_and_resume() {
+    let store = MemoryKv::new();
+    let state_file = std::env::temp_dir()
+        .join(format!("hubert-protocol-{}.json", std::process::id()));
+
+    let session = Session::new(60);
+    let mut alice = Protocol::new(session.clone(), "alice", rounds())
+        .unwrap()
+        .with_state_file(&state_file);
+    let mut bob = Protocol::new(session, "bob", rounds()).unwrap();
+
+    assert_eq!(alice.pending().unwrap().name(), "request");
+    assert!(alice.is_my_turn());
+    assert!(!bob.is_my_turn());
+
+    // Bob can't write Alice's round
+    let result = bob.write(&store, &Envelope::new("Nope"), false).await;
+    assert!(matches!(result, Err(Error::NotYourTurn { .. })));
+
+    alice
+        .write(&store, &Envelope::new("Please sign"), false)
+        .await
+        .unwrap();
+    assert!(bob.read(&store, Some(5), false).await.unwrap().is_some());
+    bob.write(&store, &Envelope::new("Signed"), false)
+        .await
+        .unwrap();
+
+    // Alice restarts and resumes from her state file
+    drop(alice);
+    let mut alice = Protocol::resume(&state_file, rounds()).unwrap();
+    assert_eq!(alice.completed_rounds(), 1);
+    assert_eq!(alice.pending().unwrap().name(), "response");
+
+    let response = alice.read(&store, Some(5), false).await.unwrap();
+    assert_eq!(response, Some(Envelope::new("Signed")));
+    alice
+        .write(&store, &Envelope::new("Thanks"), false)
+        .await
+        .unwrap();
+    assert!(alice.is_complete());
+
+    assert!(bob.read(&store, Some(5), false).await.unwrap().is_some());
+    assert!(bob.is_complete());
+    let result = bob.read(&store, Some(1), false).await;
+    assert!(matches!(result, Err(Error::ProtocolComplete)));
+
+    std::fs::remove_file(&state_file).unwrap();
+}
+
+/// Test that rounds may not share a slot
+#[test]
+fn test_protocol_rejects_reused_slot() {
+    let rounds = vec![
+        Round::new("one", "alice", SessionSlot::Request),
+        Round::new("two", "bob", SessionSlot::Request),
+    ];
+    let result = Protocol::new(Session::new(60), "alice", rounds);
+    assert!(matches!(result, Err(Error::InvalidProtocolState(_))));
+}
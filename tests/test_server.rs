@@ -1,12 +1,31 @@
+#![cfg(feature = "server")]
+
 use anyhow::Result;
-use bc_components::ARID;
-use bc_envelope::Envelope;
+use bc_components::{ARID, DigestProvider, PublicKeys, keypair};
+use bc_envelope::{Envelope, prelude::ByteString};
+use bc_ur::prelude::*;
 use hubert::{
-    KvStore,
-    server::{Server, ServerConfig, ServerKvClient},
+    KvStore, MemoryKv, Mutability, SqliteKv,
+    expiry::add_expiry,
+    server::{
+        CAPABILITY_HEADER, Capability, ConformanceStep, GetOutcome,
+        MAX_ENVELOPE_SIZE, PeerConfig, ProvenRequest, REQUEST_PROOF_HEADER,
+        ReceiptStatus, Right, Server, ServerConfig, ServerKv, ServerKvClient,
+        StepStatus, sign_request_proof,
+    },
+    ur_input::MAX_ENVELOPE_UR_LENGTH,
+    wire::{Codec, stored_size},
 };
 use tokio::time::{Duration, sleep};
 
+/// Current time in UNIX seconds.
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 /// Test basic put/get roundtrip with in-process server
 #[tokio::test(flavor = "multi_thread")]
 async fn test_server_put_get_roundtrip() -> Result<()> {
@@ -35,7 +54,9 @@ async fn test_server_put_get_roundtrip() -> Result<()> {
         .put(&arid, &envelope, None, false) // No TTL
         .await
         .map_err(|e| anyhow::anyhow!("{}", e))?;
-    assert!(!receipt.is_empty(), "Receipt should not be empty");
+    assert_eq!(receipt.backend, hubert::receipt::Backend::Server);
+    assert_eq!(receipt.arid, arid);
+    assert!(receipt.expires_at.is_some(), "Receipt should report expiry");
 
     // Get the envelope back
     let retrieved = client
@@ -162,7 +183,7 @@ async fn test_server_default_ttl() -> Result<()> {
     let config = ServerConfig {
         port: 45683,
         max_ttl: 2, // 2 seconds
-        verbose: false,
+        ..Default::default()
     };
     let server = Server::new_memory(config.clone());
 
@@ -214,7 +235,7 @@ async fn test_server_ttl_clamping() -> Result<()> {
     let config = ServerConfig {
         port: 45684,
         max_ttl: 2, // 2 seconds max
-        verbose: false,
+        ..Default::default()
     };
     let server = Server::new_memory(config.clone());
 
@@ -257,6 +278,102 @@ async fn test_server_ttl_clamping() -> Result<()> {
     Ok(())
 }
 
+/// Test that default_ttl applies to puts without a TTL but does not cap them
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_separate_default_ttl() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig {
+        port: 45713,
+        max_ttl: 60,
+        default_ttl: Some(2),
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let (defaulted, requested) = (ARID::new(), ARID::new());
+    let envelope = Envelope::new("Message with default TTL");
+
+    // One put takes the 2-second default, the other asks for longer
+    client
+        .put(&defaulted, &envelope, None, false)
+        .await
+        .map_err(err)?;
+    client
+        .put(&requested, &envelope, Some(30), false)
+        .await
+        .map_err(err)?;
+
+    sleep(Duration::from_secs(3)).await;
+
+    assert!(
+        client
+            .get(&defaulted, Some(0), false)
+            .await
+            .map_err(err)?
+            .is_none()
+    );
+    assert!(
+        client
+            .get(&requested, Some(0), false)
+            .await
+            .map_err(err)?
+            .is_some()
+    );
+    Ok(())
+}
+
+/// Test that a put's response reports the expiry the server applied
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_put_reports_expiry() -> Result<()> {
+    bc_components::register_tags();
+
+    let config =
+        ServerConfig { port: 45714, max_ttl: 60, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let envelope = Envelope::new("Message with reported expiry");
+    let applied = |receipt| match receipt {
+        ReceiptStatus::Stored { stored_at, expires_at } => {
+            expires_at - stored_at
+        }
+        other => panic!("Unexpected receipt: {:?}", other),
+    };
+
+    // A TTL within the maximum is kept, a longer one is shortened
+    let receipt = client
+        .put_detailed(&ARID::new(), &envelope, Some(10), false)
+        .await
+        .map_err(err)?;
+    assert_eq!(applied(receipt), 10);
+    let receipt = client
+        .put_detailed(&ARID::new(), &envelope, Some(3600), false)
+        .await
+        .map_err(err)?;
+    assert_eq!(applied(receipt), 60);
+
+    let text = client
+        .put(&ARID::new(), &envelope, None, false)
+        .await
+        .map_err(err)?;
+    assert!(text.starts_with("Stored until "), "{}", text);
+    Ok(())
+}
+
 /// Test get timeout polling behavior
 #[tokio::test(flavor = "multi_thread")]
 async fn test_server_get_timeout() -> Result<()> {
@@ -264,7 +381,7 @@ async fn test_server_get_timeout() -> Result<()> {
 
     bc_components::register_tags();
 
-    let config = ServerConfig { port: 45685, max_ttl: 86400, verbose: false };
+    let config = ServerConfig { port: 45685, ..Default::default() };
     let server = Server::new_memory(config.clone());
 
     tokio::spawn(async move { server.run().await });
@@ -300,3 +417,2045 @@ async fn test_server_get_timeout() -> Result<()> {
 
     Ok(())
 }
+
+/// Test that connections which never send a request are closed
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_idle_connection_closed() -> Result<()> {
+    use tokio::{io::AsyncReadExt, net::TcpStream, time::timeout};
+
+    let config =
+        ServerConfig { port: 45686, idle_timeout: 1, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    // Connect but never send anything (slowloris-style client)
+    let mut stream =
+        TcpStream::connect(format!("127.0.0.1:{}", config.port)).await?;
+
+    // The server should drop the connection after ~1 second
+    let mut buf = [0u8; 16];
+    let read = timeout(Duration::from_secs(5), stream.read(&mut buf)).await;
+    assert!(
+        matches!(read, Ok(Ok(0)) | Ok(Err(_))),
+        "Idle connection should be closed by the server"
+    );
+
+    Ok(())
+}
+
+/// Test that a putter can learn its entry expired via /receipts
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_receipts() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45687, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+
+    // Never stored
+    let arid = ARID::new();
+    let status = client
+        .receipt(&arid)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(status, ReceiptStatus::Unknown);
+
+    // Stored with a short TTL
+    let envelope = Envelope::new("Receipt test");
+    client
+        .put(&arid, &envelope, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let status = client
+        .receipt(&arid)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(matches!(status, ReceiptStatus::Stored { .. }));
+
+    // Expired
+    sleep(Duration::from_secs(3)).await;
+    let status = client
+        .receipt(&arid)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(
+        matches!(status, ReceiptStatus::Expired { .. }),
+        "Expected expired receipt, got {:?}",
+        status
+    );
+
+    Ok(())
+}
+
+/// Test that the server describes its API at /openapi.json
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_openapi() -> Result<()> {
+    let config = ServerConfig { port: 45690, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}/openapi.json", config.port);
+    let body = reqwest::get(&url).await?.text().await?;
+    let spec: serde_json::Value = serde_json::from_str(&body)?;
+
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    for path in ["/health", "/put", "/get", "/get_many", "/receipts"] {
+        assert!(spec["paths"][path].is_object(), "Missing path {}", path);
+    }
+    assert_eq!(
+        spec,
+        serde_json::from_str::<serde_json::Value>(&Server::openapi_json())?
+    );
+
+    Ok(())
+}
+
+/// Test that a server with trust roots requires a valid capability
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_capabilities() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let (other_private, _) = keypair();
+
+    let config = ServerConfig {
+        port: 45691,
+        capability_roots: vec![root_public.ur_string()],
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let arid = ARID::new();
+    let envelope = Envelope::new("Capability test");
+
+    // No capability
+    let client = ServerKvClient::new(&url);
+    assert!(client.put(&arid, &envelope, None, false).await.is_err());
+
+    // Signed by an untrusted key
+    let full = Capability::new().allow(Right::Put).allow(Right::Get);
+    let client =
+        ServerKvClient::new(&url).with_capability(&full.sign(&other_private));
+    assert!(client.put(&arid, &envelope, None, false).await.is_err());
+
+    // Read-only capability cannot put
+    let reader = ServerKvClient::new(&url).with_capability(
+        &Capability::new().allow(Right::Get).sign(&root_private),
+    );
+    assert!(reader.put(&arid, &envelope, None, false).await.is_err());
+
+    // Capability scoped to another ARID prefix
+    let other_prefix = vec![arid.data()[0].wrapping_add(1)];
+    let scoped = ServerKvClient::new(&url).with_capability(
+        &full
+            .clone()
+            .with_arid_prefix(other_prefix)
+            .sign(&root_private),
+    );
+    assert!(scoped.put(&arid, &envelope, None, false).await.is_err());
+
+    // Valid capability
+    let writer =
+        ServerKvClient::new(&url).with_capability(&full.sign(&root_private));
+    writer
+        .put(&arid, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let retrieved = reader
+        .get(&arid, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(retrieved, Some(envelope));
+
+    Ok(())
+}
+
+/// Test that a capability bound to a holder refuses replayed requests
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_request_proofs() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let (holder_private, holder_public) = keypair();
+    let (other_private, _) = keypair();
+
+    let config = ServerConfig {
+        port: 45715,
+        capability_roots: vec![root_public.ur_string()],
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let token = Capability::new()
+        .allow(Right::Put)
+        .allow(Right::Get)
+        .with_holder(holder_public)
+        .sign(&root_private);
+    let (arid, envelope) = (ARID::new(), Envelope::new("Proof test"));
+
+    // Without proofs, or with proofs by another key, the token is refused
+    let bearer = ServerKvClient::new(&url).with_capability(&token);
+    assert!(bearer.put(&arid, &envelope, None, false).await.is_err());
+    let impostor = ServerKvClient::new(&url)
+        .with_capability(&token)
+        .with_holder_keys(other_private);
+    assert!(impostor.put(&arid, &envelope, None, false).await.is_err());
+
+    let holder = ServerKvClient::new(&url)
+        .with_capability(&token)
+        .with_holder_keys(holder_private.clone());
+    holder
+        .put(&arid, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    // A captured request is refused when sent again
+    let body = arid.ur_string();
+    let request = ProvenRequest {
+        method: "POST",
+        path: "/get",
+        arids: &[arid],
+        body: body.as_bytes(),
+    };
+    let proof = sign_request_proof(&holder_private, &request).ur_string();
+    let send = |body: String| {
+        reqwest::Client::new()
+            .post(format!("{}/get", url))
+            .header(CAPABILITY_HEADER, token.ur_string())
+            .header(REQUEST_PROOF_HEADER, &proof)
+            .body(body)
+            .send()
+    };
+    let replay = || send(body.clone());
+    // Nor can the proof be moved to another request
+    let moved = send(ARID::new().ur_string()).await?;
+    assert_eq!(moved.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(replay().await?.status(), reqwest::StatusCode::OK);
+    assert_eq!(replay().await?.status(), reqwest::StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+/// Test that address filters apply to the client behind a trusted proxy
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_forwarded_client_filter() -> Result<()> {
+    let config = ServerConfig {
+        port: 45716,
+        trusted_proxies: vec!["127.0.0.0/8".parse()?],
+        denied_ips: vec!["203.0.113.0/24".parse()?],
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let health = |forwarded_for: Option<&'static str>| {
+        let mut request = reqwest::Client::new()
+            .get(format!("http://127.0.0.1:{}/health", config.port));
+        if let Some(client) = forwarded_for {
+            request = request.header("X-Forwarded-For", client);
+        }
+        request.send()
+    };
+    assert_eq!(health(None).await?.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        health(Some("198.51.100.7")).await?.status(),
+        reqwest::StatusCode::OK
+    );
+    assert_eq!(
+        health(Some("203.0.113.9")).await?.status(),
+        reqwest::StatusCode::FORBIDDEN
+    );
+    // A client cannot hide behind a forged first hop
+    assert_eq!(
+        health(Some("198.51.100.7, 203.0.113.9")).await?.status(),
+        reqwest::StatusCode::FORBIDDEN
+    );
+    Ok(())
+}
+
+/// Test that a server serves totals at /metrics only when configured to
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_metrics() -> Result<()> {
+    bc_components::register_tags();
+
+    let config =
+        ServerConfig { port: 45732, metrics: true, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+    tokio::spawn(async move { server.run().await });
+    let plain = ServerConfig { port: 45733, ..Default::default() };
+    let server = Server::new_memory(plain.clone());
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    ServerKvClient::new(&url)
+        .put(&ARID::new(), &Envelope::new("Counted"), None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let response = reqwest::get(format!("{}/metrics", url)).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let metrics = response.text().await?;
+    assert!(metrics.contains("\nhubert_entries 1\n"), "{}", metrics);
+    assert!(metrics.contains("\nhubert_draining 0\n"), "{}", metrics);
+
+    let url = format!("http://127.0.0.1:{}/metrics", plain.port);
+    let response = reqwest::get(url).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+/// Test that a read-only server serves existing entries but refuses puts
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_read_only() -> Result<()> {
+    bc_components::register_tags();
+
+    let storage = MemoryKv::new();
+    let archived = ARID::new();
+    let envelope = Envelope::new("Archived");
+    storage
+        .put(&archived, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let config =
+        ServerConfig { port: 45693, read_only: true, ..Default::default() };
+    let server = Server::new(config.clone(), ServerKv::Memory(storage));
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+
+    let arid = ARID::new();
+    let result = client.put(&arid, &Envelope::new("New"), None, false).await;
+    assert!(result.is_err());
+
+    let retrieved = client
+        .get(&archived, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(retrieved, Some(envelope));
+
+    Ok(())
+}
+
+/// Test that an admin can drain the server, which refuses puts but keeps
+/// serving gets
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_drain() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let config = ServerConfig {
+        port: 45694,
+        capability_roots: vec![root_public.ur_string()],
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let data = Capability::new().allow(Right::Put).allow(Right::Get);
+    let client =
+        ServerKvClient::new(&url).with_capability(&data.sign(&root_private));
+    let admin = ServerKvClient::new(&url).with_capability(
+        &Capability::new().allow(Right::Admin).sign(&root_private),
+    );
+
+    let arid = ARID::new();
+    let envelope = Envelope::new("Before drain");
+    client
+        .put(&arid, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    // Only admins may toggle drain mode
+    assert!(client.set_draining(true).await.is_err());
+    admin
+        .set_draining(true)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let body = reqwest::get(format!("{}/health", url))
+        .await?
+        .text()
+        .await?;
+    let health: serde_json::Value = serde_json::from_str(&body)?;
+    assert_eq!(health["status"], "draining");
+
+    assert!(
+        client
+            .put(&ARID::new(), &envelope, None, false)
+            .await
+            .is_err()
+    );
+    let retrieved = client
+        .get(&arid, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(retrieved, Some(envelope.clone()));
+
+    admin
+        .set_draining(false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    client
+        .put(&ARID::new(), &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    Ok(())
+}
+
+/// Test that the client's health probe identifies the server
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_health() -> Result<()> {
+    let config = ServerConfig { port: 45695, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let report = ServerKvClient::new(&url).health().await;
+    assert!(report.is_available(), "{:?}", report.error);
+    assert_eq!(report.backend, "server");
+    assert_eq!(report.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+
+    // Nothing listens on port 1
+    let report = ServerKvClient::new("http://127.0.0.1:1").health().await;
+    assert!(!report.is_available());
+
+    Ok(())
+}
+
+/// Test that get_all batches server lookups and keeps the ARIDs' order
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_get_all() -> Result<()> {
+    use futures_util::StreamExt;
+
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45696, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let client = ServerKvClient::new(&url);
+    let (stored, late, missing) = (ARID::new(), ARID::new(), ARID::new());
+    client
+        .put(&stored, &Envelope::new("Stored"), None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let arids = [late, missing, stored];
+    let (results, put) = tokio::join!(
+        client.get_all(&arids, Some(3), false).collect::<Vec<_>>(),
+        // Published while the batch is already polling
+        async {
+            sleep(Duration::from_millis(500)).await;
+            client.put(&late, &Envelope::new("Late"), None, false).await
+        }
+    );
+    put.map_err(|e| anyhow::anyhow!("{}", e))?;
+    let results = results
+        .into_iter()
+        .collect::<hubert::Result<Vec<_>>>()
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(
+        results,
+        [
+            (late, Some(Envelope::new("Late"))),
+            (missing, None),
+            (stored, Some(Envelope::new("Stored"))),
+        ]
+    );
+
+    Ok(())
+}
+
+/// Test that SQLite answers get_all from its table, skipping expired rows
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_get_all() -> Result<()> {
+    use futures_util::StreamExt;
+
+    let path = std::env::temp_dir()
+        .join(format!("hubert-get-all-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let (kept, expired) = (ARID::new(), ARID::new());
+    store
+        .put(&kept, &Envelope::new("Kept"), None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    store
+        .put(&expired, &Envelope::new("Expired"), Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    sleep(Duration::from_millis(2100)).await;
+
+    let arids = [expired, kept];
+    let results: Vec<_> = store
+        .get_all(&arids, Some(0), false)
+        .map(|r| r.unwrap())
+        .collect()
+        .await;
+    assert_eq!(
+        results,
+        [(expired, None), (kept, Some(Envelope::new("Kept")))]
+    );
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that admins can page through the server's entries
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_admin_list() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let config = ServerConfig {
+        port: 45697,
+        capability_roots: vec![root_public.ur_string()],
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let data = Capability::new().allow(Right::Put).allow(Right::Get);
+    let client =
+        ServerKvClient::new(&url).with_capability(&data.sign(&root_private));
+    let admin = ServerKvClient::new(&url).with_capability(
+        &Capability::new().allow(Right::Admin).sign(&root_private),
+    );
+
+    let mut arids: Vec<ARID> = (0..3).map(|_| ARID::new()).collect();
+    for arid in &arids {
+        client
+            .put(arid, &Envelope::new("Listed"), Some(600), false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+
+    // Only admins may list
+    assert!(client.list(0, 10, false, None, None).await.is_err());
+
+    let first = admin
+        .list(0, 2, false, None, None)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let rest = admin
+        .list(2, 2, false, None, None)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!((first.len(), rest.len()), (2, 1));
+
+    let mut listed: Vec<ARID> =
+        first.iter().chain(&rest).map(|entry| entry.arid).collect();
+    listed.sort_by_key(|arid| arid.ur_string());
+    arids.sort_by_key(|arid| arid.ur_string());
+    assert_eq!(listed, arids);
+    for entry in first.iter().chain(&rest) {
+        assert!(!entry.expired);
+        assert!(entry.expires_at.is_some());
+        assert_eq!(entry.size, Envelope::new("Listed").to_cbor_data().len());
+    }
+
+    assert!(admin.list(0, 5000, false, None, None).await.is_err());
+
+    Ok(())
+}
+
+/// Test that stores list expired entries only on request
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_list_expired() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-list-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let memory = MemoryKv::new();
+
+    let (kept, expired) = (ARID::new(), ARID::new());
+    for store in [&sqlite as &dyn KvStore, &memory] {
+        store
+            .put(&kept, &Envelope::new("Kept"), None, false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        store
+            .put(&expired, &Envelope::new("Expired"), Some(1), false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+    sleep(Duration::from_millis(2100)).await;
+
+    let listings = [
+        (
+            sqlite
+                .list(0, 10, false, None, None)
+                .map_err(|e| anyhow::anyhow!("{}", e))?,
+            sqlite
+                .list(0, 10, true, None, None)
+                .map_err(|e| anyhow::anyhow!("{}", e))?,
+        ),
+        (
+            memory.list(0, 10, false, None, None),
+            memory.list(0, 10, true, None, None),
+        ),
+    ];
+    for (live, all) in listings {
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].arid, kept);
+        assert_eq!(live[0].expires_at, None);
+        assert_eq!(all.len(), 2);
+        assert!(
+            all.iter()
+                .any(|entry| entry.arid == expired && entry.expired)
+        );
+    }
+
+    // A prefix of the UR selects the entries whose ARIDs start with it
+    let ur = kept.ur_string();
+    let prefix = &ur[..14];
+    for listed in [
+        sqlite
+            .list(0, 10, true, None, Some(prefix))
+            .map_err(|e| anyhow::anyhow!("{}", e))?,
+        memory.list(0, 10, true, None, Some(prefix)),
+    ] {
+        assert!(listed.iter().any(|entry| entry.arid == kept));
+        assert!(listed.iter().all(|e| e.arid.ur_string().starts_with(prefix)));
+    }
+    // Every ARID's UR starts with the CBOR header's bytewords, hdcx
+    let none = sqlite
+        .list(0, 10, true, None, Some("ur:arid/zz"))
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(none.is_empty());
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that admins can count and purge entries by tag
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_tags() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let path = std::env::temp_dir()
+        .join(format!("hubert-tags-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = ServerConfig {
+        port: 45698,
+        capability_roots: vec![root_public.ur_string()],
+        ..Default::default()
+    };
+    let storage = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let server = Server::new_sqlite(config.clone(), storage);
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let token = Capability::new()
+        .allow(Right::Put)
+        .allow(Right::Get)
+        .sign(&root_private);
+    let tagged = ServerKvClient::new(&url)
+        .with_capability(&token)
+        .with_tag("test-run-7");
+    let untagged = ServerKvClient::new(&url).with_capability(&token);
+    let admin = ServerKvClient::new(&url).with_capability(
+        &Capability::new().allow(Right::Admin).sign(&root_private),
+    );
+
+    let envelope = Envelope::new("Tagged");
+    let arids: Vec<ARID> = (0..2).map(|_| ARID::new()).collect();
+    for arid in &arids {
+        tagged
+            .put(arid, &envelope, None, false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+    let kept = ARID::new();
+    untagged
+        .put(&kept, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let count = |tag| admin.count(tag);
+    assert_eq!(count(Some("test-run-7")).await.unwrap(), 2);
+    assert_eq!(count(None).await.unwrap(), 3);
+    let listed = admin
+        .list(0, 10, false, Some("test-run-7"), None)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(listed.len(), 2);
+    assert!(listed.iter().all(|entry| {
+        arids.contains(&entry.arid)
+            && entry.tag.as_deref() == Some("test-run-7")
+    }));
+
+    // Only admins may purge
+    assert!(tagged.purge("test-run-7").await.is_err());
+    assert_eq!(admin.purge("test-run-7").await.unwrap(), 2);
+    assert_eq!(count(Some("test-run-7")).await.unwrap(), 0);
+    assert!(
+        tagged
+            .get(&arids[0], Some(0), false)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(untagged.get(&kept, Some(0), false).await.unwrap().is_some());
+
+    // Oversized tags are refused
+    let result = ServerKvClient::new(&url)
+        .with_capability(&token)
+        .with_tag("x".repeat(65))
+        .put(&ARID::new(), &envelope, None, false)
+        .await;
+    assert!(result.is_err());
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that gets of expired entries receive their tombstone
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_tombstones() -> Result<()> {
+    use futures_util::StreamExt;
+
+    bc_components::register_tags();
+
+    let config = ServerConfig {
+        port: 45699,
+        tombstone_retention: Some(60),
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let (arid, envelope) = (ARID::new(), Envelope::new("Short-lived"));
+    client
+        .put(&arid, &envelope, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    sleep(Duration::from_millis(2100)).await;
+
+    match client.get(&arid, Some(0), false).await {
+        Err(hubert::Error::Server(hubert::server::Error::Gone(tombstone))) => {
+            assert_eq!(tombstone.arid, arid);
+            assert_eq!(tombstone.digest, envelope.digest());
+        }
+        other => panic!("expected 410 Gone, got {:?}", other),
+    }
+    assert!(!client.exists(&arid).await.unwrap());
+
+    // A watch reports the tombstone in place of an envelope, then ends
+    let mut watch = client.watch(&arid, false);
+    assert!(matches!(
+        watch.next().await,
+        Some(Err(hubert::Error::Server(hubert::server::Error::Gone(_))))
+    ));
+    assert!(watch.next().await.is_none());
+    assert!(matches!(
+        client.get_detailed(&arid, Some(0), false).await.unwrap(),
+        GetOutcome::Expired { digest: Some(digest), .. }
+            if digest == envelope.digest()
+    ));
+
+    // The ARID stays taken while the tombstone is kept
+    let result = client.put(&arid, &envelope, None, false).await;
+    assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+
+    // Never-stored ARIDs are still plain 404s
+    assert!(
+        client
+            .get(&ARID::new(), Some(0), false)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    Ok(())
+}
+
+/// Test that stores keep tombstones only when enabled
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_tombstones() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-tombstones-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .with_tombstones(60);
+    let memory = MemoryKv::new().with_tombstones(60);
+    let untracked = MemoryKv::new();
+
+    let (arid, envelope) = (ARID::new(), Envelope::new("Short-lived"));
+    for store in [&sqlite as &dyn KvStore, &memory, &untracked] {
+        store
+            .put(&arid, &envelope, Some(1), false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+    sleep(Duration::from_millis(2100)).await;
+
+    let sqlite_tombstone = sqlite
+        .tombstone(&arid)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    for tombstone in [sqlite_tombstone, memory.tombstone(&arid)] {
+        let tombstone = tombstone.expect("tombstone kept");
+        assert_eq!(tombstone.arid, arid);
+        assert_eq!(tombstone.digest, envelope.digest());
+    }
+    assert!(untracked.tombstone(&arid).is_none());
+    assert!(untracked.put(&arid, &envelope, None, false).await.is_ok());
+    assert!(sqlite.put(&arid, &envelope, None, false).await.is_err());
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that deleted entries are gone at once, tombstoned if enabled
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_delete() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-delete-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .with_tombstones(60);
+    let memory = MemoryKv::new().with_tombstones(60);
+    let untracked = MemoryKv::new();
+
+    let (arid, envelope) = (ARID::new(), Envelope::new("Retracted"));
+    for store in [&sqlite as &dyn KvStore, &memory, &untracked] {
+        assert!(store.capabilities().delete);
+        store
+            .put(&arid, &envelope, Some(3600), false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert!(store.delete(&arid, false).await.unwrap());
+        assert!(store.get(&arid, Some(0), false).await.unwrap().is_none());
+        assert!(!store.delete(&arid, false).await.unwrap());
+    }
+
+    let sqlite_tombstone = sqlite
+        .tombstone(&arid)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    for tombstone in [sqlite_tombstone, memory.tombstone(&arid)] {
+        assert_eq!(
+            tombstone.expect("tombstone kept").digest,
+            envelope.digest()
+        );
+    }
+    assert!(memory.put(&arid, &envelope, None, false).await.is_err());
+    assert!(untracked.put(&arid, &envelope, None, false).await.is_ok());
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that the server deletes entries and reports them expired
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_delete() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45721, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let (arid, envelope) = (ARID::new(), Envelope::new("Retracted"));
+    client
+        .put(&arid, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    assert!(client.delete(&arid, false).await.unwrap());
+    assert!(client.get(&arid, Some(0), false).await.unwrap().is_none());
+    assert!(matches!(
+        client.receipt(&arid).await.unwrap(),
+        ReceiptStatus::Expired { .. }
+    ));
+    assert!(!client.delete(&arid, false).await.unwrap());
+    assert!(!client.delete(&ARID::new(), false).await.unwrap());
+
+    Ok(())
+}
+
+/// Test that server-side stores describe entries without returning them
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_stat() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-stat-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let memory = MemoryKv::new();
+
+    let (arid, envelope) = (ARID::new(), Envelope::new("Described"));
+    for store in [&sqlite as &dyn KvStore, &memory] {
+        assert!(store.capabilities().stat);
+        assert!(store.stat(&arid, false).await.unwrap().is_none());
+        let receipt = store
+            .put(&arid, &envelope, Some(3600), false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let meta = store.stat(&arid, false).await.unwrap().unwrap();
+        assert_eq!(meta.backend, receipt.backend);
+        assert_eq!(meta.location, receipt.location);
+        assert_eq!(meta.size, receipt.size);
+        // Memory keeps expiry to the nanosecond and reports whole seconds
+        let expires_at = meta.expires_at.unwrap();
+        assert!(expires_at.abs_diff(receipt.expires_at.unwrap()) <= 1);
+        let created_at = meta.created_at.expect("creation time recorded");
+        assert!(receipt.expires_at.unwrap() - created_at <= 3601);
+        assert!((3590..=3600).contains(&meta.ttl_remaining.unwrap()));
+
+        assert!(store.delete(&arid, false).await.unwrap());
+        assert!(store.stat(&arid, false).await.unwrap().is_none());
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that the server reports entry metadata over `/stat`
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_stat() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45727, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    assert!(client.stat(&ARID::new(), false).await.unwrap().is_none());
+
+    let (arid, envelope) = (ARID::new(), Envelope::new("Described"));
+    let receipt = client
+        .put(&arid, &envelope, Some(600), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let meta = client.stat(&arid, false).await.unwrap().unwrap();
+    assert_eq!(meta.arid, arid);
+    assert_eq!(meta.location, client.base_url());
+    assert_eq!(meta.size, receipt.size);
+    let expires_at = meta.expires_at.unwrap();
+    assert!(expires_at.abs_diff(receipt.expires_at.unwrap()) <= 1);
+    assert!(meta.created_at.is_some());
+    assert!(meta.ttl_remaining.unwrap() <= 600);
+
+    Ok(())
+}
+
+/// Test that detailed gets tell expired entries from never-stored ones
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_get_detailed() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45700, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let (kept, expired) = (ARID::new(), ARID::new());
+    let envelope = Envelope::new("Detailed");
+    client
+        .put(&kept, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    client
+        .put(&expired, &envelope, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    sleep(Duration::from_millis(2100)).await;
+
+    let get = |arid| client.get_detailed(arid, Some(0), false);
+    assert_eq!(get(&kept).await.unwrap(), GetOutcome::Found(envelope));
+    // Without tombstones, the receipt still shows the entry expired
+    assert!(matches!(
+        get(&expired).await.unwrap(),
+        GetOutcome::Expired { digest: None, .. }
+    ));
+    assert_eq!(get(&ARID::new()).await.unwrap(), GetOutcome::NeverExisted);
+
+    Ok(())
+}
+
+/// Test that a zero timeout makes one lookup on every server-side store
+#[tokio::test(flavor = "multi_thread")]
+async fn test_zero_timeout() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45701, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let path = std::env::temp_dir()
+        .join(format!("hubert-zero-timeout-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let memory = MemoryKv::new();
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+
+    for store in [&sqlite as &dyn KvStore, &memory, &client] {
+        let arid = ARID::new();
+        let start = std::time::Instant::now();
+        assert!(store.get(&arid, Some(0), false).await.unwrap().is_none());
+        assert!(!store.exists(&arid).await.unwrap());
+        // Each store waits at least 500 ms between polls
+        assert!(start.elapsed() < Duration::from_millis(400));
+
+        let envelope = Envelope::new("Immediate");
+        store
+            .put(&arid, &envelope, None, false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let retrieved = store.get(&arid, Some(0), false).await.unwrap();
+        assert_eq!(retrieved, Some(envelope));
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that a put wakes a waiting get on the same store at once, rather
+/// than at its next poll
+#[tokio::test(flavor = "multi_thread")]
+async fn test_put_wakes_waiting_get() -> Result<()> {
+    bc_components::register_tags();
+
+    let path = std::env::temp_dir()
+        .join(format!("hubert-wakeup-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let memory = MemoryKv::new();
+
+    for store in [&sqlite as &dyn KvStore, &memory] {
+        let arid = ARID::new();
+        let envelope = Envelope::new("Wake up");
+        let get = async {
+            let result = store.get(&arid, Some(10), false).await.unwrap();
+            (result, std::time::Instant::now())
+        };
+        let put = async {
+            sleep(Duration::from_millis(100)).await;
+            store.put(&arid, &envelope, None, false).await.unwrap();
+            std::time::Instant::now()
+        };
+        let ((result, got_at), put_at) = tokio::join!(get, put);
+        assert_eq!(result, Some(envelope.clone()));
+        // Polling every 500 ms would take 400 ms here
+        assert!(got_at.duration_since(put_at) < Duration::from_millis(100));
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that a client get long-polls the server, which answers as soon as
+/// another client's put lands
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_long_poll() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45708, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let (reader, writer) =
+        (ServerKvClient::new(&url), ServerKvClient::new(&url));
+    let arid = ARID::new();
+    let envelope = Envelope::new("Long poll");
+
+    let get = async {
+        let result = reader.get(&arid, Some(10), false).await.unwrap();
+        (result, std::time::Instant::now())
+    };
+    let put = async {
+        sleep(Duration::from_millis(300)).await;
+        writer.put(&arid, &envelope, None, false).await.unwrap();
+        std::time::Instant::now()
+    };
+    let ((result, got_at), put_at) = tokio::join!(get, put);
+    assert_eq!(result, Some(envelope));
+    // Polling every second would take 700 ms here
+    assert!(got_at.duration_since(put_at) < Duration::from_millis(200));
+
+    // A bare /get still answers at once
+    let start = std::time::Instant::now();
+    let response = reqwest::Client::new()
+        .post(format!("{}/get", url))
+        .body(ARID::new().ur_string())
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    assert!(start.elapsed() < Duration::from_millis(500));
+    Ok(())
+}
+
+/// Test that the server tracks gets per ARID and counts messages nobody
+/// picked up before they expired
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_access_stats() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let config = ServerConfig {
+        port: 45709,
+        capability_roots: vec![root_public.ur_string()],
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+    tokio::spawn(async move { server.run().await });
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let data = Capability::new().allow(Right::Put).allow(Right::Get);
+    let client =
+        ServerKvClient::new(&url).with_capability(&data.sign(&root_private));
+    let admin = ServerKvClient::new(&url).with_capability(
+        &Capability::new().allow(Right::Admin).sign(&root_private),
+    );
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+
+    let (read, unread) = (ARID::new(), ARID::new());
+    assert!(
+        client
+            .get(&read, Some(0), false)
+            .await
+            .map_err(err)?
+            .is_none()
+    );
+    for arid in [&read, &unread] {
+        client
+            .put(arid, &Envelope::new("Tracked"), Some(1), false)
+            .await
+            .map_err(err)?;
+    }
+    assert!(
+        client
+            .get(&read, Some(0), false)
+            .await
+            .map_err(err)?
+            .is_some()
+    );
+
+    let stats = admin.stats(&read).await.map_err(err)?;
+    assert_eq!((stats.gets, stats.found), (2, 1));
+    assert!(stats.first_found_at.is_some());
+    assert_eq!(admin.stats(&unread).await.map_err(err)?.gets, 0);
+
+    // Only admins may read statistics
+    assert!(client.stats(&read).await.is_err());
+
+    sleep(Duration::from_millis(2100)).await;
+    let summary = admin.stats_summary().await.map_err(err)?;
+    assert_eq!((summary.arids, summary.gets, summary.found), (1, 2, 1));
+    assert_eq!(summary.expired_unread, 1);
+    Ok(())
+}
+
+/// Test that identical re-puts succeed only when enabled
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_idempotent_puts() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-idempotent-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .with_idempotent_puts(true);
+    let memory = MemoryKv::new().with_idempotent_puts(true);
+    let strict = MemoryKv::new();
+
+    let envelope = Envelope::new("Retried");
+    for store in [&sqlite as &dyn KvStore, &memory] {
+        let arid = ARID::new();
+        store.put(&arid, &envelope, None, false).await?;
+        store.put(&arid, &envelope, None, false).await?;
+        let result = store
+            .put(&arid, &Envelope::new("Different"), None, false)
+            .await;
+        assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+        assert_eq!(
+            store.get(&arid, Some(0), false).await?,
+            Some(envelope.clone())
+        );
+    }
+
+    let arid = ARID::new();
+    strict.put(&arid, &envelope, None, false).await?;
+    let result = strict.put(&arid, &envelope, None, false).await;
+    assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that the server accepts identical re-puts when configured to
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_idempotent_puts() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig {
+        port: 45710,
+        idempotent_puts: true,
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let (arid, envelope) = (ARID::new(), Envelope::new("Retried"));
+    client
+        .put(&arid, &envelope, Some(600), false)
+        .await
+        .map_err(err)?;
+    let ReceiptStatus::Stored { expires_at, .. } =
+        client.receipt(&arid).await.map_err(err)?
+    else {
+        panic!("expected a stored receipt");
+    };
+
+    // The retry leaves the entry and its expiry as they were
+    sleep(Duration::from_millis(1100)).await;
+    client
+        .put(&arid, &envelope, Some(600), false)
+        .await
+        .map_err(err)?;
+    assert!(matches!(
+        client.receipt(&arid).await.map_err(err)?,
+        ReceiptStatus::Stored { expires_at: e, .. } if e == expires_at
+    ));
+
+    let result = client
+        .put(&arid, &Envelope::new("Different"), None, false)
+        .await;
+    assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+    Ok(())
+}
+
+/// Test that updatable stores replace entries and count their versions
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_update() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-update-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .with_mutability(Mutability::Updatable);
+    let memory = MemoryKv::new().with_mutability(Mutability::Updatable);
+
+    for store in [&sqlite as &dyn KvStore, &memory] {
+        assert!(store.capabilities().update);
+        let arid = ARID::new();
+        for round in 1..=3u64 {
+            let envelope = Envelope::new(format!("Round {}", round));
+            let receipt = store.update(&arid, &envelope, None, false).await?;
+            assert_eq!(receipt.sequence, Some(round));
+            assert_eq!(store.get(&arid, Some(0), false).await?, Some(envelope));
+        }
+
+        // Puts stay write-once
+        let result = store.put(&arid, &Envelope::new("Put"), None, false).await;
+        assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+    }
+
+    let strict = MemoryKv::new();
+    assert!(!strict.capabilities().update);
+    let arid = ARID::new();
+    let result = strict.update(&arid, &Envelope::new("No"), None, false).await;
+    assert!(matches!(result, Err(hubert::Error::Unsupported { .. })));
+    assert!(!strict.exists(&arid).await?);
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that a server accepts updates only when configured to
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_update() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig {
+        port: 45728,
+        mutability: Mutability::Updatable,
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+    tokio::spawn(async move { server.run().await });
+    let strict = ServerConfig { port: 45729, ..Default::default() };
+    let server = Server::new_memory(strict.clone());
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let client =
+        ServerKvClient::new(&url).with_mutability(Mutability::Updatable);
+    let arid = ARID::new();
+    client
+        .put(&arid, &Envelope::new("First"), None, false)
+        .await
+        .map_err(err)?;
+    let receipt = client
+        .update(&arid, &Envelope::new("Second"), Some(600), false)
+        .await
+        .map_err(err)?;
+    assert_eq!(receipt.sequence, Some(2));
+    assert_eq!(
+        client.get(&arid, Some(0), false).await.map_err(err)?,
+        Some(Envelope::new("Second"))
+    );
+
+    // The client must opt in as well as the server
+    let result = ServerKvClient::new(&url)
+        .update(&arid, &Envelope::new("Third"), None, false)
+        .await;
+    assert!(matches!(result, Err(hubert::Error::Unsupported { .. })));
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", strict.port))
+            .with_mutability(Mutability::Updatable);
+    let result = client
+        .update(&ARID::new(), &Envelope::new("Refused"), None, false)
+        .await;
+    assert!(matches!(result, Err(hubert::Error::Unsupported { .. })));
+    Ok(())
+}
+
+/// Test that watches on updatable stores yield each new version
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_updates() -> Result<()> {
+    use futures_util::StreamExt;
+
+    bc_components::register_tags();
+
+    let config = ServerConfig {
+        port: 45734,
+        mutability: Mutability::Updatable,
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let path = std::env::temp_dir()
+        .join(format!("hubert-watch-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .with_mutability(Mutability::Updatable);
+    let memory = MemoryKv::new().with_mutability(Mutability::Updatable);
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port))
+            .with_mutability(Mutability::Updatable);
+
+    let wait = Duration::from_secs(10);
+    for store in [&sqlite as &dyn KvStore, &memory, &client] {
+        let arid = ARID::new();
+        let first = Envelope::new("Version 1");
+        store.put(&arid, &first, None, false).await?;
+        let mut watch = store.watch(&arid, false);
+        let found = tokio::time::timeout(wait, watch.next()).await?;
+        assert_eq!(found.expect("Watch ended")?, first);
+
+        // Each update reaches the open watch
+        for version in 2..=3 {
+            let envelope = Envelope::new(format!("Version {}", version));
+            store.update(&arid, &envelope, None, false).await?;
+            let found = tokio::time::timeout(wait, watch.next()).await?;
+            assert_eq!(found.expect("Watch ended")?, envelope);
+        }
+
+        // Nothing is yielded twice
+        let quiet = Duration::from_millis(200);
+        assert!(tokio::time::timeout(quiet, watch.next()).await.is_err());
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that a reserved ARID takes only its reserver's envelope
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_reservations() -> Result<()> {
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let exists = |r: hubert::Result<hubert::Receipt>| {
+        matches!(r, Err(hubert::Error::AlreadyExists { .. }))
+    };
+    let not_reserved = |r: hubert::Result<hubert::Receipt>| {
+        matches!(
+            r,
+            Err(hubert::Error::Server(
+                hubert::server::Error::NotReserved { .. }
+            ))
+        )
+    };
+    let envelope = Envelope::new("Produced later");
+
+    let memory = MemoryKv::new();
+    let arid = ARID::new();
+    let reservation = memory.reserve(&arid, 60, false).map_err(err)?;
+    assert_eq!(reservation.arid, arid);
+    assert!(memory.reserve(&arid, 60, false).is_err());
+    assert!(exists(
+        memory.put_tagged(&arid, &envelope, None, None, false)
+    ));
+    assert!(not_reserved(
+        memory.commit(&arid, "wrong", &envelope, None, None, false)
+    ));
+    memory
+        .commit(&arid, &reservation.secret, &envelope, None, None, false)
+        .map_err(err)?;
+    assert_eq!(
+        memory.get(&arid, Some(0), false).await?,
+        Some(envelope.clone())
+    );
+    assert!(exists(memory.commit(
+        &arid,
+        &reservation.secret,
+        &envelope,
+        None,
+        None,
+        false
+    )));
+
+    // An unfilled reservation lapses and frees the ARID
+    let lapsing = ARID::new();
+    let reservation = memory.reserve(&lapsing, 1, false).map_err(err)?;
+    sleep(Duration::from_millis(2100)).await;
+    assert!(not_reserved(memory.commit(
+        &lapsing,
+        &reservation.secret,
+        &envelope,
+        None,
+        None,
+        false
+    )));
+    memory.put(&lapsing, &envelope, None, false).await?;
+
+    // SQLite reservations survive reopening the database
+    let path = std::env::temp_dir()
+        .join(format!("hubert-reservations-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let arid = ARID::new();
+    let reservation = SqliteKv::new(&path)
+        .map_err(err)?
+        .reserve(&arid, 60, false)
+        .map_err(err)?;
+    let sqlite = SqliteKv::new(&path).map_err(err)?;
+    assert!(exists(
+        sqlite.put_tagged(&arid, &envelope, None, None, false)
+    ));
+    assert!(not_reserved(
+        sqlite.commit(&arid, "wrong", &envelope, None, None, false)
+    ));
+    sqlite
+        .commit(&arid, &reservation.secret, &envelope, None, None, false)
+        .map_err(err)?;
+    assert_eq!(sqlite.get(&arid, Some(0), false).await?, Some(envelope));
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test reserving and committing through the server
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_reservations() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45711, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let (arid, envelope) = (ARID::new(), Envelope::new("Produced later"));
+    let reservation = client.reserve(&arid, Some(60)).await.map_err(err)?;
+    assert!(matches!(
+        client.reserve(&arid, None).await,
+        Err(hubert::Error::AlreadyExists { .. })
+    ));
+    assert!(matches!(
+        client.put(&arid, &envelope, None, false).await,
+        Err(hubert::Error::AlreadyExists { .. })
+    ));
+
+    let forged = hubert::server::Reservation {
+        secret: "00".repeat(32),
+        ..reservation.clone()
+    };
+    assert!(matches!(
+        client.commit(&forged, &envelope, None, false).await,
+        Err(hubert::Error::Server(
+            hubert::server::Error::NotReserved { .. }
+        ))
+    ));
+
+    // A reader waiting on the ARID sees the committed envelope
+    let get = client.get(&arid, Some(10), false);
+    let commit = async {
+        sleep(Duration::from_millis(200)).await;
+        client.commit(&reservation, &envelope, None, false).await
+    };
+    let (found, committed) = tokio::join!(get, commit);
+    committed.map_err(err)?;
+    assert_eq!(found.map_err(err)?, Some(envelope));
+    assert!(matches!(
+        client.receipt(&arid).await.map_err(err)?,
+        ReceiptStatus::Stored { .. }
+    ));
+    Ok(())
+}
+
+/// Test that a get conditional on the held digest skips the envelope
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_conditional_get() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45712, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let url = format!("http://127.0.0.1:{}", config.port);
+    let client = ServerKvClient::new(&url);
+    let (arid, envelope) = (ARID::new(), Envelope::new("Held already"));
+    client
+        .put(&arid, &envelope, None, false)
+        .await
+        .map_err(err)?;
+
+    let held = envelope.digest();
+    let other = Envelope::new("Something else").digest();
+    assert_eq!(
+        client
+            .get_if_digest_differs(&arid, &held, Some(0), false)
+            .await
+            .map_err(err)?,
+        GetOutcome::NotModified
+    );
+    assert_eq!(
+        client
+            .get_if_digest_differs(&arid, &other, Some(0), false)
+            .await
+            .map_err(err)?,
+        GetOutcome::Found(envelope)
+    );
+    assert_eq!(
+        client
+            .get_if_digest_differs(&ARID::new(), &held, Some(0), false)
+            .await
+            .map_err(err)?,
+        GetOutcome::NotFound
+    );
+
+    // Over plain HTTP, the ETag comes back and a match has no body
+    let http = reqwest::Client::new();
+    let response = http
+        .post(format!("{}/get", url))
+        .body(arid.ur_string())
+        .send()
+        .await?;
+    let etag = response.headers()[reqwest::header::ETAG].clone();
+    let response = http
+        .post(format!("{}/get", url))
+        .header(reqwest::header::IF_NONE_MATCH, etag)
+        .body(arid.ur_string())
+        .send()
+        .await?;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_MODIFIED);
+    assert!(response.text().await?.is_empty());
+    Ok(())
+}
+
+/// Test that large envelopes are stored compressed and read back intact
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_compression() -> Result<()> {
+    bc_components::register_tags();
+
+    let (root_private, root_public) = keypair();
+    let path = std::env::temp_dir()
+        .join(format!("hubert-compression-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = ServerConfig {
+        port: 45717,
+        capability_roots: vec![root_public.ur_string()],
+        compression_threshold: Some(1024),
+        ..Default::default()
+    };
+    let storage = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let server = Server::new_sqlite(config.clone(), storage);
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port))
+            .with_capability(
+                &Capability::new()
+                    .allow(Right::Put)
+                    .allow(Right::Get)
+                    .allow(Right::Admin)
+                    .sign(&root_private),
+            );
+
+    let large = Envelope::new("compressible ".repeat(1000));
+    let small = Envelope::new("Too small to compress");
+    let (large_arid, small_arid) = (ARID::new(), ARID::new());
+    for (arid, envelope) in [(&large_arid, &large), (&small_arid, &small)] {
+        client
+            .put(arid, envelope, None, false)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+
+    let stats = client
+        .storage_stats()
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.compressed_entries, 1);
+    assert_eq!(
+        stats.compressed_original_bytes,
+        large.to_cbor_data().len() as u64
+    );
+    assert!(stats.compression_ratio().unwrap() > 10.0);
+
+    // Listings report the envelope's size, not the compressed size
+    let listed = client
+        .list(0, 10, false, None, None)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let listed_large = listed
+        .iter()
+        .find(|entry| entry.arid == large_arid)
+        .unwrap();
+    assert_eq!(listed_large.size, large.to_cbor_data().len());
+
+    assert_eq!(
+        client.get(&large_arid, Some(0), false).await.unwrap(),
+        Some(large.clone())
+    );
+    assert_eq!(
+        client.get(&small_arid, Some(0), false).await.unwrap(),
+        Some(small)
+    );
+
+    // Compressed rows are flagged, so a store without compression reads them
+    let uncompressed =
+        SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(
+        uncompressed.get(&large_arid, Some(0), false).await.unwrap(),
+        Some(large)
+    );
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that old entries move to cold storage and are read back from it
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_cold_storage() -> Result<()> {
+    bc_components::register_tags();
+
+    let id = std::process::id();
+    let path = std::env::temp_dir().join(format!("hubert-cold-{}.sqlite", id));
+    let cold_dir = std::env::temp_dir().join(format!("hubert-cold-{}", id));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&cold_dir);
+    let store = SqliteKv::new(&path)
+        .map_err(|e| anyhow::anyhow!("{}", e))?
+        .with_compression(1024)
+        .with_cold_storage(&cold_dir, 0);
+
+    let large = Envelope::new("compressible ".repeat(1000));
+    let small = Envelope::new("Small");
+    let (large_arid, small_arid) = (ARID::new(), ARID::new());
+    store
+        .put_tagged(&large_arid, &large, None, Some("cold"), false)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    store
+        .put_tagged(&small_arid, &small, None, Some("cold"), false)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    assert_eq!(store.move_to_cold_storage().unwrap(), 2);
+    assert_eq!(store.move_to_cold_storage().unwrap(), 0);
+    assert_eq!(std::fs::read_dir(&cold_dir)?.count(), 2);
+
+    let stats = store.storage_stats().unwrap();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.cold_entries, 2);
+    assert_eq!(stats.stored_bytes, 0);
+
+    // Gets, listings, and write-once checks see through the stubs
+    assert_eq!(
+        store.get(&large_arid, Some(0), false).await.unwrap(),
+        Some(large.clone())
+    );
+    assert_eq!(
+        store.get(&small_arid, Some(0), false).await.unwrap(),
+        Some(small)
+    );
+    let listed = store.list(0, 10, false, None, None).unwrap();
+    assert!(listed.iter().any(|entry| {
+        entry.arid == large_arid && entry.size == large.to_cbor_data().len()
+    }));
+    assert!(store.put(&large_arid, &large, None, false).await.is_err());
+
+    // Removing the entries removes their files
+    assert_eq!(store.purge("cold").unwrap(), 2);
+    assert_eq!(std::fs::read_dir(&cold_dir)?.count(), 0);
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&cold_dir);
+    Ok(())
+}
+
+/// Test that a client following the conformance script passes
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_conformance() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45718, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+    let script = client
+        .start_conformance("rust-client")
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(script.steps, ConformanceStep::ALL);
+
+    let arid = ARID::from_ur_string(&script.arid)?;
+    let envelope = Envelope::from_ur_string(&script.envelope)?;
+    let duplicate = Envelope::from_ur_string(&script.duplicate_envelope)?;
+    client
+        .put(&arid, &envelope, Some(script.ttl), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(
+        client
+            .put(&arid, &duplicate, Some(script.ttl), false)
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        client.get(&arid, Some(0), false).await.unwrap(),
+        Some(envelope)
+    );
+
+    let report = client
+        .conformance_report("rust-client")
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(!report.passed);
+    assert_eq!(report.steps[3].status, StepStatus::Pending);
+
+    sleep(Duration::from_secs(script.ttl + 2)).await;
+    assert_eq!(client.get(&arid, Some(0), false).await.unwrap(), None);
+
+    let report = client
+        .conformance_report("rust-client")
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(report.passed, "{:?}", report);
+
+    // Unknown tokens and malformed ones are refused
+    assert!(client.conformance_report("nobody").await.is_err());
+    assert!(client.start_conformance("not a token").await.is_err());
+
+    Ok(())
+}
+
+/// Test that batch puts store every entry or, if any ARID is taken, none
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_put_many() -> Result<()> {
+    let path = std::env::temp_dir()
+        .join(format!("hubert-put-many-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let sqlite = SqliteKv::new(&path).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let memory = MemoryKv::new();
+
+    for store in [&sqlite as &dyn KvStore, &memory] {
+        let entries: Vec<(ARID, Envelope)> = (0..50)
+            .map(|i| (ARID::new(), Envelope::new(format!("Share {}", i))))
+            .collect();
+        let receipts = store.put_many(&entries, Some(600), false).await?;
+        assert_eq!(receipts.len(), entries.len());
+        assert_eq!(receipts[7].arid, entries[7].0);
+        assert_eq!(receipts[7].ttl, Some(600));
+        let arids: Vec<ARID> = entries.iter().map(|(arid, _)| *arid).collect();
+        let found = store.get_many(&arids, false).await?;
+        let expected: Vec<_> =
+            entries.iter().map(|(_, e)| Some(e.clone())).collect();
+        assert_eq!(found, expected);
+
+        // Without a TTL, each entry is kept until its own expiry
+        let now = unix_now();
+        let dated = [
+            (ARID::new(), add_expiry(Envelope::new("Hour"), now + 3600)),
+            (ARID::new(), Envelope::new("Undated")),
+        ];
+        let receipts = store.put_many(&dated, None, false).await?;
+        let ttl = receipts[0].ttl.unwrap();
+        assert!((3599..=3600).contains(&ttl), "{}", ttl);
+        assert_eq!(receipts[1].ttl, None);
+
+        // An expired entry keeps the whole batch out
+        let late = ARID::new();
+        let batch = [
+            (late, Envelope::new("Late")),
+            (ARID::new(), add_expiry(Envelope::new("Stale"), now - 60)),
+        ];
+        let result = store.put_many(&batch, None, false).await;
+        assert!(matches!(result, Err(hubert::Error::EnvelopeExpired { .. })));
+        assert!(!store.exists(&late).await?);
+
+        // One taken ARID keeps the whole batch out
+        let fresh = ARID::new();
+        let batch =
+            [(fresh, Envelope::new("New")), (arids[7], Envelope::new("Taken"))];
+        let result = store.put_many(&batch, None, false).await;
+        assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+        assert!(!store.exists(&fresh).await?);
+
+        // So does an ARID repeated within the batch
+        let batch =
+            [(fresh, Envelope::new("One")), (fresh, Envelope::new("Two"))];
+        let result = store.put_many(&batch, None, false).await;
+        assert!(matches!(result, Err(hubert::Error::AlreadyExists { .. })));
+        assert!(!store.exists(&fresh).await?);
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Test that the server stores a batch in one request, all or nothing
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_put_many() -> Result<()> {
+    bc_components::register_tags();
+
+    let config = ServerConfig { port: 45723, ..Default::default() };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let client =
+        ServerKvClient::new(&format!("http://127.0.0.1:{}", config.port));
+
+    // More entries than one request carries
+    let entries: Vec<(ARID, Envelope)> = (0..150)
+        .map(|i| (ARID::new(), Envelope::new(format!("Share {}", i))))
+        .collect();
+    let receipts = client
+        .put_many_detailed(&entries, Some(600), false)
+        .await
+        .map_err(err)?;
+    assert_eq!(receipts.len(), entries.len());
+    assert!(
+        receipts
+            .iter()
+            .all(|r| matches!(r, ReceiptStatus::Stored { .. }))
+    );
+    let arids: Vec<ARID> = entries.iter().map(|(arid, _)| *arid).collect();
+    let found = client.get_many(&arids, false).await.map_err(err)?;
+    assert!(found.iter().all(Option::is_some));
+    assert_eq!(found[149], Some(entries[149].1.clone()));
+
+    let fresh = ARID::new();
+    let batch =
+        [(fresh, Envelope::new("New")), (arids[3], Envelope::new("Taken"))];
+    let result = client.put_many(&batch, None, false).await;
+    assert!(matches!(
+        result,
+        Err(hubert::Error::AlreadyExists { arid }) if arid == arids[3].ur_string()
+    ));
+    assert!(!client.exists(&fresh).await.map_err(err)?);
+
+    // Without a TTL, the server keeps each entry until its own expiry
+    let expires_at = unix_now() + 3600;
+    let dated = [(ARID::new(), add_expiry(Envelope::new("Hour"), expires_at))];
+    let receipts = client
+        .put_many_detailed(&dated, None, false)
+        .await
+        .map_err(err)?;
+    assert!(matches!(
+        receipts[0],
+        ReceiptStatus::Stored { expires_at: at, .. }
+            if at.abs_diff(expires_at) <= 1
+    ));
+
+    Ok(())
+}
+
+/// Test that the client's size limit admits exactly the envelopes whose UR
+/// the server accepts
+#[test]
+fn test_server_size_limit() {
+    let sized = |n: usize| Envelope::new(ByteString::from(vec![0u8; n]));
+    let overhead = stored_size(&sized(100_000), Codec::Dcbor) - 100_000;
+    let client = ServerKvClient::new("http://127.0.0.1:45678");
+    assert_eq!(client.capabilities().max_value_size, Some(MAX_ENVELOPE_SIZE));
+
+    let largest = sized(MAX_ENVELOPE_SIZE - overhead);
+    assert!(client.validate(&largest).is_ok());
+    assert!(largest.ur_string().len() <= MAX_ENVELOPE_UR_LENGTH);
+    let too_large = sized(MAX_ENVELOPE_SIZE - overhead + 1);
+    assert!(client.validate(&too_large).is_err());
+}
+
+/// Test that a server that was down catches up on the entries put at its
+/// peer meanwhile, and the peer on the entries the server held
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_sync_after_downtime() -> Result<()> {
+    bc_components::register_tags();
+
+    let (a_private, a_public) = keypair();
+    let (b_private, b_public) = keypair();
+    let peer = |port: u16, keys: &PublicKeys| PeerConfig {
+        url: format!("http://127.0.0.1:{}", port),
+        public_keys: keys.ur_string(),
+    };
+    let a_config = ServerConfig {
+        port: 45730,
+        identity: Some(a_private.ur_string()),
+        peers: vec![peer(45731, &b_public)],
+        sync_interval: 1,
+        ..Default::default()
+    };
+    let b_config = ServerConfig {
+        port: 45731,
+        identity: Some(b_private.ur_string()),
+        peers: vec![peer(45730, &a_public)],
+        sync_interval: 1,
+        ..Default::default()
+    };
+
+    let err = |e: hubert::Error| anyhow::anyhow!("{}", e);
+    let a_client = ServerKvClient::new("http://127.0.0.1:45730");
+    let b_client = ServerKvClient::new("http://127.0.0.1:45731");
+
+    // Only A is up while the first entries are put
+    let a = Server::new_memory(a_config);
+    tokio::spawn(async move { a.run().await });
+    sleep(Duration::from_millis(100)).await;
+    let put_while_down: Vec<(ARID, Envelope)> = (0..3)
+        .map(|i| (ARID::new(), Envelope::new(format!("While down {}", i))))
+        .collect();
+    for (arid, envelope) in &put_while_down {
+        a_client.put(arid, envelope, Some(600), false).await.map_err(err)?;
+    }
+
+    // B comes back up with an entry A never saw
+    let b_storage = MemoryKv::new();
+    let only_on_b = ARID::new();
+    let envelope = Envelope::new("Only on B");
+    b_storage
+        .put(&only_on_b, &envelope, Some(600), false)
+        .await
+        .map_err(err)?;
+    let b = Server::new(b_config, ServerKv::Memory(b_storage));
+    tokio::spawn(async move { b.run().await });
+
+    // Within a few passes each server holds everything
+    for (arid, expected) in &put_while_down {
+        let found = b_client.get(arid, Some(5), false).await.map_err(err)?;
+        assert_eq!(found.as_ref(), Some(expected));
+    }
+    let found = a_client.get(&only_on_b, Some(5), false).await.map_err(err)?;
+    assert_eq!(found, Some(envelope));
+
+    Ok(())
+}
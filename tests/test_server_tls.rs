@@ -0,0 +1,144 @@
+#![cfg(feature = "tls")]
+
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Result;
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use hubert::{
+    KvStore,
+    server::{
+        ClientPolicy, Right, Server, ServerConfig, ServerKvClient, TlsConfig,
+    },
+};
+use rcgen::{
+    BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair,
+};
+use tokio::time::{Duration, sleep};
+
+/// A certificate and private key, concatenated as PEM.
+fn issue(
+    issuer: &CertifiedIssuer<'_, KeyPair>,
+    names: Vec<String>,
+    common_name: &str,
+) -> Result<String> {
+    let mut params = CertificateParams::new(names)?;
+    params
+        .distinguished_name
+        .push(DnType::CommonName, common_name);
+    let key = KeyPair::generate()?;
+    let cert = params.signed_by(&key, &**issuer)?;
+    Ok(format!("{}{}", cert.pem(), key.serialize_pem()))
+}
+
+fn new_ca(common_name: &str) -> Result<CertifiedIssuer<'static, KeyPair>> {
+    let mut params = CertificateParams::new(Vec::<String>::new())?;
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, common_name);
+    Ok(CertifiedIssuer::self_signed(params, KeyPair::generate()?)?)
+}
+
+/// Test that a TLS server admits only clients with trusted certificates and
+/// applies their policies
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_client_certificates() -> Result<()> {
+    bc_components::register_tags();
+
+    let dir =
+        std::env::temp_dir().join(format!("hubert-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let write = |name: &str, pem: &str| -> Result<PathBuf> {
+        let path = dir.join(name);
+        std::fs::write(&path, pem)?;
+        Ok(path)
+    };
+
+    let ca = new_ca("Hubert Test CA")?;
+    let server_pem = issue(&ca, vec!["127.0.0.1".into()], "hubert")?;
+    let writer_pem = issue(&ca, vec![], "writer")?;
+    let reader_pem = issue(&ca, vec![], "reader")?;
+    let stranger_pem = issue(&ca, vec![], "stranger")?;
+    let rogue_pem = issue(&new_ca("Rogue CA")?, vec![], "writer")?;
+
+    let server_path = write("server.pem", &server_pem)?;
+    let config = ServerConfig {
+        port: 45692,
+        tls: Some(TlsConfig {
+            cert: server_path.clone(),
+            key: server_path,
+            client_ca: write("ca.pem", &ca.pem())?,
+            clients: HashMap::from([
+                (
+                    "writer".to_string(),
+                    ClientPolicy {
+                        allow: vec![Right::Put, Right::Get],
+                        max_puts_per_hour: Some(2),
+                    },
+                ),
+                (
+                    "reader".to_string(),
+                    ClientPolicy {
+                        allow: vec![Right::Get],
+                        max_puts_per_hour: None,
+                    },
+                ),
+            ]),
+        }),
+        ..Default::default()
+    };
+    let server = Server::new_memory(config.clone());
+
+    tokio::spawn(async move { server.run().await });
+
+    sleep(Duration::from_millis(100)).await;
+
+    let url = format!("https://127.0.0.1:{}", config.port);
+    let client = |pem: &str| {
+        ServerKvClient::new(&url)
+            .with_client_cert(pem.as_bytes(), ca.pem().as_bytes())
+            .unwrap()
+    };
+    let arid = ARID::new();
+    let envelope = Envelope::new("TLS test");
+
+    // Certificates from an untrusted CA fail the handshake
+    let rogue = client(&rogue_pem);
+    assert!(rogue.put(&arid, &envelope, None, false).await.is_err());
+
+    // Trusted but unlisted identities and read-only clients cannot put
+    let stranger = client(&stranger_pem);
+    assert!(stranger.put(&arid, &envelope, None, false).await.is_err());
+    let reader = client(&reader_pem);
+    assert!(reader.put(&arid, &envelope, None, false).await.is_err());
+
+    // The writer may put twice per hour, and a conflicting put does not
+    // count
+    let writer = client(&writer_pem);
+    writer
+        .put(&arid, &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let conflict = writer.put(&arid, &envelope, None, false).await;
+    assert!(matches!(conflict, Err(hubert::Error::AlreadyExists { .. })));
+    writer
+        .put(&ARID::new(), &envelope, None, false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert!(
+        writer
+            .put(&ARID::new(), &envelope, None, false)
+            .await
+            .is_err()
+    );
+
+    let retrieved = reader
+        .get(&arid, Some(1), false)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    assert_eq!(retrieved, Some(envelope));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
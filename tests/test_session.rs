@@ -0,0 +1,71 @@
+use std::time::{Duration, SystemTime};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use hubert::{Error, MemoryKv, Session, SessionSlot};
+
+/// Test a request/response exchange through a shared session
+#[tokio::test]
+async fn test_session_request_response() {
+    let store = MemoryKv::new();
+
+    let initiator = Session::new(60);
+    let responder = Session::join(*initiator.root(), initiator.expires_at());
+
+    let request = Envelope::new("Please sign");
+    initiator
+        .publish_request(&store, &request, false)
+        .await
+        .unwrap();
+
+    let received = responder
+        .await_request(&store, Some(5), false)
+        .await
+        .unwrap();
+    assert_eq!(received, Some(request));
+
+    let response = Envelope::new("Signed");
+    responder
+        .publish_response(&store, &response, false)
+        .await
+        .unwrap();
+    responder
+        .publish_attachment(&store, 0, &Envelope::new("Signature"), false)
+        .await
+        .unwrap();
+
+    let received = initiator
+        .await_response(&store, Some(5), false)
+        .await
+        .unwrap();
+    assert_eq!(received, Some(response));
+    assert!(
+        initiator
+            .await_attachment(&store, 0, Some(5), false)
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    // Slots are write-once like any other ARID
+    let result = initiator
+        .publish(&store, SessionSlot::Request, &Envelope::new("Again"), false)
+        .await;
+    assert!(matches!(result, Err(Error::AlreadyExists { .. })));
+}
+
+/// Test that an expired session refuses to publish or wait
+#[tokio::test]
+async fn test_session_expired() {
+    let store = MemoryKv::new();
+    let expired =
+        Session::join(ARID::new(), SystemTime::now() - Duration::from_secs(1));
+
+    let result = expired
+        .publish_status(&store, &Envelope::new("Late"), false)
+        .await;
+    assert!(matches!(result, Err(Error::SessionExpired)));
+
+    let result = expired.await_status(&store, Some(1), false).await;
+    assert!(matches!(result, Err(Error::SessionExpired)));
+}
@@ -0,0 +1,159 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use hubert::{
+    Error, KvStore, MemoryKv, ReadOnlyKv, StoreCapabilities, sharded::ShardedKv,
+};
+
+fn shards(names: &[&str]) -> HashMap<String, MemoryKv> {
+    names
+        .iter()
+        .map(|name| (name.to_string(), MemoryKv::new()))
+        .collect()
+}
+
+fn router(shards: &HashMap<String, MemoryKv>, replication: usize) -> ShardedKv {
+    shards
+        .iter()
+        .fold(ShardedKv::new(replication), |router, (name, store)| {
+            router.with_shard(name.clone(), store.clone())
+        })
+}
+
+/// Test that entries land on exactly their replica shards
+#[tokio::test]
+async fn test_sharded_put_get() {
+    let shards = shards(&["a", "b", "c"]);
+    let store = router(&shards, 2);
+
+    let arid = ARID::new();
+    let envelope = Envelope::new("Sharded");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+
+    let placement = store.ring().shards_for(&arid);
+    assert_eq!(placement.len(), 2);
+    for (name, shard) in &shards {
+        let held = shard.exists(&arid).await.unwrap();
+        assert_eq!(held, placement.contains(&name.as_str()));
+    }
+
+    let retrieved = store.get(&arid, Some(1), false).await.unwrap();
+    assert_eq!(retrieved, Some(envelope.clone()));
+    assert!(store.exists(&arid).await.unwrap());
+
+    // Write-once holds across the tier
+    let result = store.put(&arid, &envelope, None, false).await;
+    assert!(matches!(result, Err(Error::AlreadyExists { .. })));
+}
+
+/// Test that a router without shards refuses to store
+#[tokio::test]
+async fn test_sharded_no_shards() {
+    let store = ShardedKv::new(1);
+    let result = store
+        .put(&ARID::new(), &Envelope::new("Nowhere"), None, false)
+        .await;
+    assert!(matches!(result, Err(Error::NoShards)));
+}
+
+/// Test that rebalancing copies entries onto a newly added shard
+#[tokio::test]
+async fn test_sharded_rebalance() {
+    let mut shards = shards(&["a", "b", "c"]);
+    let store = router(&shards, 2);
+    let arids: Vec<ARID> = (0..50).map(|_| ARID::new()).collect();
+    for arid in &arids {
+        store
+            .put(arid, &Envelope::new("Entry"), None, false)
+            .await
+            .unwrap();
+    }
+    let previous = store.ring().clone();
+
+    let added = MemoryKv::new();
+    shards.insert("d".to_string(), added.clone());
+    let store = router(&shards, 2);
+    let copies = store
+        .rebalance(&previous, &arids, None, false)
+        .await
+        .unwrap();
+
+    let mut expected = 0;
+    for arid in &arids {
+        let placement = store.ring().shards_for(arid);
+        for name in &placement {
+            assert!(shards[*name].exists(arid).await.unwrap());
+        }
+        if placement.contains(&"d") {
+            expected += 1;
+        }
+    }
+    assert!(expected > 0);
+    assert_eq!(copies, expected);
+    assert_eq!(
+        store
+            .rebalance(&previous, &arids, None, false)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+/// Test that health covers every shard
+#[tokio::test]
+async fn test_sharded_health() {
+    let shards = shards(&["a", "b", "c"]);
+    let report = router(&shards, 2).health().await;
+    assert!(report.is_available());
+    assert_eq!(report.backend, "sharded");
+    let mut parts: Vec<&str> = report
+        .components
+        .iter()
+        .map(|c| c.backend.as_str())
+        .collect();
+    parts.sort();
+    assert_eq!(parts, ["a (memory)", "b (memory)", "c (memory)"]);
+
+    assert!(!ShardedKv::new(1).health().await.is_available());
+}
+
+/// Test that a tier reports only what every shard supports
+#[tokio::test]
+async fn test_sharded_capabilities() {
+    let store = router(&shards(&["a", "b"]), 2);
+    assert!(store.capabilities().ttl);
+    assert!(!store.capabilities().delete);
+
+    // A router without shards promises nothing
+    assert_eq!(
+        ShardedKv::new(1).capabilities(),
+        StoreCapabilities::default()
+    );
+}
+
+/// Test that shards can be stores chosen at runtime and shared with other
+/// wrappers
+#[tokio::test]
+async fn test_sharded_trait_objects() {
+    let backends: Vec<Arc<dyn KvStore>> =
+        vec![Arc::new(MemoryKv::new()), Arc::new(MemoryKv::new())];
+    let store = ShardedKv::new(2)
+        .with_shard("a", Arc::clone(&backends[0]))
+        .with_shard(
+            "b",
+            Box::new(Arc::clone(&backends[1])) as Box<dyn KvStore>,
+        );
+
+    let arid = ARID::new();
+    let envelope = Envelope::new("Injected");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+
+    let reader = ReadOnlyKv::new(Arc::clone(&backends[1]));
+    assert_eq!(
+        reader.get(&arid, Some(0), false).await.unwrap(),
+        Some(envelope)
+    );
+    let result = reader.put(&ARID::new(), &Envelope::new("No"), None, false);
+    assert!(result.await.is_err());
+}
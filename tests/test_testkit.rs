@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+#[cfg(feature = "server")]
+use hubert::SqliteKv;
+use hubert::{MemoryKv, testkit};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_memory_conformance() {
+    bc_components::register_tags();
+    let store = MemoryKv::new();
+    let setup = || std::future::ready(store.clone());
+    testkit::run_suite(setup, Duration::from_secs(1), None).await;
+}
+
+#[cfg(feature = "server")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_conformance() {
+    bc_components::register_tags();
+    let path = std::env::temp_dir()
+        .join(format!("hubert-testkit-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = SqliteKv::new(&path).unwrap();
+    let setup = || std::future::ready(store.clone());
+    testkit::run_suite(setup, Duration::from_secs(1), None).await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_single_shard_memory_conformance() {
+    bc_components::register_tags();
+    let store = MemoryKv::new().with_shards(1);
+    let setup = || std::future::ready(store.clone());
+    testkit::run_suite(setup, Duration::from_secs(1), None).await;
+}
@@ -0,0 +1,179 @@
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use hubert::{
+    Error, KvStore, MemoryKv, ReadOnlyKv,
+    chaos::ChaosKv,
+    threshold::{FailureReason, ThresholdKv, share_arid},
+};
+
+fn custodians(count: usize) -> Vec<(String, MemoryKv)> {
+    (0..count)
+        .map(|i| (format!("custodian-{}", i), MemoryKv::new()))
+        .collect()
+}
+
+fn custody(custodians: &[(String, MemoryKv)], threshold: usize) -> ThresholdKv {
+    custodians
+        .iter()
+        .fold(ThresholdKv::new(threshold), |store, (name, custodian)| {
+            store.with_custodian(name.clone(), custodian.clone())
+        })
+}
+
+/// Test that each custodian holds one share, which alone reveals nothing
+#[tokio::test]
+async fn test_threshold_put_get() {
+    let custodians = custodians(3);
+    let store = custody(&custodians, 2);
+
+    let arid = ARID::new();
+    let envelope = Envelope::new("Custodied");
+    let receipt = store.put(&arid, &envelope, Some(600), false).await.unwrap();
+    assert_eq!(receipt.shards.len(), 3);
+    assert_eq!(receipt.ttl, Some(600));
+
+    for (name, custodian) in &custodians {
+        assert!(!custodian.exists(&arid).await.unwrap());
+        let share = custodian
+            .get(&share_arid(&arid, name), Some(0), false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(share, envelope);
+        assert!(share.subject().is_encrypted());
+    }
+
+    assert_eq!(store.get(&arid, Some(0), false).await.unwrap(), Some(envelope));
+    assert!(store.exists(&arid).await.unwrap());
+
+    // Write-once holds across the custodians
+    let result = store.put(&arid, &Envelope::new("Again"), None, false).await;
+    assert!(matches!(result, Err(Error::AlreadyExists { .. })));
+}
+
+/// Test that gets tolerate custodians down to the threshold, and report
+/// each missing share below it
+#[tokio::test]
+async fn test_threshold_missing_shares() {
+    let custodians = custodians(3);
+    let store = custody(&custodians, 2);
+    let arid = ARID::new();
+    let envelope = Envelope::new("Resilient");
+    store.put(&arid, &envelope, None, false).await.unwrap();
+
+    // One custodian loses its share, another is unreachable
+    let (lost, failing) = (&custodians[0], &custodians[1]);
+    assert!(lost.1.remove(&share_arid(&arid, &lost.0)));
+    let store = ThresholdKv::new(2)
+        .with_custodian(lost.0.clone(), lost.1.clone())
+        .with_custodian(
+            failing.0.clone(),
+            ChaosKv::new(failing.1.clone()).with_error_rate(1.0),
+        )
+        .with_custodian(custodians[2].0.clone(), custodians[2].1.clone());
+
+    let Err(Error::ThresholdNotMet { succeeded, needed, failures, .. }) =
+        store.get(&arid, Some(0), false).await
+    else {
+        panic!("expected too few shares");
+    };
+    assert_eq!((succeeded, needed), (1, 2));
+    let mut failed: Vec<&str> =
+        failures.iter().map(|f| f.custodian.as_str()).collect();
+    failed.sort();
+    assert_eq!(failed, [lost.0.as_str(), failing.0.as_str()]);
+    assert!(failures.iter().any(|f| f.reason == FailureReason::NotFound));
+
+    // Existence counts only the custodians that answer
+    assert!(!store.exists(&ARID::new()).await.unwrap());
+    let result = store.exists(&arid).await;
+    assert!(matches!(result, Err(Error::ThresholdNotMet { .. })));
+    let reachable = ThresholdKv::new(2)
+        .with_custodian(
+            failing.0.clone(),
+            ChaosKv::new(failing.1.clone()).with_error_rate(1.0),
+        )
+        .with_custodian(custodians[0].0.clone(), custodians[0].1.clone())
+        .with_custodian(custodians[2].0.clone(), custodians[2].1.clone());
+    let other = ARID::new();
+    custody(&custodians, 2)
+        .put(&other, &Envelope::new("Other"), None, false)
+        .await
+        .unwrap();
+    assert!(reachable.exists(&other).await.unwrap());
+
+    // Any two shares suffice
+    let intact = custody(&custodians[1..], 2);
+    let found = intact.get(&arid, Some(0), false).await.unwrap();
+    assert_eq!(found, Some(envelope));
+
+    // Nothing stored is an ordinary miss
+    let missing = intact.get(&ARID::new(), Some(0), false).await.unwrap();
+    assert_eq!(missing, None);
+}
+
+/// Test that a put fails, naming the custodian, unless every share is
+/// stored
+#[tokio::test]
+async fn test_threshold_put_failure() {
+    let custodians = custodians(2);
+    let store = custody(&custodians, 2)
+        .with_custodian("custodian-9", ReadOnlyKv::new(MemoryKv::new()));
+    let result = store
+        .put(&ARID::new(), &Envelope::new("Partial"), None, false)
+        .await;
+    let Err(Error::ThresholdNotMet { succeeded, needed, failures, .. }) = result
+    else {
+        panic!("expected a failed put");
+    };
+    assert_eq!((succeeded, needed), (2, 3));
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].custodian, "custodian-9");
+    assert_eq!(
+        failures[0].reason,
+        FailureReason::Error(Error::ReadOnly.to_string())
+    );
+}
+
+/// Test that a failed put takes back the shares it stored, so a retry is
+/// not refused as a duplicate
+#[tokio::test]
+async fn test_threshold_put_failure_cleans_up() {
+    let custodians = custodians(2);
+    // Seeded so the existence check gets through and the put fails
+    let flaky = ChaosKv::new(MemoryKv::new())
+        .with_error_rate(0.5)
+        .with_seed(0);
+    let store = custody(&custodians, 2).with_custodian("custodian-9", flaky);
+
+    let arid = ARID::new();
+    let envelope = Envelope::new("Retried");
+    let result = store.put(&arid, &envelope, None, false).await;
+    let Err(Error::ThresholdNotMet { succeeded, failures, .. }) = result else {
+        panic!("expected a failed put");
+    };
+    assert_eq!(succeeded, 2);
+    assert_eq!(failures[0].custodian, "custodian-9");
+    for (name, custodian) in &custodians {
+        assert!(!custodian.exists(&share_arid(&arid, name)).await.unwrap());
+    }
+
+    // Once the custodian recovers, the retry goes through
+    let store =
+        custody(&custodians, 2).with_custodian("custodian-9", MemoryKv::new());
+    store.put(&arid, &envelope, None, false).await.unwrap();
+    assert_eq!(store.get(&arid, Some(0), false).await.unwrap(), Some(envelope));
+}
+
+/// Test that a threshold the custodians cannot meet is refused
+#[tokio::test]
+async fn test_threshold_invalid() {
+    let envelope = Envelope::new("Nowhere");
+    for (threshold, count) in [(0, 2), (3, 2), (2, 17)] {
+        let store = custody(&custodians(count), threshold);
+        let result = store.put(&ARID::new(), &envelope, None, false).await;
+        assert!(matches!(result, Err(Error::InvalidThreshold { .. })));
+        assert!(!store.health().await.is_available());
+    }
+    assert!(custody(&custodians(2), 2).health().await.is_available());
+}
@@ -0,0 +1,143 @@
+//! Golden vectors for the wire formats in `hubert::wire`.
+//!
+//! Every vector uses the ARID whose bytes are 0x00 through 0x1f and the
+//! envelope `"Hello"`. Other implementations can check themselves against
+//! the same values; if one of these changes, the format has changed.
+
+use bc_components::ARID;
+use bc_envelope::Envelope;
+use bc_ur::prelude::*;
+use hubert::{
+    hubert_core,
+    wire::{ObfuscatedPayload, ReceiptStatus},
+};
+
+const ARID_UR: &str = "ur:arid/hdcxaeadaoaxaaahamatayasbkbdbnbtbabsbebybgbwbbbzcmchcscfcycwcecackctwerlrpyn";
+const ENVELOPE_UR: &str = "ur:envelope/tpsoihfdihjzjzjllamdlowy";
+
+fn arid() -> ARID {
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    ARID::from_data(bytes)
+}
+
+fn envelope() -> Envelope { Envelope::new("Hello") }
+
+#[test]
+fn test_urs() {
+    bc_components::register_tags();
+    assert_eq!(arid().ur_string(), ARID_UR);
+    assert_eq!(envelope().ur_string(), ENVELOPE_UR);
+}
+
+#[test]
+fn test_derived_keys() {
+    let arid = arid();
+    assert_eq!(
+        hex::encode(hubert_core::derive_mainline_key(arid.data())),
+        "02efd41deb8e3727164967ab17b94fc0f669b23a"
+    );
+    assert_eq!(
+        hubert_core::derive_ipfs_key_name(arid.data()),
+        "5f3ed31fd61de63d5745bfde2d2fef216ae202c32a3ab6688c8e6ec024f6f692"
+    );
+}
+
+#[test]
+fn test_obfuscated_payload() {
+    let arid = arid();
+    let payload = ObfuscatedPayload::encode(&arid, &envelope());
+    // Obfuscates the envelope's tagged dCBOR, d8c8d8c96548656c6c6f
+    assert_eq!(hex::encode(payload.as_bytes()), "8a5ae66670ae6f248f7f");
+
+    let received = ObfuscatedPayload::from_bytes(
+        hex::decode("8a5ae66670ae6f248f7f").unwrap(),
+    );
+    assert_eq!(received.decode(&arid).unwrap(), envelope());
+    assert!(received.decode(&ARID::new()).is_err());
+}
+
+#[test]
+fn test_receipt_status() {
+    let stored = ReceiptStatus::Stored {
+        stored_at: 1_700_000_000,
+        expires_at: 1_700_086_400,
+    };
+    let json =
+        r#"{"status":"stored","stored_at":1700000000,"expires_at":1700086400}"#;
+    assert_eq!(serde_json::to_string(&stored).unwrap(), json);
+    assert_eq!(serde_json::from_str::<ReceiptStatus>(json).unwrap(), stored);
+
+    let unknown = r#"{"status":"unknown"}"#;
+    assert_eq!(
+        serde_json::to_string(&ReceiptStatus::Unknown).unwrap(),
+        unknown
+    );
+}
+
+#[cfg(feature = "server-client")]
+#[test]
+fn test_server_bodies() {
+    use hubert::wire::{GetManyBody, GetManyResponse, PutBody, ReserveBody};
+
+    bc_components::register_tags();
+    let put = PutBody { arid: arid(), envelope: envelope(), ttl: Some(3600) };
+    let text = format!("{}\n{}\n3600", ARID_UR, ENVELOPE_UR);
+    assert_eq!(put.encode(), text);
+    assert_eq!(PutBody::decode(&text).unwrap(), put);
+
+    let reserve = ReserveBody { arid: arid(), ttl: None };
+    assert_eq!(reserve.encode(), ARID_UR);
+    assert_eq!(ReserveBody::decode(ARID_UR).unwrap(), reserve);
+
+    let get_many = GetManyBody { arids: vec![arid(), arid()] };
+    assert_eq!(get_many.encode(), format!("{}\n{}", ARID_UR, ARID_UR));
+
+    let found = GetManyResponse { found: vec![(arid(), envelope())] };
+    let text = format!("{} {}", ARID_UR, ENVELOPE_UR);
+    assert_eq!(found.encode(), text);
+    assert_eq!(GetManyResponse::decode(&text).unwrap(), found);
+}
+
+#[cfg(feature = "hybrid")]
+#[test]
+fn test_reference() {
+    use hubert::wire::{Location, Reference};
+
+    let reference = Reference::new(arid(), 5000, Location::Ipfs);
+    let envelope = reference.to_envelope();
+    assert!(envelope.is_subject_unit());
+    assert_eq!(envelope.assertions().len(), 3);
+    assert_eq!(Reference::from_envelope(&envelope).unwrap(), reference);
+}
+
+#[test]
+fn test_stored_size() {
+    use hubert::{
+        hubert_core::reference::{DEFAULT_DHT_SIZE_LIMIT, needs_reference},
+        wire::{Codec, stored_size},
+    };
+
+    // d8c8d8c96548656c6c6f, bencoded as "10:" followed by it
+    assert_eq!(stored_size(&envelope(), Codec::Dcbor), 10);
+    assert_eq!(stored_size(&envelope(), Codec::Bep44), 13);
+
+    // Tags and a three-byte text header add 7 bytes to the string
+    let largest = Envelope::new("x".repeat(989));
+    assert_eq!(stored_size(&largest, Codec::Dcbor), 996);
+    assert_eq!(stored_size(&largest, Codec::Bep44), 1000);
+    assert!(!needs_reference(
+        stored_size(&largest, Codec::Bep44),
+        DEFAULT_DHT_SIZE_LIMIT
+    ));
+
+    let too_large = Envelope::new("x".repeat(990));
+    assert_eq!(stored_size(&too_large, Codec::Dcbor), 997);
+    assert_eq!(stored_size(&too_large, Codec::Bep44), 1001);
+    assert!(needs_reference(
+        stored_size(&too_large, Codec::Bep44),
+        DEFAULT_DHT_SIZE_LIMIT
+    ));
+}